@@ -17,7 +17,7 @@ use itertools::Itertools;
 use prost::Message;
 use prost_types::Any;
 use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::hash::Hash;
 use thiserror::Error;
@@ -118,15 +118,39 @@ pub enum ContractCallError {
     AlloyError(RpcError<TransportErrorKind>),
     #[error("ethereum node took too long to perform call")]
     Timeout,
+    #[error("call ran out of gas before reaching its gas limit")]
+    OutOfGas,
     #[error("internal error: {0}")]
     Internal(String),
 }
 
+impl ContractCallError {
+    /// Whether making the same call against the same block will always
+    /// fail with this error, e.g., because the call reverted or because
+    /// it can't be encoded. Such errors are part of the result of the call
+    /// and must be handled the same way by every indexer
+    pub fn is_deterministic(&self) -> bool {
+        match self {
+            ContractCallError::ABIError(_)
+            | ContractCallError::TypeError(_, _)
+            | ContractCallError::EncodingError(_)
+            | ContractCallError::OutOfGas => true,
+            ContractCallError::AlloyError(e) => crate::call_helper::is_revert(e),
+            ContractCallError::Timeout | ContractCallError::Internal(_) => false,
+        }
+    }
+}
+
 impl From<ContractCallError> for MappingError {
     fn from(e: ContractCallError) -> Self {
         match e {
-            // Any error reported by the Ethereum node could be due to the block no longer being on
-            // the main chain. This is very unespecific but we don't want to risk failing a
+            // Reverts, calls that can't be encoded and calls that run out of
+            // their gas limit will fail the same way when they are made again
+            e if e.is_deterministic() => {
+                MappingError::Deterministic(anyhow::anyhow!("Error when making an eth_call: {e}"))
+            }
+            // Any other error reported by the Ethereum node could be due to the block no longer being
+            // on the main chain. This is very unespecific but we don't want to risk failing a
             // subgraph due to a transient error such as a reorg.
             ContractCallError::AlloyError(e) => MappingError::PossibleReorg(anyhow::anyhow!(
                 "Ethereum node returned an error for an eth_call: {e}"
@@ -263,6 +287,18 @@ impl TriggerFilter {
         !self.call.is_empty() || self.block.requires_traces()
     }
 
+    /// Whether triggers for this filter are found in transaction receipts
+    pub(crate) fn requires_receipts(&self) -> bool {
+        !self.log.is_empty() || !self.call.is_empty()
+    }
+
+    /// Whether all triggers for this filter are logs, so that `eth_getLogs`
+    /// finds every block with triggers. Block handlers, even with a filter,
+    /// and call handlers need other blocks, too
+    pub(crate) fn only_logs(&self) -> bool {
+        !self.log.is_empty() && self.call.is_empty() && self.block.is_empty()
+    }
+
     #[cfg(debug_assertions)]
     pub fn log(&self) -> &EthereumLogFilter {
         &self.log
@@ -345,6 +381,65 @@ impl bc::TriggerFilter<Chain> for TriggerFilter {
             value: combined_filter.encode_to_vec(),
         }]
     }
+
+    fn is_equivalent(&self, other: &Self) -> bool {
+        // The RPC block stream produces polling block triggers from the
+        // polling intervals, which the Firehose filter only summarizes
+        self.block.polling_intervals == other.block.polling_intervals
+            && FirehoseConditions::new(self.clone()) == FirehoseConditions::new(other.clone())
+    }
+}
+
+/// The conditions of the `CombinedFilter` that `to_firehose_filter` sends
+/// to Firehose, independent of the order in which they are listed. Every
+/// log and call filter is flattened into `(address, signature)` pairs where
+/// an empty value matches anything.
+#[derive(Debug, PartialEq, Eq)]
+struct FirehoseConditions {
+    logs: BTreeSet<(Vec<u8>, Vec<u8>)>,
+    calls: BTreeSet<(Vec<u8>, Vec<u8>)>,
+    send_all_block_headers: bool,
+}
+
+impl FirehoseConditions {
+    fn new(filter: TriggerFilter) -> Option<Self> {
+        let any = filter.to_firehose_filter().into_iter().next()?;
+        let CombinedFilter {
+            log_filters,
+            call_filters,
+            send_all_block_headers,
+        } = CombinedFilter::decode(any.value.as_slice())
+            .expect("the filter we just encoded can be decoded");
+
+        Some(Self {
+            logs: log_filters
+                .into_iter()
+                .flat_map(|f| Self::pairs(f.addresses, f.event_signatures))
+                .collect(),
+            calls: call_filters
+                .into_iter()
+                .flat_map(|f| Self::pairs(f.addresses, f.signatures))
+                .collect(),
+            send_all_block_headers,
+        })
+    }
+
+    fn pairs(
+        addresses: Vec<Vec<u8>>,
+        signatures: Vec<Vec<u8>>,
+    ) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> {
+        let or_any = |values: Vec<Vec<u8>>| {
+            if values.is_empty() {
+                vec![vec![]]
+            } else {
+                values
+            }
+        };
+
+        or_any(addresses)
+            .into_iter()
+            .cartesian_product(or_any(signatures))
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -361,6 +456,43 @@ pub struct EthereumLogFilter {
     /// Events with any of the topic filters set.
     /// Values are booleans indicating whether the trigger requires a transaction receipt.
     events_with_topic_filters: MergeMap<EventSignatureWithTopics>,
+    /// The blocks in which the logs of a contract are relevant, taken from
+    /// the start and end blocks of the data sources for the contract.
+    /// Contracts that are not in this map are relevant in every block.
+    contract_scopes: HashMap<Address, BlockScope>,
+}
+
+/// The blocks `[from, to]` in which a contract is watched; `to` is `None`
+/// if the contract is watched in all blocks from `from` on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BlockScope {
+    from: BlockNumber,
+    to: Option<BlockNumber>,
+}
+
+impl BlockScope {
+    fn contains(&self, block: BlockNumber) -> bool {
+        self.from <= block && self.to.is_none_or(|to| block <= to)
+    }
+
+    /// Whether any block in `[from, to]` is in this scope
+    fn overlaps(&self, from: BlockNumber, to: BlockNumber) -> bool {
+        let start = self.from.max(from);
+        let end = self.to.map_or(to, |end| end.min(to));
+        start <= end
+    }
+
+    /// The smallest scope that contains both `self` and `other`
+    fn merge(self, other: BlockScope) -> BlockScope {
+        let to = match (self.to, other.to) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
+        };
+        BlockScope {
+            from: self.from.min(other.from),
+            to,
+        }
+    }
 }
 
 /// `HashMap<K, bool>` wrapper whose values are OR-merged on every write.
@@ -399,6 +531,10 @@ impl<K: Eq + Hash> MergeMap<K> {
     fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.0.retain(|k, _| f(k))
+    }
 }
 
 impl<K: Eq + Hash> IntoIterator for MergeMap<K> {
@@ -463,7 +599,12 @@ impl MergeGraph {
 
 impl From<EthereumLogFilter> for Vec<LogFilter> {
     fn from(val: EthereumLogFilter) -> Self {
-        val.eth_get_logs_filters(ENV_VARS.get_logs_max_contracts)
+        // Firehose log filters apply to every block of the stream, so
+        // contracts can only be left out if they are never watched. Logs
+        // of other contracts outside their scope are sent anyway and
+        // ignored by the data sources
+        val.active_in(0, BLOCK_NUMBER_MAX)
+            .eth_get_logs_filters(ENV_VARS.get_logs_max_contracts)
             .map(
                 |EthGetLogsFilter {
                      contracts,
@@ -495,15 +636,23 @@ impl EthereumLogFilter {
                 // The `Log` matches the filter either if the filter contains
                 // a (contract address, event signature) pair that matches the
                 // `Log`, or if the filter contains wildcard event that matches.
+                // Contracts only match in the blocks in which they are
+                // watched
                 let contract = LogFilterNode::Contract(log.address());
                 let event = LogFilterNode::Event(*sig);
-                self.contracts_and_events_graph
+                let in_scope = |address: &Address| match log.block_number {
+                    Some(number) => self.watches(address, number as BlockNumber),
+                    None => true,
+                };
+                (self
+                    .contracts_and_events_graph
                     .contains_edge(contract, event)
+                    && in_scope(&log.address()))
                     || self.wildcard_events.contains_key(sig)
-                    || self
-                        .events_with_topic_filters
-                        .iter()
-                        .any(|(e, _)| e.matches(Some(&log.address()), *sig, log.topics()))
+                    || self.events_with_topic_filters.iter().any(|(e, _)| {
+                        e.matches(Some(&log.address()), *sig, log.topics())
+                            && e.address.as_ref().is_none_or(in_scope)
+                    })
             }
         }
     }
@@ -550,9 +699,32 @@ impl EthereumLogFilter {
         false
     }
 
+    /// Whether the logs of `address` are relevant in `block`
+    fn watches(&self, address: &Address, block: BlockNumber) -> bool {
+        self.contract_scopes
+            .get(address)
+            .is_none_or(|scope| scope.contains(block))
+    }
+
+    fn add_scope(&mut self, address: Address, scope: BlockScope) {
+        self.contract_scopes
+            .entry(address)
+            .and_modify(|existing| *existing = existing.merge(scope))
+            .or_insert(scope);
+    }
+
     pub fn from_data_sources<'a>(iter: impl IntoIterator<Item = &'a DataSource>) -> Self {
         let mut this = EthereumLogFilter::default();
         for ds in iter {
+            if let Some(contract) = ds.address
+                && !ds.mapping.event_handlers.is_empty()
+            {
+                let scope = BlockScope {
+                    from: ds.start_block,
+                    to: ds.end_block,
+                };
+                this.add_scope(contract, scope);
+            }
             for event_handler in ds.mapping.event_handlers.iter() {
                 let event_sig = event_handler.topic0();
                 match ds.address {
@@ -626,10 +798,14 @@ impl EthereumLogFilter {
             contracts_and_events_graph,
             wildcard_events,
             events_with_topic_filters,
+            contract_scopes,
         } = other;
         for (s, t, e) in contracts_and_events_graph.all_edges() {
             self.contracts_and_events_graph.or_add_edge(s, t, *e);
         }
+        for (address, scope) in contract_scopes {
+            self.add_scope(address, scope);
+        }
         for (k, v) in wildcard_events {
             self.wildcard_events.or_insert(k, v);
         }
@@ -645,12 +821,40 @@ impl EthereumLogFilter {
             contracts_and_events_graph,
             wildcard_events,
             events_with_topic_filters,
+            contract_scopes: _,
         } = self;
         contracts_and_events_graph.edge_count() == 0
             && wildcard_events.is_empty()
             && events_with_topic_filters.is_empty()
     }
 
+    /// Restrict this filter to the blocks `[from, to]` by removing the
+    /// contracts that are not watched in any of these blocks, so that
+    /// their logs are not requested. Wildcard events are kept
+    pub fn active_in(mut self, from: BlockNumber, to: BlockNumber) -> Self {
+        let inactive: HashSet<Address> = self
+            .contract_scopes
+            .iter()
+            .filter(|(_, scope)| !scope.overlaps(from, to))
+            .map(|(address, _)| *address)
+            .collect();
+        if inactive.is_empty() {
+            return self;
+        }
+
+        for address in &inactive {
+            self.contracts_and_events_graph
+                .remove_node(LogFilterNode::Contract(*address));
+        }
+        self.events_with_topic_filters.retain(|event| {
+            event
+                .address
+                .as_ref()
+                .is_none_or(|address| !inactive.contains(address))
+        });
+        self
+    }
+
     /// Filters for `eth_getLogs` calls. The filters will not return false positives. This attempts
     /// to balance between having granular filters but too many calls and having few calls but too
     /// broad filters causing the Ethereum endpoint to timeout.
@@ -1286,6 +1490,29 @@ pub trait EthereumAdapter: Send + Sync + 'static {
         block_ptr: BlockPtr,
     ) -> Result<alloy::primitives::Bytes, EthereumRpcError>;
 
+    /// Returns the base fees, gas used ratios and the given percentiles of
+    /// priority fees for the `block_count` blocks ending at `newest_block`
+    async fn get_fee_history(
+        &self,
+        logger: &Logger,
+        block_count: u64,
+        newest_block: BlockNumber,
+        reward_percentiles: &[f64],
+    ) -> Result<alloy::rpc::types::FeeHistory, EthereumRpcError>;
+
+    /// Returns the logs that `address` emitted in the blocks from `from` up
+    /// to and including the block `to` whose topics match `topics`. Each
+    /// entry of `topics` lists the values that the topic at that position
+    /// may have; an empty entry matches any value
+    async fn get_logs(
+        &self,
+        logger: &Logger,
+        address: Address,
+        topics: &[Vec<B256>],
+        from: BlockNumber,
+        to: &BlockPtr,
+    ) -> Result<Vec<Log>, EthereumRpcError>;
+
     /// Returns a boolean indicating whether the adapter can reach
     /// the RPC provider it is configured to use.
     /// This is used to determine if a provider should be considered healthy.
@@ -2114,6 +2341,26 @@ fn receipt_merge_test_event_handler(
     }
 }
 
+/// A filter for one event from any contract, i.e., a filter that only
+/// has log triggers
+#[cfg(test)]
+pub(crate) fn test_log_trigger_filter() -> TriggerFilter {
+    let ds = receipt_merge_test_data_source(
+        None,
+        vec![receipt_merge_test_event_handler(
+            receipt_merge_test_sig(1),
+            None,
+            None,
+            None,
+            false,
+        )],
+    );
+    TriggerFilter {
+        log: EthereumLogFilter::from_data_sources([&ds]),
+        ..Default::default()
+    }
+}
+
 #[cfg(test)]
 fn receipt_merge_test_mapping(
     handlers: Vec<crate::data_source::MappingEventHandler>,
@@ -2337,3 +2584,163 @@ fn requires_transaction_receipt_has_or_semantics_across_handlers() {
         );
     }
 }
+
+#[test]
+fn only_logs_requires_a_log_filter_and_nothing_else() {
+    let ds = receipt_merge_test_data_source(
+        Some(receipt_merge_test_addr(1)),
+        vec![receipt_merge_test_event_handler(
+            receipt_merge_test_sig(1),
+            None,
+            None,
+            None,
+            false,
+        )],
+    );
+    let logs = TriggerFilter {
+        log: EthereumLogFilter::from_data_sources([&ds]),
+        ..Default::default()
+    };
+    assert!(logs.only_logs());
+    assert!(!TriggerFilter::default().only_logs());
+
+    // Block handlers that run on every block or on a polling interval
+    // need blocks that have no logs
+    let mut every_block = logs.clone();
+    every_block.block.trigger_every_block = true;
+    assert!(!every_block.only_logs());
+
+    let mut polling = logs.clone();
+    polling.block.polling_intervals.insert((0, 10));
+    assert!(!polling.only_logs());
+}
+
+#[test]
+fn redundant_data_sources_leave_the_trigger_filter_equivalent() {
+    use graph::blockchain::TriggerFilter as _;
+
+    let ds = |addr: u64, sigs: &[u64]| {
+        receipt_merge_test_data_source(
+            Some(receipt_merge_test_addr(addr)),
+            sigs.iter()
+                .map(|sig| {
+                    receipt_merge_test_event_handler(
+                        receipt_merge_test_sig(*sig),
+                        None,
+                        None,
+                        None,
+                        false,
+                    )
+                })
+                .collect(),
+        )
+    };
+    let existing = [ds(1, &[10, 11]), ds(2, &[10]), ds(3, &[12])];
+    let filter = TriggerFilter::from_data_sources(existing.iter());
+
+    let mut redundant = filter.clone();
+    redundant.extend([ds(2, &[10])].iter());
+    assert!(redundant.is_equivalent(&filter));
+    assert!(filter.is_equivalent(&redundant));
+
+    let mut new_address = filter.clone();
+    new_address.extend([ds(4, &[10])].iter());
+    assert!(!new_address.is_equivalent(&filter));
+
+    let mut new_event = filter.clone();
+    new_event.extend([ds(3, &[10])].iter());
+    assert!(!new_event.is_equivalent(&filter));
+
+    assert!(TriggerFilter::default().is_equivalent(&TriggerFilter::default()));
+    assert!(!filter.is_equivalent(&TriggerFilter::default()));
+}
+
+#[cfg(test)]
+fn scoped_test_data_source(
+    addr: u64,
+    sig: u64,
+    start_block: BlockNumber,
+    end_block: Option<BlockNumber>,
+) -> crate::data_source::DataSource {
+    let mut ds = receipt_merge_test_data_source(
+        Some(receipt_merge_test_addr(addr)),
+        vec![receipt_merge_test_event_handler(
+            receipt_merge_test_sig(sig),
+            None,
+            None,
+            None,
+            false,
+        )],
+    );
+    ds.start_block = start_block;
+    ds.end_block = end_block;
+    ds
+}
+
+#[test]
+fn scoped_contracts_only_match_logs_in_their_blocks() {
+    let log = |addr: u64, block: u64| Log {
+        inner: alloy::primitives::Log::new_unchecked(
+            receipt_merge_test_addr(addr),
+            vec![receipt_merge_test_sig(1)],
+            Default::default(),
+        ),
+        block_number: Some(block),
+        ..Default::default()
+    };
+
+    let filter = EthereumLogFilter::from_data_sources(&[
+        scoped_test_data_source(1, 1, 10, Some(20)),
+        scoped_test_data_source(2, 1, 0, None),
+    ]);
+    assert!(!filter.matches(&log(1, 9)));
+    assert!(filter.matches(&log(1, 10)));
+    assert!(filter.matches(&log(1, 20)));
+    assert!(!filter.matches(&log(1, 21)));
+    assert!(filter.matches(&log(2, 21)));
+
+    // Data sources for the same contract widen its scope
+    let mut filter =
+        EthereumLogFilter::from_data_sources(&[scoped_test_data_source(1, 1, 10, Some(20))]);
+    filter.extend(EthereumLogFilter::from_data_sources(&[
+        scoped_test_data_source(1, 1, 30, None),
+    ]));
+    assert!(!filter.matches(&log(1, 9)));
+    assert!(filter.matches(&log(1, 25)));
+    assert!(filter.matches(&log(1, 100)));
+}
+
+#[test]
+fn scoped_contracts_are_only_requested_in_their_blocks() {
+    let filter = EthereumLogFilter::from_data_sources(&[
+        scoped_test_data_source(1, 1, 10, Some(20)),
+        scoped_test_data_source(2, 1, 0, None),
+    ]);
+    let contracts = |from, to| {
+        filter
+            .clone()
+            .active_in(from, to)
+            .eth_get_logs_filters(100)
+            .flat_map(|filter| filter.contracts)
+            .sorted()
+            .collect_vec()
+    };
+    let (one, two) = (receipt_merge_test_addr(1), receipt_merge_test_addr(2));
+    assert_eq!(vec![two], contracts(0, 9));
+    assert_eq!(vec![one, two], contracts(5, 15));
+    assert_eq!(vec![one, two], contracts(20, 30));
+    assert_eq!(vec![two], contracts(21, 30));
+
+    // Firehose filters can not be limited to blocks, but contracts that
+    // are never watched are left out
+    let filter = TriggerFilter {
+        log: EthereumLogFilter::from_data_sources(&[
+            scoped_test_data_source(1, 1, 10, Some(5)),
+            scoped_test_data_source(2, 1, 10, Some(20)),
+        ]),
+        ..Default::default()
+    };
+    let conditions = FirehoseConditions::new(filter).unwrap();
+    let expected = BTreeSet::from([(two.to_vec(), receipt_merge_test_sig(1).to_vec())]);
+    assert_eq!(expected, conditions.logs);
+}
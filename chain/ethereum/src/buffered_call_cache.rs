@@ -6,31 +6,85 @@ use std::{
 use async_trait::async_trait;
 use graph::{
     cheap_clone::CheapClone,
-    components::store::EthereumCallCache,
+    components::store::{ChainStore, EthereumCallCache},
     data::store::ethereum::call,
-    prelude::{BlockPtr, CachedEthereumCall},
+    prelude::{BlockNumber, BlockPtr, CachedEthereumCall, alloy::primitives::Address},
     slog::{Logger, error},
 };
 
+/// The key under which a `BufferedCallCache` stores the result of a call:
+/// the address of the contract, the calldata, and the block the call is
+/// made against. Two keys are equal, and hash the same, whenever they
+/// describe the same call in the same block; the index of the request does
+/// not matter.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CallCacheKey {
+    pub call: call::Request,
+    pub block: BlockPtr,
+}
+
+impl CallCacheKey {
+    pub fn new(address: Address, calldata: Vec<u8>, block: BlockPtr) -> Self {
+        Self {
+            call: call::Request::new(address, calldata, 0),
+            block,
+        }
+    }
+
+    /// The key for the request `call` against `block`
+    pub fn for_request(call: &call::Request, block: &BlockPtr) -> Self {
+        Self {
+            call: call.cheap_clone(),
+            block: block.cheap_clone(),
+        }
+    }
+}
+
 /// A wrapper around an Ethereum call cache that buffers call results in
 /// memory for the duration of a block. If `get_call` or `set_call` are
 /// called with a different block pointer than the one used in the previous
 /// call, the buffer is cleared.
+///
+/// Only results for blocks that are at least `finality_depth` blocks
+/// behind the chain head are written to the underlying cache; results for
+/// more recent blocks are only buffered.
 pub struct BufferedCallCache {
     call_cache: Arc<dyn EthereumCallCache>,
+    chain_store: Arc<dyn ChainStore>,
+    finality_depth: BlockNumber,
     buffer: Arc<Mutex<HashMap<call::Request, call::Retval>>>,
     block: Arc<Mutex<Option<BlockPtr>>>,
 }
 
 impl BufferedCallCache {
-    pub fn new(call_cache: Arc<dyn EthereumCallCache>) -> Self {
+    pub fn new(
+        call_cache: Arc<dyn EthereumCallCache>,
+        chain_store: Arc<dyn ChainStore>,
+        finality_depth: BlockNumber,
+    ) -> Self {
         Self {
             call_cache,
+            chain_store,
+            finality_depth,
             buffer: Arc::new(Mutex::new(HashMap::new())),
             block: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Whether `block` is final, i.e., at least `finality_depth` blocks
+    /// behind the chain head. Without a chain head, no block is final
+    async fn is_final(&self, logger: &Logger, block: &BlockPtr) -> bool {
+        match self.chain_store.cheap_clone().chain_head_ptr().await {
+            Ok(Some(head)) => block.number <= head.number.saturating_sub(self.finality_depth),
+            Ok(None) => false,
+            Err(e) => {
+                error!(logger, "BufferedCallCache: failed to get the chain head";
+                                "error" => e.to_string());
+                false
+            }
+        }
+    }
+
     fn check_block(&self, block: &BlockPtr) {
         let mut self_block = self.block.lock().unwrap();
         if self_block.as_ref() != Some(block) {
@@ -46,6 +100,25 @@ impl BufferedCallCache {
                 .response(retval.clone(), call::Source::Memory)
         })
     }
+
+    /// Return the buffered result for `key`. Only results for the block
+    /// that is currently being processed are buffered, so this returns
+    /// `None` for keys for any other block.
+    pub fn lookup(&self, key: &CallCacheKey) -> Option<call::Retval> {
+        if self.block.lock().unwrap().as_ref() != Some(&key.block) {
+            return None;
+        }
+        self.buffer.lock().unwrap().get(&key.call).cloned()
+    }
+
+    /// Buffer `retval` as the result for `key`, e.g., to warm the cache
+    /// before a block is processed. The result is not written to the
+    /// underlying cache. If `key` is for a different block than the
+    /// buffered results, the buffer is cleared first.
+    pub fn insert(&self, key: CallCacheKey, retval: call::Retval) {
+        self.check_block(&key.block);
+        self.buffer.lock().unwrap().insert(key.call, retval);
+    }
 }
 
 #[async_trait]
@@ -63,14 +136,13 @@ impl EthereumCallCache for BufferedCallCache {
 
         let result = self.call_cache.get_call(call, block).await?;
 
-        let mut buffer = self.buffer.lock().unwrap();
         if let Some(call::Response {
             retval,
             req: _,
             source: _,
         }) = &result
         {
-            buffer.insert(call.cheap_clone(), retval.clone());
+            self.insert(CallCacheKey::for_request(call, &block), retval.clone());
         }
         Ok(result)
     }
@@ -125,9 +197,15 @@ impl EthereumCallCache for BufferedCallCache {
         // handlers will find it, but add it to the underlying cache in the
         // background so we do not have to wait for that as it will be a
         // cache backed by the database
-        {
-            let mut buffer = self.buffer.lock().unwrap();
-            buffer.insert(call.cheap_clone(), return_value.clone());
+        self.insert(
+            CallCacheKey::for_request(&call, &block),
+            return_value.clone(),
+        );
+
+        // Results for blocks that can still be reverted are not worth
+        // keeping beyond the block
+        if !self.is_final(logger, &block).await {
+            return Ok(());
         }
 
         let cache = self.call_cache.cheap_clone();
@@ -144,3 +222,125 @@ impl EthereumCallCache for BufferedCallCache {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use graph::blockchain::mock::{MockChainStore, test_ptr};
+    use graph::components::store::EthereumCallCache;
+    use graph::data::store::ethereum::call;
+    use graph::prelude::alloy::primitives::Address;
+    use graph::prelude::{BlockPtr, CachedEthereumCall, Error};
+    use graph::slog::Logger;
+
+    use super::{BufferedCallCache, CallCacheKey};
+
+    /// A call cache that finds nothing and remembers the blocks for which
+    /// calls were stored
+    #[derive(Default)]
+    struct StoredBlocks(Mutex<Vec<BlockPtr>>);
+
+    #[async_trait]
+    impl EthereumCallCache for StoredBlocks {
+        async fn get_call(
+            &self,
+            _: &call::Request,
+            _: BlockPtr,
+        ) -> Result<Option<call::Response>, Error> {
+            Ok(None)
+        }
+
+        async fn get_calls(
+            &self,
+            reqs: &[call::Request],
+            _: BlockPtr,
+        ) -> Result<(Vec<call::Response>, Vec<call::Request>), Error> {
+            Ok((vec![], reqs.to_vec()))
+        }
+
+        async fn get_calls_in_block(&self, _: BlockPtr) -> Result<Vec<CachedEthereumCall>, Error> {
+            Ok(vec![])
+        }
+
+        async fn set_call(
+            self: Arc<Self>,
+            _: &Logger,
+            _: call::Request,
+            block: BlockPtr,
+            _: call::Retval,
+        ) -> Result<(), Error> {
+            self.0.lock().unwrap().push(block);
+            Ok(())
+        }
+    }
+
+    fn hash(key: &CallCacheKey) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn identical_calls_have_identical_keys() {
+        let address = Address::repeat_byte(0x11);
+        let key = CallCacheKey::new(address, vec![1, 2, 3], test_ptr(7));
+
+        // The index of a request does not affect its key
+        let req = call::Request::new(address, vec![1, 2, 3], 42);
+        let other = CallCacheKey::for_request(&req, &test_ptr(7));
+
+        assert_eq!(key, other);
+        assert_eq!(hash(&key), hash(&other));
+    }
+
+    #[test]
+    fn different_calls_have_different_keys() {
+        let address = Address::repeat_byte(0x11);
+        let key = CallCacheKey::new(address, vec![1, 2, 3], test_ptr(7));
+
+        let other_block = CallCacheKey::new(address, vec![1, 2, 3], test_ptr(8));
+        assert_ne!(key, other_block);
+        assert_ne!(hash(&key), hash(&other_block));
+
+        let other_calldata = CallCacheKey::new(address, vec![1, 2, 4], test_ptr(7));
+        assert_ne!(key, other_calldata);
+
+        let other_address =
+            CallCacheKey::new(Address::repeat_byte(0x22), vec![1, 2, 3], test_ptr(7));
+        assert_ne!(key, other_address);
+    }
+
+    #[graph::test]
+    async fn only_calls_at_final_blocks_are_stored() {
+        let logger = graph::log::discard();
+        let stored = Arc::new(StoredBlocks::default());
+        let chain_store = MockChainStore::default();
+        *chain_store.head.lock().unwrap() = Some(test_ptr(20));
+        // Blocks up to 10 are final
+        let cache = Arc::new(BufferedCallCache::new(
+            stored.clone(),
+            Arc::new(chain_store),
+            10,
+        ));
+
+        let req = call::Request::new(Address::repeat_byte(0x11), vec![1, 2, 3], 0);
+        for number in [9, 10, 11] {
+            let retval = call::Retval::Value(vec![number as u8].into());
+            cache
+                .clone()
+                .set_call(&logger, req.clone(), test_ptr(number), retval.clone())
+                .await
+                .unwrap();
+
+            // Every result is buffered for the block
+            let key = CallCacheKey::for_request(&req, &test_ptr(number));
+            assert_eq!(Some(retval), cache.lookup(&key));
+        }
+
+        assert_eq!(vec![test_ptr(9), test_ptr(10)], *stored.0.lock().unwrap());
+    }
+}
@@ -81,6 +81,24 @@ fn is_rpc_revert_message(message: &str) -> bool {
     execution_errors.any(|e| message.to_lowercase().contains(e))
 }
 
+/// Whether `err` says that the call ran out of gas. Clients report that in
+/// the error message, or, for Parity and Nethermind, in the error data.
+pub fn is_out_of_gas(err: &RpcError<TransportErrorKind>) -> bool {
+    let RpcError::ErrorResp(rpc_error) = err else {
+        return false;
+    };
+
+    if rpc_error.message.to_lowercase().contains("out of gas") {
+        return true;
+    }
+
+    let data: Option<String> = rpc_error
+        .data
+        .as_ref()
+        .and_then(|d| serde_json::from_str(d.get()).ok());
+    rpc_error.code == PARITY_VM_EXECUTION_ERROR && data.as_deref() == Some(PARITY_OUT_OF_GAS)
+}
+
 /// Decode a Solidity revert(reason) payload, returning the reason string when possible.
 fn as_solidity_revert_reason(bytes: &[u8]) -> Option<String> {
     let selector = &graph::prelude::alloy::primitives::keccak256(b"Error(string)")[..4];
@@ -94,39 +112,48 @@ fn as_solidity_revert_reason(bytes: &[u8]) -> Option<String> {
     }
 }
 
+/// If `err` says that the call reverted, return the reason for the revert
+fn revert_reason(err: &RpcError<TransportErrorKind>) -> Option<String> {
+    let RpcError::ErrorResp(rpc_error) = err else {
+        return None;
+    };
+
+    if is_rpc_revert_message(&rpc_error.message) {
+        return Some(rpc_error.message.to_string());
+    }
+
+    let data: Option<String> = rpc_error
+        .data
+        .as_ref()
+        .and_then(|d| serde_json::from_str(d.get()).ok());
+
+    match data {
+        Some(data) if rpc_error.code == PARITY_VM_EXECUTION_ERROR && is_parity_revert(&data) => {
+            Some(parity_revert_reason(&data))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `err` says that the call reverted. Reverts are deterministic:
+/// making the same call against the same block will revert again
+pub fn is_revert(err: &RpcError<TransportErrorKind>) -> bool {
+    revert_reason(err).is_some()
+}
+
 /// Interpret the error returned by `eth_call`, distinguishing genuine failures from
 /// EVM reverts. Returns `Ok(Null)` for reverts or a proper error otherwise.
 pub fn interpret_eth_call_error(
     logger: &Logger,
     err: RpcError<TransportErrorKind>,
 ) -> Result<call::Retval, ContractCallError> {
-    fn reverted(logger: &Logger, reason: &str) -> Result<call::Retval, ContractCallError> {
-        info!(logger, "Contract call reverted"; "reason" => reason);
-        Ok(call::Retval::Null)
-    }
-
-    if let RpcError::ErrorResp(rpc_error) = &err
-        && is_rpc_revert_message(&rpc_error.message)
-    {
-        return reverted(logger, &rpc_error.message);
-    }
-
-    if let RpcError::ErrorResp(rpc_error) = &err {
-        let code = rpc_error.code;
-        let data: Option<String> = rpc_error
-            .data
-            .as_ref()
-            .and_then(|d| serde_json::from_str(d.get()).ok());
-
-        if code == PARITY_VM_EXECUTION_ERROR
-            && let Some(data) = data
-            && is_parity_revert(&data)
-        {
-            return reverted(logger, &parity_revert_reason(&data));
+    match revert_reason(&err) {
+        Some(reason) => {
+            info!(logger, "Contract call reverted"; "reason" => reason);
+            Ok(call::Retval::Null)
         }
+        None => Err(ContractCallError::AlloyError(err)),
     }
-
-    Err(ContractCallError::AlloyError(err))
 }
 
 fn is_parity_revert(data: &str) -> bool {
@@ -154,3 +181,117 @@ fn parity_revert_reason(data: &str) -> String {
         .and_then(|decoded| as_solidity_revert_reason(&decoded))
         .unwrap_or_else(|| "no reason".to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use graph::components::subgraph::MappingError;
+    use graph::prelude::alloy::rpc::json_rpc::ErrorPayload;
+    use graph::prelude::serde_json::value::RawValue;
+
+    use super::*;
+
+    fn error_resp(code: i64, message: &str, data: Option<&str>) -> RpcError<TransportErrorKind> {
+        RpcError::ErrorResp(ErrorPayload {
+            code,
+            message: message.to_string().into(),
+            data: data.map(|data| RawValue::from_string(format!("{data:?}")).unwrap()),
+        })
+    }
+
+    #[test]
+    fn out_of_gas_is_detected() {
+        // Geth and Reth
+        assert!(is_out_of_gas(&error_resp(-32000, "out of gas", None)));
+        assert!(is_out_of_gas(&error_resp(
+            -32000,
+            "out of gas: gas required exceeds: 100000",
+            None
+        )));
+        // Parity and Nethermind
+        assert!(is_out_of_gas(&error_resp(
+            PARITY_VM_EXECUTION_ERROR,
+            "VM execution error.",
+            Some(PARITY_OUT_OF_GAS)
+        )));
+
+        assert!(!is_out_of_gas(&error_resp(
+            -32000,
+            "execution reverted",
+            None
+        )));
+        assert!(!is_out_of_gas(&error_resp(
+            PARITY_VM_EXECUTION_ERROR,
+            "VM execution error.",
+            Some("Reverted 0x")
+        )));
+        assert!(!is_out_of_gas(&RpcError::NullResp));
+    }
+
+    #[test]
+    fn out_of_gas_without_a_limit_is_a_revert() {
+        let logger = graph::log::discard();
+        let retval = interpret_eth_call_error(&logger, error_resp(-32000, "out of gas", None));
+        assert!(matches!(retval, Ok(call::Retval::Null)));
+    }
+
+    #[test]
+    fn contract_call_errors_are_classified() {
+        let revert = ContractCallError::AlloyError(error_resp(-32000, "execution reverted", None));
+        assert!(revert.is_deterministic());
+
+        let parity_revert = ContractCallError::AlloyError(error_resp(
+            PARITY_VM_EXECUTION_ERROR,
+            "VM execution error.",
+            Some("Reverted 0x"),
+        ));
+        assert!(parity_revert.is_deterministic());
+
+        assert!(ContractCallError::OutOfGas.is_deterministic());
+
+        let transport = ContractCallError::AlloyError(TransportErrorKind::custom_str(
+            "connection reset by peer",
+        ));
+        assert!(!transport.is_deterministic());
+
+        let provider = ContractCallError::AlloyError(error_resp(-32005, "rate limited", None));
+        assert!(!provider.is_deterministic());
+
+        assert!(!ContractCallError::Timeout.is_deterministic());
+
+        let internal = ContractCallError::Internal("oops".to_string());
+        assert!(!internal.is_deterministic());
+    }
+
+    #[test]
+    fn declared_calls_that_run_out_of_gas_fail_deterministically() {
+        let err = MappingError::from(ContractCallError::OutOfGas);
+        assert!(matches!(err, MappingError::Deterministic(_)));
+    }
+
+    #[test]
+    fn declared_call_errors_follow_their_classification() {
+        let revert = ContractCallError::AlloyError(error_resp(-32000, "execution reverted", None));
+        assert!(matches!(
+            MappingError::from(revert),
+            MappingError::Deterministic(_)
+        ));
+
+        let abi = ContractCallError::ABIError(anyhow::anyhow!("bad type"));
+        assert!(matches!(
+            MappingError::from(abi),
+            MappingError::Deterministic(_)
+        ));
+
+        let provider = ContractCallError::AlloyError(error_resp(-32005, "rate limited", None));
+        assert!(matches!(
+            MappingError::from(provider),
+            MappingError::PossibleReorg(_)
+        ));
+
+        let internal = ContractCallError::Internal("oops".to_string());
+        assert!(matches!(
+            MappingError::from(internal),
+            MappingError::Unknown(_)
+        ));
+    }
+}
@@ -66,4 +66,16 @@ impl graph::blockchain::NodeCapabilities<crate::Chain> for NodeCapabilities {
             }),
         }
     }
+
+    /// A node without `archive` is a full node; any node can serve
+    /// subgraphs that only need a full node.
+    fn missing(&self, required: &Self) -> Option<&'static str> {
+        if required.archive && !self.archive {
+            return Some("archive");
+        }
+        if required.traces && !self.traces {
+            return Some("traces");
+        }
+        None
+    }
 }
@@ -4,28 +4,29 @@ use async_trait::async_trait;
 use graph::blockchain::client::ChainClient;
 use graph::blockchain::firehose_block_ingestor::{FirehoseBlockIngestor, Transforms};
 use graph::blockchain::{
-    BlockIngestor, BlockTime, BlockchainKind, ChainIdentifier, ExtendedBlockPtr,
-    TriggerFilterWrapper, TriggersAdapterSelector,
+    BlockIngestor, BlockTime, BlockchainKind, ChainHealth, ChainIdentifier, ChainMetadata,
+    ExtendedBlockPtr, TriggerFilterWrapper, TriggersAdapterSelector, with_ingestor_timeout,
 };
 use graph::components::network_provider::ChainName;
 use graph::components::store::{DeploymentCursorTracker, SourceableStore};
 use graph::data::subgraph::UnifiedMappingApiVersion;
 use graph::firehose::{FirehoseEndpoint, FirehoseEndpoints, ForkStep};
-use graph::futures03::TryStreamExt;
+use graph::futures03::{Stream, TryStreamExt};
 use graph::prelude::{
     BlockHash, ComponentLoggerConfig, ElasticComponentLoggerConfig, EthereumBlock,
     EthereumCallCache, LightEthereumBlock, LightEthereumBlockExt, MetricsRegistry, StoreError,
     retry,
 };
-use graph::slog::{debug, error, trace, warn};
+use graph::slog::{debug, error, info, trace, warn};
 use graph::{
     blockchain::{
-        Block, BlockPtr, Blockchain, ChainHeadUpdateListener, IngestorError,
+        Block, BlockPtr, Blockchain, ChainHeadUpdateListener, ChainHeadUpdateStream, IngestorError,
         RuntimeAdapter as RuntimeAdapterTrait, TriggerFilter as _,
         block_stream::{
             BlockRefetcher, BlockStreamEvent, BlockWithTriggers, FirehoseError,
             FirehoseMapper as FirehoseMapperTrait, TriggersAdapter as TriggersAdapterTrait,
         },
+        fallback_block_stream::FallbackBlockStream,
         firehose_block_stream::FirehoseBlockStream,
     },
     cheap_clone::CheapClone,
@@ -40,16 +41,19 @@ use prost::Message;
 use std::collections::{BTreeSet, HashSet};
 use std::future::Future;
 use std::iter::FromIterator;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
 use std::time::Duration;
 
 use crate::codec::HeaderOnlyBlock;
 use crate::data_source::DataSourceTemplate;
 use crate::data_source::UnresolvedDataSourceTemplate;
-use crate::ingestor::PollingBlockIngestor;
+use crate::ingestor::{IngestorMetrics, PollingBlockIngestor, ReceiptDemand, ReceiptDemandGuard};
 use crate::network::EthereumNetworkAdapters;
 use crate::polling_block_stream::PollingBlockStream;
 use crate::runtime::runtime_adapter::eth_call_gas;
+use crate::spot_check::FirehoseSpotCheck;
 use crate::{BufferedCallCache, NodeCapabilities};
 use crate::{
     ENV_VARS, SubgraphEthRpcMetrics, TriggerFilter,
@@ -69,6 +73,12 @@ use graph::blockchain::block_stream::{
 /// Celo Mainnet: 42220, Testnet Alfajores: 44787, Testnet Baklava: 62320
 const CELO_CHAIN_IDS: [u64; 3] = [42220, 44787, 62320];
 
+/// The symbol of the native token of chains that do not configure one
+pub const NATIVE_TOKEN_SYMBOL: &str = "ETH";
+
+/// The decimals of the native token of chains that do not configure them
+pub const NATIVE_TOKEN_DECIMALS: u8 = 18;
+
 /// Resolved per-chain settings. Populated at chain initialisation from the config file (with
 /// ENV_VAR fallbacks) and stored on [`Chain`] and [`crate::EthereumAdapter`].
 #[derive(Clone, Debug)]
@@ -84,6 +94,13 @@ pub struct ChainSettings {
     pub get_logs_max_contracts: usize,
     pub block_ingestor_max_concurrent_json_rpc_calls: usize,
     pub genesis_block_number: u64,
+    /// How long the chain head may stay the same before the block ingestor
+    /// switches providers; zero turns the check off
+    pub block_ingestor_stall_timeout: Duration,
+    /// The symbol of the chain's native token
+    pub native_token_symbol: String,
+    /// The number of decimals of the chain's native token
+    pub native_token_decimals: u8,
 }
 
 impl ChainSettings {
@@ -103,12 +120,73 @@ impl ChainSettings {
             block_ingestor_max_concurrent_json_rpc_calls: ENV_VARS
                 .block_ingestor_max_concurrent_json_rpc_calls,
             genesis_block_number: ENV_VARS.genesis_block_number,
+            block_ingestor_stall_timeout: ENV_VARS.block_ingestor_stall_timeout,
+            native_token_symbol: NATIVE_TOKEN_SYMBOL.to_string(),
+            native_token_decimals: NATIVE_TOKEN_DECIMALS,
+        }
+    }
+
+    /// The metadata of the chain with these settings and `identifier`
+    pub fn chain_metadata(&self, identifier: ChainIdentifier) -> ChainMetadata {
+        ChainMetadata {
+            native_token_symbol: self.native_token_symbol.clone(),
+            native_token_decimals: self.native_token_decimals,
+            identifier,
         }
     }
 }
 
 pub struct EthereumStreamBuilder {}
 
+impl EthereumStreamBuilder {
+    /// Build a polling block stream that gets its blocks from the RPC
+    /// providers of `chain`, even if the chain uses Firehose
+    async fn build_rpc_fallback(
+        &self,
+        chain: &Chain,
+        deployment: &DeploymentLocator,
+        start_blocks: Vec<BlockNumber>,
+        subgraph_current_block: Option<BlockPtr>,
+        filter: Arc<TriggerFilter>,
+        unified_api_version: UnifiedMappingApiVersion,
+    ) -> Result<Box<dyn BlockStream<Chain>>> {
+        let logger = chain
+            .logger_factory
+            .subgraph_logger(deployment)
+            .new(o!("component" => "BlockStream"));
+
+        let adapter = TriggersAdapter {
+            logger: logger.clone(),
+            ethrpc_metrics: Arc::new(SubgraphEthRpcMetrics::new(
+                chain.registry.clone(),
+                &deployment.hash,
+            )),
+            chain_store: chain.chain_store.cheap_clone(),
+            chain_client: Arc::new(ChainClient::new_rpc(chain.eth_adapters.as_ref().clone())),
+            capabilities: filter.node_capabilities(),
+            unified_api_version: unified_api_version.clone(),
+            eth_adapters: chain.eth_adapters.cheap_clone(),
+        };
+        let chain_head_update_stream = chain
+            .chain_head_update_listener
+            .subscribe(chain.name.to_string(), logger.clone());
+
+        Ok(Box::new(PollingBlockStream::new(
+            chain_head_update_stream,
+            Arc::new(TriggersAdapterWrapper::new(Arc::new(adapter), vec![])),
+            deployment.hash.clone(),
+            Arc::new(TriggerFilterWrapper::new(filter.as_ref().clone(), vec![])),
+            start_blocks,
+            chain.reorg_threshold,
+            logger,
+            chain.settings.max_block_range_size,
+            chain.settings.target_triggers_per_block_range,
+            unified_api_version,
+            subgraph_current_block,
+        )))
+    }
+}
+
 #[async_trait]
 impl BlockStreamBuilder<Chain> for EthereumStreamBuilder {
     async fn build_firehose(
@@ -136,7 +214,67 @@ impl BlockStreamBuilder<Chain> for EthereumStreamBuilder {
             .subgraph_logger(&deployment)
             .new(o!("component" => "FirehoseBlockStream"));
 
-        let firehose_mapper = Arc::new(FirehoseMapper { adapter, filter });
+        let spot_check = (ENV_VARS.firehose_spot_check_interval > 0).then(|| {
+            Arc::new(FirehoseSpotCheck::new(
+                ENV_VARS.firehose_spot_check_interval,
+                chain.reorg_threshold,
+                chain.eth_adapters.cheap_clone(),
+                &chain.registry,
+                &deployment.hash,
+            ))
+        });
+        let firehose_mapper = Arc::new(FirehoseMapper {
+            adapter,
+            filter: filter.cheap_clone(),
+            spot_check,
+        });
+
+        if ENV_VARS.firehose_rpc_fallback && block_cursor.is_none() {
+            let start_block = subgraph_current_block
+                .as_ref()
+                .map(|ptr| ptr.number + 1)
+                .or_else(|| start_blocks.iter().min().copied())
+                .unwrap_or(0);
+            let endpoint = chain.chain_client().firehose_endpoint().await?;
+            let first_streamable = endpoint.info().await?.genesis_block_ptr()?.number;
+
+            if start_block < first_streamable {
+                info!(
+                    logger,
+                    "Firehose can not serve the first blocks of the subgraph, getting them from RPC";
+                    "start_block" => start_block,
+                    "first_streamable_block" => first_streamable,
+                );
+
+                let historical = self
+                    .build_rpc_fallback(
+                        chain,
+                        &deployment,
+                        start_blocks,
+                        subgraph_current_block,
+                        filter,
+                        unified_api_version,
+                    )
+                    .await?;
+                let live: Box<dyn BlockStream<Chain>> = Box::new(FirehoseBlockStream::new(
+                    deployment.hash,
+                    chain.chain_client(),
+                    None,
+                    FirehoseCursor::None,
+                    firehose_mapper,
+                    vec![first_streamable],
+                    logger.clone(),
+                    chain.registry.clone(),
+                ));
+
+                return Ok(Box::new(FallbackBlockStream::new(
+                    historical,
+                    first_streamable,
+                    Box::pin(async move { Ok(live) }),
+                    logger,
+                )));
+            }
+        }
 
         Ok(Box::new(FirehoseBlockStream::new(
             deployment.hash,
@@ -220,10 +358,10 @@ impl BlockStreamBuilder<Chain> for EthereumStreamBuilder {
                 if CELO_CHAIN_IDS.contains(&chain_id) {
                     0
                 } else {
-                    chain.reorg_threshold
+                    chain.finality_depth()
                 }
             }
-            _ if is_using_subgraph_composition => chain.reorg_threshold,
+            _ if is_using_subgraph_composition => chain.finality_depth(),
             _ => panic!(
                 "expected rpc when using polling blockstream : {}",
                 is_using_subgraph_composition
@@ -236,7 +374,12 @@ impl BlockStreamBuilder<Chain> for EthereumStreamBuilder {
             chain.settings.max_block_range_size
         };
 
-        Ok(Box::new(PollingBlockStream::new(
+        let receipt_guard = filter
+            .chain_filter
+            .requires_receipts()
+            .then(|| chain.receipt_demand.register());
+
+        let stream = Box::new(PollingBlockStream::new(
             chain_head_update_stream,
             Arc::new(adapter),
             deployment.hash,
@@ -248,7 +391,33 @@ impl BlockStreamBuilder<Chain> for EthereumStreamBuilder {
             chain.settings.target_triggers_per_block_range,
             unified_api_version,
             subgraph_current_block,
-        )))
+        ));
+
+        Ok(Box::new(ReceiptDemandStream {
+            stream,
+            _guard: receipt_guard,
+        }))
+    }
+}
+
+/// A block stream that keeps the block ingestor loading receipts for as
+/// long as it is alive
+struct ReceiptDemandStream {
+    stream: Box<dyn BlockStream<Chain>>,
+    _guard: Option<ReceiptDemandGuard>,
+}
+
+impl Stream for ReceiptDemandStream {
+    type Item = Result<BlockStreamEvent<Chain>, BlockStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+}
+
+impl BlockStream<Chain> for ReceiptDemandStream {
+    fn buffer_size_hint(&self) -> usize {
+        self.stream.buffer_size_hint()
     }
 }
 
@@ -335,6 +504,8 @@ pub trait RuntimeAdapterBuilder: Send + Sync + 'static {
         eth_adapters: Arc<EthereumNetworkAdapters>,
         call_cache: Arc<dyn EthereumCallCache>,
         chain_identifier: Arc<ChainIdentifier>,
+        chain_store: Arc<dyn ChainStore>,
+        reorg_threshold: BlockNumber,
     ) -> Arc<dyn RuntimeAdapterTrait<Chain>>;
 }
 
@@ -346,11 +517,15 @@ impl RuntimeAdapterBuilder for EthereumRuntimeAdapterBuilder {
         eth_adapters: Arc<EthereumNetworkAdapters>,
         call_cache: Arc<dyn EthereumCallCache>,
         chain_identifier: Arc<ChainIdentifier>,
+        chain_store: Arc<dyn ChainStore>,
+        reorg_threshold: BlockNumber,
     ) -> Arc<dyn RuntimeAdapterTrait<Chain>> {
         Arc::new(RuntimeAdapter {
             eth_adapters,
             call_cache,
             chain_identifier,
+            chain_store,
+            reorg_threshold,
         })
     }
 }
@@ -371,6 +546,8 @@ pub struct Chain {
     runtime_adapter_builder: Arc<dyn RuntimeAdapterBuilder>,
     eth_adapters: Arc<EthereumNetworkAdapters>,
     pub settings: Arc<ChainSettings>,
+    /// Counts the block streams that need the ingestor to store receipts
+    receipt_demand: ReceiptDemand,
 }
 
 impl std::fmt::Debug for Chain {
@@ -446,6 +623,7 @@ impl Chain {
             reorg_threshold,
             is_ingestible,
             settings,
+            receipt_demand: ReceiptDemand::default(),
         }
     }
 
@@ -471,6 +649,26 @@ impl Chain {
         };
         adapters.cheapest().await.unwrap()
     }
+
+    /// Ask one of our providers for the chain head
+    async fn provider_head_ptr(&self, logger: &Logger) -> Result<BlockPtr, Error> {
+        match self.client.as_ref() {
+            ChainClient::Firehose(endpoints) => {
+                endpoints
+                    .endpoint()
+                    .await?
+                    .head_block_ptr::<HeaderOnlyBlock>(logger)
+                    .await
+            }
+            ChainClient::Rpc(adapters) => {
+                let adapter = adapters
+                    .cheapest()
+                    .await
+                    .with_context(|| format!("no adapter for chain {}", self.name))?;
+                Ok(adapter.latest_block_ptr(logger).await?)
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -569,18 +767,36 @@ impl Blockchain for Chain {
         self.chain_store.cheap_clone().chain_head_ptr().await
     }
 
+    fn chain_head_updates(&self) -> ChainHeadUpdateStream {
+        let logger = self
+            .logger_factory
+            .component_logger("ChainHeadUpdates", None);
+        self.chain_head_update_listener
+            .subscribe(self.name.to_string(), logger)
+    }
+
+    async fn chain_metadata(&self) -> Result<ChainMetadata, Error> {
+        let identifier = self.chain_store.chain_identifier().await?;
+        Ok(self.settings.chain_metadata(identifier))
+    }
+
     async fn block_pointer_from_number(
         &self,
         logger: &Logger,
         number: BlockNumber,
     ) -> Result<BlockPtr, IngestorError> {
+        let timeout = ENV_VARS.block_pointer_timeout;
+
         match self.client.as_ref() {
-            ChainClient::Firehose(endpoints) => endpoints
-                .endpoint()
-                .await?
-                .block_ptr_for_number::<HeaderOnlyBlock>(logger, number)
+            ChainClient::Firehose(endpoints) => {
+                let endpoint = endpoints.endpoint().await?;
+                with_ingestor_timeout(
+                    timeout,
+                    "block_ptr_for_number",
+                    endpoint.block_ptr_for_number::<HeaderOnlyBlock>(logger, number),
+                )
                 .await
-                .map_err(IngestorError::Unknown),
+            }
             ChainClient::Rpc(adapters) => {
                 let cached = self
                     .chain_store
@@ -600,18 +816,35 @@ impl Blockchain for Chain {
                     .with_context(|| format!("no adapter for chain {}", self.name))?
                     .clone();
 
-                adapter
-                    .next_existing_ptr_to_number(logger, number)
-                    .await
-                    .map_err(From::from)
+                with_ingestor_timeout(
+                    timeout,
+                    "next_existing_ptr_to_number",
+                    adapter.next_existing_ptr_to_number(logger, number),
+                )
+                .await
             }
         }
     }
 
+    fn finality_depth(&self) -> BlockNumber {
+        self.reorg_threshold
+    }
+
     fn is_refetch_block_required(&self) -> bool {
         self.block_refetcher.required(self)
     }
 
+    async fn health_check(&self) -> ChainHealth {
+        let logger = self
+            .logger_factory
+            .component_logger("ChainHealthCheck", None);
+        ChainHealth::from(self.provider_head_ptr(&logger).await)
+    }
+
+    fn supported_handler_kinds() -> HashSet<&'static str> {
+        crate::data_source::HANDLER_KINDS.into_iter().collect()
+    }
+
     async fn refetch_firehose_block(
         &self,
         logger: &Logger,
@@ -623,13 +856,19 @@ impl Blockchain for Chain {
     async fn runtime(
         &self,
     ) -> anyhow::Result<(Arc<dyn RuntimeAdapterTrait<Self>>, Self::DecoderHook)> {
-        let call_cache = Arc::new(BufferedCallCache::new(self.call_cache.cheap_clone()));
+        let call_cache = Arc::new(BufferedCallCache::new(
+            self.call_cache.cheap_clone(),
+            self.chain_store.cheap_clone(),
+            self.finality_depth(),
+        ));
         let chain_ident = self.chain_store.chain_identifier().await?;
 
         let builder = self.runtime_adapter_builder.build(
             self.eth_adapters.cheap_clone(),
             call_cache.cheap_clone(),
             Arc::new(chain_ident.clone()),
+            self.chain_store.cheap_clone(),
+            self.reorg_threshold,
         );
         let eth_call_gas = eth_call_gas(&chain_ident);
 
@@ -680,17 +919,26 @@ impl Blockchain for Chain {
                     );
                 }
 
-                // The block ingestor must be configured to keep at least REORG_THRESHOLD ancestors,
-                // because the json-rpc BlockStream expects blocks after the reorg threshold to be
-                // present in the DB.
-                Box::new(PollingBlockIngestor::new(
+                // The block ingestor must be configured to keep at least as many ancestors as
+                // the finality depth, because the json-rpc BlockStream expects blocks that are
+                // not final yet to be present in the DB.
+                let ingestor = PollingBlockIngestor::new(
                     logger,
-                    graph::env::ENV_VARS.reorg_threshold(),
+                    self.finality_depth(),
                     self.chain_client(),
                     self.chain_store.cheap_clone(),
                     self.settings.polling_interval,
                     self.name.clone(),
-                )?)
+                )?
+                .with_metrics(IngestorMetrics::new(&self.registry, &self.name))
+                .with_stall_timeout(self.settings.block_ingestor_stall_timeout);
+                let ingestor = if ENV_VARS.block_ingestor_lazy_receipts {
+                    ingestor.with_receipt_demand(self.receipt_demand.clone())
+                } else {
+                    ingestor
+                };
+
+                Box::new(ingestor)
             }
         };
 
@@ -726,6 +974,59 @@ impl BlockFinality {
             BlockFinality::Ptr(_) => unreachable!("light_block called on HeaderOnly"),
         }
     }
+
+    /// A rough estimate of the number of triggers in the block: its logs and
+    /// calls if we have them, and its transactions otherwise, plus one for
+    /// block handlers.
+    pub(crate) fn estimated_trigger_count(&self) -> usize {
+        let transaction_count = |block: &LightEthereumBlock| block.inner().transactions.len();
+
+        match self {
+            BlockFinality::Final(block) => transaction_count(block) + 1,
+            BlockFinality::NonFinal(block) => {
+                let receipts = &block.ethereum_block.transaction_receipts;
+                let events = if receipts.is_empty() {
+                    transaction_count(&block.ethereum_block.block)
+                } else {
+                    receipts.iter().map(|r| r.logs().len()).sum()
+                };
+                let calls = block.calls.as_ref().map_or(0, |calls| calls.len());
+
+                events + calls + 1
+            }
+            BlockFinality::Ptr(_) => 0,
+        }
+    }
+
+    /// A rough estimate of the memory the block takes up, based on typical
+    /// sizes of transactions, logs and calls rather than their actual
+    /// contents
+    fn estimated_byte_size(&self) -> usize {
+        const HEADER_BYTES: usize = 1024;
+        const TRANSACTION_BYTES: usize = 512;
+        const RECEIPT_BYTES: usize = 256;
+        const LOG_BYTES: usize = 256;
+        const CALL_BYTES: usize = 512;
+
+        let transactions =
+            |block: &LightEthereumBlock| block.inner().transactions.len() * TRANSACTION_BYTES;
+
+        match self {
+            BlockFinality::Final(block) => HEADER_BYTES + transactions(block),
+            BlockFinality::NonFinal(block) => {
+                let receipts: usize = block
+                    .ethereum_block
+                    .transaction_receipts
+                    .iter()
+                    .map(|r| RECEIPT_BYTES + r.logs().len() * LOG_BYTES)
+                    .sum();
+                let calls = block.calls.as_ref().map_or(0, |calls| calls.len()) * CALL_BYTES;
+
+                HEADER_BYTES + transactions(&block.ethereum_block.block) + receipts + calls
+            }
+            BlockFinality::Ptr(_) => std::mem::size_of::<ExtendedBlockPtr>(),
+        }
+    }
 }
 
 impl Block for BlockFinality {
@@ -793,6 +1094,10 @@ impl Block for BlockFinality {
             BlockFinality::Ptr(block) => block.timestamp,
         }
     }
+
+    fn byte_size(&self) -> usize {
+        self.estimated_byte_size()
+    }
 }
 
 pub struct DummyDataSourceTemplate;
@@ -928,6 +1233,39 @@ impl TriggersAdapterTrait<Chain> for TriggersAdapter {
         .await
     }
 
+    async fn next_block_with_triggers(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        filter: &TriggerFilter,
+    ) -> Result<Option<BlockNumber>, Error> {
+        // Without other kinds of triggers, `eth_getLogs` tells us which
+        // blocks we need to look at
+        if !filter.only_logs() {
+            return Ok(Some(from));
+        }
+
+        let logs = self
+            .chain_client
+            .rpc()?
+            .cheapest_with(&self.capabilities)
+            .await?
+            .logs_in_block_range(
+                &self.logger,
+                self.ethrpc_metrics.clone(),
+                from,
+                to,
+                filter.log.clone(),
+            )
+            .await?;
+
+        Ok(logs
+            .iter()
+            .filter_map(|log| log.block_number)
+            .map(|number| number as BlockNumber)
+            .min())
+    }
+
     async fn load_block_ptrs_by_numbers(
         &self,
         logger: Logger,
@@ -1074,6 +1412,10 @@ impl TriggersAdapterTrait<Chain> for TriggersAdapter {
         }
     }
 
+    fn estimated_trigger_count(&self, block: &BlockFinality) -> usize {
+        block.estimated_trigger_count()
+    }
+
     async fn is_on_main_chain(&self, ptr: BlockPtr) -> Result<bool, Error> {
         // It is tempting to use the block cache here; but that can go wrong
         // when graph-node gets shut down and some of its nonfinal blocks
@@ -1299,6 +1641,7 @@ impl TriggersAdapter {
 pub struct FirehoseMapper {
     adapter: Arc<dyn TriggersAdapterTrait<Chain>>,
     filter: Arc<TriggerFilter>,
+    spot_check: Option<Arc<FirehoseSpotCheck>>,
 }
 
 #[async_trait]
@@ -1369,6 +1712,9 @@ impl FirehoseMapperTrait<Chain> for FirehoseMapper {
             StepNew => {
                 // unwrap: Input cannot be None so output will be error or block.
                 let block = self.decode_block(Some(any_block.value.as_ref()))?.unwrap();
+                if let Some(spot_check) = &self.spot_check {
+                    spot_check.check(logger, &block);
+                }
                 let block_with_triggers = self.block_with_triggers(logger, block).await?;
 
                 Ok(BlockStreamEvent::ProcessBlock(
@@ -1676,4 +2022,88 @@ mod tests {
             "root=101: should stop at 102 (child of root, across skip)"
         );
     }
+
+    fn block_with_logs(logs_per_receipt: &[usize]) -> BlockFinality {
+        use graph::prelude::alloy::consensus::{Eip658Value, Receipt, ReceiptWithBloom};
+        use graph::prelude::alloy::network::AnyReceiptEnvelope;
+        use graph::prelude::alloy::primitives::{Address, B256, Bloom};
+        use graph::prelude::alloy::rpc::types::{Log, TransactionReceipt};
+
+        let receipts = logs_per_receipt
+            .iter()
+            .map(|&count| {
+                let receipt = Receipt {
+                    status: Eip658Value::Eip658(true),
+                    cumulative_gas_used: 0,
+                    logs: vec![Log::default(); count],
+                };
+
+                Arc::new(TransactionReceipt {
+                    transaction_hash: B256::ZERO,
+                    transaction_index: Some(0),
+                    block_hash: None,
+                    block_number: None,
+                    gas_used: 0,
+                    contract_address: None,
+                    from: Address::ZERO,
+                    to: None,
+                    effective_gas_price: 0,
+                    blob_gas_used: None,
+                    blob_gas_price: None,
+                    inner: AnyReceiptEnvelope {
+                        inner: ReceiptWithBloom::new(receipt, Bloom::default()),
+                        r#type: 0,
+                    },
+                })
+            })
+            .collect();
+
+        BlockFinality::NonFinal(EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock {
+                block: Arc::default(),
+                transaction_receipts: receipts,
+            },
+            calls: Some(vec![]),
+        })
+    }
+
+    #[test]
+    fn test_estimated_trigger_count_tracks_logs() {
+        // One block trigger for a block without any logs
+        assert_eq!(block_with_logs(&[]).estimated_trigger_count(), 1);
+        assert_eq!(block_with_logs(&[0, 0]).estimated_trigger_count(), 1);
+
+        assert_eq!(block_with_logs(&[3]).estimated_trigger_count(), 4);
+        assert_eq!(block_with_logs(&[3, 2]).estimated_trigger_count(), 6);
+        assert_eq!(block_with_logs(&[100; 50]).estimated_trigger_count(), 5001);
+
+        // Calls are counted too
+        let BlockFinality::NonFinal(mut block) = block_with_logs(&[3]) else {
+            unreachable!()
+        };
+        block.calls = Some(vec![graph::prelude::EthereumCall::default(); 2]);
+        assert_eq!(BlockFinality::NonFinal(block).estimated_trigger_count(), 6);
+    }
+
+    #[test]
+    fn chain_metadata_uses_the_configured_native_token() {
+        let identifier = ChainIdentifier {
+            net_version: "137".to_string(),
+            genesis_block_hash: BlockHash::from(vec![1; 32]),
+        };
+
+        let settings = ChainSettings::from_env_defaults();
+        let metadata = settings.chain_metadata(identifier.clone());
+        assert_eq!("ETH", metadata.native_token_symbol);
+        assert_eq!(18, metadata.native_token_decimals);
+
+        let settings = ChainSettings {
+            native_token_symbol: "POL".to_string(),
+            ..ChainSettings::from_env_defaults()
+        };
+        let metadata = settings.chain_metadata(identifier.clone());
+        assert_eq!("POL", metadata.native_token_symbol);
+        assert_eq!(18, metadata.native_token_decimals);
+        assert_eq!(identifier, metadata.identifier);
+    }
 }
@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use graph::abi;
 use graph::abi::EventExt;
 use graph::abi::FunctionExt;
-use graph::blockchain::{BlockPtr, TriggerWithHandler};
+use graph::blockchain::{BlockPtr, BlockchainKind, TriggerWithHandler};
 use graph::components::ethereum::AnyTransaction;
 use graph::components::link_resolver::LinkResolverContext;
 use graph::components::metrics::subgraph::SubgraphInstanceMetrics;
@@ -63,6 +63,13 @@ const ETHEREUM_KINDS: &[&str] = &["ethereum/contract", "ethereum"];
 const EVENT_HANDLER_KIND: &str = "event";
 const CALL_HANDLER_KIND: &str = "call";
 const BLOCK_HANDLER_KIND: &str = "block";
+const BLOCK_FILTER_CALL_HANDLER_KIND: &str = "block_filter_call";
+const BLOCK_FILTER_ONCE_HANDLER_KIND: &str = "block_filter_once";
+const BLOCK_FILTER_POLLING_HANDLER_KIND: &str = "block_filter_polling";
+
+/// The handler kinds that data sources can declare in the manifest
+pub(crate) const HANDLER_KINDS: [&str; 3] =
+    [EVENT_HANDLER_KIND, CALL_HANDLER_KIND, BLOCK_HANDLER_KIND];
 
 /// Runtime representation of a data source.
 // Note: Not great for memory usage that this needs to be `Clone`, considering how there may be tens
@@ -100,42 +107,7 @@ impl blockchain::DataSource<Chain> for DataSource {
             "Cannot create onchain data source from offchain template"
         ))?;
 
-        // Obtain the address from the parameters
-        let string = params
-            .first()
-            .with_context(|| {
-                format!(
-                    "Failed to create data source from template `{}`: address parameter is missing",
-                    template.name
-                )
-            })?
-            .trim_start_matches("0x");
-
-        let address = Address::from_str(string).with_context(|| {
-            format!(
-                "Failed to create data source from template `{}`, invalid address provided",
-                template.name
-            )
-        })?;
-
-        let contract_abi = template
-            .mapping
-            .find_abi(&template.source.abi)
-            .with_context(|| format!("template `{}`", template.name))?;
-
-        Ok(DataSource {
-            kind: template.kind.clone(),
-            network: template.network.clone(),
-            name: template.name.clone(),
-            manifest_idx: template.manifest_idx,
-            address: Some(address),
-            start_block: creation_block,
-            end_block: None,
-            mapping: template.mapping.clone(),
-            context: Arc::new(context),
-            creation_block: Some(creation_block),
-            contract_abi,
-        })
+        DataSource::from_template(template, &params, context, creation_block)
     }
 
     fn address(&self) -> Option<&[u8]> {
@@ -301,7 +273,9 @@ impl blockchain::DataSource<Chain> for DataSource {
     fn validate(&self, spec_version: &semver::Version) -> Vec<Error> {
         let mut errors = vec![];
 
-        if !ETHEREUM_KINDS.contains(&self.kind.as_str()) {
+        if !ETHEREUM_KINDS.contains(&self.kind.as_str())
+            && BlockchainKind::from_alias(&self.kind) != Some(BlockchainKind::Ethereum)
+        {
             errors.push(anyhow!(
                 "data source has invalid `kind`, expected `ethereum` but found {}",
                 self.kind
@@ -389,6 +363,7 @@ impl blockchain::DataSource<Chain> for DataSource {
         }
 
         for handler in &self.mapping.event_handlers {
+            let event = self.contract_event_with_signature(&handler.event);
             for call in handler.calls.decls.as_ref() {
                 match self.mapping.find_abi(&call.expr.abi) {
                     // TODO: Handle overloaded functions by passing a signature
@@ -402,6 +377,16 @@ impl blockchain::DataSource<Chain> for DataSource {
                         errors.push(e);
                     }
                 }
+                if let Some(event) = event
+                    && let Err(e) = call.expr.validate_event_params(event)
+                {
+                    errors.push(anyhow!(
+                        "handler {}: declared call `{}`: {}",
+                        handler.event,
+                        call.label,
+                        e
+                    ));
+                }
             }
         }
         errors
@@ -475,6 +460,73 @@ fn create_dummy_transaction(
 }
 
 impl DataSource {
+    /// Create the data source that a mapping gets when it instantiates
+    /// `template` with `params` in block `creation_block`
+    fn from_template(
+        template: &DataSourceTemplate,
+        params: &[String],
+        context: Option<DataSourceContext>,
+        creation_block: BlockNumber,
+    ) -> Result<Self, Error> {
+        // Obtain the address from the parameters
+        let string = params
+            .first()
+            .with_context(|| {
+                format!(
+                    "Failed to create data source from template `{}`: address parameter is missing",
+                    template.name
+                )
+            })?
+            .trim_start_matches("0x");
+
+        let address = Address::from_str(string).with_context(|| {
+            format!(
+                "Failed to create data source from template `{}`, invalid address provided",
+                template.name
+            )
+        })?;
+
+        let contract_abi = template
+            .mapping
+            .find_abi(&template.source.abi)
+            .with_context(|| format!("template `{}`", template.name))?;
+
+        Ok(DataSource {
+            kind: template.kind.clone(),
+            network: template.network.clone(),
+            name: template.name.clone(),
+            manifest_idx: template.manifest_idx,
+            address: Some(address),
+            start_block: creation_block,
+            end_block: None,
+            mapping: template.mapping.clone(),
+            context: Arc::new(context),
+            creation_block: Some(creation_block),
+            contract_abi,
+        })
+    }
+
+    /// Create a dynamic data source from `template` the same way that
+    /// `dataSource.create` in a mapping does, without having to run a
+    /// subgraph. Unlike mappings, which may pass additional parameters
+    /// that are ignored, `params` must consist of exactly the address of
+    /// the contract.
+    #[cfg(test)]
+    pub fn from_template_for_testing(
+        template: &DataSourceTemplate,
+        params: &[String],
+        context: Option<DataSourceContext>,
+        creation_block: BlockNumber,
+    ) -> Result<Self, Error> {
+        ensure!(
+            params.len() == 1,
+            "template `{}` takes the address of a contract as its only parameter, but got {} parameters",
+            template.name,
+            params.len()
+        );
+        DataSource::from_template(template, params, context, creation_block)
+    }
+
     fn from_manifest(
         kind: String,
         network: Option<String>,
@@ -1337,7 +1389,8 @@ pub struct Mapping {
 
 impl Mapping {
     pub fn requires_archive(&self) -> anyhow::Result<bool> {
-        calls_host_fn(&self.runtime, "ethereum.call")
+        Ok(calls_host_fn(&self.runtime, "ethereum.call")?
+            || calls_host_fn(&self.runtime, "ethereum.callAtBlock")?)
     }
 
     pub fn has_call_handler(&self) -> bool {
@@ -1449,9 +1502,9 @@ impl MappingBlockHandler {
     pub fn kind(&self) -> &str {
         match &self.filter {
             Some(filter) => match filter {
-                BlockHandlerFilter::Call => "block_filter_call",
-                BlockHandlerFilter::Once => "block_filter_once",
-                BlockHandlerFilter::Polling { .. } => "block_filter_polling",
+                BlockHandlerFilter::Call => BLOCK_FILTER_CALL_HANDLER_KIND,
+                BlockHandlerFilter::Once => BLOCK_FILTER_ONCE_HANDLER_KIND,
+                BlockHandlerFilter::Polling { .. } => BLOCK_FILTER_POLLING_HANDLER_KIND,
             },
             None => BLOCK_HANDLER_KIND,
         }
@@ -1597,3 +1650,153 @@ fn string_to_b256(s: &str) -> B256 {
 pub struct TemplateSource {
     pub abi: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use graph::data::subgraph::SPEC_VERSION_1_6_0;
+
+    fn template() -> DataSourceTemplate {
+        let abi = Arc::new(MappingABI {
+            name: "Factory".to_string(),
+            contract: abi::JsonAbi::new(),
+        });
+        DataSourceTemplate {
+            kind: "ethereum/contract".to_string(),
+            network: Some("mainnet".to_string()),
+            name: "Pool".to_string(),
+            manifest_idx: 3,
+            source: TemplateSource {
+                abi: "Factory".to_string(),
+            },
+            mapping: Mapping {
+                kind: "ethereum/events".to_string(),
+                api_version: semver::Version::new(0, 0, 7),
+                language: "wasm/assemblyscript".to_string(),
+                entities: vec![],
+                abis: vec![abi],
+                block_handlers: vec![],
+                call_handlers: vec![],
+                event_handlers: vec![],
+                runtime: Arc::new(vec![]),
+                link: Link {
+                    link: "mapping".to_string(),
+                },
+            },
+        }
+    }
+
+    fn params(params: &[&str]) -> Vec<String> {
+        params.iter().map(|param| param.to_string()).collect()
+    }
+
+    #[test]
+    fn template_with_address_creates_data_source() {
+        let address = "0x22843e74c59580b3eaf6c233fa67d8b7c561a835";
+
+        let ds = DataSource::from_template_for_testing(&template(), &params(&[address]), None, 17)
+            .unwrap();
+
+        assert_eq!(ds.name, "Pool");
+        assert_eq!(ds.kind, "ethereum/contract");
+        assert_eq!(ds.network.as_deref(), Some("mainnet"));
+        assert_eq!(ds.manifest_idx, 3);
+        assert_eq!(ds.address, Some(Address::from_str(address).unwrap()));
+        assert_eq!(ds.start_block, 17);
+        assert_eq!(ds.creation_block, Some(17));
+        assert_eq!(ds.end_block, None);
+        assert_eq!(ds.contract_abi.name, "Factory");
+        assert!(ds.context.is_none());
+    }
+
+    #[test]
+    fn template_with_invalid_params_is_an_error() {
+        let template = template();
+        let address = "0x22843e74c59580b3eaf6c233fa67d8b7c561a835";
+
+        for invalid in [
+            params(&[]),
+            params(&["not an address"]),
+            params(&[address, "extra"]),
+        ] {
+            let res = DataSource::from_template_for_testing(&template, &invalid, None, 17);
+            assert!(res.is_err(), "params {:?} should be rejected", invalid);
+        }
+
+        // The template refers to an ABI that the mapping does not have
+        let mut template = template;
+        template.source.abi = "Missing".to_string();
+        let res = DataSource::from_template_for_testing(&template, &params(&[address]), None, 17);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn declared_calls_must_refer_to_event_params() {
+        const ABI: &str = r#"[
+            {
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "", "type": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "indexed": false }
+                ]
+            },
+            {
+                "type": "function",
+                "name": "balanceOf",
+                "stateMutability": "view",
+                "inputs": [{ "name": "owner", "type": "address" }],
+                "outputs": [{ "name": "", "type": "uint256" }]
+            }
+        ]"#;
+        const EVENT: &str = "Transfer(indexed address,indexed address,uint256)";
+
+        let mut template = template();
+        template.source.abi = "ERC20".to_string();
+        template.mapping.abis = vec![Arc::new(MappingABI {
+            name: "ERC20".to_string(),
+            contract: serde_json::from_str(ABI).unwrap(),
+        })];
+        let address = "0x22843e74c59580b3eaf6c233fa67d8b7c561a835";
+        let ds = DataSource::from_template_for_testing(&template, &params(&[address]), None, 17)
+            .unwrap();
+
+        let validate = |call: &str| {
+            let calls: UnresolvedCallDecls =
+                serde_json::from_value(serde_json::json!({ "balance": call })).unwrap();
+            let calls = calls
+                .resolve(
+                    &AbiJson::new(ABI.as_bytes()).unwrap(),
+                    Some(EVENT),
+                    &SPEC_VERSION_1_6_0,
+                )
+                .unwrap();
+            let mut ds = ds.clone();
+            ds.mapping.event_handlers = vec![MappingEventHandler {
+                event: EVENT.to_string(),
+                topic0: None,
+                topic1: None,
+                topic2: None,
+                topic3: None,
+                handler: "handleTransfer".to_string(),
+                receipt: false,
+                calls,
+            }];
+            <DataSource as blockchain::DataSource<Chain>>::validate(&ds, &SPEC_VERSION_1_6_0)
+        };
+
+        assert!(validate("ERC20[event.address].balanceOf(event.params.from)").is_empty());
+        assert!(validate("ERC20[event.address].balanceOf(event.params.1)").is_empty());
+
+        let errors = validate("ERC20[event.address].balanceOf(event.params.to)");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("no param named `to`"));
+
+        let errors = validate("ERC20[event.params.3].balanceOf(event.params.from)");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("no param at position 3"));
+    }
+}
@@ -2,6 +2,8 @@ use envconfig::Envconfig;
 use graph::env::EnvVarBoolean;
 use graph::prelude::{BlockNumber, envconfig, lazy_static};
 use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
 lazy_static! {
@@ -41,6 +43,14 @@ pub struct EnvVars {
     /// Set by the environment variable `GRAPH_ETHEREUM_MAX_BLOCK_RANGE_SIZE`.
     /// The default value is 2000 blocks.
     pub max_block_range_size: BlockNumber,
+    /// How many blocks the polling block stream may skip at once on ranges
+    /// where a subgraph has no triggers. Only applies to subgraphs whose
+    /// triggers can all be found with `eth_getLogs`, i.e., that have no
+    /// block or call handlers. A value of 0 disables skipping.
+    ///
+    /// Set by the environment variable `GRAPH_ETHEREUM_FAST_FORWARD_RANGE`.
+    /// The default value is 0.
+    pub fast_forward_range: BlockNumber,
     /// This should not be too large that it causes requests to timeout without
     /// us catching it, nor too small that it causes us to timeout requests that
     /// would've succeeded. We've seen successful `eth_getLogs` requests take
@@ -53,6 +63,12 @@ pub struct EnvVars {
     /// Set by the environment variable `GRAPH_ETHEREUM_BLOCK_RECEIPTS_CHECK_TIMEOUT`
     /// (expressed in seconds). The default value is 10s.
     pub block_receipts_check_timeout: Duration,
+    /// How long the block ingestor waits for the chain head or a block
+    /// pointer before it gives up on the request and tries again.
+    ///
+    /// Set by the environment variable `GRAPH_ETHEREUM_BLOCK_POINTER_TIMEOUT`
+    /// (expressed in seconds). The default value is 30s.
+    pub block_pointer_timeout: Duration,
     /// This is used for requests that will not fail the subgraph if the limit
     /// is reached, but will simply restart the syncing step, so it can be low.
     /// This limit guards against scenarios such as requesting a block hash that
@@ -63,12 +79,115 @@ pub struct EnvVars {
     pub request_retries: usize,
     /// Set by the environment variable
     /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_MAX_CONCURRENT_JSON_RPC_CALLS_FOR_TXN_RECEIPTS`.
-    /// The default value is 1000.
+    /// The default value is 50.
     pub block_ingestor_max_concurrent_json_rpc_calls: usize,
+    /// Number of blocks directly below the latest block that the polling
+    /// block ingestor loads in parallel into the block cache when it has
+    /// fallen behind, before walking back through missing parents one at
+    /// a time. A value of 0 disables prefetching.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_PREFETCH_WINDOW`. The default value is 0.
+    pub block_ingestor_prefetch_window: usize,
+    /// How many blocks the chain head has to be behind the latest block
+    /// before the polling block ingestor prefetches blocks.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_PREFETCH_THRESHOLD`. The default value
+    /// is 15.
+    pub block_ingestor_prefetch_threshold: BlockNumber,
+    /// Directory in which the polling block ingestor saves its progress
+    /// after each poll, so that it can resume filling in missing blocks
+    /// after a restart. Checkpoints are not written if this is not set.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_CHECKPOINT_DIR`. Not set by default.
+    pub block_ingestor_checkpoint_dir: Option<PathBuf>,
+    /// Check that every block the block ingestor loads while filling in
+    /// missing blocks is the parent that its child block points to, and
+    /// fail the poll if it is not.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_VERIFY_LINKAGE`. The default value is
+    /// `false`.
+    pub block_ingestor_verify_linkage: bool,
+    /// The fraction by which the block ingestor randomly shortens or
+    /// lengthens each sleep between polls, so that ingestors for different
+    /// chains don't all send requests at the same moment. A value of 0
+    /// turns jitter off.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_POLL_JITTER`. The default value is 0.1.
+    pub block_ingestor_poll_jitter: f64,
+    /// What the block ingestor does with a block whose timestamp is not
+    /// greater than the timestamp of its parent in the block cache.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_TIMESTAMP_CHECK`. The default value is
+    /// `warn`.
+    pub block_ingestor_timestamp_check: TimestampCheck,
+    /// What the block ingestor does with a block whose timestamp is more
+    /// than `block_ingestor_future_timestamp_tolerance` ahead of the
+    /// current time.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_FUTURE_TIMESTAMP_CHECK`. The default
+    /// value is `warn`.
+    pub block_ingestor_future_timestamp_check: TimestampCheck,
+    /// How far ahead of the current time a block's timestamp may be before
+    /// the block ingestor considers it bogus. Leaves room for clocks that
+    /// are not quite in sync.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_FUTURE_TIMESTAMP_TOLERANCE` (expressed
+    /// in seconds). The default value is 300.
+    pub block_ingestor_future_timestamp_tolerance: Duration,
+    /// Only load transaction receipts for ingested blocks while a subgraph
+    /// on the chain needs them. Blocks that are stored without receipts
+    /// have them loaded later by whoever needs them.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_LAZY_RECEIPTS`. The default value is
+    /// `false`.
+    pub block_ingestor_lazy_receipts: bool,
+    /// How long the chain head may stay the same before the block ingestor
+    /// considers its provider stuck and switches to a provider that is
+    /// ahead. A value of 0 turns the check off. Chains can override this
+    /// in the configuration file.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_STALL_TIMEOUT` (expressed in seconds).
+    /// The default value is 0.
+    pub block_ingestor_stall_timeout: Duration,
+    /// Abort the process when the chain head is stuck and no other provider
+    /// is ahead, in the hope that a restart fixes the connection.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_KILL_IF_STALLED`. The default value is
+    /// `false`.
+    pub block_ingestor_kill_if_stalled: bool,
+    /// The `User-Agent` header to send with JSON-RPC requests over HTTP.
+    ///
+    /// Set by the environment variable `GRAPH_ETHEREUM_RPC_USER_AGENT`. Not
+    /// set by default, which uses the HTTP client's default.
+    pub rpc_user_agent: Option<String>,
+    /// Send a unique ID in the `x-request-id` header with every JSON-RPC
+    /// request over HTTP and log it at debug level.
+    ///
+    /// Set by the flag `GRAPH_ETHEREUM_RPC_REQUEST_IDS`. Off by default.
+    pub rpc_request_ids: bool,
     /// Set by the flag `GRAPH_ETHEREUM_FETCH_TXN_RECEIPTS_IN_BATCHES`. Enabled
     /// by default on macOS (to avoid DNS issues) and disabled by default on all
     /// other systems.
     pub fetch_receipts_in_batches: bool,
+    /// When fetching receipts in batches, the most receipts to request in
+    /// one JSON-RPC batch. The batches for a block are sent one after the
+    /// other, each with its own timeout, so that blocks with many
+    /// transactions do not swamp the provider.
+    ///
+    /// Set by the environment variable
+    /// `GRAPH_ETHEREUM_MAX_TXN_RECEIPTS_PER_BATCH`. The default value is 100.
+    pub max_receipts_per_batch: usize,
     /// `graph_node::config` disallows setting this in a store with multiple
     /// shards. See 8b6ad0c64e244023ac20ced7897fe666 for the reason.
     ///
@@ -95,6 +214,16 @@ pub struct EnvVars {
     /// When enabled, forces the use of RPC instead of Firehose for loading block pointers by numbers.
     /// This is used in composable subgraphs. Firehose can be slow for loading block pointers by numbers.
     pub force_rpc_for_block_ptrs: bool,
+    /// Set by the flag `GRAPH_ETHEREUM_FIREHOSE_RPC_FALLBACK`. Off by default.
+    /// When enabled, a Firehose block stream for a subgraph that starts before
+    /// the first block the Firehose endpoint can stream gets those blocks from
+    /// RPC and switches to Firehose once it reaches the first streamable block.
+    pub firehose_rpc_fallback: bool,
+    /// Set by the flag `GRAPH_ETHEREUM_FIREHOSE_SPOT_CHECK_INTERVAL`. When
+    /// set to `n`, every `n`-th block a Firehose block stream delivers is
+    /// compared with the same block from RPC. The default is 0, which turns
+    /// the spot checks off.
+    pub firehose_spot_check_interval: BlockNumber,
 }
 
 // This does not print any values avoid accidentally leaking any sensitive env vars
@@ -125,17 +254,38 @@ impl From<Inner> for EnvVars {
             block_batch_size: x.block_batch_size,
             block_ptr_batch_size: x.block_ptr_batch_size,
             max_block_range_size: x.max_block_range_size,
+            fast_forward_range: x.fast_forward_range.max(0),
             json_rpc_timeout: Duration::from_secs(x.json_rpc_timeout_in_secs),
             block_receipts_check_timeout: Duration::from_secs(
                 x.block_receipts_check_timeout_in_seccs,
             ),
+            block_pointer_timeout: Duration::from_secs(x.block_pointer_timeout_in_secs),
             request_retries: x.request_retries,
             block_ingestor_max_concurrent_json_rpc_calls: x
-                .block_ingestor_max_concurrent_json_rpc_calls,
+                .block_ingestor_max_concurrent_json_rpc_calls
+                .max(1),
+            block_ingestor_prefetch_window: x.block_ingestor_prefetch_window,
+            block_ingestor_prefetch_threshold: x.block_ingestor_prefetch_threshold.max(1),
+            block_ingestor_checkpoint_dir: x.block_ingestor_checkpoint_dir.map(PathBuf::from),
+            block_ingestor_verify_linkage: x.block_ingestor_verify_linkage.0,
+            block_ingestor_poll_jitter: x.block_ingestor_poll_jitter.clamp(0.0, 1.0),
+            block_ingestor_timestamp_check: x.block_ingestor_timestamp_check,
+            block_ingestor_future_timestamp_check: x.block_ingestor_future_timestamp_check.0,
+            block_ingestor_future_timestamp_tolerance: Duration::from_secs(
+                x.block_ingestor_future_timestamp_tolerance_in_secs,
+            ),
+            block_ingestor_lazy_receipts: x.block_ingestor_lazy_receipts.0,
+            block_ingestor_stall_timeout: Duration::from_secs(
+                x.block_ingestor_stall_timeout_in_secs,
+            ),
+            block_ingestor_kill_if_stalled: x.block_ingestor_kill_if_stalled.0,
+            rpc_user_agent: x.rpc_user_agent,
+            rpc_request_ids: x.rpc_request_ids.0,
             fetch_receipts_in_batches: x
                 .fetch_receipts_in_batches
                 .map(|b| b.0)
                 .unwrap_or(cfg!(target_os = "macos")),
+            max_receipts_per_batch: x.max_receipts_per_batch.max(1),
             cleanup_blocks: x.cleanup_blocks.0,
             target_triggers_per_block_range: x.target_triggers_per_block_range,
             genesis_block_number: x.genesis_block_number,
@@ -146,6 +296,8 @@ impl From<Inner> for EnvVars {
                 .map(str::to_string)
                 .collect(),
             force_rpc_for_block_ptrs: x.force_rpc_for_block_ptrs.0,
+            firehose_rpc_fallback: x.firehose_rpc_fallback.0,
+            firehose_spot_check_interval: x.firehose_spot_check_interval.max(0),
         }
     }
 }
@@ -173,19 +325,72 @@ struct Inner {
     block_ptr_batch_size: usize,
     #[envconfig(from = "GRAPH_ETHEREUM_MAX_BLOCK_RANGE_SIZE", default = "2000")]
     max_block_range_size: BlockNumber,
+    #[envconfig(from = "GRAPH_ETHEREUM_FAST_FORWARD_RANGE", default = "0")]
+    fast_forward_range: BlockNumber,
     #[envconfig(from = "GRAPH_ETHEREUM_JSON_RPC_TIMEOUT", default = "180")]
     json_rpc_timeout_in_secs: u64,
     #[envconfig(from = "GRAPH_ETHEREUM_BLOCK_RECEIPTS_CHECK_TIMEOUT", default = "10")]
     block_receipts_check_timeout_in_seccs: u64,
+    #[envconfig(from = "GRAPH_ETHEREUM_BLOCK_POINTER_TIMEOUT", default = "30")]
+    block_pointer_timeout_in_secs: u64,
     #[envconfig(from = "GRAPH_ETHEREUM_REQUEST_RETRIES", default = "10")]
     request_retries: usize,
     #[envconfig(
         from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_MAX_CONCURRENT_JSON_RPC_CALLS_FOR_TXN_RECEIPTS",
-        default = "1000"
+        default = "50"
     )]
     block_ingestor_max_concurrent_json_rpc_calls: usize,
+    #[envconfig(from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_PREFETCH_WINDOW", default = "0")]
+    block_ingestor_prefetch_window: usize,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_PREFETCH_THRESHOLD",
+        default = "15"
+    )]
+    block_ingestor_prefetch_threshold: BlockNumber,
+    #[envconfig(from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_CHECKPOINT_DIR")]
+    block_ingestor_checkpoint_dir: Option<String>,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_VERIFY_LINKAGE",
+        default = "false"
+    )]
+    block_ingestor_verify_linkage: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_POLL_JITTER", default = "0.1")]
+    block_ingestor_poll_jitter: f64,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_TIMESTAMP_CHECK",
+        default = "warn"
+    )]
+    block_ingestor_timestamp_check: TimestampCheck,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_FUTURE_TIMESTAMP_CHECK",
+        default = "warn"
+    )]
+    block_ingestor_future_timestamp_check: FutureTimestampCheck,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_FUTURE_TIMESTAMP_TOLERANCE",
+        default = "300"
+    )]
+    block_ingestor_future_timestamp_tolerance_in_secs: u64,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_LAZY_RECEIPTS",
+        default = "false"
+    )]
+    block_ingestor_lazy_receipts: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_STALL_TIMEOUT", default = "0")]
+    block_ingestor_stall_timeout_in_secs: u64,
+    #[envconfig(
+        from = "GRAPH_ETHEREUM_BLOCK_INGESTOR_KILL_IF_STALLED",
+        default = "false"
+    )]
+    block_ingestor_kill_if_stalled: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_ETHEREUM_RPC_USER_AGENT")]
+    rpc_user_agent: Option<String>,
+    #[envconfig(from = "GRAPH_ETHEREUM_RPC_REQUEST_IDS", default = "false")]
+    rpc_request_ids: EnvVarBoolean,
     #[envconfig(from = "GRAPH_ETHEREUM_FETCH_TXN_RECEIPTS_IN_BATCHES")]
     fetch_receipts_in_batches: Option<EnvVarBoolean>,
+    #[envconfig(from = "GRAPH_ETHEREUM_MAX_TXN_RECEIPTS_PER_BATCH", default = "100")]
+    max_receipts_per_batch: usize,
     #[envconfig(from = "GRAPH_ETHEREUM_CLEANUP_BLOCKS", default = "false")]
     cleanup_blocks: EnvVarBoolean,
     #[envconfig(
@@ -199,4 +404,56 @@ struct Inner {
     eth_call_no_gas: String,
     #[envconfig(from = "GRAPH_ETHEREUM_FORCE_RPC_FOR_BLOCK_PTRS", default = "true")]
     force_rpc_for_block_ptrs: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_ETHEREUM_FIREHOSE_RPC_FALLBACK", default = "false")]
+    firehose_rpc_fallback: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_ETHEREUM_FIREHOSE_SPOT_CHECK_INTERVAL", default = "0")]
+    firehose_spot_check_interval: BlockNumber,
+}
+
+/// How the block ingestor treats a block whose timestamp is implausible
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampCheck {
+    /// Don't compare timestamps
+    Off,
+    /// Log a warning, but store the block anyway
+    Warn,
+    /// Refuse to store the block
+    Reject,
+}
+
+impl TimestampCheck {
+    /// Parse the value `s` of the environment variable `var`
+    fn parse(var: &str, s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(TimestampCheck::Off),
+            "warn" => Ok(TimestampCheck::Warn),
+            "reject" => Ok(TimestampCheck::Reject),
+            _ => Err(format!(
+                "invalid {} '{}', expected 'off', 'warn', or 'reject'",
+                var, s
+            )),
+        }
+    }
+}
+
+impl FromStr for TimestampCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimestampCheck::parse("GRAPH_ETHEREUM_BLOCK_INGESTOR_TIMESTAMP_CHECK", s)
+    }
+}
+
+/// The `TimestampCheck` for blocks with timestamps in the future, which
+/// is set by its own environment variable
+#[derive(Clone, Copy, Debug)]
+struct FutureTimestampCheck(TimestampCheck);
+
+impl FromStr for FutureTimestampCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimestampCheck::parse("GRAPH_ETHEREUM_BLOCK_INGESTOR_FUTURE_TIMESTAMP_CHECK", s)
+            .map(FutureTimestampCheck)
+    }
 }
@@ -68,7 +68,7 @@ use crate::TriggerFilter;
 use crate::adapter::EthGetLogsFilter;
 use crate::adapter::EthereumRpcError;
 use crate::adapter::ProviderStatus;
-use crate::call_helper::interpret_eth_call_error;
+use crate::call_helper::{interpret_eth_call_error, is_out_of_gas};
 use crate::chain::BlockFinality;
 use crate::chain::ChainSettings;
 use crate::trigger::{LogPosition, LogRef};
@@ -204,6 +204,28 @@ impl EthereumAdapter {
         }
     }
 
+    /// An adapter whose requests are answered by `asserter`
+    #[cfg(test)]
+    pub(crate) fn mocked(asserter: alloy::providers::mock::Asserter) -> Self {
+        let alloy = alloy::providers::ProviderBuilder::<_, _, AnyNetworkBare>::default()
+            .network::<AnyNetworkBare>()
+            .connect_mocked_client(asserter);
+        let registry = Arc::new(graph::prelude::MetricsRegistry::mock());
+        EthereumAdapter {
+            logger: Logger::root(graph::slog::Discard, o!()),
+            provider: "mock".to_string(),
+            alloy: Arc::new(alloy),
+            metrics: Arc::new(ProviderEthRpcMetrics::new(registry)),
+            supports_eip_1898: true,
+            call_only: false,
+            supports_block_receipts: Arc::new(RwLock::new(None)),
+            settings: Arc::new(ChainSettings {
+                request_retries: 1,
+                ..ChainSettings::from_env_defaults()
+            }),
+        }
+    }
+
     async fn traces(
         self,
         logger: ProviderLogger,
@@ -661,12 +683,80 @@ impl EthereumAdapter {
             .map_err(|e| e.into_inner().unwrap_or(EthereumRpcError::Timeout))
     }
 
+    async fn fee_history(
+        &self,
+        logger: ProviderLogger,
+        block_count: u64,
+        newest_block: BlockNumber,
+        reward_percentiles: Vec<f64>,
+    ) -> Result<alloy::rpc::types::FeeHistory, EthereumRpcError> {
+        let alloy = self.alloy.clone();
+
+        let retry_log_message = format!(
+            "eth_feeHistory RPC call for {} blocks up to block {}",
+            block_count, newest_block
+        );
+
+        retry(retry_log_message, &logger)
+            .redact_log_urls(true)
+            .when(|result| result.is_err())
+            .limit(self.settings.request_retries)
+            .timeout_secs(self.settings.json_rpc_timeout.as_secs())
+            .run(move || {
+                let alloy = alloy.cheap_clone();
+                let reward_percentiles = reward_percentiles.clone();
+                async move {
+                    let result = alloy
+                        .get_fee_history(
+                            block_count,
+                            alloy::rpc::types::BlockNumberOrTag::Number(newest_block as u64),
+                            &reward_percentiles,
+                        )
+                        .await;
+                    match result {
+                        Ok(fee_history) => Ok(fee_history),
+                        Err(err) => Err(EthereumRpcError::AlloyError(err)),
+                    }
+                }
+            })
+            .await
+            .map_err(|e| e.into_inner().unwrap_or(EthereumRpcError::Timeout))
+    }
+
+    async fn logs_for_filter(
+        &self,
+        logger: &ProviderLogger,
+        retry_log_message: String,
+        filter: alloy::rpc::types::Filter,
+    ) -> Result<Vec<alloy::rpc::types::Log>, EthereumRpcError> {
+        let alloy = self.alloy.clone();
+
+        retry(retry_log_message, logger)
+            .redact_log_urls(true)
+            .when(|result| result.is_err())
+            .limit(self.settings.request_retries)
+            .timeout_secs(self.settings.json_rpc_timeout.as_secs())
+            .run(move || {
+                let alloy = alloy.cheap_clone();
+                let filter = filter.clone();
+                async move {
+                    alloy
+                        .get_logs(&filter)
+                        .await
+                        .map_err(EthereumRpcError::AlloyError)
+                }
+            })
+            .await
+            .map_err(|e| e.into_inner().unwrap_or(EthereumRpcError::Timeout))
+    }
+
     async fn call(
         &self,
         logger: ProviderLogger,
         call_data: call::Request,
         block_ptr: BlockPtr,
         gas: Option<u32>,
+        gas_limited: bool,
     ) -> Result<call::Retval, ContractCallError> {
         let alloy = self.alloy.clone();
 
@@ -674,6 +764,9 @@ impl EthereumAdapter {
         let retry_log_message = format!("eth_call RPC call for block {}", block_ptr);
         retry(retry_log_message, &logger)
             .redact_log_urls(true)
+            // Deterministic errors like running out of gas will happen again
+            // when we retry
+            .when(|result| result.as_ref().is_err_and(|e| !e.is_deterministic()))
             .limit(self.settings.request_retries)
             .timeout_secs(self.settings.json_rpc_timeout.as_secs())
             .run(move || {
@@ -681,20 +774,15 @@ impl EthereumAdapter {
                 let alloy = alloy.cheap_clone();
                 let logger = logger.cheap_clone();
                 async move {
-                    let mut req = TransactionRequest::default()
-                        .input(TransactionInput::both(alloy::primitives::Bytes::from(
-                            call_data.encoded_call.to_vec(),
-                        )))
-                        .to(call_data.address);
-
-                    if let Some(gas) = gas {
-                        req = req.gas_limit(gas as u64);
-                    }
+                    let req = eth_call_request(&call_data, gas);
 
                     let result = alloy.call(req.into()).block(alloy_block_id).await;
 
                     match result {
                         Ok(bytes) => Ok(call::Retval::Value(scalar::Bytes::from(bytes))),
+                        Err(err) if gas_limited && is_out_of_gas(&err) => {
+                            Err(ContractCallError::OutOfGas)
+                        }
                         Err(err) => interpret_eth_call_error(&logger, err),
                     }
                 }
@@ -715,7 +803,8 @@ impl EthereumAdapter {
                 logger.clone(),
                 req.cheap_clone(),
                 call.block_ptr.clone(),
-                call.gas,
+                call.effective_gas(),
+                call.gas_limit.is_some(),
             )
             .await?;
         if let Err(e) = cache
@@ -928,9 +1017,12 @@ impl EthereumAdapter {
         let eth: Self = self.cheap_clone();
         let logger = self.provider_logger(logger);
 
+        // Only ask for the logs of contracts that are watched somewhere in
+        // the range
         let max_contracts = eth.settings.get_logs_max_contracts;
         futures03::stream::iter(
             log_filter
+                .active_in(from, to)
                 .eth_get_logs_filters(max_contracts)
                 .map(move |filter| {
                     eth.cheap_clone().log_stream(
@@ -1145,6 +1237,21 @@ impl EthereumAdapter {
     }
 }
 
+/// The parameters for an `eth_call` of `call_data`, with `gas` as the gas
+/// limit if it is set
+fn eth_call_request(call_data: &call::Request, gas: Option<u32>) -> TransactionRequest {
+    let req = TransactionRequest::default()
+        .input(TransactionInput::both(alloy::primitives::Bytes::from(
+            call_data.encoded_call.to_vec(),
+        )))
+        .to(call_data.address);
+
+    match gas {
+        Some(gas) => req.gas_limit(gas as u64),
+        None => req,
+    }
+}
+
 // Detects null blocks as can occur on Filecoin EVM chains, by checking for the FEVM-specific
 // error returned when requesting such a null round. Ideally there should be a defined reponse or
 // message for this case, or a check that is less dependent on the Filecoin implementation.
@@ -1425,6 +1532,79 @@ impl EthereumAdapterTrait for EthereumAdapter {
         self.code(logger, address, block_ptr).await
     }
 
+    async fn get_fee_history(
+        &self,
+        logger: &Logger,
+        block_count: u64,
+        newest_block: BlockNumber,
+        reward_percentiles: &[f64],
+    ) -> Result<alloy::rpc::types::FeeHistory, EthereumRpcError> {
+        let logger = self.provider_logger(logger);
+        debug!(
+            logger, "eth_feeHistory";
+            "block_count" => block_count,
+            "newest_block" => newest_block,
+            "reward_percentiles" => format!("{:?}", reward_percentiles)
+        );
+        self.fee_history(
+            logger,
+            block_count,
+            newest_block,
+            reward_percentiles.to_vec(),
+        )
+        .await
+    }
+
+    async fn get_logs(
+        &self,
+        logger: &Logger,
+        address: Address,
+        topics: &[Vec<B256>],
+        from: BlockNumber,
+        to: &BlockPtr,
+    ) -> Result<Vec<alloy::rpc::types::Log>, EthereumRpcError> {
+        let logger = self.provider_logger(logger);
+        debug!(
+            logger, "eth_getLogs";
+            "address" => format!("{:x}", address),
+            "topics" => format!("{:?}", topics),
+            "from" => from,
+            "to" => to.number
+        );
+
+        let topic = |i: usize| topics.get(i).cloned().unwrap_or_default();
+        let filter = alloy::rpc::types::Filter::new()
+            .address(address)
+            .event_signature(topic(0))
+            .topic1(topic(1))
+            .topic2(topic(2))
+            .topic3(topic(3));
+
+        // Ask for the logs of the last block by its hash so that a provider
+        // that does not have that block fails the request rather than
+        // leaving its logs out
+        let mut logs = if from < to.number {
+            let filter = filter
+                .clone()
+                .from_block(alloy::rpc::types::BlockNumberOrTag::Number(from as u64))
+                .to_block(alloy::rpc::types::BlockNumberOrTag::Number(
+                    (to.number - 1) as u64,
+                ));
+            let message = format!(
+                "eth_getLogs RPC call for block range: [{}..{}]",
+                from,
+                to.number - 1
+            );
+            self.logs_for_filter(&logger, message, filter).await?
+        } else {
+            vec![]
+        };
+        let message = format!("eth_getLogs RPC call for block {}", to);
+        let filter = filter.at_block_hash(to.hash.as_b256());
+        logs.extend(self.logs_for_filter(&logger, message, filter).await?);
+        Ok(logs)
+    }
+
     async fn next_existing_ptr_to_number(
         &self,
         logger: &Logger,
@@ -2227,7 +2407,10 @@ async fn filter_call_triggers_from_unsuccessful_transactions(
     Ok(block)
 }
 
-/// Deprecated. Wraps the [`fetch_transaction_receipts_in_batch`] in a retry loop.
+/// Deprecated. Splits the receipts into batches of at most
+/// `GRAPH_ETHEREUM_MAX_TXN_RECEIPTS_PER_BATCH` and wraps
+/// [`fetch_transaction_receipts_in_batch`] for each of them in a retry
+/// loop. Every batch gets its own timeout.
 async fn fetch_transaction_receipts_in_batch_with_retry(
     alloy: Arc<AlloyProvider>,
     hashes: Vec<B256>,
@@ -2235,23 +2418,30 @@ async fn fetch_transaction_receipts_in_batch_with_retry(
     logger: ProviderLogger,
     settings: &ChainSettings,
 ) -> Result<Vec<Arc<AnyTransactionReceiptBare>>, IngestorError> {
-    let retry_log_message = format!(
-        "batch eth_getTransactionReceipt RPC call for block {:?}",
-        block_hash
-    );
-    retry(retry_log_message, &logger)
-        .redact_log_urls(true)
-        .limit(settings.request_retries)
-        .no_logging()
-        .timeout_secs(settings.json_rpc_timeout.as_secs())
-        .run(move || {
-            let alloy = alloy.cheap_clone();
-            let hashes = hashes.clone();
-            let logger = logger.cheap_clone();
-            fetch_transaction_receipts_in_batch(alloy, hashes, block_hash, logger).boxed()
-        })
-        .await
-        .map_err(|_timeout| anyhow!(block_hash).into())
+    let request_retries = settings.request_retries;
+    let json_rpc_timeout = settings.json_rpc_timeout;
+
+    in_batches(hashes, ENV_VARS.max_receipts_per_batch, |hashes| {
+        let alloy = alloy.cheap_clone();
+        let logger = logger.cheap_clone();
+        let retry_log_message = format!(
+            "batch eth_getTransactionReceipt RPC call for block {:?}",
+            block_hash
+        );
+        retry(retry_log_message, &logger)
+            .redact_log_urls(true)
+            .limit(request_retries)
+            .no_logging()
+            .timeout_secs(json_rpc_timeout.as_secs())
+            .run(move || {
+                let alloy = alloy.cheap_clone();
+                let hashes = hashes.clone();
+                let logger = logger.cheap_clone();
+                fetch_transaction_receipts_in_batch(alloy, hashes, block_hash, logger).boxed()
+            })
+            .map_err(move |_timeout| -> IngestorError { anyhow!(block_hash).into() })
+    })
+    .await
 }
 
 /// Deprecated. Attempts to fetch multiple transaction receipts in a batching context.
@@ -2261,7 +2451,6 @@ async fn fetch_transaction_receipts_in_batch(
     block_hash: B256,
     logger: ProviderLogger,
 ) -> Result<Vec<Arc<AnyTransactionReceiptBare>>, IngestorError> {
-    // Use the batch method to get all receipts at once
     let receipts = batch_get_transaction_receipts(alloy, hashes.clone())
         .await
         .map_err(|e| {
@@ -2286,6 +2475,43 @@ async fn fetch_transaction_receipts_in_batch(
     Ok(result)
 }
 
+/// Call `fetch` for consecutive chunks of at most `batch_size` of `hashes`,
+/// waiting for each call to finish before making the next one, and
+/// concatenate the results
+async fn in_batches<T, E, F, Fut>(
+    hashes: Vec<B256>,
+    batch_size: usize,
+    mut fetch: F,
+) -> Result<Vec<T>, E>
+where
+    F: FnMut(Vec<B256>) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<T>, E>>,
+{
+    let mut results = Vec::with_capacity(hashes.len());
+    for chunk in hashes.chunks(batch_size.max(1)) {
+        results.extend(fetch(chunk.to_vec()).await?);
+    }
+    Ok(results)
+}
+
+/// Call `fetch` for each of `hashes` with at most `limit` calls in flight
+/// at any time, and collect the results in the order of `hashes`
+async fn fetch_concurrently<T, E, F, Fut>(
+    hashes: Vec<B256>,
+    limit: usize,
+    fetch: F,
+) -> Result<Vec<T>, E>
+where
+    F: FnMut(B256) -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+{
+    futures03::stream::iter(hashes)
+        .map(fetch)
+        .buffered(limit.max(1))
+        .try_collect()
+        .await
+}
+
 async fn batch_get_transaction_receipts(
     provider: Arc<AlloyProvider>,
     tx_hashes: Vec<B256>,
@@ -2377,24 +2603,18 @@ async fn fetch_individual_receipts_with_retry(
     let json_rpc_timeout = settings.json_rpc_timeout;
     let concurrent_requests = settings.block_ingestor_max_concurrent_json_rpc_calls;
 
-    // Use a stream to fetch receipts individually
-    let hash_stream = tokio_stream::iter(hashes);
-    let receipt_stream = hash_stream
-        .map(move |tx_hash| {
-            fetch_transaction_receipt_with_retry(
-                alloy.cheap_clone(),
-                tx_hash,
-                block_hash,
-                logger.cheap_clone(),
-                request_retries,
-                json_rpc_timeout,
-            )
-        })
-        .buffered(concurrent_requests);
-
-    tokio_stream::StreamExt::collect::<Result<Vec<Arc<AnyTransactionReceiptBare>>, IngestorError>>(
-        receipt_stream,
-    )
+    // Fetch receipts individually, but only a limited number at a time so
+    // that a block with many transactions does not overwhelm the provider
+    fetch_concurrently(hashes, concurrent_requests, move |tx_hash| {
+        fetch_transaction_receipt_with_retry(
+            alloy.cheap_clone(),
+            tx_hash,
+            block_hash,
+            logger.cheap_clone(),
+            request_retries,
+            json_rpc_timeout,
+        )
+    })
     .await
 }
 
@@ -2694,10 +2914,12 @@ mod tests {
 
     use super::{
         EthereumBlock, EthereumBlockFilter, EthereumBlockWithCalls,
-        block_trigger_types_from_intervals, check_block_receipt_support, parse_block_triggers,
+        block_trigger_types_from_intervals, check_block_receipt_support, eth_call_request,
+        fetch_concurrently, in_batches, parse_block_triggers,
     };
     use graph::blockchain::BlockPtr;
     use graph::components::ethereum::AnyNetworkBare;
+    use graph::data::store::ethereum::call;
     use graph::prelude::alloy::primitives::{Address, B256, Bytes};
     use graph::prelude::alloy::providers::ProviderBuilder;
     use graph::prelude::alloy::providers::mock::Asserter;
@@ -2850,6 +3072,87 @@ mod tests {
             .unwrap();
     }
 
+    #[graph::test]
+    async fn receipt_batches_stay_within_the_batch_size() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // A block with a lot of transactions
+        let hashes: Vec<_> = (0..2_345u64)
+            .map(|i| B256::left_padding_from(&i.to_be_bytes()))
+            .collect();
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let receipts = in_batches(hashes.clone(), 100, |batch| {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let now = in_flight.fetch_add(batch.len(), Ordering::SeqCst) + batch.len();
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(batch.len(), Ordering::SeqCst);
+                Ok::<_, anyhow::Error>(batch)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(hashes, receipts);
+        assert_eq!(100, max_in_flight.load(Ordering::SeqCst));
+
+        // An error in any batch fails the whole fetch
+        let res = in_batches(hashes, 100, |batch| async move {
+            if batch[0] == B256::left_padding_from(&1_000u64.to_be_bytes()) {
+                Err(anyhow::anyhow!("rate limited"))
+            } else {
+                Ok(batch)
+            }
+        })
+        .await;
+        assert!(res.is_err());
+    }
+
+    #[graph::test]
+    async fn receipt_requests_stay_within_the_concurrency_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // A block with a lot of transactions
+        let hashes: Vec<_> = (0..2_345u64)
+            .map(|i| B256::left_padding_from(&i.to_be_bytes()))
+            .collect();
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let receipts = fetch_concurrently(hashes.clone(), 10, |hash| {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok::<_, anyhow::Error>(hash)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(hashes, receipts);
+        assert_eq!(10, max_in_flight.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn eth_call_request_sends_the_gas_limit() {
+        let call_data = call::Request::new(address(1), vec![1, 2, 3, 4], 0);
+
+        let req = eth_call_request(&call_data, Some(100_000));
+        assert_eq!(Some(100_000), req.gas);
+        assert_eq!(Some(address(1)), req.to.and_then(|to| to.to().copied()));
+
+        let req = eth_call_request(&call_data, None);
+        assert_eq!(None, req.gas);
+    }
+
     #[test]
     fn parse_block_triggers_specific_call_not_found() {
         let block = create_minimal_block_for_test(2, hash(2));
@@ -1,30 +1,391 @@
+use crate::env::TimestampCheck;
 use crate::{ENV_VARS, chain::BlockFinality};
 use crate::{EthereumAdapter, EthereumAdapterTrait as _};
 use async_trait::async_trait;
+use futures::StreamExt;
 use futures::future::select_ok;
 use graph::blockchain::BlockchainKind;
 use graph::blockchain::client::ChainClient;
+use graph::components::ethereum::{AnyBlock, CachedBlock};
 use graph::components::network_provider::ChainName;
 use graph::prelude::alloy::primitives::B256;
 use graph::slog::o;
 use graph::util::backoff::ExponentialBackoff;
 use graph::{
-    blockchain::{BlockHash, BlockIngestor, BlockPtr, IngestorError},
+    blockchain::{
+        Block, BlockHash, BlockIngestor, BlockPtr, BlockTime, IngestorError, IngestorPause,
+        with_ingestor_timeout,
+    },
     cheap_clone::CheapClone,
     prelude::{
-        ChainStore, Error, EthereumBlockWithCalls, LogCode, Logger, debug, error, info, tokio,
-        trace, warn,
+        BlockNumber, ChainStore, Error, EthereumBlockWithCalls, Gauge, LightEthereumBlock,
+        LightEthereumBlockExt as _, LogCode, Logger, MetricsRegistry, anyhow, crit, debug, error,
+        info, rand, serde_json, tokio, trace, warn,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
     },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use std::{sync::Arc, time::Duration};
+
+/// A handle to the polling interval of a `PollingBlockIngestor` that can
+/// be changed while the ingestor is running. A change takes effect the
+/// next time the poll loop goes to sleep; reading and writing the interval
+/// is lock-free.
+#[derive(Clone, Debug)]
+pub struct PollingInterval(Arc<AtomicU64>);
+
+impl PollingInterval {
+    pub fn new(interval: Duration) -> Self {
+        Self(Arc::new(AtomicU64::new(Self::millis(interval))))
+    }
+
+    pub fn get(&self) -> Duration {
+        Duration::from_millis(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, interval: Duration) {
+        self.0.store(Self::millis(interval), Ordering::Relaxed);
+    }
+
+    fn millis(interval: Duration) -> u64 {
+        u64::try_from(interval.as_millis()).unwrap_or(u64::MAX)
+    }
+}
+
+/// Counts the subgraphs on a chain that need transaction receipts. While
+/// there are none, a `PollingBlockIngestor` that was given this demand
+/// stores blocks without receipts.
+#[derive(Clone, Debug, Default)]
+pub struct ReceiptDemand(Arc<AtomicUsize>);
+
+impl ReceiptDemand {
+    /// Count a subgraph that needs receipts until the returned guard is
+    /// dropped
+    pub fn register(&self) -> ReceiptDemandGuard {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        ReceiptDemandGuard(self.clone())
+    }
+
+    pub fn is_needed(&self) -> bool {
+        self.0.load(Ordering::Relaxed) > 0
+    }
+}
+
+#[derive(Debug)]
+pub struct ReceiptDemandGuard(ReceiptDemand);
+
+impl Drop for ReceiptDemandGuard {
+    fn drop(&mut self) {
+        (self.0).0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A hook that can change a block before a `PollingBlockIngestor` stores
+/// it, for example to fill in data from another source. Blocks with
+/// receipts are passed as `BlockFinality::NonFinal`, blocks without them
+/// as `BlockFinality::Final`. The transform must not change the hash or
+/// number of the block; returning an error skips the block.
+pub type BlockTransform = Arc<dyn Fn(BlockFinality) -> Result<BlockFinality, Error> + Send + Sync>;
+
+/// A block that is stored without its transaction receipts. The block
+/// cache returns it as a light block, and whoever needs the receipts loads
+/// them from a provider.
+struct BlockWithoutReceipts(Arc<LightEthereumBlock>);
+
+impl Block for BlockWithoutReceipts {
+    fn ptr(&self) -> BlockPtr {
+        self.0.block_ptr()
+    }
+
+    fn parent_ptr(&self) -> Option<BlockPtr> {
+        self.0.parent_ptr()
+    }
+
+    fn data(&self) -> Result<serde_json::Value, serde_json::Error> {
+        let block = serde_json::to_value(self.0.as_ref())?;
+        Ok(serde_json::json!({ "block": block }))
+    }
+
+    fn timestamp(&self) -> BlockTime {
+        block_time(&self.0)
+    }
+}
+
+fn block_time(block: &LightEthereumBlock) -> BlockTime {
+    i64::try_from(block.timestamp_u64())
+        .map(|ts| BlockTime::since_epoch(ts, 0))
+        .unwrap_or(BlockTime::MAX)
+}
+
+/// Load the receipts of `block` if `with_receipts` is set
+async fn complete_block<A: crate::EthereumAdapterTrait>(
+    logger: &Logger,
+    eth_adapter: &Arc<A>,
+    block: AnyBlock,
+    with_receipts: bool,
+) -> Result<CachedBlock, IngestorError> {
+    if with_receipts {
+        let block = eth_adapter.load_full_block(logger, block).await?;
+        Ok(CachedBlock::Full(block))
+    } else {
+        Ok(CachedBlock::Light(Arc::new(LightEthereumBlock::new(block))))
+    }
+}
+
+/// Store `block` in the block cache, after passing it through `transform`
+/// if there is one. A block without receipts never replaces a block that
+/// is already cached since that might have receipts. A block that is
+/// already cached must not be overwritten with different contents; that
+/// would mean that the provider changed its mind about what is in the block
+async fn store_block(
+    logger: &Logger,
+    chain_store: &Arc<dyn ChainStore>,
+    block: CachedBlock,
+    transform: Option<&BlockTransform>,
+) -> Result<(), IngestorError> {
+    // We need something that implements `Block` to store the block;
+    // the store does not care whether the block is final or not
+    let block = match block {
+        CachedBlock::Full(ethereum_block) => BlockFinality::NonFinal(EthereumBlockWithCalls {
+            ethereum_block,
+            calls: None,
+        }),
+        CachedBlock::Light(block) => BlockFinality::Final(block),
+    };
+
+    let block = match transform {
+        Some(transform) => {
+            let ptr = block.ptr();
+            match transform(block) {
+                Ok(block) if block.ptr() == ptr => block,
+                Ok(block) => {
+                    return Err(anyhow!(
+                        "block transform changed block {} into {}",
+                        ptr,
+                        block.ptr()
+                    )
+                    .into());
+                }
+                Err(e) => {
+                    warn!(logger, "Block transform rejected block, not storing it";
+                        "block" => ptr.to_string(), "error" => format!("{:#}", e));
+                    return Ok(());
+                }
+            }
+        }
+        None => block,
+    };
+
+    let hash = block.ptr().hash.as_b256();
+    let stored = match block {
+        BlockFinality::Final(block) => {
+            chain_store
+                .upsert_block_unless_conflicting(Arc::new(BlockWithoutReceipts(block)), false)
+                .await?
+        }
+        block => {
+            chain_store
+                .upsert_block_unless_conflicting(Arc::new(block), true)
+                .await?
+        }
+    };
+    if !stored {
+        return Err(IngestorError::BlockDataMismatched(hash));
+    }
+    Ok(())
+}
+
+/// A random duration within `fraction * interval` of `interval`
+fn jittered(interval: Duration, fraction: f64) -> Duration {
+    if fraction <= 0.0 {
+        return interval;
+    }
+    let jitter = rand::Rng::random_range(&mut rand::rng(), -fraction..=fraction);
+    interval.mul_f64(1.0 + jitter)
+}
+
+/// The progress of a `PollingBlockIngestor`, saved while it fills in
+/// missing blocks so that it can pick up where it left off after a restart
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Checkpoint {
+    /// The chain head when the checkpoint was written
+    head: Option<BlockPtr>,
+    /// The next block that the ancestor loop needs, or `None` if the loop
+    /// completed
+    missing: Option<BlockHash>,
+}
+
+/// The on-disk form of a `Checkpoint`
+#[derive(Serialize, Deserialize)]
+struct CheckpointRepr {
+    head_number: Option<BlockNumber>,
+    head_hash: Option<String>,
+    missing: Option<String>,
+}
+
+/// A file in which a `Checkpoint` is stored
+struct CheckpointFile {
+    path: PathBuf,
+}
+
+impl CheckpointFile {
+    fn new(dir: &Path, network_name: &ChainName) -> Self {
+        Self {
+            path: dir.join(format!("{}.json", network_name)),
+        }
+    }
+
+    async fn load(&self) -> Result<Option<Checkpoint>, Error> {
+        let text = match tokio::fs::read_to_string(&self.path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let repr: CheckpointRepr = serde_json::from_str(&text)?;
+        let head = match (repr.head_number, repr.head_hash) {
+            (Some(number), Some(hash)) => Some(BlockPtr::new(BlockHash::from_str(&hash)?, number)),
+            _ => None,
+        };
+        let missing = repr
+            .missing
+            .map(|hash| BlockHash::from_str(&hash))
+            .transpose()?;
+        Ok(Some(Checkpoint { head, missing }))
+    }
+
+    /// Write `checkpoint` to a temporary file first so that a crash while
+    /// writing never leaves a truncated checkpoint behind
+    async fn save(&self, checkpoint: &Checkpoint) -> Result<(), Error> {
+        let repr = CheckpointRepr {
+            head_number: checkpoint.head.as_ref().map(|ptr| ptr.number),
+            head_hash: checkpoint.head.as_ref().map(|ptr| ptr.hash.to_string()),
+            missing: checkpoint.missing.as_ref().map(|hash| hash.to_string()),
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_string(&repr)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// Metrics of a `PollingBlockIngestor`
+#[derive(Clone)]
+pub struct IngestorMetrics {
+    /// How many blocks the ancestor loop had to ingest during the last
+    /// poll
+    ancestor_backfill_iterations: Gauge,
+}
+
+impl IngestorMetrics {
+    pub fn new(registry: &MetricsRegistry, network_name: &ChainName) -> Self {
+        let ancestor_backfill_iterations = registry
+            .global_gauge_vec(
+                "ethereum_ancestor_backfill_iterations",
+                "Number of missing ancestors of the latest block that the block ingestor filled in during its last poll",
+                &["network"],
+            )
+            .unwrap()
+            .with_label_values(&[network_name.as_str()]);
+        Self {
+            ancestor_backfill_iterations,
+        }
+    }
+}
+
+/// A block that was just ingested; the next block that is ingested is
+/// often its parent
+struct IngestedBlock {
+    ptr: BlockPtr,
+    parent_hash: Option<BlockHash>,
+}
+
+/// Check that `block`, which was requested by the hash `expected`, really
+/// has that hash and, if the `child` that points to it is known, sits
+/// directly below the child
+fn check_linkage(
+    expected: B256,
+    child: Option<&IngestedBlock>,
+    block: &BlockPtr,
+) -> Result<(), IngestorError> {
+    let expected_number = child.map(|child| child.ptr.number - 1);
+    if block.hash.as_b256() != expected || expected_number.is_some_and(|n| n != block.number) {
+        return Err(IngestorError::BlockLinkageMismatched {
+            expected_hash: expected,
+            expected_number,
+            actual: block.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Notices when the chain head stops advancing even though polls succeed,
+/// which happens when a provider is stuck on an old block while the chain
+/// moves on
+struct StallWatchdog {
+    /// A zero timeout turns the watchdog off
+    timeout: Duration,
+    head: Option<BlockPtr>,
+    since: Instant,
+}
+
+impl StallWatchdog {
+    fn new(timeout: Duration, now: Instant) -> Self {
+        Self {
+            timeout,
+            head: None,
+            since: now,
+        }
+    }
+
+    /// Record that `head` is the chain head at `now`. If it has not changed
+    /// for at least the timeout, return how long it has been stuck
+    fn check(&mut self, head: Option<BlockPtr>, now: Instant) -> Option<Duration> {
+        if self.timeout.is_zero() {
+            return None;
+        }
+        if head != self.head {
+            self.head = head;
+            self.since = now;
+            return None;
+        }
+        let stalled_for = now.saturating_duration_since(self.since);
+        (stalled_for >= self.timeout).then_some(stalled_for)
+    }
+
+    /// Give the chain head another full timeout to advance
+    fn restart(&mut self, now: Instant) {
+        self.since = now;
+    }
+}
 
 pub struct PollingBlockIngestor {
     logger: Logger,
     ancestor_count: i32,
     chain_client: Arc<ChainClient<crate::chain::Chain>>,
     chain_store: Arc<dyn ChainStore>,
-    polling_interval: Duration,
+    polling_interval: PollingInterval,
     network_name: ChainName,
+    prefetch_window: usize,
+    prefetch_threshold: BlockNumber,
+    block_pointer_timeout: Duration,
+    checkpoint: Option<CheckpointFile>,
+    verify_linkage: bool,
+    poll_jitter: f64,
+    timestamp_check: TimestampCheck,
+    future_timestamp_check: TimestampCheck,
+    future_timestamp_tolerance: Duration,
+    receipt_demand: Option<ReceiptDemand>,
+    block_transform: Option<BlockTransform>,
+    metrics: Option<IngestorMetrics>,
+    stall_timeout: Duration,
+    pause: IngestorPause,
 }
 
 impl PollingBlockIngestor {
@@ -36,16 +397,135 @@ impl PollingBlockIngestor {
         polling_interval: Duration,
         network_name: ChainName,
     ) -> Result<PollingBlockIngestor, Error> {
+        let checkpoint = ENV_VARS
+            .block_ingestor_checkpoint_dir
+            .as_deref()
+            .map(|dir| CheckpointFile::new(dir, &network_name));
         Ok(PollingBlockIngestor {
             logger,
             ancestor_count,
             chain_client,
             chain_store,
-            polling_interval,
+            polling_interval: PollingInterval::new(polling_interval),
             network_name,
+            prefetch_window: ENV_VARS.block_ingestor_prefetch_window,
+            prefetch_threshold: ENV_VARS.block_ingestor_prefetch_threshold,
+            block_pointer_timeout: ENV_VARS.block_pointer_timeout,
+            checkpoint,
+            verify_linkage: ENV_VARS.block_ingestor_verify_linkage,
+            poll_jitter: ENV_VARS.block_ingestor_poll_jitter,
+            timestamp_check: ENV_VARS.block_ingestor_timestamp_check,
+            future_timestamp_check: ENV_VARS.block_ingestor_future_timestamp_check,
+            future_timestamp_tolerance: ENV_VARS.block_ingestor_future_timestamp_tolerance,
+            receipt_demand: None,
+            block_transform: None,
+            metrics: None,
+            stall_timeout: ENV_VARS.block_ingestor_stall_timeout,
+            pause: IngestorPause::default(),
         })
     }
 
+    /// Only load transaction receipts while `demand` says that a subgraph
+    /// needs them. Without a demand, receipts are always loaded.
+    pub fn with_receipt_demand(mut self, demand: ReceiptDemand) -> Self {
+        self.receipt_demand = Some(demand);
+        self
+    }
+
+    /// Pass every block through `transform` before storing it
+    pub fn with_block_transform(mut self, transform: BlockTransform) -> Self {
+        self.block_transform = Some(transform);
+        self
+    }
+
+    /// Report how the ingestor is doing to `metrics`
+    pub fn with_metrics(mut self, metrics: IngestorMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Switch to another provider when the chain head has not changed for
+    /// `timeout`; a zero timeout turns this off
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = timeout;
+        self
+    }
+
+    /// Stop polling the chain until `resume` is called. A poll that is
+    /// already underway still finishes.
+    pub fn pause(&self) {
+        self.pause.pause();
+    }
+
+    pub fn resume(&self) {
+        self.pause.resume();
+    }
+
+    /// Sleep for as long as the ingestor is paused. Since the chain head
+    /// can not advance while we are paused, the stall watchdog starts over
+    /// once we resume.
+    async fn wait_while_paused(&self, watchdog: &mut StallWatchdog) {
+        if !self.pause.is_paused() {
+            return;
+        }
+        info!(self.logger, "Block ingestion paused");
+        while self.pause.is_paused() {
+            tokio::time::sleep(self.poll_delay()).await;
+        }
+        info!(self.logger, "Block ingestion resumed");
+        watchdog.restart(Instant::now());
+    }
+
+    fn loads_receipts(&self) -> bool {
+        self.receipt_demand
+            .as_ref()
+            .is_none_or(ReceiptDemand::is_needed)
+    }
+
+    /// Change how long the poll loop sleeps between polls. The new interval
+    /// is used from the next iteration of the loop on.
+    pub fn set_polling_interval(&self, interval: Duration) {
+        self.polling_interval.set(interval);
+    }
+
+    /// A handle that can be used to change the polling interval after the
+    /// ingestor has been handed off to run.
+    pub fn polling_interval(&self) -> PollingInterval {
+        self.polling_interval.clone()
+    }
+
+    /// How long to sleep before the next poll
+    fn poll_delay(&self) -> Duration {
+        self.polling_interval.get()
+    }
+
+    /// Re-evaluate the chain head from the blocks in the block cache and
+    /// return the resulting head. This runs the same head update that
+    /// ingesting a block does, and is meant for recovery tooling after
+    /// blocks in the cache were changed by hand. It is safe to call while
+    /// the poll loop is idle.
+    pub async fn recompute_chain_head(&self) -> Result<Option<BlockPtr>, Error> {
+        if let Some(missing) = self.update_chain_head().await? {
+            info!(
+                self.logger,
+                "Chain head can not advance until a missing block is ingested";
+                "missing_block_hash" => format!("{:x}", missing),
+            );
+        }
+        self.chain_store.cheap_clone().chain_head_ptr().await
+    }
+
+    /// Move the chain head to the best block in the block cache. Return
+    /// the hash of a block that has to be ingested before the head can
+    /// advance further, if there is one
+    async fn update_chain_head(&self) -> Result<Option<BlockHash>, Error> {
+        self.chain_store
+            .cheap_clone()
+            .attempt_chain_head_update(self.ancestor_count)
+            .await
+            .map(|missing| missing.map(BlockHash::from))
+    }
+
     async fn cleanup_cached_blocks(&self) {
         match self
             .chain_store
@@ -134,6 +614,9 @@ impl PollingBlockIngestor {
                         "code" => code,
                     );
                 }
+
+                self.prefetch(logger, &eth_adapter, head_number, latest_number)
+                    .await;
             }
         }
 
@@ -141,10 +624,86 @@ impl PollingBlockIngestor {
         // Might be a no-op if latest block is one that we have seen.
         // ingest_blocks will return a (potentially incomplete) list of blocks that are
         // missing.
-        let mut missing_block_hash = self
-            .ingest_block(logger, &eth_adapter, &latest_block.hash)
+        let (latest, missing_block_hash) = self
+            .ingest_block(logger, &eth_adapter, &latest_block.hash, None)
             .await?;
 
+        self.ingest_ancestors(logger, &eth_adapter, missing_block_hash, Some(latest))
+            .await
+    }
+
+    /// Warm the block cache with the blocks right below the latest block
+    /// if the chain head at `head` is at least `prefetch_threshold` blocks
+    /// behind `latest`, so that the ancestor loop rarely has to go to the
+    /// network. This only stores blocks and never touches the chain head.
+    async fn prefetch<A: crate::EthereumAdapterTrait>(
+        &self,
+        logger: &Logger,
+        eth_adapter: &Arc<A>,
+        head: BlockNumber,
+        latest: BlockNumber,
+    ) {
+        if latest - head < self.prefetch_threshold {
+            return;
+        }
+        let numbers = prefetch_numbers(
+            head,
+            latest,
+            self.prefetch_window.min(self.ancestor_count as usize),
+        );
+        if !numbers.is_empty() {
+            prefetch_blocks(
+                logger,
+                eth_adapter,
+                &self.chain_store,
+                numbers,
+                self.loads_receipts(),
+                self.block_transform.as_ref(),
+            )
+            .await;
+        }
+    }
+
+    /// Ingest `missing_block_hash` and all the blocks that are still missing
+    /// below it. If a checkpoint file is configured, progress is saved
+    /// before each block is ingested and once all blocks are present.
+    /// `child` is the block that was ingested right before, if any.
+    ///
+    /// More than `ancestor_count` missing blocks can only happen when the
+    /// provider misbehaves, and makes the loop fail instead of going on.
+    async fn ingest_ancestors<A: crate::EthereumAdapterTrait>(
+        &self,
+        logger: &Logger,
+        eth_adapter: &Arc<A>,
+        missing_block_hash: Option<BlockHash>,
+        child: Option<IngestedBlock>,
+    ) -> Result<(), IngestorError> {
+        let mut iterations = 0;
+        let res = self
+            .ingest_missing_ancestors(
+                logger,
+                eth_adapter,
+                missing_block_hash,
+                child,
+                &mut iterations,
+            )
+            .await;
+        if let Some(metrics) = &self.metrics {
+            metrics.ancestor_backfill_iterations.set(iterations as f64);
+        }
+        res
+    }
+
+    /// The loop of `ingest_ancestors`; `iterations` counts the blocks that
+    /// the loop tried to ingest
+    async fn ingest_missing_ancestors<A: crate::EthereumAdapterTrait>(
+        &self,
+        logger: &Logger,
+        eth_adapter: &Arc<A>,
+        mut missing_block_hash: Option<BlockHash>,
+        mut child: Option<IngestedBlock>,
+        iterations: &mut BlockNumber,
+    ) -> Result<(), IngestorError> {
         // Repeatedly fetch missing parent blocks, and ingest them.
         // ingest_blocks will continue to tell us about more missing parent
         // blocks until we have filled in all missing pieces of the
@@ -163,56 +722,238 @@ impl PollingBlockIngestor {
         //   most block number N, then the missing parents in the next
         //   iteration will have at most block number N-1.
         // - Therefore, the loop will iterate at most ancestor_count times.
+        //   A provider that keeps handing out blocks whose parents are
+        //   missing breaks that argument, and we stop instead.
         while let Some(hash) = missing_block_hash {
-            missing_block_hash = self.ingest_block(logger, &eth_adapter, &hash).await?;
+            if *iterations >= self.ancestor_count {
+                return Err(IngestorError::AncestorLimitExceeded {
+                    limit: self.ancestor_count,
+                    missing: hash.as_b256(),
+                });
+            }
+            *iterations += 1;
+
+            self.save_checkpoint(logger, Some(hash.clone())).await;
+            // The missing block is usually the parent of the block we just
+            // ingested, but it can also be further down the chain
+            let parent_of = child
+                .as_ref()
+                .filter(|child| child.parent_hash.as_ref() == Some(&hash));
+            let (ingested, missing) = self
+                .ingest_block(logger, eth_adapter, &hash, parent_of)
+                .await?;
+            child = Some(ingested);
+            missing_block_hash = missing;
         }
+        self.save_checkpoint(logger, None).await;
         Ok(())
     }
 
-    async fn ingest_block(
+    /// Save a checkpoint with the current chain head and `missing`. The
+    /// head that is recorded never moves backwards, even if the chain
+    /// head in the store did. Failures are only logged.
+    async fn save_checkpoint(&self, logger: &Logger, missing: Option<BlockHash>) {
+        let Some(file) = &self.checkpoint else {
+            return;
+        };
+
+        let res = async {
+            let head = self.chain_store.cheap_clone().chain_head_ptr().await?;
+            let prev = file.load().await?.and_then(|checkpoint| checkpoint.head);
+            let head = match (head, prev) {
+                (Some(head), Some(prev)) if prev.number > head.number => Some(prev),
+                (head, prev) => head.or(prev),
+            };
+            file.save(&Checkpoint { head, missing }).await
+        }
+        .await;
+
+        if let Err(e) = res {
+            warn!(logger, "Failed to save block ingestor checkpoint"; "error" => format!("{:#}", e));
+        }
+    }
+
+    /// If the ingestor was interrupted while filling in missing blocks,
+    /// continue with the block it needed when it saved its last
+    /// checkpoint. A checkpoint whose head is behind the chain head in the
+    /// store is outdated and ignored.
+    async fn resume_from_checkpoint<A: crate::EthereumAdapterTrait>(
+        &self,
+        logger: &Logger,
+        eth_adapter: &Arc<A>,
+    ) -> Result<(), IngestorError> {
+        let Some(file) = &self.checkpoint else {
+            return Ok(());
+        };
+        let Some(Checkpoint {
+            head: checkpoint_head,
+            missing: Some(missing),
+        }) = file.load().await?
+        else {
+            return Ok(());
+        };
+
+        let head = self.chain_store.cheap_clone().chain_head_ptr().await?;
+        let head_number = head.map(|ptr| ptr.number);
+        let checkpoint_number = checkpoint_head.map(|ptr| ptr.number);
+        if head_number > checkpoint_number {
+            debug!(logger, "Ignoring outdated block ingestor checkpoint";
+                "current_block_head" => head_number,
+                "checkpoint_block_head" => checkpoint_number);
+            return Ok(());
+        }
+
+        info!(logger, "Resuming block ingestion from checkpoint";
+            "missing_block_hash" => format!("{:x}", missing));
+        self.ingest_ancestors(logger, eth_adapter, Some(missing), None)
+            .await
+    }
+
+    /// Load the block with `block_hash`, store it and try to advance the
+    /// chain head. Returns the stored block and the hash of the next block
+    /// that is missing, if any. When linkage verification is turned on,
+    /// the block must be the parent of `child`.
+    async fn ingest_block<A: crate::EthereumAdapterTrait>(
         &self,
         logger: &Logger,
-        eth_adapter: &Arc<EthereumAdapter>,
+        eth_adapter: &Arc<A>,
         block_hash: &BlockHash,
-    ) -> Result<Option<BlockHash>, IngestorError> {
+        child: Option<&IngestedBlock>,
+    ) -> Result<(IngestedBlock, Option<BlockHash>), IngestorError> {
         let block_hash = B256::from_slice(block_hash.as_slice());
 
-        // Get the fully populated block
+        // Get the block, fully populated if any subgraph needs receipts
         let block = eth_adapter
             .block_by_hash(logger, block_hash)
             .await?
             .ok_or(IngestorError::BlockUnavailable(block_hash))?;
-        let ethereum_block = eth_adapter.load_full_block(logger, block).await?;
+        let ethereum_block =
+            complete_block(logger, eth_adapter, block, self.loads_receipts()).await?;
 
-        // We need something that implements `Block` to store the block; the
-        // store does not care whether the block is final or not
-        let ethereum_block = BlockFinality::NonFinal(EthereumBlockWithCalls {
-            ethereum_block,
-            calls: None,
-        });
+        let parent_hash = ethereum_block.light_block().inner().header.parent_hash;
+        let parent = self
+            .chain_store
+            .cheap_clone()
+            .blocks(vec![parent_hash.into()])
+            .await?;
+
+        let ingested = IngestedBlock {
+            ptr: ethereum_block.light_block().block_ptr(),
+            parent_hash: ethereum_block
+                .light_block()
+                .parent_ptr()
+                .map(|ptr| ptr.hash),
+        };
+        if self.verify_linkage {
+            check_linkage(block_hash, child, &ingested.ptr)?;
+        }
+        self.check_future_timestamp(logger, &ethereum_block, SystemTime::now())?;
+        if let Some(parent) = parent.first() {
+            self.check_timestamp(logger, parent, &ethereum_block)?;
+        }
 
         // Store it in the database and try to advance the chain head pointer
-        self.chain_store
-            .upsert_block(Arc::new(ethereum_block))
-            .await?;
+        store_block(
+            logger,
+            &self.chain_store,
+            ethereum_block,
+            self.block_transform.as_ref(),
+        )
+        .await?;
 
-        self.chain_store
-            .cheap_clone()
-            .attempt_chain_head_update(self.ancestor_count)
-            .await
-            .map(|missing| missing.map(|h256| h256.into()))
-            .map_err(|e| {
-                error!(logger, "failed to update chain head");
-                IngestorError::Unknown(e)
-            })
+        let missing = self.update_chain_head().await.map_err(|e| {
+            error!(logger, "failed to update chain head");
+            IngestorError::Unknown(e)
+        })?;
+        Ok((ingested, missing))
+    }
+
+    /// Check that `block` is newer than its `parent`. Depending on
+    /// `timestamp_check`, a block that is not is only logged or rejected.
+    fn check_timestamp(
+        &self,
+        logger: &Logger,
+        parent: &CachedBlock,
+        block: &CachedBlock,
+    ) -> Result<(), IngestorError> {
+        if self.timestamp_check == TimestampCheck::Off {
+            return Ok(());
+        }
+
+        let parent_timestamp = block_time(parent.light_block());
+        let timestamp = block_time(block.light_block());
+        if timestamp > parent_timestamp {
+            return Ok(());
+        }
+
+        let err = IngestorError::BlockTimestampNotIncreasing {
+            block: block.light_block().block_ptr(),
+            timestamp,
+            parent_timestamp,
+        };
+        match self.timestamp_check {
+            TimestampCheck::Reject => Err(err),
+            TimestampCheck::Warn | TimestampCheck::Off => {
+                warn!(logger, "{}", err);
+                Ok(())
+            }
+        }
+    }
+
+    /// Check that the timestamp of `block` is not more than
+    /// `future_timestamp_tolerance` after `now`. Such a timestamp comes
+    /// from bad data at the provider and would throw off anything that
+    /// uses block times. Depending on `future_timestamp_check`, the block is
+    /// only logged or rejected.
+    fn check_future_timestamp(
+        &self,
+        logger: &Logger,
+        block: &CachedBlock,
+        now: SystemTime,
+    ) -> Result<(), IngestorError> {
+        if self.future_timestamp_check == TimestampCheck::Off {
+            return Ok(());
+        }
+
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs() as i64)
+            .unwrap_or(0);
+        let timestamp = block_time(block.light_block());
+        let tolerance = self.future_timestamp_tolerance.as_secs() as i64;
+        if timestamp.as_secs_since_epoch() <= now.saturating_add(tolerance) {
+            return Ok(());
+        }
+
+        let err = IngestorError::BlockTimestampInFuture {
+            block: block.light_block().block_ptr(),
+            timestamp,
+            now: BlockTime::since_epoch(now, 0),
+            tolerance: self.future_timestamp_tolerance,
+        };
+        match self.future_timestamp_check {
+            TimestampCheck::Reject => Err(err),
+            TimestampCheck::Warn | TimestampCheck::Off => {
+                warn!(logger, "{}", err);
+                Ok(())
+            }
+        }
     }
 
-    async fn latest_block(
+    /// Ask the provider for its latest block. A provider that does not
+    /// answer within `block_pointer_timeout` fails the poll rather than
+    /// stalling it.
+    async fn latest_block<A: crate::EthereumAdapterTrait>(
         &self,
         logger: &Logger,
-        eth_adapter: &Arc<EthereumAdapter>,
+        eth_adapter: &Arc<A>,
     ) -> Result<BlockPtr, IngestorError> {
-        eth_adapter.latest_block_ptr(logger).await
+        with_ingestor_timeout(
+            self.block_pointer_timeout,
+            "latest_block_ptr",
+            eth_adapter.latest_block_ptr(logger),
+        )
+        .await
     }
 
     /// Executes one polling iteration. On failure delegates to `on_poll_failure` for
@@ -234,6 +975,106 @@ impl PollingBlockIngestor {
             on_poll_failure(providers, current_provider, &self.logger).await;
         }
     }
+
+    /// If the chain head has not changed for longer than the stall
+    /// timeout, switch to a provider that is ahead of it. Without such a
+    /// provider, the process is aborted if
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_KILL_IF_STALLED` is set
+    async fn check_stalled_head<A: crate::EthereumAdapterTrait>(
+        &self,
+        providers: &[Arc<A>],
+        current_provider: &mut Option<String>,
+        watchdog: &mut StallWatchdog,
+    ) {
+        if self.stall_timeout.is_zero() {
+            return;
+        }
+        let head = match self.chain_store.cheap_clone().chain_head_ptr().await {
+            Ok(head) => head,
+            Err(e) => {
+                warn!(self.logger, "Failed to check whether the chain head is stalled"; "error" => format!("{:#}", e));
+                return;
+            }
+        };
+        let head_number = head.as_ref().map(|ptr| ptr.number);
+        let Some(stalled_for) = watchdog.check(head, Instant::now()) else {
+            return;
+        };
+        watchdog.restart(Instant::now());
+
+        crit!(
+            self.logger,
+            "Chain head has not advanced, the RPC provider might be stuck";
+            "provider" => current_provider.as_deref().unwrap_or_default(),
+            "current_block_head" => head_number,
+            "stalled_for_secs" => stalled_for.as_secs(),
+            "code" => LogCode::BlockIngestionStalled,
+        );
+
+        if switch_from_stalled_provider(
+            providers,
+            current_provider,
+            head_number,
+            self.block_pointer_timeout,
+            &self.logger,
+        )
+        .await
+        {
+            return;
+        }
+
+        if ENV_VARS.block_ingestor_kill_if_stalled {
+            crit!(
+                self.logger,
+                "No RPC provider is ahead of the stalled chain head, killing process";
+                "current_block_head" => head_number,
+            );
+            std::process::abort()
+        }
+    }
+}
+
+/// The numbers of the blocks that should be prefetched when the chain head
+/// is at `head` and the provider reports `latest`. These are the (at most
+/// `window`) blocks directly below `latest` that the ancestor loop would
+/// otherwise load one at a time; `latest` itself is always ingested
+/// separately.
+fn prefetch_numbers(head: BlockNumber, latest: BlockNumber, window: usize) -> Vec<BlockNumber> {
+    let window = BlockNumber::try_from(window).unwrap_or(BlockNumber::MAX);
+    let start = (head + 1).max(latest.saturating_sub(window));
+    (start..latest).collect()
+}
+
+/// Load the blocks with the given `numbers` in parallel and store them in
+/// the block cache. Failures are only logged since the ancestor loop will
+/// load any block that is still missing by hash.
+async fn prefetch_blocks<A: crate::EthereumAdapterTrait>(
+    logger: &Logger,
+    eth_adapter: &Arc<A>,
+    chain_store: &Arc<dyn ChainStore>,
+    numbers: Vec<BlockNumber>,
+    with_receipts: bool,
+    transform: Option<&BlockTransform>,
+) {
+    let parallelism = numbers.len().max(1);
+    let results: Vec<_> = futures::stream::iter(numbers)
+        .map(|number| async move {
+            let block = eth_adapter
+                .block_by_number(logger, number)
+                .await?
+                .ok_or_else(|| anyhow!("block {} is not available", number))?;
+            let block = complete_block(logger, eth_adapter, block, with_receipts).await?;
+            store_block(logger, chain_store, block, transform)
+                .await
+                .map_err(Error::from)
+        })
+        .buffer_unordered(parallelism)
+        .collect()
+        .await;
+
+    for err in results.into_iter().filter_map(Result::err) {
+        debug!(logger, "Failed to prefetch block"; "error" => format!("{:#}", err));
+    }
 }
 
 /// Returns the currently-tracked provider from `providers`.
@@ -323,6 +1164,50 @@ async fn on_poll_failure<A: crate::EthereumAdapterTrait>(
     }
 }
 
+/// Switch `current_provider` to another provider whose latest block is
+/// past the chain `head`. Returns whether there was such a provider
+async fn switch_from_stalled_provider<A: crate::EthereumAdapterTrait>(
+    providers: &[Arc<A>],
+    current_provider: &mut Option<String>,
+    head: Option<BlockNumber>,
+    timeout: Duration,
+    logger: &Logger,
+) -> bool {
+    let current_name = current_provider.clone().unwrap_or_default();
+    let futs: Vec<_> = providers
+        .iter()
+        .filter(|p| p.provider() != current_name)
+        .map(|p| {
+            Box::pin(async move {
+                let latest =
+                    with_ingestor_timeout(timeout, "latest_block_ptr", p.latest_block_ptr(logger))
+                        .await;
+                match latest {
+                    Ok(latest) if Some(latest.number) > head => Ok(p.provider().to_string()),
+                    _ => Err(()),
+                }
+            })
+        })
+        .collect();
+    if futs.is_empty() {
+        return false;
+    }
+
+    match select_ok(futs).await {
+        Ok((next_name, _)) => {
+            warn!(
+                logger,
+                "Switching RPC provider for block ingestor since the chain head is stalled";
+                "from" => &current_name,
+                "to" => &next_name,
+            );
+            *current_provider = Some(next_name);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 #[async_trait]
 impl BlockIngestor for PollingBlockIngestor {
     async fn run(self: Box<Self>) {
@@ -330,8 +1215,12 @@ impl BlockIngestor for PollingBlockIngestor {
             ExponentialBackoff::new(Duration::from_millis(250), Duration::from_secs(30));
         // Name of the provider currently in use. `None` until the first poll.
         let mut current_provider: Option<String> = None;
+        let mut resume = self.checkpoint.is_some();
+        let mut watchdog = StallWatchdog::new(self.stall_timeout, Instant::now());
 
         loop {
+            self.wait_while_paused(&mut watchdog).await;
+
             let providers = self
                 .chain_client
                 .rpc()
@@ -346,13 +1235,26 @@ impl BlockIngestor for PollingBlockIngestor {
             }
             backoff.reset();
 
+            if resume {
+                resume = false;
+                let eth_adapter = resolve_provider(&providers, &mut current_provider, &self.logger);
+                if let Err(err) = self.resume_from_checkpoint(&self.logger, eth_adapter).await {
+                    warn!(
+                        self.logger,
+                        "Failed to resume block ingestion from checkpoint: {}", err
+                    );
+                }
+            }
+
             self.poll_once(&providers, &mut current_provider).await;
+            self.check_stalled_head(&providers, &mut current_provider, &mut watchdog)
+                .await;
 
             if ENV_VARS.cleanup_blocks {
                 self.cleanup_cached_blocks().await;
             }
 
-            tokio::time::sleep(self.polling_interval).await;
+            tokio::time::sleep(jittered(self.poll_delay(), self.poll_jitter)).await;
         }
     }
 
@@ -363,6 +1265,10 @@ impl BlockIngestor for PollingBlockIngestor {
     fn kind(&self) -> BlockchainKind {
         BlockchainKind::Ethereum
     }
+
+    fn pause_handle(&self) -> Option<IngestorPause> {
+        Some(self.pause.clone())
+    }
 }
 
 #[cfg(test)]
@@ -372,21 +1278,29 @@ mod tests {
     use crate::adapter::{
         ContractCallError, EthereumAdapter as EthereumAdapterTrait, EthereumRpcError,
     };
+    use crate::network::EthereumNetworkAdapters;
     use async_trait::async_trait;
+    use graph::blockchain::mock::{MockChainStore, test_ptr};
     use graph::blockchain::{BlockPtr, ChainIdentifier};
     use graph::components::ethereum::AnyBlock;
     use graph::components::ethereum::LightEthereumBlock;
     use graph::data::store::ethereum::call;
     use graph::data_source::common::ContractCall;
     use graph::prelude::alloy::primitives::{Address, B256, Bytes, U256};
-    use graph::prelude::{BlockNumber, Error, EthereumCallCache, Logger};
+    use graph::prelude::alloy::rpc::types::{FeeHistory, Log};
+    use graph::prelude::{
+        BlockNumber, Error, EthereumCallCache, Logger, create_minimal_block_for_test,
+    };
     use graph::slog::Discard;
     use std::collections::HashSet;
+    use std::fs;
     use std::sync::Arc;
 
     struct MockEthAdapter {
         provider_name: String,
         reachable: bool,
+        blocks: Vec<AnyBlock>,
+        unresponsive: bool,
     }
 
     impl MockEthAdapter {
@@ -394,10 +1308,44 @@ mod tests {
             Arc::new(Self {
                 provider_name: name.to_string(),
                 reachable,
+                blocks: vec![],
+                unresponsive: false,
+            })
+        }
+
+        /// An adapter that accepts requests but never answers them
+        fn unresponsive(name: &str) -> Arc<Self> {
+            Arc::new(Self {
+                provider_name: name.to_string(),
+                reachable: true,
+                blocks: vec![],
+                unresponsive: true,
+            })
+        }
+
+        /// An adapter that knows about the blocks `0..=latest` of a chain
+        /// where the parent of block `n` is block `n - 1`
+        fn with_chain(name: &str, latest: BlockNumber) -> Arc<Self> {
+            let blocks = (0..=latest).map(test_block).collect();
+            Arc::new(Self {
+                provider_name: name.to_string(),
+                reachable: true,
+                blocks,
+                unresponsive: false,
             })
         }
     }
 
+    fn test_block(number: BlockNumber) -> AnyBlock {
+        let parent_hash = test_ptr(number.max(1) - 1).hash.as_b256();
+        create_minimal_block_for_test(number as u64, test_ptr(number).hash.as_b256()).map_header(
+            |mut header| {
+                header.inner.parent_hash = parent_hash;
+                header
+            },
+        )
+    }
+
     #[async_trait]
     impl EthereumAdapterTrait for MockEthAdapter {
         fn provider(&self) -> &str {
@@ -415,7 +1363,11 @@ mod tests {
             &self,
             _: &Logger,
         ) -> Result<BlockPtr, graph::blockchain::IngestorError> {
-            unimplemented!()
+            if self.unresponsive {
+                return std::future::pending().await;
+            }
+            let latest = self.blocks.last().expect("the mock adapter has blocks");
+            Ok(LightEthereumBlock::new(latest.clone()).block_ptr())
         }
         async fn load_blocks(
             &self,
@@ -425,22 +1377,29 @@ mod tests {
         ) -> Result<Vec<Arc<LightEthereumBlock>>, Error> {
             unimplemented!()
         }
-        async fn block_by_hash(&self, _: &Logger, _: B256) -> Result<Option<AnyBlock>, Error> {
-            unimplemented!()
+        async fn block_by_hash(&self, _: &Logger, hash: B256) -> Result<Option<AnyBlock>, Error> {
+            Ok(self.blocks.iter().find(|b| b.header.hash == hash).cloned())
         }
         async fn block_by_number(
             &self,
             _: &Logger,
-            _: BlockNumber,
+            number: BlockNumber,
         ) -> Result<Option<AnyBlock>, Error> {
-            unimplemented!()
+            Ok(self
+                .blocks
+                .iter()
+                .find(|b| b.header.number == number as u64)
+                .cloned())
         }
         async fn load_full_block(
             &self,
             _: &Logger,
-            _: AnyBlock,
+            block: AnyBlock,
         ) -> Result<graph::prelude::EthereumBlock, graph::blockchain::IngestorError> {
-            unimplemented!()
+            Ok(graph::prelude::EthereumBlock {
+                block: Arc::new(LightEthereumBlock::new(block)),
+                transaction_receipts: vec![],
+            })
         }
         async fn next_existing_ptr_to_number(
             &self,
@@ -483,12 +1442,46 @@ mod tests {
         ) -> Result<Bytes, EthereumRpcError> {
             unimplemented!()
         }
+        async fn get_fee_history(
+            &self,
+            _: &Logger,
+            _: u64,
+            _: BlockNumber,
+            _: &[f64],
+        ) -> Result<FeeHistory, EthereumRpcError> {
+            unimplemented!()
+        }
+        async fn get_logs(
+            &self,
+            _: &Logger,
+            _: Address,
+            _: &[Vec<B256>],
+            _: BlockNumber,
+            _: &BlockPtr,
+        ) -> Result<Vec<Log>, EthereumRpcError> {
+            unimplemented!()
+        }
     }
 
     fn discard_logger() -> Logger {
         Logger::root(Discard, o!())
     }
 
+    fn test_ingestor(chain_store: Arc<dyn ChainStore>) -> PollingBlockIngestor {
+        let chain_client = Arc::new(ChainClient::new_rpc(
+            EthereumNetworkAdapters::empty_for_testing(),
+        ));
+        PollingBlockIngestor::new(
+            discard_logger(),
+            10,
+            chain_client,
+            chain_store,
+            Duration::from_millis(10),
+            "mainnet".into(),
+        )
+        .unwrap()
+    }
+
     #[test]
     fn test_current_provider_unavailable_resets_to_first() {
         // p0 left the validated list; only p1 and p2 remain (p1 is now at index 0).
@@ -626,4 +1619,738 @@ mod tests {
         assert_eq!(resolved.provider(), "p1");
         assert_eq!(current_provider, Some("p1".to_string()));
     }
+
+    #[test]
+    fn test_prefetch_numbers() {
+        // Far behind: only the `window` blocks right below latest
+        assert_eq!(prefetch_numbers(10, 100, 3), vec![97, 98, 99]);
+        // The window reaches past the head: never refetch the head
+        assert_eq!(prefetch_numbers(95, 100, 10), vec![96, 97, 98, 99]);
+        // Only the latest block is new, nothing to prefetch
+        assert!(prefetch_numbers(99, 100, 10).is_empty());
+        // Prefetching disabled
+        assert!(prefetch_numbers(10, 100, 0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_populates_cache_without_moving_head() {
+        let logger = discard_logger();
+        let adapter = MockEthAdapter::with_chain("p0", 20);
+        let store = Arc::new(MockChainStore::default());
+        *store.head.lock().unwrap() = Some(test_ptr(10));
+        let chain_store: Arc<dyn ChainStore> = store.clone();
+
+        let numbers = prefetch_numbers(10, 20, 5);
+        prefetch_blocks(&logger, &adapter, &chain_store, numbers, true, None).await;
+
+        let mut upserted = store.upserted_numbers();
+        upserted.sort();
+        assert_eq!(upserted, vec![15, 16, 17, 18, 19]);
+        // Prefetching must not touch the chain head
+        assert_eq!(
+            chain_store.cheap_clone().chain_head_ptr().await.unwrap(),
+            Some(test_ptr(10))
+        );
+
+        // A regular head update afterwards still moves the head normally
+        chain_store
+            .upsert_block(Arc::new(BlockFinality::Final(Arc::new(
+                LightEthereumBlock::new(test_block(20)),
+            ))))
+            .await
+            .unwrap();
+        chain_store
+            .cheap_clone()
+            .attempt_chain_head_update(10)
+            .await
+            .unwrap();
+        assert_eq!(
+            chain_store.cheap_clone().chain_head_ptr().await.unwrap(),
+            Some(test_ptr(20))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_only_when_far_enough_behind() {
+        let logger = discard_logger();
+        let adapter = MockEthAdapter::with_chain("p0", 20);
+        let store = Arc::new(MockChainStore::default());
+        *store.head.lock().unwrap() = Some(test_ptr(10));
+        let mut ingestor = test_ingestor(store.clone());
+        ingestor.prefetch_window = 5;
+        ingestor.prefetch_threshold = 8;
+
+        // Behind by less than the threshold: nothing is prefetched
+        ingestor.prefetch(&logger, &adapter, 13, 20).await;
+        assert!(store.upserted_numbers().is_empty());
+
+        // Behind by the threshold: the window below the latest block is
+        // prefetched, and the head stays put
+        ingestor.prefetch(&logger, &adapter, 12, 20).await;
+        let mut upserted = store.upserted_numbers();
+        upserted.sort();
+        assert_eq!(upserted, vec![15, 16, 17, 18, 19]);
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(10)));
+    }
+
+    #[tokio::test]
+    async fn test_recompute_chain_head_after_manual_inserts() {
+        let store = Arc::new(MockChainStore::default());
+        *store.head.lock().unwrap() = Some(test_ptr(5));
+        let ingestor = test_ingestor(store.clone());
+
+        // Nothing changed in the cache: the head stays put
+        assert_eq!(
+            ingestor.recompute_chain_head().await.unwrap(),
+            Some(test_ptr(5))
+        );
+
+        // Insert blocks by hand, out of order, bypassing the poll loop
+        for number in [8, 6, 9, 7] {
+            store
+                .upsert_block(Arc::new(BlockFinality::Final(Arc::new(
+                    LightEthereumBlock::new(test_block(number)),
+                ))))
+                .await
+                .unwrap();
+        }
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(5)));
+
+        assert_eq!(
+            ingestor.recompute_chain_head().await.unwrap(),
+            Some(test_ptr(9))
+        );
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(9)));
+    }
+
+    #[test]
+    fn test_changed_polling_interval_is_observed() {
+        let ingestor = test_ingestor(Arc::new(MockChainStore::default()));
+        assert_eq!(ingestor.poll_delay(), Duration::from_millis(10));
+
+        ingestor.set_polling_interval(Duration::from_millis(500));
+        assert_eq!(ingestor.poll_delay(), Duration::from_millis(500));
+
+        // Changes through a handle, e.g., after the ingestor was boxed and
+        // handed to `run`, are seen by the loop as well
+        let handle = ingestor.polling_interval();
+        handle.set(Duration::from_secs(3));
+        assert_eq!(ingestor.poll_delay(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_poll_delay_jitter_stays_within_band() {
+        let interval = Duration::from_millis(1000);
+        assert_eq!(jittered(interval, 0.0), interval);
+
+        let delays: HashSet<_> = (0..100).map(|_| jittered(interval, 0.1)).collect();
+        assert!(delays.len() > 1);
+        assert!(
+            delays
+                .iter()
+                .all(|d| *d >= Duration::from_millis(900) && *d <= Duration::from_millis(1100))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_provider_times_out() {
+        let mut ingestor = test_ingestor(Arc::new(MockChainStore::default()));
+        ingestor.block_pointer_timeout = Duration::from_millis(10);
+        let adapter = MockEthAdapter::unresponsive("p0");
+
+        let res = ingestor.latest_block(&discard_logger(), &adapter).await;
+        assert!(matches!(res, Err(IngestorError::Unknown(_))));
+    }
+
+    #[test]
+    fn test_stall_watchdog() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let mut watchdog = StallWatchdog::new(Duration::from_secs(60), start);
+        assert_eq!(watchdog.check(Some(test_ptr(1)), at(0)), None);
+        assert_eq!(watchdog.check(Some(test_ptr(2)), at(50)), None);
+        // The head last changed at 50s
+        assert_eq!(watchdog.check(Some(test_ptr(2)), at(100)), None);
+        assert_eq!(
+            watchdog.check(Some(test_ptr(2)), at(115)),
+            Some(Duration::from_secs(65))
+        );
+        watchdog.restart(at(115));
+        assert_eq!(watchdog.check(Some(test_ptr(2)), at(120)), None);
+
+        let mut watchdog = StallWatchdog::new(Duration::ZERO, start);
+        assert_eq!(watchdog.check(None, at(0)), None);
+        assert_eq!(watchdog.check(None, at(1000)), None);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_provider_triggers_switch() {
+        let store = Arc::new(MockChainStore::default());
+        *store.head.lock().unwrap() = Some(test_ptr(5));
+        let ingestor = test_ingestor(store.clone()).with_stall_timeout(Duration::from_millis(20));
+
+        // p0 is stuck at the chain head while p1 has moved on
+        let providers: Vec<Arc<MockEthAdapter>> = vec![
+            MockEthAdapter::with_chain("p0", 5),
+            MockEthAdapter::with_chain("p1", 9),
+        ];
+        let mut current_provider = Some("p0".to_string());
+        let mut watchdog = StallWatchdog::new(ingestor.stall_timeout, Instant::now());
+
+        ingestor
+            .check_stalled_head(&providers, &mut current_provider, &mut watchdog)
+            .await;
+        assert_eq!(current_provider, Some("p0".to_string()));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        ingestor
+            .check_stalled_head(&providers, &mut current_provider, &mut watchdog)
+            .await;
+        assert_eq!(current_provider, Some("p1".to_string()));
+
+        // A provider that is not ahead of the head is no better
+        let providers: Vec<Arc<MockEthAdapter>> = vec![
+            MockEthAdapter::with_chain("p0", 5),
+            MockEthAdapter::with_chain("p1", 5),
+        ];
+        let mut current_provider = Some("p0".to_string());
+        assert!(
+            !switch_from_stalled_provider(
+                &providers,
+                &mut current_provider,
+                Some(5),
+                Duration::from_secs(1),
+                &discard_logger(),
+            )
+            .await
+        );
+        assert_eq!(current_provider, Some("p0".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_paused_ingestor_does_not_poll() {
+        let store = Arc::new(MockChainStore::default());
+        let ingestor = test_ingestor(store);
+        let mut watchdog = StallWatchdog::new(Duration::from_millis(20), Instant::now());
+
+        // A running ingestor goes straight on to poll
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            ingestor.wait_while_paused(&mut watchdog),
+        )
+        .await
+        .expect("a running ingestor polls");
+
+        // A paused ingestor keeps sleeping without polling
+        ingestor.pause();
+        assert!(ingestor.pause_handle().unwrap().is_paused());
+        assert!(
+            tokio::time::timeout(
+                Duration::from_millis(50),
+                ingestor.wait_while_paused(&mut watchdog),
+            )
+            .await
+            .is_err()
+        );
+        assert!(watchdog.check(None, Instant::now()).is_some());
+
+        // Resuming through a handle lets the ingestor poll again, and the
+        // time spent paused does not count as a stalled chain head
+        let handle = ingestor.pause_handle().unwrap();
+        let resume = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            handle.resume();
+        };
+        let (waited, ()) = tokio::join!(
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                ingestor.wait_while_paused(&mut watchdog)
+            ),
+            resume
+        );
+        assert!(waited.is_ok());
+        assert!(!ingestor.pause_handle().unwrap().is_paused());
+        assert_eq!(watchdog.check(None, Instant::now()), None);
+    }
+
+    /// A checkpoint file for `name` in a fresh temporary directory
+    fn checkpoint_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "graph-ingestor-checkpoint-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_resume_backfill_from_checkpoint() {
+        let logger = discard_logger();
+        let dir = checkpoint_dir("resume");
+        let store = Arc::new(MockChainStore {
+            require_ancestors: true,
+            ..Default::default()
+        });
+        *store.head.lock().unwrap() = Some(test_ptr(5));
+
+        // The first run crashes while filling in the blocks between the
+        // head and the latest block because block 12 can not be loaded
+        let mut ingestor = test_ingestor(store.clone());
+        ingestor.checkpoint = Some(CheckpointFile::new(&dir, &"mainnet".into()));
+        let adapter = Arc::new(MockEthAdapter {
+            provider_name: "p0".to_string(),
+            reachable: true,
+            blocks: (0..=20).filter(|n| *n != 12).map(test_block).collect(),
+            unresponsive: false,
+        });
+        let (latest, missing) = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(20).hash, None)
+            .await
+            .unwrap();
+        let res = ingestor
+            .ingest_ancestors(&logger, &adapter, missing, Some(latest))
+            .await;
+        assert!(matches!(res, Err(IngestorError::BlockUnavailable(_))));
+        assert_eq!(
+            store.upserted_numbers(),
+            vec![20, 19, 18, 17, 16, 15, 14, 13]
+        );
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(5)));
+
+        // After the restart, the ingestor continues with block 12 and does
+        // not load the blocks it already has again
+        let mut ingestor = test_ingestor(store.clone());
+        ingestor.checkpoint = Some(CheckpointFile::new(&dir, &"mainnet".into()));
+        let adapter = MockEthAdapter::with_chain("p0", 20);
+        ingestor
+            .resume_from_checkpoint(&logger, &adapter)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.upserted_numbers()[8..].to_vec(),
+            vec![12, 11, 10, 9, 8, 7, 6]
+        );
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(20)));
+
+        let checkpoint = ingestor.checkpoint.as_ref().unwrap().load().await.unwrap();
+        assert_eq!(
+            checkpoint,
+            Some(Checkpoint {
+                head: Some(test_ptr(20)),
+                missing: None,
+            })
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ancestor_loop_is_bounded_and_reported() {
+        let logger = discard_logger();
+        let registry = MetricsRegistry::mock();
+        let metrics = IngestorMetrics::new(&registry, &"mainnet".into());
+        let store = Arc::new(MockChainStore {
+            require_ancestors: true,
+            ..Default::default()
+        });
+        *store.head.lock().unwrap() = Some(test_ptr(5));
+        let mut ingestor = test_ingestor(store.clone()).with_metrics(metrics.clone());
+        ingestor.ancestor_count = 50;
+
+        // Filling in blocks 19 to 6 takes one iteration per block
+        let adapter = MockEthAdapter::with_chain("p0", 20);
+        let (latest, missing) = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(20).hash, None)
+            .await
+            .unwrap();
+        ingestor
+            .ingest_ancestors(&logger, &adapter, missing, Some(latest))
+            .await
+            .unwrap();
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(20)));
+        assert_eq!(metrics.ancestor_backfill_iterations.get(), 14.0);
+
+        // A provider that keeps producing missing parents makes the loop
+        // give up after `ancestor_count` blocks
+        let adapter = MockEthAdapter::with_chain("p0", 100);
+        let (latest, missing) = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(100).hash, None)
+            .await
+            .unwrap();
+        let res = ingestor
+            .ingest_ancestors(&logger, &adapter, missing, Some(latest))
+            .await;
+        match res {
+            Err(IngestorError::AncestorLimitExceeded { limit, missing }) => {
+                assert_eq!(limit, 50);
+                assert_eq!(missing, test_ptr(49).hash.as_b256());
+            }
+            res => panic!("expected the ancestor loop to give up, got {:?}", res),
+        }
+        assert_eq!(store.upserted_numbers().len(), 15 + 1 + 50);
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(20)));
+        assert_eq!(metrics.ancestor_backfill_iterations.get(), 50.0);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_never_moves_head_backwards() {
+        let logger = discard_logger();
+        let dir = checkpoint_dir("outdated");
+        let store = Arc::new(MockChainStore::default());
+        *store.head.lock().unwrap() = Some(test_ptr(30));
+
+        let mut ingestor = test_ingestor(store.clone());
+        let file = CheckpointFile::new(&dir, &"mainnet".into());
+        file.save(&Checkpoint {
+            head: Some(test_ptr(20)),
+            missing: Some(test_ptr(12).hash),
+        })
+        .await
+        .unwrap();
+        ingestor.checkpoint = Some(file);
+
+        // The store moved past the checkpoint; resuming from it would
+        // ingest blocks below the head
+        let adapter = MockEthAdapter::with_chain("p0", 20);
+        ingestor
+            .resume_from_checkpoint(&logger, &adapter)
+            .await
+            .unwrap();
+        assert!(store.upserted_numbers().is_empty());
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(30)));
+
+        ingestor.save_checkpoint(&logger, None).await;
+        let file = ingestor.checkpoint.as_ref().unwrap();
+        assert_eq!(file.load().await.unwrap().unwrap().head, Some(test_ptr(30)));
+
+        // A head that went backwards in the store is not recorded
+        *store.head.lock().unwrap() = Some(test_ptr(10));
+        ingestor.save_checkpoint(&logger, None).await;
+        assert_eq!(file.load().await.unwrap().unwrap().head, Some(test_ptr(30)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_linkage_rejects_inconsistent_parents() {
+        let logger = discard_logger();
+        // Block 15 points to block 14, but the provider returns a block with
+        // number 11 for that hash
+        let mut blocks: Vec<_> = (0..=20).filter(|n| *n != 14).map(test_block).collect();
+        blocks.push(
+            create_minimal_block_for_test(11, test_ptr(14).hash.as_b256()).map_header(
+                |mut header| {
+                    header.inner.parent_hash = test_ptr(10).hash.as_b256();
+                    header
+                },
+            ),
+        );
+        let adapter = Arc::new(MockEthAdapter {
+            provider_name: "p0".to_string(),
+            reachable: true,
+            blocks,
+            unresponsive: false,
+        });
+
+        let store = Arc::new(MockChainStore {
+            require_ancestors: true,
+            ..Default::default()
+        });
+        *store.head.lock().unwrap() = Some(test_ptr(5));
+        let mut ingestor = test_ingestor(store.clone());
+        ingestor.verify_linkage = true;
+
+        let (latest, missing) = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(20).hash, None)
+            .await
+            .unwrap();
+        let res = ingestor
+            .ingest_ancestors(&logger, &adapter, missing, Some(latest))
+            .await;
+        match res {
+            Err(IngestorError::BlockLinkageMismatched {
+                expected_hash,
+                expected_number,
+                actual,
+            }) => {
+                assert_eq!(expected_hash, test_ptr(14).hash.as_b256());
+                assert_eq!(expected_number, Some(14));
+                assert_eq!(actual.number, 11);
+            }
+            res => panic!("expected a linkage error, got {:?}", res),
+        }
+        // The inconsistent block was not stored
+        assert_eq!(store.upserted_numbers(), vec![20, 19, 18, 17, 16, 15]);
+        assert_eq!(*store.head.lock().unwrap(), Some(test_ptr(5)));
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_block_data_is_not_cached() {
+        let logger = discard_logger();
+        let store = Arc::new(MockChainStore::default());
+        let ingestor = test_ingestor(store.clone());
+
+        let adapter = MockEthAdapter::with_chain("p0", 20);
+        let hash = test_ptr(20).hash;
+        ingestor
+            .ingest_block(&logger, &adapter, &hash, None)
+            .await
+            .unwrap();
+
+        // Ingesting the same data again is fine
+        ingestor
+            .ingest_block(&logger, &adapter, &hash, None)
+            .await
+            .unwrap();
+
+        // Another provider claims that the block has a different parent
+        let conflicting = test_block(20).map_header(|mut header| {
+            header.inner.parent_hash = test_ptr(5).hash.as_b256();
+            header
+        });
+        let adapter = Arc::new(MockEthAdapter {
+            provider_name: "p1".to_string(),
+            reachable: true,
+            blocks: vec![conflicting],
+            unresponsive: false,
+        });
+        let res = ingestor.ingest_block(&logger, &adapter, &hash, None).await;
+        assert!(matches!(res, Err(IngestorError::BlockDataMismatched(h)) if h == hash.as_b256()));
+        assert_eq!(store.upserted_numbers(), vec![20, 20]);
+        assert_eq!(store.parents.lock().unwrap()[&hash], test_ptr(19));
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_must_increase() {
+        let logger = discard_logger();
+        let with_timestamp = |number, timestamp| {
+            test_block(number).map_header(|mut header| {
+                header.inner.timestamp = timestamp;
+                header
+            })
+        };
+        // Block 20 claims to be older than its parent
+        let adapter = Arc::new(MockEthAdapter {
+            provider_name: "p0".to_string(),
+            reachable: true,
+            blocks: vec![with_timestamp(19, 100), with_timestamp(20, 90)],
+            unresponsive: false,
+        });
+
+        let store = Arc::new(MockChainStore::default());
+        let mut ingestor = test_ingestor(store.clone());
+        ingestor.timestamp_check = TimestampCheck::Reject;
+
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(19).hash, None)
+            .await
+            .unwrap();
+        let res = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(20).hash, None)
+            .await;
+        match res {
+            Err(IngestorError::BlockTimestampNotIncreasing {
+                block,
+                timestamp,
+                parent_timestamp,
+            }) => {
+                assert_eq!(block, test_ptr(20));
+                assert_eq!(timestamp, BlockTime::since_epoch(90, 0));
+                assert_eq!(parent_timestamp, BlockTime::since_epoch(100, 0));
+            }
+            res => panic!("expected a timestamp error, got {:?}", res),
+        }
+        assert_eq!(store.upserted_numbers(), vec![19]);
+
+        // With the default, the block is stored anyway
+        ingestor.timestamp_check = TimestampCheck::Warn;
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(20).hash, None)
+            .await
+            .unwrap();
+        assert_eq!(store.upserted_numbers(), vec![19, 20]);
+    }
+
+    #[tokio::test]
+    async fn test_future_timestamp_is_caught() {
+        let logger = discard_logger();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let with_timestamp = |number, timestamp| {
+            test_block(number).map_header(|mut header| {
+                header.inner.timestamp = timestamp;
+                header
+            })
+        };
+        // Block 1 is slightly ahead of our clock, block 2 is a day ahead
+        let adapter = Arc::new(MockEthAdapter {
+            provider_name: "p0".to_string(),
+            reachable: true,
+            blocks: vec![
+                with_timestamp(0, now - 10),
+                with_timestamp(1, now + 10),
+                with_timestamp(2, now + 86_400),
+            ],
+            unresponsive: false,
+        });
+
+        let store = Arc::new(MockChainStore::default());
+        let mut ingestor = test_ingestor(store.clone());
+        ingestor.future_timestamp_check = TimestampCheck::Reject;
+        ingestor.future_timestamp_tolerance = Duration::from_secs(300);
+
+        for number in [0, 1] {
+            ingestor
+                .ingest_block(&logger, &adapter, &test_ptr(number).hash, None)
+                .await
+                .unwrap();
+        }
+        let res = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(2).hash, None)
+            .await;
+        match res {
+            Err(IngestorError::BlockTimestampInFuture {
+                block,
+                timestamp,
+                tolerance,
+                ..
+            }) => {
+                assert_eq!(block, test_ptr(2));
+                assert_eq!(timestamp, BlockTime::since_epoch(now as i64 + 86_400, 0));
+                assert_eq!(tolerance, Duration::from_secs(300));
+            }
+            res => panic!("expected a future timestamp error, got {:?}", res),
+        }
+        assert_eq!(store.upserted_numbers(), vec![0, 1]);
+
+        // With the default, the block is stored anyway
+        ingestor.future_timestamp_check = TimestampCheck::Warn;
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(2).hash, None)
+            .await
+            .unwrap();
+        assert_eq!(store.upserted_numbers(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_receipts_are_only_loaded_when_needed() {
+        let logger = discard_logger();
+        let adapter = MockEthAdapter::with_chain("p0", 3);
+        let store = Arc::new(MockChainStore::default());
+        let demand = ReceiptDemand::default();
+        let ingestor = test_ingestor(store.clone()).with_receipt_demand(demand.clone());
+
+        // No subgraph needs receipts
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(1).hash, None)
+            .await
+            .unwrap();
+
+        // A subgraph with event handlers starts
+        let guard = demand.register();
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(2).hash, None)
+            .await
+            .unwrap();
+
+        // ... and stops again
+        drop(guard);
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(3).hash, None)
+            .await
+            .unwrap();
+
+        let cached = store
+            .clone()
+            .blocks(vec![test_ptr(1).hash, test_ptr(2).hash, test_ptr(3).hash])
+            .await
+            .unwrap();
+        assert!(matches!(cached[0], CachedBlock::Light(_)));
+        assert!(matches!(cached[1], CachedBlock::Full(_)));
+        assert!(matches!(cached[2], CachedBlock::Light(_)));
+    }
+
+    #[tokio::test]
+    async fn test_transformed_block_is_stored() {
+        let logger = discard_logger();
+        let adapter = MockEthAdapter::with_chain("p0", 4);
+        let store = Arc::new(MockChainStore::default());
+        let set_gas_used = |gas_used: u64| -> BlockTransform {
+            let with_gas_used = move |block: &LightEthereumBlock| {
+                let inner = block.inner().clone().map_header(|mut header| {
+                    header.inner.gas_used = gas_used;
+                    header
+                });
+                Arc::new(LightEthereumBlock::new(inner))
+            };
+            Arc::new(move |block| match block {
+                BlockFinality::NonFinal(mut block) => {
+                    block.ethereum_block.block = with_gas_used(&block.ethereum_block.block);
+                    Ok(BlockFinality::NonFinal(block))
+                }
+                BlockFinality::Final(block) => Ok(BlockFinality::Final(with_gas_used(&block))),
+                block => Ok(block),
+            })
+        };
+
+        // A block with receipts
+        let ingestor = test_ingestor(store.clone()).with_block_transform(set_gas_used(42));
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(1).hash, None)
+            .await
+            .unwrap();
+        let cached = store.clone().blocks(vec![test_ptr(1).hash]).await.unwrap();
+        assert!(matches!(cached[0], CachedBlock::Full(_)));
+        assert_eq!(cached[0].light_block().inner().header.gas_used, 42);
+
+        // A block without receipts
+        let ingestor = test_ingestor(store.clone())
+            .with_receipt_demand(ReceiptDemand::default())
+            .with_block_transform(set_gas_used(43));
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(2).hash, None)
+            .await
+            .unwrap();
+        let cached = store.clone().blocks(vec![test_ptr(2).hash]).await.unwrap();
+        assert!(matches!(cached[0], CachedBlock::Light(_)));
+        assert_eq!(cached[0].light_block().inner().header.gas_used, 43);
+
+        // A transform that fails skips the block
+        let failing: BlockTransform = Arc::new(|_| Err(anyhow!("no data for block")));
+        let ingestor = test_ingestor(store.clone()).with_block_transform(failing);
+        ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(3).hash, None)
+            .await
+            .unwrap();
+
+        // A transform must not change which block it is
+        let renumber: BlockTransform = Arc::new(|block| match block {
+            BlockFinality::NonFinal(mut block) => {
+                let inner = block
+                    .ethereum_block
+                    .block
+                    .inner()
+                    .clone()
+                    .map_header(|mut header| {
+                        header.inner.number += 1;
+                        header
+                    });
+                block.ethereum_block.block = Arc::new(LightEthereumBlock::new(inner));
+                Ok(BlockFinality::NonFinal(block))
+            }
+            block => Ok(block),
+        });
+        let ingestor = test_ingestor(store.clone()).with_block_transform(renumber);
+        let res = ingestor
+            .ingest_block(&logger, &adapter, &test_ptr(4).hash, None)
+            .await;
+        assert!(res.is_err());
+
+        assert_eq!(store.upserted_numbers(), vec![1, 2]);
+    }
 }
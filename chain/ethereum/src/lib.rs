@@ -9,15 +9,19 @@ mod ethereum_adapter;
 mod ingestor;
 mod polling_block_stream;
 pub mod runtime;
+mod spot_check;
 mod transport;
 
 pub use self::capabilities::NodeCapabilities;
 pub use self::ethereum_adapter::EthereumAdapter;
+pub use self::ingestor::{BlockTransform, PollingBlockIngestor, PollingInterval};
 pub use self::runtime::RuntimeAdapter;
-pub use self::transport::{Compression, Transport};
+pub use self::transport::{
+    Compression, MethodFilter, MethodHeaders, REQUEST_ID_HEADER, RequestTracing, Transport,
+};
 pub use env::ENV_VARS;
 
-pub use buffered_call_cache::BufferedCallCache;
+pub use buffered_call_cache::{BufferedCallCache, CallCacheKey};
 
 // ETHDEP: These concrete types should probably not be exposed.
 pub use data_source::{
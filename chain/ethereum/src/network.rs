@@ -1,6 +1,7 @@
 use anyhow::{anyhow, bail};
 use async_trait::async_trait;
 use graph::blockchain::ChainIdentifier;
+use graph::blockchain::NodeCapabilities as _;
 use graph::components::network_provider::ChainName;
 use graph::components::network_provider::NetworkDetails;
 use graph::components::network_provider::ProviderManager;
@@ -150,7 +151,7 @@ impl EthereumNetworkAdapters {
     ) -> impl Iterator<Item = &'a EthereumNetworkAdapter> + 'a {
         let cheapest_sufficient_capability = input
             .iter()
-            .find(|adapter| &adapter.capabilities >= required_capabilities)
+            .find(|adapter| adapter.capabilities.satisfies(required_capabilities))
             .map(|adapter| &adapter.capabilities);
 
         input
@@ -335,11 +336,12 @@ mod tests {
     use std::sync::Arc;
 
     use crate::{
-        Compression, EthereumAdapter, EthereumAdapterTrait, ProviderEthRpcMetrics, Transport,
-        chain::ChainSettings,
+        Compression, EthereumAdapter, EthereumAdapterTrait, MethodFilter, MethodHeaders,
+        ProviderEthRpcMetrics, RequestTracing, Transport, chain::ChainSettings,
     };
 
     use super::{EthereumNetworkAdapter, EthereumNetworkAdapters, NodeCapabilities};
+    use graph::blockchain::NodeCapabilities as _;
 
     #[test]
     #[allow(clippy::neg_cmp_op_on_partial_ord)]
@@ -397,6 +399,35 @@ mod tests {
         assert!(full_traces >= full_traces);
     }
 
+    #[test]
+    fn ethereum_capabilities_satisfies() {
+        let caps = |archive, traces| NodeCapabilities { archive, traces };
+        let full = caps(false, false);
+        let archive = caps(true, false);
+        let traces = caps(false, true);
+        let archive_traces = caps(true, true);
+
+        // Satisfied
+        for provider in [&full, &archive, &traces, &archive_traces] {
+            assert!(provider.satisfies(&full));
+            assert_eq!(provider.missing(&full), None);
+        }
+        assert!(archive.satisfies(&archive));
+        assert!(archive_traces.satisfies(&archive));
+        assert!(traces.satisfies(&traces));
+        assert!(archive_traces.satisfies(&traces));
+        assert!(archive_traces.satisfies(&archive_traces));
+
+        // Not satisfied
+        assert!(!full.satisfies(&archive));
+        assert_eq!(full.missing(&archive), Some("archive"));
+        assert_eq!(full.missing(&traces), Some("traces"));
+        assert_eq!(traces.missing(&archive), Some("archive"));
+        assert_eq!(archive.missing(&traces), Some("traces"));
+        assert_eq!(archive.missing(&archive_traces), Some("traces"));
+        assert_eq!(traces.missing(&archive_traces), Some("archive"));
+    }
+
     #[graph::test]
     async fn adapter_selector_selects_eth_call() {
         let metrics = Arc::new(EndpointMetrics::mock());
@@ -409,6 +440,9 @@ mod tests {
             "",
             false,
             Compression::None,
+            RequestTracing::none(),
+            MethodFilter::All,
+            MethodHeaders::default(),
         );
         let provider_metrics = Arc::new(ProviderEthRpcMetrics::new(mock_registry.clone()));
 
@@ -518,6 +552,9 @@ mod tests {
             "",
             false,
             Compression::None,
+            RequestTracing::none(),
+            MethodFilter::All,
+            MethodHeaders::default(),
         );
         let provider_metrics = Arc::new(ProviderEthRpcMetrics::new(mock_registry.clone()));
 
@@ -593,6 +630,9 @@ mod tests {
             "",
             false,
             Compression::None,
+            RequestTracing::none(),
+            MethodFilter::All,
+            MethodHeaders::default(),
         );
         let provider_metrics = Arc::new(ProviderEthRpcMetrics::new(mock_registry.clone()));
 
@@ -661,6 +701,9 @@ mod tests {
             "",
             false,
             Compression::None,
+            RequestTracing::none(),
+            MethodFilter::All,
+            MethodHeaders::default(),
         );
         let provider_metrics = Arc::new(ProviderEthRpcMetrics::new(mock_registry.clone()));
 
@@ -951,6 +994,9 @@ mod tests {
             "",
             false,
             Compression::None,
+            RequestTracing::none(),
+            MethodFilter::All,
+            MethodHeaders::default(),
         );
 
         Arc::new(
@@ -13,12 +13,12 @@ use graph::blockchain::block_stream::{
 use graph::blockchain::{Block, BlockPtr, TriggerFilterWrapper};
 use graph::futures03::{Future, FutureExt, stream::Stream};
 use graph::prelude::{BLOCK_NUMBER_MAX, DeploymentHash};
-use graph::slog::{Logger, info, trace, warn};
+use graph::slog::{Logger, debug, info, trace, warn};
 
 use graph::components::store::BlockNumber;
 use graph::data::subgraph::UnifiedMappingApiVersion;
 
-use crate::Chain;
+use crate::{Chain, ENV_VARS};
 
 // A high number here forces a slow start.
 const STARTING_PREVIOUS_TRIGGERS_PER_BLOCK: f64 = 1_000_000.0;
@@ -86,6 +86,8 @@ struct PollingBlockStreamContext {
     // Not a BlockNumber, but the difference between two block numbers
     max_block_range_size: BlockNumber,
     target_triggers_per_block_range: u64,
+    // Not a BlockNumber, but the difference between two block numbers
+    fast_forward_range: BlockNumber,
     unified_api_version: UnifiedMappingApiVersion,
     current_block: Option<BlockPtr>,
 }
@@ -103,6 +105,7 @@ impl Clone for PollingBlockStreamContext {
             previous_block_range_size: self.previous_block_range_size,
             max_block_range_size: self.max_block_range_size,
             target_triggers_per_block_range: self.target_triggers_per_block_range,
+            fast_forward_range: self.fast_forward_range,
             unified_api_version: self.unified_api_version.clone(),
             current_block: self.current_block.clone(),
         }
@@ -156,6 +159,7 @@ impl PollingBlockStream {
                 previous_block_range_size: 1,
                 max_block_range_size,
                 target_triggers_per_block_range,
+                fast_forward_range: ENV_VARS.fast_forward_range,
                 unified_api_version,
             },
         }
@@ -324,6 +328,36 @@ impl PollingBlockStreamContext {
             // reorg threshold due to race conditions.
             let to_limit = cmp::min(head_ptr.number - reorg_threshold, next_start_block - 1);
 
+            // On sparse chains, most block ranges contain no triggers. If
+            // the last range was empty, look further ahead for the next
+            // block with triggers and start scanning there. Of the blocks
+            // we skip, we only emit the last one, without triggers, as a
+            // marker of our progress
+            let (scan_from, marker) = if self.can_fast_forward() {
+                let ahead = cmp::min(from + self.fast_forward_range - 1, to_limit);
+                let next = self
+                    .adapter
+                    .next_block_with_triggers(from, ahead, &self.filter)
+                    .await?;
+                let scan_from = fast_forward_from(from, ahead, next);
+                let marker = if scan_from > from {
+                    debug!(
+                        ctx.logger,
+                        "Skipping blocks [{}, {}] without triggers",
+                        from,
+                        scan_from - 1
+                    );
+                    self.adapter
+                        .empty_block(&self.logger, scan_from - 1)
+                        .await?
+                } else {
+                    None
+                };
+                (scan_from, marker)
+            } else {
+                (from, None)
+            };
+
             // Calculate the range size according to the target number of triggers,
             // respecting the global maximum and also not increasing too
             // drastically from the previous block range size.
@@ -347,18 +381,18 @@ impl PollingBlockStreamContext {
                     .max(1.0)
                     .min(range_size_upper_limit as f64) as BlockNumber
             };
-            let to = cmp::min(from + target_range_size - 1, to_limit);
+            let to = cmp::min(scan_from + target_range_size - 1, to_limit);
 
             info!(
                 ctx.logger,
-                "Scanning blocks [{}, {}]", from, to;
+                "Scanning blocks [{}, {}]", scan_from, to;
                 "target_range_size" => target_range_size
             );
 
             // Update with actually scanned range, to account for any skipped null blocks.
             let (blocks, to) = self
                 .adapter
-                .scan_triggers(&self.logger, from, to, &self.filter)
+                .scan_triggers(&self.logger, scan_from, to, &self.filter)
                 .await?;
             let range_size = to - from + 1;
 
@@ -372,10 +406,12 @@ impl PollingBlockStreamContext {
 
             info!(
                 ctx.logger,
-                "Scanned blocks [{}, {}]", from, to;
+                "Scanned blocks [{}, {}]", scan_from, to;
                 "range_size" => range_size
             );
 
+            let blocks = marker.into_iter().chain(blocks).collect();
+
             Ok(ReconciliationStep::ProcessDescendantBlocks(
                 blocks, range_size,
             ))
@@ -462,6 +498,16 @@ impl PollingBlockStreamContext {
         }
     }
 
+    /// Whether we can skip blocks without triggers. We only do that after
+    /// a range without triggers, and when every block with triggers can be
+    /// found by asking the chain for logs
+    fn can_fast_forward(&self) -> bool {
+        self.fast_forward_range > 0
+            && self.previous_triggers_per_block == 0.0
+            && self.filter.subgraph_filter.is_empty()
+            && self.filter.chain_filter.only_logs()
+    }
+
     async fn parent_ptr(&self, block_ptr: &BlockPtr, reason: &str) -> Result<BlockPtr, Error> {
         let ptr =
             self.adapter.parent_ptr(block_ptr).await?.ok_or_else(|| {
@@ -472,6 +518,13 @@ impl PollingBlockStreamContext {
     }
 }
 
+/// The block at which to start scanning after looking for the first block
+/// with triggers in `[from, to]` found `next`. If there is no such block,
+/// we still scan `to` so that the stream moves past the range
+fn fast_forward_from(from: BlockNumber, to: BlockNumber, next: Option<BlockNumber>) -> BlockNumber {
+    next.map_or(to, |next| next.clamp(from, to))
+}
+
 impl BlockStream<Chain> for PollingBlockStream {
     fn buffer_size_hint(&self) -> usize {
         BUFFERED_BLOCK_STREAM_SIZE
@@ -624,3 +677,201 @@ impl Stream for PollingBlockStream {
         result.map_err(BlockStreamError::from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use graph::blockchain::block_stream::TriggersAdapter;
+    use graph::blockchain::{BlockHash, BlockTime, ExtendedBlockPtr};
+    use graph::log::discard;
+    use graph::prelude::alloy::primitives::B256;
+
+    use super::*;
+    use crate::adapter::test_log_trigger_filter;
+    use crate::chain::BlockFinality;
+
+    fn hash(number: BlockNumber) -> BlockHash {
+        B256::left_padding_from(&number.to_be_bytes()).into()
+    }
+
+    fn block(number: BlockNumber) -> BlockFinality {
+        BlockFinality::Ptr(Arc::new(ExtendedBlockPtr::new(
+            hash(number),
+            number,
+            hash(number - 1),
+            BlockTime::NONE,
+        )))
+    }
+
+    /// A chain whose only block with triggers is `next`. Records the
+    /// ranges that were searched for triggers and that were scanned
+    struct SparseChain {
+        head: BlockNumber,
+        next: BlockNumber,
+        searched: Mutex<Vec<(BlockNumber, BlockNumber)>>,
+        scanned: Mutex<Vec<(BlockNumber, BlockNumber)>>,
+    }
+
+    #[async_trait]
+    impl TriggersAdapter<Chain> for SparseChain {
+        async fn ancestor_block(
+            &self,
+            _ptr: BlockPtr,
+            _offset: BlockNumber,
+            _root: Option<BlockHash>,
+        ) -> Result<Option<BlockFinality>, Error> {
+            Ok(None)
+        }
+
+        async fn scan_triggers(
+            &self,
+            from: BlockNumber,
+            to: BlockNumber,
+            _filter: &crate::TriggerFilter,
+        ) -> Result<(Vec<BlockWithTriggers<Chain>>, BlockNumber), Error> {
+            self.scanned.lock().unwrap().push((from, to));
+            // Like the real adapter, always return the last block of the
+            // range. Its triggers don't matter here
+            let blocks = vec![BlockWithTriggers::new(block(to), vec![], &discard())];
+            Ok((blocks, to))
+        }
+
+        async fn next_block_with_triggers(
+            &self,
+            from: BlockNumber,
+            to: BlockNumber,
+            _filter: &crate::TriggerFilter,
+        ) -> Result<Option<BlockNumber>, Error> {
+            self.searched.lock().unwrap().push((from, to));
+            Ok(Some(self.next).filter(|next| (from..=to).contains(next)))
+        }
+
+        async fn triggers_in_block(
+            &self,
+            logger: &Logger,
+            block: BlockFinality,
+            _filter: &crate::TriggerFilter,
+        ) -> Result<BlockWithTriggers<Chain>, Error> {
+            Ok(BlockWithTriggers::new(block, vec![], logger))
+        }
+
+        async fn is_on_main_chain(&self, _ptr: BlockPtr) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        async fn parent_ptr(&self, block: &BlockPtr) -> Result<Option<BlockPtr>, Error> {
+            Ok(Some(BlockPtr::new(
+                hash(block.number - 1),
+                block.number - 1,
+            )))
+        }
+
+        async fn chain_head_ptr(&self) -> Result<Option<BlockPtr>, Error> {
+            Ok(Some(BlockPtr::new(hash(self.head), self.head)))
+        }
+
+        async fn load_block_ptrs_by_numbers(
+            &self,
+            _logger: Logger,
+            block_numbers: BTreeSet<BlockNumber>,
+        ) -> Result<Vec<BlockFinality>, Error> {
+            Ok(block_numbers.into_iter().map(block).collect())
+        }
+    }
+
+    #[graph::test]
+    async fn stream_skips_ranges_without_triggers() {
+        let chain = Arc::new(SparseChain {
+            head: 10_000,
+            next: 3_000,
+            searched: Mutex::new(vec![]),
+            scanned: Mutex::new(vec![]),
+        });
+        let ctx = PollingBlockStreamContext {
+            adapter: Arc::new(TriggersAdapterWrapper::new(chain.clone(), vec![])),
+            subgraph_id: DeploymentHash::new("sparse").unwrap(),
+            reorg_threshold: 10,
+            filter: Arc::new(TriggerFilterWrapper::new(test_log_trigger_filter(), vec![])),
+            start_blocks: vec![],
+            logger: discard(),
+            // The last range had no triggers
+            previous_triggers_per_block: 0.0,
+            previous_block_range_size: 100,
+            max_block_range_size: 100,
+            target_triggers_per_block_range: 1_000,
+            fast_forward_range: 5_000,
+            unified_api_version: Default::default(),
+            current_block: Some(BlockPtr::new(hash(99), 99)),
+        };
+
+        let ReconciliationStep::ProcessDescendantBlocks(blocks, range_size) =
+            ctx.get_next_step().await.unwrap()
+        else {
+            panic!("expected the stream to move forward");
+        };
+
+        // The lookahead is not limited by the maximum block range, and
+        // scanning starts at the block with triggers
+        assert_eq!(vec![(100, 5_099)], *chain.searched.lock().unwrap());
+        assert_eq!(vec![(3_000, 3_099)], *chain.scanned.lock().unwrap());
+
+        // The last skipped block marks the progress through the skipped
+        // blocks, followed by the scanned blocks
+        let numbers: Vec<_> = blocks.iter().map(|block| block.block.number()).collect();
+        assert_eq!(vec![2_999, 3_099], numbers);
+        assert!(blocks[0].trigger_data.is_empty());
+        assert_eq!(3_000, range_size);
+    }
+
+    #[graph::test]
+    async fn stream_does_not_skip_after_ranges_with_triggers() {
+        let chain = Arc::new(SparseChain {
+            head: 10_000,
+            next: 3_000,
+            searched: Mutex::new(vec![]),
+            scanned: Mutex::new(vec![]),
+        });
+        let ctx = PollingBlockStreamContext {
+            adapter: Arc::new(TriggersAdapterWrapper::new(chain.clone(), vec![])),
+            subgraph_id: DeploymentHash::new("sparse").unwrap(),
+            reorg_threshold: 10,
+            filter: Arc::new(TriggerFilterWrapper::new(test_log_trigger_filter(), vec![])),
+            start_blocks: vec![],
+            logger: discard(),
+            previous_triggers_per_block: 1.0,
+            previous_block_range_size: 100,
+            max_block_range_size: 100,
+            target_triggers_per_block_range: 1_000,
+            fast_forward_range: 5_000,
+            unified_api_version: Default::default(),
+            current_block: Some(BlockPtr::new(hash(99), 99)),
+        };
+
+        let ReconciliationStep::ProcessDescendantBlocks(blocks, _) =
+            ctx.get_next_step().await.unwrap()
+        else {
+            panic!("expected the stream to move forward");
+        };
+
+        assert!(chain.searched.lock().unwrap().is_empty());
+        assert_eq!(vec![(100, 199)], *chain.scanned.lock().unwrap());
+        assert_eq!(1, blocks.len());
+    }
+
+    #[test]
+    fn fast_forward_skips_to_next_block_with_triggers() {
+        // The next block with triggers is where we start scanning
+        assert_eq!(250, fast_forward_from(100, 1_099, Some(250)));
+        assert_eq!(100, fast_forward_from(100, 1_099, Some(100)));
+
+        // Without triggers in the range, only its last block is scanned
+        assert_eq!(1_099, fast_forward_from(100, 1_099, None));
+
+        // Never skip past the range that was searched
+        assert_eq!(1_099, fast_forward_from(100, 1_099, Some(5_000)));
+        assert_eq!(100, fast_forward_from(100, 1_099, Some(7)));
+    }
+}
@@ -1,4 +1,6 @@
-use super::runtime_adapter::UnresolvedContractCall;
+use super::runtime_adapter::{
+    ContractCallAtBlock, EthereumFeeHistory, FeeHistoryCall, GetLogsCall, UnresolvedContractCall,
+};
 use crate::trigger::{
     EthereumBlockData, EthereumCallData, EthereumEventData, EthereumTransactionData,
 };
@@ -19,7 +21,7 @@ use graph::{
 use graph_runtime_derive::AscType;
 use graph_runtime_wasm::asc_abi::class::{
     Array, AscAddress, AscBigInt, AscEnum, AscH160, AscString, AscWrapped, EthereumValueKind,
-    Uint8Array,
+    TypedArray, Uint8Array,
 };
 use semver::Version;
 
@@ -89,10 +91,51 @@ impl ToAscObj<AscTopicArray> for &[alloy::primitives::B256] {
     }
 }
 
+impl FromAscObj<AscTopicArray> for Vec<alloy::primitives::B256> {
+    fn from_asc_obj<H: AscHeap + ?Sized>(
+        array: AscTopicArray,
+        heap: &H,
+        gas: &GasCounter,
+        depth: usize,
+    ) -> Result<Self, DeterministicHostError> {
+        Vec::from_asc_obj(array.0, heap, gas, depth)
+    }
+}
+
 impl AscIndexId for AscTopicArray {
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::ArrayB256;
 }
 
+pub struct AscTopicArrayArray(Array<AscPtr<AscTopicArray>>);
+
+impl AscType for AscTopicArrayArray {
+    fn to_asc_bytes(&self) -> Result<Vec<u8>, DeterministicHostError> {
+        self.0.to_asc_bytes()
+    }
+
+    fn from_asc_bytes(
+        asc_obj: &[u8],
+        api_version: &Version,
+    ) -> Result<Self, DeterministicHostError> {
+        Ok(Self(Array::from_asc_bytes(asc_obj, api_version)?))
+    }
+}
+
+impl FromAscObj<AscTopicArrayArray> for Vec<Vec<alloy::primitives::B256>> {
+    fn from_asc_obj<H: AscHeap + ?Sized>(
+        array: AscTopicArrayArray,
+        heap: &H,
+        gas: &GasCounter,
+        depth: usize,
+    ) -> Result<Self, DeterministicHostError> {
+        Vec::from_asc_obj(array.0, heap, gas, depth)
+    }
+}
+
+impl AscIndexId for AscTopicArrayArray {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::ArrayArrayB256;
+}
+
 pub struct AscLogArray(Array<AscPtr<AscEthereumLog>>);
 
 impl AscType for AscLogArray {
@@ -128,6 +171,74 @@ impl AscIndexId for AscLogArray {
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::ArrayLog;
 }
 
+pub struct AscBigIntArray(Array<AscPtr<AscBigInt>>);
+
+impl AscType for AscBigIntArray {
+    fn to_asc_bytes(&self) -> Result<Vec<u8>, DeterministicHostError> {
+        self.0.to_asc_bytes()
+    }
+
+    fn from_asc_bytes(
+        asc_obj: &[u8],
+        api_version: &Version,
+    ) -> Result<Self, DeterministicHostError> {
+        Ok(Self(Array::from_asc_bytes(asc_obj, api_version)?))
+    }
+}
+
+#[async_trait]
+impl ToAscObj<AscBigIntArray> for &[BigInt] {
+    async fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscBigIntArray, HostExportError> {
+        let mut values = Vec::with_capacity(self.len());
+        for value in *self {
+            values.push(asc_new(heap, value, gas).await?);
+        }
+        Ok(AscBigIntArray(Array::new(&values, heap, gas).await?))
+    }
+}
+
+impl AscIndexId for AscBigIntArray {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::ArrayBigInt;
+}
+
+pub struct AscBigIntArrayArray(Array<AscPtr<AscBigIntArray>>);
+
+impl AscType for AscBigIntArrayArray {
+    fn to_asc_bytes(&self) -> Result<Vec<u8>, DeterministicHostError> {
+        self.0.to_asc_bytes()
+    }
+
+    fn from_asc_bytes(
+        asc_obj: &[u8],
+        api_version: &Version,
+    ) -> Result<Self, DeterministicHostError> {
+        Ok(Self(Array::from_asc_bytes(asc_obj, api_version)?))
+    }
+}
+
+#[async_trait]
+impl ToAscObj<AscBigIntArrayArray> for &[Vec<BigInt>] {
+    async fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscBigIntArrayArray, HostExportError> {
+        let mut arrays = Vec::with_capacity(self.len());
+        for values in *self {
+            arrays.push(asc_new(heap, &values.as_slice(), gas).await?);
+        }
+        Ok(AscBigIntArrayArray(Array::new(&arrays, heap, gas).await?))
+    }
+}
+
+impl AscIndexId for AscBigIntArrayArray {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::ArrayArrayBigInt;
+}
+
 #[repr(C)]
 #[derive(AscType)]
 #[allow(non_camel_case_types)]
@@ -143,6 +254,119 @@ impl AscIndexId for AscUnresolvedContractCall_0_0_4 {
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::SmartContractCall;
 }
 
+#[repr(C)]
+#[derive(AscType)]
+pub struct AscUnresolvedContractCallAtBlock {
+    pub call: AscPtr<AscUnresolvedContractCall_0_0_4>,
+    pub block: AscPtr<AscBigInt>,
+}
+
+impl AscIndexId for AscUnresolvedContractCallAtBlock {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::SmartContractCallAtBlock;
+}
+
+impl FromAscObj<AscUnresolvedContractCallAtBlock> for ContractCallAtBlock {
+    fn from_asc_obj<H: AscHeap + ?Sized>(
+        asc_call: AscUnresolvedContractCallAtBlock,
+        heap: &H,
+        gas: &GasCounter,
+        depth: usize,
+    ) -> Result<Self, DeterministicHostError> {
+        Ok(ContractCallAtBlock {
+            call: asc_get::<_, AscUnresolvedContractCall_0_0_4, _>(
+                heap,
+                asc_call.call,
+                gas,
+                depth,
+            )?,
+            block: asc_get(heap, asc_call.block, gas, depth)?,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(AscType)]
+pub struct AscFeeHistoryCall {
+    pub block_count: AscPtr<AscBigInt>,
+    pub newest_block: AscPtr<AscBigInt>,
+    pub reward_percentiles: AscPtr<TypedArray<f64>>,
+}
+
+impl AscIndexId for AscFeeHistoryCall {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::FeeHistoryCall;
+}
+
+impl FromAscObj<AscFeeHistoryCall> for FeeHistoryCall {
+    fn from_asc_obj<H: AscHeap + ?Sized>(
+        asc_call: AscFeeHistoryCall,
+        heap: &H,
+        gas: &GasCounter,
+        depth: usize,
+    ) -> Result<Self, DeterministicHostError> {
+        Ok(FeeHistoryCall {
+            block_count: asc_get(heap, asc_call.block_count, gas, depth)?,
+            newest_block: asc_get(heap, asc_call.newest_block, gas, depth)?,
+            reward_percentiles: asc_get(heap, asc_call.reward_percentiles, gas, depth)?,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(AscType)]
+pub struct AscGetLogsCall {
+    pub address: AscPtr<AscAddress>,
+    pub topics: AscPtr<AscTopicArrayArray>,
+    pub block_count: AscPtr<AscBigInt>,
+}
+
+impl AscIndexId for AscGetLogsCall {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::GetLogsCall;
+}
+
+impl FromAscObj<AscGetLogsCall> for GetLogsCall {
+    fn from_asc_obj<H: AscHeap + ?Sized>(
+        asc_call: AscGetLogsCall,
+        heap: &H,
+        gas: &GasCounter,
+        depth: usize,
+    ) -> Result<Self, DeterministicHostError> {
+        Ok(GetLogsCall {
+            address: asc_get(heap, asc_call.address, gas, depth)?,
+            topics: asc_get(heap, asc_call.topics, gas, depth)?,
+            block_count: asc_get(heap, asc_call.block_count, gas, depth)?,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(AscType)]
+pub(crate) struct AscEthereumFeeHistory {
+    pub oldest_block: AscPtr<AscBigInt>,
+    pub base_fee_per_gas: AscPtr<AscBigIntArray>,
+    pub gas_used_ratio: AscPtr<TypedArray<f64>>,
+    pub reward: AscPtr<AscBigIntArrayArray>,
+}
+
+impl AscIndexId for AscEthereumFeeHistory {
+    const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::FeeHistory;
+}
+
+#[async_trait]
+impl ToAscObj<AscEthereumFeeHistory> for EthereumFeeHistory {
+    async fn to_asc_obj<H: AscHeap + ?Sized>(
+        &self,
+        heap: &mut H,
+        gas: &GasCounter,
+    ) -> Result<AscEthereumFeeHistory, HostExportError> {
+        Ok(AscEthereumFeeHistory {
+            oldest_block: asc_new(heap, &self.oldest_block, gas).await?,
+            base_fee_per_gas: asc_new(heap, &self.base_fee_per_gas.as_slice(), gas).await?,
+            gas_used_ratio: asc_new(heap, self.gas_used_ratio.as_slice(), gas).await?,
+            reward: asc_new(heap, &self.reward.as_slice(), gas).await?,
+        })
+    }
+}
+
 impl FromAscObj<AscUnresolvedContractCall_0_0_4> for UnresolvedContractCall {
     fn from_asc_obj<H: AscHeap + ?Sized>(
         asc_call: AscUnresolvedContractCall_0_0_4,
@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Instant};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use crate::adapter::EthereumRpcError;
 use crate::{
@@ -13,24 +13,37 @@ use graph::blockchain::ChainIdentifier;
 use graph::components::subgraph::HostMetrics;
 use graph::data::store::ethereum::call;
 use graph::data::store::scalar::BigInt;
-use graph::data::subgraph::{API_VERSION_0_0_4, API_VERSION_0_0_9};
+use graph::data::subgraph::{API_VERSION_0_0_4, API_VERSION_0_0_9, API_VERSION_0_0_10};
 use graph::data_source;
 use graph::data_source::common::{ContractCall, MappingABI};
-use graph::runtime::gas::Gas;
+use graph::runtime::gas::{self, Gas, GasOp, complexity};
 use graph::runtime::{AscIndexId, IndexForAscTypeId};
 use graph::slog::debug;
 use graph::{
     blockchain::{self, BlockPtr, HostFnCtx},
     cheap_clone::CheapClone,
-    futures03::FutureExt,
-    prelude::{EthereumCallCache, alloy::primitives::Address},
+    futures03::{FutureExt, TryStreamExt},
+    prelude::{
+        BlockNumber, ChainStore, EthereumCallCache,
+        alloy::{
+            dyn_abi::TypedData,
+            primitives::{Address, B256},
+            rpc::types::{FeeHistory, Log},
+        },
+        serde_json,
+    },
     runtime::{AscPtr, HostExportError, asc_get, asc_new},
     slog::Logger,
 };
-use graph_runtime_wasm::asc_abi::class::{AscBigInt, AscEnumArray, AscWrapped, EthereumValueKind};
+use graph_runtime_wasm::asc_abi::class::{
+    AscBigInt, AscEnumArray, AscWrapped, EthereumValueKind, Uint8Array,
+};
 use itertools::Itertools;
 
-use super::abi::{AscUnresolvedContractCall, AscUnresolvedContractCall_0_0_4};
+use super::abi::{
+    AscEthereumFeeHistory, AscFeeHistoryCall, AscGetLogsCall, AscLogArray,
+    AscUnresolvedContractCall, AscUnresolvedContractCall_0_0_4, AscUnresolvedContractCallAtBlock,
+};
 
 /// Gas limit for `eth_call`. The value of 50_000_000 is a protocol-wide parameter so this
 /// should be changed only for debugging purposes and never on an indexer in the network. This
@@ -58,10 +71,40 @@ pub const ETH_GET_BALANCE: Gas = Gas::new(5_000_000_000);
 // TODO: Determine the appropriate gas cost for `ETH_HAS_CODE`, initially aligned with `ETHEREUM_CALL`.
 pub const ETH_HAS_CODE: Gas = Gas::new(5_000_000_000);
 
+// TODO: Determine the appropriate gas cost for `ETH_FEE_HISTORY`, initially aligned with `ETHEREUM_CALL`.
+pub const ETH_FEE_HISTORY: Gas = Gas::new(5_000_000_000);
+
+/// The most blocks that `ethereum.feeHistory` returns fees for. This is the
+/// limit that Geth and most other clients impose on `eth_feeHistory`
+const MAX_FEE_HISTORY_BLOCKS: u64 = 1024;
+
+/// How many blocks `newestBlock` for `ethereum.feeHistory` has to be behind
+/// the block that is being processed. Fees are looked up by block number,
+/// which is only safe for final blocks. This must not depend on the
+/// configuration of the node since it decides which calls fail
+const FEE_HISTORY_FINALITY_DEPTH: BlockNumber = 250;
+
+// TODO: Determine the appropriate gas cost for `ETH_GET_LOGS`, initially aligned with `ETHEREUM_CALL`.
+pub const ETH_GET_LOGS: Gas = Gas::new(5_000_000_000);
+
+/// The most blocks that `ethereum.getLogs` searches for logs
+const MAX_GET_LOGS_BLOCKS: u64 = 1000;
+
+/// The most logs that `ethereum.getLogs` returns. A search that finds more
+/// fails instead of returning some of them since which logs would be left
+/// out could differ between providers
+const MAX_GET_LOGS_RESULTS: usize = 1000;
+
+// Parsing the typed data dominates the cost of `ethereum.hashTypedData`,
+// so it is charged like parsing JSON.
+pub const ETH_HASH_TYPED_DATA: GasOp = gas::JSON_FROM_BYTES;
+
 pub struct RuntimeAdapter {
     pub eth_adapters: Arc<EthereumNetworkAdapters>,
     pub call_cache: Arc<dyn EthereumCallCache>,
     pub chain_identifier: Arc<ChainIdentifier>,
+    pub chain_store: Arc<dyn ChainStore>,
+    pub reorg_threshold: BlockNumber,
 }
 
 pub fn eth_call_gas(chain_identifier: &ChainIdentifier) -> Option<u32> {
@@ -83,8 +126,11 @@ impl blockchain::RuntimeAdapter<Chain> for RuntimeAdapter {
             abis: Arc<Vec<Arc<MappingABI>>>, // Use Arc to ensure `'static` lifetimes.
             archive: bool,
             call_cache: Arc<dyn EthereumCallCache>,
+            chain_store: Arc<dyn ChainStore>,
             eth_adapters: Arc<EthereumNetworkAdapters>,
             eth_call_gas: Option<u32>,
+            start_block: BlockNumber,
+            reorg_threshold: BlockNumber,
         ) -> Vec<HostFn> {
             vec![
                 HostFn {
@@ -118,6 +164,41 @@ impl blockchain::RuntimeAdapter<Chain> for RuntimeAdapter {
                         }
                     }),
                 },
+                HostFn {
+                    name: "ethereum.callAtBlock",
+                    func: Arc::new({
+                        let eth_adapters = eth_adapters.clone();
+                        let call_cache = call_cache.clone();
+                        let abis = abis.clone();
+                        move |ctx, wasm_ptr| {
+                            let eth_adapters = eth_adapters.cheap_clone();
+                            let call_cache = call_cache.cheap_clone();
+                            let chain_store = chain_store.cheap_clone();
+                            let abis = abis.cheap_clone();
+                            async move {
+                                let eth_adapter =
+                                    eth_adapters.call_or_cheapest(Some(&NodeCapabilities {
+                                        archive,
+                                        traces: false,
+                                    }))?;
+                                ethereum_call_at_block(
+                                    &eth_adapter,
+                                    call_cache.clone(),
+                                    chain_store,
+                                    ctx,
+                                    wasm_ptr,
+                                    &abis,
+                                    eth_call_gas,
+                                    start_block,
+                                    reorg_threshold,
+                                )
+                                .await
+                                .map(|ptr| ptr.wasm_ptr())
+                            }
+                            .boxed()
+                        }
+                    }),
+                },
                 HostFn {
                     name: "ethereum.getBalance",
                     func: Arc::new({
@@ -138,6 +219,65 @@ impl blockchain::RuntimeAdapter<Chain> for RuntimeAdapter {
                         }
                     }),
                 },
+                HostFn {
+                    name: "ethereum.hashTypedData",
+                    func: Arc::new(move |ctx, wasm_ptr| {
+                        async move {
+                            eth_hash_typed_data(ctx, wasm_ptr)
+                                .await
+                                .map(|ptr| ptr.wasm_ptr())
+                        }
+                        .boxed()
+                    }),
+                },
+                HostFn {
+                    name: "ethereum.feeHistory",
+                    func: Arc::new({
+                        let eth_adapters = eth_adapters.clone();
+                        move |ctx, wasm_ptr| {
+                            let eth_adapters = eth_adapters.cheap_clone();
+                            async move {
+                                let eth_adapter =
+                                    eth_adapters.unverified_cheapest_with(&NodeCapabilities {
+                                        archive,
+                                        traces: false,
+                                    })?;
+                                eth_fee_history(&eth_adapter, ctx, wasm_ptr)
+                                    .await
+                                    .map(|ptr| ptr.wasm_ptr())
+                            }
+                            .boxed()
+                        }
+                    }),
+                },
+                HostFn {
+                    name: "ethereum.getLogs",
+                    func: Arc::new({
+                        let eth_adapters = eth_adapters.clone();
+                        let chain_store = chain_store.clone();
+                        move |ctx, wasm_ptr| {
+                            let eth_adapters = eth_adapters.cheap_clone();
+                            let chain_store = chain_store.cheap_clone();
+                            async move {
+                                let eth_adapter =
+                                    eth_adapters.unverified_cheapest_with(&NodeCapabilities {
+                                        archive,
+                                        traces: false,
+                                    })?;
+                                eth_get_logs(
+                                    &eth_adapter,
+                                    chain_store,
+                                    ctx,
+                                    wasm_ptr,
+                                    reorg_threshold,
+                                )
+                                .await
+                                .map(|ptr| ptr.wasm_ptr())
+                            }
+                            .boxed()
+                        }
+                    }),
+                },
                 HostFn {
                     name: "ethereum.hasCode",
                     func: Arc::new({
@@ -165,19 +305,41 @@ impl blockchain::RuntimeAdapter<Chain> for RuntimeAdapter {
                 let abis = Arc::new(onchain_ds.mapping.abis.clone());
                 let archive = onchain_ds.mapping.requires_archive()?;
                 let call_cache = self.call_cache.cheap_clone();
+                let chain_store = self.chain_store.cheap_clone();
                 let eth_adapters = self.eth_adapters.cheap_clone();
                 let eth_call_gas = eth_call_gas(&self.chain_identifier);
+                let start_block = onchain_ds.start_block;
 
-                create_host_fns(abis, archive, call_cache, eth_adapters, eth_call_gas)
+                create_host_fns(
+                    abis,
+                    archive,
+                    call_cache,
+                    chain_store,
+                    eth_adapters,
+                    eth_call_gas,
+                    start_block,
+                    self.reorg_threshold,
+                )
             }
             data_source::DataSource::Subgraph(subgraph_ds) => {
                 let abis = Arc::new(subgraph_ds.mapping.abis.clone());
                 let archive = subgraph_ds.mapping.requires_archive()?;
                 let call_cache = self.call_cache.cheap_clone();
+                let chain_store = self.chain_store.cheap_clone();
                 let eth_adapters = self.eth_adapters.cheap_clone();
                 let eth_call_gas = eth_call_gas(&self.chain_identifier);
+                let start_block = subgraph_ds.source.start_block;
 
-                create_host_fns(abis, archive, call_cache, eth_adapters, eth_call_gas)
+                create_host_fns(
+                    abis,
+                    archive,
+                    call_cache,
+                    chain_store,
+                    eth_adapters,
+                    eth_call_gas,
+                    start_block,
+                    self.reorg_threshold,
+                )
             }
             data_source::DataSource::Offchain(_) => vec![],
             data_source::DataSource::Amp(_) => vec![],
@@ -225,6 +387,184 @@ async fn ethereum_call(
     }
 }
 
+/// function ethereum.callAtBlock(call: SmartContractCallAtBlock): Array<Token> | null
+///
+/// Like `ethereum.call`, but runs the call against the state at `block`
+/// instead of the state at the block that is being processed
+async fn ethereum_call_at_block(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    chain_store: Arc<dyn ChainStore>,
+    ctx: HostFnCtx<'_>,
+    wasm_ptr: u32,
+    abis: &[Arc<MappingABI>],
+    eth_call_gas: Option<u32>,
+    start_block: BlockNumber,
+    reorg_threshold: BlockNumber,
+) -> Result<AscEnumArray<EthereumValueKind>, HostExportError> {
+    ctx.gas
+        .consume_host_fn_with_metrics(ETHEREUM_CALL, "ethereum_call_at_block")?;
+
+    if ctx.heap.api_version() < &API_VERSION_0_0_10 {
+        return Err(HostExportError::Deterministic(anyhow!(
+            "ethereum.callAtBlock call is not supported before API version 0.0.10"
+        )));
+    }
+
+    let call: ContractCallAtBlock =
+        asc_get::<_, AscUnresolvedContractCallAtBlock, _>(ctx.heap, wasm_ptr.into(), &ctx.gas, 0)?;
+    let result = eth_call_at_block(
+        eth_adapter,
+        call_cache,
+        chain_store,
+        &ctx.logger,
+        &ctx.block_ptr,
+        call,
+        abis,
+        eth_call_gas,
+        start_block,
+        reorg_threshold,
+        ctx.metrics.cheap_clone(),
+    )
+    .await?;
+    match result {
+        Some(tokens) => Ok(asc_new(ctx.heap, tokens.as_slice(), &ctx.gas).await?),
+        None => Ok(AscPtr::null()),
+    }
+}
+
+/// Resolves the block for `call` relative to `current_block` and runs the
+/// call against it. Returns `Ok(None)` if the call was reverted.
+async fn eth_call_at_block(
+    eth_adapter: &EthereumAdapter,
+    call_cache: Arc<dyn EthereumCallCache>,
+    chain_store: Arc<dyn ChainStore>,
+    logger: &Logger,
+    current_block: &BlockPtr,
+    call: ContractCallAtBlock,
+    abis: &[Arc<MappingABI>],
+    eth_call_gas: Option<u32>,
+    start_block: BlockNumber,
+    reorg_threshold: BlockNumber,
+    metrics: Arc<HostMetrics>,
+) -> Result<Option<Vec<abi::DynSolValue>>, HostExportError> {
+    let number = call
+        .block_number(start_block, current_block.number)
+        .map_err(HostExportError::Deterministic)?;
+    let block_ptr = historical_block_ptr(
+        eth_adapter,
+        chain_store,
+        logger,
+        number,
+        current_block,
+        reorg_threshold,
+    )
+    .await?;
+
+    eth_call(
+        eth_adapter,
+        call_cache,
+        logger,
+        &block_ptr,
+        call.call,
+        abis,
+        eth_call_gas,
+        metrics,
+    )
+    .await
+}
+
+/// Returns the pointer to the ancestor of `current_block` with the given
+/// `number`, which must not be later than `current_block`.
+///
+/// Blocks that are at least `reorg_threshold` blocks older than
+/// `current_block` are final and are looked up through the provider by
+/// number. More recent blocks could still be on a different fork than
+/// `current_block`; they are looked up in the chain store by following
+/// parent hashes
+async fn historical_block_ptr(
+    eth_adapter: &EthereumAdapter,
+    chain_store: Arc<dyn ChainStore>,
+    logger: &Logger,
+    number: BlockNumber,
+    current_block: &BlockPtr,
+    reorg_threshold: BlockNumber,
+) -> Result<BlockPtr, HostExportError> {
+    if number == current_block.number {
+        return Ok(current_block.cheap_clone());
+    }
+
+    let does_not_exist = || {
+        HostExportError::Deterministic(anyhow!(
+            "ethereum.callAtBlock: block {} does not exist",
+            number
+        ))
+    };
+
+    if number <= current_block.number.saturating_sub(reorg_threshold) {
+        // The provider skips over null blocks and returns the next block
+        // that exists
+        let ptr = eth_adapter
+            .next_existing_ptr_to_number(logger, number)
+            .await
+            .map_err(HostExportError::Unknown)?;
+        if ptr.number != number {
+            return Err(does_not_exist());
+        }
+        return Ok(ptr);
+    }
+
+    let not_cached = || {
+        HostExportError::Unknown(anyhow!(
+            "ethereum.callAtBlock: block {} is not in the block cache for block {}",
+            number,
+            current_block
+        ))
+    };
+
+    // The numbers of the ancestors of `current_block` strictly decrease
+    // with their offset, but on chains with null blocks, the ancestor at
+    // offset `current_block.number - number` can be earlier than `number`.
+    // We therefore search between an offset whose ancestor is later than
+    // `number` and one whose ancestor is earlier. The store returns the
+    // furthest ancestor it has when the block cache has a gap, so an
+    // ancestor that is later than `number` might not be at the offset we
+    // asked for
+    let mut later = (0, current_block.cheap_clone());
+    let mut earlier = current_block.number - number + 1;
+    let mut offset = current_block.number - number;
+    loop {
+        let ptr = chain_store
+            .cheap_clone()
+            .ancestor_block_ptr(current_block.cheap_clone(), offset, None)
+            .await
+            .map_err(HostExportError::Unknown)?
+            .ok_or_else(not_cached)?;
+
+        match ptr.number.cmp(&number) {
+            std::cmp::Ordering::Equal => return Ok(ptr),
+            std::cmp::Ordering::Greater => later = (offset, ptr),
+            std::cmp::Ordering::Less => earlier = offset,
+        }
+        if earlier - later.0 > 1 {
+            offset = later.0 + (earlier - later.0) / 2;
+            continue;
+        }
+
+        // `number` is only a null block if the parent of the block after
+        // it is known and earlier than `number`
+        let parent = chain_store
+            .cheap_clone()
+            .block_parent_ptr(&later.1.hash)
+            .await
+            .map_err(HostExportError::Unknown)?;
+        return match parent {
+            Some(parent) if parent.number < number => Err(does_not_exist()),
+            _ => Err(not_cached()),
+        };
+    }
+}
+
 async fn eth_get_balance(
     eth_adapter: &EthereumAdapter,
     ctx: HostFnCtx<'_>,
@@ -295,6 +635,199 @@ async fn eth_has_code(
     }
 }
 
+/// function ethereum.feeHistory(call: FeeHistoryCall): FeeHistory
+///
+/// Returns the fees of the `blockCount` blocks ending at `newestBlock`,
+/// which has to be at least `FEE_HISTORY_FINALITY_DEPTH` blocks before the
+/// block that is being processed
+async fn eth_fee_history(
+    eth_adapter: &EthereumAdapter,
+    ctx: HostFnCtx<'_>,
+    wasm_ptr: u32,
+) -> Result<AscPtr<AscEthereumFeeHistory>, HostExportError> {
+    ctx.gas
+        .consume_host_fn_with_metrics(ETH_FEE_HISTORY, "eth_fee_history")?;
+
+    if ctx.heap.api_version() < &API_VERSION_0_0_10 {
+        return Err(HostExportError::Deterministic(anyhow!(
+            "ethereum.feeHistory call is not supported before API version 0.0.10"
+        )));
+    }
+
+    let call: FeeHistoryCall =
+        asc_get::<_, AscFeeHistoryCall, _>(ctx.heap, wasm_ptr.into(), &ctx.gas, 0)?;
+    let (block_count, newest_block) = call
+        .block_range(ctx.block_ptr.number - FEE_HISTORY_FINALITY_DEPTH)
+        .map_err(HostExportError::Deterministic)?;
+
+    let result = eth_adapter
+        .get_fee_history(
+            &ctx.logger,
+            block_count,
+            newest_block,
+            &call.reward_percentiles,
+        )
+        .await;
+
+    let fee_history = match result {
+        // A node that does not have all the blocks that were asked for
+        // returns fewer of them, so treat an incomplete response like any
+        // other error and retry
+        Ok(v) => EthereumFeeHistory::new(&call, block_count, newest_block, v)
+            .map_err(HostExportError::PossibleReorg)?,
+        // Retry on any kind of error
+        Err(EthereumRpcError::AlloyError(e)) => {
+            return Err(HostExportError::PossibleReorg(e.into()));
+        }
+        Err(EthereumRpcError::Timeout) => {
+            return Err(HostExportError::PossibleReorg(
+                EthereumRpcError::Timeout.into(),
+            ));
+        }
+    };
+
+    Ok(asc_new(ctx.heap, &fee_history, &ctx.gas).await?)
+}
+
+/// function ethereum.getLogs(call: GetLogsCall): Array<Log>
+///
+/// Returns the logs that `address` emitted in the `blockCount` blocks that
+/// end at the block that is being processed, in the order in which they
+/// were emitted
+async fn eth_get_logs(
+    eth_adapter: &EthereumAdapter,
+    chain_store: Arc<dyn ChainStore>,
+    ctx: HostFnCtx<'_>,
+    wasm_ptr: u32,
+    reorg_threshold: BlockNumber,
+) -> Result<AscPtr<AscLogArray>, HostExportError> {
+    ctx.gas
+        .consume_host_fn_with_metrics(ETH_GET_LOGS, "eth_get_logs")?;
+
+    if ctx.heap.api_version() < &API_VERSION_0_0_10 {
+        return Err(HostExportError::Deterministic(anyhow!(
+            "ethereum.getLogs call is not supported before API version 0.0.10"
+        )));
+    }
+
+    let call: GetLogsCall =
+        asc_get::<_, AscGetLogsCall, _>(ctx.heap, wasm_ptr.into(), &ctx.gas, 0)?;
+    let logs = get_logs(
+        eth_adapter,
+        chain_store,
+        &ctx.logger,
+        &call,
+        &ctx.block_ptr,
+        reorg_threshold,
+    )
+    .await?;
+
+    Ok(asc_new(ctx.heap, &logs.as_slice(), &ctx.gas).await?)
+}
+
+async fn get_logs(
+    eth_adapter: &EthereumAdapter,
+    chain_store: Arc<dyn ChainStore>,
+    logger: &Logger,
+    call: &GetLogsCall,
+    block_ptr: &BlockPtr,
+    reorg_threshold: BlockNumber,
+) -> Result<Vec<Log>, HostExportError> {
+    let from = call
+        .first_block(block_ptr.number)
+        .map_err(HostExportError::Deterministic)?;
+
+    let result = eth_adapter
+        .get_logs(logger, call.address, &call.topics, from, block_ptr)
+        .await;
+
+    match result {
+        Ok(logs) => {
+            // Blocks that are at least `reorg_threshold` blocks older
+            // than the chain head are final
+            let head = chain_store
+                .cheap_clone()
+                .chain_head_ptr()
+                .await
+                .map_err(HostExportError::Unknown)?
+                .map_or(block_ptr.number, |head| head.number.max(block_ptr.number));
+            let final_block = head.saturating_sub(reorg_threshold);
+            let ancestors = ancestor_hashes(chain_store, from, block_ptr)
+                .await
+                .map_err(HostExportError::Unknown)?;
+            call.check_logs(from, block_ptr, final_block, &ancestors, logs)
+        }
+        // Retry on any kind of error
+        Err(EthereumRpcError::AlloyError(e)) => Err(HostExportError::PossibleReorg(e.into())),
+        Err(EthereumRpcError::Timeout) => Err(HostExportError::PossibleReorg(
+            EthereumRpcError::Timeout.into(),
+        )),
+    }
+}
+
+/// Returns the hashes of `block_ptr` and of those of its ancestors back to
+/// block `from` that are in the block cache. Since every block is looked
+/// up through its child, the oldest of them is where the block cache ends
+/// or where there is a gap in it
+async fn ancestor_hashes(
+    chain_store: Arc<dyn ChainStore>,
+    from: BlockNumber,
+    block_ptr: &BlockPtr,
+) -> Result<HashMap<BlockNumber, B256>, Error> {
+    let blocks: HashMap<_, _> = chain_store
+        .cheap_clone()
+        .block_ptrs_in_range(from..block_ptr.number, MAX_GET_LOGS_BLOCKS as usize)
+        .map_ok(|(ptr, parent_hash)| (ptr.hash.clone(), (ptr, parent_hash)))
+        .try_collect()
+        .await?;
+    let parent_hash = chain_store
+        .block_parent_ptr(&block_ptr.hash)
+        .await?
+        .map(|parent| parent.hash);
+
+    let mut hashes = HashMap::from([(block_ptr.number, block_ptr.hash.as_b256())]);
+    let mut next = parent_hash.and_then(|hash| blocks.get(&hash));
+    while let Some((ptr, parent_hash)) = next {
+        hashes.insert(ptr.number, ptr.hash.as_b256());
+        next = blocks.get(parent_hash);
+    }
+    Ok(hashes)
+}
+
+/// function ethereum.hashTypedData(typedData: string): Bytes
+///
+/// Returns the EIP-712 signing hash of `typedData`, which must be the JSON
+/// representation of the typed data as accepted by `eth_signTypedData_v4`
+async fn eth_hash_typed_data(
+    ctx: HostFnCtx<'_>,
+    wasm_ptr: u32,
+) -> Result<AscPtr<Uint8Array>, HostExportError> {
+    if ctx.heap.api_version() < &API_VERSION_0_0_9 {
+        return Err(HostExportError::Deterministic(anyhow!(
+            "ethereum.hashTypedData call is not supported before API version 0.0.9"
+        )));
+    }
+
+    let json: String = asc_get(ctx.heap, wasm_ptr.into(), &ctx.gas, 0)?;
+
+    ctx.gas.consume_host_fn_with_metrics(
+        ETH_HASH_TYPED_DATA.with_args(complexity::Size, &json),
+        "eth_hash_typed_data",
+    )?;
+
+    let hash = hash_typed_data(&json).map_err(HostExportError::Deterministic)?;
+
+    Ok(asc_new(ctx.heap, &hash, &ctx.gas).await?)
+}
+
+fn hash_typed_data(json: &str) -> Result<B256, Error> {
+    let typed_data: TypedData =
+        serde_json::from_str(json).context("typed data is not valid EIP-712 JSON")?;
+    typed_data
+        .eip712_signing_hash()
+        .context("failed to hash EIP-712 typed data")
+}
+
 /// Returns `Ok(None)` if the call was reverted.
 async fn eth_call(
     eth_adapter: &EthereumAdapter,
@@ -336,6 +869,7 @@ async fn eth_call(
         function: function.clone(),
         args: unresolved_call.function_args.clone(),
         gas: eth_call_gas,
+        gas_limit: None,
     };
 
     // Run Ethereum call in tokio runtime
@@ -418,6 +952,263 @@ pub struct UnresolvedContractCall {
     pub function_args: Vec<abi::DynSolValue>,
 }
 
+/// The arguments of `ethereum.callAtBlock`
+#[derive(Clone, Debug)]
+pub struct ContractCallAtBlock {
+    pub call: UnresolvedContractCall,
+    pub block: BigInt,
+}
+
+impl ContractCallAtBlock {
+    /// Checks that the call is for a block that the data source has
+    /// already synced, i.e., one between `start_block` and
+    /// `current_block`, and returns its number
+    fn block_number(
+        &self,
+        start_block: BlockNumber,
+        current_block: BlockNumber,
+    ) -> Result<BlockNumber, Error> {
+        u64::try_from(&self.block)
+            .ok()
+            .and_then(|block| BlockNumber::try_from(block).ok())
+            .filter(|block| (start_block..=current_block).contains(block))
+            .ok_or_else(|| {
+                anyhow!(
+                    "ethereum.callAtBlock: block must be between the start block {} and the current block {} but is {}",
+                    start_block,
+                    current_block,
+                    self.block
+                )
+            })
+    }
+}
+
+/// The arguments of `ethereum.feeHistory`
+#[derive(Clone, Debug)]
+pub struct FeeHistoryCall {
+    pub block_count: BigInt,
+    pub newest_block: BigInt,
+    pub reward_percentiles: Vec<f64>,
+}
+
+impl FeeHistoryCall {
+    /// Checks that the call only asks for blocks up to `final_block` and
+    /// returns the number of blocks and the newest block. Since the fees of
+    /// final blocks never change, that makes the result deterministic.
+    fn block_range(&self, final_block: BlockNumber) -> Result<(u64, BlockNumber), Error> {
+        let block_count = u64::try_from(&self.block_count)
+            .ok()
+            .filter(|count| (1..=MAX_FEE_HISTORY_BLOCKS).contains(count))
+            .ok_or_else(|| {
+                anyhow!(
+                    "ethereum.feeHistory: block count must be between 1 and {} but is {}",
+                    MAX_FEE_HISTORY_BLOCKS,
+                    self.block_count
+                )
+            })?;
+
+        let newest_block = u64::try_from(&self.newest_block)
+            .ok()
+            .and_then(|newest| BlockNumber::try_from(newest).ok())
+            .filter(|newest| (0..=final_block).contains(newest))
+            .ok_or_else(|| {
+                anyhow!(
+                    "ethereum.feeHistory: newest block must be between 0 and the last final block {} but is {}",
+                    final_block,
+                    self.newest_block
+                )
+            })?;
+
+        if block_count > newest_block as u64 + 1 {
+            return Err(anyhow!(
+                "ethereum.feeHistory: can not get {} blocks up to block {}",
+                block_count,
+                newest_block
+            ));
+        }
+
+        let percentiles_valid = self
+            .reward_percentiles
+            .iter()
+            .all(|p| (0.0..=100.0).contains(p))
+            && self.reward_percentiles.windows(2).all(|w| w[0] <= w[1]);
+        if !percentiles_valid {
+            return Err(anyhow!(
+                "ethereum.feeHistory: reward percentiles must be increasing and between 0 and 100 but are {:?}",
+                self.reward_percentiles
+            ));
+        }
+
+        Ok((block_count, newest_block))
+    }
+}
+
+/// The result of `ethereum.feeHistory`. `base_fee_per_gas` has one more
+/// entry than there are blocks, the base fee of the block after the newest
+/// block, and `reward` has the requested percentiles of the priority fees
+/// for each block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EthereumFeeHistory {
+    pub oldest_block: BigInt,
+    pub base_fee_per_gas: Vec<BigInt>,
+    pub gas_used_ratio: Vec<f64>,
+    pub reward: Vec<Vec<BigInt>>,
+}
+
+impl EthereumFeeHistory {
+    /// Converts the response to `call` and fails if it does not cover
+    /// exactly the blocks that were asked for
+    fn new(
+        call: &FeeHistoryCall,
+        block_count: u64,
+        newest_block: BlockNumber,
+        fee_history: FeeHistory,
+    ) -> Result<Self, Error> {
+        let FeeHistory {
+            oldest_block,
+            base_fee_per_gas,
+            gas_used_ratio,
+            reward,
+            ..
+        } = fee_history;
+        let reward = reward.unwrap_or_default();
+
+        let expected_oldest = newest_block as u64 + 1 - block_count;
+        let block_count = block_count as usize;
+        let percentiles = call.reward_percentiles.len();
+        let complete = oldest_block == expected_oldest
+            && gas_used_ratio.len() == block_count
+            && base_fee_per_gas.len() == block_count + 1
+            && if percentiles == 0 {
+                reward.is_empty()
+            } else {
+                reward.len() == block_count && reward.iter().all(|r| r.len() == percentiles)
+            };
+        if !complete {
+            return Err(anyhow!(
+                "eth_feeHistory returned {} blocks starting at block {} but {} blocks starting at block {} were requested",
+                gas_used_ratio.len(),
+                oldest_block,
+                block_count,
+                expected_oldest
+            ));
+        }
+
+        Ok(Self {
+            oldest_block: BigInt::from(oldest_block),
+            base_fee_per_gas: base_fee_per_gas.into_iter().map(BigInt::from).collect(),
+            gas_used_ratio,
+            reward: reward
+                .into_iter()
+                .map(|r| r.into_iter().map(BigInt::from).collect())
+                .collect(),
+        })
+    }
+}
+
+/// The arguments of `ethereum.getLogs`. Each entry of `topics` lists the
+/// values that the topic at that position may have; an empty entry matches
+/// any value
+#[derive(Clone, Debug)]
+pub struct GetLogsCall {
+    pub address: Address,
+    pub topics: Vec<Vec<B256>>,
+    pub block_count: BigInt,
+}
+
+impl GetLogsCall {
+    /// Checks that the call asks for a bounded number of blocks that end
+    /// at `current_block` and returns the first of them
+    fn first_block(&self, current_block: BlockNumber) -> Result<BlockNumber, Error> {
+        if self.topics.len() > 4 {
+            return Err(anyhow!(
+                "ethereum.getLogs: logs have at most 4 topics but {} were given",
+                self.topics.len()
+            ));
+        }
+
+        let block_count = u64::try_from(&self.block_count)
+            .ok()
+            .filter(|count| (1..=MAX_GET_LOGS_BLOCKS).contains(count))
+            .ok_or_else(|| {
+                anyhow!(
+                    "ethereum.getLogs: block count must be between 1 and {} but is {}",
+                    MAX_GET_LOGS_BLOCKS,
+                    self.block_count
+                )
+            })?;
+
+        if block_count > current_block as u64 + 1 {
+            return Err(anyhow!(
+                "ethereum.getLogs: can not get logs for {} blocks up to block {}",
+                block_count,
+                current_block
+            ));
+        }
+
+        Ok(current_block + 1 - block_count as BlockNumber)
+    }
+
+    fn matches(&self, log: &Log) -> bool {
+        log.address() == self.address
+            && self.topics.iter().enumerate().all(|(i, topics)| {
+                topics.is_empty()
+                    || log
+                        .topics()
+                        .get(i)
+                        .is_some_and(|topic| topics.contains(topic))
+            })
+    }
+
+    /// Checks the `logs` that a provider returned for the blocks from
+    /// `from` up to `block_ptr` and sorts them in the order in which they
+    /// were emitted. Each log must come from the block in `ancestors`, the
+    /// hashes of `block_ptr` and its cached ancestors, with its number,
+    /// unless its block is not later than `final_block` and can therefore
+    /// not be on a different fork. Logs that were not asked for, that come
+    /// from a different fork or from a recent block that can not be
+    /// checked mean that the provider is out of sync with the chain, which
+    /// is worth retrying; finding too many logs is not.
+    fn check_logs(
+        &self,
+        from: BlockNumber,
+        block_ptr: &BlockPtr,
+        final_block: BlockNumber,
+        ancestors: &HashMap<BlockNumber, B256>,
+        mut logs: Vec<Log>,
+    ) -> Result<Vec<Log>, HostExportError> {
+        for log in &logs {
+            let in_range = log.block_number.is_some_and(|number| {
+                (from as u64..=block_ptr.number as u64).contains(&number)
+                    && match ancestors.get(&(number as BlockNumber)) {
+                        Some(hash) => log.block_hash == Some(*hash),
+                        None => (number as BlockNumber) <= final_block,
+                    }
+            });
+            if !in_range || log.log_index.is_none() || log.removed || !self.matches(log) {
+                return Err(HostExportError::PossibleReorg(anyhow!(
+                    "eth_getLogs returned a log that was not asked for: block {:?} ({:?}), log index {:?}, address {:x}",
+                    log.block_number,
+                    log.block_hash,
+                    log.log_index,
+                    log.address()
+                )));
+            }
+        }
+
+        if logs.len() > MAX_GET_LOGS_RESULTS {
+            return Err(HostExportError::Deterministic(anyhow!(
+                "ethereum.getLogs: found {} logs but at most {} can be returned, ask for fewer blocks",
+                logs.len(),
+                MAX_GET_LOGS_RESULTS
+            )));
+        }
+
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+        Ok(logs)
+    }
+}
+
 impl AscIndexId for AscUnresolvedContractCall {
     const INDEX_ASC_TYPE_ID: IndexForAscTypeId = IndexForAscTypeId::SmartContractCall;
 }
@@ -430,3 +1221,672 @@ fn values_to_string(values: &[abi::DynSolValue]) -> String {
         .collect_vec()
         .join(", ")
 }
+
+#[cfg(test)]
+mod tests {
+    use graph::blockchain::mock::MockChainStore;
+    use graph::blockchain::{BlockTime, ExtendedBlockPtr};
+    use graph::prelude::alloy::primitives::{U256, keccak256};
+    use graph::prelude::alloy::providers::mock::Asserter;
+    use graph::prelude::serde_json::Value;
+
+    use super::*;
+
+    // The `Mail` example from EIP-712
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "Person": [
+                { "name": "name", "type": "string" },
+                { "name": "wallet", "type": "address" }
+            ],
+            "Mail": [
+                { "name": "from", "type": "Person" },
+                { "name": "to", "type": "Person" },
+                { "name": "contents", "type": "string" }
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {
+                "name": "Cow",
+                "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+            },
+            "to": {
+                "name": "Bob",
+                "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+            },
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    const FEE_HISTORY: &str = r#"{
+        "oldestBlock": "0x112f2c6",
+        "baseFeePerGas": ["0x3c5a2d4d5", "0x3b6b2f0b7", "0x3f73d8d3a"],
+        "gasUsedRatio": [0.4357, 0.7717],
+        "baseFeePerBlobGas": ["0x1", "0x1", "0x1"],
+        "blobGasUsedRatio": [0.0, 0.5],
+        "reward": [["0x5f5e100", "0xb2d05e00"], ["0x3b9aca00", "0x77359400"]]
+    }"#;
+
+    fn fee_history_call(
+        block_count: u64,
+        newest_block: u64,
+        percentiles: &[f64],
+    ) -> FeeHistoryCall {
+        FeeHistoryCall {
+            block_count: BigInt::from(block_count),
+            newest_block: BigInt::from(newest_block),
+            reward_percentiles: percentiles.to_vec(),
+        }
+    }
+
+    #[test]
+    fn fee_history_converts_a_known_response() {
+        let call = fee_history_call(2, 18019015, &[10.0, 90.0]);
+        let (block_count, newest_block) = call.block_range(18019020).unwrap();
+        let response: FeeHistory = serde_json::from_str(FEE_HISTORY).unwrap();
+
+        let fee_history =
+            EthereumFeeHistory::new(&call, block_count, newest_block, response).unwrap();
+
+        let big = |values: &[u64]| values.iter().copied().map(BigInt::from).collect::<Vec<_>>();
+        assert_eq!(
+            EthereumFeeHistory {
+                oldest_block: BigInt::from(18019014u64),
+                base_fee_per_gas: big(&[16200684757, 15950082231, 17032908090]),
+                gas_used_ratio: vec![0.4357, 0.7717],
+                reward: vec![
+                    big(&[100000000, 3000000000]),
+                    big(&[1000000000, 2000000000])
+                ],
+            },
+            fee_history
+        );
+    }
+
+    #[test]
+    fn fee_history_rejects_incomplete_responses() {
+        let response: FeeHistory = serde_json::from_str(FEE_HISTORY).unwrap();
+
+        // The node returned fewer blocks than were asked for
+        let call = fee_history_call(3, 18019015, &[10.0, 90.0]);
+        assert!(EthereumFeeHistory::new(&call, 3, 18019015, response.clone()).is_err());
+
+        // The node returned fewer percentiles than were asked for
+        let call = fee_history_call(2, 18019015, &[10.0, 50.0, 90.0]);
+        assert!(EthereumFeeHistory::new(&call, 2, 18019015, response).is_err());
+    }
+
+    #[test]
+    fn fee_history_only_allows_final_blocks() {
+        assert_eq!(
+            (2, 100),
+            fee_history_call(2, 100, &[]).block_range(100).unwrap()
+        );
+        assert_eq!(
+            (1, 0),
+            fee_history_call(1, 0, &[50.0]).block_range(100).unwrap()
+        );
+
+        // Blocks after the last final block
+        assert!(fee_history_call(2, 101, &[]).block_range(100).is_err());
+        // No block is final yet
+        assert!(fee_history_call(1, 0, &[]).block_range(-10).is_err());
+        // More blocks than there are up to the newest block
+        assert!(fee_history_call(2, 0, &[]).block_range(100).is_err());
+        // Too few or too many blocks
+        assert!(fee_history_call(0, 100, &[]).block_range(100).is_err());
+        assert!(fee_history_call(2000, 5000, &[]).block_range(5000).is_err());
+        // Percentiles that are out of range or not increasing
+        assert!(fee_history_call(1, 100, &[101.0]).block_range(100).is_err());
+        assert!(
+            fee_history_call(1, 100, &[90.0, 10.0])
+                .block_range(100)
+                .is_err()
+        );
+    }
+
+    const LOG_ADDRESS: &str = "0x6b175474e89094c44da98b954eedeac495271d0f";
+    const CURRENT_HASH: &str = "0x96e5b4f4c0e6e5c7c3a6f3c0c3d3d0e2e7c1b7d1f0b2c3c7b8a3c2f1e0d9c8b7";
+
+    fn transfer_topic() -> B256 {
+        keccak256("Transfer(address,address,uint256)")
+    }
+
+    fn get_logs_call(topics: Vec<Vec<B256>>, block_count: u64) -> GetLogsCall {
+        GetLogsCall {
+            address: LOG_ADDRESS.parse().unwrap(),
+            topics,
+            block_count: BigInt::from(block_count),
+        }
+    }
+
+    fn current_block() -> BlockPtr {
+        BlockPtr::new(CURRENT_HASH.parse::<B256>().unwrap().into(), 100)
+    }
+
+    fn log_json(block_number: u64, block_hash: &str, log_index: u64, topic: B256) -> Value {
+        serde_json::json!({
+            "address": LOG_ADDRESS,
+            "topics": [topic],
+            "data": "0x",
+            "blockNumber": format!("{block_number:#x}"),
+            "blockHash": block_hash,
+            "transactionHash": format!("{:#x}", B256::with_last_byte(log_index as u8)),
+            "transactionIndex": "0x0",
+            "logIndex": format!("{log_index:#x}"),
+            "removed": false
+        })
+    }
+
+    fn log(block_number: u64, block_hash: &str, log_index: u64, topic: B256) -> Log {
+        serde_json::from_value(log_json(block_number, block_hash, log_index, topic)).unwrap()
+    }
+
+    #[test]
+    fn get_logs_only_allows_bounded_past_ranges() {
+        assert_eq!(91, get_logs_call(vec![], 10).first_block(100).unwrap());
+        assert_eq!(0, get_logs_call(vec![], 101).first_block(100).unwrap());
+
+        // More blocks than there are up to the current block
+        assert!(get_logs_call(vec![], 102).first_block(100).is_err());
+        // Too few or too many blocks
+        assert!(get_logs_call(vec![], 0).first_block(100).is_err());
+        assert!(get_logs_call(vec![], 1001).first_block(5000).is_err());
+        // Too many topics
+        assert!(get_logs_call(vec![vec![]; 5], 1).first_block(100).is_err());
+    }
+
+    fn block_hash(number: u64) -> String {
+        format!("{:#x}", B256::with_last_byte(number as u8))
+    }
+
+    #[test]
+    fn get_logs_rejects_logs_that_were_not_asked_for() {
+        let topic = transfer_topic();
+        let call = get_logs_call(vec![vec![topic]], 10);
+        let block = current_block();
+        let other_hash = format!("{:#x}", B256::with_last_byte(1));
+
+        // Blocks 95 to 99 are in the block cache
+        let mut ancestors = HashMap::from([(100, block.hash.as_b256())]);
+        for number in 95..100 {
+            ancestors.insert(number, B256::with_last_byte(number as u8));
+        }
+        // Blocks up to 93 are final
+        let check = |logs| call.check_logs(91, &block, 93, &ancestors, logs);
+
+        assert!(check(vec![log(97, &block_hash(97), 0, topic)]).is_ok());
+        // Final blocks before the block cache can not be checked
+        assert!(check(vec![log(92, &other_hash, 0, topic)]).is_ok());
+        // A recent block that is not in the block cache
+        assert!(matches!(
+            check(vec![log(94, &other_hash, 0, topic)]),
+            Err(HostExportError::PossibleReorg(_))
+        ));
+        // A log from a block on a different fork
+        assert!(matches!(
+            check(vec![log(97, &other_hash, 0, topic)]),
+            Err(HostExportError::PossibleReorg(_))
+        ));
+        // A log from a block before the range
+        assert!(matches!(
+            check(vec![log(90, &other_hash, 0, topic)]),
+            Err(HostExportError::PossibleReorg(_))
+        ));
+        // A log from a different version of the current block
+        assert!(matches!(
+            check(vec![log(100, &other_hash, 0, topic)]),
+            Err(HostExportError::PossibleReorg(_))
+        ));
+        // A log with a different topic
+        assert!(matches!(
+            check(vec![log(92, &other_hash, 0, B256::ZERO)]),
+            Err(HostExportError::PossibleReorg(_))
+        ));
+        // Too many logs
+        let logs = (0..=MAX_GET_LOGS_RESULTS as u64)
+            .map(|i| log(92, &other_hash, i, topic))
+            .collect();
+        assert!(matches!(
+            check(logs),
+            Err(HostExportError::Deterministic(_))
+        ));
+    }
+
+    #[graph::test]
+    async fn get_logs_returns_logs_in_order() {
+        let topic = transfer_topic();
+        let call = get_logs_call(vec![vec![topic]], 10);
+        let block = current_block();
+        let other_hash = format!("{:#x}", B256::with_last_byte(1));
+
+        let asserter = Asserter::new();
+        // The logs before the current block, and those of the current block
+        asserter.push_success(&Value::Array(vec![
+            log_json(97, &other_hash, 3, topic),
+            log_json(92, &other_hash, 7, topic),
+        ]));
+        asserter.push_success(&Value::Array(vec![log_json(100, CURRENT_HASH, 1, topic)]));
+        let adapter = EthereumAdapter::mocked(asserter);
+        // Blocks up to 95 are final
+        let chain_store = MockChainStore::default();
+        *chain_store.head.lock().unwrap() = Some(BlockPtr::new(B256::ZERO.into(), 105));
+        let chain_store = Arc::new(chain_store);
+        let logger = Logger::root(graph::slog::Discard, graph::prelude::o!());
+
+        // Block 97 is neither final nor in the block cache
+        let res = get_logs(
+            &adapter,
+            chain_store.cheap_clone(),
+            &logger,
+            &call,
+            &block,
+            10,
+        )
+        .await;
+        assert!(matches!(res, Err(HostExportError::PossibleReorg(_))));
+
+        let asserter = Asserter::new();
+        asserter.push_success(&Value::Array(vec![
+            log_json(97, &other_hash, 3, topic),
+            log_json(92, &other_hash, 7, topic),
+        ]));
+        asserter.push_success(&Value::Array(vec![log_json(100, CURRENT_HASH, 1, topic)]));
+        let adapter = EthereumAdapter::mocked(asserter);
+        let logs = get_logs(&adapter, chain_store, &logger, &call, &block, 5)
+            .await
+            .unwrap();
+
+        let blocks = logs
+            .iter()
+            .map(|log| (log.block_number.unwrap(), log.log_index.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(92, 7), (97, 3), (100, 1)], blocks);
+    }
+
+    #[graph::test]
+    async fn get_logs_follows_the_ancestors_of_the_current_block() {
+        let block = current_block();
+        let ptr = |number: BlockNumber, fork: u8| {
+            let mut hash = B256::with_last_byte(number as u8);
+            hash.0[0] = fork;
+            BlockPtr::new(hash.into(), number)
+        };
+        let cached = |ptr: BlockPtr, parent: &BlockPtr| ExtendedBlockPtr {
+            timestamp: BlockTime::for_test(&ptr),
+            hash: ptr.hash,
+            number: ptr.number,
+            parent_hash: parent.hash.clone(),
+        };
+
+        // Blocks 96 to 99 are in the block cache, and block 98 has a
+        // competing version on another fork
+        let mut chain_store = MockChainStore::default();
+        for number in 96..100 {
+            let mut blocks = vec![cached(ptr(number, 0), &ptr(number - 1, 0))];
+            if number == 98 {
+                blocks.push(cached(ptr(number, 1), &ptr(number - 1, 0)));
+            }
+            chain_store.blocks.insert(number, blocks);
+        }
+        chain_store
+            .parents
+            .lock()
+            .unwrap()
+            .insert(block.hash.clone(), ptr(99, 0));
+
+        let hashes = ancestor_hashes(Arc::new(chain_store), 91, &block)
+            .await
+            .unwrap();
+
+        let mut expected = HashMap::from([(100, block.hash.as_b256())]);
+        for number in 96..100 {
+            expected.insert(number, ptr(number, 0).hash.as_b256());
+        }
+        assert_eq!(expected, hashes);
+    }
+
+    fn call_at_block(block: i64) -> ContractCallAtBlock {
+        ContractCallAtBlock {
+            call: UnresolvedContractCall {
+                contract_name: "Token".to_string(),
+                contract_address: LOG_ADDRESS.parse().unwrap(),
+                function_name: "totalSupply".to_string(),
+                function_signature: Some("totalSupply():(uint256)".to_string()),
+                function_args: vec![],
+            },
+            block: BigInt::from(block),
+        }
+    }
+
+    #[test]
+    fn call_at_block_only_allows_synced_blocks() {
+        assert_eq!(10, call_at_block(10).block_number(10, 100).unwrap());
+        assert_eq!(55, call_at_block(55).block_number(10, 100).unwrap());
+        assert_eq!(100, call_at_block(100).block_number(10, 100).unwrap());
+
+        // Blocks before the start block
+        assert!(call_at_block(9).block_number(10, 100).is_err());
+        assert!(call_at_block(-1).block_number(0, 100).is_err());
+        // Blocks after the current block
+        assert!(call_at_block(101).block_number(10, 100).is_err());
+        assert!(call_at_block(i64::MAX).block_number(10, 100).is_err());
+    }
+
+    #[graph::test]
+    async fn call_at_block_targets_the_requested_block() {
+        let block = current_block();
+        let ptr =
+            |number: BlockNumber| BlockPtr::new(B256::with_last_byte(number as u8).into(), number);
+
+        // Blocks 85 to 100 are in the block cache, except for block 95,
+        // which is a null block
+        let chain_store = MockChainStore::default();
+        {
+            let mut parents = chain_store.parents.lock().unwrap();
+            parents.insert(block.hash.clone(), ptr(99));
+            for number in 86..100 {
+                let parent = if number == 96 { 94 } else { number - 1 };
+                if number != 95 {
+                    parents.insert(ptr(number).hash, ptr(parent));
+                }
+            }
+            parents.insert(ptr(85).hash, ptr(84));
+        }
+        let chain_store = Arc::new(chain_store);
+        // None of these blocks are final, so the provider is never asked
+        let adapter = EthereumAdapter::mocked(Asserter::new());
+        let logger = graph::log::discard();
+        let historical_block_ptr = async |number| {
+            historical_block_ptr(
+                &adapter,
+                chain_store.cheap_clone(),
+                &logger,
+                number,
+                &block,
+                50,
+            )
+            .await
+        };
+
+        assert_eq!(ptr(90), historical_block_ptr(90).await.unwrap());
+
+        // The current block is used as is
+        assert_eq!(block, historical_block_ptr(100).await.unwrap());
+
+        let res = historical_block_ptr(95).await;
+        assert!(matches!(res, Err(HostExportError::Deterministic(_))));
+
+        // Blocks that are not cached cause a non-deterministic error
+        let res = historical_block_ptr(80).await;
+        assert!(matches!(res, Err(HostExportError::Unknown(_))));
+
+        // So do blocks behind a gap in the block cache, even though the
+        // ancestors we get back are later than the block
+        chain_store.parents.lock().unwrap().remove(&ptr(88).hash);
+        let res = historical_block_ptr(86).await;
+        assert!(matches!(res, Err(HostExportError::Unknown(_))));
+    }
+
+    /// The response to `eth_getBlockByNumber` for a block that only has a
+    /// header
+    fn block_json(number: u64, hash: B256) -> Value {
+        serde_json::json!({
+            "hash": hash,
+            "parentHash": B256::ZERO,
+            "sha3Uncles": B256::ZERO,
+            "miner": Address::ZERO,
+            "stateRoot": B256::ZERO,
+            "transactionsRoot": B256::ZERO,
+            "receiptsRoot": B256::ZERO,
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "difficulty": "0x0",
+            "number": format!("{:#x}", number),
+            "gasLimit": "0x0",
+            "gasUsed": "0x0",
+            "timestamp": "0x0",
+            "extraData": "0x",
+            "mixHash": B256::ZERO,
+            "nonce": "0x0000000000000000",
+            "uncles": [],
+            "transactions": []
+        })
+    }
+
+    /// A call cache that answers every call with `retval` and remembers
+    /// the blocks it was asked about
+    struct RecordingCallCache {
+        retval: call::Retval,
+        blocks: std::sync::Mutex<Vec<BlockPtr>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EthereumCallCache for RecordingCallCache {
+        async fn get_call(
+            &self,
+            req: &call::Request,
+            block: BlockPtr,
+        ) -> Result<Option<call::Response>, Error> {
+            let (mut resps, _) = self.get_calls(std::slice::from_ref(req), block).await?;
+            Ok(resps.pop())
+        }
+
+        async fn get_calls(
+            &self,
+            reqs: &[call::Request],
+            block: BlockPtr,
+        ) -> Result<(Vec<call::Response>, Vec<call::Request>), Error> {
+            self.blocks.lock().unwrap().push(block);
+            let resps = reqs
+                .iter()
+                .map(|req| call::Response {
+                    req: req.clone(),
+                    retval: self.retval.clone(),
+                    source: call::Source::Memory,
+                })
+                .collect();
+            Ok((resps, vec![]))
+        }
+
+        async fn get_calls_in_block(
+            &self,
+            _: BlockPtr,
+        ) -> Result<Vec<graph::prelude::CachedEthereumCall>, Error> {
+            Ok(vec![])
+        }
+
+        async fn set_call(
+            self: Arc<Self>,
+            _: &Logger,
+            _: call::Request,
+            _: BlockPtr,
+            _: call::Retval,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[graph::test]
+    async fn call_at_block_calls_final_blocks_at_the_canonical_block() {
+        let abi: abi::JsonAbi = serde_json::from_str(
+            r#"[{
+                "type": "function",
+                "name": "totalSupply",
+                "inputs": [],
+                "outputs": [{ "name": "", "type": "uint256" }],
+                "stateMutability": "view"
+            }]"#,
+        )
+        .unwrap();
+        let abis = vec![Arc::new(MappingABI {
+            name: "Token".to_string(),
+            contract: abi,
+        })];
+
+        // Block 40 is final for block 100 and is not in the block cache,
+        // so it has to come from the provider
+        let hash = B256::with_last_byte(40);
+        let asserter = Asserter::new();
+        asserter.push_success(&block_json(40, hash));
+        let adapter = EthereumAdapter::mocked(asserter);
+        let call_cache = Arc::new(RecordingCallCache {
+            retval: call::Retval::Value(U256::from(42).to_be_bytes_vec().into()),
+            blocks: std::sync::Mutex::new(vec![]),
+        });
+
+        let res = eth_call_at_block(
+            &adapter,
+            call_cache.clone(),
+            Arc::new(MockChainStore::default()),
+            &graph::log::discard(),
+            &current_block(),
+            call_at_block(40),
+            &abis,
+            None,
+            0,
+            50,
+            host_metrics(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(Some(vec![abi::DynSolValue::Uint(U256::from(42), 256)]), res);
+        assert_eq!(
+            vec![BlockPtr::new(hash.into(), 40)],
+            *call_cache.blocks.lock().unwrap()
+        );
+    }
+
+    /// A call cache that never has any calls
+    struct EmptyCallCache;
+
+    #[async_trait::async_trait]
+    impl EthereumCallCache for EmptyCallCache {
+        async fn get_call(
+            &self,
+            _: &call::Request,
+            _: BlockPtr,
+        ) -> Result<Option<call::Response>, Error> {
+            Ok(None)
+        }
+
+        async fn get_calls(
+            &self,
+            reqs: &[call::Request],
+            _: BlockPtr,
+        ) -> Result<(Vec<call::Response>, Vec<call::Request>), Error> {
+            Ok((vec![], reqs.to_vec()))
+        }
+
+        async fn get_calls_in_block(
+            &self,
+            _: BlockPtr,
+        ) -> Result<Vec<graph::prelude::CachedEthereumCall>, Error> {
+            Ok(vec![])
+        }
+
+        async fn set_call(
+            self: Arc<Self>,
+            _: &Logger,
+            _: call::Request,
+            _: BlockPtr,
+            _: call::Retval,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn host_metrics() -> Arc<HostMetrics> {
+        let registry = Arc::new(graph::prelude::MetricsRegistry::mock());
+        let deployment = graph::prelude::DeploymentHash::new("test").unwrap();
+        let stopwatch = graph::prelude::StopwatchMetrics::new(
+            graph::log::discard(),
+            deployment.clone(),
+            "test",
+            registry.cheap_clone(),
+            "test_shard".to_string(),
+        );
+        let gas_metrics =
+            graph::components::metrics::gas::GasMetrics::new(deployment.clone(), registry.clone());
+        Arc::new(HostMetrics::new(
+            registry,
+            deployment.as_str(),
+            stopwatch,
+            gas_metrics,
+        ))
+    }
+
+    #[graph::test]
+    async fn eth_call_from_a_mapping_treats_out_of_gas_as_a_revert() {
+        let abi: abi::JsonAbi = serde_json::from_str(
+            r#"[{
+                "type": "function",
+                "name": "totalSupply",
+                "inputs": [],
+                "outputs": [{ "name": "", "type": "uint256" }],
+                "stateMutability": "view"
+            }]"#,
+        )
+        .unwrap();
+        let abis = vec![Arc::new(MappingABI {
+            name: "Token".to_string(),
+            contract: abi,
+        })];
+
+        // Mappings can not set a gas limit for their calls, so running out
+        // of gas must not fail the subgraph
+        let asserter = Asserter::new();
+        asserter.push_failure(graph::prelude::alloy::rpc::json_rpc::ErrorPayload {
+            code: -32000,
+            message: "out of gas".into(),
+            data: None,
+        });
+        let adapter = EthereumAdapter::mocked(asserter);
+        let logger = graph::log::discard();
+
+        let res = eth_call(
+            &adapter,
+            Arc::new(EmptyCallCache),
+            &logger,
+            &current_block(),
+            call_at_block(100).call,
+            &abis,
+            None,
+            host_metrics(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(None, res);
+    }
+
+    #[test]
+    fn hash_typed_data_matches_the_eip_example() {
+        let hash = hash_typed_data(MAIL).unwrap();
+
+        assert_eq!(
+            "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2",
+            hash.to_string()
+        );
+    }
+
+    #[test]
+    fn hash_typed_data_rejects_invalid_input() {
+        assert!(hash_typed_data("not json").is_err());
+
+        let unknown_type = MAIL.replace(r#""primaryType": "Mail""#, r#""primaryType": "Letter""#);
+        assert!(hash_typed_data(&unknown_type).is_err());
+    }
+}
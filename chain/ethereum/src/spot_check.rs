@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use graph::blockchain::{Block, BlockHash, BlockPtr, BlockTime};
+use graph::cheap_clone::CheapClone;
+use graph::components::ethereum::LightEthereumBlock;
+use graph::prelude::{
+    BlockNumber, Counter, DeploymentHash, Error, Logger, MetricsRegistry, debug, error,
+};
+
+use crate::EthereumAdapter;
+use crate::EthereumAdapterTrait as _;
+use crate::chain::BlockFinality;
+use crate::network::EthereumNetworkAdapters;
+
+/// The parts of a block that a spot check compares
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BlockSummary {
+    ptr: BlockPtr,
+    parent_hash: Option<BlockHash>,
+    timestamp: BlockTime,
+}
+
+impl BlockSummary {
+    fn new(block: &dyn Block) -> Self {
+        Self {
+            ptr: block.ptr(),
+            parent_hash: block.parent_ptr().map(|ptr| ptr.hash),
+            timestamp: block.timestamp(),
+        }
+    }
+}
+
+/// Compares every `interval`-th block that a Firehose block stream
+/// delivers with the same block from an RPC provider. The comparison
+/// happens in the background so that it never holds up the block stream;
+/// a block that differs is counted in the
+/// `deployment_firehose_spot_check_divergences` metric and logged as an
+/// error.
+///
+/// Blocks near the chain head can legitimately differ between Firehose
+/// and RPC because of reorgs. We therefore look the block up by its hash
+/// first, and only compare by number once the block is more than
+/// `reorg_threshold` blocks behind the RPC provider's head.
+pub struct FirehoseSpotCheck {
+    interval: BlockNumber,
+    reorg_threshold: BlockNumber,
+    adapters: Arc<EthereumNetworkAdapters>,
+    divergences: Counter,
+}
+
+impl FirehoseSpotCheck {
+    pub fn new(
+        interval: BlockNumber,
+        reorg_threshold: BlockNumber,
+        adapters: Arc<EthereumNetworkAdapters>,
+        registry: &MetricsRegistry,
+        deployment: &DeploymentHash,
+    ) -> Self {
+        let divergences = registry
+            .new_deployment_counter(
+                "deployment_firehose_spot_check_divergences",
+                "Counts Firehose blocks that differ from the block returned by RPC",
+                deployment.as_str(),
+            )
+            .expect("failed to create `deployment_firehose_spot_check_divergences` counter");
+        Self {
+            interval,
+            reorg_threshold,
+            adapters,
+            divergences,
+        }
+    }
+
+    fn is_due(&self, number: BlockNumber) -> bool {
+        self.interval > 0 && number % self.interval == 0
+    }
+
+    /// Whether the block `number` is far enough behind `head` that it can
+    /// not be reorged anymore
+    fn is_final(&self, number: BlockNumber, head: BlockNumber) -> bool {
+        number <= head.saturating_sub(self.reorg_threshold)
+    }
+
+    /// Find the block from RPC that `firehose` should be compared with. If
+    /// RPC knows a block with the same hash, that block must agree with
+    /// the Firehose block on everything else. Otherwise, the Firehose block
+    /// might have been reorged away, and only once it is final do we
+    /// compare it with RPC's block at the same number. Returns `None` if
+    /// there is nothing to compare with yet
+    async fn rpc_summary(
+        &self,
+        logger: &Logger,
+        adapter: &EthereumAdapter,
+        firehose: &BlockSummary,
+    ) -> Result<Option<BlockSummary>, Error> {
+        let summary = |block| {
+            BlockSummary::new(&BlockFinality::Final(Arc::new(LightEthereumBlock::new(
+                block,
+            ))))
+        };
+
+        if let Some(block) = adapter
+            .block_by_hash(logger, firehose.ptr.hash.as_b256())
+            .await?
+        {
+            return Ok(Some(summary(block)));
+        }
+
+        let head = adapter.latest_block_ptr(logger).await?;
+        if !self.is_final(firehose.ptr.number, head.number) {
+            return Ok(None);
+        }
+        Ok(adapter
+            .block_by_number(logger, firehose.ptr.number)
+            .await?
+            .map(summary))
+    }
+
+    /// Compare `block` with the block from RPC in the background if it is
+    /// due for a spot check
+    pub fn check(self: &Arc<Self>, logger: &Logger, block: &BlockFinality) {
+        if !self.is_due(block.number()) {
+            return;
+        }
+
+        let this = self.cheap_clone();
+        let logger = logger.clone();
+        let firehose = BlockSummary::new(block);
+        graph::spawn(async move {
+            let number = firehose.ptr.number;
+            let Some(adapter) = this.adapters.cheapest().await else {
+                debug!(logger, "No RPC provider to spot check Firehose block"; "block" => number);
+                return;
+            };
+            match this.rpc_summary(&logger, &adapter, &firehose).await {
+                Ok(Some(rpc)) => {
+                    this.compare(&logger, &firehose, &rpc);
+                }
+                Ok(None) => {
+                    debug!(logger, "Skipping spot check of Firehose block that is not final yet"; "block" => number)
+                }
+                Err(e) => {
+                    debug!(logger, "Failed to load block for spot check";
+                        "block" => number, "error" => format!("{:#}", e))
+                }
+            }
+        });
+    }
+
+    /// Compare the block from Firehose with the block from RPC and report
+    /// if they differ. Returns `true` if they are the same
+    fn compare(&self, logger: &Logger, firehose: &BlockSummary, rpc: &BlockSummary) -> bool {
+        if firehose == rpc {
+            return true;
+        }
+
+        self.divergences.inc();
+        error!(logger, "Firehose block differs from the block returned by RPC";
+            "block" => firehose.ptr.number,
+            "firehose_hash" => firehose.ptr.hash_hex(),
+            "rpc_hash" => rpc.ptr.hash_hex(),
+            "firehose_parent_hash" => firehose.parent_hash.as_ref().map(|h| h.hash_hex()),
+            "rpc_parent_hash" => rpc.parent_hash.as_ref().map(|h| h.hash_hex()),
+            "firehose_timestamp" => firehose.timestamp.to_string(),
+            "rpc_timestamp" => rpc.timestamp.to_string());
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::blockchain::mock::test_ptr;
+    use graph::prelude::create_minimal_block_for_test;
+    use graph::slog::{Discard, o};
+
+    use super::*;
+
+    fn summary(number: BlockNumber, parent: BlockNumber) -> BlockSummary {
+        let block = create_minimal_block_for_test(number as u64, test_ptr(number).hash.as_b256())
+            .map_header(|mut header| {
+                header.inner.parent_hash = test_ptr(parent).hash.as_b256();
+                header
+            });
+        BlockSummary::new(&BlockFinality::Final(Arc::new(LightEthereumBlock::new(
+            block,
+        ))))
+    }
+
+    fn spot_check(interval: BlockNumber) -> FirehoseSpotCheck {
+        FirehoseSpotCheck::new(
+            interval,
+            50,
+            Arc::new(EthereumNetworkAdapters::empty_for_testing()),
+            &MetricsRegistry::mock(),
+            &DeploymentHash::new("spotcheck").unwrap(),
+        )
+    }
+
+    #[test]
+    fn corrupted_firehose_block_is_flagged() {
+        let logger = Logger::root(Discard, o!());
+        let check = spot_check(10);
+
+        assert!(check.compare(&logger, &summary(20, 19), &summary(20, 19)));
+        assert_eq!(0.0, check.divergences.get());
+
+        // Firehose claims that block 20 has a different parent
+        assert!(!check.compare(&logger, &summary(20, 7), &summary(20, 19)));
+        assert_eq!(1.0, check.divergences.get());
+    }
+
+    #[test]
+    fn only_every_interval_block_is_checked() {
+        let check = spot_check(10);
+        assert!(check.is_due(0));
+        assert!(!check.is_due(9));
+        assert!(check.is_due(20));
+
+        let check = spot_check(0);
+        assert!(!check.is_due(0));
+        assert!(!check.is_due(20));
+    }
+
+    #[test]
+    fn only_final_blocks_are_compared_by_number() {
+        let check = spot_check(10);
+        assert!(check.is_final(50, 100));
+        assert!(!check.is_final(51, 100));
+        assert!(!check.is_final(100, 100));
+        assert!(!check.is_final(20, 30));
+    }
+}
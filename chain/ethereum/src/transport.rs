@@ -6,11 +6,20 @@ use graph::prelude::alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
 use graph::prelude::alloy::transports::{ipc::IpcConnect, ws::WsConnect};
 use graph::prelude::*;
 use graph::url::Url;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Instant;
 use tower::Service;
 
+use crate::ENV_VARS;
+
+/// The header that carries the ID of an RPC request when request IDs are
+/// turned on
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
 /// Compression method for RPC requests.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Compression {
@@ -32,6 +41,160 @@ impl std::fmt::Display for Compression {
     }
 }
 
+/// Settings that help providers attribute requests to this node and that
+/// make it possible to find a request in the provider's logs
+#[derive(Clone, Debug)]
+pub struct RequestTracing {
+    /// The `User-Agent` header for all requests; if `None`, the HTTP
+    /// client's default is used
+    pub user_agent: Option<String>,
+    /// Send a unique ID in the `x-request-id` header with every request
+    /// and log it together with the outcome of the request
+    pub request_ids: bool,
+    pub logger: Logger,
+}
+
+impl RequestTracing {
+    /// The settings from `GRAPH_ETHEREUM_RPC_USER_AGENT` and
+    /// `GRAPH_ETHEREUM_RPC_REQUEST_IDS`; request IDs are logged to `logger`
+    pub fn from_env(logger: &Logger) -> Self {
+        Self {
+            user_agent: ENV_VARS.rpc_user_agent.clone(),
+            request_ids: ENV_VARS.rpc_request_ids,
+            logger: logger.clone(),
+        }
+    }
+
+    /// Do not add anything to requests
+    pub fn none() -> Self {
+        Self {
+            user_agent: None,
+            request_ids: false,
+            logger: graph::log::discard(),
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.user_agent.is_none() && !self.request_ids
+    }
+
+    /// The headers for the handshake of a WebSocket connection. Requests
+    /// over the connection have no headers of their own, so the request
+    /// ID identifies the connection and is logged when it is opened
+    fn handshake_headers(&self) -> graph::http::HeaderMap {
+        let mut headers = graph::http::HeaderMap::new();
+        if let Some(user_agent) = &self.user_agent {
+            let user_agent = graph::http::HeaderValue::from_str(user_agent)
+                .expect("GRAPH_ETHEREUM_RPC_USER_AGENT is not a valid header value");
+            headers.insert(graph::http::header::USER_AGENT, user_agent);
+        }
+        if self.request_ids {
+            let id = new_request_id();
+            debug!(self.logger, "Opening WebSocket connection"; "request_id" => &id);
+            // unwrap: request IDs are hex strings
+            headers.insert(REQUEST_ID_HEADER, id.parse().unwrap());
+        }
+        headers
+    }
+}
+
+/// Which RPC methods may be sent to a provider. Requests for other methods
+/// fail without being sent
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MethodFilter {
+    #[default]
+    All,
+    /// Only these methods may be sent
+    Allow(BTreeSet<String>),
+    /// All methods except these may be sent
+    Deny(BTreeSet<String>),
+}
+
+impl MethodFilter {
+    pub fn is_all(&self) -> bool {
+        matches!(self, MethodFilter::All)
+    }
+
+    pub fn permits(&self, method: &str) -> bool {
+        match self {
+            MethodFilter::All => true,
+            MethodFilter::Allow(methods) => methods.contains(method),
+            MethodFilter::Deny(methods) => !methods.contains(method),
+        }
+    }
+
+    /// Check that all methods in `request` may be sent
+    fn check(&self, request: &RequestPacket) -> Result<(), TransportError> {
+        let denied = match request {
+            RequestPacket::Single(req) => Some(req.method()).filter(|m| !self.permits(m)),
+            RequestPacket::Batch(reqs) => reqs
+                .iter()
+                .map(|req| req.method())
+                .find(|m| !self.permits(m)),
+        };
+        match denied {
+            Some(method) => Err(TransportErrorKind::custom_str(&format!(
+                "the RPC method `{}` is not allowed for this provider",
+                method
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Additional HTTP headers that are only sent with requests for certain RPC
+/// methods, e.g. credentials that a provider requires for expensive
+/// methods. Each entry maps a method pattern to the headers for it; a
+/// pattern is either a method name or a prefix followed by `*`, like
+/// `trace_*`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MethodHeaders(Vec<(String, graph::http::HeaderMap)>);
+
+impl MethodHeaders {
+    pub fn new(headers: impl IntoIterator<Item = (String, graph::http::HeaderMap)>) -> Self {
+        Self(headers.into_iter().collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn matches(pattern: &str, method: &str) -> bool {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => pattern == method,
+        }
+    }
+
+    /// The headers for `request`. For a batch, these are the headers for
+    /// all methods in the batch
+    fn for_request(&self, request: &RequestPacket) -> graph::http::HeaderMap {
+        let mut headers = graph::http::HeaderMap::new();
+        if self.is_empty() {
+            return headers;
+        }
+
+        let methods: Vec<&str> = match request {
+            RequestPacket::Single(req) => vec![req.method()],
+            RequestPacket::Batch(reqs) => reqs.iter().map(|req| req.method()).collect(),
+        };
+        for (pattern, extra) in &self.0 {
+            if methods.iter().any(|method| Self::matches(pattern, method)) {
+                for (name, value) in extra {
+                    headers.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        headers
+    }
+}
+
+/// Generate an ID for a request that is very unlikely to repeat
+fn new_request_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
 /// Abstraction over different transport types for Alloy providers.
 #[derive(Clone, Debug)]
 pub enum Transport {
@@ -62,9 +225,10 @@ impl Transport {
         panic!("IPC connections are not supported on non-Unix platforms")
     }
 
-    /// Creates a WebSocket transport.
-    pub async fn new_ws(ws: &str) -> Self {
-        let transport = WsConnect::new(ws.to_string());
+    /// Creates a WebSocket transport. The user agent and request ID from
+    /// `tracing` are sent with the handshake.
+    pub async fn new_ws(ws: &str, tracing: RequestTracing) -> Self {
+        let transport = WsConnect::new(ws.to_string()).with_headers(tracing.handshake_headers());
 
         Transport::WS(transport)
     }
@@ -80,8 +244,36 @@ impl Transport {
         provider: impl AsRef<str>,
         no_eip2718: bool,
         compression: Compression,
+        tracing: RequestTracing,
+        methods: MethodFilter,
+        method_headers: MethodHeaders,
     ) -> Self {
+        let client = Self::http_client(headers, compression, tracing.user_agent.as_deref());
+
+        let logger = tracing
+            .logger
+            .new(o!("provider" => provider.as_ref().to_string()));
+        let patching_transport = PatchingHttp::new(client, rpc, no_eip2718)
+            .with_request_ids(tracing.request_ids, logger)
+            .with_method_filter(methods)
+            .with_method_headers(method_headers);
+        let metrics_transport =
+            MetricsHttp::new(patching_transport, metrics, provider.as_ref().into());
+        let rpc_client = alloy::rpc::client::RpcClient::new(metrics_transport, false);
+
+        Transport::RPC(rpc_client)
+    }
+
+    /// The HTTP client for a JSON-RPC over HTTP transport
+    fn http_client(
+        headers: graph::http::HeaderMap,
+        compression: Compression,
+        user_agent: Option<&str>,
+    ) -> reqwest::Client {
         let mut client_builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(user_agent) = user_agent {
+            client_builder = client_builder.user_agent(user_agent);
+        }
 
         match compression {
             Compression::None => {}
@@ -96,14 +288,7 @@ impl Transport {
             }
         }
 
-        let client = client_builder.build().expect("Failed to build HTTP client");
-
-        let patching_transport = PatchingHttp::new(client, rpc, no_eip2718);
-        let metrics_transport =
-            MetricsHttp::new(patching_transport, metrics, provider.as_ref().into());
-        let rpc_client = alloy::rpc::client::RpcClient::new(metrics_transport, false);
-
-        Transport::RPC(rpc_client)
+        client_builder.build().expect("Failed to build HTTP client")
     }
 }
 
@@ -176,6 +361,11 @@ pub struct PatchingHttp {
     client: reqwest::Client,
     url: Url,
     no_eip2718: bool,
+    /// If set, every request gets a unique ID that is sent to the provider
+    /// and logged here
+    request_ids: Option<Logger>,
+    methods: MethodFilter,
+    method_headers: MethodHeaders,
 }
 
 impl PatchingHttp {
@@ -184,6 +374,36 @@ impl PatchingHttp {
             client,
             url,
             no_eip2718,
+            request_ids: None,
+            methods: MethodFilter::All,
+            method_headers: MethodHeaders::default(),
+        }
+    }
+
+    /// Send a unique ID with each request if `enabled`, and log it to
+    /// `logger` with the outcome of the request
+    pub fn with_request_ids(mut self, enabled: bool, logger: Logger) -> Self {
+        self.request_ids = enabled.then_some(logger);
+        self
+    }
+
+    /// Reject requests for methods that `methods` does not permit
+    pub fn with_method_filter(mut self, methods: MethodFilter) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Send the headers in `method_headers` with requests for the
+    /// methods they are configured for
+    pub fn with_method_headers(mut self, method_headers: MethodHeaders) -> Self {
+        self.method_headers = method_headers;
+        self
+    }
+
+    fn method(request: &RequestPacket) -> &str {
+        match request {
+            RequestPacket::Single(req) => req.method(),
+            RequestPacket::Batch(reqs) => reqs.first().map(|r| r.method()).unwrap_or("batch"),
         }
     }
 
@@ -231,9 +451,18 @@ impl Service<RequestPacket> for PatchingHttp {
     }
 
     fn call(&mut self, request: RequestPacket) -> Self::Future {
+        if let Err(e) = self.methods.check(&request) {
+            return Box::pin(async move { Err(e) });
+        }
+
         let client = self.client.clone();
         let url = self.url.clone();
         let no_eip2718 = self.no_eip2718;
+        let method_headers = self.method_headers.for_request(&request);
+        let request_id = self.request_ids.clone().map(|logger| {
+            let method = Self::method(&request).to_string();
+            (new_request_id(), method, logger)
+        });
 
         let should_patch = if no_eip2718 {
             match &request {
@@ -247,13 +476,33 @@ impl Service<RequestPacket> for PatchingHttp {
         };
 
         Box::pin(async move {
-            let resp = client
+            let mut req = client
                 .post(url)
                 .json(&request)
                 .headers(request.headers())
-                .send()
-                .await
-                .map_err(TransportErrorKind::custom)?;
+                .headers(method_headers);
+            if let Some((id, _, _)) = &request_id {
+                req = req.header(REQUEST_ID_HEADER, id.as_str());
+            }
+
+            let start = Instant::now();
+            let resp = req.send().await;
+            if let Some((id, method, logger)) = &request_id {
+                let elapsed_ms = start.elapsed().as_millis() as u64;
+                match &resp {
+                    Ok(resp) => debug!(logger, "RPC request finished";
+                        "request_id" => id,
+                        "method" => method,
+                        "status" => resp.status().as_u16(),
+                        "elapsed_ms" => elapsed_ms),
+                    Err(e) => debug!(logger, "RPC request failed";
+                        "request_id" => id,
+                        "method" => method,
+                        "error" => e.to_string(),
+                        "elapsed_ms" => elapsed_ms),
+                }
+            }
+            let resp = resp.map_err(TransportErrorKind::custom)?;
 
             let status = resp.status();
             let body = resp.bytes().await.map_err(TransportErrorKind::custom)?;
@@ -278,6 +527,8 @@ impl Service<RequestPacket> for PatchingHttp {
 
 #[cfg(test)]
 mod tests {
+    use graph::prelude::alloy::rpc::json_rpc::{Id, Request};
+
     use super::*;
 
     #[test]
@@ -302,4 +553,229 @@ mod tests {
         assert_eq!(json[0]["result"]["type"], "0x0");
         assert_eq!(json[1]["result"]["type"], "0x0");
     }
+
+    #[test]
+    fn request_ids_are_distinct_hex_strings() {
+        let a = new_request_id();
+        let b = new_request_id();
+        assert_eq!(16, a.len());
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn request_tracing_is_none_without_user_agent_or_ids() {
+        assert!(RequestTracing::none().is_none());
+
+        let tracing = RequestTracing {
+            user_agent: Some("graph-node".to_string()),
+            ..RequestTracing::none()
+        };
+        assert!(!tracing.is_none());
+
+        let tracing = RequestTracing {
+            request_ids: true,
+            ..RequestTracing::none()
+        };
+        assert!(!tracing.is_none());
+    }
+
+    #[test]
+    fn websocket_handshake_carries_tracing_headers() {
+        assert!(RequestTracing::none().handshake_headers().is_empty());
+
+        let tracing = RequestTracing {
+            user_agent: Some("graph-node".to_string()),
+            request_ids: true,
+            ..RequestTracing::none()
+        };
+        let headers = tracing.handshake_headers();
+        assert_eq!(
+            "graph-node",
+            headers[graph::http::header::USER_AGENT].to_str().unwrap()
+        );
+        let id = headers[REQUEST_ID_HEADER].to_str().unwrap();
+        assert_eq!(16, id.len());
+
+        // Every connection gets its own ID
+        let other = tracing.handshake_headers();
+        assert_ne!(id, other[REQUEST_ID_HEADER].to_str().unwrap());
+    }
+
+    fn request(method: &'static str) -> RequestPacket {
+        Request::new(method, Id::Number(1), ())
+            .serialize()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn method_filter_rejects_denied_methods() {
+        let allow = MethodFilter::Allow(["eth_call".to_string()].into());
+        assert!(allow.check(&request("eth_call")).is_ok());
+        assert!(allow.check(&request("trace_block")).is_err());
+
+        let deny = MethodFilter::Deny(["trace_block".to_string()].into());
+        assert!(deny.check(&request("eth_call")).is_ok());
+        assert!(deny.check(&request("trace_block")).is_err());
+
+        // One denied method fails the whole batch
+        let batch = RequestPacket::Batch(vec![
+            Request::new("eth_call", 1u64.into(), ())
+                .serialize()
+                .unwrap(),
+            Request::new("trace_block", 2u64.into(), ())
+                .serialize()
+                .unwrap(),
+        ]);
+        assert!(deny.check(&batch).is_err());
+
+        assert!(MethodFilter::All.check(&request("trace_block")).is_ok());
+    }
+
+    #[test]
+    fn method_headers_only_apply_to_configured_methods() {
+        fn headers(name: &str, value: &str) -> graph::http::HeaderMap {
+            let mut headers = graph::http::HeaderMap::new();
+            headers.insert(
+                name.parse::<graph::http::header::HeaderName>().unwrap(),
+                value.parse().unwrap(),
+            );
+            headers
+        }
+
+        let method_headers = MethodHeaders::new([
+            ("eth_getLogs".to_string(), headers("x-api-key", "archive")),
+            ("trace_*".to_string(), headers("x-trace-key", "traces")),
+        ]);
+
+        let sent = method_headers.for_request(&request("eth_getLogs"));
+        assert_eq!(1, sent.len());
+        assert_eq!("archive", sent["x-api-key"]);
+
+        let sent = method_headers.for_request(&request("trace_block"));
+        assert_eq!(1, sent.len());
+        assert_eq!("traces", sent["x-trace-key"]);
+
+        assert!(method_headers.for_request(&request("eth_call")).is_empty());
+        assert!(
+            method_headers
+                .for_request(&request("eth_getLogsX"))
+                .is_empty()
+        );
+
+        // A batch gets the headers for all of its methods
+        let batch = RequestPacket::Batch(vec![
+            Request::new("eth_call", 1u64.into(), ())
+                .serialize()
+                .unwrap(),
+            Request::new("eth_getLogs", 2u64.into(), ())
+                .serialize()
+                .unwrap(),
+        ]);
+        let sent = method_headers.for_request(&batch);
+        assert_eq!(1, sent.len());
+        assert_eq!("archive", sent["x-api-key"]);
+
+        assert!(
+            MethodHeaders::default()
+                .for_request(&request("eth_getLogs"))
+                .is_empty()
+        );
+    }
+
+    #[graph::test]
+    async fn denied_methods_are_not_sent() {
+        // Nothing listens on this port; the request must fail before it
+        // tries to connect
+        let url = Url::parse("http://127.0.0.1:9").unwrap();
+        let mut http = PatchingHttp::new(reqwest::Client::new(), url, false)
+            .with_method_filter(MethodFilter::Deny(["trace_block".to_string()].into()));
+
+        let err = http.call(request("trace_block")).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("the RPC method `trace_block` is not allowed"),
+            "unexpected error {}",
+            err
+        );
+
+        let err = http.call(request("eth_call")).await.unwrap_err();
+        assert!(!err.to_string().contains("is not allowed"));
+    }
+
+    #[graph::test]
+    async fn user_agent_and_request_id_are_sent_and_logged() {
+        use graph::slog::{Drain, KV, OwnedKVList, Record, Serializer};
+        use std::sync::Mutex;
+        use wiremock::matchers::{header, header_exists, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        /// Captures the `request_id` of every log record
+        struct RequestIdCapture(Arc<Mutex<Vec<String>>>);
+
+        impl Drain for RequestIdCapture {
+            type Ok = ();
+            type Err = std::io::Error;
+
+            fn log(&self, record: &Record, _: &OwnedKVList) -> std::result::Result<(), Self::Err> {
+                struct RequestId<'a>(&'a Mutex<Vec<String>>);
+
+                impl Serializer for RequestId<'_> {
+                    fn emit_arguments(
+                        &mut self,
+                        key: graph::slog::Key,
+                        val: &std::fmt::Arguments,
+                    ) -> graph::slog::Result {
+                        if key == "request_id" {
+                            self.0.lock().unwrap().push(val.to_string());
+                        }
+                        Ok(())
+                    }
+                }
+
+                record
+                    .kv()
+                    .serialize(record, &mut RequestId(&self.0))
+                    .unwrap();
+                Ok(())
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("user-agent", "graph-node-test"))
+            .and(header_exists(REQUEST_ID_HEADER))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": "0x1",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let logger = Logger::root(RequestIdCapture(logged.clone()).fuse(), o!());
+
+        let client = Transport::http_client(
+            graph::http::HeaderMap::new(),
+            Compression::None,
+            Some("graph-node-test"),
+        );
+        let url = Url::parse(&server.uri()).unwrap();
+        let mut http = PatchingHttp::new(client, url, false).with_request_ids(true, logger);
+
+        http.call(request("eth_chainId")).await.unwrap();
+
+        let received = server.received_requests().await.unwrap();
+        let sent_id = received[0]
+            .headers
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(vec![sent_id], *logged.lock().unwrap());
+    }
 }
@@ -95,13 +95,24 @@ impl bc::TriggerFilter<Chain> for TriggerFilter {
             value: filter.encode_to_vec(),
         }]
     }
+
+    fn is_equivalent(&self, other: &Self) -> bool {
+        // Mirrors `to_firehose_filter`, which sends no filter at all when
+        // every block is needed or there is no receipt filter
+        let effective = |filter: &Self| {
+            (!filter.block_filter.trigger_every_block && !filter.receipt_filter.is_empty())
+                .then_some(&filter.receipt_filter)
+        };
+
+        effective(self) == effective(other)
+    }
 }
 
 pub(crate) type Account = String;
 
 /// NearReceiptFilter requires the account to be set, it will match every receipt where `source.account` is the recipient.
 /// see docs: https://thegraph.com/docs/en/supported-networks/near/
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub(crate) struct NearReceiptFilter {
     pub accounts: HashSet<Account>,
     pub partial_accounts: HashSet<(Option<String>, Option<String>)>,
@@ -317,6 +328,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn near_trigger_filter_equivalence() {
+        let filter = |every_block: bool, accounts: &[&str]| TriggerFilter {
+            block_filter: NearBlockFilter {
+                trigger_every_block: every_block,
+            },
+            receipt_filter: super::NearReceiptFilter {
+                accounts: accounts.iter().map(|acc| acc.to_string()).collect(),
+                partial_accounts: HashSet::new(),
+            },
+        };
+
+        let base = filter(false, &["acc1", "acc2"]);
+        assert!(base.is_equivalent(&filter(false, &["acc2", "acc1", "acc1"])));
+        assert!(!base.is_equivalent(&filter(false, &["acc1", "acc2", "acc3"])));
+        assert!(!base.is_equivalent(&filter(true, &["acc1", "acc2"])));
+
+        // Neither filter restricts what Firehose sends
+        assert!(filter(true, &["acc1"]).is_equivalent(&filter(true, &["acc2"])));
+        assert!(filter(true, &["acc1"]).is_equivalent(&filter(false, &[])));
+    }
+
     #[test]
     fn near_trigger_partial_filter() {
         let filter = TriggerFilter {
@@ -2,11 +2,12 @@ use async_trait::async_trait;
 use graph::blockchain::client::ChainClient;
 use graph::blockchain::firehose_block_ingestor::FirehoseBlockIngestor;
 use graph::blockchain::{
-    BlockIngestor, BlockchainKind, NoopDecoderHook, NoopRuntimeAdapter, TriggerFilterWrapper,
+    BlockIngestor, BlockchainKind, ChainHeadUpdateListener, ChainHeadUpdateStream, ChainHealth,
+    ChainMetadata, NoopDecoderHook, NoopRuntimeAdapter, TriggerFilterWrapper,
 };
 use graph::cheap_clone::CheapClone;
 use graph::components::network_provider::ChainName;
-use graph::components::store::{ChainHeadStore, DeploymentCursorTracker, SourceableStore};
+use graph::components::store::{ChainStore, DeploymentCursorTracker, SourceableStore};
 use graph::data::subgraph::UnifiedMappingApiVersion;
 use graph::firehose::{FirehoseEndpoint, FirehoseEndpoints};
 use graph::futures03::TryFutureExt;
@@ -27,7 +28,7 @@ use graph::{
     prelude::{BlockNumber, Error, Logger, LoggerFactory, o},
 };
 use prost::Message;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 use std::sync::Arc;
 
 use crate::adapter::TriggerFilter;
@@ -101,7 +102,8 @@ pub struct Chain {
     logger_factory: LoggerFactory,
     name: ChainName,
     client: Arc<ChainClient<Self>>,
-    chain_head_store: Arc<dyn ChainHeadStore>,
+    chain_store: Arc<dyn ChainStore>,
+    chain_head_update_listener: Arc<dyn ChainHeadUpdateListener>,
     metrics_registry: Arc<MetricsRegistry>,
     block_stream_builder: Arc<dyn BlockStreamBuilder<Self>>,
 }
@@ -116,14 +118,16 @@ impl Chain {
     pub fn new(
         logger_factory: LoggerFactory,
         name: ChainName,
-        chain_head_store: Arc<dyn ChainHeadStore>,
+        chain_store: Arc<dyn ChainStore>,
+        chain_head_update_listener: Arc<dyn ChainHeadUpdateListener>,
         firehose_endpoints: FirehoseEndpoints,
         metrics_registry: Arc<MetricsRegistry>,
     ) -> Self {
         Chain {
             logger_factory,
             name,
-            chain_head_store,
+            chain_store,
+            chain_head_update_listener,
             client: Arc::new(ChainClient::new_firehose(firehose_endpoints)),
             metrics_registry,
             block_stream_builder: Arc::new(NearStreamBuilder {}),
@@ -188,10 +192,33 @@ impl Blockchain for Chain {
             .await
     }
 
+    fn finality_depth(&self) -> BlockNumber {
+        graph::env::ENV_VARS.reorg_threshold_for(self.name.as_str())
+    }
+
     fn is_refetch_block_required(&self) -> bool {
         false
     }
 
+    async fn health_check(&self) -> ChainHealth {
+        let logger = self
+            .logger_factory
+            .component_logger("ChainHealthCheck", None);
+        let head = match self.client.firehose_endpoint().await {
+            Ok(endpoint) => {
+                endpoint
+                    .head_block_ptr::<codec::HeaderOnlyBlock>(&logger)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        ChainHealth::from(head)
+    }
+
+    fn supported_handler_kinds() -> HashSet<&'static str> {
+        crate::data_source::HANDLER_KINDS.into_iter().collect()
+    }
+
     async fn refetch_firehose_block(
         &self,
         _logger: &Logger,
@@ -203,7 +230,24 @@ impl Blockchain for Chain {
     }
 
     async fn chain_head_ptr(&self) -> Result<Option<BlockPtr>, Error> {
-        self.chain_head_store.cheap_clone().chain_head_ptr().await
+        self.chain_store.cheap_clone().chain_head_ptr().await
+    }
+
+    fn chain_head_updates(&self) -> ChainHeadUpdateStream {
+        let logger = self
+            .logger_factory
+            .component_logger("ChainHeadUpdates", None);
+        self.chain_head_update_listener
+            .subscribe(self.name.to_string(), logger)
+    }
+
+    async fn chain_metadata(&self) -> Result<ChainMetadata, Error> {
+        Ok(ChainMetadata {
+            native_token_symbol: "NEAR".to_string(),
+            // Amounts of NEAR are counted in yoctoNEAR
+            native_token_decimals: 24,
+            identifier: self.chain_store.chain_identifier().await?,
+        })
     }
 
     async fn block_pointer_from_number(
@@ -231,7 +275,7 @@ impl Blockchain for Chain {
 
     async fn block_ingestor(&self) -> anyhow::Result<Box<dyn BlockIngestor>> {
         let ingestor = FirehoseBlockIngestor::<crate::HeaderOnlyBlock, Self>::new(
-            self.chain_head_store.cheap_clone(),
+            self.chain_store.cheap_clone().as_head_store(),
             self.chain_client(),
             self.logger_factory
                 .component_logger("NearFirehoseBlockIngestor", None),
@@ -6,6 +6,7 @@ use graph::{
     blockchain::{Block as BlockchainBlock, BlockPtr, BlockTime},
     prelude::{BlockNumber, alloy::primitives::B256, hex},
 };
+use prost::Message as _;
 use std::convert::TryFrom;
 use std::fmt::LowerHex;
 
@@ -74,6 +75,10 @@ impl BlockchainBlock for Block {
     fn timestamp(&self) -> BlockTime {
         block_time_from_header(self.header())
     }
+
+    fn byte_size(&self) -> usize {
+        self.encoded_len()
+    }
 }
 
 impl HeaderOnlyBlock {
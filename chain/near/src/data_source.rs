@@ -22,6 +22,9 @@ pub const NEAR_KIND: &str = "near";
 const BLOCK_HANDLER_KIND: &str = "block";
 const RECEIPT_HANDLER_KIND: &str = "receipt";
 
+/// The handler kinds that data sources can declare in the manifest
+pub(crate) const HANDLER_KINDS: [&str; 2] = [BLOCK_HANDLER_KIND, RECEIPT_HANDLER_KIND];
+
 /// Runtime representation of a data source.
 #[derive(Clone, Debug)]
 pub struct DataSource {
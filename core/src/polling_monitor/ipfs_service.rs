@@ -5,7 +5,7 @@ use anyhow::Error;
 use anyhow::anyhow;
 use bytes::Bytes;
 use graph::futures03::future::BoxFuture;
-use graph::ipfs::{ContentPath, IpfsClient, IpfsContext, RetryPolicy};
+use graph::ipfs::{ContentPath, DeploymentIpfsClients, IpfsClient, IpfsContext, RetryPolicy};
 use graph::{derive::CheapClone, prelude::CheapClone};
 use tower::{ServiceBuilder, ServiceExt, buffer::Buffer};
 
@@ -17,14 +17,18 @@ pub struct IpfsRequest {
     pub path: ContentPath,
 }
 
+/// Creates a service that loads files with `client`, or with the client of
+/// the deployment that makes the request if it is in `deployment_clients`.
 pub fn ipfs_service(
     client: Arc<dyn IpfsClient>,
+    deployment_clients: DeploymentIpfsClients,
     max_file_size: usize,
     timeout: Duration,
     rate_limit: u16,
 ) -> IpfsService {
     let ipfs = IpfsServiceInner {
         client,
+        deployment_clients,
         timeout,
         max_file_size,
     };
@@ -42,6 +46,7 @@ pub fn ipfs_service(
 #[derive(Clone, CheapClone)]
 struct IpfsServiceInner {
     client: Arc<dyn IpfsClient>,
+    deployment_clients: DeploymentIpfsClients,
     timeout: Duration,
     max_file_size: usize,
 }
@@ -57,7 +62,8 @@ impl IpfsServiceInner {
         }
 
         let res = self
-            .client
+            .deployment_clients
+            .client_for(&ctx.deployment_hash, &self.client)
             .cat(
                 &ctx,
                 &path,
@@ -138,7 +144,13 @@ mod test {
         )
         .unwrap();
 
-        let svc = ipfs_service(Arc::new(client), 100000, Duration::from_secs(30), 10);
+        let svc = ipfs_service(
+            Arc::new(client),
+            DeploymentIpfsClients::default(),
+            100000,
+            Duration::from_secs(30),
+            10,
+        );
 
         let path = ContentPath::new(format!("{dir_cid}/file.txt")).unwrap();
         let content = svc
@@ -177,7 +189,13 @@ mod test {
         let server = MockServer::start().await;
         let ipfs_client =
             IpfsRpcClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard()).unwrap();
-        let ipfs_service = ipfs_service(Arc::new(ipfs_client), 10, Duration::from_secs(1), 1);
+        let ipfs_service = ipfs_service(
+            Arc::new(ipfs_client),
+            DeploymentIpfsClients::default(),
+            10,
+            Duration::from_secs(1),
+            1,
+        );
         let path = ContentPath::new(CID).unwrap();
 
         Mock::given(m::method("POST"))
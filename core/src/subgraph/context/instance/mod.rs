@@ -1,6 +1,7 @@
 mod hosts;
 
 use anyhow::ensure;
+use graph::components::store::StoredDynamicDataSource;
 use graph::futures01::sync::mpsc::Sender;
 use graph::{
     blockchain::{Blockchain, TriggerData as _},
@@ -11,6 +12,8 @@ use graph::{
     prelude::*,
 };
 use hosts::{OffchainHosts, OnchainHosts};
+
+use crate::subgraph::error::{NonDeterministicErrorHelper as _, ProcessingError};
 use std::collections::HashMap;
 
 pub(crate) struct SubgraphInstance<C: Blockchain, T: RuntimeHostBuilder<C>> {
@@ -40,6 +43,9 @@ pub(crate) struct SubgraphInstance<C: Blockchain, T: RuntimeHostBuilder<C>> {
 
     /// This manages the sequence of causality regions for the subgraph.
     causality_region_seq: CausalityRegionSeq,
+
+    /// The most data sources the subgraph can have, set by `GRAPH_SUBGRAPH_MAX_DATA_SOURCES`
+    max_data_sources: usize,
 }
 
 impl<T, C> SubgraphInstance<C, T>
@@ -72,6 +78,7 @@ where
         host_builder: T,
         host_metrics: Arc<HostMetrics>,
         causality_region_seq: CausalityRegionSeq,
+        max_data_sources: usize,
     ) -> Self {
         let subgraph_id = manifest.id.clone();
         let network = manifest.network_name();
@@ -89,6 +96,7 @@ where
             templates,
             host_metrics,
             causality_region_seq,
+            max_data_sources,
         }
     }
 
@@ -134,15 +142,26 @@ where
         &mut self,
         logger: &Logger,
         data_source: DataSource<C>,
-    ) -> Result<Option<Arc<T::Host>>, Error> {
-        // Protect against creating more than the allowed maximum number of data sources
-        if self.hosts_len() >= ENV_VARS.subgraph_max_data_sources {
-            anyhow::bail!(
-                "Limit of {} data sources per subgraph exceeded",
-                ENV_VARS.subgraph_max_data_sources,
-            );
+    ) -> Result<Option<Arc<T::Host>>, ProcessingError> {
+        // Protect against creating more than the allowed maximum number of data sources.
+        // Every run of a subgraph creates the same data sources up to a given block,
+        // so exceeding the limit is deterministic.
+        if self.hosts_len() >= self.max_data_sources {
+            return Err(ProcessingError::Deterministic(Box::new(anyhow!(
+                "Limit of {} data sources per subgraph exceeded; the limit can be raised with \
+                 GRAPH_SUBGRAPH_MAX_DATA_SOURCES",
+                self.max_data_sources,
+            ))));
         }
 
+        self.add_host(logger, data_source).non_deterministic()
+    }
+
+    fn add_host(
+        &mut self,
+        logger: &Logger,
+        data_source: DataSource<C>,
+    ) -> Result<Option<Arc<T::Host>>, Error> {
         let Some(host) = self.new_host(logger.clone(), data_source)? else {
             return Ok(None);
         };
@@ -255,4 +274,14 @@ where
     pub fn hosts_len(&self) -> usize {
         self.onchain_hosts.len() + self.offchain_hosts.len()
     }
+
+    /// The stored form of all dynamic data sources that currently have a host
+    pub fn dynamic_data_sources(&self) -> impl Iterator<Item = StoredDynamicDataSource> + '_ {
+        self.onchain_hosts
+            .hosts()
+            .iter()
+            .chain(self.offchain_hosts.all())
+            .filter(|host| host.creation_block_number().is_some())
+            .map(|host| host.data_source().as_stored_dynamic_data_source())
+    }
 }
@@ -7,10 +7,14 @@ use anyhow::{self, Error};
 use bytes::Bytes;
 use graph::{
     blockchain::Blockchain,
-    components::{store::DeploymentId, subgraph::HostMetrics},
+    components::{
+        store::{DeploymentId, StoredDynamicDataSource},
+        subgraph::HostMetrics,
+    },
     data::subgraph::SubgraphManifest,
     data_source::{
         CausalityRegion, DataSource, DataSourceTemplate,
+        activity::{DataSourceActivity, OrphanedDataSource},
         causality_region::CausalityRegionSeq,
         offchain::{self, Base64},
     },
@@ -30,6 +34,7 @@ use tokio::sync::mpsc;
 
 use self::instance::SubgraphInstance;
 use super::Decoder;
+use super::error::ProcessingError;
 
 #[derive(Clone, CheapClone, Debug)]
 pub struct SubgraphKeepAlive {
@@ -76,6 +81,7 @@ where
     pub offchain_monitor: OffchainMonitor,
     pub(crate) trigger_processor: Box<dyn TriggerProcessor<C, T>>,
     pub(crate) decoder: Box<Decoder<C, T>>,
+    data_source_activity: DataSourceActivity,
 }
 
 impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
@@ -88,12 +94,14 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
         offchain_monitor: OffchainMonitor,
         trigger_processor: Box<dyn TriggerProcessor<C, T>>,
         decoder: Box<Decoder<C, T>>,
+        max_data_sources: usize,
     ) -> Self {
         let instance = SubgraphInstance::new(
             manifest,
             host_builder,
             host_metrics.clone(),
             causality_region_seq,
+            max_data_sources,
         );
 
         Self {
@@ -102,6 +110,7 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
             offchain_monitor,
             trigger_processor,
             decoder,
+            data_source_activity: DataSourceActivity::new(),
         }
     }
 
@@ -116,6 +125,7 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
     /// File data sources that have been marked not done during this process will get re-queued
     pub fn revert_data_sources(&mut self, reverted_block: BlockNumber) {
         let removed = self.instance.revert_data_sources(reverted_block);
+        self.data_source_activity.revert(reverted_block);
 
         removed
             .into_iter()
@@ -126,7 +136,7 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
         &mut self,
         logger: &Logger,
         data_source: DataSource<C>,
-    ) -> Result<Option<Arc<T::Host>>, Error> {
+    ) -> Result<Option<Arc<T::Host>>, ProcessingError> {
         let offchain_fields = data_source
             .as_offchain()
             .map(|ds| (ds.source.clone(), ds.is_processed()));
@@ -163,6 +173,36 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> IndexingContext<C, T> {
     pub fn templates(&self) -> &[DataSourceTemplate<C>] {
         &self.instance.templates
     }
+
+    /// Record which dynamic data sources matched triggers in `block`
+    pub fn observe_data_source_matches(
+        &mut self,
+        block: BlockNumber,
+        matched: &[StoredDynamicDataSource],
+    ) {
+        self.data_source_activity.observe_block(block, matched);
+    }
+
+    /// Whether it is time to report orphaned data sources again; see
+    /// `DataSourceActivity::report_due`
+    pub fn orphaned_data_sources_report_due(
+        &mut self,
+        block: BlockNumber,
+        threshold: BlockNumber,
+    ) -> bool {
+        self.data_source_activity.report_due(block, threshold)
+    }
+
+    /// Dynamic data sources that were created more than `threshold` blocks
+    /// before `block` and that have not matched a trigger since then
+    pub fn orphaned_data_sources(
+        &self,
+        block: BlockNumber,
+        threshold: BlockNumber,
+    ) -> Vec<OrphanedDataSource> {
+        self.data_source_activity
+            .orphaned(self.instance.dynamic_data_sources(), block, threshold)
+    }
 }
 
 pub struct OffchainMonitor {
@@ -539,6 +539,7 @@ impl<S: SubgraphStore, AC: amp::Client> SubgraphInstanceManager<S, AC> {
                 offchain_monitor,
                 tp,
                 decoder,
+                env_vars.subgraph_max_data_sources,
             );
             for data_source in data_sources {
                 ctx.add_dynamic_data_source(&logger, data_source)?;
@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use async_trait::async_trait;
 use graph::amp;
-use graph::blockchain::{Blockchain, BlockchainKind, BlockchainMap};
+use graph::blockchain::{Blockchain, BlockchainKind, BlockchainMap, validate_manifest_chain};
 use graph::components::{
     link_resolver::LinkResolverContext,
     network_provider::AmpChainNames,
@@ -13,6 +13,7 @@ use graph::data::{
     subgraph::{Graft, schema::DeploymentCreate},
     value::Word,
 };
+use graph::data_source::offchain;
 use graph::futures03::{self, Stream, StreamExt, future::TryFutureExt};
 use graph::prelude::{CreateSubgraphResult, SubgraphRegistrar as SubgraphRegistrarTrait, *};
 use graph::util::futures::{RETRY_DEFAULT_LIMIT, retry_strategy};
@@ -293,8 +294,11 @@ where
             raw
         };
 
-        let kind = BlockchainKind::from_manifest(&raw).map_err(|e| {
-            SubgraphRegistrarError::ResolveError(SubgraphManifestResolveError::ResolveError(e))
+        let kind = validate_manifest_chain(&raw).map_err(|errors| {
+            let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+            SubgraphRegistrarError::ResolveError(SubgraphManifestResolveError::ResolveError(
+                anyhow!("invalid manifest: {}", errors.join("; ")),
+            ))
         })?;
 
         // Give priority to deployment specific history_blocks value.
@@ -454,6 +458,51 @@ async fn resolve_graft_block(
         })
 }
 
+/// Fetches the IPFS files that the offchain data sources of `manifest`
+/// reference in the background so that they are cached by the time the
+/// subgraph needs them. Files that can not be fetched are logged and
+/// otherwise ignored; they will be fetched again when they are needed.
+fn warm_ipfs_cache<C: Blockchain>(
+    logger: &Logger,
+    resolver: &Arc<dyn LinkResolver>,
+    deployment: &DeploymentHash,
+    manifest: &SubgraphManifest<C>,
+) {
+    let paths: Vec<_> = manifest
+        .data_sources
+        .iter()
+        .filter_map(|ds| ds.as_offchain())
+        .filter_map(|ds| match &ds.source {
+            offchain::Source::Ipfs(path) => Some(path.clone()),
+            offchain::Source::Arweave(_) => None,
+        })
+        .collect();
+
+    if paths.is_empty() {
+        return;
+    }
+
+    let logger = logger.clone();
+    let resolver = resolver.cheap_clone();
+    let ctx = LinkResolverContext::new(deployment, &logger);
+
+    graph::spawn(async move {
+        let outcomes = resolver.warm_cache(&ctx, &paths).await;
+        let mut failed = 0;
+        for (path, res) in &outcomes {
+            if let Err(e) = res {
+                failed += 1;
+                warn!(logger, "Failed to warm the IPFS cache";
+                    "path" => path.to_string(),
+                    "error" => e.to_string());
+            }
+        }
+        info!(logger, "Warmed the IPFS cache";
+            "files" => outcomes.len() - failed,
+            "failed" => failed);
+    });
+}
+
 async fn create_subgraph_version<C: Blockchain, S: SubgraphStore, AC: amp::Client>(
     logger: &Logger,
     store: Arc<S>,
@@ -547,6 +596,8 @@ async fn create_subgraph_version<C: Blockchain, S: SubgraphStore, AC: amp::Clien
         "block" => format!("{:?}", base_block.as_ref().map(|(_,ptr)| ptr.number))
     );
 
+    warm_ipfs_cache(&logger, resolver, &deployment, &manifest);
+
     // Entity types that may be touched by offchain data sources need a causality region column.
     let needs_causality_region = manifest
         .data_sources
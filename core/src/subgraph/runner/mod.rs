@@ -70,6 +70,8 @@ where
     logger: Logger,
     pub metrics: RunnerMetrics,
     cancel_handle: Option<CancelHandle>,
+    /// The chain filter that the current block stream was started with
+    stream_filter: Option<Arc<C::TriggerFilter>>,
     /// The current state in the runner's state machine.
     /// This field drives the main loop of the runner.
     runner_state: RunnerState<C>,
@@ -114,6 +116,7 @@ where
             logger,
             metrics,
             cancel_handle: None,
+            stream_filter: None,
             runner_state: RunnerState::Initializing,
         }
     }
@@ -170,6 +173,7 @@ where
             .map(|ds| SubgraphFilter {
                 subgraph: ds.source.address(),
                 start_block: ds.source.start_block,
+                end_block: ds.source.end_block,
                 entities: ds
                     .mapping
                     .handlers
@@ -177,6 +181,7 @@ where
                     .map(|handler| handler.entity.clone())
                     .collect(),
                 manifest_idx: ds.manifest_idx,
+                include_deletions: ds.source.include_deletions,
             })
             .collect::<Vec<_>>();
 
@@ -226,8 +231,9 @@ where
         let block_stream_cancel_handle = block_stream_canceler.handle();
         // TriggerFilter needs to be rebuilt eveytime the blockstream is restarted
         let filter = self.build_filter();
+        self.stream_filter = Some(filter.chain_filter.cheap_clone());
 
-        let block_stream = new_block_stream(&self.inputs, filter, &self.metrics.subgraph)
+        let block_stream = new_block_stream(&self.inputs, filter, &self.metrics)
             .await?
             .cancelable(&block_stream_canceler);
 
@@ -1019,6 +1025,7 @@ where
                 // way to revert the effect of `create_dynamic_data_sources` so we may return a
                 // clean context as in b21fa73b-6453-4340-99fb-1a78ec62efb1.
                 match e {
+                    MappingError::Deterministic(e) => ProcessingError::Deterministic(Box::new(e)),
                     MappingError::PossibleReorg(e) | MappingError::Unknown(e) => {
                         ProcessingError::Unknown(e)
                     }
@@ -1107,6 +1114,12 @@ where
         // Stage 1: Match triggers to hosts and decode
         let runnables = self.match_triggers(&logger, &block, triggers).await;
 
+        let orphan_threshold = ENV_VARS.orphaned_data_source_threshold;
+        let matched_data_sources = match &runnables {
+            Ok(runnables) if orphan_threshold > 0 => matched_dynamic_data_sources(runnables),
+            _ => vec![],
+        };
+
         // Stage 2: Execute triggers
         let res = match runnables {
             Ok(runnables) => {
@@ -1129,11 +1142,27 @@ where
             // Some form of unknown or non-deterministic error ocurred.
             Err(MappingError::Unknown(e)) => return Err(ProcessingError::Unknown(e)),
 
+            // An error that will happen again, e.g., a declared call that ran out of gas.
+            Err(MappingError::Deterministic(e)) => {
+                return Err(ProcessingError::Deterministic(Box::new(e)));
+            }
+
             // Possible blockchain reorg detected - signal restart via ProcessingError::PossibleReorg.
             // See also b21fa73b-6453-4340-99fb-1a78ec62efb1.
             Err(MappingError::PossibleReorg(e)) => return Err(ProcessingError::PossibleReorg(e)),
         }
 
+        if orphan_threshold > 0 {
+            self.ctx
+                .observe_data_source_matches(block_ptr.number, &matched_data_sources);
+            if self
+                .ctx
+                .orphaned_data_sources_report_due(block_ptr.number, orphan_threshold)
+            {
+                self.report_orphaned_data_sources(&logger, block_ptr.number, orphan_threshold);
+            }
+        }
+
         // Check if there are any datasources that have expired in this block. ie: the end_block
         // of that data source is equal to the block number of the current block.
         let has_expired_data_sources = self.inputs.end_blocks.contains(&block_ptr.number);
@@ -1143,10 +1172,6 @@ where
         let created_data_sources_needs_restart =
             !self.is_static_filters_enabled() && block_state.has_created_on_chain_data_sources();
 
-        // Determine if the block stream needs to be restarted due to newly created on-chain data sources
-        // or data sources that have reached their end block.
-        let needs_restart = created_data_sources_needs_restart || has_expired_data_sources;
-
         // Checkpoint before dynamic DS processing for potential rollback scenarios.
         // This captures the current state so it can be restored if dynamic data source
         // processing fails in a way that requires partial rollback.
@@ -1164,6 +1189,13 @@ where
             )
             .await?;
 
+        // Determine if the block stream needs to be restarted due to newly created on-chain data sources
+        // or data sources that have reached their end block. New data sources that do not change the
+        // filter of the block stream, for example because they watch an address that is already
+        // watched, do not need a restart.
+        let needs_restart = has_expired_data_sources
+            || (created_data_sources_needs_restart && !self.stream_filter_is_current());
+
         // Stage 4: Process offchain triggers
         let (offchain_mods, processed_offchain_data_sources) = self
             .process_offchain_triggers(&block, &mut block_state)
@@ -1188,6 +1220,67 @@ where
         }
     }
 
+    /// Whether the block stream was started with a filter that is
+    /// equivalent to the one for the current data sources
+    fn stream_filter_is_current(&self) -> bool {
+        self.stream_filter
+            .as_ref()
+            .is_some_and(|filter| filter.is_equivalent(&self.build_filter().chain_filter))
+    }
+
+    /// Log the dynamic data sources that have not matched any triggers in
+    /// the last `threshold` blocks. Removing them is up to the operator.
+    fn report_orphaned_data_sources(
+        &self,
+        logger: &Logger,
+        block: BlockNumber,
+        threshold: BlockNumber,
+    ) {
+        const MAX_REPORTED: usize = 10;
+
+        let orphans = self.ctx.orphaned_data_sources(block, threshold);
+        if orphans.is_empty() {
+            return;
+        }
+
+        let examples = orphans
+            .iter()
+            .take(MAX_REPORTED)
+            .map(|orphan| {
+                let ds = &orphan.data_source;
+                let template = self
+                    .ctx
+                    .templates()
+                    .iter()
+                    .find(|template| template.manifest_idx() == ds.manifest_idx)
+                    .map(|template| template.name().to_string())
+                    .unwrap_or_else(|| ds.manifest_idx.to_string());
+                let param = ds
+                    .param
+                    .as_ref()
+                    .map(|param| param.to_string())
+                    .unwrap_or_default();
+                let last_match = orphan
+                    .last_match
+                    .map(|number| number.to_string())
+                    .unwrap_or_else(|| "none".to_string());
+                format!(
+                    "{}({}) created at {:?}, last match {}",
+                    template, param, ds.creation_block, last_match
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        warn!(
+            logger,
+            "Dynamic data sources have not matched any triggers recently";
+            "count" => orphans.len(),
+            "threshold" => threshold,
+            "data_sources" => examples,
+        );
+    }
+
     /// Refetch the block if it that is needed. Otherwise return the block as is.
     async fn refetch_block(
         &mut self,
@@ -1267,8 +1360,7 @@ where
             // Try to create a runtime host for the data source
             let host = self
                 .ctx
-                .add_dynamic_data_source(&self.logger, data_source.clone())
-                .non_deterministic()?;
+                .add_dynamic_data_source(&self.logger, data_source.clone())?;
 
             match host {
                 Some(host) => {
@@ -1564,7 +1656,9 @@ where
                     let err = match err {
                         // Ignoring `PossibleReorg` isn't so bad since the subgraph will retry
                         // non-deterministic errors.
-                        MappingError::PossibleReorg(e) | MappingError::Unknown(e) => e,
+                        MappingError::PossibleReorg(e)
+                        | MappingError::Deterministic(e)
+                        | MappingError::Unknown(e) => e,
                     };
                     return Err(err.context("failed to process trigger".to_string()));
                 }
@@ -1771,6 +1865,19 @@ async fn update_proof_of_indexing(
     Ok(())
 }
 
+/// The stored form of the dynamic data sources whose hosts matched one of
+/// the `runnables`
+fn matched_dynamic_data_sources<C: Blockchain>(
+    runnables: &[RunnableTriggers<'_, C>],
+) -> Vec<StoredDynamicDataSource> {
+    runnables
+        .iter()
+        .flat_map(|runnable| runnable.hosted_triggers.iter())
+        .filter(|hosted| hosted.host.creation_block_number().is_some())
+        .map(|hosted| hosted.host.data_source().as_stored_dynamic_data_source())
+        .collect()
+}
+
 /// Checks if the Deployment BlockPtr is within N blocks of the chain head or ahead.
 fn close_to_chain_head(
     deployment_head_ptr: &BlockPtr,
@@ -2,13 +2,13 @@ use crate::subgraph::inputs::IndexingInputs;
 use anyhow::bail;
 use graph::blockchain::block_stream::{BlockStream, BufferedBlockStream};
 use graph::blockchain::{Blockchain, TriggerFilterWrapper};
-use graph::prelude::{CheapClone, Error, SubgraphInstanceMetrics};
+use graph::prelude::{CheapClone, Error, RunnerMetrics};
 use std::sync::Arc;
 
 pub async fn new_block_stream<C: Blockchain>(
     inputs: &IndexingInputs<C>,
     filter: TriggerFilterWrapper<C>,
-    metrics: &SubgraphInstanceMetrics,
+    metrics: &RunnerMetrics,
 ) -> Result<Box<dyn BlockStream<C>>, Error> {
     let is_firehose = inputs.chain.chain_client().is_firehose();
 
@@ -24,13 +24,14 @@ pub async fn new_block_stream<C: Blockchain>(
         )
         .await
     {
-        Ok(block_stream) => Ok(BufferedBlockStream::spawn_from_stream(
+        Ok(block_stream) => Ok(BufferedBlockStream::spawn_with_metrics(
             block_stream.buffer_size_hint(),
             block_stream,
+            Some(metrics.stream.buffer.clone()),
         )),
         Err(e) => {
             if is_firehose {
-                metrics.firehose_connection_errors.inc();
+                metrics.subgraph.firehose_connection_errors.inc();
             }
             bail!(e);
         }
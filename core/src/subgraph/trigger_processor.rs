@@ -5,11 +5,16 @@ use graph::components::store::SubgraphFork;
 use graph::components::subgraph::{MappingError, SharedProofOfIndexing};
 use graph::components::trigger_processor::{HostedTrigger, RunnableTriggers};
 use graph::data_source::TriggerData;
+use graph::prelude::tokio::runtime::{Handle, RuntimeFlavor};
+use graph::prelude::tokio::task::block_in_place;
 use graph::prelude::tokio::time::Instant;
 use graph::prelude::{
-    BlockState, RuntimeHost, RuntimeHostBuilder, SubgraphInstanceMetrics, TriggerProcessor,
+    BlockState, ENV_VARS, RuntimeHost, RuntimeHostBuilder, SubgraphInstanceMetrics,
+    TriggerMatchStats, TriggerProcessor, lazy_static,
 };
 use graph::slog::Logger;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use std::marker::PhantomData;
 use std::sync::Arc;
 
@@ -107,6 +112,47 @@ where
     }
 }
 
+lazy_static! {
+    /// The threads that decode triggers in parallel. They are shared by all
+    /// subgraphs so that `GRAPH_TRIGGER_DECODE_WORKERS` bounds how many
+    /// threads are used for decoding overall
+    static ref DECODE_POOL: Option<ThreadPool> = (ENV_VARS.trigger_decode_workers > 1)
+        .then(|| {
+            ThreadPoolBuilder::new()
+                .num_threads(ENV_VARS.trigger_decode_workers)
+                .thread_name(|i| format!("trigger-decode-{i}"))
+                .build()
+                .expect("failed to start trigger decoding threads")
+        });
+}
+
+/// Apply `f` to all `items`, on the threads of `pool` if there is one. The
+/// results are in the same order as `items`, regardless of how the work is
+/// split up.
+///
+/// While the pool does the work, the calling thread waits for it. When
+/// that thread belongs to a multi-threaded tokio runtime, we tell tokio so
+/// that it can move its other tasks elsewhere; a single-threaded runtime
+/// can't do that, and we therefore do all the work on the calling thread.
+fn map_in_order<I, R, F>(items: &[I], pool: Option<&ThreadPool>, f: F) -> Vec<R>
+where
+    I: Sync,
+    R: Send,
+    F: Fn(&I) -> R + Send + Sync,
+{
+    let pool = match pool {
+        Some(pool) if items.len() > 1 => pool,
+        _ => return items.iter().map(f).collect(),
+    };
+
+    let decode = || pool.install(|| items.par_iter().map(&f).collect());
+    match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::CurrentThread) => items.iter().map(&f).collect(),
+        Ok(_) => block_in_place(decode),
+        Err(_) => decode(),
+    }
+}
+
 impl<C: Blockchain, T: RuntimeHostBuilder<C>> Decoder<C, T> {
     fn match_and_decode_inner<'a>(
         &'a self,
@@ -115,14 +161,28 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> Decoder<C, T> {
         trigger: &TriggerData<C>,
         hosts: Box<dyn Iterator<Item = &'a T::Host> + Send + 'a>,
         subgraph_metrics: &Arc<SubgraphInstanceMetrics>,
+        mut stats: Option<&mut TriggerMatchStats>,
     ) -> Result<Vec<HostedTrigger<'a, C>>, MappingError> {
         let mut host_mapping = vec![];
 
         {
             let _section = subgraph_metrics.stopwatch.start_section("match_and_decode");
 
-            for host in hosts {
-                let mapping_trigger = match host.match_and_decode(trigger, block, logger)? {
+            // Decoding can happen in parallel, but the hosted triggers
+            // must be in the same order as `hosts`
+            let hosts: Vec<_> = hosts.collect();
+            let decoded = map_in_order(&hosts, DECODE_POOL.as_ref(), |host| {
+                host.match_and_decode(trigger, block, logger)
+            });
+
+            for (host, mapping_trigger) in hosts.into_iter().zip(decoded) {
+                let mapping_trigger = mapping_trigger?;
+
+                if let Some(stats) = stats.as_mut() {
+                    stats.record(host.data_source().name(), mapping_trigger.is_some());
+                }
+
+                let mapping_trigger = match mapping_trigger {
                     // Trigger matches and was decoded as a mapping trigger.
                     Some(mapping_trigger) => mapping_trigger,
 
@@ -147,7 +207,19 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> Decoder<C, T> {
         hosts: Box<dyn Iterator<Item = &'a T::Host> + Send + 'a>,
         subgraph_metrics: &Arc<SubgraphInstanceMetrics>,
     ) -> Result<RunnableTriggers<'a, C>, MappingError> {
-        self.match_and_decode_inner(logger, block, &trigger, hosts, subgraph_metrics)
+        self.match_and_decode_sampled(logger, block, trigger, hosts, subgraph_metrics, None)
+    }
+
+    fn match_and_decode_sampled<'a>(
+        &'a self,
+        logger: &Logger,
+        block: &Arc<C::Block>,
+        trigger: TriggerData<C>,
+        hosts: Box<dyn Iterator<Item = &'a T::Host> + Send + 'a>,
+        subgraph_metrics: &Arc<SubgraphInstanceMetrics>,
+        stats: Option<&mut TriggerMatchStats>,
+    ) -> Result<RunnableTriggers<'a, C>, MappingError> {
+        self.match_and_decode_inner(logger, block, &trigger, hosts, subgraph_metrics, stats)
             .map_err(|e| e.add_trigger_context(&trigger))
             .map(|hosted_triggers| RunnableTriggers {
                 trigger,
@@ -167,15 +239,264 @@ impl<C: Blockchain, T: RuntimeHostBuilder<C>> Decoder<C, T> {
         F: Fn(&TriggerData<C>) -> Box<dyn Iterator<Item = &'a T::Host> + Send + 'a>,
     {
         let mut runnables = vec![];
+        let mut stats = TriggerMatchStats::for_block(block.number());
         for trigger in triggers {
             let hosts = hosts_filter(&trigger);
-            match self.match_and_decode(logger, block, trigger, hosts, metrics) {
+            match self.match_and_decode_sampled(
+                logger,
+                block,
+                trigger,
+                hosts,
+                metrics,
+                stats.as_mut(),
+            ) {
                 Ok(runnable_triggers) => runnables.push(runnable_triggers),
                 Err(e) => return Err(e),
             }
         }
+        if let Some(stats) = &stats {
+            metrics.observe_trigger_match_stats(stats);
+        }
         self.hook
             .after_decode(logger, &block.ptr(), runnables, metrics)
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Error;
+    use async_trait::async_trait;
+    use graph::blockchain::mock::{
+        MockBlock, MockBlockchain, MockDataSource, MockMappingTrigger, MockTriggerData,
+    };
+    use graph::blockchain::{Block as _, NoopDecoderHook};
+    use graph::components::metrics::stopwatch::StopwatchMetrics;
+    use graph::components::metrics::subgraph::DeploymentStatusMetric;
+    use graph::components::store::{DeploymentId, DeploymentLocator, SubgraphFork};
+    use graph::components::subgraph::{MappingError, SharedProofOfIndexing};
+    use graph::data_source::{
+        DataSource, DataSourceTemplate, MappingTrigger, TriggerData, TriggerWithHandler,
+    };
+    use graph::futures01::sync::mpsc;
+    use graph::prelude::{
+        BlockNumber, BlockState, DeploymentHash, HostMetrics, MetricsRegistry, RuntimeHost,
+        RuntimeHostBuilder, SubgraphInstanceMetrics, TriggerMatchStats,
+    };
+    use graph::semver::Version;
+    use graph::slog::{Logger, o};
+    use rayon::ThreadPoolBuilder;
+
+    use super::{Decoder, map_in_order};
+
+    /// A host that matches every trigger if `matches` is set, and no
+    /// trigger otherwise
+    struct MockHost {
+        data_source: DataSource<MockBlockchain>,
+        matches: bool,
+    }
+
+    impl MockHost {
+        fn new(name: &str, matches: bool) -> Self {
+            let data_source = DataSource::Onchain(MockDataSource {
+                name: name.to_string(),
+                api_version: Version::new(1, 0, 0),
+                kind: "mock/kind".into(),
+                network: None,
+            });
+            MockHost {
+                data_source,
+                matches,
+            }
+        }
+    }
+
+    impl PartialEq for MockHost {
+        fn eq(&self, other: &Self) -> bool {
+            self.data_source.name() == other.data_source.name()
+        }
+    }
+
+    #[async_trait]
+    impl RuntimeHost<MockBlockchain> for MockHost {
+        fn data_source(&self) -> &DataSource<MockBlockchain> {
+            &self.data_source
+        }
+
+        fn match_and_decode(
+            &self,
+            _trigger: &TriggerData<MockBlockchain>,
+            block: &Arc<MockBlock>,
+            _logger: &Logger,
+        ) -> Result<Option<TriggerWithHandler<MappingTrigger<MockBlockchain>>>, Error> {
+            Ok(self.matches.then(|| {
+                TriggerWithHandler::new(
+                    MappingTrigger::Onchain(MockMappingTrigger {}),
+                    "handle".to_string(),
+                    block.ptr(),
+                    block.timestamp(),
+                )
+            }))
+        }
+
+        async fn process_mapping_trigger(
+            &self,
+            _logger: &Logger,
+            _trigger: TriggerWithHandler<MappingTrigger<MockBlockchain>>,
+            state: BlockState,
+            _proof_of_indexing: SharedProofOfIndexing,
+            _debug_fork: &Option<Arc<dyn SubgraphFork>>,
+            _instrument: bool,
+        ) -> Result<BlockState, MappingError> {
+            Ok(state)
+        }
+
+        fn creation_block_number(&self) -> Option<BlockNumber> {
+            None
+        }
+
+        fn done_at(&self) -> Option<BlockNumber> {
+            None
+        }
+
+        fn set_done_at(&self, _block: Option<BlockNumber>) {}
+
+        fn host_metrics(&self) -> Arc<HostMetrics> {
+            unimplemented!("matching triggers does not use host metrics")
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockHostBuilder;
+
+    impl RuntimeHostBuilder<MockBlockchain> for MockHostBuilder {
+        type Host = MockHost;
+        type Req = ();
+
+        fn build(
+            &self,
+            _network_name: String,
+            _subgraph_id: DeploymentHash,
+            _data_source: DataSource<MockBlockchain>,
+            _top_level_templates: Arc<Vec<DataSourceTemplate<MockBlockchain>>>,
+            _mapping_request_sender: mpsc::Sender<()>,
+            _metrics: Arc<HostMetrics>,
+        ) -> Result<MockHost, Error> {
+            unimplemented!("hosts are created directly")
+        }
+
+        fn spawn_mapping(
+            _raw_module: &[u8],
+            _logger: Logger,
+            _subgraph_id: DeploymentHash,
+            _metrics: Arc<HostMetrics>,
+        ) -> Result<mpsc::Sender<()>, Error> {
+            unimplemented!("hosts do not run mappings")
+        }
+    }
+
+    fn subgraph_metrics(logger: &Logger) -> Arc<SubgraphInstanceMetrics> {
+        let registry = Arc::new(MetricsRegistry::mock());
+        let hash = DeploymentHash::new("QmTriggerMatchStats").unwrap();
+        let locator = DeploymentLocator::new(DeploymentId::new(1), hash.clone());
+        let stopwatch = StopwatchMetrics::new(
+            logger.clone(),
+            hash.clone(),
+            "test",
+            registry.clone(),
+            "primary".to_string(),
+        );
+        let status = DeploymentStatusMetric::register(&registry, &locator);
+        Arc::new(SubgraphInstanceMetrics::new(
+            registry,
+            hash.as_str(),
+            stopwatch,
+            status,
+        ))
+    }
+
+    #[test]
+    fn matching_records_trigger_match_stats() {
+        let logger = Logger::root(graph::slog::Discard, o!());
+        let metrics = subgraph_metrics(&logger);
+        let decoder = Decoder::<MockBlockchain, MockHostBuilder>::new(NoopDecoderHook);
+        let block = Arc::new(MockBlock { number: 1, size: 0 });
+        let hosts = [
+            MockHost::new("Token", true),
+            MockHost::new("Factory", false),
+        ];
+
+        // Three triggers, each tested against both data sources
+        let mut stats = TriggerMatchStats::default();
+        for _ in 0..3 {
+            let runnable = decoder
+                .match_and_decode_sampled(
+                    &logger,
+                    &block,
+                    TriggerData::Onchain(MockTriggerData),
+                    Box::new(hosts.iter()),
+                    &metrics,
+                    Some(&mut stats),
+                )
+                .unwrap();
+            assert_eq!(1, runnable.hosted_triggers.len());
+        }
+
+        assert_eq!(stats.tested("Token"), 3);
+        assert_eq!(stats.matched("Token"), 3);
+        assert_eq!(stats.tested("Factory"), 3);
+        assert_eq!(stats.matched("Factory"), 0);
+    }
+
+    #[test]
+    fn parallel_decoding_matches_serial_decoding() {
+        // Something that is not trivial to compute and depends on the item
+        let decode = |item: &u64| -> Result<Option<u64>, String> {
+            match item % 7 {
+                0 => Ok(None),
+                6 => Err(format!("cannot decode {}", item)),
+                _ => Ok(Some(
+                    (0..*item).fold(*item, |acc, x| acc.wrapping_mul(31) ^ x),
+                )),
+            }
+        };
+
+        for len in [0, 1, 2, 7, 23, 100] {
+            let items: Vec<u64> = (0..len).collect();
+            let serial = map_in_order(&items, None, decode);
+            assert_eq!(len as usize, serial.len());
+            for workers in [2, 3, 4, 8, 200] {
+                let pool = ThreadPoolBuilder::new()
+                    .num_threads(workers)
+                    .build()
+                    .unwrap();
+                let parallel = map_in_order(&items, Some(&pool), decode);
+                assert_eq!(
+                    serial, parallel,
+                    "{} workers decoding {} items",
+                    workers, len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_decoding_uses_the_pool() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let items: Vec<u32> = (0..8).collect();
+        let threads = map_in_order(&items, Some(&pool), |_| std::thread::current().id());
+
+        assert!(!threads.contains(&std::thread::current().id()));
+    }
+
+    #[tokio::test]
+    async fn single_threaded_runtime_decodes_on_the_calling_thread() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let items: Vec<u32> = (0..8).collect();
+        let threads = map_in_order(&items, Some(&pool), |_| std::thread::current().id());
+
+        assert!(threads.iter().all(|id| *id == std::thread::current().id()));
+    }
+}
@@ -5,6 +5,7 @@ use clap::Parser;
 use graph::{
     components::link_resolver::FileLinkResolver,
     env::EnvVars,
+    ipfs::{DeploymentIpfsClients, SwappableIpfsClient},
     prelude::{CheapClone, DeploymentHash, LinkResolver, SubgraphName},
     slog::{Logger, error, info},
 };
@@ -156,9 +157,11 @@ async fn run_graph_node(
     let ipfs_client = graph::ipfs::new_ipfs_client(&opt.ipfs, &metrics_registry, logger)
         .await
         .unwrap_or_else(|err| panic!("Failed to create IPFS client: {err:#}"));
+    let ipfs_client = Arc::new(SwappableIpfsClient::new(ipfs_client));
 
     let ipfs_service = ipfs_service(
         ipfs_client.cheap_clone(),
+        DeploymentIpfsClients::default(),
         env_vars.mappings.max_ipfs_file_bytes,
         env_vars.mappings.ipfs_timeout,
         env_vars.mappings.ipfs_request_limit,
@@ -168,6 +171,7 @@ async fn run_graph_node(
         logger.clone(),
         opt,
         env_vars,
+        ipfs_client,
         ipfs_service,
         link_resolver,
         Some(subgraph_updates_channel),
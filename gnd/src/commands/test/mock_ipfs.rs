@@ -29,7 +29,11 @@ impl IpfsClient for MockIpfsClient {
 
     async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
         let path = match req {
-            IpfsRequest::Cat(p) | IpfsRequest::GetBlock(p) => p,
+            IpfsRequest::Cat(p)
+            | IpfsRequest::CatRange(p, _)
+            | IpfsRequest::GetBlock(p)
+            | IpfsRequest::Ls(p)
+            | IpfsRequest::CatIfNoneMatch(p, _) => p,
         };
 
         match self.files.get(&path) {
@@ -6,14 +6,21 @@ use anyhow::anyhow;
 use graph::blockchain::{self, ChainIdentifier, HostFn, HostFnCtx};
 use graph::data_source;
 use graph::futures03::FutureExt;
-use graph::prelude::EthereumCallCache;
+use graph::prelude::{BlockNumber, ChainStore, EthereumCallCache};
 use graph::runtime::HostExportError;
 use graph_chain_ethereum::Chain;
 use graph_chain_ethereum::chain::{EthereumRuntimeAdapterBuilder, RuntimeAdapterBuilder};
 use graph_chain_ethereum::network::EthereumNetworkAdapters;
 use std::sync::Arc;
 
-const WRAPPED_HOST_FNS: &[&str] = &["ethereum.call", "ethereum.getBalance", "ethereum.hasCode"];
+const WRAPPED_HOST_FNS: &[&str] = &[
+    "ethereum.call",
+    "ethereum.callAtBlock",
+    "ethereum.feeHistory",
+    "ethereum.getBalance",
+    "ethereum.getLogs",
+    "ethereum.hasCode",
+];
 
 pub struct TestRuntimeAdapterBuilder;
 
@@ -23,9 +30,16 @@ impl RuntimeAdapterBuilder for TestRuntimeAdapterBuilder {
         eth_adapters: Arc<EthereumNetworkAdapters>,
         call_cache: Arc<dyn EthereumCallCache>,
         chain_identifier: Arc<ChainIdentifier>,
+        chain_store: Arc<dyn ChainStore>,
+        reorg_threshold: BlockNumber,
     ) -> Arc<dyn blockchain::RuntimeAdapter<Chain>> {
-        let real_adapter =
-            EthereumRuntimeAdapterBuilder {}.build(eth_adapters, call_cache, chain_identifier);
+        let real_adapter = EthereumRuntimeAdapterBuilder {}.build(
+            eth_adapters,
+            call_cache,
+            chain_identifier,
+            chain_store,
+            reorg_threshold,
+        );
         Arc::new(TestRuntimeAdapter { real_adapter })
     }
 }
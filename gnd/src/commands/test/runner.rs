@@ -39,7 +39,7 @@ use graph::data::subgraph::schema::SubgraphError;
 use graph::endpoint::EndpointMetrics;
 use graph::env::EnvVars;
 use graph::firehose::{FirehoseEndpoint, FirehoseEndpoints, SubgraphLimit};
-use graph::ipfs::{ContentPath, IpfsMetrics};
+use graph::ipfs::{ContentPath, DeploymentIpfsClients, IpfsMetrics};
 use graph::prelude::{
     DeploymentHash, LoggerFactory, NodeId, SubgraphCountMetric, SubgraphName, SubgraphRegistrar,
     SubgraphStore as SubgraphStoreTrait, SubgraphVersionSwitchingMode,
@@ -711,6 +711,7 @@ async fn setup_context(
 
     let ipfs_service = ipfs_service(
         ipfs_client,
+        DeploymentIpfsClients::default(),
         env_vars.mappings.max_ipfs_file_bytes,
         env_vars.mappings.ipfs_timeout,
         env_vars.mappings.ipfs_request_limit,
@@ -0,0 +1,265 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Error;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+
+use crate::env::ENV_VARS;
+
+/// Every zstd frame starts with these bytes; since JSON text can never
+/// start with them, they tell the two formats apart
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The zstd default, which compresses JSON well without slowing down
+/// block ingestion noticeably
+const ZSTD_LEVEL: i32 = 3;
+
+/// A compressed block is stored as an object with this key and the base64
+/// encoded compressed block as its value. Since the chain store keeps block
+/// data in a `jsonb` column, the compressed block has to remain valid JSON.
+const COMPRESSED_DATA_KEY: &str = "zstd";
+
+/// The format in which a chain store writes block data. Each chain can
+/// set its own format with `block_data_format` in its configuration.
+///
+/// Readers never need to know the format that was used to write block
+/// data since `decode_block_data` detects it. Block data that was written
+/// before there was a choice of formats is always JSON.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockDataFormat {
+    /// Plain JSON text
+    #[default]
+    Json,
+    /// zstd-compressed JSON, which is much smaller for large blocks
+    Zstd,
+}
+
+impl BlockDataFormat {
+    /// The format for chains that do not configure one, `zstd` if
+    /// `GRAPH_STORE_COMPRESS_BLOCK_DATA` is set and `json` otherwise
+    pub fn from_env() -> Self {
+        if ENV_VARS.store.compress_block_data {
+            BlockDataFormat::Zstd
+        } else {
+            BlockDataFormat::Json
+        }
+    }
+
+    /// Prepare block data as the chain store keeps it for writing in this
+    /// format
+    pub fn encode_block(&self, data: serde_json::Value) -> serde_json::Value {
+        match self {
+            BlockDataFormat::Json => data,
+            BlockDataFormat::Zstd => compress_block(data),
+        }
+    }
+
+    /// The format in which `bytes` were encoded
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            BlockDataFormat::Zstd
+        } else {
+            BlockDataFormat::Json
+        }
+    }
+}
+
+impl fmt::Display for BlockDataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockDataFormat::Json => write!(f, "json"),
+            BlockDataFormat::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for BlockDataFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(BlockDataFormat::Json),
+            "zstd" => Ok(BlockDataFormat::Zstd),
+            _ => Err(format!(
+                "invalid block data format '{}', expected 'json' or 'zstd'",
+                s
+            )),
+        }
+    }
+}
+
+/// Serialize `data` in `format`
+pub fn encode_block_data<T: Serialize>(
+    data: &T,
+    format: BlockDataFormat,
+) -> Result<Vec<u8>, Error> {
+    let json = serde_json::to_vec(data)?;
+    match format {
+        BlockDataFormat::Json => Ok(json),
+        BlockDataFormat::Zstd => Ok(zstd::encode_all(json.as_slice(), ZSTD_LEVEL)?),
+    }
+}
+
+/// Deserialize block data that was serialized with `encode_block_data` in
+/// any format
+pub fn decode_block_data<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    match BlockDataFormat::detect(bytes) {
+        BlockDataFormat::Json => Ok(serde_json::from_slice(bytes)?),
+        BlockDataFormat::Zstd => {
+            let json = zstd::decode_all(bytes)?;
+            Ok(serde_json::from_slice(&json)?)
+        }
+    }
+}
+
+/// Compress the `block` entry of block data as the chain store keeps it,
+/// i.e., `{ "block": .., "transaction_receipts": .. }`. The compressed
+/// block only keeps its `timestamp` in the clear so that the chain store
+/// can still read it with JSON operators. Transaction receipts are left
+/// alone since the chain store queries them, and so are blocks without
+/// data, which the chain store uses to find blocks whose data was removed.
+pub fn compress_block(mut data: serde_json::Value) -> serde_json::Value {
+    let Some(block) = data.get_mut("block") else {
+        return data;
+    };
+    if !block.is_object() || block.get("data").is_some_and(|data| data.is_null()) {
+        return data;
+    }
+
+    if let Ok(bytes) = encode_block_data(block, BlockDataFormat::Zstd) {
+        let timestamp = block.get("timestamp").cloned();
+        *block = json!({
+            "timestamp": timestamp,
+            COMPRESSED_DATA_KEY: STANDARD.encode(bytes)
+        });
+    }
+    data
+}
+
+/// Undo `compress_block` for the `block` entry of block data; blocks that
+/// are not compressed are returned unchanged
+pub fn decompress_block(block: serde_json::Value) -> Result<serde_json::Value, Error> {
+    match block
+        .get(COMPRESSED_DATA_KEY)
+        .and_then(|data| data.as_str())
+    {
+        Some(encoded) => {
+            let bytes = STANDARD.decode(encoded)?;
+            decode_block_data(&bytes)
+        }
+        None => Ok(block),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{ChainStoreBlock, ChainStoreData};
+
+    fn chain_store_data() -> ChainStoreData {
+        let transactions: Vec<_> = (0..200)
+            .map(|i| json!({ "hash": format!("0x{:064x}", i), "input": "0x" }))
+            .collect();
+        ChainStoreData {
+            block: ChainStoreBlock::new(
+                1_700_000_000,
+                json!({ "number": "0x10", "transactions": transactions }),
+            ),
+        }
+    }
+
+    #[test]
+    fn round_trips_in_both_formats() {
+        let data = chain_store_data();
+
+        for format in [BlockDataFormat::Json, BlockDataFormat::Zstd] {
+            let bytes = encode_block_data(&data, format).unwrap();
+            assert_eq!(format, BlockDataFormat::detect(&bytes));
+            assert_eq!(data, decode_block_data::<ChainStoreData>(&bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn zstd_format_is_smaller() {
+        let data = chain_store_data();
+
+        let json = encode_block_data(&data, BlockDataFormat::Json).unwrap();
+        let zstd = encode_block_data(&data, BlockDataFormat::Zstd).unwrap();
+        assert!(zstd.len() < json.len() / 2);
+    }
+
+    #[test]
+    fn decodes_existing_json() {
+        let bytes = br#"{ "block": { "timestamp": "0x64", "data": { "number": "0x1" } } }"#;
+
+        let data: ChainStoreData = decode_block_data(bytes).unwrap();
+        assert_eq!(100, data.block.timestamp());
+
+        // Block data that is not `ChainStoreData` can be decoded, too
+        let value: serde_json::Value = decode_block_data(bytes).unwrap();
+        assert_eq!(json!("0x1"), value["block"]["data"]["number"]);
+    }
+
+    fn rpc_block_data() -> serde_json::Value {
+        let transactions: Vec<_> = (0..200)
+            .map(|i| json!({ "hash": format!("0x{:064x}", i), "input": "0x" }))
+            .collect();
+        json!({
+            "block": { "number": "0x10", "timestamp": "0x64", "transactions": transactions },
+            "transaction_receipts": [{ "transactionHash": "0x01" }]
+        })
+    }
+
+    #[test]
+    fn round_trips_compressed_blocks() {
+        let data = rpc_block_data();
+        let compressed = compress_block(data.clone());
+
+        assert!(compressed.to_string().len() < data.to_string().len() / 2);
+        // The timestamp and the receipts stay readable
+        assert_eq!(json!("0x64"), compressed["block"]["timestamp"]);
+        assert_eq!(
+            data["transaction_receipts"],
+            compressed["transaction_receipts"]
+        );
+        assert!(compressed["block"].get("transactions").is_none());
+
+        let block = decompress_block(compressed["block"].clone()).unwrap();
+        assert_eq!(data["block"], block);
+    }
+
+    #[test]
+    fn encodes_blocks_in_the_chain_format() {
+        let data = rpc_block_data();
+
+        assert_eq!(data, BlockDataFormat::Json.encode_block(data.clone()));
+        assert_eq!(
+            compress_block(data.clone()),
+            BlockDataFormat::Zstd.encode_block(data)
+        );
+    }
+
+    #[test]
+    fn leaves_blocks_without_data_alone() {
+        let data = json!({ "block": { "timestamp": "100", "data": null } });
+        assert_eq!(data, compress_block(data.clone()));
+
+        let data = json!({ "number": "0x1" });
+        assert_eq!(data, compress_block(data.clone()));
+
+        // Blocks that were written without compression read as they are
+        let block = rpc_block_data()["block"].clone();
+        assert_eq!(block, decompress_block(block.clone()).unwrap());
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!(Ok(BlockDataFormat::Json), "json".parse());
+        assert_eq!(Ok(BlockDataFormat::Zstd), "Zstd".parse());
+        assert!("bincode".parse::<BlockDataFormat>().is_err());
+    }
+}
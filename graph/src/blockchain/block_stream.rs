@@ -6,8 +6,10 @@ use async_trait::async_trait;
 use futures03::Stream;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::Notify;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
 use super::{Block, BlockPtr, Blockchain, Trigger, TriggerFilterWrapper};
@@ -16,12 +18,17 @@ use crate::components::store::{BlockNumber, DeploymentLocator, SourceableStore};
 use crate::data::subgraph::UnifiedMappingApiVersion;
 use crate::firehose::{self, FirehoseEndpoint};
 use crate::futures03::stream::StreamExt as _;
+use crate::prelude::*;
+use crate::prometheus::{IntCounter, IntGauge, labels};
 use crate::schema::{EntityType, InputSchema};
-use crate::{prelude::*, prometheus::labels};
 
 pub const BUFFERED_BLOCK_STREAM_SIZE: usize = 100;
 pub const FIREHOSE_BUFFER_STREAM_SIZE: usize = 1;
 
+/// How often a `BufferedBlockStream` updates the time since the last block
+/// while it waits for the next block
+const TIME_SINCE_LAST_BLOCK_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct BufferedBlockStream<C: Blockchain> {
     inner: Pin<Box<dyn Stream<Item = Result<BlockStreamEvent<C>, BlockStreamError>> + Send>>,
 }
@@ -30,15 +37,57 @@ impl<C: Blockchain + 'static> BufferedBlockStream<C> {
     pub fn spawn_from_stream(
         size_hint: usize,
         stream: Box<dyn BlockStream<C>>,
+    ) -> Box<dyn BlockStream<C>> {
+        Self::spawn_with_metrics(size_hint, stream, None)
+    }
+
+    /// Like `spawn_from_stream`, but records how events flow through the
+    /// buffer in `metrics`
+    pub fn spawn_with_metrics(
+        size_hint: usize,
+        stream: Box<dyn BlockStream<C>>,
+        metrics: Option<BlockStreamBufferMetrics>,
+    ) -> Box<dyn BlockStream<C>> {
+        Self::spawn_with_limits(
+            size_hint,
+            ENV_VARS.block_stream_buffer_bytes,
+            stream,
+            metrics,
+        )
+    }
+
+    /// Like `spawn_with_metrics`, but the buffer holds at most `max_bytes`
+    /// worth of blocks according to `Block::byte_size`, in addition to
+    /// holding at most `size_hint` events. A `max_bytes` of 0 turns the
+    /// limit on the size off.
+    pub fn spawn_with_limits(
+        size_hint: usize,
+        max_bytes: usize,
+        stream: Box<dyn BlockStream<C>>,
+        metrics: Option<BlockStreamBufferMetrics>,
     ) -> Box<dyn BlockStream<C>> {
         let (sender, receiver) =
             mpsc::channel::<Result<BlockStreamEvent<C>, BlockStreamError>>(size_hint);
-        crate::spawn(async move { BufferedBlockStream::stream_blocks(stream, sender).await });
+        let limit = (max_bytes > 0).then(|| Arc::new(ByteLimit::new(max_bytes)));
+        let producer_metrics = metrics.clone();
+        let producer_limit = limit.clone();
+        crate::spawn(async move {
+            BufferedBlockStream::stream_blocks(stream, sender, producer_metrics, producer_limit)
+                .await
+        });
+
+        Box::new(BufferedBlockStream::with_metrics(receiver, metrics, limit))
+    }
 
-        Box::new(BufferedBlockStream::new(receiver))
+    pub fn new(receiver: Receiver<Result<BlockStreamEvent<C>, BlockStreamError>>) -> Self {
+        Self::with_metrics(receiver, None, None)
     }
 
-    pub fn new(mut receiver: Receiver<Result<BlockStreamEvent<C>, BlockStreamError>>) -> Self {
+    fn with_metrics(
+        mut receiver: Receiver<Result<BlockStreamEvent<C>, BlockStreamError>>,
+        metrics: Option<BlockStreamBufferMetrics>,
+        limit: Option<Arc<ByteLimit>>,
+    ) -> Self {
         let inner = stream! {
             loop {
                 let event = match receiver.recv().await {
@@ -46,6 +95,13 @@ impl<C: Blockchain + 'static> BufferedBlockStream<C> {
                     None => return,
                 };
 
+                if let Some(metrics) = &metrics {
+                    metrics.observe_consumed(receiver.len());
+                }
+                if let Some(limit) = &limit {
+                    limit.release(limit.cost(&event));
+                }
+
                 yield event
             }
         };
@@ -55,13 +111,52 @@ impl<C: Blockchain + 'static> BufferedBlockStream<C> {
         }
     }
 
-    pub async fn stream_blocks(
+    async fn stream_blocks(
         mut stream: Box<dyn BlockStream<C>>,
         sender: Sender<Result<BlockStreamEvent<C>, BlockStreamError>>,
+        metrics: Option<BlockStreamBufferMetrics>,
+        limit: Option<Arc<ByteLimit>>,
     ) -> Result<(), Error> {
-        while let Some(event) = stream.next().await {
+        let mut ticker = tokio::time::interval(TIME_SINCE_LAST_BLOCK_INTERVAL);
+
+        loop {
+            // While waiting for the next event, keep the time since the
+            // last block current
+            let event = tokio::select! {
+                event = stream.next() => event,
+                _ = ticker.tick(), if metrics.is_some() => {
+                    if let Some(metrics) = &metrics {
+                        metrics.update_time_since_last_block();
+                    }
+                    continue;
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+            if let Some(metrics) = &metrics {
+                metrics.observe_arrival();
+            }
+
+            // Wait until the consumer has made enough room before fetching
+            // more blocks from `stream`. If the consumer goes away while we
+            // wait, no room will ever be made
+            if let Some(limit) = &limit {
+                tokio::select! {
+                    _ = limit.acquire(limit.cost(&event)) => {}
+                    _ = sender.closed() => {
+                        return Err(anyhow!(
+                            "buffered blockstream channel is closed, stopping"
+                        ));
+                    }
+                }
+            }
             match sender.send(event).await {
-                Ok(_) => continue,
+                Ok(_) => {
+                    if let Some(metrics) = &metrics {
+                        metrics.observe_produced(sender.max_capacity() - sender.capacity());
+                    }
+                }
                 Err(err) => {
                     return Err(anyhow!(
                         "buffered blockstream channel is closed, stopping. Err: {}",
@@ -75,6 +170,52 @@ impl<C: Blockchain + 'static> BufferedBlockStream<C> {
     }
 }
 
+/// Limits the total estimated size of the blocks that are waiting in the
+/// buffer of a `BufferedBlockStream`
+struct ByteLimit {
+    max: usize,
+    used: Mutex<usize>,
+    released: Notify,
+}
+
+impl ByteLimit {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            used: Mutex::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    /// How much of the limit `event` uses up. A block that is larger than
+    /// the limit uses up all of it so that it can still pass through an
+    /// empty buffer
+    fn cost<C: Blockchain>(&self, event: &Result<BlockStreamEvent<C>, BlockStreamError>) -> usize {
+        match event {
+            Ok(BlockStreamEvent::ProcessBlock(block, _)) => block.block.byte_size().min(self.max),
+            Ok(BlockStreamEvent::Revert(..)) | Err(_) => 0,
+        }
+    }
+
+    async fn acquire(&self, bytes: usize) {
+        loop {
+            {
+                let mut used = self.used.lock().unwrap();
+                if *used + bytes <= self.max {
+                    *used += bytes;
+                    return;
+                }
+            }
+            self.released.notified().await;
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        *self.used.lock().unwrap() -= bytes;
+        self.released.notify_one();
+    }
+}
+
 impl<C: Blockchain> BlockStream<C> for BufferedBlockStream<C> {
     fn buffer_size_hint(&self) -> usize {
         unreachable!()
@@ -481,9 +622,30 @@ async fn get_entities_for_range(
         .iter()
         .map(|name| schema.entity_type(name))
         .collect();
-    Ok(store
+    // Nothing after the end block of the filter produces triggers
+    if filter.has_expired(from) {
+        return Ok(BTreeMap::new());
+    }
+    let to = match filter.end_block {
+        Some(end_block) => to.min(end_block.saturating_add(1)),
+        None => to,
+    };
+    if from >= to {
+        return Ok(BTreeMap::new());
+    }
+
+    let mut entities = store
         .get_range(entity_types?, CausalityRegion::ONCHAIN, from..to)
-        .await?)
+        .await?;
+
+    if !filter.include_deletions {
+        entities.retain(|_, ops| {
+            ops.retain(|op| op.entity_op != EntityOperationKind::Delete);
+            !ops.is_empty()
+        });
+    }
+
+    Ok(entities)
 }
 
 impl<C: Blockchain> TriggersAdapterWrapper<C> {
@@ -520,6 +682,41 @@ impl<C: Blockchain> TriggersAdapterWrapper<C> {
             .await
     }
 
+    pub async fn next_block_with_triggers(
+        &self,
+        from: BlockNumber,
+        to: BlockNumber,
+        filter: &Arc<TriggerFilterWrapper<C>>,
+    ) -> Result<Option<BlockNumber>, Error> {
+        // Triggers from source subgraphs can't be found by asking the chain
+        if !filter.subgraph_filter.is_empty() {
+            return Ok(Some(from));
+        }
+
+        self.adapter
+            .next_block_with_triggers(from, to, &filter.chain_filter)
+            .await
+    }
+
+    /// Load the block with number `number` without any triggers. Block
+    /// streams emit such blocks to mark their progress through blocks they
+    /// skip. Returns `None` if there is no block with that number
+    pub async fn empty_block(
+        &self,
+        logger: &Logger,
+        number: BlockNumber,
+    ) -> Result<Option<BlockWithTriggers<C>>, Error> {
+        let blocks = self
+            .adapter
+            .load_block_ptrs_by_numbers(logger.clone(), BTreeSet::from([number]))
+            .await?;
+
+        Ok(blocks
+            .into_iter()
+            .next()
+            .map(|block| BlockWithTriggers::new(block, vec![], logger)))
+    }
+
     pub async fn triggers_in_block(
         &self,
         logger: &Logger,
@@ -550,6 +747,10 @@ impl<C: Blockchain> TriggersAdapterWrapper<C> {
             .await
     }
 
+    pub fn estimated_trigger_count(&self, block: &C::Block) -> usize {
+        self.adapter.estimated_trigger_count(block)
+    }
+
     pub async fn is_on_main_chain(&self, ptr: BlockPtr) -> Result<bool, Error> {
         self.adapter.is_on_main_chain(ptr).await
     }
@@ -602,6 +803,19 @@ pub trait TriggersAdapter<C: Blockchain>: Send + Sync {
         filter: &C::TriggerFilter,
     ) -> Result<(Vec<BlockWithTriggers<C>>, BlockNumber), Error>;
 
+    /// Return the first block in `[from, to]` that might contain triggers
+    /// matching `filter`, or `None` if no block in that range does. Block
+    /// streams use this to skip ranges without triggers. The default never
+    /// skips any blocks
+    async fn next_block_with_triggers(
+        &self,
+        from: BlockNumber,
+        _to: BlockNumber,
+        _filter: &C::TriggerFilter,
+    ) -> Result<Option<BlockNumber>, Error> {
+        Ok(Some(from))
+    }
+
     // Used for reprocessing blocks when creating a data source.
     async fn triggers_in_block(
         &self,
@@ -610,6 +824,14 @@ pub trait TriggersAdapter<C: Blockchain>: Send + Sync {
         filter: &C::TriggerFilter,
     ) -> Result<BlockWithTriggers<C>, Error>;
 
+    /// Return a rough estimate of how many triggers `block` contains before
+    /// any filtering. Since matching and decoding triggers is quadratic in the
+    /// number of triggers, this lets callers spot heavy blocks up front. The
+    /// default returns 0, meaning that the chain can not estimate it.
+    fn estimated_trigger_count(&self, _block: &C::Block) -> usize {
+        0
+    }
+
     /// Return `true` if the block with the given hash and number is on the
     /// main chain, i.e., the chain going back from the current chain head.
     async fn is_on_main_chain(&self, ptr: BlockPtr) -> Result<bool, Error>;
@@ -720,6 +942,7 @@ pub struct BlockStreamMetrics {
     pub deployment_head: Box<Gauge>,
     pub reverted_blocks: Gauge,
     pub stopwatch: StopwatchMetrics,
+    pub buffer: BlockStreamBufferMetrics,
 }
 
 impl BlockStreamMetrics {
@@ -749,14 +972,124 @@ impl BlockStreamMetrics {
                 labels.clone(),
             )
             .expect("failed to create `deployment_head` gauge");
+        let buffer = BlockStreamBufferMetrics::new(registry, labels);
         Self {
             deployment_head,
             reverted_blocks,
             stopwatch,
+            buffer,
         }
     }
 }
 
+/// Measures how events flow from a block stream through the buffer of a
+/// `BufferedBlockStream` to the subgraph runner. Since every block stream
+/// implementation is consumed through that buffer, the numbers are
+/// comparable across Firehose and RPC block streams.
+///
+/// The occupancy is the number of events in the buffer. A buffer that is
+/// constantly full means that processing blocks is the bottleneck, one that
+/// is constantly empty means that the block stream can not keep up.
+///
+/// The time since the last block is the time since the block stream last
+/// produced an event. It is also updated while the block stream waits for
+/// the next event, so that a stalled block stream shows up as a growing
+/// value rather than as the interval between the last two events.
+#[derive(Clone)]
+pub struct BlockStreamBufferMetrics {
+    produced: IntCounter,
+    consumed: IntCounter,
+    occupancy: IntGauge,
+    time_since_last_block: Box<Gauge>,
+    last_block: Arc<Mutex<Option<tokio::time::Instant>>>,
+}
+
+impl BlockStreamBufferMetrics {
+    fn new(registry: Arc<MetricsRegistry>, labels: HashMap<String, String>) -> Self {
+        let produced = registry
+            .new_int_counter(
+                "deployment_block_stream_produced",
+                "Counts the events a block stream put into its buffer",
+                labels.clone(),
+            )
+            .expect("failed to create `deployment_block_stream_produced` counter");
+        let consumed = registry
+            .new_int_counter(
+                "deployment_block_stream_consumed",
+                "Counts the events the subgraph runner took out of the block stream buffer",
+                labels.clone(),
+            )
+            .expect("failed to create `deployment_block_stream_consumed` counter");
+        let occupancy = registry
+            .new_int_gauge(
+                "deployment_block_stream_buffer_occupancy",
+                "Number of events in the buffer of a block stream",
+                labels.clone(),
+            )
+            .expect("failed to create `deployment_block_stream_buffer_occupancy` gauge");
+        let time_since_last_block = registry
+            .new_gauge(
+                "deployment_block_stream_time_since_last_block",
+                "Seconds since a block stream last produced an event",
+                labels,
+            )
+            .expect("failed to create `deployment_block_stream_time_since_last_block` gauge");
+
+        Self {
+            produced,
+            consumed,
+            occupancy,
+            time_since_last_block,
+            last_block: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Record that the block stream produced an event
+    pub fn observe_arrival(&self) {
+        *self.last_block.lock().unwrap() = Some(tokio::time::Instant::now());
+        self.time_since_last_block.set(0.0);
+    }
+
+    /// Record that an event was put into the buffer, which now holds
+    /// `buffered` events
+    pub fn observe_produced(&self, buffered: usize) {
+        self.produced.inc();
+        self.occupancy.set(buffered as i64);
+    }
+
+    /// Record that the consumer of the block stream took an event out of
+    /// the buffer, which now holds `buffered` events
+    pub fn observe_consumed(&self, buffered: usize) {
+        self.consumed.inc();
+        self.occupancy.set(buffered as i64);
+    }
+
+    /// Update the time since the last block to how long ago the block
+    /// stream produced its last event
+    pub fn update_time_since_last_block(&self) {
+        if let Some(last_block) = *self.last_block.lock().unwrap() {
+            self.time_since_last_block
+                .set(last_block.elapsed().as_secs_f64());
+        }
+    }
+
+    pub fn produced(&self) -> u64 {
+        self.produced.get()
+    }
+
+    pub fn consumed(&self) -> u64 {
+        self.consumed.get()
+    }
+
+    pub fn occupancy(&self) -> i64 {
+        self.occupancy.get()
+    }
+
+    pub fn time_since_last_block(&self) -> f64 {
+        self.time_since_last_block.get()
+    }
+}
+
 /// Notifications about the chain head advancing. The block ingestor sends
 /// an update on this stream whenever the head of the underlying chain
 /// changes. The updates have no payload, receivers should call
@@ -770,7 +1103,7 @@ pub trait ChainHeadUpdateListener: Send + Sync + 'static {
 
 #[cfg(test)]
 mod test {
-    use std::{collections::HashSet, task::Poll};
+    use std::{collections::HashSet, sync::Arc, task::Poll, time::Duration};
 
     use futures03::{Stream, StreamExt, TryStreamExt};
 
@@ -780,9 +1113,10 @@ mod test {
     };
 
     use super::{
-        BlockStream, BlockStreamError, BlockStreamEvent, BlockWithTriggers, BufferedBlockStream,
-        FirehoseCursor,
+        BlockStream, BlockStreamBufferMetrics, BlockStreamError, BlockStreamEvent,
+        BlockWithTriggers, BufferedBlockStream, FirehoseCursor,
     };
+    use crate::prelude::MetricsRegistry;
 
     #[derive(Debug)]
     struct TestStream {
@@ -807,6 +1141,7 @@ mod test {
                 BlockWithTriggers::<MockBlockchain> {
                     block: MockBlock {
                         number: self.number - 1,
+                        size: 0,
                     },
                     trigger_data: vec![],
                 },
@@ -858,4 +1193,246 @@ mod test {
         );
         assert_eq!(count, blocks.len(), "should not have duplicated blocks");
     }
+
+    /// A block stream that produces the blocks `number..end`, each of
+    /// which reports `size` as its byte size, and then ends
+    struct FiniteStream {
+        number: u64,
+        end: u64,
+        size: usize,
+    }
+
+    impl BlockStream<MockBlockchain> for FiniteStream {
+        fn buffer_size_hint(&self) -> usize {
+            1
+        }
+    }
+
+    impl Stream for FiniteStream {
+        type Item = Result<BlockStreamEvent<MockBlockchain>, BlockStreamError>;
+
+        fn poll_next(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Self::Item>> {
+            if self.number == self.end {
+                return Poll::Ready(None);
+            }
+            self.number += 1;
+            Poll::Ready(Some(Ok(BlockStreamEvent::ProcessBlock(
+                BlockWithTriggers::<MockBlockchain> {
+                    block: MockBlock {
+                        number: self.number - 1,
+                        size: self.size,
+                    },
+                    trigger_data: vec![],
+                },
+                FirehoseCursor::None,
+            ))))
+        }
+    }
+
+    #[crate::test]
+    async fn buffer_occupancy_follows_blocks() {
+        let metrics =
+            BlockStreamBufferMetrics::new(Arc::new(MetricsRegistry::mock()), Default::default());
+
+        let stream = Box::new(FiniteStream {
+            number: 0,
+            end: 3,
+            size: 0,
+        });
+        let mut stream = BufferedBlockStream::spawn_with_metrics(5, stream, Some(metrics.clone()));
+
+        // The buffer is larger than the stream, so the producer puts all
+        // blocks into it without anybody consuming them
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.produced() < 3 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the block stream fills the buffer");
+        assert_eq!(3, metrics.occupancy());
+        assert_eq!(0, metrics.consumed());
+
+        stream.next().await.unwrap().unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(1, metrics.occupancy());
+        assert_eq!(2, metrics.consumed());
+
+        stream.next().await.unwrap().unwrap();
+        assert!(stream.next().await.is_none());
+        assert_eq!(0, metrics.occupancy());
+        assert_eq!(3, metrics.produced());
+        assert_eq!(3, metrics.consumed());
+    }
+
+    /// A stream that produces one block and then never produces anything
+    /// again
+    struct StalledStream {
+        produced: bool,
+    }
+
+    impl BlockStream<MockBlockchain> for StalledStream {
+        fn buffer_size_hint(&self) -> usize {
+            1
+        }
+    }
+
+    impl Stream for StalledStream {
+        type Item = Result<BlockStreamEvent<MockBlockchain>, BlockStreamError>;
+
+        fn poll_next(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Self::Item>> {
+            if self.produced {
+                return Poll::Pending;
+            }
+            self.produced = true;
+            Poll::Ready(Some(Ok(BlockStreamEvent::ProcessBlock(
+                BlockWithTriggers::<MockBlockchain> {
+                    block: MockBlock { number: 0, size: 0 },
+                    trigger_data: vec![],
+                },
+                FirehoseCursor::None,
+            ))))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn time_since_last_block_grows_while_the_stream_stalls() {
+        let metrics =
+            BlockStreamBufferMetrics::new(Arc::new(MetricsRegistry::mock()), Default::default());
+
+        let stream = Box::new(StalledStream { produced: false });
+        let mut stream = BufferedBlockStream::spawn_with_metrics(5, stream, Some(metrics.clone()));
+
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(0.0, metrics.time_since_last_block());
+
+        // The gauge reflects how long ago the last block arrived, even
+        // though no further block arrives
+        tokio::time::sleep(TIME_SINCE_LAST_BLOCK_INTERVAL * 3).await;
+        tokio::task::yield_now().await;
+        let elapsed = metrics.time_since_last_block();
+        assert!(
+            elapsed >= TIME_SINCE_LAST_BLOCK_INTERVAL.as_secs_f64() * 2.0,
+            "time since last block is {elapsed}"
+        );
+    }
+
+    /// A `FiniteStream` that reports when it is dropped
+    struct DropStream {
+        inner: FiniteStream,
+        dropped: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl Drop for DropStream {
+        fn drop(&mut self) {
+            self.dropped
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    impl BlockStream<MockBlockchain> for DropStream {
+        fn buffer_size_hint(&self) -> usize {
+            1
+        }
+    }
+
+    impl Stream for DropStream {
+        type Item = Result<BlockStreamEvent<MockBlockchain>, BlockStreamError>;
+
+        fn poll_next(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Self::Item>> {
+            self.inner.poll_next_unpin(cx)
+        }
+    }
+
+    #[crate::test]
+    async fn producer_stops_when_consumer_is_dropped_at_the_byte_limit() {
+        let metrics =
+            BlockStreamBufferMetrics::new(Arc::new(MetricsRegistry::mock()), Default::default());
+        let dropped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let stream = Box::new(DropStream {
+            inner: FiniteStream {
+                number: 0,
+                end: 100,
+                size: 400,
+            },
+            dropped: dropped.clone(),
+        });
+        let stream =
+            BufferedBlockStream::spawn_with_limits(10, 1000, stream, Some(metrics.clone()));
+
+        // Wait until the producer is blocked on the byte limit
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.produced() < 2 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the block stream fills the buffer");
+
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !dropped.load(std::sync::atomic::Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the producer stops and drops its stream");
+    }
+
+    #[crate::test]
+    async fn large_blocks_hit_the_byte_limit_before_the_count_limit() {
+        let metrics =
+            BlockStreamBufferMetrics::new(Arc::new(MetricsRegistry::mock()), Default::default());
+
+        // The buffer could hold 10 blocks, but only two blocks of 400
+        // bytes fit into 1000 bytes
+        let stream = Box::new(FiniteStream {
+            number: 0,
+            end: 5,
+            size: 400,
+        });
+        let mut stream =
+            BufferedBlockStream::spawn_with_limits(10, 1000, stream, Some(metrics.clone()));
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.produced() < 2 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the block stream fills the buffer");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(2, metrics.produced());
+        assert_eq!(2, metrics.occupancy());
+
+        // Taking a block out of the buffer makes room for exactly one more
+        stream.next().await.unwrap().unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.produced() < 3 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the block stream refills the buffer");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(3, metrics.produced());
+
+        let mut numbers = Vec::new();
+        while let Some(event) = stream.next().await {
+            match event.unwrap() {
+                BlockStreamEvent::ProcessBlock(block, _) => numbers.push(block.block.number),
+                BlockStreamEvent::Revert(..) => panic!("unexpected revert"),
+            }
+        }
+        assert_eq!(vec![1, 2, 3, 4], numbers);
+    }
 }
@@ -43,4 +43,8 @@ where
     fn from_data_sources(_data_sources: &[C::DataSource]) -> Self {
         EmptyNodeCapabilities(PhantomData)
     }
+
+    fn missing(&self, _required: &Self) -> Option<&'static str> {
+        None
+    }
 }
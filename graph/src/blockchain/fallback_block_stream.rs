@@ -0,0 +1,198 @@
+use super::block_stream::{BlockStream, BlockStreamError, BlockStreamEvent};
+use super::{Block, Blockchain};
+use crate::prelude::*;
+use async_stream::try_stream;
+use futures03::future::BoxFuture;
+use futures03::{Stream, StreamExt};
+use std::task::{Context, Poll};
+
+/// A block stream for deployments whose start lies before the first block
+/// that the `live` stream can serve, e.g., because a Firehose endpoint does
+/// not have very old blocks. Blocks before `switch_block` are taken from the
+/// `historical` stream, usually one that is backed by RPC. Once that stream
+/// reaches `switch_block`, it is dropped and all further blocks come from the
+/// `live` stream, which must start at `switch_block`.
+pub struct FallbackBlockStream<C: Blockchain> {
+    stream: Pin<Box<dyn Stream<Item = Result<BlockStreamEvent<C>, BlockStreamError>> + Send>>,
+    buffer_size_hint: usize,
+}
+
+impl<C: Blockchain> FallbackBlockStream<C> {
+    pub fn new(
+        historical: Box<dyn BlockStream<C>>,
+        switch_block: BlockNumber,
+        live: BoxFuture<'static, Result<Box<dyn BlockStream<C>>, Error>>,
+        logger: Logger,
+    ) -> Self {
+        let buffer_size_hint = historical.buffer_size_hint();
+
+        FallbackBlockStream {
+            stream: Box::pin(stream_blocks(historical, switch_block, live, logger)),
+            buffer_size_hint,
+        }
+    }
+}
+
+fn stream_blocks<C: Blockchain>(
+    mut historical: Box<dyn BlockStream<C>>,
+    switch_block: BlockNumber,
+    live: BoxFuture<'static, Result<Box<dyn BlockStream<C>>, Error>>,
+    logger: Logger,
+) -> impl Stream<Item = Result<BlockStreamEvent<C>, BlockStreamError>> {
+    try_stream! {
+        let mut last_block = None;
+
+        while let Some(event) = historical.next().await {
+            let event = event?;
+
+            if let BlockStreamEvent::ProcessBlock(ref block, _) = event {
+                let number = block.block.number();
+                if number >= switch_block {
+                    // The historical stream did not find anything to
+                    // process between the last block and `switch_block`,
+                    // so nothing is lost by starting the live stream there
+                    break;
+                }
+                last_block = Some(number);
+            }
+
+            yield event;
+
+            if last_block == Some(switch_block - 1) {
+                break;
+            }
+        }
+        drop(historical);
+
+        info!(
+            logger,
+            "Switching from the historical to the live block stream";
+            "switch_block" => switch_block,
+            "last_historical_block" => last_block,
+        );
+
+        let mut live = live.await?;
+        while let Some(event) = live.next().await {
+            yield event?;
+        }
+    }
+}
+
+impl<C: Blockchain> Stream for FallbackBlockStream<C> {
+    type Item = Result<BlockStreamEvent<C>, BlockStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.poll_next_unpin(cx)
+    }
+}
+
+impl<C: Blockchain> BlockStream<C> for FallbackBlockStream<C> {
+    fn buffer_size_hint(&self) -> usize {
+        self.buffer_size_hint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures03::FutureExt;
+
+    use super::*;
+    use crate::blockchain::block_stream::{BlockWithTriggers, FirehoseCursor};
+    use crate::blockchain::mock::{MockBlock, MockBlockchain};
+
+    struct TestStream {
+        stream: Pin<
+            Box<
+                dyn Stream<Item = Result<BlockStreamEvent<MockBlockchain>, BlockStreamError>>
+                    + Send,
+            >,
+        >,
+    }
+
+    impl Stream for TestStream {
+        type Item = Result<BlockStreamEvent<MockBlockchain>, BlockStreamError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.stream.poll_next_unpin(cx)
+        }
+    }
+
+    impl BlockStream<MockBlockchain> for TestStream {
+        fn buffer_size_hint(&self) -> usize {
+            1
+        }
+    }
+
+    fn blocks(numbers: Vec<u64>) -> Box<dyn BlockStream<MockBlockchain>> {
+        let events = numbers.into_iter().map(|number| {
+            Ok(BlockStreamEvent::ProcessBlock(
+                BlockWithTriggers {
+                    block: MockBlock { number, size: 0 },
+                    trigger_data: vec![],
+                },
+                FirehoseCursor::None,
+            ))
+        });
+        Box::new(TestStream {
+            stream: Box::pin(futures03::stream::iter(events)),
+        })
+    }
+
+    async fn numbers(stream: FallbackBlockStream<MockBlockchain>) -> Vec<u64> {
+        stream
+            .map(|event| match event.unwrap() {
+                BlockStreamEvent::ProcessBlock(block, _) => block.block.number,
+                BlockStreamEvent::Revert(..) => panic!("unexpected revert"),
+            })
+            .collect()
+            .await
+    }
+
+    fn logger() -> Logger {
+        Logger::root(slog::Discard, o!())
+    }
+
+    #[crate::test]
+    async fn fills_a_gap_in_the_live_stream_from_the_historical_stream() {
+        // The live stream has no blocks before 10; the historical stream
+        // keeps going past that and its blocks from 10 on are ignored
+        let stream = FallbackBlockStream::new(
+            blocks(vec![3, 5, 12, 13]),
+            10,
+            async { Ok(blocks(vec![10, 11, 12, 13])) }.boxed(),
+            logger(),
+        );
+
+        assert_eq!(numbers(stream).await, vec![3, 5, 10, 11, 12, 13]);
+    }
+
+    #[crate::test]
+    async fn switches_as_soon_as_the_gap_is_filled() {
+        // The historical stream would never end; it must not be polled
+        // after it delivered the last block before the switch
+        let historical = Box::new(TestStream {
+            stream: Box::pin(
+                futures03::stream::iter(vec![8, 9])
+                    .map(|number| {
+                        Ok(BlockStreamEvent::ProcessBlock(
+                            BlockWithTriggers {
+                                block: MockBlock { number, size: 0 },
+                                trigger_data: vec![],
+                            },
+                            FirehoseCursor::None,
+                        ))
+                    })
+                    .chain(futures03::stream::pending()),
+            ),
+        });
+
+        let stream = FallbackBlockStream::new(
+            historical,
+            10,
+            async { Ok(blocks(vec![10])) }.boxed(),
+            logger(),
+        );
+
+        assert_eq!(numbers(stream).await, vec![8, 9, 10]);
+    }
+}
@@ -1,8 +1,8 @@
-use super::Blockchain;
 use super::block_stream::{
     BlockStream, BlockStreamError, BlockStreamEvent, FIREHOSE_BUFFER_STREAM_SIZE, FirehoseMapper,
 };
 use super::client::ChainClient;
+use super::{Block, Blockchain};
 use crate::blockchain::TriggerFilter;
 use crate::blockchain::block_stream::FirehoseCursor;
 use crate::prelude::*;
@@ -10,6 +10,8 @@ use crate::util::backoff::ExponentialBackoff;
 use crate::{firehose, firehose::FirehoseEndpoint};
 use async_stream::try_stream;
 use futures03::{Stream, StreamExt};
+use std::future::Future;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
@@ -213,6 +215,59 @@ fn stream_blocks<C: Blockchain, F: FirehoseMapper<C>>(
         #[allow(unused_assignments)]
         let mut skip_backoff = false;
 
+        // A subgraph that starts without a cursor is usually far behind the chain head. Backfill
+        // the final blocks up to the head with several streams over disjoint block ranges, and
+        // continue with a single stream from the cursor of the last backfilled block. Failures
+        // are not fatal since the single stream picks up wherever the backfill stopped.
+        let parallelism = ENV_VARS.firehose_backfill_parallelism;
+        if parallelism > 1 && latest_cursor.is_none() && !check_subgraph_continuity {
+            let endpoint = client.firehose_endpoint().await?;
+            let logger = logger.new(o!("deployment" => deployment.clone(), "provider" => endpoint.provider.to_string()));
+
+            match final_head_number(&endpoint, &headers, mapper.as_ref(), &logger).await {
+                Ok(final_head) => {
+                    let segments = backfill_segments(start_block_num, final_head, ENV_VARS.firehose_backfill_segment_size);
+
+                    if segments.len() > 1 {
+                        info!(
+                            &logger,
+                            "Backfilling final blocks with parallel firehose streams";
+                            "start_block" => start_block_num,
+                            "end_block" => final_head,
+                            "streams" => parallelism,
+                        );
+
+                        let mut segments = ordered_segments(segments, parallelism, |segment| {
+                            fetch_final_segment(&endpoint, &headers, mapper.as_ref(), segment, &logger)
+                        });
+
+                        while let Some(result) = segments.next().await {
+                            match result {
+                                Ok(events) => {
+                                    for (event, cursor) in events {
+                                        if let BlockStreamEvent::ProcessBlock(ref block, _) = event {
+                                            start_block_num = block.block.number() + 1;
+                                        }
+
+                                        yield event;
+
+                                        latest_cursor = FirehoseCursor::from(cursor);
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!(&logger, "Parallel backfill failed, continuing with a single stream: {:#}", e);
+                                    break;
+                                }
+                            }
+                        }
+
+                        info!(&logger, "Parallel backfill done, switching to a single stream"; "start_block" => start_block_num);
+                    }
+                }
+                Err(e) => warn!(&logger, "Unable to determine the final block, skipping parallel backfill: {:#}", e),
+            }
+        }
+
         loop {
             let endpoint = client.firehose_endpoint().await?;
             let logger = logger.new(o!("deployment" => deployment.clone(), "provider" => endpoint.provider.to_string()));
@@ -338,6 +393,112 @@ fn stream_blocks<C: Blockchain, F: FirehoseMapper<C>>(
     }
 }
 
+/// Returns the number of the latest final block according to the endpoint.
+async fn final_head_number<C: Blockchain, F: FirehoseMapper<C>>(
+    endpoint: &Arc<FirehoseEndpoint>,
+    headers: &firehose::ConnectionHeaders,
+    mapper: &F,
+    logger: &Logger,
+) -> Result<BlockNumber, Error> {
+    let request = firehose::Request {
+        start_block_num: -1,
+        final_blocks_only: false,
+        ..Default::default()
+    };
+
+    let mut stream = endpoint.clone().stream_blocks(request, headers).await?;
+    let response = stream
+        .next()
+        .await
+        .ok_or_else(|| anyhow!("Firehose stream ended before sending the head block"))?
+        .context("An error occurred while streaming the head block")?;
+
+    match mapper.to_block_stream_event(logger, &response).await? {
+        BlockStreamEvent::ProcessBlock(block, _) => Ok(mapper
+            .final_block_ptr_for(logger, endpoint, &block.block)
+            .await?
+            .number),
+        BlockStreamEvent::Revert(..) => {
+            Err(anyhow!("Firehose sent a revert instead of the head block"))
+        }
+    }
+}
+
+/// Splits the blocks `start..=end` into consecutive ranges of at most `segment_size` blocks.
+fn backfill_segments(
+    start: BlockNumber,
+    end: BlockNumber,
+    segment_size: usize,
+) -> Vec<RangeInclusive<BlockNumber>> {
+    let segment_size = BlockNumber::try_from(segment_size)
+        .unwrap_or(BlockNumber::MAX)
+        .max(1);
+
+    let mut segments = Vec::new();
+    let mut from = start;
+    while from <= end {
+        let to = from.saturating_add(segment_size - 1).min(end);
+        segments.push(from..=to);
+
+        if to == BlockNumber::MAX {
+            break;
+        }
+        from = to + 1;
+    }
+
+    segments
+}
+
+/// Fetches up to `parallelism` segments at the same time but yields the results in the
+/// order of `segments`, so that the blocks come out in the same order as with one stream.
+fn ordered_segments<T, E, Fut>(
+    segments: Vec<RangeInclusive<BlockNumber>>,
+    parallelism: usize,
+    fetch: impl FnMut(RangeInclusive<BlockNumber>) -> Fut,
+) -> impl Stream<Item = Result<Vec<T>, E>>
+where
+    Fut: Future<Output = Result<Vec<T>, E>>,
+{
+    futures03::stream::iter(segments)
+        .map(fetch)
+        .buffered(parallelism)
+}
+
+/// Streams the final blocks of `segment` and returns them with their cursors.
+async fn fetch_final_segment<C: Blockchain, F: FirehoseMapper<C>>(
+    endpoint: &Arc<FirehoseEndpoint>,
+    headers: &firehose::ConnectionHeaders,
+    mapper: &F,
+    segment: RangeInclusive<BlockNumber>,
+    logger: &Logger,
+) -> Result<Vec<(BlockStreamEvent<C>, String)>, Error> {
+    let mut request = firehose::Request {
+        start_block_num: *segment.start() as i64,
+        stop_block_num: *segment.end() as u64,
+        final_blocks_only: true,
+        ..Default::default()
+    };
+
+    if endpoint.filters_enabled {
+        request.transforms = mapper.trigger_filter().clone().to_firehose_filter();
+    }
+
+    let mut stream = endpoint.clone().stream_blocks(request, headers).await?;
+    let mut events = Vec::new();
+
+    while let Some(response) = stream.next().await {
+        let response = response.context("An error occurred while streaming blocks")?;
+        let event = mapper
+            .to_block_stream_event(logger, &response)
+            .await
+            .context("Mapping block to BlockStreamEvent failed")?;
+
+        events.push((event, response.cursor));
+    }
+
+    Ok(events)
+}
+
 enum BlockResponse<C: Blockchain> {
     Proceed(BlockStreamEvent<C>, String),
     Rewind(BlockPtr),
@@ -455,6 +616,9 @@ mod tests {
         firehose_block_stream::must_check_subgraph_continuity,
     };
     use slog::{Logger, o};
+    use std::time::Duration;
+
+    use super::{backfill_segments, ordered_segments};
 
     #[test]
     fn check_continuity() {
@@ -531,4 +695,40 @@ mod tests {
             10
         ),);
     }
+
+    #[test]
+    fn backfill_segments_cover_the_range_in_order() {
+        assert_eq!(backfill_segments(10, 9, 5), vec![]);
+        assert_eq!(backfill_segments(10, 10, 5), vec![10..=10]);
+        assert_eq!(backfill_segments(10, 14, 5), vec![10..=14]);
+        assert_eq!(
+            backfill_segments(10, 21, 5),
+            vec![10..=14, 15..=19, 20..=21]
+        );
+        assert_eq!(backfill_segments(0, 2, 0), vec![0..=0, 1..=1, 2..=2]);
+        assert_eq!(
+            backfill_segments(i32::MAX - 1, i32::MAX, 5),
+            vec![i32::MAX - 1..=i32::MAX]
+        );
+    }
+
+    #[crate::test]
+    async fn ordered_segments_match_a_single_stream() {
+        use futures03::StreamExt;
+
+        let segments = backfill_segments(0, 99, 7);
+        let last = *segments.last().unwrap().end();
+
+        // Later segments finish first, which must not change the order of the blocks
+        let blocks: Vec<i32> = ordered_segments(segments, 4, |segment| async move {
+            let delay = (last - segment.start()) as u64;
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok::<_, ()>(segment.collect::<Vec<_>>())
+        })
+        .map(|result| result.unwrap())
+        .concat()
+        .await;
+
+        assert_eq!(blocks, (0..=99).collect::<Vec<_>>());
+    }
 }
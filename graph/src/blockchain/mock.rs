@@ -20,18 +20,20 @@ use crate::{
 use alloy::primitives::{B256, U256};
 use anyhow::{Error, Result};
 use async_trait::async_trait;
+use futures03::channel::mpsc;
+use futures03::stream::{self as stream03, BoxStream, StreamExt as _};
 use serde::Deserialize;
 use slog::Logger;
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     convert::TryFrom,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use super::{
-    BlockIngestor, BlockTime, ChainIdentifier, EmptyNodeCapabilities, ExtendedBlockPtr, HostFn,
-    IngestorError, MappingTriggerTrait, NoopDecoderHook, Trigger, TriggerFilterWrapper,
-    TriggerWithHandler,
+    BlockIngestor, BlockTime, ChainHeadUpdateListener, ChainHeadUpdateStream, ChainHealth,
+    ChainIdentifier, ChainMetadata, EmptyNodeCapabilities, ExtendedBlockPtr, HostFn, IngestorError,
+    MappingTriggerTrait, NoopDecoderHook, Trigger, TriggerFilterWrapper, TriggerWithHandler,
     block_stream::{self, BlockStream, FirehoseCursor},
     client::ChainClient,
 };
@@ -42,12 +44,57 @@ use super::{
     UnresolvedDataSourceTemplate, block_stream::BlockWithTriggers,
 };
 
-#[derive(Debug)]
-pub struct MockBlockchain;
+#[derive(Debug, Default)]
+pub struct MockBlockchain {
+    /// How the providers of the chain respond to `health_check`
+    pub head: MockChainHead,
+    /// Where `chain_head_updates` subscribes
+    pub head_updates: Arc<MockChainHeadUpdateListener>,
+}
+
+/// A `ChainHeadUpdateListener` that sends updates to its subscribers when
+/// `new_head` is called rather than when the database is notified
+#[derive(Debug, Default)]
+pub struct MockChainHeadUpdateListener {
+    subscribers: Mutex<Vec<(String, mpsc::UnboundedSender<()>)>>,
+}
+
+impl MockChainHeadUpdateListener {
+    /// Send an update to all subscribers for `network`
+    pub fn new_head(&self, network: &str) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|(name, sender)| name != network || sender.unbounded_send(()).is_ok());
+    }
+}
+
+impl ChainHeadUpdateListener for MockChainHeadUpdateListener {
+    fn subscribe(&self, network: String, _logger: Logger) -> ChainHeadUpdateStream {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers.lock().unwrap().push((network, sender));
+        Box::new(receiver)
+    }
+}
+
+/// The possible responses of the providers of a `MockBlockchain` when
+/// `health_check` asks them for the chain head
+#[derive(Debug, Default, Clone)]
+pub enum MockChainHead {
+    /// The chain has no providers to ask
+    #[default]
+    NoProviders,
+    Head(BlockPtr),
+    Error(String),
+    /// Never respond
+    Hang,
+}
 
 #[derive(Clone, Hash, Eq, PartialEq, Debug, Default)]
 pub struct MockBlock {
     pub number: u64,
+    /// The value that `byte_size` reports
+    pub size: usize,
 }
 
 impl Block for MockBlock {
@@ -66,6 +113,10 @@ impl Block for MockBlock {
     fn timestamp(&self) -> BlockTime {
         BlockTime::since_epoch(self.ptr().number as i64 * 45 * 60, 0)
     }
+
+    fn byte_size(&self) -> usize {
+        self.size
+    }
 }
 
 pub fn test_ptr(n: BlockNumber) -> BlockPtr {
@@ -83,6 +134,7 @@ pub fn test_ptr_reorged(n: BlockNumber, reorg_n: u32) -> BlockPtr {
 
 #[derive(Clone)]
 pub struct MockDataSource {
+    pub name: String,
     pub api_version: semver::Version,
     pub kind: String,
     pub network: Option<String>,
@@ -127,7 +179,7 @@ impl<C: Blockchain> DataSource<C> for MockDataSource {
     }
 
     fn name(&self) -> &str {
-        todo!()
+        self.name.as_str()
     }
 
     fn kind(&self) -> &str {
@@ -274,6 +326,7 @@ impl TriggersAdapter<MockBlockchain> for MockTriggersAdapter {
             .into_iter()
             .map(|number| MockBlock {
                 number: number as u64,
+                size: 0,
             })
             .collect())
     }
@@ -328,7 +381,7 @@ async fn blocks_with_triggers(
 > {
     Ok((
         vec![BlockWithTriggers {
-            block: MockBlock { number: 0 },
+            block: MockBlock { number: 0, size: 0 },
             trigger_data: vec![Trigger::Chain(MockTriggerData)],
         }],
         to,
@@ -434,10 +487,27 @@ impl Blockchain for MockBlockchain {
         todo!()
     }
 
+    fn finality_depth(&self) -> BlockNumber {
+        crate::env::ENV_VARS.reorg_threshold()
+    }
+
     fn is_refetch_block_required(&self) -> bool {
         false
     }
 
+    async fn health_check(&self) -> ChainHealth {
+        match &self.head {
+            MockChainHead::NoProviders => ChainHealth::Unhealthy("no providers".to_string()),
+            MockChainHead::Head(ptr) => ChainHealth::Healthy(ptr.clone()),
+            MockChainHead::Error(msg) => ChainHealth::Unhealthy(msg.clone()),
+            MockChainHead::Hang => std::future::pending().await,
+        }
+    }
+
+    fn supported_handler_kinds() -> HashSet<&'static str> {
+        HashSet::from(["mock_handler_1", "mock_handler_2"])
+    }
+
     async fn refetch_firehose_block(
         &self,
         _logger: &slog::Logger,
@@ -450,6 +520,19 @@ impl Blockchain for MockBlockchain {
         todo!()
     }
 
+    fn chain_head_updates(&self) -> ChainHeadUpdateStream {
+        self.head_updates
+            .subscribe("mock".to_string(), Logger::root(slog::Discard, slog::o!()))
+    }
+
+    async fn chain_metadata(&self) -> Result<ChainMetadata, Error> {
+        Ok(ChainMetadata {
+            native_token_symbol: "ETH".to_string(),
+            native_token_decimals: 18,
+            identifier: ChainIdentifier::default(),
+        })
+    }
+
     async fn block_pointer_from_number(
         &self,
         _logger: &slog::Logger,
@@ -477,12 +560,54 @@ impl Blockchain for MockBlockchain {
 #[derive(Default)]
 pub struct MockChainStore {
     pub blocks: BTreeMap<BlockNumber, Vec<ExtendedBlockPtr>>,
+    /// Pointers of all blocks passed to `upsert_block`, in upsert order
+    pub upserted: Mutex<Vec<BlockPtr>>,
+    /// The chain head; only changed by `attempt_chain_head_update`
+    pub head: Mutex<Option<BlockPtr>>,
+    /// If set, `attempt_chain_head_update` only moves the head once all
+    /// blocks between the head and the highest upserted block have been
+    /// upserted, and otherwise reports the first one that is missing
+    pub require_ancestors: bool,
+    /// The parents of all blocks passed to `upsert_block`
+    pub parents: Mutex<HashMap<BlockHash, BlockPtr>>,
+    /// The data of all blocks passed to `upsert_block`, returned by `blocks`
+    pub data: Mutex<HashMap<BlockHash, serde_json::Value>>,
+}
+
+impl MockChainStore {
+    pub fn upserted_numbers(&self) -> Vec<BlockNumber> {
+        self.upserted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|ptr| ptr.number)
+            .collect()
+    }
+
+    /// The pointers and parent hashes of the blocks in `range`, ordered by
+    /// number and hash
+    fn sorted_ptrs_in_range(
+        &self,
+        range: std::ops::Range<BlockNumber>,
+    ) -> Vec<(BlockPtr, BlockHash)> {
+        let mut ptrs: Vec<_> = self
+            .blocks
+            .range(range)
+            .flat_map(|(_, blocks)| blocks.iter())
+            .map(|block| {
+                let ptr = BlockPtr::new(block.hash.clone(), block.number);
+                (ptr, block.parent_hash.clone())
+            })
+            .collect();
+        ptrs.sort_by(|(a, _), (b, _)| (a.number, &*a.hash.0).cmp(&(b.number, &*b.hash.0)));
+        ptrs
+    }
 }
 
 #[async_trait]
 impl ChainHeadStore for MockChainStore {
     async fn chain_head_ptr(self: Arc<Self>) -> Result<Option<BlockPtr>, Error> {
-        unimplemented!()
+        Ok(self.head.lock().unwrap().clone())
     }
     async fn chain_head_cursor(&self) -> Result<Option<String>, Error> {
         unimplemented!()
@@ -515,26 +640,128 @@ impl ChainStore for MockChainStore {
     async fn genesis_block_ptr(&self) -> Result<BlockPtr, Error> {
         unimplemented!()
     }
-    async fn upsert_block(&self, _block: Arc<dyn Block>) -> Result<(), Error> {
-        unimplemented!()
+    async fn upsert_block(&self, block: Arc<dyn Block>) -> Result<(), Error> {
+        if let Some(parent) = block.parent_ptr() {
+            self.parents.lock().unwrap().insert(block.hash(), parent);
+        }
+        if let Ok(data) = block.data() {
+            self.data.lock().unwrap().insert(block.hash(), data);
+        }
+        self.upserted.lock().unwrap().push(block.ptr());
+        Ok(())
     }
-    async fn upsert_light_blocks(&self, _blocks: &[&dyn Block]) -> Result<(), Error> {
-        unimplemented!()
+    async fn upsert_block_unless_conflicting(
+        &self,
+        block: Arc<dyn Block>,
+        overwrite: bool,
+    ) -> Result<bool, Error> {
+        let cached = self
+            .data
+            .lock()
+            .unwrap()
+            .get(&block.hash())
+            .cloned()
+            .and_then(CachedBlock::from_json);
+        let new = block.data().ok().and_then(CachedBlock::from_json);
+        if let (Some(cached), Some(new)) = (&cached, &new)
+            && cached.conflicts_with(new)
+        {
+            return Ok(false);
+        }
+        if overwrite {
+            self.upsert_block(block).await?;
+        } else {
+            self.upsert_light_blocks(&[block.as_ref()]).await?;
+        }
+        Ok(true)
+    }
+    async fn upsert_light_blocks(&self, blocks: &[&dyn Block]) -> Result<(), Error> {
+        // Unlike `upsert_block`, blocks that are already present are kept
+        for block in blocks {
+            if self.data.lock().unwrap().contains_key(&block.hash()) {
+                continue;
+            }
+            if let Some(parent) = block.parent_ptr() {
+                self.parents.lock().unwrap().insert(block.hash(), parent);
+            }
+            if let Ok(data) = block.data() {
+                self.data.lock().unwrap().insert(block.hash(), data);
+            }
+            self.upserted.lock().unwrap().push(block.ptr());
+        }
+        Ok(())
     }
     async fn attempt_chain_head_update(
         self: Arc<Self>,
         _ancestor_count: BlockNumber,
     ) -> Result<Option<B256>, Error> {
-        unimplemented!()
-    }
-    async fn blocks(self: Arc<Self>, _hashes: Vec<BlockHash>) -> Result<Vec<CachedBlock>, Error> {
-        unimplemented!()
+        // Move the head to the highest upserted block; unless
+        // `require_ancestors` is set, ancestors are assumed to be present
+        let highest = self
+            .upserted
+            .lock()
+            .unwrap()
+            .iter()
+            .max_by_key(|ptr| ptr.number)
+            .cloned();
+        let mut head = self.head.lock().unwrap();
+        if self.require_ancestors
+            && let (Some(highest), Some(current)) = (&highest, head.as_ref())
+        {
+            let parents = self.parents.lock().unwrap();
+            let mut ptr = highest.clone();
+            while ptr.number > current.number + 1 {
+                match parents.get(&ptr.hash) {
+                    Some(parent) if parents.contains_key(&parent.hash) => ptr = parent.clone(),
+                    Some(parent) => return Ok(Some(parent.hash.as_b256())),
+                    None => break,
+                }
+            }
+        }
+        if let Some(highest) = highest
+            && head
+                .as_ref()
+                .is_none_or(|head| highest.number > head.number)
+        {
+            *head = Some(highest);
+        }
+        Ok(None)
+    }
+    async fn blocks(self: Arc<Self>, hashes: Vec<BlockHash>) -> Result<Vec<CachedBlock>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(hashes
+            .iter()
+            .filter_map(|hash| data.get(hash).cloned())
+            .filter_map(CachedBlock::from_json)
+            .collect())
     }
     async fn block_parent_ptr(
         self: Arc<Self>,
-        _hash: &BlockHash,
+        hash: &BlockHash,
     ) -> Result<Option<BlockPtr>, Error> {
-        unimplemented!()
+        Ok(self.parents.lock().unwrap().get(hash).cloned())
+    }
+    fn block_ptrs_in_range(
+        self: Arc<Self>,
+        range: std::ops::Range<BlockNumber>,
+        _batch_size: usize,
+    ) -> BoxStream<'static, Result<(BlockPtr, BlockHash), Error>> {
+        let ptrs = self.sorted_ptrs_in_range(range);
+        stream03::iter(ptrs.into_iter().map(Ok)).boxed()
+    }
+    fn blocks_in_range(
+        self: Arc<Self>,
+        range: std::ops::Range<BlockNumber>,
+        _batch_size: usize,
+    ) -> BoxStream<'static, Result<CachedBlock, Error>> {
+        let data = self.data.lock().unwrap();
+        let blocks: Vec<_> = self
+            .sorted_ptrs_in_range(range)
+            .into_iter()
+            .filter_map(|(ptr, _)| data.get(&ptr.hash).cloned())
+            .filter_map(CachedBlock::from_json)
+            .collect();
+        stream03::iter(blocks.into_iter().map(Ok)).boxed()
     }
     async fn ancestor_block(
         self: Arc<Self>,
@@ -546,11 +773,24 @@ impl ChainStore for MockChainStore {
     }
     async fn ancestor_block_ptr(
         self: Arc<Self>,
-        _block_ptr: BlockPtr,
-        _offset: BlockNumber,
+        block_ptr: BlockPtr,
+        offset: BlockNumber,
         _root: Option<BlockHash>,
     ) -> Result<Option<BlockPtr>, Error> {
-        unimplemented!()
+        // Like the real store, follow the parents of upserted blocks and
+        // return the furthest one we can reach
+        let parents = self.parents.lock().unwrap();
+        if !parents.contains_key(&block_ptr.hash) {
+            return Ok(None);
+        }
+        let mut ptr = block_ptr;
+        for _ in 0..offset {
+            match parents.get(&ptr.hash) {
+                Some(parent) if parents.contains_key(&parent.hash) => ptr = parent.clone(),
+                _ => break,
+            }
+        }
+        Ok(Some(ptr))
     }
     async fn cleanup_cached_blocks(
         &self,
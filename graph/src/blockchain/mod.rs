@@ -2,13 +2,16 @@
 //! blockchain into Graph Node. A blockchain is represented by an implementation of the `Blockchain`
 //! trait which is the centerpiece of this module.
 
+mod block_data;
 pub mod block_stream;
 pub mod client;
 mod empty_node_capabilities;
+pub mod fallback_block_stream;
 pub mod firehose_block_ingestor;
 pub mod firehose_block_stream;
 pub mod mock;
 mod noop_runtime_adapter;
+pub mod reorg_replay;
 mod types;
 
 // Try to reexport most of the necessary types
@@ -23,7 +26,7 @@ use crate::{
         trigger_processor::RunnableTriggers,
     },
     data::subgraph::{MIN_SPEC_VERSION, UnifiedMappingApiVersion},
-    data_source::{self, DataSourceTemplateInfo, subgraph},
+    data_source::{self, DataSourceTemplateInfo, offchain::OFFCHAIN_KINDS, subgraph},
     prelude::{DataSourceContext, DeploymentHash},
     runtime::{AscHeap, HostExportError, gas::GasCounter},
 };
@@ -36,21 +39,30 @@ use anyhow::{Context, Error, anyhow};
 use async_trait::async_trait;
 use futures03::future::BoxFuture;
 use graph_derive::CheapClone;
+use lazy_static::lazy_static;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use slog::Logger;
 use std::{
     any::Any,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{self, Debug},
+    future::Future,
     str::FromStr,
-    sync::Arc,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 
+pub use block_data::{
+    BlockDataFormat, compress_block, decode_block_data, decompress_block, encode_block_data,
+};
 pub use block_stream::{ChainHeadUpdateListener, ChainHeadUpdateStream, TriggersAdapter};
 pub use empty_node_capabilities::EmptyNodeCapabilities;
 pub use noop_runtime_adapter::NoopRuntimeAdapter;
-pub use types::{BlockHash, BlockPtr, BlockTime, ChainIdentifier, ExtendedBlockPtr};
+pub use types::{BlockHash, BlockPtr, BlockTime, ChainIdentifier, ChainMetadata, ExtendedBlockPtr};
 
 use self::{
     block_stream::{BlockStream, FirehoseCursor},
@@ -63,6 +75,32 @@ pub trait BlockIngestor: 'static + Send + Sync {
     async fn run(self: Box<Self>);
     fn network_name(&self) -> ChainName;
     fn kind(&self) -> BlockchainKind;
+
+    /// A handle that pauses and resumes this ingestor while it runs, or
+    /// `None` if the ingestor can not be paused
+    fn pause_handle(&self) -> Option<IngestorPause> {
+        None
+    }
+}
+
+/// Shared switch for pausing a running block ingestor. All clones refer to
+/// the same switch; a paused ingestor keeps running but stops polling its
+/// chain until it is resumed.
+#[derive(Clone, Debug, Default)]
+pub struct IngestorPause(Arc<AtomicBool>);
+
+impl IngestorPause {
+    pub fn pause(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
 pub trait TriggersAdapterSelector<C: Blockchain>: Sync + Send {
@@ -97,6 +135,13 @@ pub trait Block: Send + Sync {
     }
 
     fn timestamp(&self) -> BlockTime;
+
+    /// A rough estimate of how many bytes of memory the block takes up.
+    /// Block streams use this to limit how much block data they buffer.
+    /// Blocks that return 0, the default, are only limited by their count
+    fn byte_size(&self) -> usize {
+        0
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -196,12 +241,37 @@ pub trait Blockchain: Debug + Sized + Send + Sync + Unpin + 'static {
     /// Return the pointer for the latest block that we are aware of
     async fn chain_head_ptr(&self) -> Result<Option<BlockPtr>, Error>;
 
+    /// Subscribe to updates of the chain head. The stream produces an
+    /// item whenever the block ingestor for this chain stores a new head;
+    /// use `chain_head_ptr` to find out what the head is
+    fn chain_head_updates(&self) -> ChainHeadUpdateStream;
+
+    /// Return the native token and the identifier of this chain
+    async fn chain_metadata(&self) -> Result<ChainMetadata, Error>;
+
     async fn block_pointer_from_number(
         &self,
         logger: &Logger,
         number: BlockNumber,
     ) -> Result<BlockPtr, IngestorError>;
 
+    /// The number of blocks behind the chain head after which a block can
+    /// no longer be reverted by a reorg. Chains should use
+    /// `ENV_VARS.reorg_threshold_for` with their name so that
+    /// `ETHEREUM_REORG_THRESHOLD__<NETWORK>` applies to them
+    fn finality_depth(&self) -> BlockNumber;
+
+    /// Return `true` if the block with number `number` is final when the
+    /// chain head is at `head`
+    fn is_final(&self, number: BlockNumber, head: BlockNumber) -> bool {
+        number <= head.saturating_sub(self.finality_depth())
+    }
+
+    /// Check whether the chain's providers are reachable by asking them
+    /// for the chain head. Unlike `chain_head_ptr`, this must not rely on
+    /// the head recorded in the chain store
+    async fn health_check(&self) -> ChainHealth;
+
     async fn refetch_firehose_block(
         &self,
         logger: &Logger,
@@ -210,6 +280,11 @@ pub trait Blockchain: Debug + Sized + Send + Sync + Unpin + 'static {
 
     fn is_refetch_block_required(&self) -> bool;
 
+    /// The kinds of handlers that data sources on this chain can declare in
+    /// the manifest, i.e., `event` for `eventHandlers`. Manifests with data
+    /// sources that declare any other kind of handler are rejected.
+    fn supported_handler_kinds() -> HashSet<&'static str>;
+
     async fn runtime(&self) -> anyhow::Result<(Arc<dyn RuntimeAdapter<Self>>, Self::DecoderHook)>;
 
     fn chain_client(&self) -> Arc<ChainClient<Self>>;
@@ -237,11 +312,78 @@ pub enum IngestorError {
     #[error("Received confliciting block receipts for block (block hash = {0:?})")]
     BlockReceiptsMismatched(B256),
 
+    /// The block cache already holds a block with the same hash, but with
+    /// different contents
+    #[error("Received block data that conflicts with the block cache (block hash = {0:?})")]
+    BlockDataMismatched(B256),
+
+    /// The provider returned a block that is not the parent that its child
+    /// block points to
+    #[error(
+        "Block {actual} does not link up with its child (expected hash = {expected_hash:?}, expected number = {expected_number:?})"
+    )]
+    BlockLinkageMismatched {
+        expected_hash: B256,
+        expected_number: Option<BlockNumber>,
+        actual: BlockPtr,
+    },
+
+    /// The provider returned a block that is not newer than its parent
+    #[error(
+        "Block {block} has timestamp {timestamp}, which is not after the timestamp {parent_timestamp} of its parent"
+    )]
+    BlockTimestampNotIncreasing {
+        block: BlockPtr,
+        timestamp: BlockTime,
+        parent_timestamp: BlockTime,
+    },
+
+    /// The provider returned a block whose timestamp is further ahead of
+    /// the current time than clock skew can explain
+    #[error(
+        "Block {block} has timestamp {timestamp}, which is more than {tolerance:?} after the current time {now}"
+    )]
+    BlockTimestampInFuture {
+        block: BlockPtr,
+        timestamp: BlockTime,
+        now: BlockTime,
+        tolerance: Duration,
+    },
+
+    /// Filling in missing ancestors of the latest block took more steps
+    /// than there are blocks the ingestor keeps, which means that the
+    /// provider keeps returning blocks with parents that are not cached
+    #[error(
+        "Gave up filling in missing blocks after {limit} blocks (next missing block hash = {missing:?})"
+    )]
+    AncestorLimitExceeded { limit: BlockNumber, missing: B256 },
+
     /// An unexpected error occurred.
     #[error("Ingestor error: {0:#}")]
     Unknown(#[from] Error),
 }
 
+/// Run `fut`, but give up with `IngestorError::Unknown` if it does not
+/// finish within `timeout` so that a stalled provider can not hang the
+/// caller. `what` names the request in the error message.
+pub async fn with_ingestor_timeout<T, E>(
+    timeout: Duration,
+    what: &str,
+    fut: impl Future<Output = Result<T, E>>,
+) -> Result<T, IngestorError>
+where
+    E: Into<IngestorError>,
+{
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(res) => res.map_err(Into::into),
+        Err(_) => Err(IngestorError::Unknown(anyhow!(
+            "{} did not finish within {:?}",
+            what,
+            timeout
+        ))),
+    }
+}
+
 /// The `TriggerFilterWrapper` is a higher-level wrapper around the chain-specific `TriggerFilter`,
 /// enabling subgraph-based trigger filtering for subgraph datasources. This abstraction is necessary
 /// because subgraph filtering operates at a higher level than chain-based filtering. By using this wrapper,
@@ -257,8 +399,21 @@ pub struct TriggerFilterWrapper<C: Blockchain> {
 pub struct SubgraphFilter {
     pub subgraph: DeploymentHash,
     pub start_block: BlockNumber,
+    /// The last block of the source subgraph that produces triggers, if any.
+    pub end_block: Option<BlockNumber>,
     pub entities: Vec<String>,
     pub manifest_idx: u32,
+    /// Whether deletions of the observed entities in the source subgraph
+    /// produce triggers. Only creations and modifications do if this is `false`.
+    pub include_deletions: bool,
+}
+
+impl SubgraphFilter {
+    /// Whether `block` is past the end block of the filter, in which case it
+    /// produces no triggers.
+    pub fn has_expired(&self, block: BlockNumber) -> bool {
+        self.end_block.is_some_and(|end_block| block > end_block)
+    }
 }
 
 impl<C: Blockchain> TriggerFilterWrapper<C> {
@@ -295,6 +450,17 @@ pub trait TriggerFilter<C: Blockchain>: Default + Clone + Send + Sync {
     fn node_capabilities(&self) -> C::NodeCapabilities;
 
     fn to_firehose_filter(self) -> Vec<prost_types::Any>;
+
+    /// Whether `self` and `other` request the same data from Firehose, so
+    /// that replacing one with the other does not require reconnecting the
+    /// block stream.
+    ///
+    /// The default compares the encoded Firehose filters, which is only
+    /// exact when the encoding does not depend on iteration order; chains
+    /// that build their filters from hash maps should override this.
+    fn is_equivalent(&self, other: &Self) -> bool {
+        self.clone().to_firehose_filter() == other.clone().to_firehose_filter()
+    }
 }
 
 pub trait DataSource<C: Blockchain>: 'static + Sized + Send + Sync + Clone {
@@ -550,6 +716,16 @@ pub trait RuntimeAdapter<C: Blockchain>: Send + Sync {
 
 pub trait NodeCapabilities<C: Blockchain> {
     fn from_data_sources(data_sources: &[C::DataSource]) -> Self;
+
+    /// Returns the name of a capability that is in `required` but not in
+    /// `self`, or `None` if `self` has all the required capabilities.
+    fn missing(&self, required: &Self) -> Option<&'static str>;
+
+    /// Whether a provider with these capabilities can serve a subgraph
+    /// that requires `required`.
+    fn satisfies(&self, required: &Self) -> bool {
+        self.missing(required).is_none()
+    }
 }
 
 /// Blockchain technologies supported by Graph Node.
@@ -577,17 +753,64 @@ impl FromStr for BlockchainKind {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "ethereum" => Ok(BlockchainKind::Ethereum),
-            "near" => Ok(BlockchainKind::Near),
-            "subgraph" => Ok(BlockchainKind::Ethereum), // TODO(krishna): We should detect the blockchain kind from the source subgraph
-            "amp" => Ok(BlockchainKind::Ethereum),      // TODO: Maybe get this from the Amp server
-            _ => Err(anyhow!("unknown blockchain kind {}", s)),
-        }
+        Self::from_builtin(s)
+            .or_else(|| Self::from_alias(s))
+            .ok_or_else(|| anyhow!("unknown blockchain kind {}", s))
     }
 }
 
+lazy_static! {
+    /// Names for blockchain kinds that were registered at runtime
+    static ref KIND_ALIASES: RwLock<HashMap<String, BlockchainKind>> = RwLock::new(HashMap::new());
+}
+
 impl BlockchainKind {
+    fn from_builtin(s: &str) -> Option<Self> {
+        match s {
+            "ethereum" => Some(BlockchainKind::Ethereum),
+            "near" => Some(BlockchainKind::Near),
+            "subgraph" => Some(BlockchainKind::Ethereum), // TODO(krishna): We should detect the blockchain kind from the source subgraph
+            "amp" => Some(BlockchainKind::Ethereum), // TODO: Maybe get this from the Amp server
+            _ => None,
+        }
+    }
+
+    /// Register `alias` as another name for `kind`, e.g., so that subgraphs
+    /// for an EVM-compatible chain can use the name of that chain as the
+    /// kind of their data sources. It is an error to register an alias for
+    /// a different kind than the one it already refers to.
+    pub fn register_alias(alias: &str, kind: BlockchainKind) -> Result<(), Error> {
+        if let Some(existing) = Self::from_builtin(alias)
+            && existing != kind
+        {
+            return Err(anyhow!(
+                "can not register `{}` as an alias for {} since it is the name of {}",
+                alias,
+                kind,
+                existing
+            ));
+        }
+
+        let mut aliases = KIND_ALIASES.write().unwrap();
+        match aliases.get(alias) {
+            Some(existing) if *existing != kind => Err(anyhow!(
+                "can not register `{}` as an alias for {} since it already is an alias for {}",
+                alias,
+                kind,
+                existing
+            )),
+            _ => {
+                aliases.insert(alias.to_string(), kind);
+                Ok(())
+            }
+        }
+    }
+
+    /// The kind that `alias` was registered for with `register_alias`
+    pub fn from_alias(alias: &str) -> Option<BlockchainKind> {
+        KIND_ALIASES.read().unwrap().get(alias).copied()
+    }
+
     pub fn from_manifest(manifest: &serde_yaml::Mapping) -> Result<Self, Error> {
         use serde_yaml::Value;
 
@@ -607,9 +830,136 @@ impl BlockchainKind {
     }
 }
 
+/// Check that all data sources and templates in the raw `manifest` index
+/// the same kind of blockchain and return that kind. Unlike
+/// `BlockchainKind::from_manifest`, which only looks at the first data
+/// source, this reports every data source with a missing or unknown kind,
+/// and data sources that disagree about the kind.
+///
+/// File data sources work with any kind of blockchain. Subgraph and Amp
+/// data sources take their data from elsewhere and therefore use the kind
+/// of the onchain data sources in the same manifest; a manifest with only
+/// such data sources indexes Ethereum.
+pub fn validate_manifest_chain(
+    manifest: &serde_yaml::Mapping,
+) -> Result<BlockchainKind, Vec<Error>> {
+    use serde_yaml::Value;
+
+    fn entries<'a>(
+        manifest: &'a serde_yaml::Mapping,
+        key: &str,
+    ) -> impl Iterator<Item = &'a Value> + 'a {
+        manifest
+            .get(Value::String(key.to_owned()))
+            .and_then(|entries| entries.as_sequence())
+            .into_iter()
+            .flatten()
+    }
+
+    if entries(manifest, "dataSources").next().is_none() {
+        return Err(vec![anyhow!("manifest has no data sources")]);
+    }
+
+    let mut errors = Vec::new();
+    let mut kinds: BTreeMap<BlockchainKind, Vec<String>> = BTreeMap::new();
+
+    let data_sources = entries(manifest, "dataSources").map(|ds| ("data source", ds));
+    let templates = entries(manifest, "templates").map(|ds| ("template", ds));
+    for (idx, (what, ds)) in data_sources.chain(templates).enumerate() {
+        let field = |name: &str| {
+            ds.as_mapping()
+                .and_then(|ds| ds.get(Value::String(name.to_owned())))
+                .and_then(|value| value.as_str())
+        };
+        let name = field("name")
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("#{}", idx));
+
+        let Some(kind) = field("kind") else {
+            errors.push(anyhow!("{} `{}` has no kind", what, name));
+            continue;
+        };
+        if OFFCHAIN_KINDS.contains_key(kind) {
+            continue;
+        }
+
+        // Split by `/` to, for example, read 'ethereum' in 'ethereum/contracts'
+        match kind.split('/').next().unwrap_or(kind) {
+            subgraph::SUBGRAPH_DS_KIND | crate::amp::manifest::DataSource::KIND => {}
+            prefix => match BlockchainKind::from_builtin(prefix)
+                .or_else(|| BlockchainKind::from_alias(prefix))
+            {
+                Some(chain_kind) => kinds.entry(chain_kind).or_default().push(name),
+                None => errors.push(anyhow!("{} `{}` has unknown kind `{}`", what, name, kind)),
+            },
+        }
+    }
+
+    if kinds.len() > 1 {
+        let kinds: Vec<_> = kinds
+            .iter()
+            .map(|(kind, names)| format!("{} ({})", kind, names.join(", ")))
+            .collect();
+        errors.push(anyhow!(
+            "all data sources must index the same kind of blockchain, but they use {}",
+            kinds.join(" and ")
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(kinds.into_keys().next().unwrap_or(BlockchainKind::Ethereum))
+}
+
+/// The result of checking the health of a chain with
+/// `Blockchain::health_check`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainHealth {
+    /// The chain head could be determined
+    Healthy(BlockPtr),
+    /// Determining the chain head failed for the given reason
+    Unhealthy(String),
+    /// The health check did not finish in time
+    TimedOut,
+}
+
+impl ChainHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, ChainHealth::Healthy(_))
+    }
+}
+
+impl From<Result<BlockPtr, Error>> for ChainHealth {
+    fn from(head: Result<BlockPtr, Error>) -> Self {
+        match head {
+            Ok(head) => ChainHealth::Healthy(head),
+            Err(e) => ChainHealth::Unhealthy(format!("{:#}", e)),
+        }
+    }
+}
+
+/// How long `BlockchainMap::health_report` waits for any one chain
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lets `BlockchainMap` check the health of a chain without knowing its
+/// concrete type while still being able to downcast it to that type
+#[async_trait]
+trait AnyBlockchain: Any + Debug + Send + Sync {
+    async fn health_check(&self) -> ChainHealth;
+}
+
+#[async_trait]
+impl<C: Blockchain> AnyBlockchain for C {
+    async fn health_check(&self) -> ChainHealth {
+        Blockchain::health_check(self).await
+    }
+}
+
 /// A collection of blockchains, keyed by `BlockchainKind` and network.
 #[derive(Default, Debug, Clone)]
-pub struct BlockchainMap(HashMap<(BlockchainKind, ChainName), Arc<dyn Any + Send + Sync>>);
+pub struct BlockchainMap(HashMap<(BlockchainKind, ChainName), Arc<dyn AnyBlockchain>>);
 
 impl BlockchainMap {
     pub fn new() -> Self {
@@ -618,8 +968,10 @@ impl BlockchainMap {
 
     pub fn iter(
         &self,
-    ) -> impl Iterator<Item = (&(BlockchainKind, ChainName), &Arc<dyn Any + Sync + Send>)> {
-        self.0.iter()
+    ) -> impl Iterator<Item = (&(BlockchainKind, ChainName), Arc<dyn Any + Sync + Send>)> {
+        self.0
+            .iter()
+            .map(|(key, chain)| (key, chain.cheap_clone() as Arc<dyn Any + Sync + Send>))
     }
 
     pub fn insert<C: Blockchain>(&mut self, network: ChainName, chain: Arc<C>) {
@@ -630,11 +982,10 @@ impl BlockchainMap {
         &self,
         kind: BlockchainKind,
     ) -> Result<Vec<Arc<C>>, Error> {
-        self.0
-            .iter()
+        self.iter()
             .flat_map(|((k, _), chain)| {
                 if k.eq(&kind) {
-                    Some(chain.cheap_clone().downcast().map_err(|_| {
+                    Some(chain.downcast().map_err(|_| {
                         anyhow!("unable to downcast, wrong type for blockchain {}", C::KIND)
                     }))
                 } else {
@@ -645,13 +996,285 @@ impl BlockchainMap {
     }
 
     pub fn get<C: Blockchain>(&self, network: ChainName) -> Result<Arc<C>, Error> {
-        self.0
+        let chain: Arc<dyn Any + Send + Sync> = self
+            .0
             .get(&(C::KIND, network.clone()))
             .with_context(|| format!("no network {} found on chain {}", network, C::KIND))?
-            .cheap_clone()
+            .cheap_clone();
+        chain
             .downcast()
             .map_err(|_| anyhow!("unable to downcast, wrong type for blockchain {}", C::KIND))
     }
+
+    /// Check the health of all chains concurrently. Chains whose check
+    /// does not finish within `HEALTH_CHECK_TIMEOUT` are reported as
+    /// `ChainHealth::TimedOut`
+    pub async fn health_report(&self) -> Vec<(ChainName, BlockchainKind, ChainHealth)> {
+        self.health_report_with_timeout(HEALTH_CHECK_TIMEOUT).await
+    }
+
+    /// Like `health_report`, but waits at most `timeout` for each chain.
+    /// The report is sorted by chain name and kind
+    pub async fn health_report_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Vec<(ChainName, BlockchainKind, ChainHealth)> {
+        let checks = self.0.iter().map(|((kind, name), chain)| async move {
+            let health = tokio::time::timeout(timeout, chain.health_check())
+                .await
+                .unwrap_or(ChainHealth::TimedOut);
+            (name.clone(), *kind, health)
+        });
+        let mut report = futures03::future::join_all(checks).await;
+        report.sort_by(|(n1, k1, _), (n2, k2, _)| n1.cmp(n2).then(k1.cmp(k2)));
+        report
+    }
 }
 
 pub type TriggerWithHandler<C> = data_source::TriggerWithHandler<<C as Blockchain>::MappingTrigger>;
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use std::sync::Arc;
+
+    use futures03::{FutureExt, StreamExt};
+
+    use super::{
+        BlockPtr, Blockchain, BlockchainKind, BlockchainMap, ChainHealth, IngestorError,
+        validate_manifest_chain, with_ingestor_timeout,
+    };
+    use crate::blockchain::mock::{MockBlockchain, MockChainHead};
+    use crate::env::ENV_VARS;
+
+    #[crate::test]
+    async fn ingestor_timeout_gives_up_on_requests_that_never_finish() {
+        let res = with_ingestor_timeout(
+            Duration::from_millis(10),
+            "chain_head_ptr",
+            std::future::pending::<Result<(), IngestorError>>(),
+        )
+        .await;
+
+        match res {
+            Err(IngestorError::Unknown(e)) => assert!(e.to_string().contains("chain_head_ptr")),
+            res => panic!("expected a timeout, got {:?}", res),
+        }
+    }
+
+    #[crate::test]
+    async fn ingestor_timeout_passes_results_through() {
+        let res = with_ingestor_timeout(Duration::from_secs(10), "chain_head_ptr", async {
+            Ok::<_, anyhow::Error>(7)
+        })
+        .await;
+        assert_eq!(res.unwrap(), 7);
+
+        let res = with_ingestor_timeout(Duration::from_secs(10), "chain_head_ptr", async {
+            Err::<(), _>(anyhow::anyhow!("no head"))
+        })
+        .await;
+        assert!(matches!(res, Err(IngestorError::Unknown(_))));
+    }
+
+    #[test]
+    fn registered_kind_alias_resolves() {
+        assert!(BlockchainKind::from_str("test-evm-l2").is_err());
+
+        BlockchainKind::register_alias("test-evm-l2", BlockchainKind::Ethereum).unwrap();
+        // Registering the same alias again is fine
+        BlockchainKind::register_alias("test-evm-l2", BlockchainKind::Ethereum).unwrap();
+
+        assert_eq!(
+            BlockchainKind::from_str("test-evm-l2").unwrap(),
+            BlockchainKind::Ethereum
+        );
+
+        let manifest: serde_yaml::Mapping =
+            serde_yaml::from_str("dataSources:\n  - kind: test-evm-l2/contract\n").unwrap();
+        assert_eq!(
+            BlockchainKind::from_manifest(&manifest).unwrap(),
+            BlockchainKind::Ethereum
+        );
+    }
+
+    fn manifest_chain(yaml: &str) -> Result<BlockchainKind, Vec<String>> {
+        let manifest: serde_yaml::Mapping = serde_yaml::from_str(yaml).unwrap();
+        validate_manifest_chain(&manifest)
+            .map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
+    }
+
+    #[test]
+    fn manifest_chain_of_consistent_data_sources() {
+        let yaml = "
+dataSources:
+  - name: Token
+    kind: ethereum/contract
+  - name: Metadata
+    kind: file/ipfs
+templates:
+  - name: Pair
+    kind: ethereum/contract
+";
+        assert_eq!(manifest_chain(yaml), Ok(BlockchainKind::Ethereum));
+
+        let yaml = "
+dataSources:
+  - name: Receipts
+    kind: near
+";
+        assert_eq!(manifest_chain(yaml), Ok(BlockchainKind::Near));
+    }
+
+    #[test]
+    fn manifest_chain_of_mixed_data_sources() {
+        let yaml = "
+dataSources:
+  - name: Token
+    kind: ethereum/contract
+  - name: Receipts
+    kind: near
+  - name: Other
+    kind: solana/program
+  - name: NoKind
+";
+        let errors = manifest_chain(yaml).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "data source `Other` has unknown kind `solana/program`".to_string(),
+                "data source `NoKind` has no kind".to_string(),
+                "all data sources must index the same kind of blockchain, \
+                 but they use ethereum (Token) and near (Receipts)"
+                    .to_string(),
+            ]
+        );
+
+        assert_eq!(
+            manifest_chain("specVersion: 1.3.0\n"),
+            Err(vec!["manifest has no data sources".to_string()])
+        );
+    }
+
+    #[test]
+    fn manifest_chain_of_subgraph_data_sources() {
+        // Subgraph data sources use the kind of the other data sources
+        let yaml = "
+dataSources:
+  - name: Source
+    kind: subgraph
+  - name: Receipts
+    kind: near
+";
+        assert_eq!(manifest_chain(yaml), Ok(BlockchainKind::Near));
+
+        let yaml = "
+dataSources:
+  - name: Source
+    kind: subgraph
+  - name: Other
+    kind: subgraph
+";
+        assert_eq!(manifest_chain(yaml), Ok(BlockchainKind::Ethereum));
+    }
+
+    #[test]
+    fn conflicting_kind_aliases_are_rejected() {
+        BlockchainKind::register_alias("test-conflict", BlockchainKind::Near).unwrap();
+        assert!(BlockchainKind::register_alias("test-conflict", BlockchainKind::Ethereum).is_err());
+        assert!(BlockchainKind::register_alias("near", BlockchainKind::Ethereum).is_err());
+
+        // Built-in names still resolve as before
+        assert_eq!(
+            BlockchainKind::from_str("near").unwrap(),
+            BlockchainKind::Near
+        );
+        assert_eq!(
+            BlockchainKind::from_str("test-conflict").unwrap(),
+            BlockchainKind::Near
+        );
+    }
+
+    #[test]
+    fn blocks_beyond_the_finality_depth_are_final() {
+        let chain = MockBlockchain::default();
+        let depth = chain.finality_depth();
+        assert_eq!(depth, ENV_VARS.reorg_threshold());
+
+        let head = depth + 100;
+        assert!(chain.is_final(100, head));
+        assert!(chain.is_final(99, head));
+        assert!(!chain.is_final(101, head));
+        assert!(!chain.is_final(head, head));
+    }
+
+    #[crate::test]
+    async fn chain_head_updates_report_new_heads() {
+        let chain = MockBlockchain::default();
+        let mut updates = chain.chain_head_updates();
+
+        // Heads of other networks are not reported
+        chain.head_updates.new_head("other");
+        assert!(updates.next().now_or_never().is_none());
+
+        chain.head_updates.new_head("mock");
+        assert_eq!(Some(()), updates.next().await);
+    }
+
+    #[crate::test]
+    async fn health_report_covers_all_chains() {
+        let head = BlockPtr::from((alloy::primitives::B256::repeat_byte(1), 42i32));
+        let mut chains = BlockchainMap::new();
+        for (name, head) in [
+            ("healthy", MockChainHead::Head(head.clone())),
+            (
+                "failing",
+                MockChainHead::Error("connection refused".to_string()),
+            ),
+            ("unconfigured", MockChainHead::NoProviders),
+            ("hanging", MockChainHead::Hang),
+        ] {
+            let chain = MockBlockchain {
+                head,
+                ..Default::default()
+            };
+            chains.insert(name.into(), Arc::new(chain));
+        }
+
+        let report = chains
+            .health_report_with_timeout(Duration::from_millis(50))
+            .await;
+        let report: Vec<_> = report
+            .into_iter()
+            .map(|(name, kind, health)| (name.to_string(), kind, health))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    "failing".to_string(),
+                    BlockchainKind::Ethereum,
+                    ChainHealth::Unhealthy("connection refused".to_string())
+                ),
+                (
+                    "hanging".to_string(),
+                    BlockchainKind::Ethereum,
+                    ChainHealth::TimedOut
+                ),
+                (
+                    "healthy".to_string(),
+                    BlockchainKind::Ethereum,
+                    ChainHealth::Healthy(head)
+                ),
+                (
+                    "unconfigured".to_string(),
+                    BlockchainKind::Ethereum,
+                    ChainHealth::Unhealthy("no providers".to_string())
+                ),
+            ],
+            report
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Reconstruct what a subgraph sees during a reorg from the blocks in the
+//! chain store, without asking a provider for anything.
+
+use std::sync::Arc;
+
+use anyhow::{Error, anyhow};
+
+use crate::components::store::ChainStore;
+use crate::prelude::BlockNumber;
+
+use super::BlockPtr;
+
+/// One step in the sequence of events a block stream sends to a subgraph
+/// when the chain head moves from one block to another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayEvent {
+    /// Revert the changes made in `block`, which leaves the subgraph at
+    /// `parent`. This is what `BlockStreamEvent::Revert` does.
+    Revert { block: BlockPtr, parent: BlockPtr },
+    /// Process `block`, like `BlockStreamEvent::ProcessBlock`
+    Apply(BlockPtr),
+}
+
+/// Replay the reorg from `old_head` to `new_head` using only blocks in
+/// `chain_store`. Like a block stream, the blocks of the old chain segment
+/// are reverted one at a time until the common ancestor of both heads is
+/// reached, and the blocks of the new chain segment are then applied in
+/// order.
+///
+/// Fails if a block on either segment is missing from the store, or if
+/// the common ancestor is more than `max_depth` blocks below either head.
+pub async fn replay_reorg(
+    chain_store: Arc<dyn ChainStore>,
+    old_head: BlockPtr,
+    new_head: BlockPtr,
+    max_depth: BlockNumber,
+) -> Result<Vec<ReplayEvent>, Error> {
+    let parent = |ptr: BlockPtr| {
+        let chain_store = chain_store.clone();
+        async move {
+            chain_store
+                .block_parent_ptr(&ptr.hash)
+                .await?
+                .ok_or_else(|| anyhow!("block {} is not in the chain store", ptr))
+        }
+    };
+
+    let lowest = old_head.number.min(new_head.number) - max_depth;
+    let mut reverts = Vec::new();
+    let mut applies = Vec::new();
+    let mut old = old_head;
+    let mut new = new_head;
+
+    while old != new {
+        if old.number.min(new.number) < lowest {
+            return Err(anyhow!(
+                "the chain segments do not have a common ancestor within {} blocks",
+                max_depth
+            ));
+        }
+
+        // Walk back on the segment with the higher block so that both walks
+        // meet at the common ancestor even if some block numbers are skipped
+        if old.number >= new.number {
+            let old_parent = parent(old.clone()).await?;
+            reverts.push(ReplayEvent::Revert {
+                block: old,
+                parent: old_parent.clone(),
+            });
+            old = old_parent;
+        } else {
+            let new_parent = parent(new.clone()).await?;
+            applies.push(ReplayEvent::Apply(new));
+            new = new_parent;
+        }
+    }
+
+    applies.reverse();
+    reverts.extend(applies);
+    Ok(reverts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use alloy::primitives::B256;
+
+    use super::*;
+    use crate::blockchain::mock::MockChainStore;
+
+    fn ptr(number: BlockNumber, fork: u8) -> BlockPtr {
+        let mut hash = B256::ZERO;
+        hash[0] = fork;
+        hash[31] = number as u8;
+        BlockPtr::from((hash, number))
+    }
+
+    /// A chain store with blocks 0 to 4 on fork `a` and a competing
+    /// segment 3 to 5 on fork `b` that branches off after block 2
+    fn chain_store() -> Arc<dyn ChainStore> {
+        const A: u8 = 0xa;
+        const B: u8 = 0xb;
+
+        let mut parents = HashMap::new();
+        for number in 1..=4 {
+            parents.insert(ptr(number, A).hash, ptr(number - 1, A));
+        }
+        parents.insert(ptr(3, B).hash, ptr(2, A));
+        for number in 4..=5 {
+            parents.insert(ptr(number, B).hash, ptr(number - 1, B));
+        }
+
+        Arc::new(MockChainStore {
+            parents: Mutex::new(parents),
+            ..Default::default()
+        })
+    }
+
+    #[crate::test]
+    async fn reverts_the_old_segment_and_applies_the_new_one() {
+        let events = replay_reorg(chain_store(), ptr(4, 0xa), ptr(5, 0xb), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                ReplayEvent::Revert {
+                    block: ptr(4, 0xa),
+                    parent: ptr(3, 0xa)
+                },
+                ReplayEvent::Revert {
+                    block: ptr(3, 0xa),
+                    parent: ptr(2, 0xa)
+                },
+                ReplayEvent::Apply(ptr(3, 0xb)),
+                ReplayEvent::Apply(ptr(4, 0xb)),
+                ReplayEvent::Apply(ptr(5, 0xb)),
+            ]
+        );
+    }
+
+    #[crate::test]
+    async fn moving_to_an_ancestor_only_reverts() {
+        let events = replay_reorg(chain_store(), ptr(4, 0xa), ptr(3, 0xa), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events,
+            vec![ReplayEvent::Revert {
+                block: ptr(4, 0xa),
+                parent: ptr(3, 0xa)
+            }]
+        );
+    }
+
+    #[crate::test]
+    async fn fails_without_a_common_ancestor() {
+        // The common ancestor is two blocks below the old head, more than
+        // `max_depth` allows
+        replay_reorg(chain_store(), ptr(4, 0xa), ptr(5, 0xb), 1)
+            .await
+            .unwrap_err();
+
+        // Blocks that are not in the store can not be reverted
+        replay_reorg(chain_store(), ptr(7, 0xc), ptr(5, 0xb), 10)
+            .await
+            .unwrap_err();
+    }
+}
@@ -490,6 +490,17 @@ impl Default for ChainIdentifier {
     }
 }
 
+/// Information about a chain that tools and mappings need to display or
+/// convert amounts of the chain's native token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainMetadata {
+    /// The symbol of the native token, e.g., `ETH`
+    pub native_token_symbol: String,
+    /// The number of decimals of the native token
+    pub native_token_decimals: u8,
+    pub identifier: ChainIdentifier,
+}
+
 impl fmt::Display for ChainIdentifier {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
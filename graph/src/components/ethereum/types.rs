@@ -292,6 +292,44 @@ impl CachedBlock {
         }
     }
 
+    /// Whether `self` and `other`, which have the same hash, contradict
+    /// each other. The blocks conflict if they sit at a different place in
+    /// the chain, contain different transactions, or if the transactions
+    /// had different outcomes. Receipts are only compared if both blocks
+    /// have them.
+    pub fn conflicts_with(&self, other: &CachedBlock) -> bool {
+        let header = |block: &AnyBlock| {
+            (
+                block.header.number,
+                block.header.parent_hash,
+                block.transactions.hashes().collect::<Vec<_>>(),
+            )
+        };
+        if header(self.light_block().inner()) != header(other.light_block().inner()) {
+            return true;
+        }
+
+        let receipts = |block: &EthereumBlock| {
+            block
+                .transaction_receipts
+                .iter()
+                .map(|receipt| {
+                    (
+                        receipt.transaction_hash,
+                        receipt.status(),
+                        receipt.logs().to_vec(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        match (self, other) {
+            (CachedBlock::Full(block), CachedBlock::Full(other)) => {
+                receipts(block) != receipts(other)
+            }
+            _ => false,
+        }
+    }
+
     /// Serializes the block data directly. Note: the output shape differs
     /// from the store format (which wraps in a `{"block": ..}` envelope).
     pub fn to_json(&self) -> serde_json::Result<serde_json::Value> {
@@ -17,7 +17,9 @@ use crate::futures01::Poll;
 use crate::futures01::stream::Stream;
 use crate::futures01::stream::poll_fn;
 use crate::futures01::try_ready;
-use crate::ipfs::{ContentPath, IpfsClient, IpfsContext, RetryPolicy};
+use crate::ipfs::{
+    ContentPath, DeploymentIpfsClients, IpfsClient, IpfsContext, RetryPolicy, Revalidated,
+};
 use crate::prelude::*;
 
 use super::{LinkResolver, LinkResolverContext};
@@ -26,6 +28,8 @@ use super::{LinkResolver, LinkResolverContext};
 pub struct IpfsResolver {
     #[debug(skip)]
     client: Arc<dyn IpfsClient>,
+    #[debug(skip)]
+    deployment_clients: DeploymentIpfsClients,
 
     timeout: Duration,
     max_file_size: usize,
@@ -41,12 +45,25 @@ impl IpfsResolver {
 
         Self {
             client,
+            deployment_clients: DeploymentIpfsClients::default(),
             timeout: env.ipfs_timeout,
             max_file_size: env.max_ipfs_file_bytes,
             max_map_file_size: env.max_ipfs_map_file_size,
             retry: false,
         }
     }
+
+    /// Use `deployment_clients` instead of the default client for the
+    /// deployments that have their own client.
+    pub fn with_deployment_clients(mut self, deployment_clients: DeploymentIpfsClients) -> Self {
+        self.deployment_clients = deployment_clients;
+        self
+    }
+
+    fn client(&self, deployment_hash: &str) -> Arc<dyn IpfsClient> {
+        self.deployment_clients
+            .client_for(deployment_hash, &self.client)
+    }
 }
 
 #[async_trait]
@@ -87,12 +104,21 @@ impl LinkResolver for IpfsResolver {
             deployment_hash: deployment_hash.cheap_clone(),
             logger: logger.cheap_clone(),
         };
-        let data = self
-            .client
-            .clone()
-            .cat(&ctx, &path, max_file_size, timeout, retry_policy)
+        // Without a copy of our own, the content is always sent to us, but
+        // a caching client can revalidate the copy it has
+        let data = match self
+            .client(&ctx.deployment_hash)
+            .cat_if_none_match(&ctx, &path, None, max_file_size, timeout, retry_policy)
             .await?
-            .to_vec();
+        {
+            Revalidated::Modified { data, .. } => data.to_vec(),
+            Revalidated::NotModified => {
+                return Err(anyhow!(
+                    "IPFS server reported an unrequested copy of {} as current",
+                    path
+                ));
+            }
+        };
 
         Ok(data)
     }
@@ -119,8 +145,7 @@ impl LinkResolver for IpfsResolver {
             logger: logger.cheap_clone(),
         };
         let data = self
-            .client
-            .clone()
+            .client(&ctx.deployment_hash)
             .get_block(&ctx, &path, timeout, retry_policy)
             .await?
             .to_vec();
@@ -155,8 +180,7 @@ impl LinkResolver for IpfsResolver {
             logger: logger.cheap_clone(),
         };
         let mut stream = self
-            .client
-            .clone()
+            .client(&ctx.deployment_hash)
             .cat_stream(&ctx, &path, timeout, retry_policy)
             .await?
             .fuse()
@@ -234,6 +258,31 @@ impl LinkResolver for IpfsResolver {
 
         Ok(stream)
     }
+
+    async fn warm_cache(
+        &self,
+        ctx: &LinkResolverContext,
+        paths: &[ContentPath],
+    ) -> Vec<(ContentPath, Result<(), Error>)> {
+        let LinkResolverContext {
+            deployment_hash,
+            logger,
+        } = ctx;
+
+        let ctx = IpfsContext {
+            deployment_hash: deployment_hash.cheap_clone(),
+            logger: logger.cheap_clone(),
+        };
+
+        // Warming is best effort, and never retries indefinitely even if
+        // the resolver does
+        self.client(&ctx.deployment_hash)
+            .warm_cache(&ctx, paths, Some(self.timeout), RetryPolicy::Networking)
+            .await
+            .into_iter()
+            .map(|(path, res)| (path, res.map_err(Error::from)))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -243,7 +292,7 @@ mod tests {
     use super::*;
     use crate::env::EnvVars;
     use crate::ipfs::test_utils::add_files_to_local_ipfs_node_for_testing;
-    use crate::ipfs::{IpfsMetrics, IpfsRpcClient, ServerAddress};
+    use crate::ipfs::{IpfsGatewayClient, IpfsMetrics, IpfsRpcClient, ServerAddress};
 
     #[crate::test]
     async fn max_file_size() {
@@ -340,4 +389,52 @@ mod tests {
             values.unwrap()
         );
     }
+
+    #[crate::test]
+    async fn uses_the_client_of_the_deployment() {
+        use wiremock::matchers as m;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        const CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+        async fn server(body: &'static str) -> (MockServer, Arc<dyn IpfsClient>) {
+            let server = MockServer::start().await;
+            Mock::given(m::method("GET"))
+                .and(m::path(format!("/ipfs/{CID}")))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+                .mount(&server)
+                .await;
+            let client = IpfsGatewayClient::new_unchecked(
+                server.uri(),
+                IpfsMetrics::test(),
+                &crate::log::discard(),
+            )
+            .unwrap();
+            (server, Arc::new(client))
+        }
+
+        let (_default_server, default_client) = server("default").await;
+        let (_private_server, private_client) = server("private").await;
+
+        let private = DeploymentHash::new("QmPrivate").unwrap();
+        let other = DeploymentHash::new("QmOther").unwrap();
+        let resolver = IpfsResolver::new(default_client, Arc::new(EnvVars::default()))
+            .with_deployment_clients(DeploymentIpfsClients::new([(
+                private.clone(),
+                private_client,
+            )]));
+
+        let logger = crate::log::discard();
+        let link = Link {
+            link: CID.to_string(),
+        };
+
+        let ctx = LinkResolverContext::new(&private, &logger);
+        let data = resolver.cat(&ctx, &link).await.unwrap();
+        assert_eq!(b"private".to_vec(), data);
+
+        let ctx = LinkResolverContext::new(&other, &logger);
+        let data = resolver.cat(&ctx, &link).await.unwrap();
+        assert_eq!(b"default".to_vec(), data);
+    }
 }
@@ -6,6 +6,7 @@ use crate::{
     cheap_clone::CheapClone,
     data::subgraph::{DeploymentHash, Link},
     derive::CheapClone,
+    ipfs::ContentPath,
     prelude::Error,
 };
 
@@ -56,6 +57,18 @@ pub trait LinkResolver: Send + Sync + 'static + Debug {
         ctx: &LinkResolverContext,
         link: &Link,
     ) -> Result<JsonValueStream, Error>;
+
+    /// Fetches the content of all IPFS `paths` ahead of time so that later
+    /// requests for them can be served from a cache. Returns the outcome for
+    /// each path; resolvers that do not cache IPFS content do nothing and
+    /// return an empty list.
+    async fn warm_cache(
+        &self,
+        _ctx: &LinkResolverContext,
+        _paths: &[ContentPath],
+    ) -> Vec<(ContentPath, Result<(), Error>)> {
+        vec![]
+    }
 }
 
 #[derive(Debug, Clone, CheapClone)]
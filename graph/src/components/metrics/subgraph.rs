@@ -4,12 +4,14 @@ use std::time::Duration;
 
 use indoc::indoc;
 use prometheus::Counter;
+use prometheus::CounterVec;
 use prometheus::IntGauge;
 
 use super::MetricsRegistry;
 use super::stopwatch::StopwatchMetrics;
 use crate::blockchain::block_stream::BlockStreamMetrics;
-use crate::components::store::DeploymentLocator;
+use crate::components::store::{BlockNumber, DeploymentLocator};
+use crate::env::ENV_VARS;
 use crate::prelude::{Gauge, Histogram, HostMetrics};
 
 pub struct SubgraphInstanceMetrics {
@@ -24,6 +26,8 @@ pub struct SubgraphInstanceMetrics {
     trigger_processing_duration: Box<Histogram>,
     blocks_processed_secs: Box<Counter>,
     blocks_processed_count: Box<Counter>,
+    triggers_tested: Box<CounterVec>,
+    triggers_matched: Box<CounterVec>,
 }
 
 impl SubgraphInstanceMetrics {
@@ -93,6 +97,23 @@ impl SubgraphInstanceMetrics {
             )
             .expect("failed to create blocks_processed_count counter");
 
+        let triggers_tested = registry
+            .new_deployment_counter_vec(
+                "deployment_triggers_tested",
+                "Counts the triggers that were tested against a data source in sampled blocks",
+                subgraph_hash,
+                vec![String::from("data_source")],
+            )
+            .expect("failed to create `deployment_triggers_tested` counter");
+        let triggers_matched = registry
+            .new_deployment_counter_vec(
+                "deployment_triggers_matched",
+                "Counts the triggers that matched a data source in sampled blocks",
+                subgraph_hash,
+                vec![String::from("data_source")],
+            )
+            .expect("failed to create `deployment_triggers_matched` counter");
+
         let deployment_synced =
             DeploymentSyncedMetric::register(&registry, subgraph_hash, &stopwatch.shard());
 
@@ -107,6 +128,8 @@ impl SubgraphInstanceMetrics {
             trigger_processing_duration,
             blocks_processed_secs,
             blocks_processed_count,
+            triggers_tested,
+            triggers_matched,
         }
     }
 
@@ -121,15 +144,71 @@ impl SubgraphInstanceMetrics {
         }
     }
 
+    pub fn observe_trigger_match_stats(&self, stats: &TriggerMatchStats) {
+        for (data_source, (tested, matched)) in &stats.counts {
+            self.triggers_tested
+                .with_label_values(&[data_source.as_str()])
+                .inc_by(*tested as f64);
+            self.triggers_matched
+                .with_label_values(&[data_source.as_str()])
+                .inc_by(*matched as f64);
+        }
+    }
+
     pub fn unregister(&self, registry: Arc<MetricsRegistry>) {
         registry.unregister(self.block_processing_duration.clone());
         registry.unregister(self.block_trigger_count.clone());
         registry.unregister(self.trigger_processing_duration.clone());
         registry.unregister(self.block_ops_transaction_duration.clone());
+        registry.unregister(self.triggers_tested.clone());
+        registry.unregister(self.triggers_matched.clone());
         registry.unregister(Box::new(self.deployment_synced.inner.clone()));
     }
 }
 
+/// Counts, for each data source, how many triggers in a block were tested
+/// against the data source with `match_and_decode` and how many of them
+/// matched. Since this is called for every pair of trigger and data source,
+/// the counts are only collected for a sample of blocks.
+#[derive(Debug, Default)]
+pub struct TriggerMatchStats {
+    counts: HashMap<String, (u64, u64)>,
+}
+
+impl TriggerMatchStats {
+    /// Return empty stats if the block `number` should be sampled according
+    /// to `GRAPH_TRIGGER_MATCH_STATS_SAMPLE_RATE`, and `None` otherwise
+    pub fn for_block(number: BlockNumber) -> Option<Self> {
+        Self::sampled(number, ENV_VARS.trigger_match_stats_sample_rate).then(Self::default)
+    }
+
+    fn sampled(number: BlockNumber, rate: u32) -> bool {
+        rate > 0 && i64::from(number) % i64::from(rate) == 0
+    }
+
+    pub fn record(&mut self, data_source: &str, matched: bool) {
+        let counts = self.counts.entry(data_source.to_string()).or_default();
+        counts.0 += 1;
+        if matched {
+            counts.1 += 1;
+        }
+    }
+
+    /// The number of triggers that were tested against `data_source`
+    pub fn tested(&self, data_source: &str) -> u64 {
+        self.counts
+            .get(data_source)
+            .map_or(0, |(tested, _)| *tested)
+    }
+
+    /// The number of triggers that matched `data_source`
+    pub fn matched(&self, data_source: &str) -> u64 {
+        self.counts
+            .get(data_source)
+            .map_or(0, |(_, matched)| *matched)
+    }
+}
+
 #[derive(Debug)]
 pub struct SubgraphCountMetric {
     pub running_count: Box<Gauge>,
@@ -281,3 +360,34 @@ impl DeploymentSyncedMetric {
         self.inner.set(Self::NOT_SYNCED);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TriggerMatchStats;
+
+    #[test]
+    fn trigger_match_stats_count_per_data_source() {
+        // Three triggers, each tested against two data sources; `Token`
+        // matches all of them, `Factory` only the first one
+        let mut stats = TriggerMatchStats::default();
+        for trigger in 0..3 {
+            stats.record("Token", true);
+            stats.record("Factory", trigger == 0);
+        }
+
+        assert_eq!(stats.tested("Token"), 3);
+        assert_eq!(stats.matched("Token"), 3);
+        assert_eq!(stats.tested("Factory"), 3);
+        assert_eq!(stats.matched("Factory"), 1);
+        assert_eq!(stats.tested("Other"), 0);
+        assert_eq!(stats.matched("Other"), 0);
+    }
+
+    #[test]
+    fn trigger_match_stats_sample_blocks() {
+        assert!(!TriggerMatchStats::sampled(10, 0));
+        assert!(TriggerMatchStats::sampled(7, 1));
+        assert!(TriggerMatchStats::sampled(10, 5));
+        assert!(!TriggerMatchStats::sampled(11, 5));
+    }
+}
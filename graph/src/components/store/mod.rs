@@ -28,11 +28,12 @@ use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 use std::fmt::Display;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::Duration;
 
 use async_trait::async_trait;
 
+use crate::blockchain::block_stream::EntityOperationKind;
 use crate::blockchain::{Block, BlockHash, BlockPtr};
 use crate::cheap_clone::CheapClone;
 use crate::components::store::write::EntityModification;
@@ -106,6 +107,29 @@ impl DerivedEntityQuery {
     }
 }
 
+/// A position in the changelog of a deployment. Changes are ordered by
+/// block, then by entity type, then by id, and `id` is the id of the entity
+/// as bytes
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChangelogCursor {
+    pub block: BlockNumber,
+    pub entity_type: String,
+    pub id: Vec<u8>,
+}
+
+/// One change in the changelog of a deployment. For deletions, `entity` is
+/// the version of the entity that was deleted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangelogEntry {
+    pub block: BlockNumber,
+    pub op: EntityOperationKind,
+    pub entity_type: EntityType,
+    pub entity: Entity,
+    /// Pass this as the `after` argument of
+    /// [SourceableStore::get_changelog] to continue after this change
+    pub cursor: ChangelogCursor,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Child {
     pub attr: Attribute,
@@ -933,6 +957,62 @@ pub struct VersionStats {
     /// Postgres version 16 or lower, or if the table doesn't have a
     /// block_range column.
     pub block_range_upper: Vec<BlockNumber>,
+    /// Histogram of the widths of the block ranges in this table, in
+    /// blocks, sorted in ascending order. Block ranges that are still open
+    /// have a width of `BLOCK_NUMBER_MAX`. Like `block_range_upper`, this
+    /// is empty if Postgres doesn't have statistics for it.
+    pub block_range_width: Vec<BlockNumber>,
+}
+
+impl VersionStats {
+    /// Tables with fewer versions than this are small enough that the
+    /// index used for `block_range` hardly matters
+    const MIN_BRIN_VERSIONS: i64 = 10_000;
+    /// Versions whose block range is at most this wide are short-lived
+    const SHORT_LIVED_WIDTH: BlockNumber = 10_000;
+    /// Without a width histogram, tables with at most this ratio of
+    /// entities to versions are assumed to have mostly short-lived versions
+    const MAX_BRIN_RATIO: f64 = 0.1;
+
+    /// Recommend which index queries against this table should use to
+    /// find the versions visible at a block. This is only advice; whether
+    /// the BRIN index is actually used is controlled by the table's
+    /// account-like flag.
+    ///
+    /// The BRIN index pays off for large tables where most versions are
+    /// short-lived, since their block ranges are narrow and the versions
+    /// visible at a block are stored close to each other. Tables where
+    /// versions mostly stay current are better served by the GiST index.
+    pub fn recommended_index(&self) -> BlockRangeIndex {
+        if self.versions < Self::MIN_BRIN_VERSIONS {
+            return BlockRangeIndex::Gist;
+        }
+
+        let short_lived = if self.block_range_width.is_empty() {
+            self.ratio <= Self::MAX_BRIN_RATIO
+        } else {
+            let median = self.block_range_width[self.block_range_width.len() / 2];
+            median <= Self::SHORT_LIVED_WIDTH
+        };
+
+        if short_lived {
+            BlockRangeIndex::Brin
+        } else {
+            BlockRangeIndex::Gist
+        }
+    }
+}
+
+/// The index that queries should use to find the entity versions that are
+/// visible at a given block
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum BlockRangeIndex {
+    /// The GiST index on `block_range`
+    Gist,
+    /// The BRIN index on the lower and upper bound of `block_range`, which
+    /// queries use when a table is marked as account-like
+    Brin,
 }
 
 /// What phase of pruning we are working on
@@ -1091,6 +1171,11 @@ pub struct PruneRequest {
     /// `ENV_VARS.store.delete_threshold`, but can be modified after
     /// construction
     pub delete_threshold: f64,
+    /// Keep historical entity versions that are visible at a block that
+    /// is a multiple of this interval; a value of 0 keeps no historical
+    /// versions. Initialized from `ENV_VARS.store.history_sample_interval`,
+    /// but can be modified after construction
+    pub sample_interval: BlockNumber,
 }
 
 impl PruneRequest {
@@ -1155,9 +1240,30 @@ impl PruneRequest {
             first_block,
             rebuild_threshold,
             delete_threshold,
+            sample_interval: ENV_VARS.store.history_sample_interval,
         })
     }
 
+    /// Return `true` if pruning keeps a sample of historical entity
+    /// versions
+    pub fn keeps_samples(&self) -> bool {
+        self.sample_interval > 0
+    }
+
+    /// The earliest block that queries can use after pruning a deployment
+    /// whose data currently starts at `earliest_block`. Without samples,
+    /// that is `self.earliest_block`. With samples, it is the oldest
+    /// sample that pruning keeps, since every version visible at a sampled
+    /// block survives pruning
+    pub fn earliest_queryable_block(&self, earliest_block: BlockNumber) -> BlockNumber {
+        if !self.keeps_samples() {
+            return self.earliest_block;
+        }
+        let interval = self.sample_interval;
+        let oldest_sample = (earliest_block.max(0) + interval - 1) / interval * interval;
+        oldest_sample.min(self.earliest_block)
+    }
+
     /// Determine what strategy to use for pruning
     ///
     /// We are pruning `history_pct` of the blocks from a table that has a
@@ -1190,7 +1296,10 @@ impl PruneRequest {
             self.remove_pct_from_bounds(stats)
         };
 
-        if removal_ratio >= self.rebuild_threshold {
+        // The samples we keep are spread over the entire table, and
+        // rebuilding only copies versions that are visible after
+        // `earliest_block`
+        if removal_ratio >= self.rebuild_threshold && !self.keeps_samples() {
             Some(PruningStrategy::Rebuild)
         } else if removal_ratio >= self.delete_threshold {
             Some(PruningStrategy::Delete)
@@ -1244,3 +1353,69 @@ pub struct CachedEthereumCall {
     /// The encoded return value of this call.
     pub return_value: Vec<u8>,
 }
+
+/// Shared limit on how many entity versions per second subgraphs write to
+/// a shard. All clones refer to the same limit, which can be changed while
+/// graph-node is running; a limit of 0 means writes are not limited.
+#[derive(Clone, Debug, Default)]
+pub struct WriteRateLimit(Arc<AtomicU64>);
+
+impl WriteRateLimit {
+    pub fn new(rows_per_second: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(rows_per_second)))
+    }
+
+    pub fn rows_per_second(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rows_per_second(&self, rows_per_second: u64) {
+        self.0.store(rows_per_second, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(versions: i64, ratio: f64, width: Vec<BlockNumber>) -> VersionStats {
+        VersionStats {
+            entities: (versions as f64 * ratio) as i64,
+            versions,
+            tablename: "table".to_string(),
+            ratio,
+            last_pruned_block: None,
+            block_range_upper: vec![],
+            block_range_width: width,
+        }
+    }
+
+    #[test]
+    fn recommended_index_follows_range_widths() {
+        const OPEN: BlockNumber = BLOCK_NUMBER_MAX;
+
+        // Most versions are replaced within a few blocks
+        let short = stats(1_000_000, 0.5, vec![1, 1, 2, 5, 40, 300, OPEN]);
+        assert_eq!(BlockRangeIndex::Brin, short.recommended_index());
+
+        // Most versions are still current
+        let long = stats(1_000_000, 0.01, vec![1, 50_000, OPEN, OPEN, OPEN]);
+        assert_eq!(BlockRangeIndex::Gist, long.recommended_index());
+
+        // Small tables don't need the BRIN index
+        let small = stats(100, 0.01, vec![1, 1, 1]);
+        assert_eq!(BlockRangeIndex::Gist, small.recommended_index());
+    }
+
+    #[test]
+    fn recommended_index_uses_ratio_without_histogram() {
+        assert_eq!(
+            BlockRangeIndex::Brin,
+            stats(1_000_000, 0.01, vec![]).recommended_index()
+        );
+        assert_eq!(
+            BlockRangeIndex::Gist,
+            stats(1_000_000, 0.8, vec![]).recommended_index()
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::ops::Range;
 
 use anyhow::Error;
 use async_trait::async_trait;
+use futures03::stream::BoxStream;
 
 use super::*;
 use crate::blockchain::block_stream::{EntitySourceOperation, FirehoseCursor};
@@ -340,6 +341,19 @@ pub trait SourceableStore: Sync + Send + 'static {
         block_range: Range<BlockNumber>,
     ) -> Result<BTreeMap<BlockNumber, Vec<EntitySourceOperation>>, StoreError>;
 
+    /// Returns up to `limit` changes to entities of all types in
+    /// `causality_region` in the given `block_range`, ordered by block,
+    /// entity type and id. Only changes after `after` are returned, which
+    /// makes it possible to page through the changes with the cursor of the
+    /// last change of the previous page.
+    async fn get_changelog(
+        &self,
+        causality_region: CausalityRegion,
+        block_range: Range<BlockNumber>,
+        after: Option<ChangelogCursor>,
+        limit: usize,
+    ) -> Result<Vec<ChangelogEntry>, StoreError>;
+
     fn input_schema(&self) -> InputSchema;
 
     /// Get a pointer to the most recently processed block in the subgraph.
@@ -360,6 +374,18 @@ impl<T: ?Sized + SourceableStore> SourceableStore for Arc<T> {
             .await
     }
 
+    async fn get_changelog(
+        &self,
+        causality_region: CausalityRegion,
+        block_range: Range<BlockNumber>,
+        after: Option<ChangelogCursor>,
+        limit: usize,
+    ) -> Result<Vec<ChangelogEntry>, StoreError> {
+        (**self)
+            .get_changelog(causality_region, block_range, after, limit)
+            .await
+    }
+
     fn input_schema(&self) -> InputSchema {
         (**self).input_schema()
     }
@@ -565,6 +591,17 @@ pub trait ChainStore: ChainHeadStore {
 
     async fn upsert_light_blocks(&self, blocks: &[&dyn Block]) -> Result<(), Error>;
 
+    /// Insert `block` like `upsert_block` if `overwrite` is set, and like
+    /// `upsert_light_blocks` otherwise, unless the store already has a
+    /// block with the same hash that conflicts with it according to
+    /// `CachedBlock::conflicts_with`. In that case, leave the store
+    /// unchanged and return `false`
+    async fn upsert_block_unless_conflicting(
+        &self,
+        block: Arc<dyn Block>,
+        overwrite: bool,
+    ) -> Result<bool, Error>;
+
     /// Try to update the head block pointer to the block with the highest block number.
     ///
     /// Only updates pointer if there is a block with a higher block number than the current head
@@ -643,6 +680,27 @@ pub trait ChainStore: ChainHeadStore {
         ancestor_count: BlockNumber,
     ) -> Result<Option<(BlockNumber, usize)>, Error>;
 
+    /// Stream the pointers and parent hashes of the blocks in the store
+    /// whose number is in `range`, ordered by number and then by hash. All
+    /// blocks with the same number are included, so that blocks from
+    /// competing forks follow each other. The store is read `batch_size`
+    /// block numbers at a time, so that long ranges are never loaded into
+    /// memory at once
+    fn block_ptrs_in_range(
+        self: Arc<Self>,
+        range: Range<BlockNumber>,
+        batch_size: usize,
+    ) -> BoxStream<'static, Result<(BlockPtr, BlockHash), Error>>;
+
+    /// Stream the blocks in the store whose number is in `range`, in the
+    /// same order as `block_ptrs_in_range`. Blocks whose data is not in
+    /// the store are skipped. Blocks are loaded `batch_size` at a time
+    fn blocks_in_range(
+        self: Arc<Self>,
+        range: Range<BlockNumber>,
+        batch_size: usize,
+    ) -> BoxStream<'static, Result<CachedBlock, Error>>;
+
     /// Return the hashes of all blocks with the given number
     async fn block_hashes_by_block_number(
         &self,
@@ -751,6 +809,15 @@ pub trait QueryStore: Send + Sync {
 
     async fn block_ptr(&self) -> Result<Option<BlockPtr>, StoreError>;
 
+    /// Return the block at which the deployment wrote `cursor` so that
+    /// queries can be run as of that cursor. Returns `None` if the cursor
+    /// is unknown, e.g., because cursor history is not being recorded or
+    /// because the block it points to was reverted
+    async fn block_for_firehose_cursor(
+        &self,
+        cursor: &FirehoseCursor,
+    ) -> Result<Option<BlockPtr>, StoreError>;
+
     async fn block_number(&self, block_hash: &BlockHash)
     -> Result<Option<BlockNumber>, StoreError>;
 
@@ -770,6 +770,9 @@ pub struct Batch {
     pub first_block: BlockNumber,
     /// The firehose cursor corresponding to `block_ptr`
     pub firehose_cursor: FirehoseCursor,
+    /// The block pointer and firehose cursor for each block whose batch
+    /// has been appended to this one, in ascending order of block number
+    pub block_cursors: Vec<(BlockPtr, FirehoseCursor)>,
     pub mods: RowGroups,
     /// New data sources
     pub data_sources: DataSources,
@@ -819,11 +822,13 @@ impl Batch {
         let offchain_to_remove = DataSources::new(block_ptr.cheap_clone(), offchain_to_remove);
         let first_block = block_ptr.number;
         let block_times = vec![(block, block_time)];
+        let block_cursors = vec![(block_ptr.cheap_clone(), firehose_cursor.clone())];
         let mut batch = Self {
             block_ptr,
             first_block,
             block_times,
             firehose_cursor,
+            block_cursors,
             mods,
             data_sources,
             deterministic_errors,
@@ -848,6 +853,7 @@ impl Batch {
         self.block_ptr = batch.block_ptr;
         self.block_times.append(&mut batch.block_times);
         self.firehose_cursor = batch.firehose_cursor;
+        self.block_cursors.append(&mut batch.block_cursors);
         self.mods.append(batch.mods)?;
         self.data_sources.append(batch.data_sources);
         self.deterministic_errors
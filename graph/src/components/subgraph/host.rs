@@ -19,6 +19,9 @@ use crate::{blockchain::Blockchain, components::subgraph::SharedProofOfIndexing}
 pub enum MappingError {
     /// A possible reorg was detected while running the mapping.
     PossibleReorg(anyhow::Error),
+    /// An error that will happen again when the trigger is processed
+    /// again, like a declared call that ran out of gas.
+    Deterministic(anyhow::Error),
     Unknown(anyhow::Error),
 }
 
@@ -44,6 +47,7 @@ impl MappingError {
         use MappingError::*;
         match self {
             PossibleReorg(e) => PossibleReorg(e.context(s)),
+            Deterministic(e) => Deterministic(e.context(s)),
             Unknown(e) => Unknown(e.context(s)),
         }
     }
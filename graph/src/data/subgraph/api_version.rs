@@ -72,12 +72,15 @@ pub const SPEC_VERSION_1_4_0: Version = Version::new(1, 4, 0);
 // Enables support for Amp data sources;
 pub const SPEC_VERSION_1_5_0: Version = Version::new(1, 5, 0);
 
+// Enables positional event param access in declarative calls
+pub const SPEC_VERSION_1_6_0: Version = Version::new(1, 6, 0);
+
 // The latest spec version available
-pub const LATEST_VERSION: &Version = &SPEC_VERSION_1_5_0;
+pub const LATEST_VERSION: &Version = &SPEC_VERSION_1_6_0;
 
 pub const MIN_SPEC_VERSION: Version = Version::new(0, 0, 2);
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Default)]
 pub struct UnifiedMappingApiVersion(Option<Version>);
 
 impl UnifiedMappingApiVersion {
@@ -5,7 +5,7 @@
 //! or other heavy dependencies.
 
 use semver::Version;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 
 use super::api_version::API_VERSION_0_0_5;
 use super::{DifferentMappingApiVersions, SubgraphManifestValidationError};
@@ -38,6 +38,23 @@ pub fn validate_single_network(
     }
 }
 
+/// Validate that the manifest's spec version is at least the minimum that
+/// this node accepts for new deployments. This is in addition to the
+/// minimum spec version that each data source requires.
+pub fn validate_min_spec_version(
+    spec_version: &Version,
+    min_spec_version: &Version,
+) -> Result<(), SubgraphManifestValidationError> {
+    if spec_version < min_spec_version {
+        Err(SubgraphManifestValidationError::SpecVersionTooLow {
+            spec_version: spec_version.clone(),
+            required: min_spec_version.clone(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
 /// Validate that all data sources use the same API version when any version >= 0.0.5.
 ///
 /// For API versions < 0.0.5, different versions are allowed.
@@ -57,6 +74,50 @@ pub fn validate_api_versions(versions: &[Version]) -> Result<(), SubgraphManifes
     }
 }
 
+/// The handler kinds that a data source in a raw manifest declares. The
+/// handlers of each kind are listed under `<kind>Handlers` in the mapping
+/// of the data source, e.g. `eventHandlers` or `blockHandlers`.
+///
+/// This looks at the raw manifest since parsing the data source for a
+/// chain ignores handlers the chain does not know about.
+pub fn declared_handler_kinds(data_source: &serde_yaml::Value) -> BTreeSet<String> {
+    let Some(mapping) = data_source.get("mapping").and_then(|m| m.as_mapping()) else {
+        return BTreeSet::new();
+    };
+
+    mapping
+        .iter()
+        .filter_map(|(key, handlers)| {
+            let kind = key.as_str()?.strip_suffix("Handlers")?;
+            let declared = handlers.as_sequence().is_some_and(|h| !h.is_empty());
+            declared.then(|| kind.to_string())
+        })
+        .collect()
+}
+
+/// Validate that a data source only declares handler kinds that are in
+/// `supported`, the handler kinds that the chain supports.
+pub fn validate_handler_kinds(
+    data_source: &str,
+    handler_kinds: &BTreeSet<String>,
+    supported: &HashSet<&str>,
+) -> Result<(), SubgraphManifestValidationError> {
+    let unsupported: Vec<&str> = handler_kinds
+        .iter()
+        .map(String::as_str)
+        .filter(|kind| !supported.contains(kind))
+        .collect();
+
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(SubgraphManifestValidationError::UnsupportedHandlerKinds {
+            data_source: data_source.to_string(),
+            kinds: unsupported.into_iter().map(str::to_string).collect(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,6 +160,25 @@ mod tests {
         assert!(validate_single_network(&[Some("mainnet"), None, Some("mainnet")]).is_ok());
     }
 
+    #[test]
+    fn test_validate_min_spec_version() {
+        let min = Version::new(1, 0, 0);
+
+        assert!(validate_min_spec_version(&Version::new(1, 0, 0), &min).is_ok());
+        assert!(validate_min_spec_version(&Version::new(1, 2, 0), &min).is_ok());
+
+        // Below the configured minimum - error naming the required version
+        let err = validate_min_spec_version(&Version::new(0, 0, 9), &min).unwrap_err();
+        assert!(matches!(
+            err,
+            SubgraphManifestValidationError::SpecVersionTooLow { .. }
+        ));
+        assert_eq!(
+            "subgraph uses spec version 0.0.9, but this Graph Node requires at least spec version 1.0.0",
+            err.to_string()
+        );
+    }
+
     #[test]
     fn test_validate_api_versions() {
         // All same version >= 0.0.5 - ok
@@ -122,4 +202,53 @@ mod tests {
         // Single version - ok
         assert!(validate_api_versions(&[Version::new(0, 0, 5)]).is_ok());
     }
+
+    fn kinds(kinds: &[&str]) -> BTreeSet<String> {
+        kinds.iter().map(|kind| kind.to_string()).collect()
+    }
+
+    #[test]
+    fn test_declared_handler_kinds() {
+        let data_source: serde_yaml::Value = serde_yaml::from_str(
+            "
+kind: ethereum/contract
+mapping:
+  kind: ethereum/events
+  eventHandlers:
+    - event: Transfer(address,address,uint256)
+      handler: handleTransfer
+  receiptHandlers:
+    - handler: handleReceipt
+  callHandlers: []
+",
+        )
+        .unwrap();
+        assert_eq!(
+            kinds(&["event", "receipt"]),
+            declared_handler_kinds(&data_source)
+        );
+
+        // No mapping - no handlers
+        let data_source: serde_yaml::Value = serde_yaml::from_str("kind: file/ipfs").unwrap();
+        assert!(declared_handler_kinds(&data_source).is_empty());
+    }
+
+    #[test]
+    fn test_validate_handler_kinds() {
+        let supported = HashSet::from(["event", "call", "block"]);
+
+        // Only supported kinds - ok
+        assert!(validate_handler_kinds("ds", &kinds(&["event", "block"]), &supported).is_ok());
+
+        // No handlers - ok
+        assert!(validate_handler_kinds("ds", &BTreeSet::new(), &supported).is_ok());
+
+        // Unsupported kinds - error naming all of them
+        let err = validate_handler_kinds("ds", &kinds(&["receipt", "event", "log"]), &supported)
+            .unwrap_err();
+        assert_eq!(
+            "data source ds uses handler kinds that are not supported on this chain: log, receipt",
+            err.to_string()
+        );
+    }
 }
@@ -371,8 +371,23 @@ pub enum SubgraphManifestValidationError {
     FeatureValidationError(#[from] SubgraphFeatureValidationError),
     #[error("data source {0} is invalid: {1}")]
     DataSourceValidation(String, Error),
+    #[error(
+        "data source {data_source} uses handler kinds that are not supported on this chain: {}",
+        kinds.join(", ")
+    )]
+    UnsupportedHandlerKinds {
+        data_source: String,
+        kinds: Vec<String>,
+    },
     #[error("failed to validate Amp subgraph: {0:#}")]
     Amp(#[source] Error),
+    #[error(
+        "subgraph uses spec version {spec_version}, but this Graph Node requires at least spec version {required}"
+    )]
+    SpecVersionTooLow {
+        spec_version: Version,
+        required: Version,
+    },
 }
 
 #[derive(Error, Debug)]
@@ -550,6 +565,7 @@ impl Graft {
     async fn validate<S: SubgraphStore>(
         &self,
         store: Arc<S>,
+        reorg_threshold: BlockNumber,
     ) -> Result<(), SubgraphManifestValidationError> {
         use SubgraphManifestValidationError::*;
 
@@ -580,13 +596,10 @@ impl Graft {
             // The graft point must be at least `reorg_threshold` blocks
             // behind the subgraph head so that a reorg can not affect the
             // data that we copy for grafting
-            (Some(ptr), true) if self.block + ENV_VARS.reorg_threshold() > ptr.number => {
+            (Some(ptr), true) if self.block + reorg_threshold > ptr.number => {
                 Err(GraftBaseInvalid(format!(
                     "failed to graft onto `{}` at block {} since it's only at block {} which is within the reorg threshold of {} blocks",
-                    self.base,
-                    self.block,
-                    ptr.number,
-                    ENV_VARS.reorg_threshold()
+                    self.base, self.block, ptr.number, reorg_threshold
                 )))
             }
             // If the base deployment is failed *and* the `graft.block` is not
@@ -768,8 +781,12 @@ pub type UnresolvedSubgraphManifest<C> = BaseSubgraphManifest<
 pub type SubgraphManifest<C> =
     BaseSubgraphManifest<C, InputSchema, DataSource<C>, DataSourceTemplate<C>>;
 
-/// Unvalidated SubgraphManifest
-pub struct UnvalidatedSubgraphManifest<C: Blockchain>(SubgraphManifest<C>);
+/// Unvalidated SubgraphManifest, together with the handler kinds that each
+/// data source declares in the raw manifest, keyed by data source name
+pub struct UnvalidatedSubgraphManifest<C: Blockchain>(
+    SubgraphManifest<C>,
+    HashMap<String, BTreeSet<String>>,
+);
 
 impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
     fn validate_subgraph_datasources(
@@ -849,6 +866,17 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
         logger: &Logger,
         max_spec_version: semver::Version,
     ) -> Result<Self, SubgraphManifestResolveError> {
+        let handler_kinds = raw
+            .get("dataSources")
+            .and_then(|data_sources| data_sources.as_sequence())
+            .into_iter()
+            .flatten()
+            .filter_map(|ds| {
+                let name = ds.get("name")?.as_str()?.to_string();
+                Some((name, manifest_validation::declared_handler_kinds(ds)))
+            })
+            .collect();
+
         Ok(Self(
             SubgraphManifest::resolve_from_raw(
                 id,
@@ -859,6 +887,7 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
                 max_spec_version,
             )
             .await?,
+            handler_kinds,
         ))
     }
 
@@ -870,7 +899,10 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
         store: Arc<S>,
         validate_graft_base: bool,
     ) -> Result<SubgraphManifest<C>, Vec<SubgraphManifestValidationError>> {
-        use manifest_validation::{validate_has_data_sources, validate_single_network};
+        use manifest_validation::{
+            validate_handler_kinds, validate_has_data_sources, validate_min_spec_version,
+            validate_single_network,
+        };
 
         let mut errors: Vec<SubgraphManifestValidationError> = vec![];
 
@@ -879,12 +911,31 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
             errors.push(e);
         }
 
+        // Validate the spec version against the minimum this node accepts
+        if let Err(e) = validate_min_spec_version(&self.0.spec_version, &ENV_VARS.min_spec_version)
+        {
+            errors.push(e);
+        }
+
         for ds in &self.0.data_sources {
             errors.extend(ds.validate(&self.0.spec_version).into_iter().map(|e| {
                 SubgraphManifestValidationError::DataSourceValidation(ds.name().to_owned(), e)
             }));
         }
 
+        // Offchain and subgraph data sources have handler kinds that do not
+        // depend on the chain, only onchain data sources need to be checked
+        let supported_handler_kinds = C::supported_handler_kinds();
+        for ds in &self.0.data_sources {
+            if let DataSource::Onchain(_) = ds
+                && let Some(handler_kinds) = self.1.get(ds.name())
+                && let Err(e) =
+                    validate_handler_kinds(ds.name(), handler_kinds, &supported_handler_kinds)
+            {
+                errors.push(e);
+            }
+        }
+
         // For API versions newer than 0.0.5, validate that all mappings uses the same api_version
         if let Err(different_api_versions) = self.0.unified_mapping_api_version() {
             errors.push(different_api_versions.into());
@@ -895,10 +946,14 @@ impl<C: Blockchain> UnvalidatedSubgraphManifest<C> {
         if let Err(e) = validate_single_network(&networks) {
             errors.push(e);
         }
+        let reorg_threshold = match networks.iter().flatten().next() {
+            Some(network) => ENV_VARS.reorg_threshold_for(network),
+            None => ENV_VARS.reorg_threshold(),
+        };
 
         if let Some(graft) = &self.0.graft
             && validate_graft_base
-            && let Err(graft_err) = graft.validate(store).await
+            && let Err(graft_err) = graft.validate(store, reorg_threshold).await
         {
             errors.push(graft_err);
         }
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use crate::components::store::{BlockNumber, StoredDynamicDataSource};
+use crate::data::store::scalar::Bytes;
+
+use super::CausalityRegion;
+
+/// Identifies a dynamic data source by the parts of its stored form that
+/// are fixed when it is created from a template
+type DataSourceKey = (u32, Option<Bytes>, CausalityRegion);
+
+fn key(ds: &StoredDynamicDataSource) -> DataSourceKey {
+    (ds.manifest_idx, ds.param.clone(), ds.causality_region)
+}
+
+/// A dynamic data source that was created a while ago and that has not
+/// matched any triggers recently
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrphanedDataSource {
+    pub data_source: StoredDynamicDataSource,
+    /// The last block in which the data source matched a trigger, or `None`
+    /// if it did not match any trigger since tracking started
+    pub last_match: Option<BlockNumber>,
+}
+
+/// Keeps track of the last block in which each dynamic data source matched
+/// a trigger. Matches are only tracked in memory, starting with the first
+/// block that is observed; a data source is only considered orphaned once
+/// it has been watched for long enough.
+///
+/// This is only used to report data sources that could be removed; it
+/// never removes anything itself.
+#[derive(Debug, Default)]
+pub struct DataSourceActivity {
+    /// The first block for which matches were tracked
+    since: Option<BlockNumber>,
+    /// The block at which orphaned data sources were last reported
+    last_report: Option<BlockNumber>,
+    last_match: BTreeMap<DataSourceKey, BlockNumber>,
+}
+
+impl DataSourceActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that triggers in block `number` have been matched against all
+    /// data sources and that `matched` are the ones that matched
+    pub fn observe_block<'a>(
+        &mut self,
+        number: BlockNumber,
+        matched: impl IntoIterator<Item = &'a StoredDynamicDataSource>,
+    ) {
+        self.since.get_or_insert(number);
+        for ds in matched {
+            if ds.creation_block.is_none() {
+                // Not a dynamic data source
+                continue;
+            }
+            let last = self.last_match.entry(key(ds)).or_insert(number);
+            *last = number.max(*last);
+        }
+    }
+
+    /// Return the dynamic data sources among `data_sources` that were
+    /// created more than `threshold` blocks before `current` and that have
+    /// not matched a trigger in the last `threshold` blocks
+    pub fn orphaned(
+        &self,
+        data_sources: impl IntoIterator<Item = StoredDynamicDataSource>,
+        current: BlockNumber,
+        threshold: BlockNumber,
+    ) -> Vec<OrphanedDataSource> {
+        let cutoff = current.saturating_sub(threshold);
+
+        // Without having watched for `threshold` blocks, we can't tell
+        // whether a data source has been quiet for that long
+        match self.since {
+            Some(since) if since <= cutoff => {}
+            _ => return vec![],
+        }
+
+        data_sources
+            .into_iter()
+            .filter(|ds| matches!(ds.creation_block, Some(created) if created <= cutoff))
+            .filter(|ds| ds.done_at.is_none())
+            .filter_map(|ds| {
+                let last_match = self.last_match.get(&key(&ds)).copied();
+                match last_match {
+                    Some(last_match) if last_match > cutoff => None,
+                    _ => Some(OrphanedDataSource {
+                        data_source: ds,
+                        last_match,
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// Return `true` if at least `threshold` blocks have passed since
+    /// orphaned data sources were last reported, or since tracking started
+    /// if they have never been reported, and remember `current` as the
+    /// block of the last report in that case
+    pub fn report_due(&mut self, current: BlockNumber, threshold: BlockNumber) -> bool {
+        let Some(last) = self.last_report.or(self.since) else {
+            return false;
+        };
+        if current.saturating_sub(last) < threshold {
+            return false;
+        }
+        self.last_report = Some(current);
+        true
+    }
+
+    /// Forget matches in blocks at or after `reverted_block`
+    pub fn revert(&mut self, reverted_block: BlockNumber) {
+        self.last_match.retain(|_, last| *last < reverted_block);
+        if matches!(self.since, Some(since) if since >= reverted_block) {
+            self.since = None;
+        }
+        if matches!(self.last_report, Some(last) if last >= reverted_block) {
+            self.last_report = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dds(idx: u32, address: u8, creation_block: BlockNumber) -> StoredDynamicDataSource {
+        StoredDynamicDataSource {
+            manifest_idx: idx,
+            param: Some(Bytes::from(vec![address; 20])),
+            context: None,
+            creation_block: Some(creation_block),
+            done_at: None,
+            causality_region: CausalityRegion::ONCHAIN,
+        }
+    }
+
+    fn orphaned_addresses(
+        activity: &DataSourceActivity,
+        data_sources: &[StoredDynamicDataSource],
+        current: BlockNumber,
+    ) -> Vec<u8> {
+        activity
+            .orphaned(data_sources.iter().cloned(), current, 100)
+            .into_iter()
+            .map(|orphan| orphan.data_source.param.unwrap().as_slice()[0])
+            .collect()
+    }
+
+    #[test]
+    fn reports_old_data_sources_without_recent_matches() {
+        let active = dds(1, 1, 10);
+        let quiet = dds(1, 2, 10);
+        let never = dds(1, 3, 10);
+        let young = dds(1, 4, 180);
+        let static_ds = StoredDynamicDataSource {
+            creation_block: None,
+            ..dds(0, 5, 0)
+        };
+        let data_sources = vec![
+            active.clone(),
+            quiet.clone(),
+            never,
+            young,
+            static_ds.clone(),
+        ];
+
+        let mut activity = DataSourceActivity::new();
+        activity.observe_block(20, [&active, &quiet, &static_ds]);
+        activity.observe_block(190, [&active]);
+
+        assert_eq!(
+            vec![2, 3],
+            orphaned_addresses(&activity, &data_sources, 200)
+        );
+
+        let orphans = activity.orphaned(data_sources, 200, 100);
+        assert_eq!(Some(20), orphans[0].last_match);
+        assert_eq!(None, orphans[1].last_match);
+    }
+
+    #[test]
+    fn needs_to_watch_for_the_threshold_before_reporting() {
+        let quiet = dds(1, 1, 10);
+        let data_sources = vec![quiet];
+
+        let mut activity = DataSourceActivity::new();
+        assert!(orphaned_addresses(&activity, &data_sources, 500).is_empty());
+
+        activity.observe_block(450, []);
+        assert!(orphaned_addresses(&activity, &data_sources, 500).is_empty());
+        assert_eq!(vec![1], orphaned_addresses(&activity, &data_sources, 550));
+    }
+
+    #[test]
+    fn reverted_matches_are_forgotten() {
+        let ds = dds(1, 1, 10);
+        let data_sources = vec![ds.clone()];
+
+        let mut activity = DataSourceActivity::new();
+        activity.observe_block(20, []);
+        activity.observe_block(150, [&ds]);
+        assert!(orphaned_addresses(&activity, &data_sources, 200).is_empty());
+
+        activity.revert(150);
+        assert_eq!(vec![1], orphaned_addresses(&activity, &data_sources, 200));
+    }
+
+    #[test]
+    fn reports_are_due_once_per_threshold() {
+        let mut activity = DataSourceActivity::new();
+        assert!(!activity.report_due(500, 100));
+
+        // Blocks are not contiguous, so the report must not depend on
+        // hitting a multiple of the threshold
+        activity.observe_block(13, []);
+        assert!(!activity.report_due(57, 100));
+        assert!(activity.report_due(117, 100));
+        assert!(!activity.report_due(118, 100));
+        assert!(!activity.report_due(216, 100));
+        assert!(activity.report_due(251, 100));
+
+        activity.revert(240);
+        assert!(activity.report_due(240, 100));
+    }
+}
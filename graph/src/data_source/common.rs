@@ -3,6 +3,7 @@ use crate::abi::DynSolValueExt;
 use crate::abi::FunctionExt;
 use crate::blockchain::block_stream::EntitySourceOperation;
 use crate::data::subgraph::SPEC_VERSION_1_4_0;
+use crate::data::subgraph::SPEC_VERSION_1_6_0;
 use crate::prelude::{BlockPtr, Value};
 use crate::{
     components::link_resolver::{LinkResolver, LinkResolverContext},
@@ -448,12 +449,15 @@ impl UnresolvedMappingABI {
 /// ```yaml
 /// calls:
 ///   - myCall1: Contract[address].function(arg1, arg2, ...)
+///   - myCall2:
+///       call: Contract[address].function(arg1, arg2, ...)
+///       gas: 1000000
 ///   - ..
 /// ```
 ///
-/// The `address` and `arg` fields can be either `event.address` or
-/// `event.params.<name>`. Each entry under `calls` gets turned into a
-/// `CallDcl`
+/// The `address` and `arg` fields can be either `event.address`,
+/// `event.params.<name>`, or `event.params.<position>`. The optional `gas` limits how much gas the call
+/// can use. Each entry under `calls` gets turned into a `CallDcl`
 #[derive(Clone, CheapClone, Debug, Default, Hash, Eq, PartialEq)]
 #[non_exhaustive]
 pub struct CallDecls {
@@ -469,6 +473,8 @@ pub struct CallDecl {
     pub label: String,
     /// The call expression
     pub expr: CallExpr,
+    /// The most gas the call may use, if the manifest limits it
+    pub gas: Option<u32>,
 }
 
 impl CallDecl {
@@ -516,6 +522,28 @@ impl CallDecl {
 
                     Address::from(address.into_array())
                 }
+                EthereumArg::ParamIndex(index) => {
+                    let value = &params
+                        .get(*index)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "In declarative call '{}': the event has no param at position {}",
+                                self.label,
+                                index
+                            )
+                        })?
+                        .value;
+
+                    let address = value.as_address().ok_or_else(|| {
+                        anyhow!(
+                            "In declarative call '{}': param at position {} is not an address",
+                            self.label,
+                            index
+                        )
+                    })?;
+
+                    Address::from(address.into_array())
+                }
                 EthereumArg::StructField(param_name, field_accesses) => {
                     let param = params
                         .iter()
@@ -575,6 +603,10 @@ impl CallDecl {
                             .clone();
                         Ok(value)
                     }
+                    EthereumArg::ParamIndex(index) => params
+                        .get(*index)
+                        .map(|param| param.value.clone())
+                        .ok_or_else(|| anyhow!("In declarative call '{}': the event has no param at position {}", self.label, index)),
                     EthereumArg::StructField(param_name, field_accesses) => {
                         let param = params
                             .iter()
@@ -871,7 +903,37 @@ impl CallDecl {
 #[derive(Clone, CheapClone, Debug, Default, Eq, PartialEq)]
 #[non_exhaustive]
 pub struct UnresolvedCallDecls {
-    pub raw_decls: Arc<std::collections::HashMap<String, String>>,
+    pub raw_decls: Arc<std::collections::HashMap<String, RawCallDecl>>,
+}
+
+/// A call declaration as it is written in the manifest, either just the
+/// call expression or the expression together with a gas limit
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawCallDecl {
+    pub expr: String,
+    pub gas: Option<u32>,
+}
+
+impl<'de> de::Deserialize<'de> for RawCallDecl {
+    fn deserialize<D>(deserializer: D) -> Result<RawCallDecl, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Expr(String),
+            WithGas { call: String, gas: u32 },
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Expr(expr) => RawCallDecl { expr, gas: None },
+            Raw::WithGas { call, gas } => RawCallDecl {
+                expr: call,
+                gas: Some(gas),
+            },
+        })
+    }
 }
 
 impl UnresolvedCallDecls {
@@ -885,11 +947,12 @@ impl UnresolvedCallDecls {
         let decls: Result<Vec<CallDecl>, anyhow::Error> = self
             .raw_decls
             .iter()
-            .map(|(label, expr)| {
-                CallExpr::parse(expr, abi_json, event_signature, spec_version)
+            .map(|(label, raw)| {
+                CallExpr::parse(&raw.expr, abi_json, event_signature, spec_version)
                     .map(|expr| CallDecl {
                         label: label.clone(),
                         expr,
+                        gas: raw.gas,
                     })
                     .with_context(|| format!("Error in declared call '{}':", label))
             })
@@ -911,7 +974,7 @@ impl<'de> de::Deserialize<'de> for UnresolvedCallDecls {
     where
         D: de::Deserializer<'de>,
     {
-        let raw_decls: std::collections::HashMap<String, String> =
+        let raw_decls: std::collections::HashMap<String, RawCallDecl> =
             de::Deserialize::deserialize(deserializer)?;
         Ok(UnresolvedCallDecls {
             raw_decls: Arc::new(raw_decls),
@@ -952,6 +1015,39 @@ impl CallExpr {
         Ok(())
     }
 
+    /// Check that every event parameter this expression refers to, by name
+    /// or by position, exists in `event`
+    pub fn validate_event_params(&self, event: &abi::Event) -> Result<(), anyhow::Error> {
+        for arg in std::iter::once(&self.address).chain(&self.args) {
+            let CallArg::Ethereum(arg) = arg else {
+                continue;
+            };
+            match arg {
+                EthereumArg::Address => {}
+                EthereumArg::Param(name) | EthereumArg::StructField(name, _) => {
+                    if !event.inputs.iter().any(|input| input.name == name.as_str()) {
+                        return Err(anyhow!(
+                            "event `{}` has no param named `{}`",
+                            event.name,
+                            name
+                        ));
+                    }
+                }
+                EthereumArg::ParamIndex(index) => {
+                    if *index >= event.inputs.len() {
+                        return Err(anyhow!(
+                            "event `{}` has {} params, there is no param at position {}",
+                            event.name,
+                            event.inputs.len(),
+                            index
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Parse a call expression with ABI context to resolve field names at parse time
     pub fn parse(
         s: &str,
@@ -1145,6 +1241,10 @@ impl StructFieldInfo {
 pub enum EthereumArg {
     Address,
     Param(Word),
+    /// The event parameter at this position, counting both indexed and
+    /// non-indexed parameters. Unnamed parameters can only be referenced
+    /// this way
+    ParamIndex(usize),
     /// Struct field access with numeric indices (field names resolved at parse time)
     StructField(Word, Vec<usize>),
 }
@@ -1209,8 +1309,19 @@ impl CallArg {
                 // Check if there are any additional parts for struct field access
                 let remaining_parts: Vec<&str> = parts.collect();
                 if remaining_parts.is_empty() {
-                    // Simple parameter access: event.params.foo
-                    Ok(CallArg::Ethereum(EthereumArg::Param(Word::from(param))))
+                    // Simple parameter access: event.params.foo, or
+                    // event.params.0 by position since names can not start
+                    // with a digit
+                    match param.parse::<usize>() {
+                        Ok(_) if spec_version < &SPEC_VERSION_1_6_0 => Err(anyhow!(
+                            "Positional param access 'event.params.{}' in declarative calls is only supported for specVersion >= 1.6.0, current version is {}. Event: '{}'",
+                            param,
+                            spec_version,
+                            event_signature.unwrap_or("unknown")
+                        )),
+                        Ok(index) => Ok(CallArg::Ethereum(EthereumArg::ParamIndex(index))),
+                        Err(_) => Ok(CallArg::Ethereum(EthereumArg::Param(Word::from(param)))),
+                    }
                 } else {
                     // Struct field access: event.params.foo.bar.0.baz...
                     // Validate spec version before allowing any struct field access
@@ -1293,6 +1404,7 @@ pub struct DeclaredCall {
     address: Address,
     function: abi::Function,
     args: Vec<abi::DynSolValue>,
+    gas_limit: Option<u32>,
 }
 
 impl DeclaredCall {
@@ -1363,6 +1475,7 @@ impl DeclaredCall {
                 address,
                 function: function.clone(),
                 args,
+                gas_limit: decl.gas,
             });
         }
         Ok(calls)
@@ -1377,6 +1490,7 @@ impl DeclaredCall {
                 function: self.function,
                 args: self.args,
                 gas,
+                gas_limit: self.gas_limit,
             },
             self.label,
         )
@@ -1389,7 +1503,23 @@ pub struct ContractCall {
     pub block_ptr: BlockPtr,
     pub function: abi::Function,
     pub args: Vec<abi::DynSolValue>,
+    /// The gas that is passed to `eth_call` so that providers do not use
+    /// their own, possibly lower, default
     pub gas: Option<u32>,
+    /// A limit on the gas that the subgraph allows the call to use. A call
+    /// that runs out of this gas fails with an out-of-gas error instead of
+    /// being treated as a revert
+    pub gas_limit: Option<u32>,
+}
+
+impl ContractCall {
+    /// The gas to send with the `eth_call`
+    pub fn effective_gas(&self) -> Option<u32> {
+        match (self.gas, self.gas_limit) {
+            (Some(gas), Some(limit)) => Some(gas.min(limit)),
+            (gas, limit) => gas.or(limit),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1397,6 +1527,7 @@ mod tests {
     use alloy::primitives::B256;
 
     use crate::data::subgraph::SPEC_VERSION_1_3_0;
+    use crate::data::subgraph::SPEC_VERSION_1_5_0;
 
     use super::*;
 
@@ -1655,6 +1786,49 @@ mod tests {
         assert_eq!(expr.args[0], hex_address);
     }
 
+    #[test]
+    fn test_call_decls_with_gas_limit() {
+        let yaml = r#"
+plain: ERC20[event.address].balanceOf(event.params.token)
+limited:
+  call: ERC20[event.address].totalSupply()
+  gas: 100000
+"#;
+        let decls: UnresolvedCallDecls = serde_yaml::from_str(yaml).unwrap();
+
+        let plain = &decls.raw_decls["plain"];
+        assert_eq!(
+            plain.expr,
+            "ERC20[event.address].balanceOf(event.params.token)"
+        );
+        assert_eq!(plain.gas, None);
+
+        let limited = &decls.raw_decls["limited"];
+        assert_eq!(limited.expr, "ERC20[event.address].totalSupply()");
+        assert_eq!(limited.gas, Some(100_000));
+
+        // The gas limit only lowers the gas that is sent with the call
+        let call = |gas, gas_limit| ContractCall {
+            contract_name: "ERC20".to_string(),
+            address: Address::ZERO,
+            block_ptr: BlockPtr::new(B256::ZERO.into(), 1),
+            function: abi::Function::parse("totalSupply()").unwrap(),
+            args: vec![],
+            gas,
+            gas_limit,
+        };
+        assert_eq!(
+            call(Some(50_000_000), Some(100_000)).effective_gas(),
+            Some(100_000)
+        );
+        assert_eq!(
+            call(Some(100_000), Some(200_000)).effective_gas(),
+            Some(100_000)
+        );
+        assert_eq!(call(None, Some(100_000)).effective_gas(), Some(100_000));
+        assert_eq!(call(Some(100_000), None).effective_gas(), Some(100_000));
+    }
+
     #[test]
     fn test_invalid_call_args() {
         let parser = ArgParser::new();
@@ -1699,6 +1873,133 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_positional_event_params() {
+        use crate::abi::{DynSolParam, DynSolValue};
+        use alloy::primitives::U256;
+        use alloy::rpc::types::Log;
+
+        let parser = ExprParser::new();
+        let expr = "ERC20[event.params.0].balanceOf(event.params.1)";
+
+        let error_msg = format!(
+            "{:#}",
+            parser
+                .parse_with_version(expr, &SPEC_VERSION_1_5_0)
+                .unwrap_err()
+        );
+        assert!(error_msg.contains("only supported for specVersion >= 1.6.0"));
+
+        let call_decl = CallDecl {
+            label: "balance".to_string(),
+            expr: parser
+                .parse_with_version(expr, &SPEC_VERSION_1_6_0)
+                .unwrap(),
+            gas: None,
+        };
+        assert_eq!(
+            call_decl.expr.address,
+            CallArg::Ethereum(EthereumArg::ParamIndex(0))
+        );
+        assert_eq!(
+            call_decl.expr.args,
+            vec![CallArg::Ethereum(EthereumArg::ParamIndex(1))]
+        );
+
+        let log = Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: alloy::primitives::LogData::new_unchecked(vec![], vec![].into()),
+            },
+            block_hash: Some(B256::ZERO),
+            block_number: Some(1),
+            block_timestamp: None,
+            transaction_hash: Some(B256::ZERO),
+            transaction_index: Some(0),
+            log_index: Some(0),
+            removed: false,
+        };
+        let token = Address::from([1u8; 20]);
+        let holder = Address::from([2u8; 20]);
+        // Event params can be unnamed, only their position identifies them
+        let params = vec![
+            DynSolParam {
+                name: String::new(),
+                value: DynSolValue::Address(token),
+            },
+            DynSolParam {
+                name: String::new(),
+                value: DynSolValue::Address(holder),
+            },
+        ];
+
+        assert_eq!(call_decl.address_for_log(&log, &params).unwrap(), token);
+        assert_eq!(
+            call_decl.args_for_log(&log, &params).unwrap(),
+            vec![DynSolValue::Address(holder)]
+        );
+
+        let err = call_decl
+            .args_for_log(&log, &params[..1])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no param at position 1"), "{}", err);
+
+        let params = vec![DynSolParam {
+            name: String::new(),
+            value: DynSolValue::Uint(U256::from(1u8), 256),
+        }];
+        let err = call_decl
+            .address_for_log(&log, &params)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("is not an address"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_event_params() {
+        let event: abi::Event = serde_json::from_str(
+            r#"{
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "", "type": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "indexed": false }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let parser = ExprParser::new();
+        let parse = |expr: &str| {
+            parser
+                .parse_with_version(expr, &SPEC_VERSION_1_6_0)
+                .unwrap()
+        };
+
+        for valid in [
+            "ERC20[event.address].balanceOf(event.params.from)",
+            "ERC20[event.params.1].balanceOf(event.params.from)",
+            "ERC20[event.params.from].allowance(event.params.1, event.params.2)",
+        ] {
+            let expr = parse(valid);
+            assert!(
+                expr.validate_event_params(&event).is_ok(),
+                "{} should be valid",
+                valid
+            );
+        }
+
+        let expr = parse("ERC20[event.address].balanceOf(event.params.to)");
+        let err = expr.validate_event_params(&event).unwrap_err().to_string();
+        assert!(err.contains("no param named `to`"), "{}", err);
+
+        let expr = parse("ERC20[event.params.3].balanceOf(event.params.from)");
+        let err = expr.validate_event_params(&event).unwrap_err().to_string();
+        assert!(err.contains("no param at position 3"), "{}", err);
+    }
+
     #[test]
     fn test_struct_field_access_functions() {
         use crate::abi::DynSolValue;
@@ -1761,6 +2062,7 @@ mod tests {
         let call_decl = CallDecl {
             label: "myTokenCall".to_string(),
             expr: parser.ok("ERC20[event.params.asset.1].name()"),
+            gas: None,
         };
 
         // Test scenario 1: Unknown parameter
@@ -1825,6 +2127,7 @@ mod tests {
         let call_decl_with_args = CallDecl {
             label: "transferCall".to_string(),
             expr,
+            gas: None,
         };
 
         // Create a structure where base has only 2 fields instead of 3
@@ -1,3 +1,4 @@
+pub mod activity;
 pub mod causality_region;
 pub mod common;
 pub mod offchain;
@@ -13,8 +14,8 @@ mod tests;
 
 use crate::{
     blockchain::{
-        Block, BlockPtr, BlockTime, Blockchain, DataSource as _, DataSourceTemplate as _,
-        MappingTriggerTrait, TriggerData as _, UnresolvedDataSource as _,
+        Block, BlockPtr, BlockTime, Blockchain, BlockchainKind, DataSource as _,
+        DataSourceTemplate as _, MappingTriggerTrait, TriggerData as _, UnresolvedDataSource as _,
         UnresolvedDataSourceTemplate as _,
     },
     components::{
@@ -196,7 +197,7 @@ impl<C: Blockchain> DataSource<C> {
         match self {
             Self::Onchain(ds) => ds.end_block(),
             Self::Offchain(_) => None,
-            Self::Subgraph(_) => None,
+            Self::Subgraph(ds) => ds.source.end_block,
             Self::Amp(ds) => Some(ds.source.end_block as i32),
         }
     }
@@ -708,7 +709,10 @@ impl<'de, C: Blockchain> Deserialize<'de> for UnresolvedDataSource<C> {
             amp::manifest::data_source::RawDataSource::deserialize(map.into_deserializer())
                 .map(UnresolvedDataSource::Amp)
                 .map_err(serde::de::Error::custom)
-        } else if (C::KIND.to_string() == kind) || C::ALIASES.contains(&kind) {
+        } else if (C::KIND.to_string() == kind)
+            || C::ALIASES.contains(&kind)
+            || BlockchainKind::from_alias(kind) == Some(C::KIND)
+        {
             C::UnresolvedDataSource::deserialize(map.into_deserializer())
                 .map_err(serde::de::Error::custom)
                 .map(UnresolvedDataSource::Onchain)
@@ -742,7 +746,10 @@ impl<'de, C: Blockchain> Deserialize<'de> for UnresolvedDataSourceTemplate<C> {
             subgraph::UnresolvedDataSourceTemplate::deserialize(map.into_deserializer())
                 .map_err(serde::de::Error::custom)
                 .map(UnresolvedDataSourceTemplate::Subgraph)
-        } else if (C::KIND.to_string() == kind) || C::ALIASES.contains(&kind) {
+        } else if (C::KIND.to_string() == kind)
+            || C::ALIASES.contains(&kind)
+            || BlockchainKind::from_alias(kind) == Some(C::KIND)
+        {
             C::UnresolvedDataSourceTemplate::deserialize(map.into_deserializer())
                 .map_err(serde::de::Error::custom)
                 .map(UnresolvedDataSourceTemplate::Onchain)
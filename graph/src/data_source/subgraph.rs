@@ -137,11 +137,33 @@ impl DataSource {
 
 pub type Base64 = Word;
 
-#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 pub struct Source {
     pub address: DeploymentHash,
     #[serde(default)]
     pub start_block: BlockNumber,
+    #[serde(default)]
+    pub end_block: Option<BlockNumber>,
+    /// Whether entity deletions in the source subgraph trigger the
+    /// handlers. Deletions always did before this could be turned off, and
+    /// they therefore do unless the manifest says otherwise
+    #[serde(default = "include_deletions_default")]
+    pub include_deletions: bool,
+}
+
+fn include_deletions_default() -> bool {
+    true
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self {
+            address: DeploymentHash::default(),
+            start_block: 0,
+            end_block: None,
+            include_deletions: include_deletions_default(),
+        }
+    }
 }
 
 impl Source {
@@ -169,7 +191,8 @@ pub struct Mapping {
 
 impl Mapping {
     pub fn requires_archive(&self) -> anyhow::Result<bool> {
-        calls_host_fn(&self.runtime, "ethereum.call")
+        Ok(calls_host_fn(&self.runtime, "ethereum.call")?
+            || calls_host_fn(&self.runtime, "ethereum.callAtBlock")?)
     }
 }
 
@@ -225,12 +248,27 @@ pub struct UnresolvedDataSource {
     pub context: Option<DataSourceContext>,
 }
 
-#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UnresolvedSource {
     address: DeploymentHash,
     #[serde(default)]
     start_block: BlockNumber,
+    #[serde(default)]
+    end_block: Option<BlockNumber>,
+    #[serde(default = "include_deletions_default")]
+    include_deletions: bool,
+}
+
+impl Default for UnresolvedSource {
+    fn default() -> Self {
+        Self {
+            address: DeploymentHash::default(),
+            start_block: 0,
+            end_block: None,
+            include_deletions: include_deletions_default(),
+        }
+    }
 }
 
 impl UnresolvedSource {
@@ -241,6 +279,10 @@ impl UnresolvedSource {
     pub fn start_block(&self) -> BlockNumber {
         self.start_block
     }
+
+    pub fn include_deletions(&self) -> bool {
+        self.include_deletions
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize)]
@@ -475,6 +517,8 @@ impl UnresolvedDataSource {
         let source = Source {
             address: self.source.address,
             start_block: self.source.start_block,
+            end_block: self.source.end_block,
+            include_deletions: self.source.include_deletions,
         };
 
         Ok(DataSource {
@@ -59,6 +59,7 @@ fn data_source_helpers() {
     );
 
     let onchain = DataSource::<MockBlockchain>::Onchain(MockDataSource {
+        name: "mock".into(),
         api_version: Version::new(1, 0, 0),
         kind: "mock/kind".into(),
         network: Some("mock_network".into()),
@@ -88,3 +89,17 @@ fn new_datasource() -> offchain::DataSource {
         CausalityRegion::ONCHAIN.next(),
     )
 }
+
+#[test]
+fn subgraph_source_includes_deletions_by_default() {
+    const ADDRESS: &str = "address: QmWmyoMoctfbAaiEs2G46gpeUmhqFRDW6KWo64y5r581Vz";
+
+    let source: subgraph::UnresolvedSource = serde_yaml::from_str(ADDRESS).unwrap();
+    assert!(source.include_deletions());
+    assert!(subgraph::UnresolvedSource::default().include_deletions());
+    assert!(subgraph::Source::default().include_deletions);
+
+    let source: subgraph::UnresolvedSource =
+        serde_yaml::from_str(&format!("{ADDRESS}\nincludeDeletions: false")).unwrap();
+    assert!(!source.include_deletions());
+}
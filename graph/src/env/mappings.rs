@@ -40,6 +40,14 @@ pub struct EnvVarsMapping {
     /// Set by the environment variable `GRAPH_IPFS_TIMEOUT` (expressed in
     /// seconds). The default value is 60s.
     pub ipfs_timeout: Duration,
+    /// How long IPFS clients wait for a connection to an IPFS server to be
+    /// established. Unlike `ipfs_timeout`, this does not limit how long it
+    /// takes to download a file once the server is connected, so that dead
+    /// servers are detected quickly while large files can still be fetched.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_CONNECT_TIMEOUT`
+    /// (expressed in seconds). The default value is 5s.
+    pub ipfs_connect_timeout: Duration,
     /// Sets the `ipfs.map` file size limit.
     ///
     /// Set by the environment variable `GRAPH_MAX_IPFS_MAP_FILE_SIZE_LIMIT`
@@ -83,6 +91,46 @@ pub struct EnvVarsMapping {
     /// `GRAPH_FDS_MAX_BACKOFF` in seconds, defaults to 600.
     pub fds_max_backoff: Duration,
 
+    /// Fail ranged IPFS reads when the server ignores the requested range
+    /// and sends the whole content, instead of dropping the bytes outside of
+    /// the range.
+    ///
+    /// Set by the flag `GRAPH_IPFS_STRICT_RANGE_REQUESTS`. Off by default.
+    pub ipfs_strict_range_requests: bool,
+
+    /// How long resolved IPFS server addresses are cached. Unset by default,
+    /// which resolves the server address for every new connection.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_DNS_CACHE_TTL` (expressed in
+    /// seconds).
+    pub ipfs_dns_cache_ttl: Option<Duration>,
+
+    /// Store files with identical content only once in the in-memory IPFS
+    /// cache, no matter how many paths refer to them.
+    ///
+    /// Set by the flag `GRAPH_IPFS_CACHE_DEDUPLICATE`. Off by default.
+    pub ipfs_cache_deduplicate: bool,
+
+    /// Maximum number of IPFS request retries that can happen in a burst,
+    /// shared by all IPFS requests. A value of 0 means that retries are only
+    /// limited by `ipfs_max_attempts` for each request.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_RETRY_BUDGET`. Defaults to 0.
+    pub ipfs_retry_budget: usize,
+    /// How many retries per second are added back to the retry budget.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_RETRY_BUDGET_REFILL_RATE`.
+    /// Defaults to 10.
+    pub ipfs_retry_budget_refill_rate: u32,
+
+    /// IPFS servers that are used for specific deployments instead of the
+    /// servers passed with `--ipfs`, as pairs of a deployment hash and the
+    /// addresses of its servers.
+    ///
+    /// Set by the environment variable `GRAPH_IPFS_DEPLOYMENT_SERVERS` in the
+    /// form `<hash>=<address>[,<address>...][;<hash>=...]`. Empty by default.
+    pub ipfs_deployment_servers: Vec<(String, Vec<String>)>,
+
     /// Cranelift optimization level for WASM compilation.
     ///
     /// Set by the environment variable `GRAPH_WASM_OPT_LEVEL`. Valid values
@@ -143,6 +191,13 @@ impl TryFrom<InnerMappingHandlers> for EnvVarsMapping {
             .map(validate_ipfs_cache_location)
             .transpose()?;
 
+        let ipfs_deployment_servers = x
+            .ipfs_deployment_servers
+            .as_deref()
+            .map(parse_ipfs_deployment_servers)
+            .transpose()?
+            .unwrap_or_default();
+
         let vars = Self {
             entity_cache_dead_weight: x.entity_cache_dead_weight.0,
             entity_cache_size: x.entity_cache_size_in_kb * 1000,
@@ -154,6 +209,7 @@ impl TryFrom<InnerMappingHandlers> for EnvVarsMapping {
             max_ipfs_cache_file_size: x.max_ipfs_cache_file_size.0,
             max_ipfs_cache_size: x.max_ipfs_cache_size,
             ipfs_timeout: Duration::from_secs(x.ipfs_timeout_in_secs),
+            ipfs_connect_timeout: Duration::from_secs(x.ipfs_connect_timeout_in_secs),
             max_ipfs_map_file_size: x.max_ipfs_map_file_size.0,
             max_ipfs_file_bytes: x.max_ipfs_file_bytes.0,
             ipfs_request_limit: x.ipfs_request_limit,
@@ -163,6 +219,12 @@ impl TryFrom<InnerMappingHandlers> for EnvVarsMapping {
             disable_declared_calls: x.disable_declared_calls.0,
             store_errors_are_nondeterministic: x.store_errors_are_nondeterministic.0,
             fds_max_backoff: Duration::from_secs(x.fds_max_backoff),
+            ipfs_strict_range_requests: x.ipfs_strict_range_requests.0,
+            ipfs_dns_cache_ttl: x.ipfs_dns_cache_ttl_in_secs.map(Duration::from_secs),
+            ipfs_cache_deduplicate: x.ipfs_cache_deduplicate.0,
+            ipfs_retry_budget: x.ipfs_retry_budget,
+            ipfs_retry_budget_refill_rate: x.ipfs_retry_budget_refill_rate,
+            ipfs_deployment_servers,
             wasm_opt_level: x.wasm_opt_level,
         };
         Ok(vars)
@@ -189,6 +251,8 @@ pub struct InnerMappingHandlers {
     max_ipfs_cache_size: u64,
     #[envconfig(from = "GRAPH_IPFS_TIMEOUT", default = "60")]
     ipfs_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_IPFS_CONNECT_TIMEOUT", default = "5")]
+    ipfs_connect_timeout_in_secs: u64,
     #[envconfig(from = "GRAPH_MAX_IPFS_MAP_FILE_SIZE", default = "")]
     max_ipfs_map_file_size: WithDefaultUsize<usize, { 256 * 1024 * 1024 }>,
     #[envconfig(from = "GRAPH_MAX_IPFS_FILE_BYTES", default = "")]
@@ -207,10 +271,46 @@ pub struct InnerMappingHandlers {
     store_errors_are_nondeterministic: EnvVarBoolean,
     #[envconfig(from = "GRAPH_FDS_MAX_BACKOFF", default = "600")]
     fds_max_backoff: u64,
+    #[envconfig(from = "GRAPH_IPFS_STRICT_RANGE_REQUESTS", default = "false")]
+    ipfs_strict_range_requests: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_IPFS_DNS_CACHE_TTL")]
+    ipfs_dns_cache_ttl_in_secs: Option<u64>,
+    #[envconfig(from = "GRAPH_IPFS_CACHE_DEDUPLICATE", default = "false")]
+    ipfs_cache_deduplicate: EnvVarBoolean,
+    #[envconfig(from = "GRAPH_IPFS_RETRY_BUDGET", default = "0")]
+    ipfs_retry_budget: usize,
+    #[envconfig(from = "GRAPH_IPFS_RETRY_BUDGET_REFILL_RATE", default = "10")]
+    ipfs_retry_budget_refill_rate: u32,
+    #[envconfig(from = "GRAPH_IPFS_DEPLOYMENT_SERVERS")]
+    ipfs_deployment_servers: Option<String>,
     #[envconfig(from = "GRAPH_WASM_OPT_LEVEL", default = "speed")]
     wasm_opt_level: WasmOptLevel,
 }
 
+fn parse_ipfs_deployment_servers(s: &str) -> Result<Vec<(String, Vec<String>)>, anyhow::Error> {
+    s.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (hash, addresses) = entry.split_once('=').ok_or_else(|| {
+                anyhow!("GRAPH_IPFS_DEPLOYMENT_SERVERS entry `{entry}` must have the form <hash>=<addresses>")
+            })?;
+            let addresses: Vec<_> = addresses
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(str::to_string)
+                .collect();
+            if addresses.is_empty() {
+                return Err(anyhow!(
+                    "GRAPH_IPFS_DEPLOYMENT_SERVERS entry `{entry}` has no server addresses"
+                ));
+            }
+            Ok((hash.trim().to_string(), addresses))
+        })
+        .collect()
+}
+
 fn validate_ipfs_cache_location(path: PathBuf) -> Result<PathBuf, anyhow::Error> {
     if path.starts_with("redis://") {
         // We validate this later when we set up the Redis client
@@ -1,6 +1,7 @@
 mod amp;
 mod graphql;
 mod mappings;
+mod network;
 mod store;
 
 use std::{collections::HashSet, env::VarError, fmt, str::FromStr, sync::Arc, time::Duration};
@@ -16,10 +17,13 @@ pub use self::mappings::WasmOptLevel;
 use self::store::*;
 use crate::{
     components::{store::BlockNumber, subgraph::SubgraphVersionSwitchingMode},
+    data::subgraph::MIN_SPEC_VERSION,
     runtime::gas::CONST_MAX_GAS_PER_HANDLER,
 };
 
 pub use self::amp::AmpEnv;
+use self::network::NetworkOverrides;
+pub use self::network::network_suffix;
 
 #[cfg(debug_assertions)]
 use std::sync::Mutex;
@@ -32,6 +36,7 @@ lazy_static! {
     pub static ref TEST_WITH_NO_REORG: Mutex<bool> = Mutex::new(false);
     pub static ref TEST_SQL_QUERIES_ENABLED: Mutex<bool> = Mutex::new(false);
     pub static ref TEST_STORE_CALL_CACHE_DISABLED: Mutex<bool> = Mutex::new(false);
+    pub static ref TEST_FIREHOSE_CURSOR_HISTORY: Mutex<bool> = Mutex::new(false);
 }
 
 /// Panics if:
@@ -88,6 +93,12 @@ pub struct EnvVars {
     pub allow_non_deterministic_fulltext_search: bool,
     /// Set by the environment variable `GRAPH_MAX_SPEC_VERSION`.
     pub max_spec_version: Version,
+    /// The lowest spec version that new deployments may use. It can not be
+    /// lower than the lowest spec version graph-node supports.
+    ///
+    /// Set by the environment variable `GRAPH_MIN_SPEC_VERSION`. The
+    /// default value is `MIN_SPEC_VERSION`.
+    pub min_spec_version: Version,
     /// Set by the environment variable `GRAPH_LOAD_WINDOW_SIZE` (expressed in
     /// seconds). The default value is 300 seconds.
     pub load_window_size: Duration,
@@ -282,11 +293,56 @@ pub struct EnvVars {
     /// Set by the environment variable `GRAPH_FIREHOSE_BLOCK_BATCH_SIZE`.
     /// The default value is 10.
     pub firehose_block_batch_size: usize,
+    /// How many firehose streams over disjoint block ranges a block stream
+    /// opens in parallel while it backfills final blocks. A value of 1
+    /// disables parallel backfilling.
+    ///
+    /// Set by the environment variable `GRAPH_FIREHOSE_BACKFILL_PARALLELISM`.
+    /// The default value is 1.
+    pub firehose_backfill_parallelism: usize,
+    /// The number of blocks each of the parallel firehose streams requests
+    /// during a backfill.
+    ///
+    /// Set by the environment variable `GRAPH_FIREHOSE_BACKFILL_SEGMENT_SIZE`.
+    /// The default value is 1000.
+    pub firehose_backfill_segment_size: usize,
+    /// Record how many triggers were tested against and matched by each
+    /// data source for every `n`-th block. A value of 0 turns this off.
+    ///
+    /// Set by the environment variable `GRAPH_TRIGGER_MATCH_STATS_SAMPLE_RATE`.
+    /// The default value is 0.
+    pub trigger_match_stats_sample_rate: u32,
+    /// How many threads, shared by all subgraphs, decode a trigger for the
+    /// data sources of a subgraph. A value of 1 decodes for one data source
+    /// after the other on the subgraph's own thread.
+    ///
+    /// Set by the environment variable `GRAPH_TRIGGER_DECODE_WORKERS`.
+    /// The default value is 1.
+    pub trigger_decode_workers: usize,
+    /// Report dynamic data sources that were created more than this many
+    /// blocks ago and that have not matched any triggers in that many
+    /// blocks. The report is logged every time the deployment advances by
+    /// that many blocks. A value of 0 turns this off.
+    ///
+    /// Set by the environment variable `GRAPH_ORPHANED_DATA_SOURCE_THRESHOLD`.
+    /// The default value is 0.
+    pub orphaned_data_source_threshold: BlockNumber,
+    /// The maximum estimated size in bytes of the blocks that a block
+    /// stream holds in its buffer. The stream stops fetching blocks when
+    /// the buffer reaches that size, even if it holds fewer blocks than its
+    /// count limit. A value of 0 turns this off.
+    ///
+    /// Set by the environment variable `GRAPH_BLOCK_STREAM_BUFFER_BYTES`.
+    /// The default value is 0.
+    pub block_stream_buffer_bytes: usize,
     /// Timeouts to use for various IPFS requests set by
     /// `GRAPH_IPFS_REQUEST_TIMEOUT`. Defaults to 60 seconds for release
     /// builds and one second for debug builds to speed up tests. The value
     /// is in seconds.
     pub ipfs_request_timeout: Duration,
+    /// Overrides of some of these values for individual networks, set by
+    /// variables like `ETHEREUM_REORG_THRESHOLD__MAINNET`
+    network_overrides: NetworkOverrides,
 }
 
 impl EnvVars {
@@ -295,6 +351,10 @@ impl EnvVars {
         let graphql = InnerGraphQl::init_from_env()?.into();
         let mapping_handlers = InnerMappingHandlers::init_from_env()?.try_into()?;
         let store = InnerStore::init_from_env()?.try_into()?;
+        let network_overrides =
+            NetworkOverrides::from_vars(std::env::vars_os().filter_map(|(name, value)| {
+                Some((name.into_string().ok()?, value.into_string().ok()?))
+            }))?;
         let ipfs_request_timeout = match inner.ipfs_request_timeout {
             Some(timeout) => Duration::from_secs(timeout),
             None => {
@@ -320,6 +380,9 @@ impl EnvVars {
                 .0
                 || cfg!(debug_assertions),
             max_spec_version: inner.max_spec_version,
+            min_spec_version: inner
+                .min_spec_version
+                .map_or(MIN_SPEC_VERSION, |version| version.max(MIN_SPEC_VERSION)),
             load_window_size: Duration::from_secs(inner.load_window_size_in_secs),
             load_bin_size: Duration::from_secs(inner.load_bin_size_in_secs),
             elastic_search_flush_interval: Duration::from_secs(
@@ -385,7 +448,14 @@ impl EnvVars {
             firehose_block_fetch_retry_limit: inner.firehose_block_fetch_retry_limit,
             firehose_block_fetch_timeout: inner.firehose_block_fetch_timeout,
             firehose_block_batch_size: inner.firehose_block_fetch_batch_size,
+            firehose_backfill_parallelism: inner.firehose_backfill_parallelism.max(1),
+            firehose_backfill_segment_size: inner.firehose_backfill_segment_size.max(1),
+            trigger_match_stats_sample_rate: inner.trigger_match_stats_sample_rate,
+            trigger_decode_workers: inner.trigger_decode_workers.max(1),
+            orphaned_data_source_threshold: inner.orphaned_data_source_threshold.max(0),
+            block_stream_buffer_bytes: inner.block_stream_buffer_bytes,
             ipfs_request_timeout,
+            network_overrides,
         })
     }
 
@@ -436,6 +506,26 @@ impl EnvVars {
         self.reorg_threshold
     }
 
+    /// The reorg threshold for `network`; uses the global value unless
+    /// `ETHEREUM_REORG_THRESHOLD__<NETWORK>` is set
+    pub fn reorg_threshold_for(&self, network: &str) -> BlockNumber {
+        #[cfg(debug_assertions)]
+        if *TEST_WITH_NO_REORG.lock().unwrap() {
+            return 0;
+        }
+        self.network_overrides
+            .reorg_threshold(network)
+            .unwrap_or(self.reorg_threshold)
+    }
+
+    /// The polling interval for `network`; uses the global value unless
+    /// `ETHEREUM_POLLING_INTERVAL__<NETWORK>` is set
+    pub fn ingestor_polling_interval_for(&self, network: &str) -> Duration {
+        self.network_overrides
+            .polling_interval(network)
+            .unwrap_or(self.ingestor_polling_interval)
+    }
+
     #[cfg(debug_assertions)]
     pub fn sql_queries_enabled(&self) -> bool {
         // SQL queries are disabled by default for security.
@@ -475,6 +565,25 @@ impl EnvVars {
     pub fn set_store_call_cache_disabled_for_tests(&self, value: bool) {
         *TEST_STORE_CALL_CACHE_DISABLED.lock().unwrap() = value;
     }
+
+    #[cfg(debug_assertions)]
+    pub fn firehose_cursor_history(&self) -> bool {
+        if *TEST_FIREHOSE_CURSOR_HISTORY.lock().unwrap() {
+            true
+        } else {
+            self.store.firehose_cursor_history
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    pub fn firehose_cursor_history(&self) -> bool {
+        self.store.firehose_cursor_history
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn enable_firehose_cursor_history_for_tests(&self, enable: bool) {
+        *TEST_FIREHOSE_CURSOR_HISTORY.lock().unwrap() = enable;
+    }
 }
 
 impl Default for EnvVars {
@@ -503,8 +612,10 @@ struct Inner {
         default = "false"
     )]
     allow_non_deterministic_fulltext_search: EnvVarBoolean,
-    #[envconfig(from = "GRAPH_MAX_SPEC_VERSION", default = "1.5.0")]
+    #[envconfig(from = "GRAPH_MAX_SPEC_VERSION", default = "1.6.0")]
     max_spec_version: Version,
+    #[envconfig(from = "GRAPH_MIN_SPEC_VERSION")]
+    min_spec_version: Option<Version>,
     #[envconfig(from = "GRAPH_LOAD_WINDOW_SIZE", default = "300")]
     load_window_size_in_secs: u64,
     #[envconfig(from = "GRAPH_LOAD_BIN_SIZE", default = "1")]
@@ -611,6 +722,18 @@ struct Inner {
     firehose_block_fetch_timeout: u64,
     #[envconfig(from = "GRAPH_FIREHOSE_FETCH_BLOCK_BATCH_SIZE", default = "10")]
     firehose_block_fetch_batch_size: usize,
+    #[envconfig(from = "GRAPH_FIREHOSE_BACKFILL_PARALLELISM", default = "1")]
+    firehose_backfill_parallelism: usize,
+    #[envconfig(from = "GRAPH_FIREHOSE_BACKFILL_SEGMENT_SIZE", default = "1000")]
+    firehose_backfill_segment_size: usize,
+    #[envconfig(from = "GRAPH_TRIGGER_MATCH_STATS_SAMPLE_RATE", default = "0")]
+    trigger_match_stats_sample_rate: u32,
+    #[envconfig(from = "GRAPH_TRIGGER_DECODE_WORKERS", default = "1")]
+    trigger_decode_workers: usize,
+    #[envconfig(from = "GRAPH_ORPHANED_DATA_SOURCE_THRESHOLD", default = "0")]
+    orphaned_data_source_threshold: BlockNumber,
+    #[envconfig(from = "GRAPH_BLOCK_STREAM_BUFFER_BYTES", default = "0")]
+    block_stream_buffer_bytes: usize,
     #[envconfig(from = "GRAPH_IPFS_REQUEST_TIMEOUT")]
     ipfs_request_timeout: Option<u64>,
     #[envconfig(
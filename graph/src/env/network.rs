@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use crate::components::store::BlockNumber;
+
+/// Separates the name of an environment variable from the network it
+/// applies to, e.g. `ETHEREUM_REORG_THRESHOLD__ARBITRUM_ONE`
+const SEPARATOR: &str = "__";
+
+const REORG_THRESHOLD: &str = "ETHEREUM_REORG_THRESHOLD";
+const POLLING_INTERVAL: &str = "ETHEREUM_POLLING_INTERVAL";
+
+/// Values of some environment variables that only apply to one network.
+/// An override is set by appending `__<NETWORK>` to the name of the
+/// global variable, where `<NETWORK>` is the name of the network in upper
+/// case with `-` replaced by `_`. Networks without an override use the
+/// global value.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkOverrides {
+    reorg_threshold: HashMap<String, BlockNumber>,
+    polling_interval: HashMap<String, Duration>,
+}
+
+impl NetworkOverrides {
+    pub(super) fn from_vars(vars: impl Iterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        let mut overrides = Self::default();
+        for (name, value) in vars {
+            let Some((var, network)) = name.split_once(SEPARATOR) else {
+                continue;
+            };
+            if network.is_empty() {
+                continue;
+            }
+            let parse_err = |e: std::num::ParseIntError| {
+                anyhow!("failed to parse environment variable {name}: {e}")
+            };
+            match var {
+                REORG_THRESHOLD => {
+                    let threshold = value.parse().map_err(parse_err)?;
+                    overrides
+                        .reorg_threshold
+                        .insert(network.to_string(), threshold);
+                }
+                POLLING_INTERVAL => {
+                    let millis = value.parse().map_err(parse_err)?;
+                    overrides
+                        .polling_interval
+                        .insert(network.to_string(), Duration::from_millis(millis));
+                }
+                _ => {}
+            }
+        }
+        Ok(overrides)
+    }
+
+    pub fn reorg_threshold(&self, network: &str) -> Option<BlockNumber> {
+        self.reorg_threshold.get(&network_suffix(network)).copied()
+    }
+
+    pub fn polling_interval(&self, network: &str) -> Option<Duration> {
+        self.polling_interval.get(&network_suffix(network)).copied()
+    }
+}
+
+/// The suffix that environment variables use for `network`. Different
+/// networks can map to the same suffix, e.g. `arbitrum-one` and
+/// `arbitrum_one`, and can then not be told apart by an override
+pub fn network_suffix(network: &str) -> String {
+    network.to_uppercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{ENV_VARS, EnvVars};
+
+    fn env_vars(vars: &[(&str, &str)]) -> EnvVars {
+        let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+        EnvVars {
+            network_overrides: NetworkOverrides::from_vars(vars).unwrap(),
+            ..ENV_VARS.clone()
+        }
+    }
+
+    #[test]
+    fn override_takes_precedence_for_its_network() {
+        let env = env_vars(&[
+            ("ETHEREUM_REORG_THRESHOLD__ARBITRUM_ONE", "10"),
+            ("ETHEREUM_POLLING_INTERVAL__MAINNET", "250"),
+            ("GRAPH_UNRELATED__MAINNET", "whatever"),
+        ]);
+
+        assert_eq!(10, env.reorg_threshold_for("arbitrum-one"));
+        assert_eq!(env.reorg_threshold(), env.reorg_threshold_for("mainnet"));
+
+        assert_eq!(
+            Duration::from_millis(250),
+            env.ingestor_polling_interval_for("mainnet")
+        );
+        assert_eq!(
+            env.ingestor_polling_interval,
+            env.ingestor_polling_interval_for("arbitrum-one")
+        );
+    }
+
+    #[test]
+    fn invalid_override_is_an_error() {
+        let vars = [("ETHEREUM_REORG_THRESHOLD__MAINNET", "many")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert!(NetworkOverrides::from_vars(vars).is_err());
+    }
+}
@@ -9,6 +9,12 @@ pub struct EnvVarsStore {
     /// Set by the environment variable `GRAPH_CHAIN_HEAD_WATCHER_TIMEOUT`
     /// (expressed in seconds). The default value is 30 seconds.
     pub chain_head_watcher_timeout: Duration,
+    /// Chain head updates that arrive within this window after a
+    /// notification are coalesced into one notification for the latest
+    /// head at the end of the window. Set by the environment variable
+    /// `GRAPH_CHAIN_HEAD_UPDATE_WINDOW` (expressed in milliseconds). The
+    /// default value is 0, which notifies of every update immediately.
+    pub chain_head_update_window: Duration,
     /// This is how long statistics that influence query execution are cached in
     /// memory before they are reloaded from the database.
     ///
@@ -109,6 +115,11 @@ pub struct EnvVarsStore {
     /// versions, but fewer than `rebuild_threshold`, by deleting. Set by
     /// `GRAPH_STORE_HISTORY_DELETE_THRESHOLD`. The default is 0.05
     pub delete_threshold: f64,
+    /// When pruning, keep historical entity versions that are visible at a
+    /// multiple of this many blocks so that a sparse sample of the history
+    /// remains. Set by `GRAPH_STORE_HISTORY_SAMPLE_INTERVAL`. The default
+    /// is 0, which removes all history
+    pub history_sample_interval: BlockNumber,
     /// How much history a subgraph with limited history can accumulate
     /// before it will be pruned. Setting this to 1.1 means that the
     /// subgraph will be pruned every time it contains 10% more history (in
@@ -122,6 +133,10 @@ pub struct EnvVarsStore {
     /// Set by `GRAPH_STORE_PRUNE_DISABLE_RANGE_BOUND_ESTIMATION`.
     /// Defaults to false. Remove after 2025-07-15
     pub prune_disable_range_bound_estimation: bool,
+    /// Whether to run the `vacuum` and `reindex` statements that pruning
+    /// by deleting recommends instead of only logging them. Set by
+    /// `GRAPH_STORE_PRUNE_RUN_MAINTENANCE`. The default is `false`
+    pub prune_run_maintenance: bool,
     /// How long to accumulate changes into a batch before a write has to
     /// happen. Set by the environment variable
     /// `GRAPH_STORE_WRITE_BATCH_DURATION` in seconds. The default is 300s.
@@ -141,6 +156,14 @@ pub struct EnvVarsStore {
     /// Whether to create GIN indexes for array attributes. Set by
     /// `GRAPH_STORE_CREATE_GIN_INDEXES`. The default is `false`
     pub create_gin_indexes: bool,
+    /// Whether to remember the Firehose cursor that a deployment stored
+    /// with each block it wrote so that queries can be run at the block
+    /// for a cursor. Set by `GRAPH_STORE_FIREHOSE_CURSOR_HISTORY`. The
+    /// default is `false`
+    pub firehose_cursor_history: bool,
+    /// Whether to compress blocks when writing them to the chain store.
+    /// Set by `GRAPH_STORE_COMPRESS_BLOCK_DATA`. The default is `false`
+    pub compress_block_data: bool,
     /// Temporary env var in case we need to quickly rollback PR #5010
     pub use_brin_for_all_query_types: bool,
     /// Temporary env var to disable certain lookups in the chain store
@@ -185,6 +208,17 @@ pub struct EnvVarsStore {
     /// a connection; all others fail instantly with DatabaseUnavailable.
     /// Set by `GRAPH_STORE_CONNECTION_UNAVAILABLE_RETRY`. Default is 2 seconds.
     pub connection_unavailable_retry: Duration,
+    /// The maximum number of entity versions per second that are written
+    /// to each shard. The limit can be changed while graph-node is
+    /// running. Set by `GRAPH_STORE_WRITE_ROWS_PER_SECOND`. The default is
+    /// 0, which means that writes are not limited.
+    pub write_rows_per_second: u64,
+    /// How far behind the head of a deployment, in blocks, the block of a
+    /// query must be for the query to be sent to a history replica. Since
+    /// replicas lag behind the main database, blocks closer to the head
+    /// might not be on the replica yet. Set by
+    /// `GRAPH_STORE_HISTORY_REPLICA_LAG`. The default is 100.
+    pub history_replica_lag: BlockNumber,
 }
 
 // This does not print any values avoid accidentally leaking any sensitive env vars
@@ -200,6 +234,7 @@ impl TryFrom<InnerStore> for EnvVarsStore {
     fn try_from(x: InnerStore) -> Result<Self, Self::Error> {
         let vars = Self {
             chain_head_watcher_timeout: Duration::from_secs(x.chain_head_watcher_timeout_in_secs),
+            chain_head_update_window: Duration::from_millis(x.chain_head_update_window_in_millis),
             query_stats_refresh_interval: Duration::from_secs(
                 x.query_stats_refresh_interval_in_secs,
             ),
@@ -233,12 +268,16 @@ impl TryFrom<InnerStore> for EnvVarsStore {
             batch_worker_wait: Duration::from_millis(x.batch_worker_wait),
             rebuild_threshold: x.rebuild_threshold.0,
             delete_threshold: x.delete_threshold.0,
+            history_sample_interval: x.history_sample_interval.max(0),
             history_slack_factor: x.history_slack_factor.0,
             prune_keep_history: x.prune_keep_status,
             prune_disable_range_bound_estimation: x.prune_disable_range_bound_estimation,
+            prune_run_maintenance: x.prune_run_maintenance,
             write_batch_duration: Duration::from_secs(x.write_batch_duration_in_secs),
             write_batch_size: x.write_batch_size * 1_000,
             create_gin_indexes: x.create_gin_indexes,
+            firehose_cursor_history: x.firehose_cursor_history,
+            compress_block_data: x.compress_block_data,
             use_brin_for_all_query_types: x.use_brin_for_all_query_types,
             disable_block_cache_for_lookup: x.disable_block_cache_for_lookup,
             ignore_block_cache: x.ignore_block_cache,
@@ -253,6 +292,8 @@ impl TryFrom<InnerStore> for EnvVarsStore {
             connection_unavailable_retry: Duration::from_secs(
                 x.connection_unavailable_retry_in_secs,
             ),
+            write_rows_per_second: x.write_rows_per_second,
+            history_replica_lag: x.history_replica_lag,
         };
         if let Some(timeout) = vars.batch_timeout
             && timeout < 2 * vars.batch_target_duration
@@ -282,6 +323,8 @@ impl TryFrom<InnerStore> for EnvVarsStore {
 pub struct InnerStore {
     #[envconfig(from = "GRAPH_CHAIN_HEAD_WATCHER_TIMEOUT", default = "30")]
     chain_head_watcher_timeout_in_secs: u64,
+    #[envconfig(from = "GRAPH_CHAIN_HEAD_UPDATE_WINDOW", default = "0")]
+    chain_head_update_window_in_millis: u64,
     #[envconfig(from = "GRAPH_QUERY_STATS_REFRESH_INTERVAL", default = "300")]
     query_stats_refresh_interval_in_secs: u64,
     #[envconfig(from = "GRAPH_SCHEMA_CACHE_TTL")]
@@ -329,6 +372,8 @@ pub struct InnerStore {
     rebuild_threshold: ZeroToOneF64,
     #[envconfig(from = "GRAPH_STORE_HISTORY_DELETE_THRESHOLD", default = "0.05")]
     delete_threshold: ZeroToOneF64,
+    #[envconfig(from = "GRAPH_STORE_HISTORY_SAMPLE_INTERVAL", default = "0")]
+    history_sample_interval: BlockNumber,
     #[envconfig(from = "GRAPH_STORE_HISTORY_SLACK_FACTOR", default = "1.2")]
     history_slack_factor: HistorySlackF64,
     #[envconfig(from = "GRAPH_STORE_HISTORY_KEEP_STATUS", default = "5")]
@@ -338,6 +383,8 @@ pub struct InnerStore {
         default = "false"
     )]
     prune_disable_range_bound_estimation: bool,
+    #[envconfig(from = "GRAPH_STORE_PRUNE_RUN_MAINTENANCE", default = "false")]
+    prune_run_maintenance: bool,
     #[envconfig(from = "GRAPH_STORE_WRITE_BATCH_DURATION", default = "300")]
     write_batch_duration_in_secs: u64,
     #[envconfig(from = "GRAPH_STORE_WRITE_BATCH_SIZE", default = "10000")]
@@ -346,6 +393,10 @@ pub struct InnerStore {
     write_batch_memoize: bool,
     #[envconfig(from = "GRAPH_STORE_CREATE_GIN_INDEXES", default = "false")]
     create_gin_indexes: bool,
+    #[envconfig(from = "GRAPH_STORE_FIREHOSE_CURSOR_HISTORY", default = "false")]
+    firehose_cursor_history: bool,
+    #[envconfig(from = "GRAPH_STORE_COMPRESS_BLOCK_DATA", default = "false")]
+    compress_block_data: bool,
     #[envconfig(from = "GRAPH_STORE_USE_BRIN_FOR_ALL_QUERY_TYPES", default = "false")]
     use_brin_for_all_query_types: bool,
     #[envconfig(from = "GRAPH_STORE_DISABLE_BLOCK_CACHE_FOR_LOOKUP", default = "false")]
@@ -370,6 +421,10 @@ pub struct InnerStore {
     connection_validation_idle_secs: u64,
     #[envconfig(from = "GRAPH_STORE_CONNECTION_UNAVAILABLE_RETRY", default = "2")]
     connection_unavailable_retry_in_secs: u64,
+    #[envconfig(from = "GRAPH_STORE_WRITE_ROWS_PER_SECOND", default = "0")]
+    write_rows_per_second: u64,
+    #[envconfig(from = "GRAPH_STORE_HISTORY_REPLICA_LAG", default = "100")]
+    history_replica_lag: BlockNumber,
 }
 
 #[derive(Clone, Copy, Debug)]
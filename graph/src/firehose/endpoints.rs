@@ -639,6 +639,40 @@ impl FirehoseEndpoint {
         }
     }
 
+    /// Ask the provider for the block it currently considers the chain
+    /// head
+    pub async fn head_block_ptr<M>(&self, logger: &Logger) -> Result<BlockPtr, anyhow::Error>
+    where
+        M: prost::Message + BlockchainBlock + Default + 'static,
+    {
+        debug!(
+            logger,
+            "Connecting to firehose to retrieve the chain head";
+            "provider" => self.provider.as_str(),
+        );
+
+        let mut client = self.new_stream_client();
+
+        // A negative start block is relative to the chain head, and the
+        // first block of the stream is therefore the head block. We only
+        // need that one block and drop the stream after receiving it
+        let response_stream = client
+            .blocks(firehose::Request {
+                start_block_num: -1,
+                final_blocks_only: false,
+                ..Default::default()
+            })
+            .await?;
+
+        match response_stream.into_inner().next().await {
+            Some(Ok(v)) => Ok(decode_firehose_block::<M>(&v)?.ptr()),
+            Some(Err(e)) => Err(anyhow::format_err!("firehose error {}", e)),
+            None => Err(anyhow::format_err!(
+                "Firehose should have returned the head block for request"
+            )),
+        }
+    }
+
     pub async fn stream_blocks(
         self: Arc<Self>,
         request: firehose::Request,
@@ -1,4 +1,6 @@
 use std::{
+    collections::{HashMap, hash_map::Entry},
+    future::Future,
     path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration,
@@ -7,6 +9,8 @@ use std::{
 use anyhow::anyhow;
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures03::FutureExt as _;
+use futures03::future::{BoxFuture, Shared};
 use graph_derive::CheapClone;
 use lru_time_cache::LruCache;
 use object_store::{ObjectStore, ObjectStoreExt as _, local::LocalFileSystem, path::Path};
@@ -14,6 +18,7 @@ use redis::{
     AsyncCommands as _, RedisResult, Value,
     aio::{ConnectionManager, ConnectionManagerConfig},
 };
+use sha2::{Digest, Sha256};
 use slog::{Logger, debug, info, warn};
 use tokio::sync::Mutex as AsyncMutex;
 
@@ -21,7 +26,7 @@ use crate::{env::ENV_VARS, prelude::CheapClone};
 
 use super::{
     ContentPath, IpfsClient, IpfsContext, IpfsError, IpfsMetrics, IpfsRequest, IpfsResponse,
-    IpfsResult, RetryPolicy,
+    IpfsResult, RetryPolicy, Revalidated, ServerAddress,
 };
 
 struct RedisClient {
@@ -74,12 +79,73 @@ impl RedisClient {
     }
 }
 
+/// The SHA-256 hash of a cached file
+type ContentHash = [u8; 32];
+
+/// An in-memory cache that stores files with identical content only once.
+/// Paths map to the hash of their content, and the content is kept as long
+/// as at least one cached path refers to it. The capacity limits the number
+/// of paths, which are evicted in LRU order.
+struct ContentStore {
+    paths: LruCache<ContentPath, ContentHash>,
+    blobs: HashMap<ContentHash, (Bytes, usize)>,
+}
+
+impl ContentStore {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            paths: LruCache::with_capacity(capacity),
+            blobs: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &ContentPath) -> Option<Bytes> {
+        let hash = self.paths.get(path)?;
+        self.blobs.get(hash).map(|(data, _)| data.clone())
+    }
+
+    fn insert(&mut self, path: ContentPath, data: Bytes) {
+        if self.paths.contains_key(&path) {
+            return;
+        }
+
+        let hash: ContentHash = Sha256::digest(&data).into();
+        self.blobs.entry(hash).or_insert_with(|| (data, 0)).1 += 1;
+
+        let (_, evicted) = self.paths.notify_insert(path, hash);
+        for (_, hash) in evicted {
+            self.release(&hash);
+        }
+    }
+
+    /// Drop one reference to the content with `hash`, and the content
+    /// itself once nothing refers to it anymore
+    fn release(&mut self, hash: &ContentHash) {
+        if let Entry::Occupied(mut entry) = self.blobs.entry(*hash) {
+            entry.get_mut().1 -= 1;
+            if entry.get().1 == 0 {
+                entry.remove();
+            }
+        }
+    }
+
+    /// The number of distinct files that are stored
+    #[cfg(test)]
+    fn blob_count(&self) -> usize {
+        self.blobs.len()
+    }
+}
+
 #[derive(Clone, CheapClone)]
 enum Cache {
     Memory {
         cache: Arc<Mutex<LruCache<ContentPath, Bytes>>>,
         max_entry_size: usize,
     },
+    Deduplicated {
+        cache: Arc<Mutex<ContentStore>>,
+        max_entry_size: usize,
+    },
     Disk {
         store: Arc<dyn ObjectStore>,
     },
@@ -139,6 +205,13 @@ impl Cache {
                     store: Arc::new(fs),
                 })
             }
+            None if ENV_VARS.mappings.ipfs_cache_deduplicate => {
+                debug!(logger, "Using deduplicating IPFS in-memory cache"; "capacity" => capacity, "max_entry_size" => max_entry_size);
+                Ok(Self::Deduplicated {
+                    cache: Arc::new(Mutex::new(ContentStore::with_capacity(capacity))),
+                    max_entry_size,
+                })
+            }
             None => {
                 debug!(logger, "Using IPFS in-memory cache"; "capacity" => capacity, "max_entry_size" => max_entry_size);
                 Ok(Self::Memory {
@@ -155,6 +228,10 @@ impl Cache {
                 cache,
                 max_entry_size: _,
             } => cache.lock().unwrap().get(path).cloned(),
+            Cache::Deduplicated {
+                cache,
+                max_entry_size: _,
+            } => cache.lock().unwrap().get(path),
             Cache::Disk { store } => {
                 let log_err = |e: &object_store::Error| log_object_store_err(logger, e, false);
 
@@ -182,6 +259,10 @@ impl Cache {
                     cache.insert(path.clone(), data.clone());
                 }
             }
+            Cache::Deduplicated { max_entry_size, .. } if data.len() > *max_entry_size => {}
+            Cache::Deduplicated { cache, .. } => {
+                cache.lock().unwrap().insert(path, data);
+            }
             Cache::Disk { store } => {
                 let log_err = |e: &object_store::Error| log_object_store_err(logger, e, true);
                 let path = Self::disk_path(&path);
@@ -205,14 +286,45 @@ impl Cache {
     }
 }
 
+/// Identifies requests that return the same content, so that only one of
+/// them has to be sent if several are made at the same time
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum InFlightKey {
+    Cat {
+        path: ContentPath,
+        max_size: usize,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    },
+    GetBlock {
+        path: ContentPath,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    },
+}
+
+type InFlightFetch = Shared<BoxFuture<'static, Result<Bytes, Arc<IpfsError>>>>;
+
 /// An IPFS client that caches the results of `cat` and `get_block` calls in
 /// memory or on disk, depending on settings in the environment.
 ///
 /// The cache is used to avoid repeated calls to the IPFS API for the same
 /// content.
+///
+/// Concurrent identical `cat` and `get_block` requests for content that is not
+/// cached yet share a single request to the underlying client, and a single
+/// cache write. The shared request keeps running as long as at least one of
+/// them is still waiting for the content, and is logged and measured against
+/// the context of the request that started it.
+///
+/// For content that was cached by `cat_if_none_match`, the client also keeps
+/// the entity tag the server sent with it, so that it can ask the server to
+/// confirm that its copy is current instead of downloading it again.
 pub struct CachingClient {
     client: Arc<dyn IpfsClient>,
     cache: Cache,
+    in_flight: Arc<Mutex<HashMap<InFlightKey, InFlightFetch>>>,
+    etags: Mutex<LruCache<ContentPath, String>>,
 }
 
 impl CachingClient {
@@ -227,20 +339,69 @@ impl CachingClient {
         )
         .await?;
 
-        Ok(CachingClient { client, cache })
+        Ok(CachingClient {
+            client,
+            cache,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            etags: Mutex::new(LruCache::with_capacity(env.max_ipfs_cache_size as usize)),
+        })
     }
 
-    async fn with_cache<F>(&self, logger: Logger, path: &ContentPath, f: F) -> IpfsResult<Bytes>
+    /// Returns the cached content of `path`, or else the result of `fetch`,
+    /// which is then cached. Concurrent requests with the same `key` share a
+    /// single `fetch`.
+    async fn with_cache<F>(
+        self: &Arc<Self>,
+        logger: Logger,
+        path: &ContentPath,
+        key: InFlightKey,
+        fetch: F,
+    ) -> IpfsResult<Bytes>
     where
-        F: AsyncFnOnce() -> IpfsResult<Bytes>,
+        F: Future<Output = IpfsResult<Bytes>> + Send + 'static,
     {
         if let Some(data) = self.cache.find(&logger, path).await {
             return Ok(data);
         }
 
-        let data = f().await?;
-        self.cache.insert(&logger, path.clone(), data.clone()).await;
-        Ok(data)
+        let client = self.cheap_clone();
+        let path = path.cheap_clone();
+
+        self.fetch_once(key, async move {
+            let data = fetch.await?;
+            client.cache.insert(&logger, path, data.clone()).await;
+            Ok(data)
+        })
+        .await
+    }
+
+    /// Returns the result of `fetch`, unless a request with the same `key`
+    /// is already in flight, in which case that request's result is
+    /// returned instead.
+    async fn fetch_once<F>(&self, key: InFlightKey, fetch: F) -> IpfsResult<Bytes>
+    where
+        F: Future<Output = IpfsResult<Bytes>> + Send + 'static,
+    {
+        let fetch = match self.in_flight.lock().unwrap().entry(key.clone()) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                let in_flight = self.in_flight.cheap_clone();
+                let fetch = async move {
+                    let result = fetch.await.map_err(Arc::new);
+                    in_flight.lock().unwrap().remove(&key);
+                    result
+                }
+                .boxed()
+                .shared();
+
+                entry.insert(fetch.clone());
+                fetch
+            }
+        };
+
+        fetch
+            .await
+            .map_err(|err| Arc::try_unwrap(err).unwrap_or_else(IpfsError::Shared))
     }
 }
 
@@ -250,6 +411,10 @@ impl IpfsClient for CachingClient {
         self.client.metrics()
     }
 
+    fn server_address(&self) -> Option<ServerAddress> {
+        self.client.server_address()
+    }
+
     async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
         self.client.cheap_clone().call(req).await
     }
@@ -262,15 +427,86 @@ impl IpfsClient for CachingClient {
         timeout: Option<Duration>,
         retry_policy: RetryPolicy,
     ) -> IpfsResult<Bytes> {
-        self.with_cache(ctx.logger(path), path, async || {
-            {
-                self.client
-                    .cheap_clone()
-                    .cat(ctx, path, max_size, timeout, retry_policy)
+        let key = InFlightKey::Cat {
+            path: path.cheap_clone(),
+            max_size,
+            timeout,
+            retry_policy,
+        };
+        let fetch = {
+            let client = self.client.cheap_clone();
+            let ctx = ctx.cheap_clone();
+            let path = path.cheap_clone();
+
+            async move {
+                client
+                    .cat(&ctx, &path, max_size, timeout, retry_policy)
                     .await
             }
-        })
-        .await
+        };
+
+        self.with_cache(ctx.logger(path), path, key, fetch).await
+    }
+
+    async fn cat_if_none_match(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        etag: Option<&str>,
+        max_size: usize,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Revalidated> {
+        let logger = ctx.logger(path);
+
+        let known = self.etags.lock().unwrap().get(path).cloned();
+        let cached = self.cache.find(&logger, path).await;
+
+        match (&cached, &known) {
+            // The caller has the copy that we have cached
+            (Some(_), Some(known)) if etag == Some(known.as_str()) => {
+                return Ok(Revalidated::NotModified);
+            }
+            // We can't ask the server about a copy without an entity tag,
+            // but content paths are immutable, so it is still current
+            (Some(data), None) if etag.is_none() => {
+                return Ok(Revalidated::Modified {
+                    data: data.clone(),
+                    etag: None,
+                });
+            }
+            _ => {}
+        }
+
+        // If we have a copy, ask the server about it, since we can hand it
+        // to the caller if it is current
+        let cached = cached.zip(known);
+        let sent_etag = cached.as_ref().map(|(_, known)| known.as_str()).or(etag);
+        let res = self
+            .client
+            .cheap_clone()
+            .cat_if_none_match(ctx, path, sent_etag, max_size, timeout, retry_policy)
+            .await?;
+
+        match res {
+            Revalidated::NotModified => match cached {
+                Some((data, known)) => Ok(Revalidated::Modified {
+                    data,
+                    etag: Some(known),
+                }),
+                None => Ok(Revalidated::NotModified),
+            },
+            Revalidated::Modified { data, etag } => {
+                self.cache.insert(&logger, path.clone(), data.clone()).await;
+                if let Some(etag) = &etag {
+                    self.etags
+                        .lock()
+                        .unwrap()
+                        .insert(path.clone(), etag.clone());
+                }
+                Ok(Revalidated::Modified { data, etag })
+            }
+        }
     }
 
     async fn get_block(
@@ -280,12 +516,318 @@ impl IpfsClient for CachingClient {
         timeout: Option<Duration>,
         retry_policy: RetryPolicy,
     ) -> IpfsResult<Bytes> {
-        self.with_cache(ctx.logger(path), path, async || {
-            self.client
+        let key = InFlightKey::GetBlock {
+            path: path.cheap_clone(),
+            timeout,
+            retry_policy,
+        };
+        let fetch = {
+            let client = self.client.cheap_clone();
+            let ctx = ctx.cheap_clone();
+            let path = path.cheap_clone();
+
+            async move { client.get_block(&ctx, &path, timeout, retry_policy).await }
+        };
+
+        self.with_cache(ctx.logger(path), path, key, fetch).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers as m;
+
+    use super::*;
+    use crate::ipfs::{IpfsClientPool, IpfsGatewayClient};
+    use crate::log::discard;
+
+    const CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+    fn path(name: &str) -> ContentPath {
+        ContentPath::new(format!("{CID}/{name}")).unwrap()
+    }
+
+    /// A caching client for a pool with a gateway client for each of
+    /// `servers`, which all serve `a` after a delay
+    async fn slow_pool(servers: &[&MockServer]) -> Arc<CachingClient> {
+        let mut clients: Vec<Arc<dyn IpfsClient>> = Vec::new();
+
+        for server in servers {
+            Mock::given(m::method("GET"))
+                .and(m::path(format!("/ipfs/{CID}/a")))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_bytes(b"content")
+                        .set_delay(Duration::from_millis(200)),
+                )
+                .expect(1)
+                .mount(server)
+                .await;
+
+            let gateway =
+                IpfsGatewayClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard())
+                    .unwrap();
+            clients.push(Arc::new(gateway));
+        }
+
+        let pool = Arc::new(IpfsClientPool::new(clients));
+        Arc::new(CachingClient::new(pool, &discard()).await.unwrap())
+    }
+
+    #[test]
+    fn identical_content_is_stored_once() {
+        let mut store = ContentStore::with_capacity(10);
+
+        store.insert(path("a"), Bytes::from_static(b"same"));
+        store.insert(path("b"), Bytes::from_static(b"same"));
+
+        assert_eq!(store.blob_count(), 1);
+        assert_eq!(store.get(&path("a")).unwrap(), Bytes::from_static(b"same"));
+        assert_eq!(store.get(&path("b")).unwrap(), Bytes::from_static(b"same"));
+
+        store.insert(path("c"), Bytes::from_static(b"other"));
+        assert_eq!(store.blob_count(), 2);
+        assert_eq!(store.get(&path("c")).unwrap(), Bytes::from_static(b"other"));
+    }
+
+    #[test]
+    fn content_is_kept_while_a_path_refers_to_it() {
+        let mut store = ContentStore::with_capacity(2);
+
+        store.insert(path("a"), Bytes::from_static(b"same"));
+        store.insert(path("b"), Bytes::from_static(b"same"));
+
+        // Evicts `a`, but `b` still refers to the shared content
+        store.insert(path("c"), Bytes::from_static(b"other"));
+        assert!(store.get(&path("a")).is_none());
+        assert_eq!(store.blob_count(), 2);
+
+        // Evicts `b`, the last reference to the shared content
+        store.insert(path("d"), Bytes::from_static(b"more"));
+        assert!(store.get(&path("b")).is_none());
+        assert_eq!(store.blob_count(), 2);
+        assert_eq!(store.get(&path("c")).unwrap(), Bytes::from_static(b"other"));
+        assert_eq!(store.get(&path("d")).unwrap(), Bytes::from_static(b"more"));
+    }
+
+    #[crate::test]
+    async fn warming_populates_the_cache() {
+        let server = MockServer::start().await;
+
+        for name in ["a", "b"] {
+            Mock::given(m::method("GET"))
+                .and(m::path(format!("/ipfs/{CID}/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(name.as_bytes()))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        Mock::given(m::method("GET"))
+            .and(m::path(format!("/ipfs/{CID}/missing")))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let gateway =
+            IpfsGatewayClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard())
+                .unwrap();
+        let client = Arc::new(
+            CachingClient::new(Arc::new(gateway), &discard())
+                .await
+                .unwrap(),
+        );
+        let ctx = IpfsContext::test();
+        let paths = [path("a"), path("missing"), path("b")];
+
+        let warmed = client
+            .cheap_clone()
+            .warm_cache(&ctx, &paths, None, RetryPolicy::None)
+            .await;
+
+        let outcomes: Vec<_> = warmed
+            .iter()
+            .map(|(path, res)| (path.clone(), res.is_ok()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (path("a"), true),
+                (path("missing"), false),
+                (path("b"), true)
+            ]
+        );
+
+        // Served from the cache; the server expects a single request per path
+        for name in ["a", "b"] {
+            let data = client
                 .cheap_clone()
-                .get_block(ctx, path, timeout, retry_policy)
+                .cat(&ctx, &path(name), usize::MAX, None, RetryPolicy::None)
                 .await
-        })
-        .await
+                .unwrap();
+            assert_eq!(data.as_ref(), name.as_bytes());
+        }
+    }
+
+    #[crate::test]
+    async fn revalidating_cached_content_does_not_contact_the_server() {
+        let server = MockServer::start().await;
+
+        Mock::given(m::method("GET"))
+            .and(m::path(format!("/ipfs/{CID}/a")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"a\"")
+                    .set_body_bytes(b"a"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let gateway =
+            IpfsGatewayClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard())
+                .unwrap();
+        let client = Arc::new(
+            CachingClient::new(Arc::new(gateway), &discard())
+                .await
+                .unwrap(),
+        );
+        let ctx = IpfsContext::test();
+
+        let res = client
+            .cheap_clone()
+            .cat_if_none_match(&ctx, &path("a"), None, usize::MAX, None, RetryPolicy::None)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            Revalidated::Modified {
+                data: Bytes::from_static(b"a"),
+                etag: Some("\"a\"".to_owned()),
+            }
+        );
+
+        let res = client
+            .cheap_clone()
+            .cat_if_none_match(
+                &ctx,
+                &path("a"),
+                Some("\"a\""),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res, Revalidated::NotModified);
+    }
+
+    #[crate::test]
+    async fn not_modified_responses_reuse_the_cached_content() {
+        let server = MockServer::start().await;
+
+        Mock::given(m::method("GET"))
+            .and(m::path(format!("/ipfs/{CID}/a")))
+            .and(m::header("If-None-Match", "\"a\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(m::method("GET"))
+            .and(m::path(format!("/ipfs/{CID}/a")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"a\"")
+                    .set_body_bytes(b"a"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let gateway =
+            IpfsGatewayClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard())
+                .unwrap();
+        let client = Arc::new(
+            CachingClient::new(Arc::new(gateway), &discard())
+                .await
+                .unwrap(),
+        );
+        let ctx = IpfsContext::test();
+        let expected = Revalidated::Modified {
+            data: Bytes::from_static(b"a"),
+            etag: Some("\"a\"".to_owned()),
+        };
+
+        let res = client
+            .cheap_clone()
+            .cat_if_none_match(&ctx, &path("a"), None, usize::MAX, None, RetryPolicy::None)
+            .await
+            .unwrap();
+        assert_eq!(res, expected);
+
+        // The caller's copy is not the one we cached, so the server is
+        // asked about ours, and confirms it without sending it again
+        let res = client
+            .cheap_clone()
+            .cat_if_none_match(
+                &ctx,
+                &path("a"),
+                Some("\"b\""),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res, expected);
+    }
+
+    #[crate::test]
+    async fn identical_requests_share_a_single_pool_race() {
+        let server_1 = MockServer::start().await;
+        let server_2 = MockServer::start().await;
+        let client = slow_pool(&[&server_1, &server_2]).await;
+        let ctx = IpfsContext::test();
+
+        let results = futures03::future::join_all((0..10).map(|_| {
+            client
+                .cheap_clone()
+                .cat(&ctx, &path("a"), usize::MAX, None, RetryPolicy::None)
+        }))
+        .await;
+
+        for result in results {
+            assert_eq!(result.unwrap().as_ref(), b"content");
+        }
+        assert!(client.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[crate::test]
+    async fn cancelling_a_request_does_not_cancel_the_shared_fetch() {
+        let server = MockServer::start().await;
+        let client = slow_pool(&[&server]).await;
+        let ctx = IpfsContext::test();
+
+        let first = client
+            .cheap_clone()
+            .cat(&ctx, &path("a"), usize::MAX, None, RetryPolicy::None);
+        let second =
+            client
+                .cheap_clone()
+                .cat(&ctx, &path("a"), usize::MAX, None, RetryPolicy::None);
+
+        // The first requester gives up while the content is still being fetched
+        let (first, second) = tokio::join!(
+            tokio::time::timeout(Duration::from_millis(50), first),
+            second
+        );
+
+        assert!(first.is_err());
+        assert_eq!(second.unwrap().as_ref(), b"content");
     }
 }
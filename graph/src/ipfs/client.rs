@@ -1,4 +1,5 @@
 use std::future::Future;
+use std::ops::Range;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -13,7 +14,11 @@ use slog::Logger;
 use crate::cheap_clone::CheapClone as _;
 use crate::data::subgraph::DeploymentHash;
 use crate::derive::CheapClone;
-use crate::ipfs::{ContentPath, IpfsError, IpfsMetrics, IpfsResult, RetryPolicy};
+use crate::env::ENV_VARS;
+use crate::ipfs::dir_listing::parse_dir_listing;
+use crate::ipfs::{
+    ContentPath, DirEntry, IpfsError, IpfsMetrics, IpfsResult, RetryPolicy, ServerAddress,
+};
 
 /// A read-only connection to an IPFS server.
 #[async_trait]
@@ -21,6 +26,11 @@ pub trait IpfsClient: Send + Sync + 'static {
     /// Returns the metrics associated with the IPFS client.
     fn metrics(&self) -> &IpfsMetrics;
 
+    /// Returns the address of the IPFS server, if the client connects to a single server.
+    fn server_address(&self) -> Option<ServerAddress> {
+        None
+    }
+
     /// Sends a request to the IPFS server and returns a raw response.
     async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse>;
 
@@ -37,29 +47,26 @@ pub trait IpfsClient: Send + Sync + 'static {
         timeout: Option<Duration>,
         retry_policy: RetryPolicy,
     ) -> IpfsResult<BoxStream<'static, IpfsResult<Bytes>>> {
-        let fut = retry_policy
-            .create("IPFS.cat_stream", &ctx.logger(path))
-            .no_timeout()
-            .run({
+        let fut = retry_policy.run("IPFS.cat_stream", &ctx.logger(path), {
+            let path = path.cheap_clone();
+            let deployment_hash = ctx.deployment_hash();
+
+            move || {
+                let client = self.cheap_clone();
+                let metrics = self.metrics().cheap_clone();
+                let deployment_hash = deployment_hash.cheap_clone();
                 let path = path.cheap_clone();
-                let deployment_hash = ctx.deployment_hash();
 
-                move || {
-                    let client = self.cheap_clone();
-                    let metrics = self.metrics().cheap_clone();
-                    let deployment_hash = deployment_hash.cheap_clone();
-                    let path = path.cheap_clone();
-
-                    async move {
-                        run_with_metrics(
-                            client.call(IpfsRequest::Cat(path)),
-                            deployment_hash,
-                            metrics,
-                        )
-                        .await
-                    }
+                async move {
+                    run_with_metrics(
+                        client.call(IpfsRequest::Cat(path)),
+                        deployment_hash,
+                        metrics,
+                    )
+                    .await
                 }
-            });
+            }
+        });
 
         let resp = run_with_optional_timeout(path, fut, timeout).await?;
 
@@ -69,7 +76,9 @@ pub trait IpfsClient: Send + Sync + 'static {
     /// Downloads data from the specified content path.
     ///
     /// If a timeout is specified, the execution will be aborted if the IPFS server
-    /// does not return a response within the specified amount of time.
+    /// does not return a response, or stops sending the content, for the specified
+    /// amount of time. Downloading a large file may take longer than the timeout as
+    /// long as the server keeps sending it.
     async fn cat(
         self: Arc<Self>,
         ctx: &IpfsContext,
@@ -78,31 +87,140 @@ pub trait IpfsClient: Send + Sync + 'static {
         timeout: Option<Duration>,
         retry_policy: RetryPolicy,
     ) -> IpfsResult<Bytes> {
-        let fut = retry_policy
-            .create("IPFS.cat", &ctx.logger(path))
-            .no_timeout()
-            .run({
+        let fut = retry_policy.run("IPFS.cat", &ctx.logger(path), {
+            let path = path.cheap_clone();
+            let deployment_hash = ctx.deployment_hash();
+
+            move || {
+                let client = self.cheap_clone();
+                let metrics = self.metrics().cheap_clone();
+                let deployment_hash = deployment_hash.cheap_clone();
                 let path = path.cheap_clone();
-                let deployment_hash = ctx.deployment_hash();
 
-                move || {
-                    let client = self.cheap_clone();
-                    let metrics = self.metrics().cheap_clone();
-                    let deployment_hash = deployment_hash.cheap_clone();
-                    let path = path.cheap_clone();
-
-                    async move {
+                async move {
+                    let resp = run_with_optional_timeout(
+                        &path,
                         run_with_metrics(
-                            client.call(IpfsRequest::Cat(path)),
+                            client.call(IpfsRequest::Cat(path.cheap_clone())),
                             deployment_hash,
                             metrics,
-                        )
-                        .await?
-                        .bytes(Some(max_size))
-                        .await
+                        ),
+                        timeout,
+                    )
+                    .await?;
+
+                    resp.with_chunk_timeout(timeout).bytes(Some(max_size)).await
+                }
+            }
+        });
+
+        fut.await
+    }
+
+    /// Downloads data from the specified content path unless it still has the
+    /// entity tag `etag`, which the caller got with an earlier copy of it.
+    ///
+    /// Returns [Revalidated::NotModified] if the server confirms that the caller's
+    /// copy is current, and the content with its entity tag otherwise. Without an
+    /// `etag`, this always downloads the content.
+    ///
+    /// The timeout applies as for [IpfsClient::cat].
+    async fn cat_if_none_match(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        etag: Option<&str>,
+        max_size: usize,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Revalidated> {
+        let fut = retry_policy.run("IPFS.cat_if_none_match", &ctx.logger(path), {
+            let path = path.cheap_clone();
+            let etag = etag.map(str::to_owned);
+            let deployment_hash = ctx.deployment_hash();
+
+            move || {
+                let client = self.cheap_clone();
+                let metrics = self.metrics().cheap_clone();
+                let deployment_hash = deployment_hash.cheap_clone();
+                let path = path.cheap_clone();
+                let req = match etag.clone() {
+                    Some(etag) => IpfsRequest::CatIfNoneMatch(path.cheap_clone(), etag),
+                    None => IpfsRequest::Cat(path.cheap_clone()),
+                };
+
+                async move {
+                    let resp = run_with_optional_timeout(
+                        &path,
+                        run_with_metrics(client.call(req), deployment_hash, metrics),
+                        timeout,
+                    )
+                    .await?;
+
+                    if resp.is_not_modified() {
+                        return Ok(Revalidated::NotModified);
                     }
+
+                    let etag = resp.etag();
+                    let data = resp
+                        .with_chunk_timeout(timeout)
+                        .bytes(Some(max_size))
+                        .await?;
+
+                    Ok(Revalidated::Modified { data, etag })
                 }
-            });
+            }
+        });
+
+        fut.await
+    }
+
+    /// Downloads the bytes in `range` of the content at the specified path.
+    ///
+    /// If the server ignores the range and sends the whole content, the bytes outside
+    /// of the range are dropped while the content is streamed, unless strict range
+    /// requests are enabled, in which case this fails with
+    /// [IpfsError::RangeNotSupported]. A gateway that sends a different range is
+    /// treated like one that ignores the range. This also fails if the server does
+    /// not say whether it honored the range and sends more than it.
+    ///
+    /// If a timeout is specified, the execution will be aborted if the IPFS server
+    /// does not return a response within the specified amount of time.
+    async fn cat_range(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        range: Range<u64>,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Bytes> {
+        if range.is_empty() {
+            return Ok(Bytes::new());
+        }
+
+        let fut = retry_policy.run("IPFS.cat_range", &ctx.logger(path), {
+            let path = path.cheap_clone();
+            let deployment_hash = ctx.deployment_hash();
+
+            move || {
+                let client = self.cheap_clone();
+                let metrics = self.metrics().cheap_clone();
+                let deployment_hash = deployment_hash.cheap_clone();
+                let path = path.cheap_clone();
+                let range = range.clone();
+
+                async move {
+                    run_with_metrics(
+                        client.call(IpfsRequest::CatRange(path, range.clone())),
+                        deployment_hash,
+                        metrics,
+                    )
+                    .await?
+                    .range_bytes(range, ENV_VARS.mappings.ipfs_strict_range_requests)
+                    .await
+                }
+            }
+        });
 
         run_with_optional_timeout(path, fut, timeout).await
     }
@@ -118,34 +236,107 @@ pub trait IpfsClient: Send + Sync + 'static {
         timeout: Option<Duration>,
         retry_policy: RetryPolicy,
     ) -> IpfsResult<Bytes> {
-        let fut = retry_policy
-            .create("IPFS.get_block", &ctx.logger(path))
-            .no_timeout()
-            .run({
+        let fut = retry_policy.run("IPFS.get_block", &ctx.logger(path), {
+            let path = path.cheap_clone();
+            let deployment_hash = ctx.deployment_hash();
+
+            move || {
+                let client = self.cheap_clone();
+                let metrics = self.metrics().cheap_clone();
+                let deployment_hash = deployment_hash.cheap_clone();
                 let path = path.cheap_clone();
-                let deployment_hash = ctx.deployment_hash();
 
-                move || {
-                    let client = self.cheap_clone();
-                    let metrics = self.metrics().cheap_clone();
-                    let deployment_hash = deployment_hash.cheap_clone();
-                    let path = path.cheap_clone();
+                async move {
+                    run_with_metrics(
+                        client.call(IpfsRequest::GetBlock(path)),
+                        deployment_hash,
+                        metrics,
+                    )
+                    .await?
+                    .bytes(None)
+                    .await
+                }
+            }
+        });
 
-                    async move {
-                        run_with_metrics(
-                            client.call(IpfsRequest::GetBlock(path)),
-                            deployment_hash,
-                            metrics,
-                        )
-                        .await?
-                        .bytes(None)
-                        .await
-                    }
+        run_with_optional_timeout(path, fut, timeout).await
+    }
+
+    /// Lists the entries of the directory at the specified content path.
+    ///
+    /// Fails with [IpfsError::NotADirectory] if the content is not a directory.
+    ///
+    /// If a timeout is specified, the execution will be aborted if the IPFS server
+    /// does not return a response within the specified amount of time.
+    async fn ls(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Vec<DirEntry>> {
+        let fut = retry_policy.run("IPFS.ls", &ctx.logger(path), {
+            let path = path.cheap_clone();
+            let deployment_hash = ctx.deployment_hash();
+
+            move || {
+                let client = self.cheap_clone();
+                let metrics = self.metrics().cheap_clone();
+                let deployment_hash = deployment_hash.cheap_clone();
+                let path = path.cheap_clone();
+
+                async move {
+                    let bytes = run_with_metrics(
+                        client.call(IpfsRequest::Ls(path.cheap_clone())),
+                        deployment_hash,
+                        metrics,
+                    )
+                    .await?
+                    .bytes(Some(ENV_VARS.mappings.max_ipfs_file_bytes))
+                    .await?;
+
+                    parse_dir_listing(&path, &bytes)
                 }
-            });
+            }
+        });
 
         run_with_optional_timeout(path, fut, timeout).await
     }
+
+    /// Fetches the content of all `paths` so that clients that cache
+    /// content can serve later requests for them without contacting the
+    /// IPFS server.
+    ///
+    /// At most `GRAPH_IPFS_REQUEST_LIMIT` paths are fetched concurrently.
+    /// Failing to fetch a path does not stop the remaining paths from being
+    /// fetched; the outcome for each path is returned in the order of `paths`.
+    async fn warm_cache(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        paths: &[ContentPath],
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> Vec<(ContentPath, IpfsResult<()>)> {
+        let max_size = ENV_VARS.mappings.max_ipfs_file_bytes;
+        let limit = (ENV_VARS.mappings.ipfs_request_limit as usize).max(1);
+
+        futures03::stream::iter(paths)
+            .map(|path| {
+                let client = self.cheap_clone();
+
+                async move {
+                    let res = client
+                        .cat(ctx, path, max_size, timeout, retry_policy)
+                        .await
+                        .map(|_| ());
+
+                    (path.cheap_clone(), res)
+                }
+            })
+            .buffered(limit)
+            .collect()
+            .await
+    }
 }
 
 #[derive(Clone, Debug, CheapClone)]
@@ -185,7 +376,23 @@ impl IpfsContext {
 #[derive(Clone, Debug)]
 pub enum IpfsRequest {
     Cat(ContentPath),
+    /// Requests only the bytes in the range of the content
+    CatRange(ContentPath, Range<u64>),
     GetBlock(ContentPath),
+    /// Requests a listing of the directory at the path
+    Ls(ContentPath),
+    /// Requests the content unless it still has the entity tag; servers that do not
+    /// support conditional requests send the content
+    CatIfNoneMatch(ContentPath, String),
+}
+
+/// The outcome of [IpfsClient::cat_if_none_match].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Revalidated {
+    /// The content still has the entity tag that was sent with the request.
+    NotModified,
+    /// The content, with its entity tag if the server sent one.
+    Modified { data: Bytes, etag: Option<String> },
 }
 
 /// Contains a raw, successful IPFS response.
@@ -193,6 +400,29 @@ pub enum IpfsRequest {
 pub struct IpfsResponse {
     pub(super) path: ContentPath,
     pub(super) response: reqwest::Response,
+    pub(super) range: RangeHandling,
+    /// How long to wait for the next chunk of the body before giving up
+    pub(super) chunk_timeout: Option<Duration>,
+}
+
+/// How the server handled the range of a `CatRange` request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum RangeHandling {
+    /// The server confirmed that it only sent the requested range.
+    Applied,
+    /// The server sent the whole content. This is also used for responses to requests
+    /// that are not for a range.
+    Ignored,
+    /// The server does not tell. A body that is longer than the range means that the
+    /// server ignored the range, and since it is then unclear which part of the content
+    /// the body holds, that is an error.
+    Unknown,
+}
+
+/// The first byte of a `Content-Range` header value like `bytes 2-5/9`.
+pub(super) fn content_range_start(value: &str) -> Option<u64> {
+    let (first, _) = value.strip_prefix("bytes ")?.split_once('-')?;
+    first.trim().parse().ok()
 }
 
 impl IpfsResponse {
@@ -203,28 +433,56 @@ impl IpfsResponse {
         Self {
             path,
             response: reqwest::Response::from(http::Response::new(bytes)),
+            range: RangeHandling::Ignored,
+            chunk_timeout: None,
+        }
+    }
+
+    /// Makes reading the body fail with [IpfsError::RequestTimeout] if the
+    /// server does not send the next chunk of it within `timeout`.
+    pub(super) fn with_chunk_timeout(self, timeout: Option<Duration>) -> Self {
+        Self {
+            chunk_timeout: timeout,
+            ..self
         }
     }
 
+    /// Returns `true` if the server answered a conditional request with
+    /// `304 Not Modified`.
+    pub(super) fn is_not_modified(&self) -> bool {
+        self.response.status() == reqwest::StatusCode::NOT_MODIFIED
+    }
+
+    /// Returns the entity tag of the content, if the server sent one.
+    pub(super) fn etag(&self) -> Option<String> {
+        self.response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    }
+
     /// Reads and returns the response body.
     ///
     /// If the max size is specified and the response body is larger than the max size,
-    /// execution will result in an error.
+    /// execution will result in an error. For compressed responses, the max size applies
+    /// to the decoded body, and reading stops as soon as it is exceeded.
     pub async fn bytes(self, max_size: Option<usize>) -> IpfsResult<Bytes> {
-        let Some(max_size) = max_size else {
+        if max_size.is_none() && self.chunk_timeout.is_none() {
             return self.response.bytes().await.map_err(Into::into);
-        };
+        }
 
+        let path = self.path.clone();
         let bytes = self
-            .response
-            .bytes_stream()
-            .err_into()
+            .body()
             .try_fold(BytesMut::new(), |mut acc, chunk| async {
                 acc.extend(chunk);
 
-                if acc.len() > max_size {
+                if let Some(max_size) = max_size
+                    && acc.len() > max_size
+                {
                     return Err(IpfsError::ContentTooLarge {
-                        path: self.path.clone(),
+                        path: path.clone(),
                         max_size,
                     });
                 }
@@ -236,9 +494,87 @@ impl IpfsResponse {
         Ok(bytes.into())
     }
 
+    /// Reads the bytes in `range` from the response to a `CatRange` request.
+    ///
+    /// If the server sent the whole content instead of just the range, the bytes
+    /// outside of the range are dropped, or, if `strict` is set, an error is returned.
+    /// If the server does not say whether it honored the range and sends more than the
+    /// range, an error is returned.
+    pub(super) async fn range_bytes(self, range: Range<u64>, strict: bool) -> IpfsResult<Bytes> {
+        let path = self.path.clone();
+        let handling = self.range;
+
+        let start = usize::try_from(range.start).unwrap_or(usize::MAX);
+        let len = usize::try_from(range.end.saturating_sub(range.start)).unwrap_or(usize::MAX);
+        let end = start.saturating_add(len);
+
+        let mut stream = self.body();
+        let mut acc = BytesMut::new();
+
+        loop {
+            if handling == RangeHandling::Ignored && strict {
+                return Err(IpfsError::RangeNotSupported { path });
+            }
+
+            let limit = match handling {
+                RangeHandling::Applied => len,
+                RangeHandling::Ignored => end,
+                // Reading one byte more than the range tells us whether the
+                // server ignored the range.
+                RangeHandling::Unknown => len.saturating_add(1),
+            };
+
+            if acc.len() >= limit {
+                if handling == RangeHandling::Unknown {
+                    return Err(IpfsError::RangeNotSupported { path });
+                }
+                break;
+            }
+
+            match stream.next().await {
+                Some(chunk) => acc.extend_from_slice(&chunk?),
+                None => break,
+            }
+        }
+
+        let bytes = acc.freeze();
+        let bytes = match handling {
+            RangeHandling::Applied | RangeHandling::Unknown => bytes.slice(..len.min(bytes.len())),
+            RangeHandling::Ignored => bytes.slice(start.min(bytes.len())..end.min(bytes.len())),
+        };
+
+        Ok(bytes)
+    }
+
     /// Converts the response into a stream of bytes from the body.
     pub fn bytes_stream(self) -> BoxStream<'static, IpfsResult<Bytes>> {
-        self.response.bytes_stream().err_into().boxed()
+        self.body()
+    }
+
+    /// The chunks of the body, which fail with [IpfsError::RequestTimeout]
+    /// once a chunk takes longer than the chunk timeout to arrive.
+    fn body(self) -> BoxStream<'static, IpfsResult<Bytes>> {
+        let stream = self.response.bytes_stream().err_into().boxed();
+
+        let Some(timeout) = self.chunk_timeout else {
+            return stream;
+        };
+
+        let path = self.path;
+        futures03::stream::unfold(Some(stream), move |stream| {
+            let path = path.cheap_clone();
+
+            async move {
+                let mut stream = stream?;
+
+                match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(Some(chunk)) => Some((chunk, Some(stream))),
+                    Ok(None) => None,
+                    Err(_) => Some((Err(IpfsError::RequestTimeout { path }), None)),
+                }
+            }
+        })
+        .boxed()
     }
 }
 
@@ -278,7 +614,7 @@ where
             metrics.observe_request_duration(&deployment_hash, timer.elapsed().as_secs_f64())
         })
         .inspect_err(|err| {
-            if err.is_timeout() {
+            if err.is_timeout() || err.is_not_found() {
                 metrics.add_not_found(&deployment_hash)
             } else {
                 metrics.add_error(&deployment_hash)
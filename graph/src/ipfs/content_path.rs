@@ -10,6 +10,11 @@ use crate::{
 };
 
 /// Represents a path to some data on IPFS.
+///
+/// A content path always starts with a CID, and never with an IPNS name or a
+/// DNSLink domain. Servers can still send an entity tag with the content, which
+/// lets [IpfsClient::cat_if_none_match](super::IpfsClient::cat_if_none_match)
+/// confirm that a cached copy is current without downloading it again.
 #[derive(Debug, Clone, CheapClone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ContentPath {
     inner: Arc<Inner>,
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use slog::Logger;
+use slog::info;
+
+use crate::data::subgraph::DeploymentHash;
+use crate::derive::CheapClone;
+use crate::env::ENV_VARS;
+use crate::ipfs::{IpfsClient, IpfsMetrics, new_ipfs_client_with_metrics};
+
+/// The IPFS clients of deployments that do not use the default IPFS client.
+///
+/// Requests on behalf of a deployment that has its own client are only sent
+/// to that client; all other deployments use the default client.
+#[derive(Clone, CheapClone, Default)]
+pub struct DeploymentIpfsClients {
+    clients: Arc<HashMap<String, Arc<dyn IpfsClient>>>,
+}
+
+impl DeploymentIpfsClients {
+    pub fn new(clients: impl IntoIterator<Item = (DeploymentHash, Arc<dyn IpfsClient>)>) -> Self {
+        let clients = clients
+            .into_iter()
+            .map(|(hash, client)| (hash.to_string(), client))
+            .collect();
+
+        Self {
+            clients: Arc::new(clients),
+        }
+    }
+
+    /// Connects to the servers configured with `GRAPH_IPFS_DEPLOYMENT_SERVERS`.
+    ///
+    /// The clients report to `metrics` so that they show up in the same
+    /// metrics as the default client.
+    pub async fn from_env(metrics: IpfsMetrics, logger: &Logger) -> anyhow::Result<Self> {
+        let mut clients = Vec::new();
+
+        for (hash, server_addresses) in &ENV_VARS.mappings.ipfs_deployment_servers {
+            let hash = DeploymentHash::new(hash.as_str()).map_err(|e| {
+                anyhow!("GRAPH_IPFS_DEPLOYMENT_SERVERS contains an invalid deployment hash: {e}")
+            })?;
+
+            info!(logger, "Using separate IPFS servers for deployment"; "deployment" => hash.as_str());
+
+            let client =
+                new_ipfs_client_with_metrics(server_addresses, metrics.cheap_clone(), logger)
+                    .await?;
+            clients.push((hash, client));
+        }
+
+        Ok(Self::new(clients))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Returns the client for the deployment with the specified hash, or
+    /// `default` if the deployment does not have its own client.
+    pub fn client_for(
+        &self,
+        deployment_hash: &str,
+        default: &Arc<dyn IpfsClient>,
+    ) -> Arc<dyn IpfsClient> {
+        self.clients
+            .get(deployment_hash)
+            .unwrap_or(default)
+            .cheap_clone()
+    }
+}
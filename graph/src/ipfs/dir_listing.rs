@@ -0,0 +1,201 @@
+use anyhow::anyhow;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use cid::Cid;
+use serde::Deserialize;
+
+use crate::ipfs::{ContentPath, IpfsError, IpfsResult};
+
+/// The UnixFS data type of a directory that is not sharded.
+///
+/// Reference: <https://specs.ipfs.tech/unixfs/#data-types>
+const UNIXFS_DIRECTORY: u8 = 1;
+
+/// The UnixFS data type of a sharded directory.
+const UNIXFS_HAMT_SHARD: u8 = 5;
+
+/// An entry in the listing of an IPFS directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub cid: Cid,
+
+    /// The size of all blocks of the entry in bytes, which includes the
+    /// encoding overhead.
+    pub size: u64,
+}
+
+/// The DAG-JSON encodings of a node that IPFS servers respond with. The RPC
+/// API returns them for `/api/v0/dag/get` and gateways for `?format=dag-json`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Listing {
+    /// A dag-pb node, which is a directory if its UnixFS data says so.
+    DagPb {
+        #[serde(rename = "Data")]
+        data: Option<DagJsonBytes>,
+        #[serde(rename = "Links")]
+        links: Vec<DagJsonLink>,
+    },
+
+    /// A raw block, which is always (part of) a file.
+    Raw(DagJsonBytes),
+}
+
+#[derive(Deserialize)]
+struct DagJsonBytes {
+    #[serde(rename = "/")]
+    inner: DagJsonBytesInner,
+}
+
+#[derive(Deserialize)]
+struct DagJsonBytesInner {
+    bytes: String,
+}
+
+#[derive(Deserialize)]
+struct DagJsonLink {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Hash")]
+    hash: DagJsonCid,
+    #[serde(rename = "Tsize")]
+    size: u64,
+}
+
+#[derive(Deserialize)]
+struct DagJsonCid {
+    #[serde(rename = "/")]
+    cid: String,
+}
+
+/// Parses the response to an [IpfsRequest::Ls](super::IpfsRequest::Ls)
+/// request for `path`, which may come from the RPC API or a gateway. Files
+/// are never listed, even if they consist of a single block and therefore
+/// have no links.
+pub(super) fn parse_dir_listing(path: &ContentPath, bytes: &[u8]) -> IpfsResult<Vec<DirEntry>> {
+    let invalid = |reason: anyhow::Error| IpfsError::ContentNotAvailable {
+        path: path.clone(),
+        reason: reason.context("invalid directory listing"),
+    };
+
+    let listing: Listing = serde_json::from_slice(bytes).map_err(|e| invalid(e.into()))?;
+
+    let entries: Vec<_> = match listing {
+        Listing::Raw(_) => return Err(IpfsError::NotADirectory { path: path.clone() }),
+        Listing::DagPb { data, links } => {
+            let data = match data {
+                Some(data) => STANDARD_NO_PAD
+                    .decode(data.inner.bytes.trim_end_matches('='))
+                    .map_err(|e| invalid(e.into()))?,
+                None => vec![],
+            };
+
+            // The UnixFS type is the first field of the node's data and is
+            // encoded as a protobuf varint with field number 1.
+            match data.as_slice() {
+                [0x08, UNIXFS_DIRECTORY, ..] => {}
+                [0x08, UNIXFS_HAMT_SHARD, ..] => {
+                    return Err(IpfsError::ContentNotAvailable {
+                        path: path.clone(),
+                        reason: anyhow!(
+                            "listing sharded directories through a gateway is not supported"
+                        ),
+                    });
+                }
+                _ => return Err(IpfsError::NotADirectory { path: path.clone() }),
+            }
+
+            links
+                .into_iter()
+                .map(|link| (link.name, link.hash.cid, link.size))
+                .collect()
+        }
+    };
+
+    entries
+        .into_iter()
+        .map(|(name, cid, size)| {
+            let cid = cid.parse::<Cid>().map_err(|e| invalid(e.into()))?;
+
+            Ok(DirEntry { name, cid, size })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIR: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+    const FILE_A: &str = "QmPZ9gcCEpqKTo6aq61g2nXGUhM4iCL3ewB6LDXZCtioEB";
+    const FILE_B: &str = "Qmb31zcpzqga7ERaUTp83gVdYcuBasz4rXUHFufikFTJGU";
+
+    fn path() -> ContentPath {
+        ContentPath::new(DIR).unwrap()
+    }
+
+    fn entry(name: &str, cid: &str, size: u64) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            cid: cid.parse().unwrap(),
+            size,
+        }
+    }
+
+    #[test]
+    fn parses_listing() {
+        // `CAE` is a UnixFS directory without further fields
+        let body = format!(
+            r#"{{"Data":{{"/":{{"bytes":"CAE"}}}},"Links":[
+                {{"Hash":{{"/":"{FILE_A}"}},"Name":"a.json","Tsize":20}},
+                {{"Hash":{{"/":"{FILE_B}"}},"Name":"b","Tsize":4}}
+            ]}}"#
+        );
+
+        let entries = parse_dir_listing(&path(), body.as_bytes()).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![entry("a.json", FILE_A, 20), entry("b", FILE_B, 4)]
+        );
+    }
+
+    #[test]
+    fn listing_of_a_file_is_an_error() {
+        // `CAIYBA` is a UnixFS file of 4 bytes
+        let body = format!(
+            r#"{{"Data":{{"/":{{"bytes":"CAIYBA"}}}},"Links":[
+                {{"Hash":{{"/":"{FILE_A}"}},"Name":"","Tsize":4}}
+            ]}}"#
+        );
+        let err = parse_dir_listing(&path(), body.as_bytes()).unwrap_err();
+        assert!(matches!(err, IpfsError::NotADirectory { .. }));
+
+        // A file that fits into a single block has no links
+        let body = r#"{"Data":{"/":{"bytes":"CAIYBA"}},"Links":[]}"#;
+        let err = parse_dir_listing(&path(), body.as_bytes()).unwrap_err();
+        assert!(matches!(err, IpfsError::NotADirectory { .. }));
+
+        // A raw block
+        let body = r#"{"/":{"bytes":"c29tZSBkYXRh"}}"#;
+        let err = parse_dir_listing(&path(), body.as_bytes()).unwrap_err();
+        assert!(matches!(err, IpfsError::NotADirectory { .. }));
+    }
+
+    #[test]
+    fn listing_of_an_empty_directory_is_empty() {
+        let body = r#"{"Data":{"/":{"bytes":"CAE"}},"Links":[]}"#;
+
+        let entries = parse_dir_listing(&path(), body.as_bytes()).unwrap();
+
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn invalid_listing_is_an_error() {
+        let err = parse_dir_listing(&path(), b"not json").unwrap_err();
+
+        assert!(matches!(err, IpfsError::ContentNotAvailable { .. }));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures03::future::BoxFuture;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// Looks up the addresses of a host name.
+pub trait DnsLookup: Send + Sync + 'static {
+    fn lookup(&self, host: String) -> BoxFuture<'static, std::io::Result<Vec<SocketAddr>>>;
+}
+
+/// Looks up host names using the system resolver.
+pub struct SystemDnsLookup;
+
+impl DnsLookup for SystemDnsLookup {
+    fn lookup(&self, host: String) -> BoxFuture<'static, std::io::Result<Vec<SocketAddr>>> {
+        Box::pin(async move { Ok(tokio::net::lookup_host((host.as_str(), 0)).await?.collect()) })
+    }
+}
+
+/// A DNS resolver for IPFS clients that caches resolved addresses for a fixed TTL.
+///
+/// Clones share the same cache, so a single resolver can be used by all clients in a pool.
+/// A streaming response keeps reading from the connection it was opened on, so it stays
+/// pinned to the resolved address even if the cached addresses change mid-stream.
+#[derive(Clone)]
+pub struct DnsCache {
+    ttl: Duration,
+    lookup: Arc<dyn DnsLookup>,
+    entries: Arc<Mutex<HashMap<String, (Instant, Arc<[SocketAddr]>)>>>,
+}
+
+impl DnsCache {
+    /// Creates a new DNS cache that uses the system resolver.
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_lookup(ttl, Arc::new(SystemDnsLookup))
+    }
+
+    /// Creates a new DNS cache that uses the specified lookup.
+    pub fn with_lookup(ttl: Duration, lookup: Arc<dyn DnsLookup>) -> Self {
+        Self {
+            ttl,
+            lookup,
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns the addresses of the host, looking them up if they are not cached
+    /// or if the cached addresses are older than the TTL.
+    pub async fn resolve_host(&self, host: &str) -> std::io::Result<Arc<[SocketAddr]>> {
+        if let Some(addrs) = self.cached(host) {
+            return Ok(addrs);
+        }
+
+        let addrs: Arc<[SocketAddr]> = self.lookup.lookup(host.to_owned()).await?.into();
+
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(host.to_owned(), (Instant::now(), addrs.clone()));
+
+        Ok(addrs)
+    }
+
+    fn cached(&self, host: &str) -> Option<Arc<[SocketAddr]>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(host)
+            .filter(|(resolved_at, _)| resolved_at.elapsed() < self.ttl)
+            .map(|(_, addrs)| addrs.clone())
+    }
+}
+
+impl Resolve for DnsCache {
+    fn resolve(&self, name: Name) -> Resolving {
+        let cache = self.clone();
+
+        Box::pin(async move {
+            let addrs = cache.resolve_host(name.as_str()).await?;
+            let addrs: Addrs = Box::new(addrs.to_vec().into_iter());
+
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    use super::*;
+
+    /// Returns a different address every time a host name is looked up.
+    #[derive(Default)]
+    struct ChangingLookup {
+        calls: AtomicU8,
+    }
+
+    impl DnsLookup for ChangingLookup {
+        fn lookup(&self, _host: String) -> BoxFuture<'static, std::io::Result<Vec<SocketAddr>>> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let addr = SocketAddr::from(([10, 0, 0, n], 0));
+
+            Box::pin(async move { Ok(vec![addr]) })
+        }
+    }
+
+    #[crate::test]
+    async fn resolved_addresses_are_cached_for_the_ttl() {
+        let lookup = Arc::new(ChangingLookup::default());
+        let cache = DnsCache::with_lookup(Duration::from_secs(60), lookup.clone());
+
+        let first = cache.resolve_host("ipfs.test").await.unwrap();
+        let second = cache.clone().resolve_host("ipfs.test").await.unwrap();
+
+        assert_eq!(first.as_ref(), &[SocketAddr::from(([10, 0, 0, 1], 0))]);
+        assert_eq!(first, second);
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[crate::test]
+    async fn expired_addresses_are_looked_up_again() {
+        let lookup = Arc::new(ChangingLookup::default());
+        let cache = DnsCache::with_lookup(Duration::ZERO, lookup.clone());
+
+        let first = cache.resolve_host("ipfs.test").await.unwrap();
+        let second = cache.resolve_host("ipfs.test").await.unwrap();
+
+        assert_eq!(first.as_ref(), &[SocketAddr::from(([10, 0, 0, 1], 0))]);
+        assert_eq!(second.as_ref(), &[SocketAddr::from(([10, 0, 0, 2], 0))]);
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 2);
+    }
+}
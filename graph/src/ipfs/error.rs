@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use reqwest::StatusCode;
 use thiserror::Error;
 
@@ -34,9 +36,20 @@ pub enum IpfsError {
         reason: anyhow::Error,
     },
 
+    /// The server definitively reported that it does not have the content,
+    /// as opposed to failing to answer the request.
+    #[error("IPFS content from '{path}' was not found")]
+    NotFound { path: ContentPath },
+
     #[error("IPFS content from '{path}' exceeds the {max_size} bytes limit")]
     ContentTooLarge { path: ContentPath, max_size: usize },
 
+    #[error("IPFS server ignored the requested range of '{path}'")]
+    RangeNotSupported { path: ContentPath },
+
+    #[error("IPFS content from '{path}' is not a directory")]
+    NotADirectory { path: ContentPath },
+
     /// Does not consider HTTP status codes for timeouts.
     #[error("IPFS request to '{path}' timed out")]
     RequestTimeout { path: ContentPath },
@@ -52,6 +65,16 @@ pub enum IpfsError {
 
     #[error("Invalid cache configuration: {source:#}")]
     InvalidCacheConfig { source: anyhow::Error },
+
+    /// Too many IPFS requests were retried recently, and the request was
+    /// not retried after it failed with `last_error`.
+    #[error("IPFS retry budget is exhausted; last error: {last_error:#}")]
+    RetryBudgetExhausted { last_error: Box<IpfsError> },
+
+    /// The request waited for an identical request that was already in
+    /// flight instead of being sent itself, and that request failed.
+    #[error(transparent)]
+    Shared(Arc<IpfsError>),
 }
 
 #[derive(Debug, Error)]
@@ -64,7 +87,11 @@ pub struct RequestError(reqwest::Error);
 impl IpfsError {
     /// Returns true if the sever is invalid.
     pub fn is_invalid_server(&self) -> bool {
-        matches!(self, Self::InvalidServer { .. })
+        match self {
+            Self::InvalidServer { .. } => true,
+            Self::Shared(err) => err.is_invalid_server(),
+            _ => false,
+        }
     }
 
     /// Returns true if the error was caused by a timeout.
@@ -74,13 +101,36 @@ impl IpfsError {
         match self {
             Self::RequestTimeout { .. } => true,
             Self::RequestFailed(err) if err.is_timeout() => true,
+            Self::Shared(err) => err.is_timeout(),
             _ => false,
         }
     }
 
+    /// Returns true if the server reported that it does not have the content.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Self::NotFound { .. } => true,
+            Self::Shared(err) => err.is_not_found(),
+            _ => false,
+        }
+    }
+
+    /// Turns a request that the server answered with `404 Not Found` into a
+    /// `NotFound` error for `path`; all other errors are left unchanged.
+    pub(super) fn not_found_for(self, path: &ContentPath) -> Self {
+        match self {
+            Self::RequestFailed(err) if err.is_not_found() => Self::NotFound { path: path.clone() },
+            err => err,
+        }
+    }
+
     /// Returns true if the error was caused by a network connection failure.
     pub fn is_networking(&self) -> bool {
-        matches!(self, Self::RequestFailed(err) if err.is_networking())
+        match self {
+            Self::RequestFailed(err) => err.is_networking(),
+            Self::Shared(err) => err.is_networking(),
+            _ => false,
+        }
     }
 
     /// Returns true if the error is deterministic.
@@ -90,11 +140,16 @@ impl IpfsError {
             Self::InvalidServer { .. } => true,
             Self::InvalidContentPath { .. } => true,
             Self::ContentNotAvailable { .. } => false,
+            Self::NotFound { .. } => false,
             Self::ContentTooLarge { .. } => true,
+            Self::RangeNotSupported { .. } => false,
+            Self::NotADirectory { .. } => true,
             Self::RequestTimeout { .. } => false,
             Self::DeterministicFailure { .. } => true,
             Self::RequestFailed(_) => false,
             Self::InvalidCacheConfig { .. } => true,
+            Self::RetryBudgetExhausted { .. } => false,
+            Self::Shared(err) => err.is_deterministic(),
         }
     }
 }
@@ -113,6 +168,11 @@ impl RequestError {
         self.0.is_request() || self.0.is_connect() || self.0.is_timeout()
     }
 
+    /// Returns true if the server responded with `404 Not Found`.
+    pub fn is_not_found(&self) -> bool {
+        self.0.status() == Some(StatusCode::NOT_FOUND)
+    }
+
     /// Returns true if the request failed due to a timeout.
     pub fn is_timeout(&self) -> bool {
         if self.0.is_timeout() {
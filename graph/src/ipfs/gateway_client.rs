@@ -1,14 +1,20 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
 use derive_more::Debug;
 use http::header::ACCEPT;
 use http::header::CACHE_CONTROL;
+use http::header::CONTENT_RANGE;
+use http::header::IF_NONE_MATCH;
+use http::header::RANGE;
 use reqwest::{StatusCode, redirect::Policy as RedirectPolicy};
 use slog::Logger;
 
 use crate::env::ENV_VARS;
+use crate::ipfs::client::{RangeHandling, content_range_start};
+use crate::ipfs::dns::DnsCache;
 use crate::ipfs::{
     IpfsClient, IpfsError, IpfsMetrics, IpfsRequest, IpfsResponse, IpfsResult, RetryPolicy,
     ServerAddress,
@@ -24,6 +30,10 @@ pub struct IpfsGatewayClient {
     #[debug(skip)]
     http_client: reqwest::Client,
 
+    #[debug(skip)]
+    dns_cache: Option<DnsCache>,
+    connect_timeout: Duration,
+
     metrics: IpfsMetrics,
     logger: Logger,
 }
@@ -56,19 +66,60 @@ impl IpfsGatewayClient {
         metrics: IpfsMetrics,
         logger: &Logger,
     ) -> IpfsResult<Self> {
+        let connect_timeout = ENV_VARS.mappings.ipfs_connect_timeout;
+
         Ok(Self {
             server_address: ServerAddress::new(server_address)?,
-            http_client: reqwest::Client::builder()
-                // IPFS gateways allow requests to directory CIDs.
-                // However, they sometimes redirect before displaying the directory listing.
-                // This policy permits that behavior.
-                .redirect(RedirectPolicy::limited(1))
-                .build()?,
+            http_client: Self::http_client(None, connect_timeout)?,
+            dns_cache: None,
+            connect_timeout,
             metrics,
             logger: logger.to_owned(),
         })
     }
 
+    /// Makes the client resolve host names through the specified DNS cache.
+    pub fn with_dns_cache(self, dns_cache: DnsCache) -> IpfsResult<Self> {
+        Ok(Self {
+            http_client: Self::http_client(Some(dns_cache.clone()), self.connect_timeout)?,
+            dns_cache: Some(dns_cache),
+            ..self
+        })
+    }
+
+    /// Makes the client give up on connecting to the server after the
+    /// specified amount of time. This does not limit how long it takes
+    /// the server to respond once the connection is established.
+    pub fn with_connect_timeout(self, connect_timeout: Duration) -> IpfsResult<Self> {
+        Ok(Self {
+            http_client: Self::http_client(self.dns_cache.clone(), connect_timeout)?,
+            connect_timeout,
+            ..self
+        })
+    }
+
+    fn http_client(
+        dns_cache: Option<DnsCache>,
+        connect_timeout: Duration,
+    ) -> IpfsResult<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            // IPFS gateways allow requests to directory CIDs.
+            // However, they sometimes redirect before displaying the directory listing.
+            // This policy permits that behavior.
+            .redirect(RedirectPolicy::limited(1))
+            // Compressed responses are decoded transparently; size limits are
+            // enforced against the decoded bytes.
+            .gzip(true)
+            .brotli(true)
+            .connect_timeout(connect_timeout);
+
+        if let Some(dns_cache) = dns_cache {
+            builder = builder.dns_resolver(Arc::new(dns_cache));
+        }
+
+        Ok(builder.build()?)
+    }
+
     /// A one-time request sent at client initialization to verify that the specified
     /// server address is a valid IPFS gateway server.
     async fn send_test_request(&self) -> anyhow::Result<()> {
@@ -128,15 +179,36 @@ impl IpfsClient for IpfsGatewayClient {
         &self.metrics
     }
 
+    fn server_address(&self) -> Option<ServerAddress> {
+        Some(self.server_address.clone())
+    }
+
     async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
         use IpfsRequest::*;
 
-        let (path, req) = match req {
+        let (path, req, range_start) = match req {
             Cat(path) => {
                 let url = self.ipfs_url(path.to_string());
                 let req = self.http_client.get(url);
 
-                (path, req)
+                (path, req, None)
+            }
+            CatIfNoneMatch(path, etag) => {
+                let url = self.ipfs_url(path.to_string());
+                let req = self.http_client.get(url).header(IF_NONE_MATCH, etag);
+
+                (path, req, None)
+            }
+            CatRange(path, range) => {
+                let url = self.ipfs_url(path.to_string());
+
+                // HTTP ranges include the last byte.
+                let req = self.http_client.get(url).header(
+                    RANGE,
+                    format!("bytes={}-{}", range.start, range.end.saturating_sub(1)),
+                );
+
+                (path, req, Some(range.start))
             }
             GetBlock(path) => {
                 let url = self.ipfs_url(format!("{path}?format=raw"));
@@ -146,13 +218,59 @@ impl IpfsClient for IpfsGatewayClient {
                     .get(url)
                     .header(ACCEPT, "application/vnd.ipld.raw");
 
-                (path, req)
+                (path, req, None)
+            }
+            Ls(path) => {
+                let url = self.ipfs_url(format!("{path}?format=dag-json"));
+
+                let req = self
+                    .http_client
+                    .get(url)
+                    .header(ACCEPT, "application/vnd.ipld.dag-json");
+
+                (path, req, None)
             }
         };
 
-        let response = req.send().await?.error_for_status()?;
+        let response = req
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|err| IpfsError::from(err).not_found_for(&path))?;
+
+        // Gateways that honor the range respond with `206 Partial Content` and
+        // say which bytes they sent in the `Content-Range` header. If a gateway
+        // sends some other part of the content, we fetch all of it instead and
+        // cut the range out of that.
+        let (response, range) = match range_start {
+            Some(start) if response.status() == StatusCode::PARTIAL_CONTENT => {
+                let sent_start = response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(content_range_start);
+                if sent_start == Some(start) {
+                    (response, RangeHandling::Applied)
+                } else {
+                    let response = self
+                        .http_client
+                        .get(self.ipfs_url(path.to_string()))
+                        .send()
+                        .await?
+                        .error_for_status()
+                        .map_err(|err| IpfsError::from(err).not_found_for(&path))?;
+                    (response, RangeHandling::Ignored)
+                }
+            }
+            _ => (response, RangeHandling::Ignored),
+        };
 
-        Ok(IpfsResponse { path, response })
+        Ok(IpfsResponse {
+            path,
+            response,
+            range,
+            chunk_timeout: None,
+        })
     }
 }
 
@@ -160,6 +278,7 @@ impl IpfsClient for IpfsGatewayClient {
 mod tests {
     use std::time::Duration;
 
+    use bytes::Bytes;
     use bytes::BytesMut;
     use futures03::TryStreamExt;
     use wiremock::Mock;
@@ -170,7 +289,7 @@ mod tests {
 
     use super::*;
     use crate::data::subgraph::DeploymentHash;
-    use crate::ipfs::{ContentPath, IpfsContext, IpfsMetrics};
+    use crate::ipfs::{ContentPath, IpfsContext, IpfsMetrics, Revalidated};
     use crate::log::discard;
 
     const PATH: &str = "/ipfs/QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
@@ -212,10 +331,55 @@ mod tests {
         ContentPath::new(PATH).unwrap()
     }
 
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
     fn ms(millis: u64) -> Duration {
         Duration::from_millis(millis)
     }
 
+    /// Starts a server that answers every request with a body of `len`
+    /// bytes, of which it only sends the `chunks`, each after `delay`. If
+    /// the chunks are shorter than the body, the server stalls afterwards.
+    async fn trickling_server(
+        len: usize,
+        chunks: &'static [&'static [u8]],
+        delay: Duration,
+    ) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut req = [0; 4096];
+                    socket.read(&mut req).await?;
+
+                    let head = format!("HTTP/1.1 200 OK\r\ncontent-length: {len}\r\n\r\n");
+                    socket.write_all(head.as_bytes()).await?;
+
+                    for chunk in chunks {
+                        tokio::time::sleep(delay).await;
+                        socket.write_all(chunk).await?;
+                        socket.flush().await?;
+                    }
+
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    std::io::Result::Ok(())
+                });
+            }
+        });
+
+        format!("http://{addr}")
+    }
+
     #[crate::test]
     async fn new_fails_to_create_the_client_if_gateway_is_not_accessible() {
         let server = mock_server().await;
@@ -353,6 +517,30 @@ mod tests {
             .unwrap();
     }
 
+    #[crate::test]
+    async fn cat_fails_fast_on_not_found() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::NOT_FOUND))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::NonDeterministic,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(err.is_not_found());
+    }
+
     #[crate::test]
     async fn cat_returns_the_content() {
         let (server, client) = make_client().await;
@@ -427,6 +615,69 @@ mod tests {
             .unwrap_err();
     }
 
+    #[crate::test]
+    async fn cat_decodes_compressed_content() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .and(m::header_regex("accept-encoding", "gzip"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(gzip(b"some data")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = client
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"some data");
+    }
+
+    #[crate::test]
+    async fn cat_fails_if_decompressed_content_is_too_large() {
+        let (server, client) = make_client().await;
+
+        let data = vec![0u8; 16 * 1024 * 1024];
+        let compressed = gzip(&data);
+        let max_size = 1024 * 1024;
+
+        assert!(compressed.len() < max_size);
+
+        mock_get()
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(compressed),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                max_size,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, IpfsError::ContentTooLarge { .. }));
+    }
+
     #[crate::test]
     async fn cat_fails_on_timeout() {
         let (server, client) = make_client().await;
@@ -449,6 +700,120 @@ mod tests {
             .unwrap_err();
     }
 
+    #[crate::test]
+    async fn cat_fails_fast_if_the_server_can_not_be_connected() {
+        // A listener with a backlog of one whose backlog is taken by
+        // another connection never completes the connection of the client
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _backlog = std::net::TcpStream::connect(addr).unwrap();
+
+        let client = IpfsGatewayClient::new_unchecked(
+            format!("http://{addr}"),
+            IpfsMetrics::test(),
+            &discard(),
+        )
+        .unwrap()
+        .with_connect_timeout(ms(200))
+        .unwrap();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            Arc::new(client).cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            ),
+        )
+        .await
+        .expect("the connect timeout ends the request");
+
+        result.unwrap_err();
+    }
+
+    #[crate::test]
+    async fn connect_timeout_does_not_limit_slow_responses() {
+        let server = mock_server().await;
+        let client =
+            IpfsGatewayClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard())
+                .unwrap()
+                .with_connect_timeout(ms(100))
+                .unwrap();
+
+        mock_get()
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .set_body_bytes(b"some data")
+                    .set_delay(ms(500)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = Arc::new(client)
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"some data");
+    }
+
+    #[crate::test]
+    async fn cat_times_out_when_the_content_stops_arriving() {
+        let server = trickling_server(9, &[b"some "], ms(0)).await;
+        let client =
+            IpfsGatewayClient::new_unchecked(server, IpfsMetrics::test(), &discard()).unwrap();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            Arc::new(client).cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                Some(ms(200)),
+                RetryPolicy::None,
+            ),
+        )
+        .await
+        .expect("the timeout ends the stalled download");
+
+        assert!(matches!(
+            result.unwrap_err(),
+            IpfsError::RequestTimeout { .. }
+        ));
+    }
+
+    #[crate::test]
+    async fn cat_timeout_does_not_limit_downloads_that_make_progress() {
+        // The download takes 500ms, but no chunk takes longer than 100ms
+        let server = trickling_server(9, &[b"so", b"me", b" d", b"at", b"a"], ms(100)).await;
+        let client =
+            IpfsGatewayClient::new_unchecked(server, IpfsMetrics::test(), &discard()).unwrap();
+
+        let bytes = Arc::new(client)
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                Some(ms(400)),
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"some data");
+    }
+
     #[crate::test]
     async fn cat_retries_the_request_on_non_deterministic_errors() {
         let (server, client) = make_client().await;
@@ -480,6 +845,176 @@ mod tests {
         assert_eq!(bytes.as_ref(), b"some data");
     }
 
+    #[crate::test]
+    async fn cat_range_returns_the_range_if_the_gateway_honors_it() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .and(m::header("Range", "bytes=2-5"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::PARTIAL_CONTENT)
+                    .insert_header("Content-Range", "bytes 2-5/9")
+                    .set_body_bytes(b"me d"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = client
+            .cat_range(
+                &IpfsContext::test(),
+                &make_path(),
+                2..6,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"me d");
+    }
+
+    #[crate::test]
+    async fn cat_range_fetches_everything_if_the_gateway_sends_a_different_range() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .and(m::header_exists("Range"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::PARTIAL_CONTENT)
+                    .insert_header("Content-Range", "bytes 0-3/9")
+                    .set_body_bytes(b"some"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"some data"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = client
+            .cat_range(
+                &IpfsContext::test(),
+                &make_path(),
+                2..6,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"me d");
+    }
+
+    #[crate::test]
+    async fn cat_range_truncates_the_content_if_the_gateway_ignores_the_range() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"some data"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = client
+            .cat_range(
+                &IpfsContext::test(),
+                &make_path(),
+                2..6,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"me d");
+    }
+
+    #[crate::test]
+    async fn cat_range_fails_in_strict_mode_if_the_gateway_ignores_the_range() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"some data"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .call(IpfsRequest::CatRange(make_path(), 2..6))
+            .await
+            .unwrap()
+            .range_bytes(2..6, true)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, IpfsError::RangeNotSupported { .. }));
+    }
+
+    #[crate::test]
+    async fn cat_if_none_match_returns_the_content_and_its_etag() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_bytes(b"some data"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = client
+            .cat_if_none_match(
+                &IpfsContext::test(),
+                &make_path(),
+                None,
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            Revalidated::Modified {
+                data: Bytes::from_static(b"some data"),
+                etag: Some("\"v1\"".to_owned()),
+            }
+        );
+    }
+
+    #[crate::test]
+    async fn cat_if_none_match_reports_unmodified_content() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .and(m::header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(StatusCode::NOT_MODIFIED))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = client
+            .cat_if_none_match(
+                &IpfsContext::test(),
+                &make_path(),
+                Some("\"v1\""),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res, Revalidated::NotModified);
+    }
+
     #[crate::test]
     async fn get_block_returns_the_block_content() {
         let (server, client) = make_client().await;
@@ -498,6 +1033,30 @@ mod tests {
         assert_eq!(bytes.as_ref(), b"some data");
     }
 
+    #[crate::test]
+    async fn ls_lists_the_directory_entries() {
+        let (server, client) = make_client().await;
+
+        mock_get()
+            .and(m::query_param("format", "dag-json"))
+            .and(m::header("Accept", "application/vnd.ipld.dag-json"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_string(
+                r#"{"Data":{"/":{"bytes":"CAE"}},"Links":[{"Hash":{"/":"QmPZ9gcCEpqKTo6aq61g2nXGUhM4iCL3ewB6LDXZCtioEB"},"Name":"a.json","Tsize":20}]}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let entries = client
+            .ls(&IpfsContext::test(), &make_path(), None, RetryPolicy::None)
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "a.json");
+        assert_eq!(entries[0].size, 20);
+    }
+
     #[crate::test]
     async fn get_block_fails_on_timeout() {
         let (server, client) = make_client().await;
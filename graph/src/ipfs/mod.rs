@@ -4,36 +4,48 @@ use anyhow::anyhow;
 use cache::CachingClient;
 use futures03::future::BoxFuture;
 use futures03::stream::FuturesUnordered;
+use futures03::stream::Stream;
 use futures03::stream::StreamExt;
 use slog::Logger;
 use slog::info;
 
 use crate::components::metrics::MetricsRegistry;
+use crate::env::ENV_VARS;
 use crate::util::security::SafeDisplay;
 
 mod cache;
 mod client;
 mod content_path;
+mod deployment_clients;
+mod dir_listing;
+mod dns;
 mod error;
 mod gateway_client;
 mod metrics;
 mod pool;
+mod retry_budget;
 mod retry_policy;
 mod rpc_client;
 mod server_address;
+mod swappable;
 
 pub mod test_utils;
 
-pub use self::client::{IpfsClient, IpfsContext, IpfsRequest, IpfsResponse};
+pub use self::client::{IpfsClient, IpfsContext, IpfsRequest, IpfsResponse, Revalidated};
 pub use self::content_path::ContentPath;
+pub use self::deployment_clients::DeploymentIpfsClients;
+pub use self::dir_listing::DirEntry;
+pub use self::dns::{DnsCache, DnsLookup};
 pub use self::error::IpfsError;
 pub use self::error::RequestError;
 pub use self::gateway_client::IpfsGatewayClient;
 pub use self::metrics::IpfsMetrics;
 pub use self::pool::IpfsClientPool;
+pub use self::retry_budget::RetryBudget;
 pub use self::retry_policy::RetryPolicy;
 pub use self::rpc_client::IpfsRpcClient;
 pub use self::server_address::ServerAddress;
+pub use self::swappable::SwappableIpfsClient;
 
 pub type IpfsResult<T> = Result<T, IpfsError>;
 
@@ -43,7 +55,8 @@ pub type IpfsResult<T> = Result<T, IpfsError>;
 /// and for each IPFS request, the fastest client that can provide the content is
 /// automatically selected and the response is streamed from that client.
 ///
-/// All clients are set up to cache results
+/// All clients are set up to cache results. If `GRAPH_IPFS_DNS_CACHE_TTL` is set,
+/// all clients also share a cache of resolved server addresses.
 pub async fn new_ipfs_client<I, S>(
     server_addresses: I,
     registry: &MetricsRegistry,
@@ -53,7 +66,21 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<str>,
 {
-    let metrics = IpfsMetrics::new(registry);
+    new_ipfs_client_with_metrics(server_addresses, IpfsMetrics::new(registry), logger).await
+}
+
+/// Like [new_ipfs_client], but reports to existing metrics instead of
+/// registering new ones.
+async fn new_ipfs_client_with_metrics<I, S>(
+    server_addresses: I,
+    metrics: IpfsMetrics,
+    logger: &Logger,
+) -> IpfsResult<Arc<dyn IpfsClient>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let dns_cache = ENV_VARS.mappings.ipfs_dns_cache_ttl.map(DnsCache::new);
     let mut clients: Vec<Arc<dyn IpfsClient>> = Vec::new();
 
     for server_address in server_addresses {
@@ -65,7 +92,8 @@ where
             SafeDisplay(server_address)
         );
 
-        let client = use_first_valid_api(server_address, metrics.clone(), logger).await?;
+        let client =
+            use_first_valid_api(server_address, metrics.clone(), dns_cache.clone(), logger).await?;
         let client = Arc::new(CachingClient::new(client, logger).await?);
         clients.push(client);
     }
@@ -88,12 +116,54 @@ where
 async fn use_first_valid_api(
     server_address: &str,
     metrics: IpfsMetrics,
+    dns_cache: Option<DnsCache>,
     logger: &Logger,
 ) -> IpfsResult<Arc<dyn IpfsClient>> {
-    let supported_apis: Vec<BoxFuture<IpfsResult<Arc<dyn IpfsClient>>>> = vec![
-        Box::pin(async {
+    let supported_apis = supported_apis(server_address, &metrics, &dns_cache, logger);
+
+    first_valid_api(
+        server_address,
+        supported_apis.into_iter().collect::<FuturesUnordered<_>>(),
+    )
+    .await
+}
+
+/// Like [use_first_valid_api], but checks the APIs one after another instead of
+/// racing them, so that the gateway API is always used if the server supports both.
+/// This is intended for tests that need to know which client they get, and is only
+/// available to other crates with the `test-hooks` feature.
+#[cfg(any(test, feature = "test-hooks"))]
+pub async fn use_first_valid_api_in_order(
+    server_address: &str,
+    metrics: IpfsMetrics,
+    dns_cache: Option<DnsCache>,
+    logger: &Logger,
+) -> IpfsResult<Arc<dyn IpfsClient>> {
+    let supported_apis = supported_apis(server_address, &metrics, &dns_cache, logger);
+
+    first_valid_api(
+        server_address,
+        futures03::stream::iter(supported_apis).then(|api| api),
+    )
+    .await
+}
+
+/// Returns futures that each try to connect to the server with one of the supported
+/// APIs, in the order of preference.
+fn supported_apis<'a>(
+    server_address: &'a str,
+    metrics: &'a IpfsMetrics,
+    dns_cache: &'a Option<DnsCache>,
+    logger: &'a Logger,
+) -> Vec<BoxFuture<'a, IpfsResult<Arc<dyn IpfsClient>>>> {
+    vec![
+        Box::pin(async move {
             IpfsGatewayClient::new(server_address, metrics.clone(), logger)
                 .await
+                .and_then(|client| match dns_cache.clone() {
+                    Some(dns_cache) => client.with_dns_cache(dns_cache),
+                    None => Ok(client),
+                })
                 .map(|client| {
                     info!(
                         logger,
@@ -104,9 +174,13 @@ async fn use_first_valid_api(
                     Arc::new(client) as Arc<dyn IpfsClient>
                 })
         }),
-        Box::pin(async {
+        Box::pin(async move {
             IpfsRpcClient::new(server_address, metrics.clone(), logger)
                 .await
+                .and_then(|client| match dns_cache.clone() {
+                    Some(dns_cache) => client.with_dns_cache(dns_cache),
+                    None => Ok(client),
+                })
                 .map(|client| {
                     info!(
                         logger,
@@ -117,10 +191,18 @@ async fn use_first_valid_api(
                     Arc::new(client) as Arc<dyn IpfsClient>
                 })
         }),
-    ];
+    ]
+}
+
+/// Returns the first client from `clients` that connected successfully, skipping
+/// clients for APIs that the server does not support.
+async fn first_valid_api(
+    server_address: &str,
+    clients: impl Stream<Item = IpfsResult<Arc<dyn IpfsClient>>>,
+) -> IpfsResult<Arc<dyn IpfsClient>> {
+    let mut clients = std::pin::pin!(clients);
 
-    let mut stream = supported_apis.into_iter().collect::<FuturesUnordered<_>>();
-    while let Some(result) = stream.next().await {
+    while let Some(result) = clients.next().await {
         match result {
             Ok(client) => return Ok(client),
             Err(err) if err.is_invalid_server() => {}
@@ -133,3 +215,66 @@ async fn use_first_valid_api(
         reason: anyhow!("unknown server kind"),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers as m;
+
+    use super::*;
+    use crate::log::discard;
+
+    const CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+    #[crate::test]
+    async fn in_order_detection_prefers_the_gateway_api() {
+        let server = MockServer::start().await;
+
+        // The gateway check is slower, so the RPC API would usually win a race
+        Mock::given(m::method("HEAD"))
+            .and(m::path(format!("/ipfs/{CID}")))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+            .mount(&server)
+            .await;
+
+        Mock::given(m::method("POST"))
+            .and(m::path("/api/v0/version"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        Mock::given(m::method("GET"))
+            .and(m::path(format!("/ipfs/{CID}")))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"gateway"))
+            .mount(&server)
+            .await;
+
+        Mock::given(m::method("POST"))
+            .and(m::path("/api/v0/cat"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"rpc"))
+            .mount(&server)
+            .await;
+
+        let client =
+            use_first_valid_api_in_order(&server.uri(), IpfsMetrics::test(), None, &discard())
+                .await
+                .unwrap();
+
+        let bytes = client
+            .cat(
+                &IpfsContext::test(),
+                &ContentPath::new(CID).unwrap(),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"gateway");
+    }
+}
@@ -2,62 +2,140 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
+use futures03::FutureExt;
 use futures03::stream::FuturesUnordered;
 use futures03::stream::StreamExt;
 
-use crate::ipfs::{IpfsClient, IpfsError, IpfsMetrics, IpfsRequest, IpfsResponse, IpfsResult};
+use crate::ipfs::{
+    IpfsClient, IpfsError, IpfsMetrics, IpfsRequest, IpfsResponse, IpfsResult, ServerAddress,
+};
+use crate::util::security::SafeDisplay;
 
 /// Contains a list of IPFS clients and, for each read request, selects the fastest IPFS client
 /// that can provide the content and streams the response from that client.
 ///
 /// This can significantly improve performance when using multiple IPFS gateways,
 /// as some of them may already have the content cached.
+///
+/// A request only fails with `IpfsError::NotFound` if every client reported that it
+/// does not have the content; since such errors are not retried, the request is then
+/// not sent to every client again.
+///
+/// In deterministic mode, the clients are queried one after another in the order
+/// they were specified, and the response from the first client that can provide
+/// the content is returned. This is intended for tests that need reproducible results.
 pub struct IpfsClientPool {
     clients: Vec<Arc<dyn IpfsClient>>,
+    deterministic: bool,
 }
 
 impl IpfsClientPool {
     /// Creates a new IPFS client pool from the specified clients.
     pub fn new(clients: Vec<Arc<dyn IpfsClient>>) -> Self {
         assert!(!clients.is_empty());
-        Self { clients }
+
+        Self {
+            clients,
+            deterministic: false,
+        }
     }
-}
 
-#[async_trait]
-impl IpfsClient for IpfsClientPool {
-    fn metrics(&self) -> &IpfsMetrics {
-        // All clients are expected to share the same metrics.
-        self.clients[0].metrics()
+    /// Creates a new IPFS client pool that queries the specified clients in a fixed order
+    /// instead of racing them.
+    pub fn new_deterministic(clients: Vec<Arc<dyn IpfsClient>>) -> Self {
+        Self {
+            deterministic: true,
+            ..Self::new(clients)
+        }
     }
 
-    async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
+    /// Like [IpfsClient::call], but also returns the address of the server whose client
+    /// won the race and provided the response. The address is `None` if that client
+    /// does not connect to a single server.
+    pub async fn call_with_server(
+        self: Arc<Self>,
+        req: IpfsRequest,
+    ) -> IpfsResult<(IpfsResponse, Option<SafeDisplay<ServerAddress>>)> {
+        if self.deterministic {
+            return self.call_in_order(req).await;
+        }
+
         let mut futs = self
             .clients
             .iter()
-            .map(|client| client.clone().call(req.clone()))
+            .enumerate()
+            .map(|(idx, client)| {
+                client
+                    .clone()
+                    .call(req.clone())
+                    .map(move |result| result.map(|resp| (resp, idx)))
+            })
             .collect::<FuturesUnordered<_>>();
 
         let mut last_err = None;
 
         while let Some(result) = futs.next().await {
             match result {
-                Ok(resp) => return Ok(resp),
+                Ok((resp, idx)) => {
+                    let server_address = self.clients[idx].server_address().map(SafeDisplay);
+
+                    return Ok((resp, server_address));
+                }
+                // Prefer reporting other errors since another attempt might succeed
+                Err(err) if err.is_not_found() && last_err.is_some() => {}
                 Err(err) => last_err = Some(err),
             };
         }
 
+        Err(Self::no_response_error(req, last_err))
+    }
+
+    async fn call_in_order(
+        &self,
+        req: IpfsRequest,
+    ) -> IpfsResult<(IpfsResponse, Option<SafeDisplay<ServerAddress>>)> {
+        let mut last_err = None;
+
+        for client in &self.clients {
+            match client.clone().call(req.clone()).await {
+                Ok(resp) => {
+                    let server_address = client.server_address().map(SafeDisplay);
+
+                    return Ok((resp, server_address));
+                }
+                Err(err) if err.is_not_found() && last_err.is_some() => {}
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(Self::no_response_error(req, last_err))
+    }
+
+    fn no_response_error(req: IpfsRequest, last_err: Option<IpfsError>) -> IpfsError {
         let path = match req {
             IpfsRequest::Cat(path) => path,
+            IpfsRequest::CatRange(path, _) => path,
             IpfsRequest::GetBlock(path) => path,
+            IpfsRequest::Ls(path) => path,
+            IpfsRequest::CatIfNoneMatch(path, _) => path,
         };
 
-        let err = last_err.unwrap_or_else(|| IpfsError::ContentNotAvailable {
+        last_err.unwrap_or_else(|| IpfsError::ContentNotAvailable {
             path,
             reason: anyhow!("no clients can provide the content"),
-        });
+        })
+    }
+}
 
-        Err(err)
+#[async_trait]
+impl IpfsClient for IpfsClientPool {
+    fn metrics(&self) -> &IpfsMetrics {
+        // All clients are expected to share the same metrics.
+        self.clients[0].metrics()
+    }
+
+    async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
+        self.call_with_server(req).await.map(|(resp, _)| resp)
     }
 }
 
@@ -253,4 +331,151 @@ mod tests {
 
         assert_eq!(bytes.as_ref(), b"server_3")
     }
+
+    #[crate::test]
+    async fn cat_fails_fast_if_no_client_has_the_content() {
+        let (server_1, client_1) = make_client().await;
+        let (server_2, client_2) = make_client().await;
+
+        for server in [&server_1, &server_2] {
+            mock_get()
+                .respond_with(ResponseTemplate::new(StatusCode::NOT_FOUND))
+                .expect(1)
+                .mount(server)
+                .await;
+        }
+
+        let clients: Vec<Arc<dyn IpfsClient>> = vec![client_1, client_2];
+        let pool = Arc::new(IpfsClientPool::new(clients));
+
+        let err = pool
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::NonDeterministic,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(err.is_not_found());
+    }
+
+    #[crate::test]
+    async fn cat_uses_another_client_if_one_does_not_have_the_content() {
+        let (server_1, client_1) = make_client().await;
+        let (server_2, client_2) = make_client().await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::NOT_FOUND))
+            .expect(1)
+            .mount(&server_1)
+            .await;
+
+        mock_get()
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .set_body_bytes(b"server_2")
+                    .set_delay(ms(100)),
+            )
+            .expect(1)
+            .mount(&server_2)
+            .await;
+
+        let clients: Vec<Arc<dyn IpfsClient>> = vec![client_1, client_2];
+        let pool = Arc::new(IpfsClientPool::new(clients));
+
+        let bytes = pool
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::NonDeterministic,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"server_2");
+    }
+
+    #[crate::test]
+    async fn call_with_server_returns_the_address_of_the_client_with_the_content() {
+        let (server_1, client_1) = make_client().await;
+        let (server_2, client_2) = make_client().await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::NOT_FOUND))
+            .expect(1)
+            .mount(&server_1)
+            .await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"server_2"))
+            .expect(1)
+            .mount(&server_2)
+            .await;
+
+        let clients: Vec<Arc<dyn IpfsClient>> = vec![client_1, client_2];
+        let pool = Arc::new(IpfsClientPool::new(clients));
+
+        let (resp, server_address) = pool
+            .call_with_server(IpfsRequest::Cat(make_path()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server_address.unwrap().to_string(),
+            ServerAddress::new(server_2.uri()).unwrap().to_string()
+        );
+        assert_eq!(resp.bytes(None).await.unwrap().as_ref(), b"server_2");
+    }
+
+    #[crate::test]
+    async fn deterministic_mode_selects_the_first_client_that_has_the_content() {
+        let (server_1, client_1) = make_client().await;
+        let (server_2, client_2) = make_client().await;
+        let (server_3, client_3) = make_client().await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::NOT_FOUND))
+            .expect(3)
+            .mount(&server_1)
+            .await;
+
+        // Slower than the third server, but still selected.
+        mock_get()
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .set_body_bytes(b"server_2")
+                    .set_delay(ms(300)),
+            )
+            .expect(3)
+            .mount(&server_2)
+            .await;
+
+        mock_get()
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"server_3"))
+            .expect(0)
+            .mount(&server_3)
+            .await;
+
+        let clients: Vec<Arc<dyn IpfsClient>> = vec![client_1, client_2, client_3];
+        let pool = Arc::new(IpfsClientPool::new_deterministic(clients));
+
+        for _ in 0..3 {
+            let (resp, server_address) = pool
+                .clone()
+                .call_with_server(IpfsRequest::Cat(make_path()))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                server_address.unwrap().to_string(),
+                ServerAddress::new(server_2.uri()).unwrap().to_string()
+            );
+            assert_eq!(resp.bytes(None).await.unwrap().as_ref(), b"server_2");
+        }
+    }
 }
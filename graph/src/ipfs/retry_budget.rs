@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+
+use crate::env::ENV_VARS;
+
+lazy_static! {
+    static ref SHARED: Arc<RetryBudget> = Arc::new(match ENV_VARS.mappings.ipfs_retry_budget {
+        0 => RetryBudget::unlimited(),
+        capacity => RetryBudget::new(capacity, ENV_VARS.mappings.ipfs_retry_budget_refill_rate),
+    });
+}
+
+/// A token bucket that limits how often failed IPFS requests are retried.
+///
+/// Every retry takes a token from the bucket, and the bucket is refilled at
+/// a constant rate up to its capacity. Since the budget is shared by many
+/// requests, the total rate of retries stays bounded during an outage, even
+/// though each request on its own may be retried many times.
+#[derive(Debug)]
+pub struct RetryBudget {
+    /// `None` if the budget is unlimited
+    bucket: Option<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RetryBudget {
+    /// Creates a budget that allows bursts of `capacity` retries and
+    /// `refill_per_sec` retries per second after that.
+    pub fn new(capacity: usize, refill_per_sec: u32) -> Self {
+        Self {
+            bucket: Some(Mutex::new(Bucket {
+                capacity: capacity as f64,
+                refill_per_sec: refill_per_sec as f64,
+                tokens: capacity as f64,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Creates a budget that allows any number of retries.
+    pub fn unlimited() -> Self {
+        Self { bucket: None }
+    }
+
+    /// Returns the budget that is shared by all IPFS requests, configured
+    /// with `GRAPH_IPFS_RETRY_BUDGET` and `GRAPH_IPFS_RETRY_BUDGET_REFILL_RATE`.
+    pub fn shared() -> Arc<Self> {
+        SHARED.clone()
+    }
+
+    /// Takes a token for one retry from the budget. Returns `false` if the
+    /// budget is exhausted and the request should not be retried.
+    pub fn try_acquire(&self) -> bool {
+        let Some(bucket) = &self.bucket else {
+            return true;
+        };
+
+        let mut bucket = bucket.lock().unwrap();
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * bucket.refill_per_sec).min(bucket.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn unlimited_budget_is_never_exhausted() {
+        let budget = RetryBudget::unlimited();
+
+        assert!((0..10_000).all(|_| budget.try_acquire()));
+    }
+
+    #[test]
+    fn budget_is_exhausted_until_refilled() {
+        let budget = RetryBudget::new(3, 1000);
+
+        assert!((0..3).all(|_| budget.try_acquire()));
+        assert!(!budget.try_acquire());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(budget.try_acquire());
+    }
+
+    #[test]
+    fn budget_without_refill_stays_exhausted() {
+        let budget = RetryBudget::new(2, 0);
+
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!budget.try_acquire());
+    }
+}
@@ -1,12 +1,21 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use slog::Logger;
 
+use crate::ipfs::IpfsResult;
 use crate::ipfs::error::IpfsError;
+use crate::ipfs::retry_budget::RetryBudget;
 use crate::prelude::*;
 use crate::util::futures::RetryConfig;
 use crate::util::futures::retry;
 
 /// Describes retry behavior when IPFS requests fail.
-#[derive(Clone, Copy, Debug)]
+///
+/// Requests that fail because the server reported that it does not have the
+/// content are never retried, no matter the policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RetryPolicy {
     /// At the first error, immediately stops execution and returns the error.
     None,
@@ -29,14 +38,87 @@ impl RetryPolicy {
         retry(operation_name, logger)
             .limit(ENV_VARS.mappings.ipfs_max_attempts)
             .max_delay(ENV_VARS.ipfs_request_timeout)
-            .when(move |result: &Result<O, IpfsError>| match result {
-                Ok(_) => false,
-                Err(err) => match self {
-                    Self::None => false,
-                    Self::Networking => err.is_networking(),
-                    Self::NonDeterministic => !err.is_deterministic(),
-                },
+            .when(move |result: &Result<O, IpfsError>| self.should_retry(result))
+    }
+
+    /// Runs `try_it` and retries it according to this policy, where every
+    /// retry takes a token from the retry budget shared by all IPFS requests.
+    pub(super) fn run<N, O, F, R>(
+        self,
+        operation_name: N,
+        logger: &Logger,
+        try_it: F,
+    ) -> impl Future<Output = IpfsResult<O>> + use<N, O, F, R>
+    where
+        N: ToString,
+        O: Debug + Send + Sync + 'static,
+        F: Fn() -> R + Send + 'static,
+        R: Future<Output = IpfsResult<O>> + Send,
+    {
+        self.run_with_budget(operation_name, logger, RetryBudget::shared(), try_it)
+    }
+
+    /// Like `run`, but uses `budget` for retries. Once the budget is
+    /// exhausted, the request fails with [IpfsError::RetryBudgetExhausted]
+    /// instead of being retried.
+    pub(super) fn run_with_budget<N, O, F, R>(
+        self,
+        operation_name: N,
+        logger: &Logger,
+        budget: Arc<RetryBudget>,
+        try_it: F,
+    ) -> impl Future<Output = IpfsResult<O>> + use<N, O, F, R>
+    where
+        N: ToString,
+        O: Debug + Send + Sync + 'static,
+        F: Fn() -> R + Send + 'static,
+        R: Future<Output = IpfsResult<O>> + Send,
+    {
+        let exhausted = Arc::new(AtomicBool::new(false));
+
+        let fut = retry(operation_name, logger)
+            .limit(ENV_VARS.mappings.ipfs_max_attempts)
+            .max_delay(ENV_VARS.ipfs_request_timeout)
+            .when({
+                let exhausted = exhausted.clone();
+
+                move |result: &Result<O, IpfsError>| {
+                    if !self.should_retry(result) {
+                        return false;
+                    }
+                    if budget.try_acquire() {
+                        return true;
+                    }
+                    exhausted.store(true, Ordering::SeqCst);
+                    false
+                }
             })
+            .no_timeout()
+            .run(try_it);
+
+        async move {
+            fut.await.map_err(|err| {
+                if exhausted.load(Ordering::SeqCst) {
+                    IpfsError::RetryBudgetExhausted {
+                        last_error: Box::new(err),
+                    }
+                } else {
+                    err
+                }
+            })
+        }
+    }
+
+    fn should_retry<O>(self, result: &Result<O, IpfsError>) -> bool {
+        match result {
+            Ok(_) => false,
+            Err(err) if err.is_not_found() => false,
+            Err(err) => match self {
+                Self::None => false,
+                Self::Networking => err.is_networking(),
+                Self::NonDeterministic => !err.is_deterministic(),
+            },
+        }
     }
 }
 
@@ -182,6 +264,78 @@ mod tests {
         assert!(matches!(err, IpfsError::ContentTooLarge { .. }));
     }
 
+    #[crate::test]
+    async fn retry_policy_non_deterministic_does_not_retry_not_found() {
+        let counter = Arc::new(AtomicU64::new(0));
+
+        let err = RetryPolicy::NonDeterministic
+            .create::<()>("test", &discard())
+            .no_timeout()
+            .run({
+                let counter = counter.clone();
+                move || {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        Err(IpfsError::NotFound { path: path() })
+                    }
+                }
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(err.is_not_found());
+    }
+
+    #[crate::test]
+    async fn retry_budget_caps_retries_across_requests() {
+        let counter = Arc::new(AtomicU64::new(0));
+        let budget = Arc::new(RetryBudget::new(5, 0));
+
+        // Every request would retry up to `ipfs_max_attempts` times on its own
+        let requests = (0..3).map(|_| {
+            RetryPolicy::NonDeterministic.run_with_budget::<_, (), _, _>(
+                "test",
+                &discard(),
+                budget.clone(),
+                {
+                    let counter = counter.clone();
+                    move || {
+                        let counter = counter.clone();
+                        async move {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            Err(IpfsError::RequestTimeout { path: path() })
+                        }
+                    }
+                },
+            )
+        });
+        let results = futures03::future::join_all(requests).await;
+
+        // One attempt for each request, and one retry for each token
+        assert_eq!(counter.load(Ordering::SeqCst), 3 + 5);
+        for result in results {
+            let err = result.unwrap_err();
+            assert!(matches!(err, IpfsError::RetryBudgetExhausted { .. }));
+            assert!(!err.is_deterministic());
+        }
+    }
+
+    #[crate::test]
+    async fn retry_budget_does_not_change_errors_that_are_not_retried() {
+        let budget = Arc::new(RetryBudget::new(0, 0));
+
+        let err = RetryPolicy::NonDeterministic
+            .run_with_budget::<_, (), _, _>("test", &discard(), budget, || async {
+                Err(IpfsError::NotFound { path: path() })
+            })
+            .await
+            .unwrap_err();
+
+        assert!(err.is_not_found());
+    }
+
     #[crate::test]
     async fn retry_policy_non_deterministic_stops_on_success() {
         let counter = Arc::new(AtomicU64::new(0));
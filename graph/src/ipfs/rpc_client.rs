@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
@@ -9,6 +10,8 @@ use reqwest::StatusCode;
 use slog::Logger;
 
 use crate::env::ENV_VARS;
+use crate::ipfs::client::RangeHandling;
+use crate::ipfs::dns::DnsCache;
 use crate::ipfs::{
     IpfsClient, IpfsError, IpfsMetrics, IpfsRequest, IpfsResponse, IpfsResult, RetryPolicy,
     ServerAddress,
@@ -24,6 +27,10 @@ pub struct IpfsRpcClient {
     #[debug(skip)]
     http_client: reqwest::Client,
 
+    #[debug(skip)]
+    dns_cache: Option<DnsCache>,
+    connect_timeout: Duration,
+
     metrics: IpfsMetrics,
     logger: Logger,
 }
@@ -56,14 +63,54 @@ impl IpfsRpcClient {
         metrics: IpfsMetrics,
         logger: &Logger,
     ) -> IpfsResult<Self> {
+        let connect_timeout = ENV_VARS.mappings.ipfs_connect_timeout;
+
         Ok(Self {
             server_address: ServerAddress::new(server_address)?,
-            http_client: reqwest::Client::new(),
+            http_client: Self::http_client(None, connect_timeout)?,
+            dns_cache: None,
+            connect_timeout,
             metrics,
             logger: logger.to_owned(),
         })
     }
 
+    /// Makes the client resolve host names through the specified DNS cache.
+    pub fn with_dns_cache(self, dns_cache: DnsCache) -> IpfsResult<Self> {
+        Ok(Self {
+            http_client: Self::http_client(Some(dns_cache.clone()), self.connect_timeout)?,
+            dns_cache: Some(dns_cache),
+            ..self
+        })
+    }
+
+    /// Makes the client give up on connecting to the server after the
+    /// specified amount of time. This does not limit how long it takes
+    /// the server to respond once the connection is established.
+    pub fn with_connect_timeout(self, connect_timeout: Duration) -> IpfsResult<Self> {
+        Ok(Self {
+            http_client: Self::http_client(self.dns_cache.clone(), connect_timeout)?,
+            connect_timeout,
+            ..self
+        })
+    }
+
+    fn http_client(
+        dns_cache: Option<DnsCache>,
+        connect_timeout: Duration,
+    ) -> IpfsResult<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .gzip(true)
+            .brotli(true)
+            .connect_timeout(connect_timeout);
+
+        if let Some(dns_cache) = dns_cache {
+            builder = builder.dns_resolver(Arc::new(dns_cache));
+        }
+
+        Ok(builder.build()?)
+    }
+
     /// A one-time request sent at client initialization to verify that the specified
     /// server address is a valid IPFS RPC server.
     async fn send_test_request(&self) -> anyhow::Result<()> {
@@ -120,17 +167,54 @@ impl IpfsClient for IpfsRpcClient {
         &self.metrics
     }
 
+    fn server_address(&self) -> Option<ServerAddress> {
+        Some(self.server_address.clone())
+    }
+
     async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
         use IpfsRequest::*;
 
-        let (path_and_query, path) = match req {
-            Cat(path) => (format!("cat?arg={path}"), path),
-            GetBlock(path) => (format!("block/get?arg={path}"), path),
+        // The RPC API does not confirm that it honored `offset` and `length`.
+        let (path_and_query, path, range) = match req {
+            // The RPC API does not support conditional requests and always sends the
+            // content, which is a valid answer to them.
+            Cat(path) | CatIfNoneMatch(path, _) => {
+                (format!("cat?arg={path}"), path, RangeHandling::Ignored)
+            }
+            CatRange(path, range) => (
+                format!(
+                    "cat?arg={path}&offset={}&length={}",
+                    range.start,
+                    range.end.saturating_sub(range.start)
+                ),
+                path,
+                RangeHandling::Unknown,
+            ),
+            GetBlock(path) => (
+                format!("block/get?arg={path}"),
+                path,
+                RangeHandling::Ignored,
+            ),
+            // Unlike `ls`, this tells files that fit into a single block
+            // apart from empty directories
+            Ls(path) => (
+                format!("dag/get?arg={path}&output-codec=dag-json"),
+                path,
+                RangeHandling::Ignored,
+            ),
         };
 
-        let response = self.send_request(path_and_query).await?;
+        let response = self
+            .send_request(path_and_query)
+            .await
+            .map_err(|err| err.not_found_for(&path))?;
 
-        Ok(IpfsResponse { path, response })
+        Ok(IpfsResponse {
+            path,
+            response,
+            range,
+            chunk_timeout: None,
+        })
     }
 }
 
@@ -180,6 +264,14 @@ mod tests {
         ContentPath::new(CID).unwrap()
     }
 
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
     fn ms(millis: u64) -> Duration {
         Duration::from_millis(millis)
     }
@@ -386,6 +478,69 @@ mod tests {
             .unwrap_err();
     }
 
+    #[crate::test]
+    async fn cat_decodes_compressed_content() {
+        let (server, client) = make_client().await;
+
+        mock_cat()
+            .and(m::header_regex("accept-encoding", "gzip"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(gzip(b"some data")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = client
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"some data");
+    }
+
+    #[crate::test]
+    async fn cat_fails_if_decompressed_content_is_too_large() {
+        let (server, client) = make_client().await;
+
+        let data = vec![0u8; 16 * 1024 * 1024];
+        let compressed = gzip(&data);
+        let max_size = 1024 * 1024;
+
+        assert!(compressed.len() < max_size);
+
+        mock_cat()
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(compressed),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .cat(
+                &IpfsContext::test(),
+                &make_path(),
+                max_size,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, IpfsError::ContentTooLarge { .. }));
+    }
+
     #[crate::test]
     async fn cat_fails_on_timeout() {
         let (server, client) = make_client().await;
@@ -439,6 +594,56 @@ mod tests {
         assert_eq!(bytes.as_ref(), b"some data");
     }
 
+    #[crate::test]
+    async fn cat_range_returns_the_range_if_the_server_honors_it() {
+        let (server, client) = make_client().await;
+
+        mock_cat()
+            .and(m::query_param("offset", "2"))
+            .and(m::query_param("length", "4"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"me d"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = client
+            .cat_range(
+                &IpfsContext::test(),
+                &make_path(),
+                2..6,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(bytes.as_ref(), b"me d");
+    }
+
+    #[crate::test]
+    async fn cat_range_fails_if_the_server_ignores_the_range() {
+        let (server, client) = make_client().await;
+
+        mock_cat()
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_bytes(b"some data"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .cat_range(
+                &IpfsContext::test(),
+                &make_path(),
+                2..6,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, IpfsError::RangeNotSupported { .. }));
+    }
+
     #[crate::test]
     async fn get_block_returns_the_block_content() {
         let (server, client) = make_client().await;
@@ -457,6 +662,53 @@ mod tests {
         assert_eq!(bytes.as_ref(), b"some data");
     }
 
+    #[crate::test]
+    async fn ls_lists_the_directory_entries() {
+        let (server, client) = make_client().await;
+
+        mock_post("dag/get")
+            .and(m::query_param("arg", CID))
+            .and(m::query_param("output-codec", "dag-json"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_string(
+                r#"{"Data":{"/":{"bytes":"CAE"}},"Links":[{"Hash":{"/":"QmPZ9gcCEpqKTo6aq61g2nXGUhM4iCL3ewB6LDXZCtioEB"},"Name":"a.json","Tsize":20}]}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let entries = client
+            .ls(&IpfsContext::test(), &make_path(), None, RetryPolicy::None)
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "a.json");
+        assert_eq!(entries[0].size, 20);
+    }
+
+    #[crate::test]
+    async fn ls_fails_for_a_file() {
+        let (server, client) = make_client().await;
+
+        // `CAIYBA` is a UnixFS file of 4 bytes that fits into one block
+        mock_post("dag/get")
+            .and(m::query_param("arg", CID))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .set_body_string(r#"{"Data":{"/":{"bytes":"CAIYBA"}},"Links":[]}"#),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .ls(&IpfsContext::test(), &make_path(), None, RetryPolicy::None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, IpfsError::NotADirectory { .. }));
+    }
+
     #[crate::test]
     async fn get_block_fails_on_timeout() {
         let (server, client) = make_client().await;
@@ -0,0 +1,254 @@
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures03::stream::BoxStream;
+use slog::Logger;
+use slog::info;
+
+use crate::cheap_clone::CheapClone;
+use crate::ipfs::{
+    ContentPath, DirEntry, IpfsClient, IpfsContext, IpfsMetrics, IpfsRequest, IpfsResponse,
+    IpfsResult, RetryPolicy, Revalidated, ServerAddress, new_ipfs_client_with_metrics,
+};
+
+/// An IPFS client whose set of IPFS servers can be replaced while the
+/// client is in use.
+///
+/// Every request is sent to the client that is current when the request
+/// starts; requests that are in flight when the servers are swapped finish
+/// on the old set of servers.
+pub struct SwappableIpfsClient {
+    client: ArcSwap<Arc<dyn IpfsClient>>,
+    metrics: IpfsMetrics,
+}
+
+impl SwappableIpfsClient {
+    /// Creates a client that initially sends requests to `client`. Clients
+    /// that are swapped in later report to the metrics of `client`.
+    pub fn new(client: Arc<dyn IpfsClient>) -> Self {
+        let metrics = client.metrics().cheap_clone();
+
+        Self {
+            client: ArcSwap::from_pointee(client),
+            metrics,
+        }
+    }
+
+    /// Returns the client that new requests are sent to.
+    pub fn current(&self) -> Arc<dyn IpfsClient> {
+        (**self.client.load()).cheap_clone()
+    }
+
+    /// Sends all new requests to `client`.
+    pub fn swap(&self, client: Arc<dyn IpfsClient>) {
+        self.client.store(Arc::new(client));
+    }
+
+    /// Connects to `server_addresses` the same way [new_ipfs_client](super::new_ipfs_client)
+    /// does, and sends all new requests to those servers.
+    ///
+    /// If any of the servers can not be used, an error is returned and
+    /// requests keep going to the current servers.
+    pub async fn swap_servers<I, S>(&self, server_addresses: I, logger: &Logger) -> IpfsResult<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let client =
+            new_ipfs_client_with_metrics(server_addresses, self.metrics.cheap_clone(), logger)
+                .await?;
+
+        info!(logger, "Swapping in new IPFS servers");
+        self.swap(client);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IpfsClient for SwappableIpfsClient {
+    fn metrics(&self) -> &IpfsMetrics {
+        &self.metrics
+    }
+
+    fn server_address(&self) -> Option<ServerAddress> {
+        self.current().server_address()
+    }
+
+    async fn call(self: Arc<Self>, req: IpfsRequest) -> IpfsResult<IpfsResponse> {
+        self.current().call(req).await
+    }
+
+    async fn cat_stream(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<BoxStream<'static, IpfsResult<Bytes>>> {
+        self.current()
+            .cat_stream(ctx, path, timeout, retry_policy)
+            .await
+    }
+
+    async fn cat(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        max_size: usize,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Bytes> {
+        self.current()
+            .cat(ctx, path, max_size, timeout, retry_policy)
+            .await
+    }
+
+    async fn cat_if_none_match(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        etag: Option<&str>,
+        max_size: usize,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Revalidated> {
+        self.current()
+            .cat_if_none_match(ctx, path, etag, max_size, timeout, retry_policy)
+            .await
+    }
+
+    async fn cat_range(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        range: Range<u64>,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Bytes> {
+        self.current()
+            .cat_range(ctx, path, range, timeout, retry_policy)
+            .await
+    }
+
+    async fn get_block(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Bytes> {
+        self.current()
+            .get_block(ctx, path, timeout, retry_policy)
+            .await
+    }
+
+    async fn ls(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        path: &ContentPath,
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> IpfsResult<Vec<DirEntry>> {
+        self.current().ls(ctx, path, timeout, retry_policy).await
+    }
+
+    async fn warm_cache(
+        self: Arc<Self>,
+        ctx: &IpfsContext,
+        paths: &[ContentPath],
+        timeout: Option<Duration>,
+        retry_policy: RetryPolicy,
+    ) -> Vec<(ContentPath, IpfsResult<()>)> {
+        self.current()
+            .warm_cache(ctx, paths, timeout, retry_policy)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers as m;
+
+    use super::*;
+    use crate::ipfs::IpfsGatewayClient;
+    use crate::log::discard;
+
+    const CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+    async fn gateway(body: &'static [u8]) -> (MockServer, Arc<dyn IpfsClient>) {
+        let server = MockServer::start().await;
+
+        Mock::given(m::method("GET"))
+            .and(m::path(format!("/ipfs/{CID}")))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+            .mount(&server)
+            .await;
+
+        let client =
+            IpfsGatewayClient::new_unchecked(server.uri(), IpfsMetrics::test(), &discard())
+                .unwrap();
+
+        (server, Arc::new(client))
+    }
+
+    async fn cat(client: &Arc<SwappableIpfsClient>) -> IpfsResult<Bytes> {
+        client
+            .cheap_clone()
+            .cat(
+                &IpfsContext::test(),
+                &ContentPath::new(CID).unwrap(),
+                usize::MAX,
+                None,
+                RetryPolicy::None,
+            )
+            .await
+    }
+
+    #[crate::test]
+    async fn swapping_changes_which_servers_serve_new_requests() {
+        let (_old_server, old) = gateway(b"old").await;
+        let (_new_server, new) = gateway(b"new").await;
+
+        let client = Arc::new(SwappableIpfsClient::new(old));
+        assert_eq!(cat(&client).await.unwrap().as_ref(), b"old");
+
+        client.swap(new);
+        assert_eq!(cat(&client).await.unwrap().as_ref(), b"new");
+    }
+
+    #[crate::test]
+    async fn server_address_is_the_one_of_the_current_servers() {
+        let (old_server, old) = gateway(b"old").await;
+        let (new_server, new) = gateway(b"new").await;
+
+        let client = SwappableIpfsClient::new(old);
+        let address = client.server_address().unwrap();
+        assert!(address.to_string().starts_with(&old_server.uri()));
+
+        client.swap(new);
+        let address = client.server_address().unwrap();
+        assert!(address.to_string().starts_with(&new_server.uri()));
+    }
+
+    #[crate::test]
+    async fn invalid_servers_do_not_replace_the_current_ones() {
+        let (_server, old) = gateway(b"old").await;
+        let client = Arc::new(SwappableIpfsClient::new(old));
+
+        let no_servers: [&str; 0] = [];
+        client
+            .swap_servers(no_servers, &discard())
+            .await
+            .unwrap_err();
+        client.swap_servers([""], &discard()).await.unwrap_err();
+
+        assert_eq!(cat(&client).await.unwrap().as_ref(), b"old");
+    }
+}
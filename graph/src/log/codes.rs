@@ -6,6 +6,7 @@ pub enum LogCode {
     SubgraphSyncingFailureNotRecorded,
     BlockIngestionStatus,
     BlockIngestionLagging,
+    BlockIngestionStalled,
     GraphQlQuerySuccess,
     GraphQlQueryFailure,
     TokioContention,
@@ -19,6 +20,7 @@ impl Display for LogCode {
             LogCode::SubgraphSyncingFailureNotRecorded => "SubgraphSyncingFailureNotRecorded",
             LogCode::BlockIngestionStatus => "BlockIngestionStatus",
             LogCode::BlockIngestionLagging => "BlockIngestionLagging",
+            LogCode::BlockIngestionStalled => "BlockIngestionStalled",
             LogCode::GraphQlQuerySuccess => "GraphQLQuerySuccess",
             LogCode::GraphQlQueryFailure => "GraphQLQueryFailure",
             LogCode::TokioContention => "TokioContention",
@@ -263,10 +263,17 @@ pub enum IndexForAscTypeId {
     ArrayB256 = 1002,
     ArrayLog = 1003,
     ArrayTypedMapStringStoreValue = 1004,
+    FeeHistoryCall = 1005,
+    FeeHistory = 1006,
+    ArrayBigInt = 1007,
+    GetLogsCall = 1008,
+    ArrayArrayB256 = 1009,
+    SmartContractCallAtBlock = 1010,
+    ArrayArrayBigInt = 1011,
     // Continue to add more Ethereum type IDs here.
     // e.g.:
-    // NextEthereumType = 1004,
-    // AnotherEthereumType = 1005,
+    // NextEthereumType = 1012,
+    // AnotherEthereumType = 1013,
     // ...
     // LastEthereumType = 1499,
 
@@ -1186,7 +1186,8 @@ fn block_argument() -> s::InputValue {
             "The block at which the query should be executed. \
              Can either be a `{ hash: Bytes }` value containing a block hash, \
              a `{ number: Int }` containing the block number, \
-             or a `{ number_gte: Int }` containing the minimum block number. \
+             a `{ number_gte: Int }` containing the minimum block number, \
+             or a `{ cursor: String }` containing a Firehose cursor the subgraph has processed. \
              In the case of `number_gte`, the query will be executed on the latest block only if \
              the subgraph has progressed to or past the minimum block number. \
              Defaults to the latest block when omitted."
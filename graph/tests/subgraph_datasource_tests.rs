@@ -9,7 +9,7 @@ use graph::{
         },
         mock::MockTriggersAdapter,
     },
-    components::store::SourceableStore,
+    components::store::{ChangelogCursor, ChangelogEntry, SourceableStore},
     data_source::CausalityRegion,
     prelude::{BlockHash, BlockNumber, BlockPtr, DeploymentHash, StoreError, Value},
     schema::{EntityType, InputSchema},
@@ -91,6 +91,16 @@ impl SourceableStore for MockSourcableStore {
             .collect())
     }
 
+    async fn get_changelog(
+        &self,
+        _causality_region: CausalityRegion,
+        _block_range: Range<BlockNumber>,
+        _after: Option<ChangelogCursor>,
+        _limit: usize,
+    ) -> Result<Vec<ChangelogEntry>, StoreError> {
+        unimplemented!()
+    }
+
     fn input_schema(&self) -> InputSchema {
         self.schema.clone()
     }
@@ -189,8 +199,10 @@ async fn test_triggers_adapter_with_entities() {
     let filter = SubgraphFilter {
         subgraph: id,
         start_block: 0,
+        end_block: None,
         entities: vec!["User".to_string()], // Only monitoring User entities
         manifest_idx: 0,
+        include_deletions: false,
     };
 
     let logger = Logger::root(slog::Discard, slog::o!());
@@ -262,3 +274,188 @@ async fn test_triggers_adapter_with_entities() {
         "Block 3 should have no triggers but be included as it's the last block"
     );
 }
+
+#[graph::test]
+async fn test_triggers_adapter_with_deletions() {
+    let id = DeploymentHash::new("test_deployment").unwrap();
+    let schema = InputSchema::parse_latest(
+        r#"
+        type User @entity {
+            id: String!
+            name: String!
+        }
+        "#,
+        id.clone(),
+    )
+    .unwrap();
+
+    let user = schema
+        .make_entity(vec![
+            ("id".into(), Value::String("user1".to_owned())),
+            ("name".into(), Value::String("Alice".to_owned())),
+        ])
+        .unwrap();
+    let user_type = schema.entity_type("User").unwrap();
+
+    let created = EntitySourceOperation {
+        entity_type: user_type.clone(),
+        entity: user.clone(),
+        entity_op: EntityOperationKind::Create,
+        vid: 1,
+    };
+    let deleted = EntitySourceOperation {
+        entity_type: user_type,
+        entity: user,
+        entity_op: EntityOperationKind::Delete,
+        vid: 1,
+    };
+
+    let mut entities = BTreeMap::new();
+    entities.insert(1, vec![created]);
+    entities.insert(2, vec![deleted]);
+
+    let block_hash = BlockHash(vec![0u8; 32].into_boxed_slice());
+    let store = Arc::new(MockSourcableStore::new(
+        entities,
+        schema.clone(),
+        Some(BlockPtr::new(block_hash, 0)),
+    ));
+
+    let adapter = Arc::new(MockTriggersAdapter {});
+    let wrapper = TriggersAdapterWrapper::new(adapter, vec![store]);
+    let logger = Logger::root(slog::Discard, slog::o!());
+
+    let triggers_per_block = |include_deletions| {
+        let filter = SubgraphFilter {
+            subgraph: id.clone(),
+            start_block: 0,
+            end_block: None,
+            entities: vec!["User".to_string()],
+            manifest_idx: 0,
+            include_deletions,
+        };
+        let wrapper = &wrapper;
+        let logger = &logger;
+
+        async move {
+            wrapper
+                .blocks_with_subgraph_triggers(
+                    logger,
+                    &[filter],
+                    SubgraphTriggerScanRange::Range(1, 3),
+                )
+                .await
+                .unwrap()
+                .iter()
+                .map(|block| (block.block.number(), block.trigger_data.len()))
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Without the flag, the deletion in block 2 does not produce a trigger
+    assert_eq!(triggers_per_block(false).await, vec![(1, 1), (3, 0)]);
+
+    // With the flag, it does
+    assert_eq!(triggers_per_block(true).await, vec![(1, 1), (2, 1), (3, 0)]);
+
+    let blocks = wrapper
+        .blocks_with_subgraph_triggers(
+            &logger,
+            &[SubgraphFilter {
+                subgraph: id.clone(),
+                start_block: 0,
+                end_block: None,
+                entities: vec!["User".to_string()],
+                manifest_idx: 0,
+                include_deletions: true,
+            }],
+            SubgraphTriggerScanRange::Range(2, 3),
+        )
+        .await
+        .unwrap();
+
+    match &blocks[0].trigger_data[0] {
+        Trigger::Subgraph(trigger_data) => {
+            assert_eq!(trigger_data.entity.entity_op, EntityOperationKind::Delete)
+        }
+        _ => panic!("Expected subgraph trigger"),
+    }
+}
+
+#[graph::test]
+async fn test_triggers_adapter_stops_at_end_block() {
+    let id = DeploymentHash::new("test_deployment").unwrap();
+    let schema = InputSchema::parse_latest(
+        r#"
+        type User @entity {
+            id: String!
+            name: String!
+        }
+        "#,
+        id.clone(),
+    )
+    .unwrap();
+    let user_type = schema.entity_type("User").unwrap();
+
+    let mut entities = BTreeMap::new();
+    for (vid, block) in [(1, 1), (2, 2), (3, 3)] {
+        let user = schema
+            .make_entity(vec![
+                ("id".into(), Value::String(format!("user{vid}"))),
+                ("name".into(), Value::String("Alice".to_owned())),
+            ])
+            .unwrap();
+        let op = EntitySourceOperation {
+            entity_type: user_type.clone(),
+            entity: user,
+            entity_op: EntityOperationKind::Create,
+            vid,
+        };
+        entities.insert(block, vec![op]);
+    }
+
+    let block_hash = BlockHash(vec![0u8; 32].into_boxed_slice());
+    let store = Arc::new(MockSourcableStore::new(
+        entities,
+        schema.clone(),
+        Some(BlockPtr::new(block_hash, 0)),
+    ));
+
+    let adapter = Arc::new(MockTriggersAdapter {});
+    let wrapper = TriggersAdapterWrapper::new(adapter, vec![store]);
+    let logger = Logger::root(slog::Discard, slog::o!());
+
+    let filter = SubgraphFilter {
+        subgraph: id,
+        start_block: 0,
+        end_block: Some(2),
+        entities: vec!["User".to_string()],
+        manifest_idx: 0,
+        include_deletions: false,
+    };
+    assert!(!filter.has_expired(2));
+    assert!(filter.has_expired(3));
+
+    let blocks = wrapper
+        .blocks_with_subgraph_triggers(
+            &logger,
+            std::slice::from_ref(&filter),
+            SubgraphTriggerScanRange::Range(1, 5),
+        )
+        .await
+        .unwrap();
+    let triggers: Vec<_> = blocks
+        .iter()
+        .map(|block| (block.block.number(), block.trigger_data.len()))
+        .collect();
+
+    // Block 3 has an entity change but is past the end block
+    assert_eq!(triggers, vec![(1, 1), (2, 1), (5, 0)]);
+
+    // Scanning entirely past the end block produces no triggers
+    let blocks = wrapper
+        .blocks_with_subgraph_triggers(&logger, &[filter], SubgraphTriggerScanRange::Range(3, 5))
+        .await
+        .unwrap();
+    assert!(blocks.iter().all(|block| block.trigger_data.is_empty()));
+}
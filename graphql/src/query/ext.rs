@@ -61,6 +61,9 @@ pub enum BlockConstraint {
     /// Execute the query on the latest block only if the the subgraph has progressed to or past the
     /// given block number.
     Min(BlockNumber),
+    /// Execute the query at the block the subgraph had processed when it
+    /// wrote the given Firehose cursor
+    Cursor(String),
     #[default]
     Latest,
 }
@@ -72,7 +75,7 @@ impl BlockConstraint {
         use BlockConstraint::*;
         match self {
             Hash(hash) => Some(hash),
-            Number(_) | Min(_) | Latest => None,
+            Number(_) | Min(_) | Cursor(_) | Latest => None,
         }
     }
 }
@@ -96,6 +99,8 @@ impl TryFromValue for BlockConstraint {
             Ok(BlockConstraint::Min(BlockNumber::try_from_value(
                 number_value,
             )?))
+        } else if let Some(cursor) = map.get("cursor") {
+            Ok(BlockConstraint::Cursor(String::try_from_value(cursor)?))
         } else {
             Err(anyhow!("invalid `BlockConstraint`"))
         }
@@ -2,6 +2,7 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use graph::blockchain::FirehoseCursor;
 use graph::components::graphql::GraphQLMetrics as _;
 use graph::components::store::QueryPermit;
 use graph::data::graphql::load_manager::LoadManager;
@@ -143,6 +144,22 @@ impl StoreResolver {
                     }
                     ptr
                 }
+                BlockConstraint::Cursor(cursor) => {
+                    let cursor = FirehoseCursor::from(cursor);
+                    let Some(ptr) = store
+                        .block_for_firehose_cursor(&cursor)
+                        .await
+                        .map_err(QueryExecutionError::from)?
+                    else {
+                        return Err(QueryExecutionError::ValueParseError(
+                            "block.cursor".to_owned(),
+                            "no block for that cursor found".to_owned(),
+                        )
+                        .into());
+                    };
+                    block_queryable(state, ptr.number)?;
+                    ptr
+                }
                 BlockConstraint::Latest => state.latest_block.cheap_clone(),
             };
             ptrs_and_sels.push((ptr, sel));
@@ -344,6 +344,21 @@ pub enum Command {
         directory: String,
     },
 
+    /// Export the current entities of one type of a deployment
+    ///
+    /// Print the current version of every entity of the given type as one
+    /// JSON object per line, ordered by id. Entities are loaded in batches
+    /// so that even large tables can be exported.
+    Export {
+        /// The deployment (see `help info`)
+        deployment: DeploymentSearch,
+        /// The entity type to export
+        entity: String,
+        /// How many entities to load from the database at a time
+        #[clap(long, short, default_value = "1000")]
+        batch_size: usize,
+    },
+
     /// Restore a subgraph deployment from a dump directory
     ///
     /// EXPERIMENTAL - NOT FOR PRODUCTION USE
@@ -553,6 +568,39 @@ pub enum ChainCommand {
         #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new())]
         chain_name: String,
     },
+    /// Compares the cached blocks with a given number with the block the
+    /// provider returns for that number and shows how they differ.
+    ///
+    /// Reports differences in the block hash, parent hash, transaction
+    /// count and timestamp. This never changes the block cache.
+    DiffBlock {
+        /// Chain name (must be an existing chain, see 'chain list')
+        #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        chain_name: String,
+        /// The block number to compare
+        number: BlockNumber,
+    },
+    /// Replays a reorg between two cached blocks the way a block stream
+    /// would, and prints the resulting reverts and applied blocks.
+    ///
+    /// Only uses blocks from the block cache and never contacts a
+    /// provider. For each applied block, the block trigger and, if its
+    /// receipts are cached, the log triggers are shown in the order a
+    /// subgraph would see them.
+    ReplayReorg {
+        /// Chain name (must be an existing chain, see 'chain list')
+        #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        chain_name: String,
+        /// The hash of the chain head before the reorg
+        #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        old_head: String,
+        /// The hash of the chain head after the reorg
+        #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        new_head: String,
+        /// How far below the heads to look for their common ancestor
+        #[clap(long, default_value = "250")]
+        max_depth: BlockNumber,
+    },
     /// Truncates the whole block cache for the given chain.
     Truncate {
         /// Chain name (must be an existing chain, see 'chain list')
@@ -656,10 +704,66 @@ pub enum StatsCommand {
     /// Show how many distinct entities and how many versions the tables of
     /// each subgraph have. The data is based on the statistics that
     /// Postgres keeps, and only refreshed when a table is analyzed.
+    ///
+    /// Also show which index on the block range each table should use.
+    /// That recommendation is only advice; use `account-like` to make
+    /// queries use the BRIN index.
     Show {
         /// The deployment (see `help info`).
         deployment: DeploymentSearch,
     },
+    /// Count the entities in the tables of a deployment
+    ///
+    /// Unlike `show`, this counts the current version of each entity
+    /// exactly, ignoring older versions. Since every current row has to be
+    /// looked at, this can take a while for large deployments.
+    Count {
+        /// The deployment (see `help info`).
+        deployment: DeploymentSearch,
+    },
+    /// Check the immutable tables of a deployment for corruption
+    ///
+    /// Entities of immutable types are never updated, and each of them
+    /// should therefore have exactly one version. List any entity that has
+    /// more than one version, together with the blocks at which they were
+    /// written. This looks at every row of every immutable table.
+    CheckImmutable {
+        /// The deployment (see `help info`).
+        deployment: DeploymentSearch,
+    },
+    /// Check the mutable tables of a deployment for corruption
+    ///
+    /// An entity can only have one version at any block, and the block
+    /// ranges of its versions should therefore never overlap. List any
+    /// pair of versions of the same entity whose block ranges overlap.
+    /// This joins every mutable table with itself and can take a long
+    /// time for large deployments.
+    CheckBlockRanges {
+        /// The deployment (see `help info`).
+        deployment: DeploymentSearch,
+    },
+    /// Show how often the tables of a deployment are written to
+    ///
+    /// Count the entity versions that were created in each mutable table
+    /// during the last `window` blocks that the deployment processed.
+    /// Tables that get at least `min-rate` versions per block on average,
+    /// whose ratio of distinct entities to versions in that window is at
+    /// most `max-ratio`, and that are not account-like yet are flagged as
+    /// candidates for `account-like`. The flag is only advice.
+    WriteRates {
+        /// The number of blocks before the deployment head to look at
+        #[clap(long, short, default_value = "10000")]
+        window: BlockNumber,
+        /// The number of versions per block at which a table is flagged
+        #[clap(long, short, default_value = "1")]
+        min_rate: f64,
+        /// The largest ratio of entities to versions at which a table is
+        /// flagged; 0.01 means that each entity has 100 versions on average
+        #[clap(long, short = 'r', default_value = "0.01")]
+        max_ratio: f64,
+        /// The deployment (see `help info`).
+        deployment: DeploymentSearch,
+    },
     /// Perform a SQL ANALYZE in a Entity table
     Analyze {
         /// The deployment (see `help info`).
@@ -1161,6 +1265,10 @@ async fn main() -> anyhow::Result<()> {
     );
 
     let mut config = Cfg::load(&logger, &opt.clone().into()).context("Configuration error")?;
+    config
+        .chains
+        .register_kind_aliases()
+        .context("Configuration error")?;
     if opt.pool_size > 0 && !opt.cmd.use_configured_pool_size() {
         // Override pool size from configuration
         for shard in config.stores.values_mut() {
@@ -1548,6 +1656,28 @@ async fn main() -> anyhow::Result<()> {
                         }
                     }
                 }
+                DiffBlock { chain_name, number } => {
+                    let logger = ctx.logger.clone();
+                    let (chain_store, ethereum_adapter) =
+                        ctx.chain_store_and_adapter(&chain_name).await?;
+                    commands::check_blocks::diff_by_number(
+                        number,
+                        chain_store,
+                        &ethereum_adapter,
+                        &logger,
+                    )
+                    .await
+                }
+                ReplayReorg {
+                    chain_name,
+                    old_head,
+                    new_head,
+                    max_depth,
+                } => {
+                    let chain_store = ctx.chain_store(&chain_name).await?;
+                    commands::chain::replay_reorg(chain_store, &old_head, &new_head, max_depth)
+                        .await
+                }
                 Truncate { chain_name, force } => {
                     use commands::check_blocks::truncate;
                     let chain_store = ctx.chain_store(&chain_name).await?;
@@ -1615,6 +1745,41 @@ async fn main() -> anyhow::Result<()> {
                     .await
                 }
                 Show { deployment } => commands::stats::show(ctx.pools().await, &deployment).await,
+                Count { deployment } => {
+                    let (store, primary_pool) = ctx.store_and_primary().await;
+                    let subgraph_store = store.subgraph_store();
+                    commands::stats::count(subgraph_store, primary_pool, &deployment).await
+                }
+                CheckImmutable { deployment } => {
+                    let (store, primary_pool) = ctx.store_and_primary().await;
+                    let subgraph_store = store.subgraph_store();
+                    commands::stats::check_immutable(subgraph_store, primary_pool, &deployment)
+                        .await
+                }
+                CheckBlockRanges { deployment } => {
+                    let (store, primary_pool) = ctx.store_and_primary().await;
+                    let subgraph_store = store.subgraph_store();
+                    commands::stats::check_block_ranges(subgraph_store, primary_pool, &deployment)
+                        .await
+                }
+                WriteRates {
+                    window,
+                    min_rate,
+                    max_ratio,
+                    deployment,
+                } => {
+                    let (store, primary_pool) = ctx.store_and_primary().await;
+                    let subgraph_store = store.subgraph_store();
+                    commands::stats::write_rates(
+                        subgraph_store,
+                        primary_pool,
+                        &deployment,
+                        window,
+                        min_rate,
+                        max_ratio,
+                    )
+                    .await
+                }
                 Analyze { deployment, entity } => {
                     let (store, primary_pool) = ctx.store_and_primary().await;
                     let subgraph_store = store.subgraph_store();
@@ -1796,6 +1961,18 @@ async fn main() -> anyhow::Result<()> {
             commands::dump::run(subgraph_store, primary_pool, deployment, directory).await
         }
 
+        Export {
+            deployment,
+            entity,
+            batch_size,
+        } => {
+            let (store, primary_pool) = ctx.store_and_primary().await;
+            let subgraph_store = store.subgraph_store();
+
+            commands::export::run(subgraph_store, primary_pool, deployment, entity, batch_size)
+                .await
+        }
+
         Restore {
             directory,
             shard,
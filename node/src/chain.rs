@@ -25,7 +25,7 @@ use graph::prelude::MetricsRegistry;
 use graph::prelude::anyhow;
 use graph::slog::{Logger, debug, info, o, warn};
 use graph::url::Url;
-use graph_chain_ethereum::{self as ethereum, Transport};
+use graph_chain_ethereum::{self as ethereum, RequestTracing, Transport};
 use graph_store_postgres::{BlockStore, ChainHeadUpdateListener};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
@@ -161,9 +161,13 @@ impl From<ConfigChainSettings> for ChainSettings {
             get_logs_max_contracts,
             block_ingestor_max_concurrent_json_rpc_calls,
             genesis_block_number,
+            block_ingestor_stall_timeout,
+            native_token_symbol,
+            native_token_decimals,
         } = c;
         ChainSettings {
-            polling_interval,
+            // `ChainSection::validate` fills in the per-network default
+            polling_interval: polling_interval.unwrap_or(ENV_VARS.ingestor_polling_interval),
             json_rpc_timeout,
             request_retries,
             max_block_range_size,
@@ -174,6 +178,9 @@ impl From<ConfigChainSettings> for ChainSettings {
             get_logs_max_contracts,
             block_ingestor_max_concurrent_json_rpc_calls,
             genesis_block_number,
+            block_ingestor_stall_timeout,
+            native_token_symbol,
+            native_token_decimals,
         }
     }
 }
@@ -259,9 +266,12 @@ pub async fn create_ethereum_networks_for_chain(
                 &provider.label,
                 no_eip2718,
                 compression,
+                RequestTracing::from_env(&logger),
+                web3.methods.clone(),
+                web3.method_headers.clone(),
             ),
             Ipc => Transport::new_ipc(&web3.url).await,
-            Ws => Transport::new_ws(&web3.url).await,
+            Ws => Transport::new_ws(&web3.url, RequestTracing::from_env(&logger)).await,
         };
 
         let supports_eip_1898 = !web3.features.contains("no_eip1898");
@@ -415,7 +425,7 @@ pub async fn networks_as_chains(
                     Arc::new(adapter_selector),
                     Arc::new(EthereumRuntimeAdapterBuilder {}),
                     eth_adapters,
-                    ENV_VARS.reorg_threshold(),
+                    ENV_VARS.reorg_threshold_for(chain_id.as_str()),
                     true,
                     settings,
                 );
@@ -429,6 +439,7 @@ pub async fn networks_as_chains(
                     logger_factory.clone(),
                     chain_id.clone(),
                     chain_store.cheap_clone(),
+                    chain_head_update_listener.clone(),
                     firehose_endpoints,
                     metrics_registry.clone(),
                 );
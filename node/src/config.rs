@@ -1,6 +1,6 @@
 use graph::{
     anyhow::Error,
-    blockchain::BlockchainKind,
+    blockchain::{BlockDataFormat, BlockchainKind},
     components::{
         network_provider::{AmpChainNames, ChainName},
         store::BLOCK_CACHE_SIZE,
@@ -21,7 +21,7 @@ use graph::{
     },
 };
 use graph_chain_ethereum as ethereum;
-use graph_chain_ethereum::{Compression, NodeCapabilities};
+use graph_chain_ethereum::{Compression, MethodFilter, MethodHeaders, NodeCapabilities};
 use graph_store_postgres::{DeploymentPlacer, PRIMARY_SHARD, Shard as ShardName};
 
 use graph::http::{HeaderMap, Uri};
@@ -376,8 +376,13 @@ impl Shard {
             replica.validate(name == PRIMARY_SHARD.as_str(), &self.pool_size)?;
         }
 
-        let no_weight =
-            self.weight == 0 && self.replicas.values().all(|replica| replica.weight == 0);
+        // History replicas do not take part in the normal rotation, and
+        // their weights therefore do not count
+        let no_weight = self.weight == 0
+            && self
+                .replicas
+                .values()
+                .all(|replica| replica.history || replica.weight == 0);
         if no_weight {
             return Err(anyhow!(
                 "all weights for shard `{}` are 0; \
@@ -401,6 +406,7 @@ impl Shard {
                 connection: replace_host(postgres_url, host),
                 weight: opt.postgres_host_weights.get(i + 1).cloned().unwrap_or(1),
                 pool_size: pool_size.clone(),
+                history: false,
             };
             replicas.insert(format!("replica{}", i + 1), replica);
         }
@@ -516,6 +522,9 @@ pub struct Replica {
     pub weight: usize,
     #[serde(default)]
     pub pool_size: PoolSize,
+    /// Only use this replica for queries for historical blocks
+    #[serde(default)]
+    pub history: bool,
 }
 
 impl Replica {
@@ -540,34 +549,73 @@ pub struct ChainSection {
     /// the block cache
     #[serde(default = "default_cache_size")]
     pub cache_size: i32,
+    /// Additional names for blockchain kinds, e.g., to use the name of an
+    /// EVM-compatible network as the kind of its data sources
+    #[serde(default)]
+    pub kind_aliases: BTreeMap<String, BlockchainKind>,
 }
 
 impl ChainSection {
+    /// Make the `kind_aliases` from the config known to
+    /// `BlockchainKind::from_str`
+    pub fn register_kind_aliases(&self) -> Result<()> {
+        for (alias, kind) in &self.kind_aliases {
+            BlockchainKind::register_alias(alias, *kind)?;
+        }
+        Ok(())
+    }
+
     fn validate(&mut self) -> Result<()> {
         NodeId::new(&self.ingestor)
             .map_err(|node| anyhow!("invalid node id for ingestor {}", node))?;
-        let reorg_threshold = ENV_VARS.reorg_threshold();
-
-        if self.cache_size <= reorg_threshold {
-            return Err(anyhow!(
-                "default chains.cache_size ({}) must be greater than reorg_threshold ({})",
-                self.cache_size,
-                reorg_threshold
-            ));
-        }
 
         // Apply section-level cache_size as default for chains that
-        // don't set their own.
-        for chain in self.chains.values_mut() {
+        // don't set their own. It only needs to be large enough for the
+        // reorg threshold of those chains
+        for (name, chain) in self.chains.iter_mut() {
             if chain.cache_size == 0 {
+                let reorg_threshold = ENV_VARS.reorg_threshold_for(name);
+                if self.cache_size <= reorg_threshold {
+                    return Err(anyhow!(
+                        "default chains.cache_size ({}) must be greater than reorg_threshold ({}) of chain '{}'",
+                        self.cache_size,
+                        reorg_threshold,
+                        name
+                    ));
+                }
                 chain.cache_size = self.cache_size;
             }
         }
 
+        // Overrides like `ETHEREUM_REORG_THRESHOLD__<NETWORK>` can't tell
+        // apart chains whose names only differ in `-` and `_` or in case
+        let mut suffixes: BTreeMap<String, &str> = BTreeMap::new();
+        for name in self.chains.keys() {
+            let suffix = graph::env::network_suffix(name);
+            if let Some(prev) = suffixes.insert(suffix.clone(), name) {
+                return Err(anyhow!(
+                    "chains `{}` and `{}` both use `{}` for per-network environment variables",
+                    prev,
+                    name,
+                    suffix
+                ));
+            }
+        }
+
+        // Chains that don't set their own polling interval use the one
+        // from `ETHEREUM_POLLING_INTERVAL__<NETWORK>` if that is set
+        for (name, chain) in self.chains.iter_mut() {
+            chain
+                .settings
+                .polling_interval
+                .get_or_insert_with(|| ENV_VARS.ingestor_polling_interval_for(name));
+        }
+
         for (name, chain) in self.chains.iter_mut() {
             chain
                 .validate()
                 .map_err(|e| anyhow!("chain '{}': {}", name, e))?;
+            let reorg_threshold = ENV_VARS.reorg_threshold_for(name);
             if chain.cache_size <= reorg_threshold {
                 return Err(anyhow!(
                     "chain '{}': cache_size ({}) must be greater than reorg_threshold ({})",
@@ -625,6 +673,7 @@ impl ChainSection {
             chains,
             // When running without a config file, we do not prune the block cache
             cache_size: BLOCK_NUMBER_MAX,
+            kind_aliases: BTreeMap::new(),
         })
     }
 
@@ -696,6 +745,8 @@ impl ChainSection {
                         features,
                         headers: Default::default(),
                         rules: vec![],
+                        methods: MethodFilter::All,
+                        method_headers: MethodHeaders::default(),
                     }),
                 };
                 let entry = chains.entry(name.to_string()).or_insert_with(|| Chain {
@@ -704,7 +755,11 @@ impl ChainSection {
                     providers: vec![],
                     amp: None,
                     cache_size: 0,
-                    settings: ChainSettings::default(),
+                    block_data_format: None,
+                    settings: ChainSettings {
+                        polling_interval: Some(ENV_VARS.ingestor_polling_interval_for(name)),
+                        ..Default::default()
+                    },
                 });
                 entry.providers.push(provider);
             }
@@ -715,16 +770,19 @@ impl ChainSection {
 
 /// Per-chain settings. Flattened into `Chain` so all fields appear as top-level keys in
 /// `[chains.X]` TOML sections. Absent fields fall back to the corresponding `GRAPH_ETHEREUM_*` /
-/// `ETHEREUM_*` environment variable defaults via serde default functions — the same pattern as
-/// `polling_interval`.
+/// `ETHEREUM_*` environment variable defaults via serde default functions. `polling_interval` is
+/// the exception since its default depends on the name of the chain.
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct ChainSettings {
-    /// Set by `polling_interval` (milliseconds). Defaults to `ETHEREUM_POLLING_INTERVAL`.
+    /// Set by `polling_interval` (milliseconds). When it is not set,
+    /// `ChainSection::validate` fills in `ETHEREUM_POLLING_INTERVAL__<NETWORK>`
+    /// or `ETHEREUM_POLLING_INTERVAL`.
     #[serde(
-        default = "default_polling_interval",
-        deserialize_with = "deserialize_duration_millis"
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_opt_duration_millis"
     )]
-    pub polling_interval: Duration,
+    pub polling_interval: Option<Duration>,
     /// Set by `json_rpc_timeout` (seconds). Defaults to `GRAPH_ETHEREUM_JSON_RPC_TIMEOUT`.
     #[serde(
         default = "default_json_rpc_timeout",
@@ -758,12 +816,25 @@ pub struct ChainSettings {
     /// Defaults to `GRAPH_ETHEREUM_GENESIS_BLOCK_NUMBER`.
     #[serde(default = "default_genesis_block_number")]
     pub genesis_block_number: u64,
+    /// Set by `block_ingestor_stall_timeout` (seconds). Defaults to
+    /// `GRAPH_ETHEREUM_BLOCK_INGESTOR_STALL_TIMEOUT`.
+    #[serde(
+        default = "default_block_ingestor_stall_timeout",
+        deserialize_with = "deserialize_duration_secs"
+    )]
+    pub block_ingestor_stall_timeout: Duration,
+    /// The symbol of the chain's native token. Defaults to `ETH`.
+    #[serde(default = "default_native_token_symbol")]
+    pub native_token_symbol: String,
+    /// The number of decimals of the chain's native token. Defaults to 18.
+    #[serde(default = "default_native_token_decimals")]
+    pub native_token_decimals: u8,
 }
 
 impl Default for ChainSettings {
     fn default() -> Self {
         ChainSettings {
-            polling_interval: default_polling_interval(),
+            polling_interval: None,
             json_rpc_timeout: default_json_rpc_timeout(),
             request_retries: default_request_retries(),
             max_block_range_size: default_max_block_range_size(),
@@ -775,6 +846,9 @@ impl Default for ChainSettings {
             block_ingestor_max_concurrent_json_rpc_calls:
                 default_block_ingestor_max_concurrent_json_rpc_calls(),
             genesis_block_number: default_genesis_block_number(),
+            block_ingestor_stall_timeout: default_block_ingestor_stall_timeout(),
+            native_token_symbol: default_native_token_symbol(),
+            native_token_decimals: default_native_token_decimals(),
         }
     }
 }
@@ -782,7 +856,8 @@ impl Default for ChainSettings {
 impl ChainSettings {
     fn validate(&self) -> Result<()> {
         anyhow::ensure!(
-            !self.polling_interval.is_zero(),
+            self.polling_interval
+                .is_none_or(|interval| !interval.is_zero()),
             "polling_interval must be > 0"
         );
         anyhow::ensure!(
@@ -835,6 +910,11 @@ pub struct Chain {
     /// older than this are treated as if they have no data.
     #[serde(default)]
     pub cache_size: i32,
+    /// The format in which blocks for this chain are written to the chain
+    /// store. Defaults to `zstd` if `GRAPH_STORE_COMPRESS_BLOCK_DATA` is
+    /// set and `json` otherwise
+    #[serde(default)]
+    pub block_data_format: Option<BlockDataFormat>,
     /// Per-chain settings (flat fields). Absent fields fall back to ENV_VAR defaults.
     #[serde(flatten)]
     pub settings: ChainSettings,
@@ -878,6 +958,16 @@ where
     Ok(btree_map_to_http_headers(kvs))
 }
 
+fn deserialize_method_headers<'de, D>(deserializer: D) -> Result<MethodHeaders, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let kvs: BTreeMap<String, BTreeMap<String, String>> = Deserialize::deserialize(deserializer)?;
+    Ok(MethodHeaders::new(kvs.into_iter().map(
+        |(pattern, headers)| (pattern, btree_map_to_http_headers(headers)),
+    )))
+}
+
 fn btree_map_to_http_headers(kvs: BTreeMap<String, String>) -> HeaderMap {
     let mut headers = HeaderMap::new();
     for (k, v) in kvs.into_iter() {
@@ -997,6 +1087,18 @@ pub struct Web3Provider {
 
     #[serde(default, rename = "match")]
     rules: Vec<Web3Rule>,
+
+    /// The RPC methods that may be sent to this provider
+    #[serde(default, skip_serializing_if = "MethodFilter::is_all")]
+    pub methods: MethodFilter,
+
+    /// HTTP headers that are only added to requests for certain methods
+    #[serde(
+        skip_serializing,
+        default,
+        deserialize_with = "deserialize_method_headers"
+    )]
+    pub method_headers: MethodHeaders,
 }
 
 impl Web3Provider {
@@ -1111,6 +1213,32 @@ impl Provider {
                     ));
                 }
 
+                if !web3.methods.is_all() && web3.transport != Transport::Rpc {
+                    return Err(anyhow!(
+                        "provider {} uses the `{}` transport, but RPC methods can only be limited for the `rpc` transport",
+                        self.label,
+                        web3.transport
+                    ));
+                }
+
+                if !web3.method_headers.is_empty() && web3.transport != Transport::Rpc {
+                    return Err(anyhow!(
+                        "provider {} uses the `{}` transport, but method headers can only be used with the `rpc` transport",
+                        self.label,
+                        web3.transport
+                    ));
+                }
+
+                if web3.transport == Transport::Ipc
+                    && !ethereum::RequestTracing::from_env(&graph::log::discard()).is_none()
+                {
+                    return Err(anyhow!(
+                        "provider {} uses the `{}` transport, but GRAPH_ETHEREUM_RPC_USER_AGENT and GRAPH_ETHEREUM_RPC_REQUEST_IDS can not be used with the `ipc` transport",
+                        self.label,
+                        web3.transport
+                    ));
+                }
+
                 web3.url = shellexpand::env(&web3.url)?.into_owned();
 
                 let label = &self.label;
@@ -1228,6 +1356,8 @@ impl<'de> Deserialize<'de> for Provider {
                             .ok_or_else(|| serde::de::Error::missing_field("features"))?,
                         headers: headers.unwrap_or_else(HeaderMap::new),
                         rules: nodes,
+                        methods: MethodFilter::All,
+                        method_headers: MethodHeaders::default(),
                     }),
                 };
 
@@ -1487,10 +1617,6 @@ fn default_node_id() -> NodeId {
     NodeId::new("default").unwrap()
 }
 
-fn default_polling_interval() -> Duration {
-    ENV_VARS.ingestor_polling_interval
-}
-
 fn default_json_rpc_timeout() -> Duration {
     ethereum::ENV_VARS.json_rpc_timeout
 }
@@ -1531,6 +1657,18 @@ fn default_genesis_block_number() -> u64 {
     ethereum::ENV_VARS.genesis_block_number
 }
 
+fn default_block_ingestor_stall_timeout() -> Duration {
+    ethereum::ENV_VARS.block_ingestor_stall_timeout
+}
+
+fn default_native_token_symbol() -> String {
+    ethereum::chain::NATIVE_TOKEN_SYMBOL.to_string()
+}
+
+fn default_native_token_decimals() -> u8 {
+    ethereum::chain::NATIVE_TOKEN_DECIMALS
+}
+
 fn deserialize_duration_millis<'de, D>(data: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>,
@@ -1539,6 +1677,13 @@ where
     Ok(Duration::from_millis(millis))
 }
 
+fn deserialize_opt_duration_millis<'de, D>(data: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_duration_millis(data).map(Some)
+}
+
 fn deserialize_duration_secs<'de, D>(data: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>,
@@ -1588,8 +1733,8 @@ mod tests {
         ChainSection, Web3Rule, default_block_batch_size,
         default_block_ingestor_max_concurrent_json_rpc_calls, default_block_ptr_batch_size,
         default_genesis_block_number, default_get_logs_max_contracts, default_json_rpc_timeout,
-        default_max_block_range_size, default_max_event_only_range, default_polling_interval,
-        default_request_retries, default_target_triggers_per_block_range,
+        default_max_block_range_size, default_max_event_only_range, default_request_retries,
+        default_target_triggers_per_block_range,
     };
 
     use super::{
@@ -1597,6 +1742,7 @@ mod tests {
         Transport, Web3Provider,
     };
     use graph::blockchain::BlockchainKind;
+    use graph::env::ENV_VARS;
     use graph::firehose::SubgraphLimit;
     use graph::http::{HeaderMap, HeaderValue};
     use graph::prelude::regex::Regex;
@@ -1639,6 +1785,7 @@ mod tests {
                 providers: vec![],
                 amp: None,
                 cache_size: 0,
+                block_data_format: None,
                 settings: ChainSettings::default(),
             },
             actual
@@ -1663,6 +1810,7 @@ mod tests {
                 providers: vec![],
                 amp: None,
                 cache_size: 0,
+                block_data_format: None,
                 settings: ChainSettings::default(),
             },
             actual
@@ -1694,6 +1842,74 @@ mod tests {
         assert_eq!(section.chains["sepolia"].cache_size, 2000);
     }
 
+    #[test]
+    fn section_cache_size_is_only_checked_for_chains_that_use_it() {
+        let section = |chains: &str| {
+            toml::from_str::<ChainSection>(&format!(
+                r#"
+                ingestor = "block_ingestor_node"
+                cache_size = 100
+                {chains}
+                "#
+            ))
+            .unwrap()
+        };
+
+        // The default is smaller than the reorg threshold, but no chain
+        // uses it
+        let mut all_explicit = section(
+            r#"
+            [mainnet]
+            shard = "primary"
+            provider = []
+            cache_size = 2000
+            "#,
+        );
+        all_explicit.validate().unwrap();
+
+        let mut inherited = section(
+            r#"
+            [mainnet]
+            shard = "primary"
+            provider = []
+            "#,
+        );
+        let err = inherited.validate().unwrap_err();
+        assert!(err.to_string().contains("chain 'mainnet'"));
+    }
+
+    #[test]
+    fn it_works_on_chain_with_block_data_format() {
+        let section = toml::from_str::<ChainSection>(
+            r#"
+            ingestor = "block_ingestor_node"
+            [mainnet]
+            shard = "primary"
+            provider = []
+            block_data_format = "zstd"
+            [sepolia]
+            shard = "primary"
+            provider = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(BlockDataFormat::Zstd),
+            section.chains["mainnet"].block_data_format
+        );
+        assert_eq!(None, section.chains["sepolia"].block_data_format);
+
+        let err = toml::from_str::<Chain>(
+            r#"
+            shard = "primary"
+            provider = []
+            block_data_format = "bincode"
+            "#,
+        );
+        assert!(err.is_err());
+    }
+
     #[test]
     fn it_works_on_deprecated_provider_from_toml() {
         let actual = toml::from_str(
@@ -1715,6 +1931,8 @@ mod tests {
                     features: BTreeSet::new(),
                     headers: HeaderMap::new(),
                     rules: Vec::new(),
+                    methods: MethodFilter::All,
+                    method_headers: MethodHeaders::default(),
                 }),
             },
             actual
@@ -1741,6 +1959,8 @@ mod tests {
                     features: BTreeSet::new(),
                     headers: HeaderMap::new(),
                     rules: Vec::new(),
+                    methods: MethodFilter::All,
+                    method_headers: MethodHeaders::default(),
                 }),
             },
             actual
@@ -1802,6 +2022,8 @@ mod tests {
                     features,
                     headers,
                     rules: Vec::new(),
+                    methods: MethodFilter::All,
+                    method_headers: MethodHeaders::default(),
                 }),
             },
             actual
@@ -1827,6 +2049,8 @@ mod tests {
                     features: BTreeSet::new(),
                     headers: HeaderMap::new(),
                     rules: Vec::new(),
+                    methods: MethodFilter::All,
+                    method_headers: MethodHeaders::default(),
                 }),
             },
             actual
@@ -2039,6 +2263,8 @@ mod tests {
                     features: BTreeSet::new(),
                     headers: HeaderMap::new(),
                     rules: Vec::new(),
+                    methods: MethodFilter::All,
+                    method_headers: MethodHeaders::default(),
                 }),
             },
             actual
@@ -2074,6 +2300,8 @@ mod tests {
                     features,
                     headers: HeaderMap::new(),
                     rules: Vec::new(),
+                    methods: MethodFilter::All,
+                    method_headers: MethodHeaders::default(),
                 }),
             },
             actual
@@ -2141,6 +2369,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn it_parses_web3_provider_with_denied_methods() {
+        let mut actual: Provider = toml::from_str(
+            r#"
+            label = "metered"
+            details = { type = "web3", url = "http://localhost:8545", features = [], methods = { deny = ["trace_block", "trace_filter"] } }
+        "#,
+        )
+        .unwrap();
+        actual.validate().unwrap();
+
+        match actual.details {
+            ProviderDetails::Web3(ref web3) => {
+                assert!(!web3.methods.permits("trace_block"));
+                assert!(!web3.methods.permits("trace_filter"));
+                assert!(web3.methods.permits("eth_call"));
+            }
+            _ => panic!("expected Web3 provider"),
+        }
+
+        let actual: Provider = toml::from_str(
+            r#"
+            label = "limited"
+            details = { type = "web3", url = "http://localhost:8545", features = [], methods = { allow = ["eth_call"] } }
+        "#,
+        )
+        .unwrap();
+        match actual.details {
+            ProviderDetails::Web3(ref web3) => {
+                assert!(web3.methods.permits("eth_call"));
+                assert!(!web3.methods.permits("trace_block"));
+            }
+            _ => panic!("expected Web3 provider"),
+        }
+    }
+
+    #[test]
+    fn it_parses_web3_provider_with_method_headers() {
+        let mut actual: Provider = toml::from_str(
+            r#"
+            label = "archive"
+            details = { type = "web3", url = "http://localhost:8545", features = [], method_headers = { eth_getLogs = { "x-api-key" = "archive-key" }, "trace_*" = { "x-trace-key" = "trace-key" } } }
+        "#,
+        )
+        .unwrap();
+        actual.validate().unwrap();
+
+        let mut archive = HeaderMap::new();
+        archive.insert("x-api-key", "archive-key".parse().unwrap());
+        let mut trace = HeaderMap::new();
+        trace.insert("x-trace-key", "trace-key".parse().unwrap());
+        let expected = MethodHeaders::new([
+            ("eth_getLogs".to_string(), archive),
+            ("trace_*".to_string(), trace),
+        ]);
+
+        match actual.details {
+            ProviderDetails::Web3(ref web3) => {
+                assert_eq!(expected, web3.method_headers);
+                assert!(web3.headers.is_empty());
+            }
+            _ => panic!("expected Web3 provider"),
+        }
+
+        let mut actual: Provider = toml::from_str(
+            r#"
+            label = "ws"
+            details = { type = "web3", transport = "ws", url = "ws://localhost:8546", features = [], method_headers = { eth_getLogs = { "x-api-key" = "archive-key" } } }
+        "#,
+        )
+        .unwrap();
+        let err = actual.validate().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("method headers can only be used with the `rpc` transport"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn it_rejects_method_filter_for_websockets() {
+        let mut actual: Provider = toml::from_str(
+            r#"
+            label = "ws"
+            details = { type = "web3", transport = "ws", url = "ws://localhost:8546", features = [], methods = { deny = ["trace_block"] } }
+        "#,
+        )
+        .unwrap();
+
+        let err = actual.validate().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("RPC methods can only be limited for the `rpc` transport"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
     #[test]
     fn it_rejects_multiple_compression_features() {
         let mut actual: Provider = toml::from_str(
@@ -2208,13 +2535,39 @@ mod tests {
         assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
     }
 
+    #[test]
+    fn kind_aliases() {
+        let actual = toml::from_str::<ChainSection>(
+            r#"
+            ingestor = "block_ingestor_node"
+            [kind_aliases]
+            config-test-l2 = "ethereum"
+            [mainnet]
+            shard = "vip"
+            provider = []"#,
+        )
+        .unwrap();
+
+        assert_eq!(1, actual.chains.len());
+        assert_eq!(
+            Some(&BlockchainKind::Ethereum),
+            actual.kind_aliases.get("config-test-l2")
+        );
+
+        actual.register_kind_aliases().unwrap();
+        assert_eq!(
+            BlockchainKind::Ethereum,
+            "config-test-l2".parse::<BlockchainKind>().unwrap()
+        );
+    }
+
     #[test]
     fn polling_interval() {
-        let default = default_polling_interval();
+        let default = ENV_VARS.ingestor_polling_interval_for("mainnet");
         let different = 2 * default;
 
         // Polling interval not set explicitly, use default
-        let actual = toml::from_str::<ChainSection>(
+        let mut actual = toml::from_str::<ChainSection>(
             r#"
             ingestor = "block_ingestor_node"
             [mainnet]
@@ -2224,7 +2577,17 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            default,
+            None,
+            actual
+                .chains
+                .get("mainnet")
+                .unwrap()
+                .settings
+                .polling_interval
+        );
+        actual.validate().unwrap();
+        assert_eq!(
+            Some(default),
             actual
                 .chains
                 .get("mainnet")
@@ -2249,7 +2612,7 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            different,
+            Some(different),
             actual
                 .chains
                 .get("mainnet")
@@ -2259,6 +2622,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn native_token() {
+        let actual = toml::from_str::<ChainSection>(
+            r#"
+            ingestor = "block_ingestor_node"
+            [mainnet]
+            shard = "primary"
+            provider = []
+            [gnosis]
+            shard = "primary"
+            provider = []
+            native_token_symbol = "xDAI""#,
+        )
+        .unwrap();
+
+        let mainnet = &actual.chains.get("mainnet").unwrap().settings;
+        assert_eq!("ETH", mainnet.native_token_symbol);
+        assert_eq!(18, mainnet.native_token_decimals);
+
+        let gnosis = &actual.chains.get("gnosis").unwrap().settings;
+        assert_eq!("xDAI", gnosis.native_token_symbol);
+        assert_eq!(18, gnosis.native_token_decimals);
+
+        let actual = toml::from_str::<ChainSection>(
+            r#"
+            ingestor = "block_ingestor_node"
+            [custom]
+            shard = "primary"
+            provider = []
+            native_token_symbol = "CUST"
+            native_token_decimals = 6"#,
+        )
+        .unwrap();
+
+        let custom = &actual.chains.get("custom").unwrap().settings;
+        assert_eq!("CUST", custom.native_token_symbol);
+        assert_eq!(6, custom.native_token_decimals);
+    }
+
     #[test]
     fn pool_sizes() {
         let index = NodeId::new("index_node_1").unwrap();
@@ -2308,6 +2710,32 @@ fdw_pool_size = [
         assert_eq!(shard.fdw_pool_size.size_for(&other, "ashard").unwrap(), 5);
     }
 
+    #[test]
+    fn history_replicas_need_a_regular_host() {
+        let node = NodeId::new("index_node_1").unwrap();
+        let shard = |main_weight: usize| {
+            toml::from_str::<Shard>(&format!(
+                r#"
+            connection = "postgresql://u:p@h/db"
+            weight = {main_weight}
+            pool_size = 10
+            [replicas.archive]
+            connection = "postgresql://u:p@archive/db"
+            history = true
+            "#
+            ))
+            .unwrap()
+        };
+
+        let mut valid = shard(1);
+        valid.validate("primary", &node).unwrap();
+        assert!(valid.replicas["archive"].history);
+
+        // The history replica can not take the queries for current data
+        let mut invalid = shard(0);
+        assert!(invalid.validate("primary", &node).is_err());
+    }
+
     #[test]
     fn amp_chain_names_parsed_from_toml() {
         let actual: Chain = toml::from_str(
@@ -2360,6 +2788,28 @@ fdw_pool_size = [
         );
     }
 
+    #[test]
+    fn chain_names_with_the_same_env_suffix_are_rejected() {
+        let mut section = toml::from_str::<ChainSection>(
+            r#"
+            ingestor = "block_ingestor_node"
+            [arbitrum-one]
+            shard = "primary"
+            provider = []
+            [arbitrum_one]
+            shard = "primary"
+            provider = []
+            "#,
+        )
+        .unwrap();
+
+        let err = section.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("ARBITRUM_ONE"),
+            "expected env suffix collision error, got: {err}"
+        );
+    }
+
     #[test]
     fn amp_chain_names_validation_rejects_alias_colliding_with_chain_name() {
         let mut section = toml::from_str::<ChainSection>(
@@ -2517,7 +2967,7 @@ fdw_pool_size = [
     #[test]
     fn chain_settings_defaults_match_env_vars() {
         let settings = ChainSettings::default();
-        assert_eq!(settings.polling_interval, default_polling_interval());
+        assert_eq!(settings.polling_interval, None);
         assert_eq!(settings.json_rpc_timeout, default_json_rpc_timeout());
         assert_eq!(settings.request_retries, default_request_retries());
         assert_eq!(
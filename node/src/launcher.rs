@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     io::{BufRead, BufReader},
     path::Path,
     time::Duration,
@@ -17,8 +18,9 @@ use graph::prometheus::Registry;
 use graph::url::Url;
 use graph::{
     amp,
-    blockchain::{Blockchain, BlockchainKind, BlockchainMap},
-    components::network_provider::AmpChainNames,
+    blockchain::{Blockchain, BlockchainKind, BlockchainMap, IngestorPause},
+    components::network_provider::{AmpChainNames, ChainName},
+    ipfs::SwappableIpfsClient,
 };
 use graph_core::polling_monitor::{ArweaveService, IpfsService, arweave_service};
 use graph_graphql::prelude::GraphQlRunner;
@@ -183,16 +185,20 @@ async fn spawn_block_ingestor(
     network_store: &Arc<Store>,
     primary_pool: ConnectionPool,
     metrics_registry: &Arc<MetricsRegistry>,
-) {
+) -> BTreeMap<ChainName, IngestorPause> {
     let logger = logger.clone();
     let ingestors = Networks::block_ingestors(&logger, blockchain_map)
         .await
         .expect("unable to start block ingestors");
 
+    let mut pauses = BTreeMap::new();
     ingestors.into_iter().for_each(|ingestor| {
         let logger = logger.clone();
         info!(logger,"Starting block ingestor for network";"network_name" => &ingestor.network_name().as_str(), "kind" => ingestor.kind().to_string());
 
+        if let Some(pause) = ingestor.pause_handle() {
+            pauses.insert(ingestor.network_name(), pause);
+        }
         graph::spawn(ingestor.run());
     });
 
@@ -205,6 +211,8 @@ async fn spawn_block_ingestor(
         metrics_registry.clone(),
     );
     graph::spawn_blocking(job_runner.start());
+
+    pauses
 }
 
 fn deploy_subgraph_from_flag(
@@ -401,6 +409,7 @@ pub async fn run(
     logger: Logger,
     opt: Opt,
     env_vars: Arc<EnvVars>,
+    ipfs_client: Arc<SwappableIpfsClient>,
     ipfs_service: IpfsService,
     link_resolver: Arc<dyn LinkResolver>,
     dev_updates: Option<mpsc::Receiver<(DeploymentHash, SubgraphName)>>,
@@ -618,7 +627,7 @@ pub async fn run(
             amp_client.cheap_clone(),
         );
 
-        if config.is_block_ingestor() {
+        let ingestor_pauses = if config.is_block_ingestor() {
             spawn_block_ingestor(
                 &logger,
                 &blockchain_map,
@@ -626,8 +635,10 @@ pub async fn run(
                 primary_pool,
                 &metrics_registry,
             )
-            .await;
-        }
+            .await
+        } else {
+            BTreeMap::new()
+        };
 
         let amp_chain_names = Arc::new(config.amp_chain_names());
         let subgraph_registrar = build_subgraph_registrar(
@@ -660,6 +671,10 @@ pub async fn run(
             http_port,
             subgraph_registrar.clone(),
             node_id.clone(),
+            ingestor_pauses,
+            network_store.subgraph_store().write_rate_limits(),
+            ipfs_client,
+            blockchain_map.clone(),
             logger.clone(),
         )
         .await
@@ -760,6 +775,10 @@ fn setup_configuration(
         }
         Ok(config) => config,
     };
+    if let Err(e) = config.chains.register_kind_aliases() {
+        eprintln!("configuration error: {}", e);
+        std::process::exit(1);
+    }
 
     let subgraph_settings = match env_vars.subgraph_settings {
         Some(ref path) => {
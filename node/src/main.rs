@@ -1,6 +1,11 @@
 use clap::Parser as _;
 use git_testament::git_testament;
-use graph::{env::EnvVars, log::logger, prelude::*};
+use graph::{
+    env::EnvVars,
+    ipfs::{DeploymentIpfsClients, SwappableIpfsClient},
+    log::logger,
+    prelude::*,
+};
 use graph_core::polling_monitor::ipfs_service;
 use graph_node::{launcher, opt};
 use tokio_util::sync::CancellationToken;
@@ -43,20 +48,32 @@ async fn main_inner() {
     let ipfs_client = graph::ipfs::new_ipfs_client(&opt.ipfs, &metrics_registry, &logger)
         .await
         .unwrap_or_else(|err| panic!("Failed to create IPFS client: {err:#}"));
+    // Operators can swap the IPFS servers through the admin JSON-RPC server
+    let ipfs_client = Arc::new(SwappableIpfsClient::new(ipfs_client));
+
+    let deployment_ipfs_clients =
+        DeploymentIpfsClients::from_env(ipfs_client.metrics().cheap_clone(), &logger)
+            .await
+            .unwrap_or_else(|err| panic!("Failed to create IPFS clients for deployments: {err:#}"));
 
     let ipfs_service = ipfs_service(
         ipfs_client.cheap_clone(),
+        deployment_ipfs_clients.cheap_clone(),
         env_vars.mappings.max_ipfs_file_bytes,
         env_vars.mappings.ipfs_timeout,
         env_vars.mappings.ipfs_request_limit,
     );
 
-    let link_resolver = Arc::new(IpfsResolver::new(ipfs_client, env_vars.cheap_clone()));
+    let link_resolver = Arc::new(
+        IpfsResolver::new(ipfs_client.cheap_clone(), env_vars.cheap_clone())
+            .with_deployment_clients(deployment_ipfs_clients),
+    );
 
     launcher::run(
         logger,
         opt,
         env_vars,
+        ipfs_client,
         ipfs_service,
         link_resolver,
         None,
@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use diesel::sql_query;
@@ -6,13 +7,16 @@ use diesel_async::RunQueryDsl;
 use graph::blockchain::BlockHash;
 use graph::blockchain::BlockPtr;
 use graph::blockchain::ChainIdentifier;
+use graph::blockchain::reorg_replay::{self, ReplayEvent};
 use graph::cheap_clone::CheapClone;
 use graph::components::network_provider::ChainName;
 use graph::components::store::ChainIdStore;
 use graph::components::store::StoreError;
+use graph::futures03::TryStreamExt as _;
 use graph::prelude::BlockNumber;
 use graph::prelude::ChainStore as _;
 use graph::prelude::LightEthereumBlock;
+use graph::prelude::LightEthereumBlockExt as _;
 use graph::prelude::{anyhow, anyhow::bail};
 use graph::slog::Logger;
 use graph::{
@@ -22,6 +26,7 @@ use graph::{
 use graph_chain_ethereum::EthereumAdapter;
 use graph_chain_ethereum::EthereumAdapterTrait as _;
 use graph_chain_ethereum::chain::BlockFinality;
+use graph_chain_ethereum::trigger::{EthereumBlockTriggerType, EthereumTrigger, LogRef};
 use graph_store_postgres::BlockStore;
 use graph_store_postgres::ChainStore;
 use graph_store_postgres::PoolCoordinator;
@@ -30,6 +35,7 @@ use graph_store_postgres::add_chain;
 use graph_store_postgres::find_chain;
 use graph_store_postgres::update_chain_name;
 use graph_store_postgres::{ConnectionPool, command_support::catalog::block_store};
+use itertools::{Itertools as _, MinMaxResult};
 
 use crate::manager::prompt::prompt_for_confirmation;
 use crate::network_setup::Networks;
@@ -167,6 +173,131 @@ pub async fn info(
     Ok(())
 }
 
+pub async fn replay_reorg(
+    chain_store: Arc<ChainStore>,
+    old_head: &str,
+    new_head: &str,
+    max_depth: BlockNumber,
+) -> Result<(), Error> {
+    async fn cached_ptr(chain_store: &ChainStore, hash: &str) -> Result<BlockPtr, Error> {
+        let hash: BlockHash = hash.parse()?;
+        let Some((_, number, _, _)) = chain_store.block_number(&hash).await? else {
+            bail!("block {} is not in the block cache", hash);
+        };
+        Ok(BlockPtr::new(hash, number))
+    }
+
+    let old_head = cached_ptr(&chain_store, old_head).await?;
+    let new_head = cached_ptr(&chain_store, new_head).await?;
+
+    let events =
+        reorg_replay::replay_reorg(chain_store.cheap_clone(), old_head, new_head, max_depth)
+            .await?;
+
+    const BATCH_SIZE: usize = 100;
+
+    let applied: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            ReplayEvent::Apply(block) => Some(block),
+            ReplayEvent::Revert { .. } => None,
+        })
+        .collect();
+    let range = match applied.iter().map(|block| block.number).minmax() {
+        MinMaxResult::NoElements => 0..0,
+        MinMaxResult::OneElement(number) => number..number.saturating_add(1),
+        MinMaxResult::MinMax(low, high) => low..high.saturating_add(1),
+    };
+    let applied: HashSet<_> = applied.into_iter().map(|block| &block.hash).collect();
+
+    // The triggers of each applied block, and whether its receipts are
+    // cached. A block without cached receipts may have logs that we can't
+    // see
+    let mut triggers = HashMap::new();
+    let mut blocks = chain_store.cheap_clone().blocks_in_range(range, BATCH_SIZE);
+    while let Some(block) = blocks.try_next().await? {
+        let ptr = block.light_block().block_ptr();
+        if !applied.contains(&ptr.hash) {
+            continue;
+        }
+        let has_transactions = !block.light_block().inner().transactions.is_empty();
+        let mut block_triggers = vec![EthereumTrigger::Block(
+            ptr.clone(),
+            EthereumBlockTriggerType::End,
+        )];
+        let receipts = block
+            .into_full_block()
+            .map(|block| block.transaction_receipts)
+            .unwrap_or_default();
+        let complete = !has_transactions || !receipts.is_empty();
+        for receipt in receipts {
+            for log in receipt.logs() {
+                block_triggers.push(EthereumTrigger::Log(LogRef::FullLog(
+                    Arc::new(log.clone()),
+                    Some(receipt.cheap_clone()),
+                )));
+            }
+        }
+        block_triggers.sort();
+        triggers.insert(ptr.hash, (block_triggers, complete));
+    }
+
+    for event in &events {
+        match event {
+            ReplayEvent::Revert { block, parent } => {
+                println!(
+                    "revert #{} {} to #{}",
+                    block.number, block.hash, parent.number
+                )
+            }
+            ReplayEvent::Apply(block) => {
+                println!("apply  #{} {}", block.number, block.hash);
+                match triggers.get(&block.hash) {
+                    Some((triggers, complete)) => {
+                        for trigger in triggers {
+                            println!("         {}", describe_trigger(trigger));
+                        }
+                        if !*complete {
+                            println!("         (receipts not cached, log triggers unknown)");
+                        }
+                    }
+                    None => println!("         (block data not cached)"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn describe_trigger(trigger: &EthereumTrigger) -> String {
+    match trigger {
+        EthereumTrigger::Block(ptr, _) => format!("block #{} {}", ptr.number, ptr.hash),
+        EthereumTrigger::Log(log) => {
+            let topic0 = log
+                .log()
+                .topics()
+                .first()
+                .map(|topic| topic.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let tx = log
+                .log()
+                .transaction_hash
+                .map(|hash| hash.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            format!(
+                "log {} {} tx {} log index {}",
+                log.address(),
+                topic0,
+                tx,
+                log.log_index().unwrap_or_default()
+            )
+        }
+        EthereumTrigger::Call(call) => {
+            format!("call to {} tx {:?}", call.to, call.transaction_hash)
+        }
+    }
+}
+
 pub async fn remove(primary: ConnectionPool, store: BlockStore, name: String) -> Result<(), Error> {
     let sites = {
         let mut conn = graph_store_postgres::command_support::catalog::Connection::new(
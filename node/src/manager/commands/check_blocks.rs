@@ -7,6 +7,7 @@ use graph::{
         store::ChainStore as ChainStoreTrait,
     },
     prelude::{
+        BlockNumber,
         alloy::primitives::B256,
         anyhow::{self, Context, anyhow},
     },
@@ -14,6 +15,7 @@ use graph::{
 };
 use graph_chain_ethereum::{EthereumAdapter, EthereumAdapterTrait};
 use graph_store_postgres::ChainStore;
+use std::fmt;
 use std::sync::Arc;
 
 pub async fn by_hash(
@@ -91,6 +93,92 @@ pub async fn by_range(
     Ok(())
 }
 
+/// Compares the blocks with `number` in the block cache with the block
+/// that the provider returns for that number and prints how they differ.
+/// Unlike the other checks, this never changes the block cache.
+pub async fn diff_by_number(
+    number: BlockNumber,
+    chain_store: Arc<ChainStore>,
+    ethereum_adapter: &EthereumAdapter,
+    logger: &Logger,
+) -> anyhow::Result<()> {
+    let provider_block =
+        steps::fetch_provider_block_by_number(number, ethereum_adapter, logger).await?;
+    let block_hashes = steps::resolve_block_hash_from_block_number(number, &chain_store).await?;
+    if block_hashes.is_empty() {
+        bail!("Could not find a block with number {} in store", number);
+    }
+
+    let cached_blocks = chain_store
+        .cheap_clone()
+        .blocks(block_hashes.into_iter().map(Into::into).collect())
+        .await?;
+    for cached_block in cached_blocks {
+        let diff = BlockDiff::new(cached_block.light_block(), &provider_block);
+        let hash = cached_block.light_block().hash();
+        if diff.is_empty() {
+            println!("Cached block {hash} matches block {number} from the provider");
+        } else {
+            println!("Cached block {hash} differs from block {number} from the provider:");
+            print!("{diff}");
+        }
+    }
+    Ok(())
+}
+
+/// The fields in which a block from the block cache differs from the block
+/// with the same number that the provider returns. Each difference holds
+/// the cached value first and the provider's value second.
+#[derive(Debug, Default, PartialEq)]
+pub struct BlockDiff {
+    pub hash: Option<(B256, B256)>,
+    pub parent_hash: Option<(B256, B256)>,
+    pub transaction_count: Option<(usize, usize)>,
+    pub timestamp: Option<(u64, u64)>,
+}
+
+impl BlockDiff {
+    pub fn new(cached: &LightEthereumBlock, provider: &LightEthereumBlock) -> Self {
+        fn differ<T: PartialEq>(cached: T, provider: T) -> Option<(T, T)> {
+            (cached != provider).then_some((cached, provider))
+        }
+
+        let (cached, provider) = (cached.inner(), provider.inner());
+        BlockDiff {
+            hash: differ(cached.header.hash, provider.header.hash),
+            parent_hash: differ(cached.header.parent_hash, provider.header.parent_hash),
+            transaction_count: differ(cached.transactions.len(), provider.transactions.len()),
+            timestamp: differ(cached.header.timestamp, provider.header.timestamp),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &BlockDiff::default()
+    }
+}
+
+impl fmt::Display for BlockDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn line(
+            f: &mut fmt::Formatter<'_>,
+            field: &str,
+            diff: &Option<(impl fmt::Display, impl fmt::Display)>,
+        ) -> fmt::Result {
+            match diff {
+                Some((cached, provider)) => {
+                    writeln!(f, "  {field:<18} cached: {cached}, provider: {provider}")
+                }
+                None => Ok(()),
+            }
+        }
+
+        line(f, "hash", &self.hash)?;
+        line(f, "parent hash", &self.parent_hash)?;
+        line(f, "transaction count", &self.transaction_count)?;
+        line(f, "timestamp", &self.timestamp)
+    }
+}
+
 pub async fn truncate(chain_store: Arc<ChainStore>, skip_confirmation: bool) -> anyhow::Result<()> {
     let prompt = format!(
         "This will delete all cached blocks for {}.\nProceed?",
@@ -219,6 +307,20 @@ mod steps {
         Ok(LightEthereumBlock::new(provider_block))
     }
 
+    /// Fetches the block with the given number from an RPC endpoint.
+    pub(super) async fn fetch_provider_block_by_number(
+        number: BlockNumber,
+        ethereum_adapter: &EthereumAdapter,
+        logger: &Logger,
+    ) -> anyhow::Result<LightEthereumBlock> {
+        let provider_block = ethereum_adapter
+            .block_by_number(logger, number)
+            .await
+            .with_context(|| format!("failed to fetch block number {number}"))?
+            .ok_or_else(|| anyhow!("RPC provider found no block with number {number}"))?;
+        Ok(LightEthereumBlock::new(provider_block))
+    }
+
     /// Compares a cached block against a provider block.
     ///
     /// Only compares the block data (without receipts) since the provider's
@@ -333,3 +435,66 @@ mod ranges {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::alloy::rpc::types::BlockTransactions;
+    use graph::prelude::{create_dummy_transaction, create_minimal_block_for_test};
+
+    use super::*;
+
+    fn block(hash: u8, parent: u8, timestamp: u64, transactions: usize) -> LightEthereumBlock {
+        let hash = B256::repeat_byte(hash);
+        let transactions = (0..transactions)
+            .map(|i| create_dummy_transaction(10, hash, Some(i as u64), B256::ZERO))
+            .collect();
+        let block = create_minimal_block_for_test(10, hash)
+            .map_header(|mut header| {
+                header.inner.parent_hash = B256::repeat_byte(parent);
+                header.inner.timestamp = timestamp;
+                header
+            })
+            .with_transactions(BlockTransactions::Full(transactions));
+        LightEthereumBlock::new(block)
+    }
+
+    #[test]
+    fn identical_blocks_have_no_diff() {
+        let diff = BlockDiff::new(&block(1, 0, 100, 2), &block(1, 0, 100, 2));
+        assert!(diff.is_empty());
+        assert_eq!("", diff.to_string());
+    }
+
+    #[test]
+    fn divergent_block_reports_each_field() {
+        // The cached block came from a provider on a different fork
+        let cached = block(1, 0, 100, 2);
+        let provider = block(2, 3, 112, 5);
+
+        let diff = BlockDiff::new(&cached, &provider);
+        assert_eq!(
+            diff,
+            BlockDiff {
+                hash: Some((B256::repeat_byte(1), B256::repeat_byte(2))),
+                parent_hash: Some((B256::repeat_byte(0), B256::repeat_byte(3))),
+                transaction_count: Some((2, 5)),
+                timestamp: Some((100, 112)),
+            }
+        );
+        assert!(!diff.is_empty());
+
+        // Only the fields that differ are reported
+        let diff = BlockDiff::new(&cached, &block(1, 0, 100, 3));
+        assert_eq!(
+            diff,
+            BlockDiff {
+                transaction_count: Some((2, 3)),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            "  transaction count  cached: 2, provider: 3\n",
+            diff.to_string()
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use std::io::{BufWriter, Write as _};
+use std::sync::Arc;
+
+use graph::futures03::TryStreamExt as _;
+use graph::prelude::{anyhow::Result, serde_json};
+
+use graph_store_postgres::{ConnectionPool, SubgraphStore};
+
+use crate::manager::deployment::DeploymentSearch;
+
+/// Write the current entities of type `entity_type` in the deployment
+/// to stdout, one JSON object per line
+pub async fn run(
+    store: Arc<SubgraphStore>,
+    primary_pool: ConnectionPool,
+    search: DeploymentSearch,
+    entity_type: String,
+    batch_size: usize,
+) -> Result<()> {
+    let locator = search.locate_unique(&primary_pool).await?;
+
+    let mut entities = store
+        .current_entities(&locator, &entity_type, batch_size)
+        .await?;
+    let mut out = BufWriter::new(std::io::stdout());
+    while let Some(entity) = entities.try_next().await? {
+        serde_json::to_writer(&mut out, &entity)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -8,6 +8,7 @@ pub mod database;
 pub mod deploy;
 pub mod deployment;
 pub mod dump;
+pub mod export;
 pub mod index;
 pub mod listen;
 pub mod provider_checks;
@@ -167,6 +167,7 @@ struct Args {
     deployment: DeploymentLocator,
     earliest_block: BlockNumber,
     latest_block: BlockNumber,
+    reorg_threshold: BlockNumber,
 }
 
 async fn check_args(
@@ -203,6 +204,7 @@ async fn check_args(
         deployment,
         earliest_block: status.earliest_block_number,
         latest_block,
+        reorg_threshold: ENV_VARS.reorg_threshold_for(&status.network),
     })
 }
 
@@ -223,7 +225,7 @@ async fn first_prune(
     let mut req = PruneRequest::new(
         &args.deployment,
         args.history,
-        ENV_VARS.reorg_threshold(),
+        args.reorg_threshold,
         args.earliest_block,
         args.latest_block,
     )?;
@@ -263,7 +265,7 @@ async fn run_inner(
     if !once {
         store
             .subgraph_store()
-            .set_history_blocks(&args.deployment, args.history, ENV_VARS.reorg_threshold())
+            .set_history_blocks(&args.deployment, args.history, args.reorg_threshold)
             .await?;
     }
 
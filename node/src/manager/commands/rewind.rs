@@ -133,14 +133,15 @@ pub async fn run(
         let deployment_store = subgraph_store.for_site(&site)?;
         let deployment_details = deployment_store.deployment_details_for_id(locator).await?;
         let block_number_to = block_ptr_to.as_ref().map(|b| b.number).unwrap_or(0);
+        let reorg_threshold = ENV_VARS.reorg_threshold_for(&site.network);
 
-        if block_number_to < deployment_details.earliest_block_number + ENV_VARS.reorg_threshold() {
+        if block_number_to < deployment_details.earliest_block_number + reorg_threshold {
             bail!(
                 "The block number {} is not safe to rewind to for deployment {}. The earliest block number of this deployment is {}. You can only safely rewind to block number {}",
                 block_ptr_to.as_ref().map(|b| b.number).unwrap_or(0),
                 locator,
                 deployment_details.earliest_block_number,
-                deployment_details.earliest_block_number + ENV_VARS.reorg_threshold()
+                deployment_details.earliest_block_number + reorg_threshold
             );
         }
     }
@@ -16,6 +16,7 @@ use graph::components::store::DeploymentLocator;
 use graph::components::subgraph::{Settings, SubgraphInstanceManager as _};
 use graph::endpoint::EndpointMetrics;
 use graph::env::EnvVars;
+use graph::ipfs::DeploymentIpfsClients;
 use graph::prelude::{
     BlockNumber, DeploymentHash, ENV_VARS, IpfsResolver, LoggerFactory, NodeId,
     SubgraphCountMetric, SubgraphName, SubgraphRegistrar, SubgraphStore,
@@ -59,9 +60,12 @@ pub async fn run(
 
     // FIXME: Hard-coded IPFS config, take it from config file instead?
     let ipfs_client = graph::ipfs::new_ipfs_client(&ipfs_url, &metrics_registry, &logger).await?;
+    let deployment_ipfs_clients =
+        DeploymentIpfsClients::from_env(ipfs_client.metrics().cheap_clone(), &logger).await?;
 
     let ipfs_service = ipfs_service(
         ipfs_client.cheap_clone(),
+        deployment_ipfs_clients.cheap_clone(),
         env_vars.mappings.max_ipfs_file_bytes,
         env_vars.mappings.ipfs_timeout,
         env_vars.mappings.ipfs_request_limit,
@@ -88,7 +92,10 @@ pub async fn run(
 
     // Convert the clients into a link resolver. Since we want to get past
     // possible temporary DNS failures, make the resolver retry
-    let link_resolver = Arc::new(IpfsResolver::new(ipfs_client, env_vars.cheap_clone()));
+    let link_resolver = Arc::new(
+        IpfsResolver::new(ipfs_client, env_vars.cheap_clone())
+            .with_deployment_clients(deployment_ipfs_clients),
+    );
 
     let chain_head_update_listener = store_builder.chain_head_update_listener();
     let network_store = store_builder.network_store(config.chain_ids()).await;
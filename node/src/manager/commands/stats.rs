@@ -6,6 +6,8 @@ use crate::manager::deployment::DeploymentSearch;
 use crate::manager::fmt;
 use graph::components::store::DeploymentLocator;
 use graph::components::store::VersionStats;
+use graph::itertools::Itertools;
+use graph::prelude::BlockNumber;
 use graph::prelude::CheapClone as _;
 use graph::prelude::anyhow;
 use graph_store_postgres::AsyncPgConnection;
@@ -59,24 +61,31 @@ pub fn show_stats(
 ) -> Result<(), anyhow::Error> {
     fn header() {
         println!(
-            "{:^30} | {:^10} | {:^10} | {:^7}",
-            "table", "entities", "versions", "ratio"
+            "{:^30} | {:^10} | {:^10} | {:^7} | {:^5}",
+            "table", "entities", "versions", "ratio", "index"
+        );
+        println!(
+            "{:-^30}-+-{:-^10}-+-{:-^10}-+-{:-^7}-+-{:-^5}",
+            "", "", "", "", ""
         );
-        println!("{:-^30}-+-{:-^10}-+-{:-^10}-+-{:-^7}", "", "", "", "");
     }
 
-    fn footer() {
-        println!("  (a): account-like flag set");
+    fn footer(account_like: bool) {
+        if account_like {
+            println!("  (a): account-like flag set");
+        }
+        println!("  index: recommended index for block ranges; brin needs the account-like flag");
     }
 
     fn print_stats(s: &VersionStats, account_like: bool) {
         println!(
-            "{:<26} {:3} | {:>10} | {:>10} | {:>5.1}%",
+            "{:<26} {:3} | {:>10} | {:>10} | {:>5.1}% | {:<5}",
             fmt::abbreviate(&s.tablename, 26),
             if account_like { "(a)" } else { "   " },
             s.entities,
             s.versions,
-            s.ratio * 100.0
+            s.ratio * 100.0,
+            s.recommended_index().to_string()
         );
     }
 
@@ -84,9 +93,7 @@ pub fn show_stats(
     for s in stats {
         print_stats(s, account_like.contains(&s.tablename));
     }
-    if !account_like.is_empty() {
-        footer();
-    }
+    footer(!account_like.is_empty());
 
     Ok(())
 }
@@ -106,6 +113,159 @@ pub async fn show(
     show_stats(stats.as_slice(), account_like)
 }
 
+pub async fn count(
+    store: Arc<SubgraphStore>,
+    primary: ConnectionPool,
+    search: &DeploymentSearch,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary).await?;
+    let counts = store.count_entities(&locator).await?;
+
+    println!("{:^30} | {:^10}", "table", "entities");
+    println!("{:-^30}-+-{:-^10}", "", "");
+    for (table, count) in counts {
+        println!(
+            "{:<30} | {:>10}",
+            fmt::abbreviate(table.as_str(), 30),
+            count
+        );
+    }
+    Ok(())
+}
+
+pub async fn check_immutable(
+    store: Arc<SubgraphStore>,
+    primary: ConnectionPool,
+    search: &DeploymentSearch,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary).await?;
+    let violations = store.immutability_violations(&locator).await?;
+
+    if violations.is_empty() {
+        println!(
+            "no immutable entity in sgd{} has more than one version",
+            locator.id
+        );
+        return Ok(());
+    }
+
+    println!("{:^30} | {:^30} | {:^20}", "table", "id", "blocks");
+    println!("{:-^30}-+-{:-^30}-+-{:-^20}", "", "", "");
+    for (table, violations) in &violations {
+        for violation in violations {
+            println!(
+                "{:<30} | {:<30} | {}",
+                fmt::abbreviate(table.as_str(), 30),
+                fmt::abbreviate(&violation.id, 30),
+                violation.blocks.iter().join(", ")
+            );
+        }
+    }
+    let count: usize = violations.values().map(Vec::len).sum();
+    Err(anyhow!(
+        "{} immutable entities in sgd{} have more than one version",
+        count,
+        locator.id
+    ))
+}
+
+pub async fn check_block_ranges(
+    store: Arc<SubgraphStore>,
+    primary: ConnectionPool,
+    search: &DeploymentSearch,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary).await?;
+    let overlaps = store.block_range_overlaps(&locator).await?;
+
+    if overlaps.is_empty() {
+        println!(
+            "no entity in sgd{} has versions with overlapping block ranges",
+            locator.id
+        );
+        return Ok(());
+    }
+
+    let range = |(start, end): (BlockNumber, Option<BlockNumber>)| match end {
+        Some(end) => format!("[{start}, {end})"),
+        None => format!("[{start}, )"),
+    };
+
+    println!(
+        "{:^30} | {:^30} | {:^20} | {:^20}",
+        "table", "id", "first", "second"
+    );
+    println!("{:-^30}-+-{:-^30}-+-{:-^20}-+-{:-^20}", "", "", "", "");
+    for (table, overlaps) in &overlaps {
+        for overlap in overlaps {
+            println!(
+                "{:<30} | {:<30} | {:<20} | {}",
+                fmt::abbreviate(table.as_str(), 30),
+                fmt::abbreviate(&overlap.id, 30),
+                range(overlap.first),
+                range(overlap.second)
+            );
+        }
+    }
+    let count: usize = overlaps.values().map(Vec::len).sum();
+    Err(anyhow!(
+        "{} pairs of entity versions in sgd{} have overlapping block ranges",
+        count,
+        locator.id
+    ))
+}
+
+pub async fn write_rates(
+    store: Arc<SubgraphStore>,
+    primary: ConnectionPool,
+    search: &DeploymentSearch,
+    window: BlockNumber,
+    min_rate: f64,
+    max_ratio: f64,
+) -> Result<(), anyhow::Error> {
+    let locator = search.locate_unique(&primary).await?;
+    let rates = store
+        .write_rates(&locator, window, min_rate, max_ratio)
+        .await?;
+
+    if rates.is_empty() {
+        println!("sgd{} has not processed any blocks yet", locator.id);
+        return Ok(());
+    }
+
+    println!(
+        "{:^30} | {:^10} | {:^10} | {:^10} | {:^10} | {:^7} | {:^12}",
+        "table", "entities", "versions", "blocks", "rate", "ratio", "account-like"
+    );
+    println!(
+        "{:-^30}-+-{:-^10}-+-{:-^10}-+-{:-^10}-+-{:-^10}-+-{:-^7}-+-{:-^12}",
+        "", "", "", "", "", "", ""
+    );
+    for (table, rate) in &rates {
+        let account_like = if rate.is_account_like {
+            "yes"
+        } else if rate.candidate {
+            "candidate"
+        } else {
+            ""
+        };
+        println!(
+            "{:<30} | {:>10} | {:>10} | {:>10} | {:>10.2} | {:>5.1}% | {}",
+            fmt::abbreviate(table.as_str(), 30),
+            rate.entities,
+            rate.versions,
+            rate.blocks,
+            rate.rate,
+            rate.ratio * 100.0,
+            account_like
+        );
+    }
+    let candidates = rates.values().filter(|rate| rate.candidate).count();
+    println!(
+        "\n{candidates} tables are written to at least {min_rate} times per block with an entity/version ratio of at most {max_ratio} over the last {window} blocks and could be marked account-like"
+    );
+    Ok(())
+}
+
 pub async fn analyze(
     store: Arc<SubgraphStore>,
     pool: ConnectionPool,
@@ -312,11 +312,11 @@ impl Networks {
         for ((kind, id), chain) in blockchain_map.iter() {
             match kind {
                 BlockchainKind::Ethereum => {
-                    block_ingestor::<graph_chain_ethereum::Chain>(logger, id, chain, &mut res)
+                    block_ingestor::<graph_chain_ethereum::Chain>(logger, id, &chain, &mut res)
                         .await?
                 }
                 BlockchainKind::Near => {
-                    block_ingestor::<graph_chain_near::Chain>(logger, id, chain, &mut res).await?
+                    block_ingestor::<graph_chain_near::Chain>(logger, id, &chain, &mut res).await?
                 }
             }
         }
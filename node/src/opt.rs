@@ -106,7 +106,9 @@ pub struct Opt {
         long,
         value_name = "HOST:PORT",
         env = "IPFS",
-        help = "HTTP addresses of IPFS servers (RPC, Gateway)"
+        help = "HTTP addresses of IPFS servers (RPC, Gateway). They can be replaced \
+                while the node is running with the `ipfs_swap_servers` method of the \
+                JSON-RPC admin server"
     )]
     pub ipfs: Vec<String>,
     #[clap(
@@ -1,6 +1,7 @@
 use std::iter::FromIterator;
 use std::{collections::HashMap, sync::Arc};
 
+use graph::blockchain::BlockDataFormat;
 use graph::components::store::BLOCK_CACHE_SIZE;
 use graph::prelude::{MetricsRegistry, NodeId, o};
 use graph::slog::warn;
@@ -25,8 +26,8 @@ pub struct StoreBuilder {
     subscription_manager: Arc<SubscriptionManager>,
     chain_head_update_listener: Arc<PostgresChainHeadUpdateListener>,
     /// Map network names to the shards where they are/should be stored
-    /// and their cache_size setting
-    chains: HashMap<String, (ShardName, i32)>,
+    /// and their cache_size and block_data_format settings
+    chains: HashMap<String, (ShardName, i32, BlockDataFormat)>,
     pub coord: Arc<PoolCoordinator>,
     registry: Arc<MetricsRegistry>,
 }
@@ -67,7 +68,10 @@ impl StoreBuilder {
         let chains = HashMap::from_iter(config.chains.chains.iter().map(|(name, chain)| {
             let shard = ShardName::new(chain.shard.to_string())
                 .expect("config validation catches invalid names");
-            (name.to_string(), (shard, chain.cache_size))
+            let format = chain
+                .block_data_format
+                .unwrap_or_else(BlockDataFormat::from_env);
+            (name.to_string(), (shard, chain.cache_size, format))
         }));
 
         let chain_head_update_listener = Arc::new(PostgresChainHeadUpdateListener::new(
@@ -142,7 +146,7 @@ impl StoreBuilder {
                     coord.clone(),
                 );
 
-                let (read_only_conn_pools, weights) = Self::replica_pools(
+                let (read_only_conn_pools, weights, history_replicas) = Self::replica_pools(
                     &logger,
                     node,
                     name,
@@ -153,14 +157,20 @@ impl StoreBuilder {
 
                 let name =
                     ShardName::new(name.to_string()).expect("shard names have been validated");
-                Some((name, conn_pool, read_only_conn_pools, weights))
+                Some((
+                    name,
+                    conn_pool,
+                    read_only_conn_pools,
+                    weights,
+                    history_replicas,
+                ))
             })
             .collect();
 
         let pools: HashMap<_, _> = HashMap::from_iter(
             shards
                 .iter()
-                .map(|(name, pool, _, _)| (name.clone(), pool.clone())),
+                .map(|(name, pool, _, _, _)| (name.clone(), pool.clone())),
         );
 
         let store = Arc::new(SubgraphStore::new(
@@ -179,18 +189,21 @@ impl StoreBuilder {
         logger: &Logger,
         pools: HashMap<ShardName, ConnectionPool>,
         subgraph_store: Arc<SubgraphStore>,
-        chains: HashMap<String, (ShardName, i32)>,
+        chains: HashMap<String, (ShardName, i32, BlockDataFormat)>,
         networks: Vec<String>,
         registry: Arc<MetricsRegistry>,
     ) -> Arc<DieselStore> {
         let networks = networks
             .into_iter()
             .map(|name| {
-                let (shard, cache_size) = chains
-                    .get(&name)
-                    .cloned()
-                    .unwrap_or_else(|| (PRIMARY_SHARD.clone(), BLOCK_CACHE_SIZE));
-                (name, shard, cache_size)
+                let (shard, cache_size, format) = chains.get(&name).cloned().unwrap_or_else(|| {
+                    (
+                        PRIMARY_SHARD.clone(),
+                        BLOCK_CACHE_SIZE,
+                        BlockDataFormat::from_env(),
+                    )
+                });
+                (name, shard, cache_size, format)
             })
             .collect();
 
@@ -251,7 +264,9 @@ impl StoreBuilder {
         )
     }
 
-    /// Create connection pools for each of the replicas
+    /// Create connection pools for each of the replicas. Also return the
+    /// weights of the main pool and the replicas, and the indexes of the
+    /// replicas that only serve queries for historical blocks
     fn replica_pools(
         logger: &Logger,
         node: &NodeId,
@@ -259,40 +274,43 @@ impl StoreBuilder {
         shard: &Shard,
         registry: Arc<MetricsRegistry>,
         coord: Arc<PoolCoordinator>,
-    ) -> (Vec<ConnectionPool>, Vec<usize>) {
+    ) -> (Vec<ConnectionPool>, Vec<usize>, Vec<usize>) {
         let mut weights: Vec<_> = vec![shard.weight];
-        (
-            shard
-                .replicas
-                .values()
-                .enumerate()
-                .map(|(i, replica)| {
-                    let pool = format!("replica{}", i + 1);
-                    let logger = logger.new(o!("pool" => pool.clone()));
-                    info!(
-                        &logger,
-                        "Connecting to Postgres (read replica {})", i+1;
-                        "url" => SafeDisplay(replica.connection.as_str()),
-                        "weight" => replica.weight
-                    );
-                    weights.push(replica.weight);
-                    let pool_size = replica.pool_size.size_for(node, name).unwrap_or_else(|_| {
-                        panic!("we can determine the pool size for replica {}", name)
-                    });
+        let mut history = Vec::new();
+        let pools = shard
+            .replicas
+            .values()
+            .enumerate()
+            .map(|(i, replica)| {
+                let pool = format!("replica{}", i + 1);
+                let logger = logger.new(o!("pool" => pool.clone()));
+                info!(
+                    &logger,
+                    "Connecting to Postgres (read replica {})", i+1;
+                    "url" => SafeDisplay(replica.connection.as_str()),
+                    "weight" => replica.weight,
+                    "history" => replica.history
+                );
+                weights.push(replica.weight);
+                if replica.history {
+                    history.push(i);
+                }
+                let pool_size = replica.pool_size.size_for(node, name).unwrap_or_else(|_| {
+                    panic!("we can determine the pool size for replica {}", name)
+                });
 
-                    coord.clone().create_pool(
-                        &logger,
-                        name,
-                        PoolRole::Replica(pool),
-                        replica.connection.clone(),
-                        pool_size,
-                        None,
-                        registry.cheap_clone(),
-                    )
-                })
-                .collect(),
-            weights,
-        )
+                coord.clone().create_pool(
+                    &logger,
+                    name,
+                    PoolRole::Replica(pool),
+                    replica.connection.clone(),
+                    pool_size,
+                    None,
+                    registry.cheap_clone(),
+                )
+            })
+            .collect();
+        (pools, weights, history)
     }
 
     /// Return a store that combines both a `Store` for subgraph data
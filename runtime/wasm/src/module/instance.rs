@@ -457,8 +457,16 @@ pub(crate) fn build_linker(
     // Chain-specific host functions. Each is registered explicitly rather than
     // discovered dynamically from imports.
     link_chain_host_fn(&mut linker, import_name_to_modules, "ethereum.call")?;
+    link_chain_host_fn(&mut linker, import_name_to_modules, "ethereum.callAtBlock")?;
     link_chain_host_fn(&mut linker, import_name_to_modules, "ethereum.getBalance")?;
     link_chain_host_fn(&mut linker, import_name_to_modules, "ethereum.hasCode")?;
+    link_chain_host_fn(&mut linker, import_name_to_modules, "ethereum.feeHistory")?;
+    link_chain_host_fn(&mut linker, import_name_to_modules, "ethereum.getLogs")?;
+    link_chain_host_fn(
+        &mut linker,
+        import_name_to_modules,
+        "ethereum.hashTypedData",
+    )?;
 
     link!("ethereum.encode", ethereum_encode, params_ptr);
     link!("ethereum.decode", ethereum_decode, params_ptr, data_ptr);
@@ -12,6 +12,10 @@ use axum::Json;
 use axum::extract::{ConnectInfo, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
+use graph::blockchain::{BlockchainMap, ChainHealth, IngestorPause};
+use graph::components::network_provider::ChainName;
+use graph::components::store::WriteRateLimit;
+use graph::ipfs::SwappableIpfsClient;
 use graph::prelude::{
     DeploymentHash, ENV_VARS, NodeId, SubgraphName, SubgraphRegistrar, SubgraphRegistrarError,
 };
@@ -29,6 +33,10 @@ mod error_codes {
     pub const REASSIGN_ERROR: i64 = 3;
     pub const PAUSE_ERROR: i64 = 4;
     pub const RESUME_ERROR: i64 = 5;
+    pub const INGESTOR_PAUSE_ERROR: i64 = 6;
+    pub const INGESTOR_RESUME_ERROR: i64 = 7;
+    pub const SHARD_LIMIT_WRITES_ERROR: i64 = 8;
+    pub const IPFS_SWAP_SERVERS_ERROR: i64 = 9;
 }
 
 /// Shared application state for the JSON-RPC server.
@@ -36,6 +44,10 @@ pub struct AppState<R> {
     pub registrar: Arc<R>,
     pub http_port: u16,
     pub node_id: NodeId,
+    pub ingestors: BTreeMap<ChainName, IngestorPause>,
+    pub write_limits: BTreeMap<String, WriteRateLimit>,
+    pub ipfs_client: Arc<SwappableIpfsClient>,
+    pub blockchain_map: Arc<BlockchainMap>,
     pub logger: Logger,
 }
 
@@ -65,6 +77,11 @@ async fn dispatch_request<R: SubgraphRegistrar>(
         "subgraph_reassign" => handle_reassign(state, &request, id).await,
         "subgraph_pause" => handle_pause(state, &request, id).await,
         "subgraph_resume" => handle_resume(state, &request, id).await,
+        "ingestor_pause" => handle_ingestor_pause(state, &request, id).await,
+        "ingestor_resume" => handle_ingestor_resume(state, &request, id).await,
+        "shard_limit_writes" => handle_shard_limit_writes(state, &request, id).await,
+        "ipfs_swap_servers" => handle_ipfs_swap_servers(state, &request, id).await,
+        "chain_health" => handle_chain_health(state, id).await,
         _ => JsonRpcResponse::error(id, JsonRpcError::method_not_found()),
     };
 
@@ -401,6 +418,193 @@ async fn handle_resume<R: SubgraphRegistrar>(
     )
 }
 
+// Parameter struct and lookup for ingestor pause and resume
+
+#[derive(Debug, Deserialize)]
+pub struct IngestorPauseParams {
+    pub network: ChainName,
+}
+
+/// Find the pause switch for the block ingestor named in the request.
+#[allow(clippy::result_large_err)]
+fn ingestor_pause<'a, R>(
+    state: &'a AppState<R>,
+    method: &str,
+    error_code: i64,
+    request: &JsonRpcRequest,
+    id: JsonRpcId,
+) -> Result<&'a IngestorPause, JsonRpcResponse> {
+    let params: IngestorPauseParams = parse_params(request, id.clone())?;
+
+    state.ingestors.get(&params.network).ok_or_else(|| {
+        error!(state.logger, "{} failed", method;
+            "error" => "no block ingestor for network",
+            "params" => format!("{:?}", params));
+
+        let message = format!(
+            "no block ingestor for network `{}` runs on this node",
+            params.network
+        );
+        JsonRpcResponse::error(id, JsonRpcError::new(error_code, message))
+    })
+}
+
+/// Handler for `ingestor_pause`.
+async fn handle_ingestor_pause<R>(
+    state: &AppState<R>,
+    request: &JsonRpcRequest,
+    id: JsonRpcId,
+) -> JsonRpcResponse {
+    match ingestor_pause(
+        state,
+        "ingestor_pause",
+        error_codes::INGESTOR_PAUSE_ERROR,
+        request,
+        id.clone(),
+    ) {
+        Ok(pause) => {
+            pause.pause();
+            JsonRpcResponse::success(id, JsonValue::Null)
+        }
+        Err(resp) => resp,
+    }
+}
+
+/// Handler for `ingestor_resume`.
+async fn handle_ingestor_resume<R>(
+    state: &AppState<R>,
+    request: &JsonRpcRequest,
+    id: JsonRpcId,
+) -> JsonRpcResponse {
+    match ingestor_pause(
+        state,
+        "ingestor_resume",
+        error_codes::INGESTOR_RESUME_ERROR,
+        request,
+        id.clone(),
+    ) {
+        Ok(pause) => {
+            pause.resume();
+            JsonRpcResponse::success(id, JsonValue::Null)
+        }
+        Err(resp) => resp,
+    }
+}
+
+// Parameter struct and handler for changing the write limit of a shard
+
+#[derive(Debug, Deserialize)]
+pub struct ShardLimitWritesParams {
+    pub shard: String,
+    /// The maximum number of entity versions per second; 0 removes the limit
+    pub rows_per_second: u64,
+}
+
+/// Handler for `shard_limit_writes`.
+async fn handle_shard_limit_writes<R>(
+    state: &AppState<R>,
+    request: &JsonRpcRequest,
+    id: JsonRpcId,
+) -> JsonRpcResponse {
+    let params: ShardLimitWritesParams = match parse_params(request, id.clone()) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    match state.write_limits.get(&params.shard) {
+        Some(limit) => {
+            info!(state.logger, "Changing the write limit of a shard";
+                "shard" => &params.shard,
+                "rows_per_second" => params.rows_per_second);
+            limit.set_rows_per_second(params.rows_per_second);
+            JsonRpcResponse::success(id, JsonValue::Null)
+        }
+        None => {
+            error!(state.logger, "shard_limit_writes failed";
+                "error" => "unknown shard",
+                "params" => format!("{:?}", params));
+
+            let message = format!("there is no shard `{}`", params.shard);
+            JsonRpcResponse::error(
+                id,
+                JsonRpcError::new(error_codes::SHARD_LIMIT_WRITES_ERROR, message),
+            )
+        }
+    }
+}
+
+/// Parameters for `ipfs_swap_servers`.
+#[derive(Debug, Deserialize)]
+pub struct IpfsSwapServersParams {
+    /// The addresses of the IPFS servers that new requests should go to
+    pub servers: Vec<String>,
+}
+
+/// Handler for `ipfs_swap_servers`.
+async fn handle_ipfs_swap_servers<R>(
+    state: &AppState<R>,
+    request: &JsonRpcRequest,
+    id: JsonRpcId,
+) -> JsonRpcResponse {
+    let params: IpfsSwapServersParams = match parse_params(request, id.clone()) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    match state
+        .ipfs_client
+        .swap_servers(&params.servers, &state.logger)
+        .await
+    {
+        Ok(()) => JsonRpcResponse::success(id, JsonValue::Null),
+        Err(e) => {
+            error!(state.logger, "ipfs_swap_servers failed";
+                "error" => format!("{:?}", e),
+                "params" => format!("{:?}", params));
+
+            JsonRpcResponse::error(
+                id,
+                JsonRpcError::new(error_codes::IPFS_SWAP_SERVERS_ERROR, e.to_string()),
+            )
+        }
+    }
+}
+
+/// Handler for `chain_health`. Reports the health of every chain as an
+/// object with the chain's `network`, `kind` and `status`, and either the
+/// chain `head` or the `error` that determining it ran into
+async fn handle_chain_health<R>(state: &AppState<R>, id: JsonRpcId) -> JsonRpcResponse {
+    let report = state
+        .blockchain_map
+        .health_report()
+        .await
+        .into_iter()
+        .map(|(network, kind, health)| {
+            let mut entry = serde_json::json!({
+                "network": network.as_str(),
+                "kind": kind.to_string(),
+            });
+            match health {
+                ChainHealth::Healthy(head) => {
+                    entry["status"] = "healthy".into();
+                    entry["head"] = serde_json::json!({
+                        "number": head.number,
+                        "hash": head.hash_hex(),
+                    });
+                }
+                ChainHealth::Unhealthy(error) => {
+                    entry["status"] = "unhealthy".into();
+                    entry["error"] = error.into();
+                }
+                ChainHealth::TimedOut => entry["status"] = "timed_out".into(),
+            }
+            entry
+        })
+        .collect();
+
+    JsonRpcResponse::success(id, JsonValue::Array(report))
+}
+
 /// Build the subgraph routes response for deploy.
 fn subgraph_routes(name: &SubgraphName, http_port: u16) -> JsonValue {
     let http_base_url = ENV_VARS
@@ -1,7 +1,10 @@
 //! JSON-RPC admin server for subgraph management.
 //!
 //! This crate provides a JSON-RPC 2.0 server for managing subgraphs,
-//! supporting operations like create, deploy, remove, reassign, pause, and resume.
+//! supporting operations like create, deploy, remove, reassign, pause, and resume,
+//! as well as pausing and resuming the block ingestor for a chain, limiting the
+//! writes to a shard, swapping the IPFS servers, and checking the health of
+//! all chains.
 
 mod handlers;
 mod jsonrpc;
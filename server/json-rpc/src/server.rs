@@ -3,11 +3,16 @@
 //! This module provides the `JsonRpcServer` that serves JSON-RPC requests
 //! over HTTP using axum.
 
+use std::collections::BTreeMap;
 use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
 use axum::Router;
 use axum::routing::post;
+use graph::blockchain::{BlockchainMap, IngestorPause};
+use graph::components::network_provider::ChainName;
+use graph::components::store::WriteRateLimit;
+use graph::ipfs::SwappableIpfsClient;
 use graph::prelude::{NodeId, SubgraphRegistrar};
 use slog::{Logger, info};
 use thiserror::Error;
@@ -38,12 +43,20 @@ impl JsonRpcServer {
     /// * `http_port` - The HTTP port used for subgraph route URLs in deploy responses
     /// * `registrar` - The subgraph registrar for handling operations
     /// * `node_id` - Default node ID for deployments
+    /// * `ingestors` - Pause switches for the block ingestors running on this node
+    /// * `write_limits` - Write limits for the shards, keyed by shard name
+    /// * `ipfs_client` - The IPFS client whose servers operators can swap
+    /// * `blockchain_map` - The chains whose health operators can check
     /// * `logger` - Logger for request/response logging
     pub async fn serve<R>(
         port: u16,
         http_port: u16,
         registrar: Arc<R>,
         node_id: NodeId,
+        ingestors: BTreeMap<ChainName, IngestorPause>,
+        write_limits: BTreeMap<String, WriteRateLimit>,
+        ipfs_client: Arc<SwappableIpfsClient>,
+        blockchain_map: Arc<BlockchainMap>,
         logger: Logger,
     ) -> Result<Self, JsonRpcServerError>
     where
@@ -60,6 +73,10 @@ impl JsonRpcServer {
             registrar,
             http_port,
             node_id,
+            ingestors,
+            write_limits,
+            ipfs_client,
+            blockchain_map,
             logger,
         });
 
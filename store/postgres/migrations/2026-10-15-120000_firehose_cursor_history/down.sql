@@ -0,0 +1 @@
+drop table subgraphs.firehose_cursor_history;
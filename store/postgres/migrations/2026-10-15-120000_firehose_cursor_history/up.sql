@@ -0,0 +1,12 @@
+-- The Firehose cursors that deployments stored together with the blocks
+-- they wrote; only filled if GRAPH_STORE_FIREHOSE_CURSOR_HISTORY is set
+create table subgraphs.firehose_cursor_history (
+    id              int4 not null references subgraphs.head(id) on delete cascade,
+    cursor          text not null,
+    block_number    int4 not null,
+    block_hash      bytea not null,
+    primary key (id, cursor)
+);
+
+create index firehose_cursor_history_block_number
+    on subgraphs.firehose_cursor_history(id, block_number);
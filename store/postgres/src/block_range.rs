@@ -317,6 +317,18 @@ impl<'a> BlockRangeColumn<'a> {
         }
     }
 
+    /// Output the first block at which rows are valid
+    pub fn lower(&self, out: &mut AstPass<Pg>) {
+        match self {
+            BlockRangeColumn::Mutable { .. } => {
+                out.push_sql("lower(");
+                self.name(out);
+                out.push_sql(")");
+            }
+            BlockRangeColumn::Immutable { .. } => self.name(out),
+        }
+    }
+
     /// Output an expression that matches rows that are the latest version
     /// of their entity
     pub fn latest(&self, out: &mut AstPass<Pg>) {
@@ -349,6 +361,24 @@ impl<'a> BlockRangeColumn<'a> {
         }
     }
 
+    /// Output an expression that matches all rows whose block range ended
+    /// at or before `block`, i.e., the rows that pruning history below
+    /// `block` deletes. Versions of immutable entities never end.
+    pub(crate) fn closed_before<'b>(&'b self, out: &mut AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        match self {
+            BlockRangeColumn::Mutable { block, .. } => {
+                out.push_sql("coalesce(upper(");
+                out.push_identifier(BLOCK_RANGE_COLUMN)?;
+                out.push_sql("), 2147483647) <= ");
+                out.push_bind_param::<Integer, _>(block)
+            }
+            BlockRangeColumn::Immutable { .. } => {
+                out.push_sql("false");
+                Ok(())
+            }
+        }
+    }
+
     /// Output an expression that matches all rows that have been changed
     /// after `block` (inclusive)
     pub(crate) fn changed_since<'b>(&'b self, out: &mut AstPass<'_, 'b, Pg>) -> QueryResult<()> {
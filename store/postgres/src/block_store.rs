@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use diesel::{ExpressionMethods as _, QueryDsl, sql_query};
 use diesel_async::RunQueryDsl;
 use graph::{
-    blockchain::ChainIdentifier,
+    blockchain::{BlockDataFormat, ChainIdentifier},
     components::store::{BlockStore as BlockStoreTrait, QueryPermit},
     derive::CheapClone,
     prelude::{BlockNumber, BlockPtr, ENV_VARS, Logger, error, info, warn},
@@ -218,8 +218,8 @@ pub struct Inner {
     /// previous state in the database.
     stores: RwLock<HashMap<String, Arc<ChainStore>>>,
     /// We keep this information so we can create chain stores during
-    /// startup. The triple is (network, shard, cache_size)
-    shards: Vec<(String, Shard, BlockNumber)>,
+    /// startup. The tuple is (network, shard, cache_size, block_data_format)
+    shards: Vec<(String, Shard, BlockNumber, BlockDataFormat)>,
     pools: HashMap<Shard, ConnectionPool>,
     sender: Arc<NotificationSender>,
     mirror: PrimaryMirror,
@@ -241,8 +241,8 @@ impl BlockStore {
     /// a chain uses the pool from `pools` for the given shard.
     pub async fn new(
         logger: Logger,
-        // (network, shard, cache_size)
-        shards: Vec<(String, Shard, BlockNumber)>,
+        // (network, shard, cache_size, block_data_format)
+        shards: Vec<(String, Shard, BlockNumber, BlockDataFormat)>,
         // shard -> pool
         pools: HashMap<Shard, ConnectionPool>,
         sender: Arc<NotificationSender>,
@@ -290,7 +290,7 @@ impl BlockStore {
         let block_store = Self { inner };
 
         // For each configured chain, add a chain store
-        for (chain_name, shard, _cache_size) in chains {
+        for (chain_name, shard, _cache_size, _format) in chains {
             if let Some(chain) = existing_chains
                 .iter()
                 .find(|chain| chain.name == chain_name)
@@ -382,17 +382,17 @@ impl BlockStore {
         );
         let ident = chain.network_identifier()?;
         let logger = self.logger.new(o!("network" => chain.name.clone()));
-        let cache_size = self
+        let (cache_size, block_data_format) = self
             .shards
             .iter()
-            .find_map(|(network, _, chain_size)| {
+            .find_map(|(network, _, chain_size, format)| {
                 if network == &chain.name {
-                    Some(*chain_size)
+                    Some((*chain_size, *format))
                 } else {
                     None
                 }
             })
-            .unwrap_or(BLOCK_CACHE_SIZE);
+            .unwrap_or_else(|| (BLOCK_CACHE_SIZE, BlockDataFormat::from_env()));
         let store = ChainStore::new(
             logger,
             chain.name.clone(),
@@ -402,6 +402,7 @@ impl BlockStore {
             ENV_VARS.store.recent_blocks_cache_capacity,
             self.chain_store_metrics.clone(),
             cache_size,
+            block_data_format,
         );
         if create {
             store.create(&ident).await?;
@@ -533,7 +534,8 @@ impl BlockStore {
             };
 
             if let Some(head_block) = store.remove_cursor(&store.chain).await? {
-                let lower_bound = head_block.saturating_sub(ENV_VARS.reorg_threshold() * 2);
+                let lower_bound =
+                    head_block.saturating_sub(ENV_VARS.reorg_threshold_for(&store.chain) * 2);
                 info!(&self.logger, "Removed cursor for non-firehose chain, now cleaning shallow blocks"; "network" => &store.chain, "lower_bound" => lower_bound);
                 store.cleanup_shallow_blocks(lower_bound).await?;
             }
@@ -593,7 +595,7 @@ impl BlockStore {
         let shard = self
             .shards
             .iter()
-            .find_map(|(chain_id, shard, _cache_size)| {
+            .find_map(|(chain_id, shard, _cache_size, _format)| {
                 if chain_id.as_str().eq(network) {
                     Some(shard)
                 } else {
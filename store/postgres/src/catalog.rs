@@ -192,7 +192,8 @@ pub struct Catalog {
     pub has_minmax_multi_ops: bool,
 
     /// Whether the column `pg_stats.range_bounds_histogram` introduced in
-    /// Postgres 17 exists. See the [Postgres
+    /// Postgres 17 exists; `range_length_histogram` was introduced
+    /// together with it. See the [Postgres
     /// docs](https://www.postgresql.org/docs/17/view-pg-stats.html)
     pg_stats_has_range_bounds_histogram: bool,
 }
@@ -312,6 +313,7 @@ impl Catalog {
                     ratio: s.ratio,
                     last_pruned_block: s.last_pruned_block,
                     block_range_upper: vec![],
+                    block_range_width: vec![],
                 }
             }
         }
@@ -344,6 +346,35 @@ impl Catalog {
             Ok(result)
         }
 
+        #[derive(Queryable, QueryableByName)]
+        struct WidthHistogram {
+            #[diesel(sql_type = Text)]
+            tablename: String,
+            #[diesel(sql_type = Array<Integer>)]
+            width: Vec<i32>,
+        }
+
+        async fn block_range_width_histogram(
+            conn: &mut AsyncPgConnection,
+            namespace: &Namespace,
+        ) -> Result<Vec<WidthHistogram>, StoreError> {
+            // Open block ranges have an infinite length
+            let query = format!(
+                "select tablename, \
+                array_agg(least(width, {BLOCK_NUMBER_MAX})::int4 order by width) width \
+           from (select tablename,
+                        unnest(range_length_histogram::text::float8[]) width
+                   from pg_stats where schemaname = $1 and attname = '{BLOCK_RANGE_COLUMN}') a
+          group by tablename
+          order by tablename"
+            );
+            let result = sql_query(query)
+                .bind::<Text, _>(namespace.as_str())
+                .get_results::<WidthHistogram>(conn)
+                .await?;
+            Ok(result)
+        }
+
         // Get an estimate of number of rows (pg_class.reltuples) and number of
         // distinct entities (based on the planners idea of how many distinct
         // values there are in the `id` column) See the [Postgres
@@ -378,10 +409,14 @@ impl Catalog {
             .await
             .map_err(StoreError::from)?;
 
-        let mut range_histogram = if self.pg_stats_has_range_bounds_histogram {
-            block_range_histogram(conn, &self.site.namespace).await?
+        let (mut range_histogram, mut width_histogram) = if self.pg_stats_has_range_bounds_histogram
+        {
+            (
+                block_range_histogram(conn, &self.site.namespace).await?,
+                block_range_width_histogram(conn, &self.site.namespace).await?,
+            )
         } else {
-            vec![]
+            (vec![], vec![])
         };
 
         let stats = stats
@@ -397,8 +432,14 @@ impl Catalog {
                 // Since lower and upper are supposed to be histograms, we
                 // sort them
                 upper.sort_unstable();
+                let width = width_histogram
+                    .iter()
+                    .position(|h| h.tablename == s.tablename)
+                    .map(|pos| width_histogram.swap_remove(pos).width)
+                    .unwrap_or_default();
                 let mut vs = VersionStats::from(s);
                 vs.block_range_upper = upper;
+                vs.block_range_width = width;
                 vs
             })
             .collect::<Vec<_>>();
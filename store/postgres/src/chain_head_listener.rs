@@ -7,6 +7,7 @@ use graph::{
 };
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 
@@ -19,6 +20,7 @@ use graph::blockchain::ChainHeadUpdateListener as ChainHeadUpdateListenerTrait;
 use graph::prelude::serde::{Deserialize, Serialize};
 use graph::prelude::serde_json::{self, json};
 use graph::prelude::tokio::sync::{mpsc::Receiver, watch};
+use graph::prelude::tokio::time::Instant;
 use graph::prelude::{CheapClone, ENV_VARS, Logger, crit, debug, o};
 
 lazy_static! {
@@ -207,39 +209,60 @@ impl ChainHeadUpdateListenerTrait for ChainHeadUpdateListener {
             }
         };
 
-        Box::new(futures03::stream::unfold(
+        update_stream(
             update_receiver,
-            move |mut update_receiver| {
-                let logger = logger.clone();
-                async move {
-                    // To be robust against any problems with the listener for the DB channel, a
-                    // timeout is set so that subscribers are guaranteed to get periodic updates.
-                    match tokio::time::timeout(
-                        ENV_VARS.store.chain_head_watcher_timeout,
-                        update_receiver.changed(),
-                    )
-                    .await
-                    {
-                        // Received an update.
-                        Ok(Ok(())) => (),
-
-                        // The sender was dropped, this should never happen.
-                        Ok(Err(_)) => crit!(logger, "chain head watcher terminated"),
-
-                        Err(_) => debug!(
-                            logger,
-                            "no chain head update for {} seconds, polling for update",
-                            ENV_VARS.store.chain_head_watcher_timeout.as_secs()
-                        ),
-                    };
-                    Some(((), update_receiver))
-                }
-                .boxed()
-            },
-        ))
+            logger,
+            ENV_VARS.store.chain_head_update_window,
+        )
     }
 }
 
+/// Turns the updates from `update_receiver` into a stream of
+/// notifications. After each notification, the stream waits for `window`
+/// before it produces the next one, so that all updates during the window
+/// lead to a single notification.
+fn update_stream(
+    update_receiver: watch::Receiver<()>,
+    logger: Logger,
+    window: Duration,
+) -> ChainHeadUpdateStream {
+    Box::new(futures03::stream::unfold(
+        (update_receiver, None),
+        move |(mut update_receiver, last_notified): (_, Option<Instant>)| {
+            let logger = logger.clone();
+            async move {
+                if let Some(last_notified) = last_notified {
+                    tokio::time::sleep_until(last_notified + window).await;
+                }
+
+                // To be robust against any problems with the listener for the DB channel, a
+                // timeout is set so that subscribers are guaranteed to get periodic updates.
+                match tokio::time::timeout(
+                    ENV_VARS.store.chain_head_watcher_timeout,
+                    update_receiver.changed(),
+                )
+                .await
+                {
+                    // Received an update.
+                    Ok(Ok(())) => (),
+
+                    // The sender was dropped, this should never happen.
+                    Ok(Err(_)) => crit!(logger, "chain head watcher terminated"),
+
+                    Err(_) => debug!(
+                        logger,
+                        "no chain head update for {} seconds, polling for update",
+                        ENV_VARS.store.chain_head_watcher_timeout.as_secs()
+                    ),
+                };
+                let last_notified = (!window.is_zero()).then(Instant::now);
+                Some(((), (update_receiver, last_notified)))
+            }
+            .boxed()
+        },
+    ))
+}
+
 impl ChainHeadUpdateSender {
     pub fn new(
         pool: ConnectionPool,
@@ -271,3 +294,40 @@ impl ChainHeadUpdateSender {
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graph::futures03::StreamExt as _;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn rapid_updates_are_coalesced() {
+        let watcher = Watcher::new();
+        let mut updates = update_stream(
+            watcher.receiver.clone(),
+            graph::log::discard(),
+            Duration::from_millis(200),
+        );
+
+        let send = async {
+            for _ in 0..50 {
+                watcher.send();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let receive = async {
+            let deadline = Instant::now() + Duration::from_millis(700);
+            let mut notifications = 0;
+            while let Ok(Some(())) = tokio::time::timeout_at(deadline, updates.next()).await {
+                notifications += 1;
+            }
+            notifications
+        };
+        let ((), notifications) = tokio::join!(send, receive);
+
+        // The first update is reported right away, and the updates until
+        // the last one at 490ms once every 200ms after that
+        assert_eq!(4, notifications);
+    }
+}
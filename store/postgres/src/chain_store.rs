@@ -7,6 +7,7 @@ use diesel_async::RunQueryDsl;
 use graph::components::store::ChainHeadStore;
 use graph::data::store::ethereum::call;
 use graph::env::ENV_VARS;
+use graph::futures03::stream::{self, BoxStream, StreamExt as _, TryStreamExt as _};
 use graph::parking_lot::RwLock;
 use graph::prelude::MetricsRegistry;
 use graph::prelude::alloy::primitives::B256;
@@ -23,16 +24,17 @@ use std::{
     collections::HashMap,
     convert::{TryFrom, TryInto},
     iter::FromIterator,
+    ops::Range,
     sync::Arc,
 };
 
-use graph::blockchain::{Block, BlockHash, ChainIdentifier, ExtendedBlockPtr};
+use graph::blockchain::{Block, BlockDataFormat, BlockHash, ChainIdentifier, ExtendedBlockPtr};
 use graph::cheap_clone::CheapClone;
 use graph::components::ethereum::CachedBlock;
 use graph::prelude::{
     BlockNumber, BlockPtr, CachedEthereumCall, ChainStore as ChainStoreTrait, Error,
-    EthereumCallCache, StaleCallCacheResult, StoreError, serde_json as json,
-    transaction_receipt::LightTransactionReceipt,
+    EthereumCallCache, LightEthereumBlockExt as _, StaleCallCacheResult, StoreError,
+    serde_json as json, transaction_receipt::LightTransactionReceipt,
 };
 use graph::{ensure, internal_error};
 
@@ -144,7 +146,7 @@ mod data {
         update,
     };
     use diesel_async::{RunQueryDsl, SimpleAsyncConnection};
-    use graph::blockchain::{Block, BlockHash};
+    use graph::blockchain::{Block, BlockDataFormat, BlockHash, decompress_block};
     use graph::data::store::scalar::Bytes;
     use graph::internal_error;
     use graph::prelude::alloy::primitives::{Address, B256};
@@ -158,6 +160,7 @@ mod data {
     use std::convert::TryFrom;
     use std::fmt;
     use std::iter::FromIterator;
+    use std::ops::Range;
     use std::str::FromStr;
 
     use std::time::Instant;
@@ -569,82 +572,98 @@ mod data {
         /// same hash, then overwrite that block since it may be adding
         /// transaction receipts. If `overwrite` is `true`, overwrite a
         /// possibly existing entry. If it is `false`, keep the old entry.
+        ///
+        /// Return the data that was stored for the hash before, if there
+        /// was any, in the form that `Block::data` produces
         pub(super) async fn upsert_block(
             &self,
             conn: &mut AsyncPgConnection,
             chain: &str,
             block: &dyn Block,
             overwrite: bool,
-        ) -> Result<(), StoreError> {
+            format: BlockDataFormat,
+        ) -> Result<Option<json::Value>, StoreError> {
             // Hash indicating 'no parent'. It seems to be customary at
             // least on EVM-compatible chains to fill the parent hash of the
             // genesis block with this value
             const NO_PARENT: &str =
                 "0000000000000000000000000000000000000000000000000000000000000000";
 
+            #[derive(QueryableByName)]
+            struct OldData {
+                #[diesel(sql_type = Nullable<Jsonb>)]
+                data: Option<json::Value>,
+            }
+
             let number = block.number() as i64;
-            let data = block.data().expect("Failed to serialize block");
+            let data = format.encode_block(block.data().expect("Failed to serialize block"));
             let hash = block.hash();
             let parent_hash = block.parent_hash().unwrap_or_else(|| {
                 BlockHash::try_from(NO_PARENT).expect("NO_PARENT is a valid hash")
             });
 
-            match self {
+            // The `old` CTE sees the table as it was before the insert
+            let old = match self {
                 Storage::Shared => {
-                    use public::ethereum_blocks as b;
-
-                    let values = (
-                        b::hash.eq(hash.hash_hex()),
-                        b::number.eq(number),
-                        b::parent_hash.eq(parent_hash.hash_hex()),
-                        b::network_name.eq(chain),
-                        b::data.eq(data),
-                    );
-
-                    if overwrite {
-                        insert_into(b::table)
-                            .values(values.clone())
-                            .on_conflict(b::hash)
-                            .do_update()
-                            .set(values)
-                            .execute(conn)
-                            .await?;
+                    let on_conflict = if overwrite {
+                        "do update set number = $2, parent_hash = $3, network_name = $4, data = $5"
                     } else {
-                        insert_into(b::table)
-                            .values(values.clone())
-                            .on_conflict(b::hash)
-                            .do_nothing()
-                            .execute(conn)
-                            .await?;
-                    }
+                        "do nothing"
+                    };
+                    let query = format!(
+                        "with old as (select data \
+                                        from ethereum_blocks where hash = $1), \
+                              new as (insert into ethereum_blocks(hash, number, parent_hash, network_name, data) \
+                                      values ($1, $2, $3, $4, $5) \
+                                          on conflict(hash) {on_conflict}) \
+                         select data from old"
+                    );
+                    sql_query(query)
+                        .bind::<Text, _>(hash.hash_hex())
+                        .bind::<BigInt, _>(number)
+                        .bind::<Text, _>(parent_hash.hash_hex())
+                        .bind::<Text, _>(chain)
+                        .bind::<Jsonb, _>(data)
+                        .get_result::<OldData>(conn)
+                        .await
+                        .optional()?
                 }
                 Storage::Private(Schema { blocks, .. }) => {
-                    let query = if overwrite {
-                        format!(
-                            "insert into {}(hash, number, parent_hash, data) \
-                             values ($1, $2, $3, $4) \
-                                 on conflict(hash) \
-                                 do update set number = $2, parent_hash = $3, data = $4",
-                            blocks.qname,
-                        )
+                    let on_conflict = if overwrite {
+                        "do update set number = $2, parent_hash = $3, data = $4"
                     } else {
-                        format!(
-                            "insert into {}(hash, number, parent_hash, data) \
-                             values ($1, $2, $3, $4) \
-                                 on conflict(hash) do nothing",
-                            blocks.qname
-                        )
+                        "do nothing"
                     };
+                    let query = format!(
+                        "with old as (select data \
+                                        from {qname} where hash = $1), \
+                              new as (insert into {qname}(hash, number, parent_hash, data) \
+                                      values ($1, $2, $3, $4) \
+                                          on conflict(hash) {on_conflict}) \
+                         select data from old",
+                        qname = blocks.qname,
+                    );
                     sql_query(query)
                         .bind::<Bytea, _>(hash.as_slice())
                         .bind::<BigInt, _>(number)
                         .bind::<Bytea, _>(parent_hash.as_slice())
                         .bind::<Jsonb, _>(data)
-                        .execute(conn)
-                        .await?;
+                        .get_result::<OldData>(conn)
+                        .await
+                        .optional()?
                 }
             };
-            Ok(())
+            let old = old
+                .and_then(|old| old.data)
+                .map(|mut data| -> Result<_, StoreError> {
+                    match data.get_mut("block") {
+                        Some(block) => *block = decompress_block(block.take())?,
+                        None => data = decompress_block(data)?,
+                    }
+                    Ok(data)
+                })
+                .transpose()?;
+            Ok(old)
         }
 
         pub(super) async fn block_ptrs_by_numbers(
@@ -688,11 +707,16 @@ mod data {
                 }
             }?;
 
-            Ok(x.into_iter()
-                .map(|(hash, nr, parent, data)| {
-                    JsonBlock::new(BlockPtr::new(hash, nr as i32), parent, Some(data))
+            x.into_iter()
+                .map(|(hash, nr, parent, data)| -> Result<_, StoreError> {
+                    let data = decompress_block(data)?;
+                    Ok(JsonBlock::new(
+                        BlockPtr::new(hash, nr as i32),
+                        parent,
+                        Some(data),
+                    ))
                 })
-                .collect())
+                .collect()
         }
 
         pub(super) async fn blocks(
@@ -745,11 +769,16 @@ mod data {
                         .await
                 }
             }?;
-            Ok(x.into_iter()
-                .map(|(hash, nr, parent, data)| {
-                    JsonBlock::new(BlockPtr::new(hash, nr as i32), parent, Some(data))
+            x.into_iter()
+                .map(|(hash, nr, parent, data)| -> Result<_, StoreError> {
+                    let data = decompress_block(data)?;
+                    Ok(JsonBlock::new(
+                        BlockPtr::new(hash, nr as i32),
+                        parent,
+                        Some(data),
+                    ))
                 })
-                .collect())
+                .collect()
         }
 
         /// Return the parent block pointer for the block with the given hash.
@@ -819,6 +848,46 @@ mod data {
             Ok(result)
         }
 
+        /// Return the pointers and parent hashes of the blocks whose
+        /// number is in `range`, ordered by number and hash
+        pub(super) async fn block_ptrs_in_range(
+            &self,
+            conn: &mut AsyncPgConnection,
+            chain: &str,
+            range: Range<BlockNumber>,
+        ) -> Result<Vec<(BlockPtr, BlockHash)>, StoreError> {
+            let (start, end) = (range.start as i64, range.end as i64);
+            let ptrs = match self {
+                Storage::Shared => {
+                    use public::ethereum_blocks as b;
+
+                    b::table
+                        .select((b::hash, b::number, b::parent_hash))
+                        .filter(b::network_name.eq(chain))
+                        .filter(b::number.ge(start))
+                        .filter(b::number.lt(end))
+                        .order_by((b::number, b::hash))
+                        .load::<(BlockHash, i64, BlockHash)>(conn)
+                        .await
+                }
+                Storage::Private(Schema { blocks, .. }) => {
+                    blocks
+                        .table()
+                        .select((blocks.hash(), blocks.number(), blocks.parent_hash()))
+                        .filter(blocks.number().ge(start))
+                        .filter(blocks.number().lt(end))
+                        .order_by((blocks.number(), blocks.hash()))
+                        .load::<(BlockHash, i64, BlockHash)>(conn)
+                        .await
+                }
+            }?;
+
+            Ok(ptrs
+                .into_iter()
+                .map(|(hash, number, parent)| (BlockPtr::new(hash, number as i32), parent))
+                .collect())
+        }
+
         pub(super) async fn block_hashes_by_block_number(
             &self,
             conn: &mut AsyncPgConnection,
@@ -1283,17 +1352,17 @@ mod data {
             let data_and_ptr = {
                 use graph::prelude::serde_json::json;
 
-                data_and_ptr.map(|(data, ptr)| {
-                    (
-                        match data.get("block") {
-                            Some(_) => data,
-                            None => json!({ "block": data, "transaction_receipts": [] }),
-                        },
-                        ptr,
-                    )
+                data_and_ptr.map(|(mut data, ptr)| -> Result<_, Error> {
+                    match data.get_mut("block") {
+                        Some(block) => {
+                            *block = decompress_block(block.take())?;
+                            Ok((data, ptr))
+                        }
+                        None => Ok((json!({ "block": data, "transaction_receipts": [] }), ptr)),
+                    }
                 })
             };
-            Ok(data_and_ptr)
+            data_and_ptr.transpose()
         }
 
         /// Like `ancestor_block` but returns only the `BlockPtr` without
@@ -2345,6 +2414,8 @@ pub struct ChainStore {
     /// Number of blocks from chain head for which to keep block data cached.
     /// Used with `GRAPH_STORE_IGNORE_BLOCK_CACHE` to simulate block data eviction.
     cache_size: BlockNumber,
+    /// The format in which blocks are written
+    block_data_format: BlockDataFormat,
 }
 
 impl ChainStore {
@@ -2357,6 +2428,7 @@ impl ChainStore {
         recent_blocks_cache_capacity: usize,
         metrics: Arc<ChainStoreMetrics>,
         cache_size: BlockNumber,
+        block_data_format: BlockDataFormat,
     ) -> Self {
         let recent_blocks_cache =
             RecentBlocksCache::new(recent_blocks_cache_capacity, chain.clone(), metrics.clone());
@@ -2378,6 +2450,7 @@ impl ChainStore {
             chain_head_ptr_cache,
             chain_head_ptr_herd,
             cache_size,
+            block_data_format,
         }
     }
 
@@ -2813,7 +2886,13 @@ impl ChainHeadStore for ChainStore {
         let mut conn = self.pool.get_permitted().await?;
         conn.transaction(async |conn| {
             self.storage
-                .upsert_block(conn, &self.chain, block.as_ref(), true)
+                .upsert_block(
+                    conn,
+                    &self.chain,
+                    block.as_ref(),
+                    true,
+                    self.block_data_format,
+                )
                 .await?;
 
             update(n::table.filter(n::name.eq(&self.chain)))
@@ -2853,18 +2932,82 @@ impl ChainStoreTrait for ChainStore {
         let mut conn = self.pool.get_permitted().await?;
         conn.transaction(async |conn| {
             self.storage
-                .upsert_block(conn, &self.chain, block.as_ref(), true)
+                .upsert_block(
+                    conn,
+                    &self.chain,
+                    block.as_ref(),
+                    true,
+                    self.block_data_format,
+                )
                 .await
+                .map(|_| ())
         })
         .await
         .map_err(Error::from)
     }
 
+    async fn upsert_block_unless_conflicting(
+        &self,
+        block: Arc<dyn Block>,
+        overwrite: bool,
+    ) -> Result<bool, Error> {
+        let new = block.data().ok().and_then(CachedBlock::from_json);
+
+        // Checking the data that the write replaced saves looking the
+        // block up first; on a conflict, the write is rolled back
+        let mut conn = self.pool.get_permitted().await?;
+        let res = conn
+            .transaction(async |conn| {
+                let old = self
+                    .storage
+                    .upsert_block(
+                        conn,
+                        &self.chain,
+                        block.as_ref(),
+                        overwrite,
+                        self.block_data_format,
+                    )
+                    .await?
+                    .and_then(CachedBlock::from_json);
+                match (old, &new) {
+                    (Some(old), Some(new)) if old.conflicts_with(new) => {
+                        Err(StoreError::from(diesel::result::Error::RollbackTransaction))
+                    }
+                    _ => Ok(()),
+                }
+            })
+            .await;
+        match res {
+            Ok(()) => {}
+            Err(StoreError::Unknown(e))
+                if matches!(
+                    e.downcast_ref::<diesel::result::Error>(),
+                    Some(diesel::result::Error::RollbackTransaction)
+                ) =>
+            {
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        if overwrite && let Some(parent_hash) = block.parent_hash() {
+            let json_block = JsonBlock::new(block.ptr(), parent_hash, block.data().ok());
+            self.recent_blocks_cache.insert_json_block(json_block);
+        }
+        Ok(true)
+    }
+
     async fn upsert_light_blocks(&self, blocks: &[&dyn Block]) -> Result<(), Error> {
         let mut conn = self.pool.get_permitted().await?;
         for block in blocks {
             self.storage
-                .upsert_block(&mut conn, &self.chain, *block, false)
+                .upsert_block(
+                    &mut conn,
+                    &self.chain,
+                    *block,
+                    false,
+                    self.block_data_format,
+                )
                 .await?;
         }
         Ok(())
@@ -3206,6 +3349,69 @@ impl ChainStoreTrait for ChainStore {
         }
     }
 
+    fn block_ptrs_in_range(
+        self: Arc<Self>,
+        range: Range<BlockNumber>,
+        batch_size: usize,
+    ) -> BoxStream<'static, Result<(BlockPtr, BlockHash), Error>> {
+        let batch_size = BlockNumber::try_from(batch_size.max(1)).unwrap_or(BlockNumber::MAX);
+        let Range { start, end } = range;
+
+        // The state is the first block number of the next batch, or `None`
+        // before the first batch
+        let batches = stream::try_unfold(None, move |next: Option<BlockNumber>| {
+            let store = self.cheap_clone();
+            async move {
+                let first = match next {
+                    Some(first) => first,
+                    // Blocks below the cutoff count as not being in the store
+                    None => start.max(store.cache_cutoff().await),
+                };
+                if first >= end {
+                    return Ok(None);
+                }
+                let last = first.saturating_add(batch_size).min(end);
+                let mut conn = store.pool.get_permitted().await?;
+                let ptrs = store
+                    .storage
+                    .block_ptrs_in_range(&mut conn, &store.chain, first..last)
+                    .await?;
+                Ok::<_, Error>(Some((ptrs, Some(last))))
+            }
+        });
+        batches
+            .map_ok(|ptrs| stream::iter(ptrs.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    fn blocks_in_range(
+        self: Arc<Self>,
+        range: Range<BlockNumber>,
+        batch_size: usize,
+    ) -> BoxStream<'static, Result<CachedBlock, Error>> {
+        let store = self.cheap_clone();
+        self.block_ptrs_in_range(range, batch_size)
+            .try_chunks(batch_size.max(1))
+            .map_err(|e| e.1)
+            .and_then(move |ptrs| {
+                let store = store.cheap_clone();
+                async move {
+                    // `blocks` does not preserve the order of the hashes
+                    let order: HashMap<_, _> = ptrs
+                        .into_iter()
+                        .enumerate()
+                        .map(|(pos, (ptr, _))| (ptr.hash, pos))
+                        .collect();
+                    let mut blocks = store.blocks(order.keys().cloned().collect()).await?;
+                    blocks.sort_by_key(|block| order.get(&block.light_block().block_ptr().hash));
+                    Ok(stream::iter(blocks.into_iter().map(Ok)))
+                }
+            })
+            .try_flatten()
+            .boxed()
+    }
+
     async fn block_hashes_by_block_number(
         &self,
         number: BlockNumber,
@@ -134,6 +134,18 @@ table! {
     }
 }
 
+table! {
+    /// The Firehose cursors at which a deployment wrote blocks, so that a
+    /// cursor can be mapped back to the block it points at. Only populated
+    /// when `GRAPH_STORE_FIREHOSE_CURSOR_HISTORY` is set
+    subgraphs.firehose_cursor_history (id, cursor) {
+        id -> Integer,
+        cursor -> Text,
+        block_number -> Integer,
+        block_hash -> Binary,
+    }
+}
+
 table! {
     /// Deployment metadata that changes less frequently
     subgraphs.deployment (id) {
@@ -600,12 +612,13 @@ pub async fn revert_block_ptr(
     firehose_cursor: &FirehoseCursor,
 ) -> Result<(), StoreError> {
     use deployment as d;
+    use firehose_cursor_history as fch;
     use head as h;
 
     // Intention is to revert to a block lower than the reorg threshold, on the other
     // hand the earliest we can possibly go is genesys block, so go to genesys even
     // if it's within the reorg threshold.
-    let earliest_block = i32::max(ptr.number - ENV_VARS.reorg_threshold(), 0);
+    let earliest_block = i32::max(ptr.number - ENV_VARS.reorg_threshold_for(&site.network), 0);
     let affected_rows = update(
         d::table
             .filter(d::id.eq(site.id))
@@ -628,6 +641,16 @@ pub async fn revert_block_ptr(
         .execute(conn)
         .await?;
 
+    // Cursors for reverted blocks must not resolve to blocks that are no
+    // longer part of the deployment's history
+    delete(
+        fch::table
+            .filter(fch::id.eq(site.id))
+            .filter(fch::block_number.gt(ptr.number)),
+    )
+    .execute(conn)
+    .await?;
+
     match affected_rows {
         1 => Ok(()),
         0 => Err(StoreError::Unknown(anyhow!(
@@ -640,6 +663,79 @@ pub async fn revert_block_ptr(
     }
 }
 
+/// Remember the block for each of `cursors` so that queries can be run
+/// at the block for a cursor
+pub async fn record_firehose_cursors(
+    conn: &mut AsyncPgConnection,
+    site: &Site,
+    cursors: &[(BlockPtr, FirehoseCursor)],
+) -> Result<(), StoreError> {
+    use firehose_cursor_history as fch;
+
+    for (ptr, firehose_cursor) in cursors {
+        let Some(cursor) = firehose_cursor.as_ref() else {
+            continue;
+        };
+
+        insert_into(fch::table)
+            .values((
+                fch::id.eq(site.id),
+                fch::cursor.eq(cursor),
+                fch::block_number.eq(ptr.number),
+                fch::block_hash.eq(ptr.hash_slice()),
+            ))
+            .on_conflict((fch::id, fch::cursor))
+            .do_update()
+            .set((
+                fch::block_number.eq(ptr.number),
+                fch::block_hash.eq(ptr.hash_slice()),
+            ))
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Forget the cursors for blocks before `earliest_block` since queries
+/// can no longer be run at them once the deployment has been pruned
+pub async fn prune_firehose_cursor_history(
+    conn: &mut AsyncPgConnection,
+    site: &Site,
+    earliest_block: BlockNumber,
+) -> Result<(), StoreError> {
+    use firehose_cursor_history as fch;
+
+    delete(
+        fch::table
+            .filter(fch::id.eq(site.id))
+            .filter(fch::block_number.lt(earliest_block)),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Return the block that the deployment had processed when it wrote
+/// `cursor`, or `None` if the cursor was never recorded or its block has
+/// since been reverted
+pub async fn firehose_cursor_block(
+    conn: &mut AsyncPgConnection,
+    site: &Site,
+    cursor: &str,
+) -> Result<Option<BlockPtr>, StoreError> {
+    use firehose_cursor_history as fch;
+
+    let ptr = fch::table
+        .filter(fch::id.eq(site.id))
+        .filter(fch::cursor.eq(cursor))
+        .select((fch::block_hash, fch::block_number))
+        .first::<(Vec<u8>, BlockNumber)>(conn)
+        .await
+        .optional()?
+        .map(BlockPtr::from);
+    Ok(ptr)
+}
+
 pub async fn block_ptr(
     conn: &mut AsyncPgConnection,
     site: &Site,
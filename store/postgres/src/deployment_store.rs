@@ -9,18 +9,19 @@ use graph::blockchain::BlockTime;
 use graph::blockchain::block_stream::{EntitySourceOperation, FirehoseCursor};
 use graph::components::store::write::RowGroup;
 use graph::components::store::{
-    Batch, DeploymentLocator, DerivedEntityQuery, DumpReporter, PrunePhase, PruneReporter,
-    PruneRequest, PruningStrategy, QueryPermit, RestoreReporter, StoredDynamicDataSource,
-    VersionStats,
+    Batch, ChangelogCursor, ChangelogEntry, DeploymentLocator, DerivedEntityQuery, DumpReporter,
+    PrunePhase, PruneReporter, PruneRequest, PruningStrategy, QueryPermit, RestoreReporter,
+    StoredDynamicDataSource, VersionStats,
 };
 use graph::components::versions::VERSIONS;
 use graph::data::graphql::IntoValue;
 use graph::data::query::Trace;
-use graph::data::store::{IdList, SqlQueryObject};
+use graph::data::store::{Id, IdList, SqlQueryObject};
 use graph::data::subgraph::{SPEC_VERSION_0_0_6, status};
 use graph::data_source::CausalityRegion;
 use graph::derive::CheapClone;
-use graph::futures03::FutureExt;
+use graph::futures03::stream::{self, BoxStream};
+use graph::futures03::{FutureExt, StreamExt, TryStreamExt};
 use graph::prelude::alloy::primitives::Address;
 use graph::prelude::{ApiVersion, EntityOperation, PoolWaitStats, SubgraphDeploymentEntity};
 use graph::semver::Version;
@@ -53,8 +54,12 @@ use crate::detail::ErrorDetail;
 use crate::dynds::DataSourcesTable;
 use crate::primary::{DeploymentId, Primary};
 use crate::relational::index::{CreateIndex, IndexCreator, IndexList, Method};
-use crate::relational::{self, Layout, LayoutCache, STATEMENT_TIMEOUT, SqlName, Table};
+use crate::relational::{
+    self, BlockRangeOverlap, ImmutabilityViolation, Layout, LayoutCache, STATEMENT_TIMEOUT,
+    SqlName, Table, WriteRate,
+};
 use crate::relational_queries::{FromEntityData, JSONData};
+use crate::write_throttle::WriteThrottle;
 use crate::{AsyncPgConnection, advisory_lock, catalog, retry};
 use crate::{ConnectionPool, detail};
 use crate::{dynds, primary::Site};
@@ -69,6 +74,72 @@ pub enum ReplicaId {
     ReadOnly(usize),
 }
 
+/// Create a list of replicas with repetitions according to `weights`,
+/// where `weights[0]` is the weight of the main database. The read replicas
+/// in `history` only serve queries for historical blocks and are left out
+fn replica_order(weights: &[usize], history: &[usize]) -> Vec<ReplicaId> {
+    weights
+        .iter()
+        .enumerate()
+        .flat_map(|(i, weight)| {
+            let (replica, weight) = if i == 0 {
+                (ReplicaId::Main, *weight)
+            } else if history.contains(&(i - 1)) {
+                (ReplicaId::ReadOnly(i - 1), 0)
+            } else {
+                (ReplicaId::ReadOnly(i - 1), *weight)
+            };
+            vec![replica; weight]
+        })
+        .collect()
+}
+
+/// The read replicas that only serve queries for blocks that are far enough
+/// behind the head of a deployment. Those queries tend to scan a lot of
+/// history, and sending them to dedicated replicas keeps them from slowing
+/// down queries for the latest data
+struct HistoryReplicas {
+    replicas: Vec<ReplicaId>,
+    /// The position in `replicas` of the replica to pick next
+    counter: AtomicUsize,
+}
+
+impl HistoryReplicas {
+    fn new(history: &[usize]) -> Self {
+        Self {
+            replicas: history
+                .iter()
+                .map(|idx| ReplicaId::ReadOnly(*idx))
+                .collect(),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Return the replica for a query at `block` when the head of the
+    /// deployment is at `head`, or `None` if the query should go to the
+    /// regular replicas. Only blocks at least `lag` blocks behind the head
+    /// count as historical, since a replica might not have more recent
+    /// blocks yet
+    fn pick(
+        &self,
+        block: BlockNumber,
+        head: Option<BlockNumber>,
+        lag: BlockNumber,
+    ) -> Option<ReplicaId> {
+        use std::sync::atomic::Ordering;
+
+        if self.replicas.is_empty() {
+            return None;
+        }
+        let head = head?;
+        if block > head.saturating_sub(lag) {
+            return None;
+        }
+        let index = self.counter.fetch_add(1, Ordering::SeqCst) % self.replicas.len();
+        Some(self.replicas[index])
+    }
+}
+
 /// Commonly needed information about a subgraph that we cache in
 /// `Store.subgraph_cache`. Only immutable subgraph data can be cached this
 /// way as the cache lives for the lifetime of the `Store` object
@@ -105,6 +176,8 @@ pub struct StoreInner {
     /// The current position in `replica_order` so we know which one to
     /// pick next
     conn_round_robin_counter: AtomicUsize,
+    /// The replicas that queries for historical blocks are sent to
+    history_replicas: HistoryReplicas,
 
     /// A cache of commonly needed data about a subgraph.
     subgraph_cache: Mutex<LruCache<DeploymentHash, SubgraphInfo>>,
@@ -115,6 +188,9 @@ pub struct StoreInner {
     pub(crate) layout_cache: LayoutCache,
 
     prune_handles: Mutex<HashMap<DeploymentId, PruneHandle>>,
+
+    /// Limits how fast subgraphs write to this shard
+    pub(crate) write_throttle: WriteThrottle,
 }
 
 /// Storage of the data for individual deployments. Each `DeploymentStore`
@@ -136,6 +212,7 @@ impl DeploymentStore {
         pool: ConnectionPool,
         read_only_pools: Vec<ConnectionPool>,
         mut pool_weights: Vec<usize>,
+        history_replicas: Vec<usize>,
     ) -> Self {
         // Create a store-specific logger
         let logger = logger.new(o!("component" => "Store"));
@@ -144,21 +221,17 @@ impl DeploymentStore {
         // and shuffle the resulting list. Any missing weights in the list
         // default to 1
         pool_weights.resize(read_only_pools.len() + 1, 1);
-        let mut replica_order: Vec<_> = pool_weights
-            .iter()
-            .enumerate()
-            .flat_map(|(i, weight)| {
-                let replica = if i == 0 {
-                    ReplicaId::Main
-                } else {
-                    ReplicaId::ReadOnly(i - 1)
-                };
-                vec![replica; *weight]
-            })
-            .collect();
+        let mut replica_order = replica_order(&pool_weights, &history_replicas);
         let mut rng = rng();
         replica_order.shuffle(&mut rng);
         debug!(logger, "Using postgres host order {:?}", replica_order);
+        let history_replicas = HistoryReplicas::new(&history_replicas);
+        if !history_replicas.replicas.is_empty() {
+            debug!(
+                logger,
+                "Using postgres hosts {:?} for historical queries", history_replicas.replicas
+            );
+        }
 
         // Create the store
         let store = StoreInner {
@@ -168,9 +241,11 @@ impl DeploymentStore {
             read_only_pools,
             replica_order,
             conn_round_robin_counter: AtomicUsize::new(0),
+            history_replicas,
             subgraph_cache: Mutex::new(LruCache::with_capacity(100)),
             layout_cache: LayoutCache::new(ENV_VARS.store.query_stats_refresh_interval),
             prune_handles: Mutex::new(HashMap::new()),
+            write_throttle: WriteThrottle::new(ENV_VARS.store.write_rows_per_second),
         };
 
         DeploymentStore(Arc::new(store))
@@ -620,6 +695,160 @@ impl DeploymentStore {
         Ok((default, targets))
     }
 
+    pub(crate) async fn count_entities(
+        &self,
+        site: Arc<Site>,
+    ) -> Result<BTreeMap<SqlName, usize>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout.count_entities(&mut conn).await
+    }
+
+    pub(crate) async fn immutability_violations(
+        &self,
+        site: Arc<Site>,
+    ) -> Result<BTreeMap<SqlName, Vec<ImmutabilityViolation>>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout.immutability_violations(&mut conn).await
+    }
+
+    pub(crate) async fn block_range_overlaps(
+        &self,
+        site: Arc<Site>,
+    ) -> Result<BTreeMap<SqlName, Vec<BlockRangeOverlap>>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout.block_range_overlaps(&mut conn).await
+    }
+
+    pub(crate) async fn write_rates(
+        &self,
+        site: Arc<Site>,
+        window: BlockNumber,
+        min_rate: f64,
+        max_ratio: f64,
+    ) -> Result<BTreeMap<SqlName, WriteRate>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let Some(head) = Self::block_ptr_with_conn(&mut conn, site.cheap_clone()).await? else {
+            return Ok(BTreeMap::new());
+        };
+        let layout = self.layout(&mut conn, site).await?;
+        layout
+            .write_rates(&mut conn, head.number, window, min_rate, max_ratio)
+            .await
+    }
+
+    pub(crate) async fn latest_write_block(
+        &self,
+        site: Arc<Site>,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout.latest_write_block(&mut conn).await
+    }
+
+    pub(crate) async fn creation_block(
+        &self,
+        site: Arc<Site>,
+        entity_type: &str,
+        id: &str,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        let entity_type = layout.input_schema.entity_type(entity_type)?;
+        let id = entity_type.parse_id(id)?;
+        layout.creation_block(&mut conn, &entity_type, &id).await
+    }
+
+    pub(crate) async fn value_ranges(
+        &self,
+        site: Arc<Site>,
+        key: &EntityKey,
+        field: &str,
+        value: &Value,
+    ) -> Result<Vec<(BlockNumber, Option<BlockNumber>)>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout.value_ranges(&mut conn, key, field, value).await
+    }
+
+    /// Return the stretches of at least `min_gap` blocks between the
+    /// earliest block of the deployment and its head in which nothing was
+    /// written
+    pub(crate) async fn block_range_gaps(
+        &self,
+        site: Arc<Site>,
+        min_gap: BlockNumber,
+    ) -> Result<Vec<(BlockNumber, BlockNumber)>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let state = deployment::state(&mut conn, &site).await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout
+            .block_range_gaps(
+                &mut conn,
+                state.earliest_block_number,
+                state.latest_block.number,
+                min_gap,
+            )
+            .await
+    }
+
+    pub(crate) async fn get_current_and_at(
+        &self,
+        site: Arc<Site>,
+        key: &EntityKey,
+        block: BlockNumber,
+    ) -> Result<(Option<Entity>, Option<Entity>), StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout.get_current_and_at(&mut conn, key, block).await
+    }
+
+    /// Stream the current entities of type `entity_type`, loading them
+    /// `batch_size` at a time. Every batch is loaded with its own
+    /// connection so that no connection is held while the consumer of the
+    /// stream processes entities
+    pub(crate) fn current_entities(
+        self: &Arc<Self>,
+        site: Arc<Site>,
+        entity_type: String,
+        batch_size: usize,
+    ) -> BoxStream<'static, Result<Entity, StoreError>> {
+        let batch_size = batch_size.max(1);
+        let store = self.cheap_clone();
+
+        // The state is the key of the last entity of the previous batch,
+        // or `None` once the last batch has been loaded
+        let start: Option<Option<(Id, CausalityRegion)>> = Some(None);
+        let batches = stream::try_unfold(start, move |after| {
+            let store = store.cheap_clone();
+            let site = site.cheap_clone();
+            let entity_type = entity_type.clone();
+            async move {
+                let Some(after) = after else {
+                    return Ok(None);
+                };
+                let mut conn = store.pool.get_permitted().await?;
+                let layout = store.layout(&mut conn, site).await?;
+                let entity_type = layout.input_schema.entity_type(&entity_type)?;
+                let batch = layout
+                    .find_current(&mut conn, &entity_type, after.as_ref(), batch_size)
+                    .await?;
+                let next = if batch.len() < batch_size {
+                    None
+                } else {
+                    batch.last().map(|(entity, cr)| Some((entity.id(), *cr)))
+                };
+                Ok(Some((batch, next)))
+            }
+        });
+        batches
+            .map_ok(|batch| stream::iter(batch.into_iter().map(|(entity, _)| Ok(entity))))
+            .try_flatten()
+            .boxed()
+    }
+
     pub(crate) async fn set_stats_target(
         &self,
         site: Arc<Site>,
@@ -842,8 +1071,11 @@ impl DeploymentStore {
                 return Ok(reporter);
             }
 
+            let earliest_block = req.earliest_queryable_block(state.earliest_block_number);
             conn.transaction(async |conn| {
-                deployment::set_earliest_block(conn, site.as_ref(), req.earliest_block).await
+                deployment::set_earliest_block(conn, site.as_ref(), earliest_block).await?;
+                deployment::prune_firehose_cursor_history(conn, site.as_ref(), req.earliest_block)
+                    .await
             })
             .await?;
 
@@ -949,6 +1181,19 @@ impl DeploymentStore {
             .map(FirehoseCursor::from)
     }
 
+    pub(crate) async fn block_for_firehose_cursor(
+        &self,
+        site: Arc<Site>,
+        cursor: &FirehoseCursor,
+    ) -> Result<Option<BlockPtr>, StoreError> {
+        let Some(cursor) = cursor.as_ref() else {
+            return Ok(None);
+        };
+
+        let mut conn = self.pool.get_permitted().await?;
+        deployment::firehose_cursor_block(&mut conn, &site, cursor).await
+    }
+
     pub(crate) async fn block_time(
         &self,
         site: Arc<Site>,
@@ -1095,6 +1340,27 @@ impl DeploymentStore {
             .await
     }
 
+    pub(crate) async fn get_changelog(
+        &self,
+        site: Arc<Site>,
+        causality_region: CausalityRegion,
+        block_range: Range<BlockNumber>,
+        after: Option<ChangelogCursor>,
+        limit: usize,
+    ) -> Result<Vec<ChangelogEntry>, StoreError> {
+        let mut conn = self.pool.get_permitted().await?;
+        let layout = self.layout(&mut conn, site).await?;
+        layout
+            .find_changelog(
+                &mut conn,
+                causality_region,
+                block_range,
+                after.as_ref(),
+                limit,
+            )
+            .await
+    }
+
     pub(crate) async fn get_derived(
         &self,
         site: Arc<Site>,
@@ -1143,6 +1409,13 @@ impl DeploymentStore {
         stopwatch: &StopwatchMetrics,
         manifest_idx_and_name: &[(u32, String)],
     ) -> Result<(), StoreError> {
+        // Wait before we take a connection so that throttled writes do not
+        // hold on to it
+        {
+            let _section = stopwatch.start_section("transact_blocks_throttle");
+            self.write_throttle.wait(batch.entity_count()).await;
+        }
+
         let mut conn = {
             let _section = stopwatch.start_section("transact_blocks_get_conn");
             self.pool.get_permitted().await?
@@ -1207,6 +1480,10 @@ impl DeploymentStore {
                 )
                 .await?;
 
+                if ENV_VARS.firehose_cursor_history() {
+                    deployment::record_firehose_cursors(conn, &site, &batch.block_cursors).await?;
+                }
+
                 Ok((layout, earliest_block))
             })
             .await
@@ -1311,7 +1588,7 @@ impl DeploymentStore {
             let req = PruneRequest::new(
                 &site.as_ref().into(),
                 history_blocks,
-                ENV_VARS.reorg_threshold(),
+                ENV_VARS.reorg_threshold_for(&site.network),
                 earliest_block,
                 latest_block,
             )?;
@@ -1504,6 +1781,21 @@ impl DeploymentStore {
         Ok(replica_id)
     }
 
+    /// Return the replica that should run a query at `block` against a
+    /// deployment whose head is at `head`. Queries for historical blocks
+    /// go to a history replica if there is one; all other queries use
+    /// `replica`, the replica that was picked for the whole GraphQL query
+    pub(crate) fn replica_for_block(
+        &self,
+        replica: ReplicaId,
+        block: BlockNumber,
+        head: Option<BlockNumber>,
+    ) -> ReplicaId {
+        self.history_replicas
+            .pick(block, head, ENV_VARS.store.history_replica_lag)
+            .unwrap_or(replica)
+    }
+
     pub(crate) async fn load_dynamic_data_sources(
         &self,
         site: Arc<Site>,
@@ -2265,3 +2557,51 @@ impl PruneReporter for OngoingPruneReporter {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graph::prelude::BLOCK_NUMBER_MAX;
+
+    use super::*;
+
+    #[test]
+    fn history_replicas_only_get_historical_queries() {
+        let history = HistoryReplicas::new(&[1]);
+
+        // Blocks at least `lag` blocks behind the head go to the replica
+        assert_eq!(
+            Some(ReplicaId::ReadOnly(1)),
+            history.pick(50, Some(200), 100)
+        );
+        assert_eq!(
+            Some(ReplicaId::ReadOnly(1)),
+            history.pick(100, Some(200), 100)
+        );
+        // Blocks close to the head, and queries for the latest block, don't
+        assert_eq!(None, history.pick(101, Some(200), 100));
+        assert_eq!(None, history.pick(200, Some(200), 100));
+        assert_eq!(None, history.pick(BLOCK_NUMBER_MAX, Some(200), 100));
+        // Without knowing the head, no query is historical
+        assert_eq!(None, history.pick(50, None, 100));
+
+        // Without history replicas, everything stays where it is
+        assert_eq!(None, HistoryReplicas::new(&[]).pick(50, Some(200), 100));
+    }
+
+    #[test]
+    fn history_replicas_are_not_used_for_current_queries() {
+        assert_eq!(
+            vec![
+                ReplicaId::Main,
+                ReplicaId::ReadOnly(0),
+                ReplicaId::ReadOnly(0)
+            ],
+            replica_order(&[1, 2], &[])
+        );
+        assert_eq!(vec![ReplicaId::Main], replica_order(&[1, 2], &[0]));
+        assert_eq!(
+            vec![ReplicaId::Main, ReplicaId::ReadOnly(1)],
+            replica_order(&[1, 1, 1], &[0])
+        );
+    }
+}
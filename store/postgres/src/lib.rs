@@ -38,6 +38,7 @@ mod subgraph_store;
 pub mod transaction_receipt;
 mod vid_batcher;
 mod writable;
+mod write_throttle;
 
 pub mod graphman;
 
@@ -88,5 +89,8 @@ pub mod command_support {
     pub use crate::deployment::{OnSync, on_sync};
     pub use crate::primary::Namespace;
     pub use crate::relational::prune::{Phase, PruneState, PruneTableState, Viewer};
-    pub use crate::relational::{Catalog, Column, ColumnType, Layout, SqlName};
+    pub use crate::relational::{
+        BlockRangeOverlap, Catalog, Column, ColumnType, ImmutabilityViolation, Layout, SqlName,
+        WriteRate,
+    };
 }
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use std::time::Instant;
 
 use crate::deployment_store::{DeploymentStore, ReplicaId};
 use crate::sql::Parser;
 use async_trait::async_trait;
+use graph::blockchain::block_stream::FirehoseCursor;
 use graph::components::store::{DeploymentId, QueryPermit, QueryStore as QueryStoreTrait};
 use graph::data::query::Trace;
 use graph::data::store::{QueryObject, SqlQueryObject};
@@ -19,6 +21,10 @@ pub(crate) struct QueryStore {
     chain_store: Arc<crate::ChainStore>,
     api_version: Arc<ApiVersion>,
     sql_parser: Result<Parser, StoreError>,
+    /// The latest block of the deployment when `deployment_state` was
+    /// first called. Queries for blocks far enough behind it can go to a
+    /// history replica
+    latest_block: OnceLock<BlockNumber>,
 }
 
 impl QueryStore {
@@ -40,6 +46,7 @@ impl QueryStore {
             chain_store,
             api_version,
             sql_parser,
+            latest_block: OnceLock::new(),
         }
     }
 }
@@ -51,10 +58,15 @@ impl QueryStoreTrait for QueryStore {
         query: EntityQuery,
     ) -> Result<(Vec<QueryObject>, Trace), graph::prelude::QueryExecutionError> {
         assert_eq!(&self.site.deployment, &query.subgraph_id);
+        let replica = self.store.replica_for_block(
+            self.replica_id,
+            query.block,
+            self.latest_block.get().copied(),
+        );
         let start = Instant::now();
         let mut conn = self
             .store
-            .get_replica_conn(self.replica_id)
+            .get_replica_conn(replica)
             .await
             .map_err(|e| QueryExecutionError::StoreError(e.into()))?;
         let wait = start.elapsed();
@@ -107,6 +119,16 @@ impl QueryStoreTrait for QueryStore {
     async fn block_ptr(&self) -> Result<Option<BlockPtr>, StoreError> {
         self.store.block_ptr(self.site.cheap_clone()).await
     }
+
+    async fn block_for_firehose_cursor(
+        &self,
+        cursor: &FirehoseCursor,
+    ) -> Result<Option<BlockPtr>, StoreError> {
+        self.store
+            .block_for_firehose_cursor(self.site.cheap_clone(), cursor)
+            .await
+    }
+
     async fn block_number_with_timestamp_and_parent_hash(
         &self,
         block_hash: &BlockHash,
@@ -155,7 +177,9 @@ impl QueryStoreTrait for QueryStore {
     }
 
     async fn deployment_state(&self) -> Result<DeploymentState, QueryExecutionError> {
-        Ok(self.store.deployment_state(self.site.cheap_clone()).await?)
+        let state = self.store.deployment_state(self.site.cheap_clone()).await?;
+        self.latest_block.get_or_init(|| state.latest_block.number);
+        Ok(state)
     }
 
     async fn api_schema(&self) -> Result<Arc<ApiSchema>, QueryExecutionError> {
@@ -64,16 +64,22 @@ use crate::relational_queries::{
 use crate::{
     primary::{Namespace, Site},
     relational_queries::{
-        ClampRangeQuery, EntityData, EntityDeletion, FilterCollection, FilterQuery, FindManyQuery,
-        FindRangeQuery, InsertQuery, RevertClampQuery, RevertRemoveQuery,
+        BlockGapData, BlockGapsQuery, ClampRangeQuery, CountAffectedQuery, CountEntitiesQuery,
+        CreationBlockQuery, CurrentAndAtData, CurrentAndAtQuery, CurrentEntityData,
+        DuplicateIdData, DuplicateIdsQuery, EntityData, EntityDeletion, FilterCollection,
+        FilterQuery, FindCurrentQuery, FindManyQuery, FindRangeQuery, InsertQuery,
+        LatestWriteBlockQuery, OverlappingRangeData, OverlappingRangesQuery, RevertClampQuery,
+        RevertRemoveQuery, ValueRangeData, ValueRangesQuery, WriteRateData, WriteRateQuery,
     },
 };
-use graph::components::store::{AttributeNames, DerivedEntityQuery};
-use graph::data::store::{BYTES_SCALAR, IdList, IdType};
+use graph::components::store::{
+    AttributeNames, ChangelogCursor, ChangelogEntry, DerivedEntityQuery,
+};
+use graph::data::store::{BYTES_SCALAR, Id, IdList, IdType};
 use graph::data::subgraph::schema::POI_TABLE;
 use graph::prelude::{
     BlockNumber, DeploymentHash, Entity, EntityOperation, Logger, QueryExecutionError, StoreError,
-    ValueType, anyhow, info,
+    Value, ValueType, anyhow, info,
 };
 
 use crate::ForeignServer;
@@ -223,6 +229,54 @@ impl std::ops::Deref for SqlName {
     }
 }
 
+/// An entity in an immutable table that has more than one row
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImmutabilityViolation {
+    /// The id of the entity, formatted as text
+    pub id: String,
+    /// The block at which each row was written, in ascending order
+    pub blocks: Vec<BlockNumber>,
+}
+
+/// Two versions of an entity in a mutable table whose block ranges
+/// overlap. Ranges are given by their start and end; the end is `None` for
+/// the current version of the entity
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockRangeOverlap {
+    /// The id of the entity, formatted as text
+    pub id: String,
+    /// The block range of the older of the two versions
+    pub first: (BlockNumber, Option<BlockNumber>),
+    /// The block range of the newer of the two versions
+    pub second: (BlockNumber, Option<BlockNumber>),
+}
+
+/// How often a mutable table was written to during a window of recent
+/// blocks, as computed by `Layout::write_rates`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteRate {
+    /// The number of versions created during the window
+    pub versions: i64,
+    /// The number of distinct entities that versions were created for
+    /// during the window
+    pub entities: i64,
+    /// The number of distinct blocks in the window at which versions
+    /// were created
+    pub blocks: i64,
+    /// The average number of versions created per block in the window
+    pub rate: f64,
+    /// The ratio of `entities` to `versions`, or 1 if there were no
+    /// versions. Account-like tables have few entities with many versions
+    /// each and therefore a low ratio
+    pub ratio: f64,
+    /// Whether the table is already marked as account-like
+    pub is_account_like: bool,
+    /// Whether the table is written to often enough, and to few enough
+    /// entities, to be a candidate for being marked as account-like.
+    /// Tables that already are account-like are never candidates
+    pub candidate: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct Layout {
     /// Details of where the subgraph is stored
@@ -482,6 +536,43 @@ impl Layout {
             .transpose()
     }
 
+    /// Find the current version of the entity with `key` and the version
+    /// that was current at `block` with one query. Either is `None` if the
+    /// entity did not exist at that point
+    pub async fn get_current_and_at(
+        &self,
+        conn: &mut AsyncPgConnection,
+        key: &EntityKey,
+        block: BlockNumber,
+    ) -> Result<(Option<Entity>, Option<Entity>), StoreError> {
+        let table = self.table_for_entity(&key.entity_type)?;
+
+        let mut current = None;
+        let mut past = None;
+        for data in CurrentAndAtQuery::new(table, key, block)
+            .load::<CurrentAndAtData>(conn)
+            .await?
+        {
+            let version = if data.current {
+                &mut current
+            } else {
+                &mut past
+            };
+            if version.is_some() {
+                return Err(internal_error!(
+                    "duplicate entity {}[{}] in result set, block = {}",
+                    key.entity_type,
+                    key.entity_id,
+                    block
+                ));
+            }
+            let entity: Entity =
+                EntityData::new(data.entity, data.data).deserialize_with_layout(self, None)?;
+            *version = Some(entity);
+        }
+        Ok((current, past))
+    }
+
     // An optimization when looking up multiple entities, it will generate a single sql query using `UNION ALL`.
     pub async fn find_many(
         &self,
@@ -560,10 +651,6 @@ impl Layout {
                 .await
                 .optional()?
                 .unwrap_or_default();
-        let mut lower_iter = lower_vec.iter().fuse().peekable();
-        let mut upper_iter = upper_vec.iter().fuse().peekable();
-        let mut lower_now = lower_iter.next();
-        let mut upper_now = upper_iter.next();
         // A closure to convert the entity data from the database into entity operation.
         let transform = |ede: &EntityDataExt,
                          entity_op: EntityOperationKind|
@@ -582,73 +669,9 @@ impl Layout {
             Ok((ewt, block))
         };
 
-        fn compare_entity_data_ext(a: &EntityDataExt, b: &EntityDataExt) -> std::cmp::Ordering {
-            a.block_number
-                .cmp(&b.block_number)
-                .then_with(|| a.entity.cmp(&b.entity))
-                .then_with(|| a.id.cmp(&b.id))
-        }
-
-        // The algorithm is a similar to merge sort algorithm and it relays on the fact that both vectors
-        // are ordered by (block_number, entity_type, entity_id). It advances simultaneously entities from
-        // both lower_vec and upper_vec and tries to match entities that have entries in both vectors for
-        // a particular block. The match is successful if an entry in one array has the same values in the
-        // other one for the number of the block, entity type and the entity id. The comparison operation
-        // over the EntityDataExt implements that check. If there is a match it’s a modification operation,
-        // since both sides of a range are present for that block, entity type and id. If one side of the
-        // range exists and the other is missing it is a creation or deletion depending on which side is
-        // present. For immutable entities the entries in upper_vec are missing, hence they are considered
-        // having a lower bound at particular block and upper bound at infinity.
-        while lower_now.is_some() || upper_now.is_some() {
-            let (ewt, block) = match (lower_now, upper_now) {
-                (Some(lower), Some(upper)) => {
-                    match compare_entity_data_ext(lower, upper) {
-                        std::cmp::Ordering::Greater => {
-                            // we have upper bound at this block, but no lower bounds at the same block so it's deletion
-                            let (ewt, block) = transform(upper, EntityOperationKind::Delete)?;
-                            // advance upper_vec pointer
-                            upper_now = upper_iter.next();
-                            (ewt, block)
-                        }
-                        std::cmp::Ordering::Less => {
-                            // we have lower bound at this block but no upper bound at the same block so its creation
-                            let (ewt, block) = transform(lower, EntityOperationKind::Create)?;
-                            // advance lower_vec pointer
-                            lower_now = lower_iter.next();
-                            (ewt, block)
-                        }
-                        std::cmp::Ordering::Equal => {
-                            let (ewt, block) = transform(lower, EntityOperationKind::Modify)?;
-                            // advance both lower_vec and upper_vec pointers
-                            lower_now = lower_iter.next();
-                            upper_now = upper_iter.next();
-                            (ewt, block)
-                        }
-                    }
-                }
-                (Some(lower), None) => {
-                    // we have lower bound at this block but no upper bound at the same block so its creation
-                    let (ewt, block) = transform(lower, EntityOperationKind::Create)?;
-                    // advance lower_vec pointer
-                    lower_now = lower_iter.next();
-                    (ewt, block)
-                }
-                (None, Some(upper)) => {
-                    // we have upper bound at this block, but no lower bounds at all so it's deletion
-                    let (ewt, block) = transform(upper, EntityOperationKind::Delete)?;
-                    // advance upper_vec pointer
-                    upper_now = upper_iter.next();
-                    (ewt, block)
-                }
-                _ => panic!("Imposible case to happen"),
-            };
-
-            match entities.get_mut(&block) {
-                Some(vec) => vec.push(ewt),
-                None => {
-                    let _ = entities.insert(block, vec![ewt]);
-                }
-            };
+        for (ede, entity_op) in classify_range_changes(&lower_vec, &upper_vec) {
+            let (ewt, block) = transform(ede, entity_op)?;
+            entities.entry(block).or_default().push(ewt);
         }
 
         // sort the elements in each blocks bucket by vid
@@ -659,6 +682,84 @@ impl Layout {
         Ok(entities)
     }
 
+    /// Find up to `limit` changes to entities of all types after `after`.
+    /// See [SourceableStore::get_changelog](graph::components::store::SourceableStore::get_changelog)
+    pub async fn find_changelog(
+        &self,
+        conn: &mut AsyncPgConnection,
+        causality_region: CausalityRegion,
+        block_range: Range<BlockNumber>,
+        after: Option<&ChangelogCursor>,
+        limit: usize,
+    ) -> Result<Vec<ChangelogEntry>, StoreError> {
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let tables: Vec<&Table> = self
+            .tables
+            .values()
+            .filter(|table| !table.object.is_poi())
+            .map(|table| table.as_ref())
+            .collect();
+
+        let lower_vec = FindRangeQuery::new(
+            &tables,
+            causality_region,
+            BoundSide::Lower,
+            block_range.clone(),
+        )
+        .paged(after, limit as i64)
+        .get_results::<EntityDataExt>(conn)
+        .await
+        .optional()?
+        .unwrap_or_default();
+        let upper_vec =
+            FindRangeQuery::new(&tables, causality_region, BoundSide::Upper, block_range)
+                .paged(after, limit as i64)
+                .get_results::<EntityDataExt>(conn)
+                .await
+                .optional()?
+                .unwrap_or_default();
+
+        // A full page of one bound can end before changes whose other bound
+        // is on the other page. Only the changes up to the end of the
+        // shorter of the full pages are complete; the rest come with the
+        // next page.
+        let key = |ede: &EntityDataExt| (ede.block_number, ede.entity.clone(), ede.id.clone());
+        let complete_until = [&lower_vec, &upper_vec]
+            .into_iter()
+            .filter(|rows| rows.len() >= limit)
+            .filter_map(|rows| rows.last())
+            .map(key)
+            .min();
+
+        classify_range_changes(&lower_vec, &upper_vec)
+            .into_iter()
+            .filter(|(ede, _)| {
+                complete_until
+                    .as_ref()
+                    .is_none_or(|until| &key(ede) <= until)
+            })
+            .map(|(ede, op)| {
+                let data = EntityData::new(ede.entity.clone(), ede.data.clone());
+                let entity_type = data.entity_type(&self.input_schema);
+                let entity = data.deserialize_with_layout::<Entity>(self, None)?;
+                Ok(ChangelogEntry {
+                    block: ede.block_number,
+                    op,
+                    entity_type,
+                    entity,
+                    cursor: ChangelogCursor {
+                        block: ede.block_number,
+                        entity_type: ede.entity.clone(),
+                        id: ede.id.clone(),
+                    },
+                })
+            })
+            .collect()
+    }
+
     pub async fn find_derived(
         &self,
         conn: &mut AsyncPgConnection,
@@ -1064,6 +1165,292 @@ impl Layout {
         Ok(count)
     }
 
+    /// Count the entity versions that `delete` would clamp for `group`
+    /// without changing any data
+    pub async fn count_deleted(
+        &self,
+        conn: &mut AsyncPgConnection,
+        group: &RowGroup,
+    ) -> Result<usize, StoreError> {
+        if !group.has_clamps() {
+            return Ok(0);
+        }
+
+        let table = self.table_for_entity(&group.entity_type)?;
+        if table.immutable {
+            return Ok(0);
+        }
+
+        let mut count = 0;
+        for (block, rows) in group.clamps_by_block() {
+            let ids: Vec<_> = rows.iter().map(|eref| eref.id()).collect();
+            for chunk in ids.chunks(DELETE_OPERATION_CHUNK_SIZE) {
+                let chunk = IdList::try_from_iter(
+                    group.entity_type.id_type()?,
+                    chunk.iter().map(|id| (*id).to_owned()),
+                )?;
+                count += ClampRangeQuery::new(table, &chunk, block)?
+                    .count_affected()
+                    .get_result::<i64>(conn)
+                    .await? as usize;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Count the entity versions in `table` that pruning all history
+    /// before `earliest_block` would delete without changing any data
+    pub async fn count_prunable(
+        &self,
+        conn: &mut AsyncPgConnection,
+        table: &Table,
+        earliest_block: BlockNumber,
+    ) -> Result<usize, StoreError> {
+        let count = CountAffectedQuery::prune_below(table, earliest_block)
+            .get_result::<i64>(conn)
+            .await?;
+        Ok(count as usize)
+    }
+
+    /// Count the entities in each table, i.e., only the latest version of
+    /// each entity, and return the counts by table name
+    pub async fn count_entities(
+        &self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<BTreeMap<SqlName, usize>, StoreError> {
+        let mut counts = BTreeMap::new();
+        for table in self.tables.values() {
+            let count = CountEntitiesQuery::new(table)
+                .get_result::<i64>(conn)
+                .await?;
+            counts.insert(table.name.clone(), count as usize);
+        }
+        Ok(counts)
+    }
+
+    /// Find the entities in immutable tables that have more than one row.
+    /// Since immutable entities are never updated, that can only happen if
+    /// the data in the store is corrupt. Only tables that have such
+    /// entities are included in the result
+    pub async fn immutability_violations(
+        &self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<BTreeMap<SqlName, Vec<ImmutabilityViolation>>, StoreError> {
+        let mut violations = BTreeMap::new();
+        for table in self.tables.values().filter(|table| table.immutable) {
+            let duplicates: Vec<_> = DuplicateIdsQuery::new(table)?
+                .load::<DuplicateIdData>(conn)
+                .await?
+                .into_iter()
+                .map(|DuplicateIdData { id, blocks }| ImmutabilityViolation { id, blocks })
+                .collect();
+            if !duplicates.is_empty() {
+                violations.insert(table.name.clone(), duplicates);
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Find the versions of entities in mutable tables whose block ranges
+    /// overlap. The exclusion constraint on block ranges prevents that,
+    /// but not every table has one, so overlaps can only be found by
+    /// looking at the data. Only tables that have overlaps are included in
+    /// the result
+    pub async fn block_range_overlaps(
+        &self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<BTreeMap<SqlName, Vec<BlockRangeOverlap>>, StoreError> {
+        let mut overlaps = BTreeMap::new();
+        for table in self.tables.values().filter(|table| !table.immutable) {
+            let found: Vec<_> = OverlappingRangesQuery::new(table)?
+                .load::<OverlappingRangeData>(conn)
+                .await?
+                .into_iter()
+                .map(|data| BlockRangeOverlap {
+                    id: data.id,
+                    first: (data.first_start, data.first_end),
+                    second: (data.second_start, data.second_end),
+                })
+                .collect();
+            if !found.is_empty() {
+                overlaps.insert(table.name.clone(), found);
+            }
+        }
+        Ok(overlaps)
+    }
+
+    /// Compute how many versions were created per block in each mutable
+    /// table during the `window` blocks up to and including `head`, using
+    /// the lower bound of the block range of each version. Tables whose
+    /// rate is at least `min_rate`, whose ratio of entities to versions is
+    /// at most `max_ratio`, and that are not account-like yet are flagged
+    /// as candidates for being marked account-like; that only
+    /// changes how `BlockRangeColumn::contains` filters them, and the
+    /// result is purely advisory
+    pub async fn write_rates(
+        &self,
+        conn: &mut AsyncPgConnection,
+        head: BlockNumber,
+        window: BlockNumber,
+        min_rate: f64,
+        max_ratio: f64,
+    ) -> Result<BTreeMap<SqlName, WriteRate>, StoreError> {
+        let window = window.max(1);
+        let since = head.saturating_sub(window);
+        let mut rates = BTreeMap::new();
+        for table in self.tables.values().filter(|table| !table.immutable) {
+            let data = WriteRateQuery::new(table, since)
+                .get_result::<WriteRateData>(conn)
+                .await?;
+            let rate = data.versions as f64 / window as f64;
+            let ratio = if data.versions == 0 {
+                1.0
+            } else {
+                data.entities as f64 / data.versions as f64
+            };
+            let candidate = !table.is_account_like && rate >= min_rate && ratio <= max_ratio;
+            rates.insert(
+                table.name.clone(),
+                WriteRate {
+                    versions: data.versions,
+                    entities: data.entities,
+                    blocks: data.blocks,
+                    rate,
+                    ratio,
+                    is_account_like: table.is_account_like,
+                    candidate,
+                },
+            );
+        }
+        Ok(rates)
+    }
+
+    /// Return the highest block at which any entity in this layout was
+    /// written, or `None` if no entities have been written yet. Since
+    /// deleting an entity does not create a new version, a deletion is not
+    /// counted as a write
+    pub async fn latest_write_block(
+        &self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let mut latest = None;
+        for table in self.tables.values() {
+            let block = LatestWriteBlockQuery::new(table)
+                .get_result::<BlockNumber>(conn)
+                .await
+                .optional()?;
+            latest = latest.max(block);
+        }
+        Ok(latest)
+    }
+
+    /// Return up to `limit` of the current entities of type `entity_type`
+    /// together with their causality region that come after the entity
+    /// with key `after` when they are ordered by id and causality region.
+    /// Passing the key of the last entity of one page as `after` returns
+    /// the next page
+    pub async fn find_current(
+        &self,
+        conn: &mut AsyncPgConnection,
+        entity_type: &EntityType,
+        after: Option<&(Id, CausalityRegion)>,
+        limit: usize,
+    ) -> Result<Vec<(Entity, CausalityRegion)>, StoreError> {
+        let table = self.table_for_entity(entity_type)?;
+        FindCurrentQuery::new(table, after, limit)
+            .load::<CurrentEntityData>(conn)
+            .await?
+            .into_iter()
+            .map(|data| {
+                let (data, cr) = data.into_parts();
+                data.deserialize_with_layout(self, None)
+                    .map(|entity| (entity, cr))
+            })
+            .collect()
+    }
+
+    /// Return the block at which the entity of type `entity_type` with
+    /// `id` was created, or `None` if there never was such an entity. If
+    /// the entity was deleted and created again, this is the block at which
+    /// it was first created
+    pub async fn creation_block(
+        &self,
+        conn: &mut AsyncPgConnection,
+        entity_type: &EntityType,
+        id: &Id,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let table = self.table_for_entity(entity_type)?;
+        let block = CreationBlockQuery::new(table, id)
+            .get_result::<Option<BlockNumber>>(conn)
+            .await?;
+        Ok(block)
+    }
+
+    /// Return the block ranges during which the attribute `field` of the
+    /// entity with `key` had `value`. Each range is a
+    /// pair `(from, to)` of the first block with that value and the first
+    /// block without it, where `to` is `None` if the entity still has that
+    /// value. Ranges of consecutive versions are merged, and the ranges are
+    /// ordered by `from`
+    pub async fn value_ranges(
+        &self,
+        conn: &mut AsyncPgConnection,
+        key: &EntityKey,
+        field: &str,
+        value: &Value,
+    ) -> Result<Vec<(BlockNumber, Option<BlockNumber>)>, StoreError> {
+        /// Merge ranges that are sorted by `from` and that overlap or
+        /// touch each other
+        fn merge(rows: Vec<ValueRangeData>) -> Vec<(BlockNumber, Option<BlockNumber>)> {
+            let mut ranges: Vec<(BlockNumber, Option<BlockNumber>)> = Vec::new();
+            for ValueRangeData { lower, upper } in rows {
+                match ranges.last_mut() {
+                    Some((_, last_upper)) if last_upper.is_none_or(|last| lower <= last) => {
+                        *last_upper = match (*last_upper, upper) {
+                            (Some(last), Some(upper)) => Some(last.max(upper)),
+                            _ => None,
+                        };
+                    }
+                    _ => ranges.push((lower, upper)),
+                }
+            }
+            ranges
+        }
+
+        let table = self.table_for_entity(&key.entity_type)?;
+        let column = table.column_for_field(field)?;
+        let rows = ValueRangesQuery::new(table, key, column, value)?
+            .load::<ValueRangeData>(conn)
+            .await?;
+        Ok(merge(rows))
+    }
+
+    /// Return the stretches of at least `min_gap` blocks in `[from, to]`
+    /// during which no entity version in any table started or ended, as
+    /// pairs of the first and the last block of each stretch. Such
+    /// stretches are not necessarily a problem, but a long one in a
+    /// subgraph that usually writes often can be a sign of lost data
+    pub async fn block_range_gaps(
+        &self,
+        conn: &mut AsyncPgConnection,
+        from: BlockNumber,
+        to: BlockNumber,
+        min_gap: BlockNumber,
+    ) -> Result<Vec<(BlockNumber, BlockNumber)>, StoreError> {
+        if from > to {
+            return Ok(Vec::new());
+        }
+
+        let tables = self.tables.values().map(|table| table.as_ref()).collect();
+        let gaps = BlockGapsQuery::new(tables, from, to, min_gap)
+            .load::<BlockGapData>(conn)
+            .await?
+            .into_iter()
+            .map(|BlockGapData { start, finish }| (start, finish))
+            .collect();
+        Ok(gaps)
+    }
+
     pub async fn truncate_tables(&self, conn: &mut AsyncPgConnection) -> Result<(), StoreError> {
         for table in self.tables.values() {
             sql_query(format!("TRUNCATE TABLE {}", table.qualified_name))
@@ -1333,6 +1720,74 @@ impl Layout {
     }
 }
 
+/// Match up the rows that a `FindRangeQuery` returned for the lower and the
+/// upper bounds of block ranges and classify each change they describe
+fn classify_range_changes<'a>(
+    lower_vec: &'a [EntityDataExt],
+    upper_vec: &'a [EntityDataExt],
+) -> Vec<(&'a EntityDataExt, EntityOperationKind)> {
+    fn compare_entity_data_ext(a: &EntityDataExt, b: &EntityDataExt) -> std::cmp::Ordering {
+        a.block_number
+            .cmp(&b.block_number)
+            .then_with(|| a.entity.cmp(&b.entity))
+            .then_with(|| a.id.cmp(&b.id))
+    }
+
+    let mut changes = Vec::with_capacity(lower_vec.len().max(upper_vec.len()));
+    let mut lower_iter = lower_vec.iter().fuse().peekable();
+    let mut upper_iter = upper_vec.iter().fuse().peekable();
+    let mut lower_now = lower_iter.next();
+    let mut upper_now = upper_iter.next();
+
+    // The algorithm is a similar to merge sort algorithm and it relays on the fact that both vectors
+    // are ordered by (block_number, entity_type, entity_id). It advances simultaneously entities from
+    // both lower_vec and upper_vec and tries to match entities that have entries in both vectors for
+    // a particular block. The match is successful if an entry in one array has the same values in the
+    // other one for the number of the block, entity type and the entity id. The comparison operation
+    // over the EntityDataExt implements that check. If there is a match it’s a modification operation,
+    // since both sides of a range are present for that block, entity type and id. If one side of the
+    // range exists and the other is missing it is a creation or deletion depending on which side is
+    // present. For immutable entities the entries in upper_vec are missing, hence they are considered
+    // having a lower bound at particular block and upper bound at infinity.
+    while lower_now.is_some() || upper_now.is_some() {
+        let change = match (lower_now, upper_now) {
+            (Some(lower), Some(upper)) => {
+                match compare_entity_data_ext(lower, upper) {
+                    std::cmp::Ordering::Greater => {
+                        // we have upper bound at this block, but no lower bounds at the same block so it's deletion
+                        upper_now = upper_iter.next();
+                        (upper, EntityOperationKind::Delete)
+                    }
+                    std::cmp::Ordering::Less => {
+                        // we have lower bound at this block but no upper bound at the same block so its creation
+                        lower_now = lower_iter.next();
+                        (lower, EntityOperationKind::Create)
+                    }
+                    std::cmp::Ordering::Equal => {
+                        // advance both lower_vec and upper_vec pointers
+                        lower_now = lower_iter.next();
+                        upper_now = upper_iter.next();
+                        (lower, EntityOperationKind::Modify)
+                    }
+                }
+            }
+            (Some(lower), None) => {
+                // we have lower bound at this block but no upper bound at the same block so its creation
+                lower_now = lower_iter.next();
+                (lower, EntityOperationKind::Create)
+            }
+            (None, Some(upper)) => {
+                // we have upper bound at this block, but no lower bounds at all so it's deletion
+                upper_now = upper_iter.next();
+                (upper, EntityOperationKind::Delete)
+            }
+            _ => panic!("Imposible case to happen"),
+        };
+        changes.push(change);
+    }
+    changes
+}
+
 /// A user-defined enum
 #[derive(Clone, Debug, PartialEq)]
 pub struct EnumType {
@@ -23,6 +23,23 @@ const CREATE_EXCLUSION_CONSTRAINT: bool = true;
 #[cfg(not(debug_assertions))]
 const CREATE_EXCLUSION_CONSTRAINT: bool = false;
 
+/// An index on the `block_range` of a table
+pub(crate) struct BlockRangeIndex {
+    pub name: String,
+    /// Whether the index backs an exclusion constraint
+    pub constraint: bool,
+}
+
+/// The name under which Postgres knows the identifier `name`. Postgres
+/// truncates identifiers that are longer than 63 bytes
+fn pg_identifier(name: &str) -> String {
+    let mut len = name.len().min(63);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    name[..len].to_string()
+}
+
 impl Layout {
     /// Generate the DDL for the entire layout, i.e., all `create table`
     /// and `create index` etc. statements needed in the database schema
@@ -425,6 +442,37 @@ impl Table {
         Ok(())
     }
 
+    /// The indexes on `block_range` that `time_travel_indexes` and
+    /// `exclusion_ddl` create. Immutable tables have none
+    pub(crate) fn block_range_indexes(&self) -> Vec<BlockRangeIndex> {
+        if self.immutable {
+            return vec![];
+        }
+        let time_travel = self
+            .time_travel_indexes()
+            .into_iter()
+            .filter_map(|idx| idx.name().map(pg_identifier))
+            .map(|name| BlockRangeIndex {
+                name,
+                constraint: false,
+            });
+        let exclusion = BlockRangeIndex {
+            name: pg_identifier(&self.exclusion_name()),
+            constraint: self.has_causality_region || CREATE_EXCLUSION_CONSTRAINT,
+        };
+        time_travel.chain(iter::once(exclusion)).collect()
+    }
+
+    /// The name of the exclusion constraint or index on `block_range`
+    fn exclusion_name(&self) -> String {
+        format!(
+            "{}_{}_{}_excl",
+            self.name,
+            self.primary_key().name,
+            BLOCK_RANGE_COLUMN
+        )
+    }
+
     pub fn exclusion_ddl(&self, out: &mut String) -> fmt::Result {
         // Tables with causality regions need to use exclusion constraints for correctness,
         // to catch violations of write isolation.
@@ -440,9 +488,9 @@ impl Table {
                 out,
                 "
     alter table {qname}
-        add constraint {bare_name}_{id}_{block_range}_excl exclude using gist ({id} with =, {block_range} with &&);",
+        add constraint {name} exclude using gist ({id} with =, {block_range} with &&);",
                 qname = self.qualified_name,
-                bare_name = self.name,
+                name = self.exclusion_name(),
                 id = self.primary_key().name,
                 block_range = BLOCK_RANGE_COLUMN
             )?;
@@ -450,11 +498,11 @@ impl Table {
             writeln!(
                 out,
                 "
-        create index {bare_name}_{id}_{block_range}_excl on {qname}
+        create index {name} on {qname}
          using gist ({id}, {block_range});
                ",
                 qname = self.qualified_name,
-                bare_name = self.name,
+                name = self.exclusion_name(),
                 id = self.primary_key().name,
                 block_range = BLOCK_RANGE_COLUMN
             )?;
@@ -7,8 +7,8 @@ use diesel::{
 use diesel_async::{AsyncConnection, RunQueryDsl, SimpleAsyncConnection};
 use graph::{
     components::store::{PrunePhase, PruneReporter, PruneRequest, PruningStrategy, VersionStats},
-    prelude::{BLOCK_NUMBER_MAX, BlockNumber, CancelableError, CheapClone, StoreError},
-    slog::{Logger, warn},
+    prelude::{BLOCK_NUMBER_MAX, BlockNumber, CancelableError, CheapClone, ENV_VARS, StoreError},
+    slog::{Logger, info, warn},
 };
 use itertools::Itertools;
 
@@ -471,15 +471,28 @@ impl Layout {
                     tracker.start_delete(conn, table, range, &batcher).await?;
                     let qname = table.qualified_name.clone();
                     let earliest_block = req.earliest_block;
+                    // Keep versions whose block range contains a multiple
+                    // of the sample interval, i.e., versions for which the
+                    // largest multiple of the interval before the upper
+                    // end of the range is not before the lower end
+                    let samples = if req.keeps_samples() {
+                        let interval = req.sample_interval;
+                        format!(
+                            "and (upper(block_range) - 1) / {interval} * {interval} < lower(block_range)"
+                        )
+                    } else {
+                        String::new()
+                    };
                     while !batcher.finished() {
                         let rows = batch_with_timeout(conn, &mut batcher, |conn, start, end| {
                             let qname = qname.clone();
+                            let samples = samples.clone();
                             Box::pin(async move {
                                 sql_query(format!(
                                 "/* controller=prune,phase=delete,start_vid={start},batch_size={batch_size} */ \
                                  delete from {qname} \
                                               where coalesce(upper(block_range), 2147483647) <= $1 \
-                                                and vid >= $2 and vid <= $3",
+                                                and vid >= $2 and vid <= $3 {samples}",
                                 batch_size = end - start + 1
                             ))
                             .bind::<Integer, _>(earliest_block)
@@ -517,11 +530,63 @@ impl Layout {
         }
         let tables = prunable_tables.iter().map(|(table, _)| *table).collect();
         self.analyze_tables(conn, reporter, tables).await?;
+        run_maintenance(logger, conn, &prunable_tables).await;
         reporter.finish();
         Ok(())
     }
 }
 
+/// The statements that remove the bloat that pruning `tables` leaves
+/// behind. Tables that were rebuilt are brand new and need nothing; tables
+/// that we deleted from need a vacuum so that the space of the deleted rows
+/// can be reused, and their indexes on `block_range` need to be rebuilt
+/// since they degrade the most from the deletions
+fn maintenance_statements(tables: &[(&Arc<Table>, PruningStrategy)]) -> Vec<String> {
+    let mut stmts = Vec::new();
+    for (table, strat) in tables {
+        if *strat != PruningStrategy::Delete {
+            continue;
+        }
+        stmts.push(format!("vacuum {}", table.qualified_name));
+        for index in table.block_range_indexes() {
+            // Postgres can not reindex indexes that back exclusion
+            // constraints concurrently
+            let concurrently = if index.constraint {
+                ""
+            } else {
+                " concurrently"
+            };
+            stmts.push(format!(
+                "reindex index{concurrently} \"{}\".\"{}\"",
+                table.nsp, index.name
+            ));
+        }
+    }
+    stmts
+}
+
+/// Log the `maintenance_statements` for `tables`, and run them if
+/// `GRAPH_STORE_PRUNE_RUN_MAINTENANCE` is set. Failures are only logged
+/// since the data has been pruned successfully at this point
+async fn run_maintenance(
+    logger: &Logger,
+    conn: &mut AsyncPgConnection,
+    tables: &[(&Arc<Table>, PruningStrategy)],
+) {
+    for stmt in maintenance_statements(tables) {
+        if !ENV_VARS.store.prune_run_maintenance {
+            info!(logger, "Pruning recommends maintenance"; "sql" => &stmt);
+            continue;
+        }
+
+        info!(logger, "Running maintenance after pruning"; "sql" => &stmt);
+        if let Err(e) = conn.batch_execute(&stmt).await {
+            warn!(logger, "Maintenance after pruning failed";
+                  "sql" => &stmt, "error" => e.to_string());
+        }
+    }
+}
+
 /// The type of per-batch query closure expected by `batch_with_timeout`.
 /// Callers wrap their async body in `Box::pin(async move { ... })` so that
 /// the future is type-erased and explicitly `Send`. This sidesteps the
@@ -1066,3 +1131,74 @@ mod status {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use graph::{prelude::DeploymentHash, schema::InputSchema};
+
+    use crate::{catalog::Catalog, layout_for_tests::make_dummy_site};
+
+    use super::*;
+
+    const SCHEMA: &str = "
+        type Pool @entity { id: ID!, liquidity: BigInt! }
+        type Swap @entity(immutable: true) { id: ID!, pool: Pool! }
+        type Token @entity { id: ID!, symbol: String! }
+        type LiquidityPositionSnapshotForEveryAccountAndPool @entity { id: ID!, amount: BigInt! }";
+
+    fn layout() -> Layout {
+        let subgraph = DeploymentHash::new("subgraph").unwrap();
+        let schema = InputSchema::parse_latest(SCHEMA, subgraph.clone()).unwrap();
+        let namespace = Namespace::new("sgd42".to_owned()).unwrap();
+        let site = Arc::new(make_dummy_site(subgraph, namespace, "anet".to_string()));
+        let catalog = Catalog::for_tests(site.clone(), BTreeSet::new()).unwrap();
+        Layout::new(site, &schema, catalog).unwrap()
+    }
+
+    #[test]
+    fn maintenance_targets_tables_pruned_by_deleting() {
+        let layout = layout();
+        let table = |name: &str| {
+            layout
+                .tables
+                .values()
+                .find(|table| table.name.as_str() == name)
+                .unwrap()
+        };
+        let (pool, swap, token) = (table("pool"), table("swap"), table("token"));
+
+        let stmts = maintenance_statements(&[
+            (pool, PruningStrategy::Delete),
+            (token, PruningStrategy::Rebuild),
+        ]);
+        assert_eq!(
+            stmts,
+            vec![
+                "vacuum \"sgd42\".\"pool\"",
+                "reindex index concurrently \"sgd42\".\"brin_pool\"",
+                "reindex index concurrently \"sgd42\".\"pool_block_range_closed\"",
+                // Debug builds always create exclusion constraints
+                "reindex index \"sgd42\".\"pool_id_block_range_excl\"",
+            ]
+        );
+
+        // Immutable tables have no indexes on `block_range`
+        let stmts = maintenance_statements(&[(swap, PruningStrategy::Delete)]);
+        assert_eq!(stmts, vec!["vacuum \"sgd42\".\"swap\""]);
+
+        // Postgres truncates index names to 63 characters
+        let snapshot = table("liquidity_position_snapshot_for_every_account_and_pool");
+        let stmts = maintenance_statements(&[(snapshot, PruningStrategy::Delete)]);
+        assert_eq!(
+            stmts,
+            vec![
+                "vacuum \"sgd42\".\"liquidity_position_snapshot_for_every_account_and_pool\"",
+                "reindex index concurrently \"sgd42\".\"brin_liquidity_position_snapshot_for_every_account_and_pool\"",
+                "reindex index concurrently \"sgd42\".\"liquidity_position_snapshot_for_every_account_and_pool_block_ra\"",
+                "reindex index \"sgd42\".\"liquidity_position_snapshot_for_every_account_and_pool_id_block\"",
+            ]
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::{collections::BTreeSet, sync::Arc};
 use diesel::{debug_query, pg::Pg};
 use graph::{
     components::store::write::RowGroup,
+    data::store::{Id, IdList, IdType},
     data_source::CausalityRegion,
     entity,
     prelude::{DeploymentHash, EntityFilter, r, serde_json as json},
@@ -13,7 +14,10 @@ use crate::{
     block_range::BoundSide,
     layout_for_tests::{Namespace, make_dummy_site},
     relational::{Catalog, ColumnType, Layout},
-    relational_queries::{FindRangeQuery, FromColumnValue, InsertQuery},
+    relational_queries::{
+        ClampRangeQuery, CountAffectedQuery, CurrentAndAtQuery, FindRangeQuery, FromColumnValue,
+        InsertQuery,
+    },
 };
 
 use crate::relational_queries::Filter;
@@ -259,3 +263,81 @@ fn default_immutable_insert_has_no_on_conflict_skip_duplicates() {
         sql
     );
 }
+
+#[test]
+fn count_affected_uses_the_same_predicate_as_the_operation() {
+    const SCHEMA: &str = "
+    type Thing @entity {
+        id: String!,
+        name: String
+    }";
+    let layout = test_layout(SCHEMA);
+    let table = layout
+        .table_for_entity(&layout.input_schema.entity_type("Thing").unwrap())
+        .unwrap();
+
+    // The part of the query after `where`, without binds and with all bind
+    // placeholders replaced by `$` since their numbering differs
+    let where_clause = |sql: String| {
+        let (_, clause) = sql.split_once(" where ").unwrap();
+        let (clause, _) = clause.split_once(" -- binds: ").unwrap();
+        let mut normalized = String::new();
+        let mut chars = clause.chars().peekable();
+        while let Some(c) = chars.next() {
+            normalized.push(c);
+            if c == '$' {
+                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                }
+            }
+        }
+        normalized
+    };
+
+    let ids = IdList::try_from_iter(
+        IdType::String,
+        ["one", "two"].into_iter().map(|id| Id::String(id.into())),
+    )
+    .unwrap();
+    let clamp = ClampRangeQuery::new(table, &ids, 7).unwrap();
+    let clamp_sql = debug_query::<Pg, _>(&clamp).to_string();
+    let count = clamp.count_affected();
+    let count_sql = debug_query::<Pg, _>(&count).to_string();
+
+    assert!(count_sql.starts_with(&format!("select count(*) from {}", table.qualified_name)));
+    assert_eq!(where_clause(count_sql), where_clause(clamp_sql));
+
+    let prune = CountAffectedQuery::prune_below(table, 7);
+    let prune_sql = debug_query::<Pg, _>(&prune).to_string();
+    assert!(
+        prune_sql.contains(r#"coalesce(upper("block_range"), 2147483647) <= $1 -- binds: [7]"#),
+        "unexpected query {prune_sql}"
+    );
+}
+
+#[test]
+fn current_and_at_query_reads_both_versions_at_once() {
+    const SCHEMA: &str = "
+    type Thing @entity {
+        id: String!,
+        name: String
+    }";
+    let layout = test_layout(SCHEMA);
+    let entity_type = layout.input_schema.entity_type("Thing").unwrap();
+    let table = layout.table_for_entity(&entity_type).unwrap();
+    let key = entity_type.parse_key("one").unwrap();
+
+    let query = CurrentAndAtQuery::new(table, &key, 7);
+    let sql = debug_query::<Pg, _>(&query).to_string();
+
+    let (current, past) = sql.split_once("\nunion all\n").unwrap();
+    assert!(
+        current.contains("block_range @> 2147483647"),
+        "unexpected query {sql}"
+    );
+    assert!(
+        past.contains("e.block_range @> $"),
+        "unexpected query {sql}"
+    );
+    assert!(sql.ends_with(r#"-- binds: ["Thing", "one", "Thing", "one", 7]"#));
+}
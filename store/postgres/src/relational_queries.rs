@@ -11,9 +11,11 @@ use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
 use diesel::query_dsl::RunQueryDsl;
 use diesel::result::{Error as DieselError, QueryResult};
 use diesel::sql_types::Untyped;
-use diesel::sql_types::{Array, BigInt, Binary, Bool, Int8, Integer, Jsonb, Text, Timestamptz};
+use diesel::sql_types::{
+    Array, BigInt, Binary, Bool, Int8, Integer, Jsonb, Nullable, Text, Timestamptz,
+};
 use graph::components::store::write::{EntityWrite, RowGroup, WriteChunk};
-use graph::components::store::{Child as StoreChild, DerivedEntityQuery};
+use graph::components::store::{ChangelogCursor, Child as StoreChild, DerivedEntityQuery};
 
 use graph::data::graphql::IntoValue;
 use graph::data::store::{Id, IdType, NULL};
@@ -21,17 +23,18 @@ use graph::data::store::{IdList, IdRef, QueryObject};
 use graph::data::value::{Object, Word};
 use graph::data_source::CausalityRegion;
 use graph::prelude::{
-    BlockNumber, ChildMultiplicity, ENV_VARS, Entity, EntityCollection, EntityFilter, EntityLink,
-    EntityOrder, EntityOrderByChild, EntityOrderByChildInfo, EntityRange, EntityWindow, ParentLink,
-    QueryExecutionError, StoreError, Value, anyhow, r, serde_json,
+    BLOCK_NUMBER_MAX, BlockNumber, ChildMultiplicity, ENV_VARS, Entity, EntityCollection,
+    EntityFilter, EntityLink, EntityOrder, EntityOrderByChild, EntityOrderByChildInfo, EntityRange,
+    EntityWindow, ParentLink, QueryExecutionError, StoreError, Value, anyhow, r, serde_json,
 };
-use graph::schema::{EntityType, FulltextAlgorithm, FulltextConfig, InputSchema};
+use graph::schema::{EntityKey, EntityType, FulltextAlgorithm, FulltextConfig, InputSchema};
 use graph::{
     components::store::{AggregationCurrent, AttributeNames},
     data::store::scalar,
 };
 use inflector::Inflector;
 use itertools::Itertools;
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::convert::TryFrom;
 use std::fmt::{self, Display};
@@ -1900,6 +1903,7 @@ pub struct FindRangeQuery<'a> {
     bound_side: BoundSide,
     imm_range: EntityBlockRange,
     mut_range: EntityBlockRange,
+    page: Option<(Option<&'a ChangelogCursor>, i64)>,
 }
 
 impl<'a> FindRangeQuery<'a> {
@@ -1917,7 +1921,76 @@ impl<'a> FindRangeQuery<'a> {
             bound_side,
             imm_range,
             mut_range,
+            page: None,
+        }
+    }
+
+    /// Only return the first `limit` rows that come after `after` in the
+    /// order of `(block_number, entity, id)`, where entities are compared
+    /// bytewise
+    pub fn paged(self, after: Option<&'a ChangelogCursor>, limit: i64) -> Self {
+        Self {
+            page: Some((after, limit)),
+            ..self
+        }
+    }
+
+    fn block_range(&self, table: &Table) -> &EntityBlockRange {
+        if table.immutable {
+            &self.imm_range
+        } else {
+            &self.mut_range
+        }
+    }
+
+    /// Cast id to bytea to ensure consistent types across UNION. The actual
+    /// id type can be text, bytea, or numeric depending on the entity
+    fn id_as_bytea(table: &Table, out: &mut AstPass<'_, '_, Pg>) {
+        // We only support entity id types of string, bytes, and int8.
+        match table.primary_key().column_type {
+            ColumnType::String => out.push_sql("id::bytea"),
+            ColumnType::Bytes => out.push_sql("id"),
+            ColumnType::Int8 => out.push_sql("id::text::bytea"),
+            _ => out.push_sql("id::bytea"),
+        }
+    }
+
+    /// Restrict the rows of `table` to those after the cursor `after`.
+    /// Since all rows of a table have the same entity, comparing the entity
+    /// with the cursor leaves a condition on the block and the id that
+    /// can use the indexes of the table
+    fn after_cursor<'b>(
+        &'b self,
+        table: &Table,
+        after: &'b ChangelogCursor,
+        out: &mut AstPass<'_, 'b, Pg>,
+    ) -> QueryResult<()> {
+        let block_range = self.block_range(table);
+        out.push_sql(" and");
+        match table.object.as_str().cmp(after.entity_type.as_str()) {
+            Ordering::Less => {
+                block_range.compare_column(out);
+                out.push_sql("> ");
+                out.push_bind_param::<Integer, _>(&after.block)?;
+            }
+            Ordering::Equal => {
+                out.push_sql(" (");
+                block_range.compare_column(out);
+                out.push_sql(", ");
+                Self::id_as_bytea(table, out);
+                out.push_sql(") > (");
+                out.push_bind_param::<Integer, _>(&after.block)?;
+                out.push_sql(", ");
+                out.push_bind_param::<Binary, _>(&after.id)?;
+                out.push_sql(")");
+            }
+            Ordering::Greater => {
+                block_range.compare_column(out);
+                out.push_sql(">= ");
+                out.push_bind_param::<Integer, _>(&after.block)?;
+            }
         }
+        Ok(())
     }
 }
 
@@ -1931,9 +2004,18 @@ impl<'a> QueryFragment<Pg> for FindRangeQuery<'a> {
             if matches!(self.bound_side, BoundSide::Lower) || !table.immutable {
                 if first {
                     first = false;
+                    if self.page.is_some() {
+                        out.push_sql("select * from (\n");
+                    }
                 } else {
                     out.push_sql("\nunion all\n");
                 }
+                // With paging, each table only contributes its first
+                // `limit` rows so that the union never has more than
+                // `limit` rows per table to sort
+                if self.page.is_some() {
+                    out.push_sql("(");
+                }
 
                 // Generate
                 //    select '..' as entity, to_jsonb(e.*) as data, {BLOCK_STATEMENT} as block_number
@@ -1943,23 +2025,9 @@ impl<'a> QueryFragment<Pg> for FindRangeQuery<'a> {
                 out.push_sql("select ");
                 out.push_bind_param::<Text, _>(table.object.as_str())?;
                 out.push_sql(" as entity, to_jsonb(e.*) as data,");
-                if table.immutable {
-                    self.imm_range.compare_column(&mut out)
-                } else {
-                    self.mut_range.compare_column(&mut out)
-                }
-                // Cast id to bytea to ensure consistent types across UNION
-                // The actual id type can be text, bytea, or numeric depending on the entity
+                self.block_range(table).compare_column(&mut out);
                 out.push_sql("as block_number, ");
-                let pk_column = table.primary_key();
-
-                // We only support entity id types of string, bytes, and int8.
-                match pk_column.column_type {
-                    ColumnType::String => out.push_sql("id::bytea"),
-                    ColumnType::Bytes => out.push_sql("id"),
-                    ColumnType::Int8 => out.push_sql("id::text::bytea"),
-                    _ => out.push_sql("id::bytea"),
-                }
+                Self::id_as_bytea(table, &mut out);
                 out.push_sql(" as id, vid\n");
                 out.push_sql("  from ");
                 out.push_sql(table.qualified_name.as_str());
@@ -1970,10 +2038,14 @@ impl<'a> QueryFragment<Pg> for FindRangeQuery<'a> {
                     out.push_bind_param::<Integer, _>(&self.causality_region)?;
                     out.push_sql(" and ");
                 }
-                if table.immutable {
-                    self.imm_range.contains(&mut out)?;
-                } else {
-                    self.mut_range.contains(&mut out)?;
+                self.block_range(table).contains(&mut out)?;
+                if let Some((after, limit)) = &self.page {
+                    if let Some(after) = after {
+                        self.after_cursor(table, after, &mut out)?;
+                    }
+                    out.push_sql("\n  order by block_number, id limit ");
+                    out.push_bind_param::<BigInt, _>(limit)?;
+                    out.push_sql(")");
                 }
             }
         }
@@ -1984,7 +2056,14 @@ impl<'a> QueryFragment<Pg> for FindRangeQuery<'a> {
             // empty result.
             out.push_sql("select 'dummy_entity' as entity, to_jsonb(1) as data, 1 as block_number, '\\x'::bytea as id, 1 as vid where false");
         } else {
-            out.push_sql("\norder by block_number, entity, id");
+            match &self.page {
+                Some((_, limit)) => {
+                    // Compare entities bytewise, like `after_cursor` does
+                    out.push_sql("\n) c\norder by block_number, entity collate \"C\", id limit ");
+                    out.push_bind_param::<BigInt, _>(limit)?;
+                }
+                None => out.push_sql("\norder by block_number, entity, id"),
+            }
         }
 
         Ok(())
@@ -4906,6 +4985,23 @@ impl<'a> ClampRangeQuery<'a> {
             })
         }
     }
+
+    /// A query that counts the rows this query would clamp without
+    /// changing them
+    pub fn count_affected(self) -> CountAffectedQuery<'a> {
+        CountAffectedQuery {
+            table: self.table,
+            rows: AffectedRows::Clamp(self),
+        }
+    }
+
+    fn filter<'b>(&'b self, out: &mut AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        id_is_in(self.entity_ids, out)?;
+        out.push_sql(" and (");
+        self.br_column.latest(out);
+        out.push_sql(")");
+        Ok(())
+    }
 }
 
 impl<'a> QueryFragment<Pg> for ClampRangeQuery<'a> {
@@ -4920,23 +5016,846 @@ impl<'a> QueryFragment<Pg> for ClampRangeQuery<'a> {
         out.push_sql("\n   set ");
         self.br_column.clamp(&mut out)?;
         out.push_sql("\n where ");
+        self.filter(&mut out)
+    }
+}
 
-        id_is_in(self.entity_ids, &mut out)?;
-        out.push_sql(" and (");
+impl<'a> QueryId for ClampRangeQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for ClampRangeQuery<'a> {}
+
+#[derive(Debug)]
+enum AffectedRows<'a> {
+    Clamp(ClampRangeQuery<'a>),
+    PrunedBelow(BlockRangeColumn<'a>),
+}
+
+/// Count the rows that clamping block ranges or pruning history would
+/// modify, using the same predicate as the actual operation, but without
+/// modifying anything
+#[derive(Debug)]
+pub struct CountAffectedQuery<'a> {
+    table: &'a Table,
+    rows: AffectedRows<'a>,
+}
+
+impl<'a> CountAffectedQuery<'a> {
+    /// Count the versions that pruning all history before `earliest_block`
+    /// would delete
+    pub fn prune_below(table: &'a Table, earliest_block: BlockNumber) -> Self {
+        let br_column = BlockRangeColumn::new(table, "", earliest_block);
+        Self {
+            table,
+            rows: AffectedRows::PrunedBelow(br_column),
+        }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for CountAffectedQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select count(*) from table where {predicate}
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select count(*) from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql("\n where ");
+        match &self.rows {
+            AffectedRows::Clamp(clamp) => clamp.filter(&mut out),
+            AffectedRows::PrunedBelow(br_column) => br_column.closed_before(&mut out),
+        }
+    }
+}
+
+impl<'a> QueryId for CountAffectedQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for CountAffectedQuery<'a> {
+    type SqlType = BigInt;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for CountAffectedQuery<'a> {}
+
+/// Count the entities in a table, i.e., the latest version of each
+/// entity. For mutable tables, the predicate on the block range can use
+/// the index on the block range; immutable tables are simply counted
+#[derive(Debug)]
+pub struct CountEntitiesQuery<'a> {
+    table: &'a Table,
+    br_column: BlockRangeColumn<'a>,
+}
+
+impl<'a> CountEntitiesQuery<'a> {
+    pub fn new(table: &'a Table) -> Self {
+        let br_column = BlockRangeColumn::new(table, "", BLOCK_NUMBER_MAX);
+        Self { table, br_column }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for CountEntitiesQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select count(*) from table where {latest}
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select count(*) from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql("\n where ");
         self.br_column.latest(&mut out);
-        out.push_sql(")");
+        Ok(())
+    }
+}
 
+impl<'a> QueryId for CountEntitiesQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for CountEntitiesQuery<'a> {
+    type SqlType = BigInt;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for CountEntitiesQuery<'a> {}
+
+/// An id returned by `DuplicateIdsQuery` together with the blocks at which
+/// each of its rows was written, in ascending order
+#[derive(QueryableByName, Debug)]
+pub struct DuplicateIdData {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Array<Integer>)]
+    pub blocks: Vec<BlockNumber>,
+}
+
+/// A query that finds the ids that occur in more than one row of an
+/// immutable table, ordered by id. Since entities in immutable tables are
+/// never updated, each of them should have exactly one row
+#[derive(Debug)]
+pub struct DuplicateIdsQuery<'a> {
+    table: &'a Table,
+}
+
+impl<'a> DuplicateIdsQuery<'a> {
+    pub(crate) fn new(table: &'a Table) -> Result<Self, StoreError> {
+        if !table.immutable {
+            return Err(graph::internal_error!(
+                "can not look for duplicate ids in mutable table `{}`",
+                table.qualified_name
+            ));
+        }
+        Ok(Self { table })
+    }
+}
+
+impl<'a> QueryFragment<Pg> for DuplicateIdsQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select id::text as id, array_agg(block$ order by block$) as blocks
+        //   from schema.<table>
+        //  group by id
+        // having count(*) > 1
+        //  order by id
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select ");
+        out.push_sql(PRIMARY_KEY_COLUMN);
+        out.push_sql("::text as id, array_agg(");
+        out.push_sql(BLOCK_COLUMN);
+        out.push_sql(" order by ");
+        out.push_sql(BLOCK_COLUMN);
+        out.push_sql(") as blocks\n  from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql("\n group by ");
+        out.push_sql(PRIMARY_KEY_COLUMN);
+        out.push_sql("\nhaving count(*) > 1\n order by ");
+        out.push_sql(PRIMARY_KEY_COLUMN);
         Ok(())
     }
 }
 
-impl<'a> QueryId for ClampRangeQuery<'a> {
+impl<'a> QueryId for DuplicateIdsQuery<'a> {
     type QueryId = ();
 
     const HAS_STATIC_QUERY_ID: bool = false;
 }
 
-impl<'a, Conn> RunQueryDsl<Conn> for ClampRangeQuery<'a> {}
+impl<'a> Query for DuplicateIdsQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for DuplicateIdsQuery<'a> {}
+
+/// Two versions of the same entity returned by `OverlappingRangesQuery`.
+/// The ends of the block ranges are `None` for current versions
+#[derive(QueryableByName, Debug)]
+pub struct OverlappingRangeData {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Integer)]
+    pub first_start: BlockNumber,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub first_end: Option<BlockNumber>,
+    #[diesel(sql_type = Integer)]
+    pub second_start: BlockNumber,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub second_end: Option<BlockNumber>,
+}
+
+/// A query that finds the pairs of versions of the same entity in a
+/// mutable table whose block ranges overlap, ordered by id and then by
+/// vid. Since an entity can only have one version at any block, there
+/// should never be any such pairs
+#[derive(Debug)]
+pub struct OverlappingRangesQuery<'a> {
+    table: &'a Table,
+}
+
+impl<'a> OverlappingRangesQuery<'a> {
+    pub(crate) fn new(table: &'a Table) -> Result<Self, StoreError> {
+        if table.immutable {
+            return Err(graph::internal_error!(
+                "can not look for overlapping block ranges in immutable table `{}`",
+                table.qualified_name
+            ));
+        }
+        Ok(Self { table })
+    }
+}
+
+impl<'a> QueryFragment<Pg> for OverlappingRangesQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select a.id::text as id,
+        //        lower(a.block_range) as first_start,
+        //        upper(a.block_range) as first_end,
+        //        lower(b.block_range) as second_start,
+        //        upper(b.block_range) as second_end
+        //   from schema.<table> a
+        //   join schema.<table> b
+        //     on a.id = b.id and a.vid < b.vid
+        //    and a.block_range && b.block_range
+        //  order by a.id, a.vid, b.vid
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select a.");
+        out.push_sql(PRIMARY_KEY_COLUMN);
+        out.push_sql("::text as id");
+        for (alias, prefix) in [("a", "first"), ("b", "second")] {
+            for (bound, name) in [("lower", "start"), ("upper", "end")] {
+                out.push_sql(",\n       ");
+                out.push_sql(bound);
+                out.push_sql("(");
+                out.push_sql(alias);
+                out.push_sql(".");
+                out.push_sql(BLOCK_RANGE_COLUMN);
+                out.push_sql(") as ");
+                out.push_sql(prefix);
+                out.push_sql("_");
+                out.push_sql(name);
+            }
+        }
+        out.push_sql("\n  from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" a\n  join ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" b\n    on a.");
+        out.push_sql(PRIMARY_KEY_COLUMN);
+        out.push_sql(" = b.");
+        out.push_sql(PRIMARY_KEY_COLUMN);
+        out.push_sql(" and a.");
+        out.push_sql(VID_COLUMN);
+        out.push_sql(" < b.");
+        out.push_sql(VID_COLUMN);
+        out.push_sql("\n   and a.");
+        out.push_sql(BLOCK_RANGE_COLUMN);
+        out.push_sql(" && b.");
+        out.push_sql(BLOCK_RANGE_COLUMN);
+        out.push_sql("\n order by a.");
+        out.push_sql(PRIMARY_KEY_COLUMN);
+        out.push_sql(", a.");
+        out.push_sql(VID_COLUMN);
+        out.push_sql(", b.");
+        out.push_sql(VID_COLUMN);
+        Ok(())
+    }
+}
+
+impl<'a> QueryId for OverlappingRangesQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for OverlappingRangesQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for OverlappingRangesQuery<'a> {}
+
+/// The number of versions returned by `WriteRateQuery`, the number of
+/// distinct entities they belong to, and the number of distinct blocks at
+/// which they were created
+#[derive(QueryableByName, Debug)]
+pub struct WriteRateData {
+    #[diesel(sql_type = BigInt)]
+    pub versions: i64,
+    #[diesel(sql_type = BigInt)]
+    pub entities: i64,
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+}
+
+/// A query that counts the versions of entities in `table` that were
+/// created after block `since`. Updates and inserts both create a new
+/// version, so that the count is the number of writes to the table
+#[derive(Debug)]
+pub struct WriteRateQuery<'a> {
+    table: &'a Table,
+    br_column: BlockRangeColumn<'a>,
+    since: BlockNumber,
+}
+
+impl<'a> WriteRateQuery<'a> {
+    pub fn new(table: &'a Table, since: BlockNumber) -> Self {
+        let br_column = BlockRangeColumn::new(table, "e.", BLOCK_NUMBER_MAX);
+        Self {
+            table,
+            br_column,
+            since,
+        }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for WriteRateQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select count(*) as versions,
+        //        count(distinct e.id) as entities,
+        //        count(distinct lower(e.block_range)) as blocks
+        //   from schema.<table> e
+        //  where lower(e.block_range) > $since
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select count(*) as versions,\n");
+        out.push_sql("       count(distinct e.");
+        out.push_identifier(self.table.primary_key().name.as_str())?;
+        out.push_sql(") as entities,\n");
+        out.push_sql("       count(distinct ");
+        self.br_column.lower(&mut out);
+        out.push_sql(") as blocks\n  from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e\n where ");
+        self.br_column.lower(&mut out);
+        out.push_sql(" > ");
+        out.push_bind_param::<Integer, _>(&self.since)
+    }
+}
+
+impl<'a> QueryId for WriteRateQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for WriteRateQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for WriteRateQuery<'a> {}
+
+/// An entity returned by `FindCurrentQuery` together with its causality
+/// region, which `to_jsonb(e.*)` includes but deserializing the entity
+/// drops
+#[derive(QueryableByName, Debug)]
+pub struct CurrentEntityData {
+    #[diesel(sql_type = Text)]
+    entity: String,
+    #[diesel(sql_type = Jsonb)]
+    data: serde_json::Value,
+    #[diesel(sql_type = Integer)]
+    causality_region: CausalityRegion,
+}
+
+impl CurrentEntityData {
+    pub fn into_parts(self) -> (EntityData, CausalityRegion) {
+        (
+            EntityData::new(self.entity, self.data),
+            self.causality_region,
+        )
+    }
+}
+
+/// Find the next `limit` entities in a table, ordered by id, after the
+/// entity identified by `after`. Only the latest version of each entity is
+/// returned. Since the query continues from a key rather than an offset,
+/// paging through a table that is written to at the same time never
+/// returns an entity twice and never skips an entity that existed
+/// throughout.
+///
+/// Tables with a causality region can contain the same id in several
+/// causality regions, and are therefore ordered by id and causality region
+#[derive(Debug)]
+pub struct FindCurrentQuery<'a> {
+    table: &'a Table,
+    br_column: BlockRangeColumn<'a>,
+    after: Option<&'a (Id, CausalityRegion)>,
+    limit: i64,
+}
+
+impl<'a> FindCurrentQuery<'a> {
+    pub fn new(table: &'a Table, after: Option<&'a (Id, CausalityRegion)>, limit: usize) -> Self {
+        let br_column = BlockRangeColumn::new(table, "e.", BLOCK_NUMBER_MAX);
+        Self {
+            table,
+            br_column,
+            after,
+            limit: limit as i64,
+        }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for FindCurrentQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // Generate
+        //    select $object as entity, to_jsonb(e.*) as data,
+        //           e.causality_region
+        //      from schema.<table> e
+        //     where {latest} and (e.id, e.causality_region) > ($id, $cr)
+        //     order by e.id, e.causality_region
+        //     limit $limit
+        //
+        // Tables without a causality region use 0, the onchain region
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select ");
+        out.push_bind_param::<Text, _>(self.table.object.as_str())?;
+        out.push_sql(" as entity, to_jsonb(e.*) as data,\n");
+        if self.table.has_causality_region {
+            out.push_sql("       e.causality_region\n");
+        } else {
+            out.push_sql("       0 as causality_region\n");
+        }
+        out.push_sql("  from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e\n where ");
+        self.br_column.latest(&mut out);
+        if let Some((id, cr)) = self.after {
+            if self.table.has_causality_region {
+                out.push_sql(" and (e.id, e.causality_region) > (");
+                id.push_bind_param(&mut out)?;
+                out.push_sql(", ");
+                out.push_bind_param::<Integer, _>(cr)?;
+                out.push_sql(")");
+            } else {
+                out.push_sql(" and e.id > ");
+                id.push_bind_param(&mut out)?;
+            }
+        }
+        out.push_sql("\n order by e.id");
+        if self.table.has_causality_region {
+            out.push_sql(", e.causality_region");
+        }
+        out.push_sql("\n limit ");
+        out.push_bind_param::<BigInt, _>(&self.limit)?;
+        Ok(())
+    }
+}
+
+impl<'a> QueryId for FindCurrentQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for FindCurrentQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for FindCurrentQuery<'a> {}
+
+/// Find the block at which the entity with `id` was first created. Since
+/// the entity might have been deleted and created again, this is the
+/// earliest block of any of its versions. The result is `null` if there
+/// never was an entity with that id
+#[derive(Debug)]
+pub struct CreationBlockQuery<'a> {
+    table: &'a Table,
+    br_column: BlockRangeColumn<'a>,
+    id: &'a Id,
+}
+
+impl<'a> CreationBlockQuery<'a> {
+    pub fn new(table: &'a Table, id: &'a Id) -> Self {
+        let br_column = BlockRangeColumn::new(table, "e.", BLOCK_NUMBER_MAX);
+        Self {
+            table,
+            br_column,
+            id,
+        }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for CreationBlockQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select min(lower(e.block_range)) from schema.<table> e where e.id = $id
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select min(");
+        self.br_column.lower(&mut out);
+        out.push_sql(") from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e\n where e.id = ");
+        self.id.push_bind_param(&mut out)
+    }
+}
+
+impl<'a> QueryId for CreationBlockQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for CreationBlockQuery<'a> {
+    type SqlType = Nullable<Integer>;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for CreationBlockQuery<'a> {}
+
+/// Find the highest block at which any version of an entity in `table`
+/// was written. The query returns no rows if the table is empty
+#[derive(Debug)]
+pub struct LatestWriteBlockQuery<'a> {
+    table: &'a Table,
+    br_column: BlockRangeColumn<'a>,
+}
+
+impl<'a> LatestWriteBlockQuery<'a> {
+    pub fn new(table: &'a Table) -> Self {
+        let br_column = BlockRangeColumn::new(table, "e.", BLOCK_NUMBER_MAX);
+        Self { table, br_column }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for LatestWriteBlockQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select lower(e.block_range) from schema.<table> e
+        //  order by e.vid desc limit 1
+        //
+        // Versions are written in block order, and reverts delete all
+        // versions above the block they revert to, so that the version
+        // with the highest `vid` also has the highest block. Unlike
+        // `max(lower(e.block_range))`, which needs to scan the whole table
+        // since the block range only has a BRIN index, this only reads the
+        // last entry of the primary key index
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select ");
+        self.br_column.lower(&mut out);
+        out.push_sql(" from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e order by e.");
+        out.push_sql(VID_COLUMN);
+        out.push_sql(" desc limit 1");
+        Ok(())
+    }
+}
+
+impl<'a> QueryId for LatestWriteBlockQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for LatestWriteBlockQuery<'a> {
+    type SqlType = Integer;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for LatestWriteBlockQuery<'a> {}
+
+/// One of the versions returned by `CurrentAndAtQuery`; `current` tells
+/// whether it is the current version or the version at the past block
+#[derive(QueryableByName, Debug)]
+pub struct CurrentAndAtData {
+    #[diesel(sql_type = Bool)]
+    pub current: bool,
+    #[diesel(sql_type = Text)]
+    pub entity: String,
+    #[diesel(sql_type = Jsonb)]
+    pub data: serde_json::Value,
+}
+
+/// A query that finds both the current version of an entity and the
+/// version that was current at a past block. If both are the same
+/// version, it is returned twice
+#[derive(Debug)]
+pub struct CurrentAndAtQuery<'a> {
+    table: &'a Table,
+    key: &'a EntityKey,
+    current: BlockRangeColumn<'a>,
+    past: BlockRangeColumn<'a>,
+}
+
+impl<'a> CurrentAndAtQuery<'a> {
+    pub fn new(table: &'a Table, key: &'a EntityKey, block: BlockNumber) -> Self {
+        let current = BlockRangeColumn::new(table, "e.", BLOCK_NUMBER_MAX);
+        let past = BlockRangeColumn::new(table, "e.", block);
+        Self {
+            table,
+            key,
+            current,
+            past,
+        }
+    }
+
+    fn select<'b>(
+        &'b self,
+        current: bool,
+        br_column: &'b BlockRangeColumn<'a>,
+        out: &mut AstPass<'_, 'b, Pg>,
+    ) -> QueryResult<()> {
+        out.push_sql("select ");
+        out.push_sql(if current { "true" } else { "false" });
+        out.push_sql(" as current, ");
+        out.push_bind_param::<Text, _>(self.table.object.as_str())?;
+        out.push_sql(" as entity, to_jsonb(e.*) as data\n  from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e\n where e.id = ");
+        self.key.entity_id.push_bind_param(out)?;
+        if self.table.has_causality_region {
+            out.push_sql(" and e.causality_region = ");
+            out.push_bind_param::<Integer, _>(&self.key.causality_region)?;
+        }
+        out.push_sql(" and ");
+        if current {
+            br_column.latest(out);
+            Ok(())
+        } else {
+            br_column.contains(out, true)
+        }
+    }
+}
+
+impl<'a> QueryFragment<Pg> for CurrentAndAtQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select true as current, $object as entity, to_jsonb(e.*) as data
+        //   from schema.<table> e where e.id = $id and block_range @> INTMAX
+        // union all
+        // select false as current, $object as entity, to_jsonb(e.*) as data
+        //   from schema.<table> e where e.id = $id and block_range @> $block
+        out.unsafe_to_cache_prepared();
+        self.select(true, &self.current, &mut out)?;
+        out.push_sql("\nunion all\n");
+        self.select(false, &self.past, &mut out)
+    }
+}
+
+impl<'a> QueryId for CurrentAndAtQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for CurrentAndAtQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for CurrentAndAtQuery<'a> {}
+
+/// The block range of one version returned by `ValueRangesQuery`; `upper`
+/// is `None` for the current version
+#[derive(QueryableByName, Debug)]
+pub struct ValueRangeData {
+    #[diesel(sql_type = Integer)]
+    pub lower: BlockNumber,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub upper: Option<BlockNumber>,
+}
+
+/// A query that finds the block ranges of all versions of the entity with
+/// `key` in which `column` has a given value, ordered by the start of the
+/// block range. For immutable entities, the block range of the only
+/// version starts at the block at which it was created and never ends
+#[derive(Debug)]
+pub struct ValueRangesQuery<'a> {
+    table: &'a Table,
+    br_column: BlockRangeColumn<'a>,
+    key: &'a EntityKey,
+    column: &'a Column,
+    value: QueryValue<'a>,
+}
+
+impl<'a> ValueRangesQuery<'a> {
+    pub fn new(
+        table: &'a Table,
+        key: &'a EntityKey,
+        column: &'a Column,
+        value: &'a Value,
+    ) -> QueryResult<Self> {
+        let br_column = BlockRangeColumn::new(table, "e.", BLOCK_NUMBER_MAX);
+        let value = QueryValue::new(value, &column.column_type)?;
+        Ok(Self {
+            table,
+            br_column,
+            key,
+            column,
+            value,
+        })
+    }
+}
+
+impl<'a> QueryFragment<Pg> for ValueRangesQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select lower(e.block_range) as lower, upper(e.block_range) as upper
+        //   from schema.<table> e
+        //  where e.id = $id and e.causality_region = $cr
+        //    and e.<column> = $value
+        //  order by lower(e.block_range)
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select ");
+        self.br_column.lower(&mut out);
+        out.push_sql(" as lower, ");
+        if self.table.immutable {
+            out.push_sql("null::int4");
+        } else {
+            out.push_sql("upper(");
+            self.br_column.name(&mut out);
+            out.push_sql(")");
+        }
+        out.push_sql(" as upper\n  from ");
+        out.push_sql(self.table.qualified_name.as_str());
+        out.push_sql(" e\n where e.id = ");
+        self.key.entity_id.push_bind_param(&mut out)?;
+        if self.table.has_causality_region {
+            out.push_sql(" and e.causality_region = ");
+            out.push_bind_param::<Integer, _>(&self.key.causality_region)?;
+        }
+        out.push_sql(" and e.");
+        out.push_identifier(self.column.name.as_str())?;
+        if self.value.is_null() {
+            out.push_sql(" is null");
+        } else {
+            out.push_sql(" = ");
+            self.value.walk_ast(out.reborrow())?;
+        }
+        out.push_sql("\n order by ");
+        self.br_column.lower(&mut out);
+        Ok(())
+    }
+}
+
+impl<'a> QueryId for ValueRangesQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for ValueRangesQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for ValueRangesQuery<'a> {}
+
+/// A stretch of blocks returned by `BlockGapsQuery`; `start` and `finish`
+/// are the first and the last block of the stretch
+#[derive(QueryableByName, Debug)]
+pub struct BlockGapData {
+    #[diesel(sql_type = Integer)]
+    pub start: BlockNumber,
+    #[diesel(sql_type = Integer)]
+    pub finish: BlockNumber,
+}
+
+/// A query that finds stretches of at least `min_gap` blocks in
+/// `[from, to]` in which no entity version in any of `tables` starts or
+/// ends, ordered by block number
+#[derive(Debug)]
+pub struct BlockGapsQuery<'a> {
+    tables: Vec<&'a Table>,
+    from: BlockNumber,
+    to: BlockNumber,
+    min_gap: BlockNumber,
+}
+
+impl<'a> BlockGapsQuery<'a> {
+    pub fn new(
+        tables: Vec<&'a Table>,
+        from: BlockNumber,
+        to: BlockNumber,
+        min_gap: BlockNumber,
+    ) -> Self {
+        Self {
+            tables,
+            from,
+            to,
+            min_gap: min_gap.max(1),
+        }
+    }
+
+    /// Add `union select <expr> from <table> where <expr> between $from and $to`
+    fn push_boundaries<'b>(
+        &'b self,
+        out: &mut AstPass<'_, 'b, Pg>,
+        table: &'b Table,
+        expr: &str,
+    ) -> QueryResult<()> {
+        out.push_sql("\n         union select ");
+        out.push_sql(expr);
+        out.push_sql(" from ");
+        out.push_sql(table.qualified_name.as_str());
+        out.push_sql(" where ");
+        out.push_sql(expr);
+        out.push_sql(" between ");
+        out.push_bind_param::<Integer, _>(&self.from)?;
+        out.push_sql(" and ");
+        out.push_bind_param::<Integer, _>(&self.to)
+    }
+}
+
+impl<'a> QueryFragment<Pg> for BlockGapsQuery<'a> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // select b.block + 1 as start, b.next - 1 as finish
+        //   from (select block, lead(block) over (order by block) as next
+        //           from (select $from - 1 as block
+        //                 union select lower(block_range) from t1 where .. between $from and $to
+        //                 union select upper(block_range) from t1 where .. between $from and $to
+        //                 union select block$ from t2 where .. between $from and $to
+        //                 union select $to + 1) boundaries) b
+        //  where b.next - b.block > $min_gap
+        //  order by b.block
+        //
+        // The blocks just outside of `[from, to]` are boundaries, too, so
+        // that stretches at the start or the end of the range are found
+        out.unsafe_to_cache_prepared();
+        out.push_sql("select b.block + 1 as start, b.next - 1 as finish\n  from (");
+        out.push_sql("select block, lead(block) over (order by block) as next\n");
+        out.push_sql("          from (select ");
+        out.push_bind_param::<Integer, _>(&self.from)?;
+        out.push_sql(" - 1 as block");
+        for table in &self.tables {
+            if table.immutable {
+                self.push_boundaries(&mut out, table, BLOCK_COLUMN)?;
+            } else {
+                let lower = format!("lower({BLOCK_RANGE_COLUMN})");
+                let upper = format!("upper({BLOCK_RANGE_COLUMN})");
+                self.push_boundaries(&mut out, table, &lower)?;
+                self.push_boundaries(&mut out, table, &upper)?;
+            }
+        }
+        out.push_sql("\n         union select ");
+        out.push_bind_param::<Integer, _>(&self.to)?;
+        out.push_sql(" + 1) boundaries) b\n where b.next - b.block > ");
+        out.push_bind_param::<Integer, _>(&self.min_gap)?;
+        out.push_sql("\n order by b.block");
+        Ok(())
+    }
+}
+
+impl<'a> QueryId for BlockGapsQuery<'a> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<'a> Query for BlockGapsQuery<'a> {
+    type SqlType = Untyped;
+}
+
+impl<'a, Conn> RunQueryDsl<Conn> for BlockGapsQuery<'a> {}
 
 /// Helper struct for returning the id's touched by the RevertRemove and
 /// RevertExtend queries
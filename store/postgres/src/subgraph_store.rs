@@ -19,7 +19,7 @@ use graph::{
         store::{
             self, BlockPtrForNumber, BlockStore, DeploymentLocator, DeploymentSchemaVersion,
             DumpReporter, EnsLookup as EnsLookupTrait, PruneReporter, PruneRequest,
-            RestoreReporter, SubgraphFork,
+            RestoreReporter, SubgraphFork, WriteRateLimit,
         },
     },
     data::{
@@ -29,16 +29,20 @@ use graph::{
     },
     internal_error,
     prelude::{
-        ApiVersion, BlockNumber, BlockPtr, ChainStore, DeploymentHash, EntityOperation, Logger,
-        MetricsRegistry, NodeId, PartialBlockPtr, StoreError, StoreEvent, SubgraphDeploymentEntity,
-        SubgraphName, SubgraphStore as SubgraphStoreTrait, SubgraphVersionSwitchingMode, anyhow,
-        lazy_static, o,
+        ApiVersion, BlockNumber, BlockPtr, ChainStore, DeploymentHash, Entity, EntityOperation,
+        Logger, MetricsRegistry, NodeId, PartialBlockPtr, StoreError, StoreEvent,
+        SubgraphDeploymentEntity, SubgraphName, SubgraphStore as SubgraphStoreTrait,
+        SubgraphVersionSwitchingMode, Value, anyhow, lazy_static, o,
     },
-    schema::{ApiSchema, InputSchema},
+    schema::{ApiSchema, EntityKey, InputSchema},
     url::Url,
     util::timed_cache::TimedCache,
 };
-use graph::{derive::CheapClone, futures03::future::join_all, prelude::alloy::primitives::Address};
+use graph::{
+    derive::CheapClone,
+    futures03::{future::join_all, stream::BoxStream},
+    prelude::alloy::primitives::Address,
+};
 
 use crate::{
     ConnectionPool, NotificationSender,
@@ -58,7 +62,11 @@ use crate::{
     detail::DeploymentDetail,
     primary::UnusedDeployment,
 };
-use crate::{fork, relational::SqlName, relational::index::CreateIndex};
+use crate::{
+    fork,
+    relational::index::CreateIndex,
+    relational::{BlockRangeOverlap, ImmutabilityViolation, SqlName, WriteRate},
+};
 
 /// The name of a database shard; valid names must match `[a-z0-9_]+`
 #[derive(Clone, Debug, Eq, PartialEq, Hash, AsExpression, FromSqlRow)]
@@ -221,7 +229,9 @@ impl SubgraphStore {
     /// for the same database, and a list of weights determining how often
     /// to use the main pool and the read replicas for queries. The list
     /// of weights must be one longer than the list of read replicas, and
-    /// `weights[0]` is used for the main pool.
+    /// `weights[0]` is used for the main pool. The last entry lists the
+    /// indexes of the read replicas that are only used for queries for
+    /// historical blocks; their weights are ignored.
     ///
     /// All write operations for a shard are performed against the main
     /// pool. One of the shards must be named `primary`
@@ -229,7 +239,13 @@ impl SubgraphStore {
     /// The `placer` determines where `create_subgraph_deployment` puts a new deployment
     pub fn new(
         logger: &Logger,
-        stores: Vec<(Shard, ConnectionPool, Vec<ConnectionPool>, Vec<usize>)>,
+        stores: Vec<(
+            Shard,
+            ConnectionPool,
+            Vec<ConnectionPool>,
+            Vec<usize>,
+            Vec<usize>,
+        )>,
         placer: Arc<dyn DeploymentPlacer + Send + Sync + 'static>,
         sender: Arc<NotificationSender>,
         fork_base: Option<Url>,
@@ -630,7 +646,9 @@ impl Inner {
     /// for the same database, and a list of weights determining how often
     /// to use the main pool and the read replicas for queries. The list
     /// of weights must be one longer than the list of read replicas, and
-    /// `weights[0]` is used for the main pool.
+    /// `weights[0]` is used for the main pool. The last entry lists the
+    /// indexes of the read replicas that are only used for queries for
+    /// historical blocks; their weights are ignored.
     ///
     /// All write operations for a shard are performed against the main
     /// pool. One of the shards must be named `primary`
@@ -638,7 +656,13 @@ impl Inner {
     /// The `placer` determines where `create_subgraph_deployment` puts a new deployment
     pub fn new(
         logger: &Logger,
-        stores: Vec<(Shard, ConnectionPool, Vec<ConnectionPool>, Vec<usize>)>,
+        stores: Vec<(
+            Shard,
+            ConnectionPool,
+            Vec<ConnectionPool>,
+            Vec<usize>,
+            Vec<usize>,
+        )>,
         placer: Arc<dyn DeploymentPlacer + Send + Sync + 'static>,
         sender: Arc<NotificationSender>,
         registry: Arc<MetricsRegistry>,
@@ -646,20 +670,20 @@ impl Inner {
     ) -> Self {
         let primary = stores
             .iter()
-            .find(|(name, _, _, _)| name == &*PRIMARY_SHARD)
-            .map(|(_, pool, _, _)| Primary::new(Arc::new(pool.clone())))
+            .find(|(name, _, _, _, _)| name == &*PRIMARY_SHARD)
+            .map(|(_, pool, _, _, _)| Primary::new(Arc::new(pool.clone())))
             .expect("primary shard must be present");
 
         let mirror = {
             let pools = HashMap::from_iter(
                 stores
                     .iter()
-                    .map(|(name, pool, _, _)| (name.clone(), pool.clone())),
+                    .map(|(name, pool, _, _, _)| (name.clone(), pool.clone())),
             );
             PrimaryMirror::new(&pools)
         };
         let stores = HashMap::from_iter(stores.into_iter().map(
-            |(name, main_pool, read_only_pools, weights)| {
+            |(name, main_pool, read_only_pools, weights, history_replicas)| {
                 let logger = logger.new(o!("shard" => name.to_string()));
 
                 (
@@ -670,6 +694,7 @@ impl Inner {
                         main_pool,
                         read_only_pools,
                         weights,
+                        history_replicas,
                     )),
                 )
             },
@@ -777,6 +802,16 @@ impl Inner {
             .ok_or_else(|| StoreError::UnknownShard(site.shard.to_string()))
     }
 
+    /// The handles for changing how many entity versions per second
+    /// subgraphs may write to each shard while graph-node is running. The
+    /// initial limits come from `GRAPH_STORE_WRITE_ROWS_PER_SECOND`
+    pub fn write_rate_limits(&self) -> BTreeMap<String, WriteRateLimit> {
+        self.stores
+            .iter()
+            .map(|(shard, store)| (shard.to_string(), store.write_throttle.limit()))
+            .collect()
+    }
+
     pub(crate) async fn layout(&self, id: &DeploymentHash) -> Result<Arc<Layout>, StoreError> {
         let (store, site) = self.store(id).await?;
         store.find_layout(site).await
@@ -1357,6 +1392,137 @@ impl Inner {
         store.stats_targets(site).await
     }
 
+    /// Count the entities in each table of `deployment`, ignoring all but
+    /// the latest version of each entity. This has to look at every
+    /// current row and can therefore take a while for large deployments
+    pub async fn count_entities(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<BTreeMap<SqlName, usize>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.count_entities(site).await
+    }
+
+    /// Find the entities in the immutable tables of `deployment` that have
+    /// more than one version, which means that the data of the deployment
+    /// is corrupt. This scans every immutable table
+    pub async fn immutability_violations(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<BTreeMap<SqlName, Vec<ImmutabilityViolation>>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.immutability_violations(site).await
+    }
+
+    /// Find the versions of entities in the mutable tables of `deployment`
+    /// whose block ranges overlap, which means that the data of the
+    /// deployment is corrupt. This joins every mutable table with itself
+    /// and can take a long time for large deployments
+    pub async fn block_range_overlaps(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<BTreeMap<SqlName, Vec<BlockRangeOverlap>>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.block_range_overlaps(site).await
+    }
+
+    /// Compute how often each mutable table of `deployment` was written to
+    /// during the last `window` blocks and flag the tables whose rate is
+    /// at least `min_rate` and whose ratio of entities to versions is at
+    /// most `max_ratio` as candidates for being marked account-like.
+    /// Returns an empty map if the deployment has not processed any blocks
+    pub async fn write_rates(
+        &self,
+        deployment: &DeploymentLocator,
+        window: BlockNumber,
+        min_rate: f64,
+        max_ratio: f64,
+    ) -> Result<BTreeMap<SqlName, WriteRate>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.write_rates(site, window, min_rate, max_ratio).await
+    }
+
+    /// Return the highest block at which `deployment` wrote any entity.
+    /// This is usually close to the block pointer of the deployment; if
+    /// it is ahead of it, the deployment has data that should have been
+    /// reverted
+    pub async fn latest_write_block(
+        &self,
+        deployment: &DeploymentLocator,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.latest_write_block(site).await
+    }
+
+    /// Stream the current entities of type `entity_type` in `deployment`,
+    /// e.g., to export them. Entities are loaded `batch_size` at a time and
+    /// ordered by id, so that even large tables can be exported without
+    /// loading them into memory
+    pub async fn current_entities(
+        &self,
+        deployment: &DeploymentLocator,
+        entity_type: &str,
+        batch_size: usize,
+    ) -> Result<BoxStream<'static, Result<Entity, StoreError>>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        Ok(store.current_entities(site, entity_type.to_string(), batch_size))
+    }
+
+    /// Return the block at which the entity of type `entity_type` with `id`
+    /// was first created in `deployment`, or `None` if there never was
+    /// such an entity
+    pub async fn creation_block(
+        &self,
+        deployment: &DeploymentLocator,
+        entity_type: &str,
+        id: &str,
+    ) -> Result<Option<BlockNumber>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.creation_block(site, entity_type, id).await
+    }
+
+    /// Return the block ranges during which the attribute `field` of the
+    /// entity with `key` in `deployment` had `value`,
+    /// as pairs of the first block with and the first block without that
+    /// value. The second block is `None` if the entity still has the value
+    pub async fn value_ranges(
+        &self,
+        deployment: &DeploymentLocator,
+        key: &EntityKey,
+        field: &str,
+        value: &Value,
+    ) -> Result<Vec<(BlockNumber, Option<BlockNumber>)>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.value_ranges(site, key, field, value).await
+    }
+
+    /// Return the stretches of at least `min_gap` blocks in the synced
+    /// range of `deployment` in which no entity version starts or ends, as
+    /// pairs of the first and the last block of each stretch. This is only
+    /// meant for diagnosing missing data and does not change anything
+    pub async fn block_range_gaps(
+        &self,
+        deployment: &DeploymentLocator,
+        min_gap: BlockNumber,
+    ) -> Result<Vec<(BlockNumber, BlockNumber)>, StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.block_range_gaps(site, min_gap).await
+    }
+
+    /// Return the current version of the entity with `key` in `deployment`
+    /// together with the version that was current at `block`, for example
+    /// to compute how the entity changed since then. Both are read in one
+    /// query so that they are consistent with each other
+    pub async fn get_current_and_at(
+        &self,
+        deployment: &DeploymentLocator,
+        key: &EntityKey,
+        block: BlockNumber,
+    ) -> Result<(Option<Entity>, Option<Entity>), StoreError> {
+        let (store, site) = self.store(&deployment.hash).await?;
+        store.get_current_and_at(site, key, block).await
+    }
+
     /// Set the statistics target for columns `columns` in `deployment`. If
     /// `entity` is `Some`, only set it for the table for that entity, if it
     /// is `None`, set it for all tables in the deployment.
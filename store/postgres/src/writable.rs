@@ -10,7 +10,9 @@ use std::{collections::BTreeMap, sync::Arc};
 use async_trait::async_trait;
 use graph::blockchain::BlockTime;
 use graph::blockchain::block_stream::{EntitySourceOperation, FirehoseCursor};
-use graph::components::store::{Batch, DeploymentCursorTracker, DerivedEntityQuery, ReadStore};
+use graph::components::store::{
+    Batch, ChangelogCursor, ChangelogEntry, DeploymentCursorTracker, DerivedEntityQuery, ReadStore,
+};
 use graph::data::store::IdList;
 use graph::data::subgraph::schema;
 use graph::data_source::CausalityRegion;
@@ -1716,6 +1718,24 @@ impl store::SourceableStore for SourceableStore {
             .await
     }
 
+    async fn get_changelog(
+        &self,
+        causality_region: CausalityRegion,
+        block_range: Range<BlockNumber>,
+        after: Option<ChangelogCursor>,
+        limit: usize,
+    ) -> Result<Vec<ChangelogEntry>, StoreError> {
+        self.store
+            .get_changelog(
+                self.site.clone(),
+                causality_region,
+                block_range,
+                after,
+                limit,
+            )
+            .await
+    }
+
     fn input_schema(&self) -> InputSchema {
         self.input_schema.cheap_clone()
     }
@@ -0,0 +1,108 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use graph::components::store::WriteRateLimit;
+use tokio::time::Instant;
+
+/// Limits how many entity versions per second are written to a shard so
+/// that heavy indexing, for example while many subgraphs reindex, can not
+/// saturate a database that is shared with other users.
+///
+/// Every write reserves time in proportion to the number of rows it
+/// writes, and waits until all earlier reservations have passed. The limit
+/// can be changed at any time and applies to the next write.
+#[derive(Debug)]
+pub struct WriteThrottle {
+    limit: WriteRateLimit,
+    /// The point in time at which all rows written so far are paid for
+    paid_until: Mutex<Instant>,
+}
+
+impl WriteThrottle {
+    pub fn new(rows_per_second: u64) -> Self {
+        Self {
+            limit: WriteRateLimit::new(rows_per_second),
+            paid_until: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// A handle that changes the limit of this throttle
+    pub fn limit(&self) -> WriteRateLimit {
+        self.limit.clone()
+    }
+
+    /// Change the limit; a value of 0 removes it
+    pub fn set_rows_per_second(&self, rows_per_second: u64) {
+        self.limit.set_rows_per_second(rows_per_second);
+    }
+
+    /// Reserve the time for writing `rows` and return how long the write
+    /// has to wait before it can start
+    fn reserve(&self, rows: usize) -> Duration {
+        let rows_per_second = self.limit.rows_per_second();
+        // Without a limit, writes don't need to coordinate with each other
+        if rows_per_second == 0 {
+            return Duration::ZERO;
+        }
+
+        let now = Instant::now();
+        let mut paid_until = self.paid_until.lock().unwrap();
+        let start = (*paid_until).max(now);
+        *paid_until = start + Duration::from_secs_f64(rows as f64 / rows_per_second as f64);
+        start - now
+    }
+
+    /// Wait until `rows` can be written without going over the limit
+    pub async fn wait(&self, rows: usize) {
+        let delay = self.reserve(rows);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn write_rate_stays_under_the_limit() {
+        const LIMIT: u64 = 2_000;
+
+        let throttle = WriteThrottle::new(LIMIT);
+        let start = Instant::now();
+        for _ in 0..10 {
+            throttle.wait(100).await;
+        }
+
+        // The first write does not wait, every later one waits for the
+        // rows written before it. Timers round up to the next millisecond
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(450));
+        assert!(elapsed < Duration::from_millis(460));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_throttle_never_waits() {
+        let throttle = WriteThrottle::new(0);
+        let start = Instant::now();
+        for _ in 0..100 {
+            throttle.wait(1_000_000).await;
+        }
+        assert_eq!(Duration::ZERO, start.elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limit_can_be_changed() {
+        let throttle = WriteThrottle::new(0);
+        assert_eq!(Duration::ZERO, throttle.reserve(1_000));
+
+        throttle.set_rows_per_second(1_000);
+        assert_eq!(Duration::ZERO, throttle.reserve(1_000));
+        assert_eq!(Duration::from_secs(1), throttle.reserve(1_000));
+
+        // Changes through a handle apply to the throttle
+        throttle.limit().set_rows_per_second(500);
+        assert_eq!(Duration::from_secs(2), throttle.reserve(500));
+    }
+}
@@ -243,6 +243,7 @@ pub async fn create_test_subgraph_with_features(
         repository: Some(format!("repo for {}", subgraph_id)),
         schema: schema.clone(),
         data_sources: vec![DataSource::Onchain(MockDataSource {
+            name: "mock".into(),
             kind: DATA_SOURCE_KIND.into(),
             api_version: Version::new(1, 0, 0),
             network: Some(NETWORK_NAME.into()),
@@ -355,6 +356,27 @@ pub async fn transact_and_wait(
     flush(deployment).await
 }
 
+/// Like `transact_entity_operations`, but records `firehose_cursor` as the
+/// cursor for `block_ptr_to`
+pub async fn transact_entity_operations_with_cursor(
+    store: &Arc<DieselSubgraphStore>,
+    deployment: &DeploymentLocator,
+    block_ptr_to: BlockPtr,
+    firehose_cursor: FirehoseCursor,
+    ops: Vec<EntityOperation>,
+) -> Result<(), StoreError> {
+    transact_with_cursor(
+        store,
+        deployment.clone(),
+        block_ptr_to,
+        firehose_cursor,
+        vec![],
+        ops,
+        vec![],
+    )
+    .await
+}
+
 pub async fn transact_entities_and_dynamic_data_sources(
     store: &Arc<DieselSubgraphStore>,
     deployment: DeploymentLocator,
@@ -362,6 +384,27 @@ pub async fn transact_entities_and_dynamic_data_sources(
     data_sources: Vec<StoredDynamicDataSource>,
     ops: Vec<EntityOperation>,
     manifest_idx_and_name: Vec<(u32, String)>,
+) -> Result<(), StoreError> {
+    transact_with_cursor(
+        store,
+        deployment,
+        block_ptr_to,
+        FirehoseCursor::None,
+        data_sources,
+        ops,
+        manifest_idx_and_name,
+    )
+    .await
+}
+
+async fn transact_with_cursor(
+    store: &Arc<DieselSubgraphStore>,
+    deployment: DeploymentLocator,
+    block_ptr_to: BlockPtr,
+    firehose_cursor: FirehoseCursor,
+    data_sources: Vec<StoredDynamicDataSource>,
+    ops: Vec<EntityOperation>,
+    manifest_idx_and_name: Vec<(u32, String)>,
 ) -> Result<(), StoreError> {
     let store = graph::futures03::executor::block_on(store.cheap_clone().writable(
         LOGGER.clone(),
@@ -393,7 +436,7 @@ pub async fn transact_entities_and_dynamic_data_sources(
         .transact_block_operations(
             block_ptr_to,
             block_time,
-            FirehoseCursor::None,
+            firehose_cursor,
             mods,
             &stopwatch_metrics,
             data_sources,
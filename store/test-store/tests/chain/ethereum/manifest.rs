@@ -1479,6 +1479,87 @@ dataSources:
     });
 }
 
+#[test]
+fn unsupported_handler_kind_causes_validation_error() {
+    const YAML: &str = "
+specVersion: 0.0.4
+features:
+  - ipfsOnEthereumContracts
+schema:
+  file:
+    /: /ipfs/Qmschema
+dataSources:
+  - kind: ethereum/contract
+    name: Factory
+    network: mainnet
+    source:
+      abi: Factory
+      startBlock: 9562480
+    mapping:
+      kind: ethereum/events
+      apiVersion: 0.0.4
+      language: wasm/assemblyscript
+      entities:
+        - TestEntity
+      file:
+        /: /ipfs/Qmmapping
+      abis:
+        - name: Factory
+          file:
+            /: /ipfs/Qmabi
+      callHandlers:
+        - function: get(address)
+          handler: handleget
+      receiptHandlers:
+        - handler: handleReceipt
+";
+
+    test_store::run_test_sequentially(|store| async move {
+        let store = store.subgraph_store();
+        let unvalidated: UnvalidatedSubgraphManifest<Chain> = {
+            let mut resolver = TextResolver::default();
+            let id = DeploymentHash::new("Qmmanifest").unwrap();
+            resolver.add(id.as_str(), &YAML);
+            resolver.add("/ipfs/Qmabi", &ABI);
+            resolver.add("/ipfs/Qmschema", &GQL_SCHEMA);
+            resolver.add("/ipfs/Qmmapping", &MAPPING_WITH_IPFS_FUNC_WASM);
+
+            let resolver: Arc<dyn LinkResolver> = Arc::new(resolver);
+
+            let raw = serde_yaml::from_str(YAML).unwrap();
+            UnvalidatedSubgraphManifest::resolve(
+                id,
+                raw,
+                &resolver,
+                Option::<Arc<amp::FlightClient>>::None,
+                &LOGGER,
+                SPEC_VERSION_0_0_4.clone(),
+            )
+            .await
+            .expect("Parsing manifest with receipt handlers works")
+        };
+
+        let error_msg = unvalidated
+            .validate(store.clone(), true)
+            .await
+            .expect_err("Validation must fail")
+            .into_iter()
+            .find(|e| {
+                matches!(
+                    e,
+                    SubgraphManifestValidationError::UnsupportedHandlerKinds { .. }
+                )
+            })
+            .expect("There must be an UnsupportedHandlerKinds error")
+            .to_string();
+
+        assert_eq!(
+            "data source Factory uses handler kinds that are not supported on this chain: receipt",
+            error_msg
+        );
+    });
+}
+
 #[test]
 fn declared_ipfs_on_ethereum_contracts_feature_causes_no_errors() {
     const YAML: &str = "
@@ -2,24 +2,28 @@
 //! the chain head pointer gets updated in various situations
 
 use diesel_async::RunQueryDsl;
-use graph::blockchain::{BlockHash, BlockPtr};
+use graph::blockchain::client::ChainClient;
+use graph::blockchain::{Block as _, BlockHash, BlockPtr, compress_block};
 use graph::data::store::ethereum::call;
 use graph::data::store::scalar::Bytes;
 use graph::env::ENV_VARS;
-use graph::futures03::executor;
+use graph::futures03::{TryStreamExt as _, executor};
 use graph::prelude::alloy::primitives::{Address, B256};
 use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 use graph::cheap_clone::CheapClone;
 use graph::prelude::alloy;
-use graph::prelude::{BlockNumber, QueryStoreManager, QueryTarget};
+use graph::prelude::{BlockNumber, LightEthereumBlockExt as _, QueryStoreManager, QueryTarget};
 use graph::prelude::{anyhow::Error, anyhow::anyhow};
 use graph::{components::store::BlockStore as _, prelude::DeploymentHash};
 use graph::{
     components::store::ChainHeadStore as _, components::store::ChainStore as _,
     prelude::EthereumCallCache as _,
 };
+use graph_chain_ethereum::PollingBlockIngestor;
+use graph_chain_ethereum::network::EthereumNetworkAdapters;
 use graph_store_postgres::Store as DieselStore;
 use graph_store_postgres::{ChainStore as DieselChainStore, layout_for_tests::FAKE_NETWORK_SHARED};
 
@@ -306,6 +310,129 @@ fn block_hashes_by_number() {
     })
 }
 
+#[test]
+fn block_ptrs_in_range() {
+    let chain = vec![
+        &*GENESIS_BLOCK,
+        &*BLOCK_ONE,
+        &*BLOCK_ONE_SIBLING,
+        &*BLOCK_TWO,
+        &*BLOCK_TWO_NO_PARENT,
+    ];
+    run_test_async(chain, move |store, _, _| async move {
+        let ptrs: Vec<_> = store
+            .cheap_clone()
+            .block_ptrs_in_range(0..3, 1)
+            .try_collect()
+            .await
+            .unwrap();
+        let numbers: Vec<_> = ptrs.iter().map(|(ptr, _)| ptr.number).collect();
+        assert_eq!(vec![0, 1, 1, 2, 2], numbers);
+        for block in [
+            &*GENESIS_BLOCK,
+            &*BLOCK_ONE,
+            &*BLOCK_ONE_SIBLING,
+            &*BLOCK_TWO,
+            &*BLOCK_TWO_NO_PARENT,
+        ] {
+            assert!(ptrs.iter().any(|(ptr, _)| ptr == &block.block_ptr()));
+        }
+        let (_, parent) = ptrs
+            .iter()
+            .find(|(ptr, _)| ptr == &BLOCK_TWO.block_ptr())
+            .unwrap();
+        assert_eq!(&BLOCK_ONE.block_hash(), parent);
+
+        let ptrs: Vec<_> = store
+            .cheap_clone()
+            .block_ptrs_in_range(1..2, 10)
+            .try_collect()
+            .await
+            .unwrap();
+        let hashes: Vec<_> = ptrs.into_iter().map(|(ptr, _)| ptr.hash).collect();
+        assert_eq!(2, hashes.len());
+        assert!(hashes.contains(&BLOCK_ONE.block_hash()));
+        assert!(hashes.contains(&BLOCK_ONE_SIBLING.block_hash()));
+    })
+}
+
+#[test]
+fn conflicting_block_data_is_not_stored() {
+    let chain = vec![
+        &*GENESIS_BLOCK,
+        &*BLOCK_ONE,
+        &*BLOCK_ONE_SIBLING,
+        &*BLOCK_TWO,
+    ];
+    run_test_async(chain, move |store, _, _| async move {
+        // Storing the same block again is fine
+        let stored = store
+            .upsert_block_unless_conflicting(Arc::new(BLOCK_TWO.clone()), true)
+            .await
+            .unwrap();
+        assert!(stored);
+
+        // A block with the same hash but a different parent is rejected
+        let conflicting = FakeBlock {
+            parent_hash: BLOCK_ONE_SIBLING.hash.clone(),
+            ..BLOCK_TWO.clone()
+        };
+        for overwrite in [true, false] {
+            let stored = store
+                .upsert_block_unless_conflicting(Arc::new(conflicting.clone()), overwrite)
+                .await
+                .unwrap();
+            assert!(!stored);
+        }
+        let (_, _, _, parent) = store
+            .block_number(&BLOCK_TWO.block_hash())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(BLOCK_ONE.block_hash()), parent);
+    })
+}
+
+#[test]
+fn blocks_in_range() {
+    let chain = vec![
+        &*GENESIS_BLOCK,
+        &*BLOCK_ONE,
+        &*BLOCK_ONE_SIBLING,
+        &*BLOCK_TWO,
+        &*BLOCK_TWO_NO_PARENT,
+    ];
+    run_test_async(chain, move |store, _, _| async move {
+        let ptrs: Vec<_> = store
+            .cheap_clone()
+            .block_ptrs_in_range(0..3, 10)
+            .map_ok(|(ptr, _)| ptr)
+            .try_collect()
+            .await
+            .unwrap();
+        let blocks: Vec<_> = store
+            .cheap_clone()
+            .blocks_in_range(0..3, 2)
+            .map_ok(|block| block.light_block().block_ptr())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(5, blocks.len());
+        assert_eq!(ptrs, blocks);
+
+        let blocks: Vec<_> = store
+            .cheap_clone()
+            .blocks_in_range(2..10, 1)
+            .map_ok(|block| block.light_block().block_ptr())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(2, blocks.len());
+        assert!(blocks.contains(&BLOCK_TWO.block_ptr()));
+        assert!(blocks.contains(&BLOCK_TWO_NO_PARENT.block_ptr()));
+    })
+}
+
 #[track_caller]
 fn check_ancestor(
     store: &Arc<DieselChainStore>,
@@ -648,6 +775,89 @@ fn test_clear_stale_call_cache() {
     });
 }
 
+#[test]
+fn compressed_blocks_read_back() {
+    let chain = vec![&*GENESIS_BLOCK, &*BLOCK_ONE];
+
+    #[derive(diesel::QueryableByName)]
+    struct Namespace {
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        namespace: String,
+    }
+
+    run_test_async(chain, |chain_store, _, _| async move {
+        // Light blocks do not go into the recent blocks cache, reading
+        // them has to go to the database
+        chain_store
+            .upsert_light_blocks(&[&*BLOCK_TWO, &*BLOCK_THREE])
+            .await
+            .unwrap();
+
+        let namespace: String = {
+            let mut conn = PRIMARY_POOL.get().await.unwrap();
+            diesel::sql_query(format!(
+                "SELECT namespace FROM public.chains WHERE name = '{}'",
+                chain_store.chain
+            ))
+            .get_result::<Namespace>(&mut conn)
+            .await
+            .unwrap()
+            .namespace
+        };
+        let (blocks_table, network_filter) = match namespace.as_str() {
+            "public" => (
+                "ethereum_blocks".to_owned(),
+                format!("network_name = '{}'", chain_store.chain),
+            ),
+            _ => (format!("{namespace}.blocks"), "true".to_owned()),
+        };
+
+        // Store block two compressed, as if `GRAPH_STORE_COMPRESS_BLOCK_DATA`
+        // had been set when it was written
+        let compressed = compress_block(BLOCK_TWO.data().unwrap());
+        assert!(compressed["block"].get("transactions").is_none());
+        {
+            let mut conn = chain_store.get_conn_for_test().await.unwrap();
+            diesel::sql_query(format!(
+                "UPDATE {blocks_table} SET data = $1 WHERE number = 2 AND {network_filter}"
+            ))
+            .bind::<diesel::sql_types::Jsonb, _>(compressed)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        }
+
+        let timestamp_exp = BLOCK_TWO.as_ethereum_block().block.timestamp_u64();
+
+        let blocks = chain_store
+            .cheap_clone()
+            .blocks(vec![BLOCK_TWO.block_hash()])
+            .await
+            .unwrap();
+        assert_eq!(1, blocks.len());
+        assert_eq!(BLOCK_TWO.block_ptr(), blocks[0].light_block().block_ptr());
+        assert_eq!(timestamp_exp, blocks[0].light_block().timestamp_u64());
+
+        let (block, ptr) = chain_store
+            .cheap_clone()
+            .ancestor_block(BLOCK_THREE.block_ptr(), 1, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(BLOCK_TWO.block_ptr(), ptr);
+        assert_eq!(BLOCK_TWO.block_ptr(), block.light_block().block_ptr());
+
+        // The timestamp can still be read without decompressing the block
+        let (_, number, timestamp, _) = chain_store
+            .block_number(&BLOCK_TWO.block_hash())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, number);
+        assert_eq!(Some(timestamp_exp), timestamp);
+    })
+}
+
 #[test]
 /// Tests only query correctness. No data is involved.
 fn test_transaction_receipts_in_block_function() {
@@ -660,3 +870,47 @@ fn test_transaction_receipts_in_block_function() {
         assert!(receipts.is_empty())
     })
 }
+
+#[test]
+fn recompute_chain_head_after_manual_inserts() {
+    let chain = vec![&*GENESIS_BLOCK, &*BLOCK_ONE, &*BLOCK_TWO];
+    run_test_async(chain, move |store, _, _| async move {
+        let chain_client = Arc::new(ChainClient::new_rpc(
+            EthereumNetworkAdapters::empty_for_testing(),
+        ));
+        let ingestor = PollingBlockIngestor::new(
+            LOGGER.clone(),
+            ANCESTOR_COUNT,
+            chain_client,
+            store.cheap_clone(),
+            Duration::from_millis(10),
+            store.chain.as_str().into(),
+        )
+        .unwrap();
+
+        // `set_chain` leaves the head unset
+        assert_eq!(None, store.cheap_clone().chain_head_ptr().await.unwrap());
+        assert_eq!(
+            Some(BLOCK_TWO.block_ptr()),
+            ingestor.recompute_chain_head().await.unwrap()
+        );
+
+        // Insert a block by hand, bypassing the poll loop
+        store
+            .upsert_block(Arc::new(BLOCK_THREE.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(BLOCK_TWO.block_ptr()),
+            store.cheap_clone().chain_head_ptr().await.unwrap()
+        );
+        assert_eq!(
+            Some(BLOCK_THREE.block_ptr()),
+            ingestor.recompute_chain_head().await.unwrap()
+        );
+        assert_eq!(
+            Some(BLOCK_THREE.block_ptr()),
+            store.cheap_clone().chain_head_ptr().await.unwrap()
+        );
+    })
+}
@@ -647,6 +647,7 @@ fn prune() {
                 ratio: 3.0 / 5.0,
                 last_pruned_block: None,
                 block_range_upper: vec![],
+                block_range_width: vec![],
             };
             assert_eq!(
                 Some(strategy),
@@ -670,3 +671,113 @@ fn prune() {
         })
     }
 }
+
+#[test]
+fn prune_keeps_sampled_history() {
+    struct Progress;
+    impl PruneReporter for Progress {}
+
+    async fn visible_users(
+        store: &DieselSubgraphStore,
+        src: &DeploymentLocator,
+        block: BlockNumber,
+    ) -> Vec<String> {
+        let user_type = TEST_SUBGRAPH_SCHEMA.entity_type("User").unwrap();
+        let query = EntityQuery::new(
+            src.hash.clone(),
+            block,
+            EntityCollection::All(vec![(user_type, AttributeNames::All)]),
+        );
+        store
+            .find(query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entity| entity.id().to_string())
+            .collect()
+    }
+
+    run_test(move |store, src| async move {
+        // Add more versions for user 3 at blocks 3 and 4
+        for (block, vid) in [(3, 4), (4, 5)] {
+            let user3 = create_test_entity(
+                "3",
+                USER,
+                "Shaqueeena",
+                &format!("queensha{block}@email.com"),
+                28_i32,
+                111.7,
+                false,
+                None,
+                vid,
+            );
+            transact_and_wait(&store, &src, BLOCKS[block].clone(), vec![user3])
+                .await
+                .unwrap();
+        }
+
+        // Setup and the above additions create these user versions:
+        // id | versions
+        // ---+---------
+        //  1 | [0,)
+        //  2 | [1,)
+        //  3 | [1,2) [2,3) [3,4) [4,)
+
+        // Forward block ptr to block 6
+        transact_and_wait(&store, &src, BLOCKS[6].clone(), vec![])
+            .await
+            .unwrap();
+
+        // Prune to 2 blocks of history with a reorg threshold of 1 while
+        // keeping samples at every even block. Of the versions of user 3
+        // that were closed before block 4, that keeps [2,3) and removes
+        // [1,2) and [3,4)
+        let mut req = PruneRequest::new(&src, 2, 1, 0, 6)?;
+        req.rebuild_threshold = 0.0;
+        req.delete_threshold = 0.0;
+        req.sample_interval = 2;
+        let stats = VersionStats {
+            entities: 3,
+            versions: 6,
+            tablename: USER.to_ascii_lowercase(),
+            ratio: 3.0 / 6.0,
+            last_pruned_block: None,
+            block_range_upper: vec![],
+            block_range_width: vec![],
+        };
+        assert_eq!(
+            Some(PruningStrategy::Delete),
+            req.strategy(&stats),
+            "pruning with samples must delete"
+        );
+        store
+            .prune(Box::new(Progress), &src, req)
+            .await
+            .expect("pruning works");
+
+        for (block, exp) in [
+            (0, vec!["1"]),
+            (1, vec!["1", "2"]),
+            (2, vec!["1", "2", "3"]),
+            (3, vec!["1", "2"]),
+            (4, vec!["1", "2", "3"]),
+            (5, vec!["1", "2", "3"]),
+            (6, vec!["1", "2", "3"]),
+        ] {
+            assert_eq!(
+                exp,
+                visible_users(&store, &src, block).await,
+                "different users visible at block {block}"
+            );
+        }
+
+        // The sampled blocks must still be queryable; the oldest sample
+        // pruning kept is block 0
+        let state = deployment_state(STORE.as_ref(), &src.hash).await;
+        assert_eq!(0, state.earliest_block_number);
+        for block in [0, 2, 4] {
+            assert_eq!(Ok(()), state.block_queryable(block));
+        }
+        Ok(())
+    })
+}
@@ -1,7 +1,9 @@
 //! Test mapping of GraphQL schema to a relational schema
 use diesel_async::SimpleAsyncConnection;
 use graph::components::store::write::{EntityModification, RowGroup};
+use graph::data::store::Id;
 use graph::data::store::scalar;
+use graph::data_source::CausalityRegion;
 use graph::entity;
 use graph::prelude::alloy::primitives::B256;
 use graph::prelude::{
@@ -254,6 +256,34 @@ async fn remove_schema(conn: &mut AsyncPgConnection) {
         .expect("Failed to drop test schema");
 }
 
+/// Drop the constraint `<table>_<suffix>` from the table for `entity_type`
+/// so that a test can write data that the constraint would reject
+async fn drop_constraint(
+    conn: &mut AsyncPgConnection,
+    layout: &Layout,
+    entity_type: &EntityType,
+    suffix: &str,
+) {
+    let table = layout.table_for_entity(entity_type).unwrap();
+    conn.batch_execute(&format!(
+        "alter table {} drop constraint {}_{}",
+        table.qualified_name, table.name, suffix
+    ))
+    .await
+    .unwrap();
+}
+
+/// Version `vid` of the `Scalar` entity with id `one`, with the given
+/// `string` and `int` attributes
+fn version(vid: i64, string: &str, int: i32) -> Entity {
+    let mut entity = SCALAR_ENTITY.clone();
+    entity.set("id", "one").unwrap();
+    entity.set("vid", vid).unwrap();
+    entity.set("string", string).unwrap();
+    entity.set("int", int).unwrap();
+    entity
+}
+
 async fn insert_entity_at(
     conn: &mut AsyncPgConnection,
     layout: &Layout,
@@ -1005,6 +1035,676 @@ async fn insert_many_and_delete_many() {
     .await;
 }
 
+#[graph::test]
+async fn count_affected_matches_delete_and_prune() {
+    run_test(async |conn, layout| {
+        let one = SCALAR_ENTITY.clone();
+        let mut two = SCALAR_ENTITY.clone();
+        two.set("id", "two").unwrap();
+        two.set("vid", 1i64).unwrap();
+        let mut three = SCALAR_ENTITY.clone();
+        three.set("id", "three").unwrap();
+        three.set("vid", 2i64).unwrap();
+        insert_entity(conn, layout, &SCALAR_TYPE, vec![one, two, three]).await;
+
+        let entity_keys: Vec<_> = vec!["two", "three", "no such entity"]
+            .into_iter()
+            .map(|key| SCALAR_TYPE.parse_key(key).unwrap())
+            .collect();
+        let group = row_group_delete(&SCALAR_TYPE, 1, entity_keys);
+
+        // Counting does not change anything
+        let count = layout
+            .count_deleted(conn, &group)
+            .await
+            .expect("Failed to count");
+        assert_eq!(2, count);
+        assert_eq!(3, count_scalar_entities(conn, layout).await);
+
+        let num_removed = layout
+            .delete(conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to delete");
+        assert_eq!(count, num_removed);
+
+        // The versions that were just clamped end at block 1
+        let table = layout.table_for_entity(&SCALAR_TYPE).unwrap().clone();
+        let prunable = layout
+            .count_prunable(conn, &table, 0)
+            .await
+            .expect("Failed to count");
+        assert_eq!(0, prunable);
+        let prunable = layout
+            .count_prunable(conn, &table, 1)
+            .await
+            .expect("Failed to count");
+        assert_eq!(num_removed, prunable);
+    })
+    .await;
+}
+
+#[graph::test]
+async fn count_entities_ignores_old_versions() {
+    run_test(async |conn, layout| {
+        let one = SCALAR_ENTITY.clone();
+        let mut two = SCALAR_ENTITY.clone();
+        two.set("id", "two").unwrap();
+        two.set("vid", 1i64).unwrap();
+        let mut three = SCALAR_ENTITY.clone();
+        three.set("id", "three").unwrap();
+        three.set("vid", 2i64).unwrap();
+        insert_entity(conn, layout, &SCALAR_TYPE, vec![one, two, three]).await;
+
+        let table = layout.table_for_entity(&SCALAR_TYPE).unwrap().name.clone();
+        let counts = layout.count_entities(conn).await.expect("Failed to count");
+        assert_eq!(Some(&3), counts.get(&table));
+
+        // Updating clamps the old version and adds a new one
+        let mut two = SCALAR_ENTITY.clone();
+        two.set("id", "two").unwrap();
+        two.set("vid", 3i64).unwrap();
+        two.set("string", "updated").unwrap();
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![two], 1).await;
+
+        // Deleting clamps the only version
+        let key = SCALAR_TYPE.parse_key("three").unwrap();
+        let group = row_group_delete(&SCALAR_TYPE, 2, vec![key]);
+        layout
+            .delete(conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to delete");
+
+        let counts = layout.count_entities(conn).await.expect("Failed to count");
+        assert_eq!(Some(&2), counts.get(&table));
+        assert_eq!(2, count_scalar_entities(conn, layout).await);
+
+        // Immutable tables are counted, too
+        let mink = layout.table_for_entity(&MINK_TYPE).unwrap().name.clone();
+        assert_eq!(Some(&0), counts.get(&mink));
+    })
+    .await;
+}
+
+#[graph::test]
+async fn immutability_violations_finds_duplicate_ids() {
+    use graph_store_postgres::layout_for_tests::ImmutabilityViolation;
+
+    run_test(async |conn, layout| {
+        let mink = |id: &str, order: i32, vid: i64| {
+            entity! { layout.input_schema => id: id, order: order, vid: vid }
+        };
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink("marty", 5, 10)], 3).await;
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink("ferdinand", 1, 11)], 4).await;
+
+        let violations = layout
+            .immutability_violations(conn)
+            .await
+            .expect("Failed to check immutable tables");
+        assert!(violations.is_empty());
+
+        // Simulate a corrupt table by writing a second version of an
+        // immutable entity, which the unique constraint on `id` would
+        // otherwise prevent
+        drop_constraint(conn, layout, &MINK_TYPE, "id_key").await;
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink("marty", 6, 12)], 7).await;
+
+        let violations = layout
+            .immutability_violations(conn)
+            .await
+            .expect("Failed to check immutable tables");
+        let expected = vec![ImmutabilityViolation {
+            id: "marty".to_string(),
+            blocks: vec![3, 7],
+        }];
+        let table = layout.table_for_entity(&MINK_TYPE).unwrap();
+        assert_eq!(1, violations.len());
+        assert_eq!(Some(&expected), violations.get(&table.name));
+    })
+    .await;
+}
+
+#[graph::test]
+async fn block_range_overlaps_finds_overlapping_versions() {
+    use graph_store_postgres::layout_for_tests::BlockRangeOverlap;
+
+    run_test(async |conn, layout| {
+        let mut two = SCALAR_ENTITY.clone();
+        two.set("id", "two").unwrap();
+        two.set("vid", 10i64).unwrap();
+        insert_entity_at(conn, layout, &SCALAR_TYPE, vec![two.clone()], 1).await;
+        two.set("vid", 11i64).unwrap();
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![two], 5).await;
+
+        let overlaps = layout
+            .block_range_overlaps(conn)
+            .await
+            .expect("Failed to check block ranges");
+        assert!(overlaps.is_empty());
+
+        // Simulate a corrupt table by extending the block range of the
+        // first version past the start of the second one, which the
+        // exclusion constraint would otherwise prevent
+        drop_constraint(conn, layout, &SCALAR_TYPE, "id_block_range_excl").await;
+        let table = layout.table_for_entity(&SCALAR_TYPE).unwrap();
+        conn.batch_execute(&format!(
+            "update {} set block_range = int4range(1, 7) where vid = 10",
+            table.qualified_name
+        ))
+        .await
+        .unwrap();
+
+        let overlaps = layout
+            .block_range_overlaps(conn)
+            .await
+            .expect("Failed to check block ranges");
+        let expected = vec![BlockRangeOverlap {
+            id: "two".to_string(),
+            first: (1, Some(7)),
+            second: (5, None),
+        }];
+        assert_eq!(1, overlaps.len());
+        assert_eq!(Some(&expected), overlaps.get(&table.name));
+    })
+    .await;
+}
+
+#[graph::test]
+async fn write_rates_flag_frequently_written_tables() {
+    run_test(async |conn, layout| {
+        // `Scalar` gets a new version at every block from 1 to 10, `User`
+        // only gets written to once
+        let mut one = SCALAR_ENTITY.clone();
+        one.set("vid", 1i64).unwrap();
+        insert_entity_at(conn, layout, &SCALAR_TYPE, vec![one.clone()], 1).await;
+        for block in 2..=10 {
+            one.set("vid", block as i64).unwrap();
+            update_entity_at(conn, layout, &SCALAR_TYPE, vec![one.clone()], block).await;
+        }
+        insert_user_entity(
+            conn,
+            layout,
+            "1",
+            &USER_TYPE,
+            "Johnton",
+            "tonofjohn@email.com",
+            67_i32,
+            184.4,
+            false,
+            Some("yellow"),
+            None,
+            60,
+            5,
+            100,
+        )
+        .await;
+
+        let rates = layout
+            .write_rates(conn, 10, 10, 0.5, 0.5)
+            .await
+            .expect("Failed to compute write rates");
+
+        let scalar = layout.table_for_entity(&SCALAR_TYPE).unwrap();
+        let rate = rates.get(&scalar.name).unwrap();
+        assert_eq!(10, rate.versions);
+        assert_eq!(1, rate.entities);
+        assert_eq!(10, rate.blocks);
+        assert_eq!(1.0, rate.rate);
+        assert_eq!(0.1, rate.ratio);
+        assert!(rate.candidate);
+
+        let user = layout.table_for_entity(&USER_TYPE).unwrap();
+        let rate = rates.get(&user.name).unwrap();
+        assert_eq!(1, rate.versions);
+        assert_eq!(1, rate.entities);
+        assert_eq!(0.1, rate.rate);
+        assert_eq!(1.0, rate.ratio);
+        assert!(!rate.candidate);
+
+        // A table that is written to often is not flagged if the writes
+        // are spread over too many entities
+        let rates = layout
+            .write_rates(conn, 10, 10, 0.5, 0.05)
+            .await
+            .expect("Failed to compute write rates");
+        assert!(!rates.get(&scalar.name).unwrap().candidate);
+
+        // Only the versions created after the start of the window count
+        let rates = layout
+            .write_rates(conn, 10, 5, 0.5, 0.5)
+            .await
+            .expect("Failed to compute write rates");
+        assert_eq!(5, rates.get(&scalar.name).unwrap().versions);
+        assert_eq!(0, rates.get(&user.name).unwrap().versions);
+
+        // Immutable tables can not be account-like and are not included
+        let mink = layout.table_for_entity(&MINK_TYPE).unwrap();
+        assert!(!rates.contains_key(&mink.name));
+    })
+    .await;
+}
+
+#[graph::test]
+async fn latest_write_block_is_highest_write() {
+    run_test(async |conn, layout| {
+        let latest = layout
+            .latest_write_block(conn)
+            .await
+            .expect("Failed to compute latest write block");
+        assert_eq!(None, latest);
+
+        let mink = |id: &str, order: i32, vid: i64| {
+            entity! { layout.input_schema => id: id, order: order, vid: vid }
+        };
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink("marty", 5, 10)], 3).await;
+        let mut two = SCALAR_ENTITY.clone();
+        two.set("id", "two").unwrap();
+        two.set("vid", 11i64).unwrap();
+        insert_entity_at(conn, layout, &SCALAR_TYPE, vec![two.clone()], 5).await;
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink("ferdinand", 1, 12)], 4).await;
+
+        // The highest block of any table counts, not the block of the
+        // write that happened last
+        let latest = layout
+            .latest_write_block(conn)
+            .await
+            .expect("Failed to compute latest write block");
+        assert_eq!(Some(5), latest);
+
+        // A new version of a mutable entity is a write at the block where
+        // it was created
+        two.set("vid", 13i64).unwrap();
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![two], 8).await;
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink("pinky", 2, 14)], 7).await;
+        let latest = layout
+            .latest_write_block(conn)
+            .await
+            .expect("Failed to compute latest write block");
+        assert_eq!(Some(8), latest);
+    })
+    .await;
+}
+
+#[graph::test]
+async fn find_current_pages_through_latest_versions() {
+    run_test(async |conn, layout| {
+        let entities = ["one", "two", "three", "four", "five"]
+            .into_iter()
+            .enumerate()
+            .map(|(vid, id)| {
+                let mut entity = SCALAR_ENTITY.clone();
+                entity.set("id", id).unwrap();
+                entity.set("vid", vid as i64).unwrap();
+                entity
+            })
+            .collect();
+        insert_entity(conn, layout, &SCALAR_TYPE, entities).await;
+
+        let mut two = SCALAR_ENTITY.clone();
+        two.set("id", "two").unwrap();
+        two.set("vid", 5i64).unwrap();
+        two.set("string", "updated").unwrap();
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![two], 1).await;
+
+        let key = SCALAR_TYPE.parse_key("three").unwrap();
+        let group = row_group_delete(&SCALAR_TYPE, 2, vec![key]);
+        layout
+            .delete(conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to delete");
+
+        // Page through the table two entities at a time
+        let mut found = Vec::new();
+        let mut after = None;
+        loop {
+            let page = layout
+                .find_current(conn, &SCALAR_TYPE, after.as_ref(), 2)
+                .await
+                .expect("Failed to find current entities");
+            let Some((last, cr)) = page.last() else {
+                break;
+            };
+            after = Some((last.id(), *cr));
+            found.extend(page.into_iter().map(|(entity, _)| entity));
+        }
+
+        let ids: Vec<_> = found.iter().map(|entity| entity.id().to_string()).collect();
+        assert_eq!(vec!["five", "four", "one", "two"], ids);
+        assert_eq!(
+            Some(&Value::String("updated".to_string())),
+            found[3].get("string"),
+            "only the latest version of an entity is returned"
+        );
+    })
+    .await;
+}
+
+#[graph::test]
+async fn creation_block_of_recreated_entity() {
+    run_test(async |conn, layout| {
+        insert_entity_at(
+            conn,
+            layout,
+            &SCALAR_TYPE,
+            vec![version(0, "created", 0)],
+            3,
+        )
+        .await;
+        update_entity_at(
+            conn,
+            layout,
+            &SCALAR_TYPE,
+            vec![version(1, "updated", 0)],
+            5,
+        )
+        .await;
+
+        let key = SCALAR_TYPE.parse_key("one").unwrap();
+        let group = row_group_delete(&SCALAR_TYPE, 7, vec![key]);
+        layout
+            .delete(conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to delete");
+
+        insert_entity_at(
+            conn,
+            layout,
+            &SCALAR_TYPE,
+            vec![version(2, "recreated", 0)],
+            9,
+        )
+        .await;
+
+        let id = SCALAR_TYPE.parse_id("one").unwrap();
+        let block = layout
+            .creation_block(conn, &SCALAR_TYPE, &id)
+            .await
+            .expect("Failed to find creation block");
+        assert_eq!(
+            Some(3),
+            block,
+            "the earliest version determines the creation block"
+        );
+
+        let id = SCALAR_TYPE.parse_id("none").unwrap();
+        let block = layout
+            .creation_block(conn, &SCALAR_TYPE, &id)
+            .await
+            .expect("Failed to find creation block");
+        assert_eq!(None, block);
+    })
+    .await;
+}
+
+#[graph::test]
+async fn value_ranges_of_attribute() {
+    run_test(async |conn, layout| {
+        insert_entity_at(conn, layout, &SCALAR_TYPE, vec![version(0, "a", 0)], 1).await;
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![version(1, "b", 0)], 3).await;
+        // Only `int` changes; the two versions with "b" are merged
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![version(2, "b", 1)], 5).await;
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![version(3, "a", 1)], 7).await;
+        let key = SCALAR_TYPE.parse_key("one").unwrap();
+        let group = row_group_delete(&SCALAR_TYPE, 9, vec![key]);
+        layout
+            .delete(conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to delete");
+        insert_entity_at(conn, layout, &SCALAR_TYPE, vec![version(4, "a", 1)], 11).await;
+
+        let key = SCALAR_TYPE.parse_key("one").unwrap();
+        let ranges = async |conn: &mut AsyncPgConnection, field: &str, value: Value| {
+            layout
+                .value_ranges(conn, &key, field, &value)
+                .await
+                .expect("Failed to find value ranges")
+        };
+
+        assert_eq!(
+            vec![(1, Some(3)), (7, Some(9)), (11, None)],
+            ranges(conn, "string", Value::from("a")).await
+        );
+        assert_eq!(
+            vec![(3, Some(7))],
+            ranges(conn, "string", Value::from("b")).await
+        );
+        assert_eq!(vec![(1, Some(5))], ranges(conn, "int", Value::Int(0)).await);
+        assert_eq!(
+            Vec::<(BlockNumber, Option<BlockNumber>)>::new(),
+            ranges(conn, "string", Value::from("c")).await
+        );
+
+        // Immutable entities have their value from the block at which
+        // they were created on
+        let mink = entity! { layout.input_schema =>
+            id: "marty",
+            order: 5,
+            vid: 10i64
+        };
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink], 4).await;
+        let key = MINK_TYPE.parse_key("marty").unwrap();
+        let ranges = layout
+            .value_ranges(conn, &key, "order", &Value::Int(5))
+            .await
+            .expect("Failed to find value ranges");
+        assert_eq!(vec![(4, None)], ranges);
+        let ranges = layout
+            .value_ranges(conn, &key, "order", &Value::Int(6))
+            .await
+            .expect("Failed to find value ranges");
+        assert!(ranges.is_empty());
+    })
+    .await;
+}
+
+#[graph::test]
+async fn block_range_gaps() {
+    run_test(async |conn, layout| {
+        let gaps = async |conn: &mut AsyncPgConnection, from, to, min_gap| {
+            layout
+                .block_range_gaps(conn, from, to, min_gap)
+                .await
+                .expect("Failed to find gaps")
+        };
+        let none = Vec::<(BlockNumber, BlockNumber)>::new();
+
+        // Something is written in every block
+        insert_entity_at(conn, layout, &SCALAR_TYPE, vec![version(0, "scalar", 0)], 1).await;
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![version(1, "scalar", 1)], 2).await;
+        let mink = entity! { layout.input_schema => id: "marty", order: 5, vid: 10i64 };
+        insert_entity_at(conn, layout, &MINK_TYPE, vec![mink], 3).await;
+        update_entity_at(conn, layout, &SCALAR_TYPE, vec![version(2, "scalar", 2)], 4).await;
+        assert_eq!(none, gaps(conn, 1, 4, 1).await);
+
+        // Nothing is written in blocks 5 to 9, nor after block 10
+        update_entity_at(
+            conn,
+            layout,
+            &SCALAR_TYPE,
+            vec![version(3, "scalar", 3)],
+            10,
+        )
+        .await;
+        assert_eq!(vec![(5, 9), (11, 12)], gaps(conn, 1, 12, 1).await);
+        assert_eq!(vec![(5, 9)], gaps(conn, 1, 12, 3).await);
+        assert_eq!(vec![(6, 9)], gaps(conn, 6, 10, 1).await);
+        assert_eq!(none, gaps(conn, 1, 12, 6).await);
+    })
+    .await;
+}
+
+#[graph::test]
+async fn get_current_and_past_version() {
+    run_test(async |conn, layout| {
+        let string =
+            |entity: Option<Entity>| entity.map(|entity| entity.get("string").unwrap().to_string());
+
+        insert_entity_at(
+            conn,
+            layout,
+            &SCALAR_TYPE,
+            vec![version(0, "created", 0)],
+            3,
+        )
+        .await;
+        update_entity_at(
+            conn,
+            layout,
+            &SCALAR_TYPE,
+            vec![version(1, "updated", 0)],
+            5,
+        )
+        .await;
+
+        let key = SCALAR_TYPE.parse_key("one").unwrap();
+        let (current, past) = layout
+            .get_current_and_at(conn, &key, 4)
+            .await
+            .expect("Failed to load versions");
+        assert_eq!(Some("updated".to_string()), string(current));
+        assert_eq!(Some("created".to_string()), string(past));
+
+        let (current, past) = layout
+            .get_current_and_at(conn, &key, 1)
+            .await
+            .expect("Failed to load versions");
+        assert_eq!(Some("updated".to_string()), string(current));
+        assert_eq!(None, past, "the entity did not exist yet at block 1");
+
+        let group = row_group_delete(&SCALAR_TYPE, 7, vec![key.clone()]);
+        layout
+            .delete(conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to delete");
+
+        let (current, past) = layout
+            .get_current_and_at(conn, &key, 6)
+            .await
+            .expect("Failed to load versions");
+        assert_eq!(None, current, "the entity has been deleted");
+        assert_eq!(Some("updated".to_string()), string(past));
+    })
+    .await;
+}
+
+#[graph::test]
+async fn find_current_pages_through_causality_regions() {
+    run_test_with_conn(async |conn| {
+        remove_schema(conn).await;
+        let schema = InputSchema::parse_latest(THINGS_GQL, THINGS_SUBGRAPH_ID.clone()).unwrap();
+        let site = make_dummy_site(
+            THINGS_SUBGRAPH_ID.clone(),
+            NAMESPACE.clone(),
+            NETWORK_NAME.to_string(),
+        );
+        conn.batch_execute(&format!("create schema {}", NAMESPACE.as_str()))
+            .await
+            .unwrap();
+        let layout = Layout::create_relational_schema(
+            conn,
+            Arc::new(site),
+            &schema,
+            BTreeSet::from([SCALAR_TYPE.clone()]),
+        )
+        .await
+        .expect("Failed to create relational schema");
+
+        // The same ids exist in several causality regions
+        let onchain = CausalityRegion::ONCHAIN;
+        let keys = [
+            ("a", onchain),
+            ("a", onchain.next()),
+            ("a", onchain.next().next()),
+            ("b", onchain),
+            ("b", onchain.next()),
+        ];
+        let entities = keys.iter().enumerate().map(|(vid, (id, cr))| {
+            let mut entity = SCALAR_ENTITY.clone();
+            entity.set("id", *id).unwrap();
+            entity.set("vid", vid as i64).unwrap();
+            let key = SCALAR_TYPE.key_in(Id::String((*id).into()), *cr);
+            (key, entity)
+        });
+        let group = row_group_insert(&SCALAR_TYPE, 0, entities);
+        layout
+            .insert(&LOGGER, conn, &group, &MOCK_STOPWATCH)
+            .await
+            .expect("Failed to insert entities");
+
+        for page_size in [1, 2, 10] {
+            let mut found = Vec::new();
+            let mut after = None;
+            loop {
+                let page = layout
+                    .find_current(conn, &SCALAR_TYPE, after.as_ref(), page_size)
+                    .await
+                    .expect("Failed to find current entities");
+                let Some((last, cr)) = page.last() else {
+                    break;
+                };
+                after = Some((last.id(), *cr));
+                found.extend(
+                    page.into_iter()
+                        .map(|(entity, cr)| (entity.id().to_string(), cr)),
+                );
+                assert!(found.len() <= keys.len(), "paging must terminate");
+            }
+            let expected: Vec<_> = keys.iter().map(|(id, cr)| (id.to_string(), *cr)).collect();
+            assert_eq!(expected, found, "page size {page_size}");
+        }
+    })
+    .await;
+}
+
+#[graph::test]
+async fn value_ranges_keep_causality_regions_apart() {
+    run_test_with_conn(async |conn| {
+        remove_schema(conn).await;
+        let schema = InputSchema::parse_latest(THINGS_GQL, THINGS_SUBGRAPH_ID.clone()).unwrap();
+        let site = make_dummy_site(
+            THINGS_SUBGRAPH_ID.clone(),
+            NAMESPACE.clone(),
+            NETWORK_NAME.to_string(),
+        );
+        conn.batch_execute(&format!("create schema {}", NAMESPACE.as_str()))
+            .await
+            .unwrap();
+        let layout = Layout::create_relational_schema(
+            conn,
+            Arc::new(site),
+            &schema,
+            BTreeSet::from([SCALAR_TYPE.clone()]),
+        )
+        .await
+        .expect("Failed to create relational schema");
+
+        // The same id with the same value in two causality regions, created
+        // at different blocks
+        let onchain = CausalityRegion::ONCHAIN;
+        let offchain = onchain.next();
+        for (vid, cr, block) in [(0i64, onchain, 1), (1, offchain, 5)] {
+            let mut entity = SCALAR_ENTITY.clone();
+            entity.set("id", "one").unwrap();
+            entity.set("vid", vid).unwrap();
+            let key = SCALAR_TYPE.key_in(Id::String("one".into()), cr);
+            let group = row_group_insert(&SCALAR_TYPE, block, vec![(key, entity)]);
+            layout
+                .insert(&LOGGER, conn, &group, &MOCK_STOPWATCH)
+                .await
+                .expect("Failed to insert entity");
+        }
+
+        for (cr, block) in [(onchain, 1), (offchain, 5)] {
+            let key = SCALAR_TYPE.key_in(Id::String("one".into()), cr);
+            let ranges = layout
+                .value_ranges(conn, &key, "string", &Value::from("scalar"))
+                .await
+                .expect("Failed to find value ranges");
+            assert_eq!(vec![(block, None)], ranges);
+        }
+    })
+    .await;
+}
+
 #[graph::test]
 async fn layout_cache() {
     run_test_with_conn(async |conn| {
@@ -13,6 +13,7 @@ use test_store::*;
 
 use graph::components::store::{DeploymentLocator, ReadStore, WritableStore};
 use graph::data::subgraph::*;
+use graph::futures03::TryStreamExt as _;
 use graph::{
     blockchain::DataSource,
     components::store::{BlockStore as _, EntityFilter, EntityOrder, EntityQuery, StatusStore},
@@ -1736,6 +1737,60 @@ fn find_at_block() {
     shaqueeena_at_block(7000, "teeko@email.com");
 }
 
+#[test]
+fn current_entities_streams_latest_versions() {
+    async fn exported(
+        store: &DieselStore,
+        deployment: &DeploymentLocator,
+    ) -> Vec<(String, String)> {
+        store
+            .subgraph_store()
+            .current_entities(deployment, USER, 1)
+            .await
+            .unwrap()
+            .map_ok(|entity| {
+                let email = entity.get("email").unwrap().as_str().unwrap().to_string();
+                (entity.id().to_string(), email)
+            })
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    run_test(|store, _, deployment| async move {
+        // Only the latest version of user 3 is exported
+        assert_eq!(
+            vec![
+                ("1".to_string(), "tonofjohn@email.com".to_string()),
+                ("2".to_string(), "dinici@email.com".to_string()),
+                ("3".to_string(), "teeko@email.com".to_string()),
+            ],
+            exported(&store, &deployment).await
+        );
+
+        transact_and_wait(
+            &store.subgraph_store(),
+            &deployment,
+            TEST_BLOCK_3_PTR.clone(),
+            vec![EntityOperation::Remove {
+                key: USER_TYPE.parse_key("2").unwrap(),
+            }],
+        )
+        .await
+        .unwrap();
+
+        // Deleted entities are not exported
+        assert_eq!(
+            vec!["1", "3"],
+            exported(&store, &deployment)
+                .await
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        );
+    })
+}
+
 #[test]
 fn cleanup_cached_blocks() {
     if store_is_sharded() {
@@ -12,7 +12,7 @@ use std::ops::Range;
 use test_store::*;
 
 use graph::components::store::{
-    DeploymentLocator, DerivedEntityQuery, SourceableStore, WritableStore,
+    DeploymentLocator, DerivedEntityQuery, EntityQuery, SourceableStore, WritableStore,
 };
 use graph::data::subgraph::*;
 use graph::semver::Version;
@@ -367,6 +367,104 @@ fn restart() {
     })
 }
 
+/// Records cursor history while it is alive, even if the test panics
+struct CursorHistory;
+
+impl CursorHistory {
+    fn enable() -> Self {
+        ENV_VARS.enable_firehose_cursor_history_for_tests(true);
+        CursorHistory
+    }
+}
+
+impl Drop for CursorHistory {
+    fn drop(&mut self) {
+        ENV_VARS.enable_firehose_cursor_history_for_tests(false);
+    }
+}
+
+#[test]
+fn query_as_of_firehose_cursor() {
+    run_test(|store, writable, _, deployment| async move {
+        let _history = CursorHistory::enable();
+        let subgraph_store = store.subgraph_store();
+        let cursor = |block: u8| FirehoseCursor::from(format!("cursor-{block}"));
+
+        // Write all blocks in one batch
+        pause_writer(&deployment).await;
+        for block in 1..4 {
+            let ops = vec![EntityOperation::Set {
+                key: count_key("1"),
+                data: entity! { TEST_SUBGRAPH_SCHEMA =>
+                    id: "1",
+                    count: block as i32,
+                    vid: block as i64,
+                },
+            }];
+            transact_entity_operations_with_cursor(
+                &subgraph_store,
+                &deployment,
+                block_pointer(block),
+                cursor(block),
+                ops,
+            )
+            .await
+            .unwrap();
+        }
+        writable.flush().await.unwrap();
+
+        let query_store = store
+            .query_store(QueryTarget::Deployment(
+                deployment.hash.clone(),
+                Default::default(),
+            ))
+            .await
+            .unwrap();
+        let block_for = |cursor: FirehoseCursor| {
+            let query_store = query_store.cheap_clone();
+            async move {
+                query_store
+                    .block_for_firehose_cursor(&cursor)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // Every block in the batch has its cursor recorded
+        for block in 1..4 {
+            assert_eq!(Some(block_pointer(block)), block_for(cursor(block)).await);
+        }
+        let ptr = block_for(cursor(2)).await.expect("cursor-2 is known");
+
+        let query = EntityQuery::new(
+            deployment.hash.clone(),
+            ptr.number,
+            EntityCollection::All(vec![(COUNTER_TYPE.clone(), AttributeNames::All)]),
+        );
+        let counts: Vec<_> = subgraph_store
+            .find(query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entity| entity.get("count").cloned())
+            .collect();
+        assert_eq!(vec![Some(Value::Int(2))], counts);
+
+        assert_eq!(None, block_for(cursor(7)).await);
+        assert_eq!(None, block_for(FirehoseCursor::None).await);
+
+        // Reverting forgets the cursors of the reverted blocks
+        writable
+            .revert_block_operations(block_pointer(2), cursor(2))
+            .await
+            .unwrap();
+        writable.flush().await.unwrap();
+
+        assert_eq!(None, block_for(cursor(3)).await);
+        assert_eq!(Some(block_pointer(2)), block_for(cursor(2)).await);
+    })
+}
+
 #[test]
 fn read_range_test() {
     run_test(|store, writable, sourceable, deployment| async move {
@@ -418,6 +516,57 @@ fn read_range_test() {
     })
 }
 
+#[test]
+fn read_changelog_test() {
+    run_test(|store, writable, sourceable, deployment| async move {
+        let expected = [
+            "1 Create Counter 1",
+            "1 Create Counter2 1",
+            "2 Modify Counter 1",
+            "2 Create Counter2 2",
+            "3 Delete Counter 1",
+            "3 Create Counter2 3",
+            "4 Create Counter 1",
+            "4 Create Counter2 4",
+            "5 Delete Counter 1",
+            "5 Create Counter2 5",
+        ];
+        let subgraph_store = store.subgraph_store();
+        writable.deployment_synced(block_pointer(0)).await.unwrap();
+
+        for count in 1..=5 {
+            insert_count(&subgraph_store, &deployment, count, 2 * count, false).await;
+        }
+        writable.flush().await.unwrap();
+        writable.deployment_synced(block_pointer(0)).await.unwrap();
+
+        // Page through the changelog with a page size that splits the
+        // changes of a block across pages
+        let mut changes = Vec::new();
+        let mut after = None;
+        loop {
+            let page = sourceable
+                .get_changelog(CausalityRegion::ONCHAIN, 0..18, after.clone(), 3)
+                .await
+                .unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some(last.cursor.clone());
+            changes.extend(page.into_iter().map(|change| {
+                format!(
+                    "{} {:?} {} {}",
+                    change.block,
+                    change.op,
+                    change.entity_type,
+                    change.entity.id()
+                )
+            }));
+        }
+        assert_eq!(expected.to_vec(), changes);
+    })
+}
+
 #[test]
 fn read_immutable_only_range_test() {
     run_test(|store, writable, sourceable, deployment| async move {
@@ -38,7 +38,7 @@ use graph::futures03::{Stream, StreamExt};
 use graph::http_body_util::Full;
 use graph::hyper::Request;
 use graph::hyper::body::Bytes;
-use graph::ipfs::{IpfsClient, IpfsMetrics};
+use graph::ipfs::{DeploymentIpfsClients, IpfsClient, IpfsMetrics};
 use graph::prelude::alloy::primitives::B256;
 use graph::prelude::alloy::primitives::U256;
 use graph::prelude::serde_json::{self, json};
@@ -558,6 +558,7 @@ pub async fn setup_inner<C: Blockchain>(
 
     let ipfs_service = ipfs_service(
         ipfs_client.cheap_clone(),
+        DeploymentIpfsClients::default(),
         env_vars.mappings.max_ipfs_file_bytes,
         env_vars.mappings.ipfs_timeout,
         env_vars.mappings.ipfs_request_limit,
@@ -981,6 +982,8 @@ impl RuntimeAdapterBuilder for NoopRuntimeAdapterBuilder {
         _: Arc<EthereumNetworkAdapters>,
         _: Arc<dyn EthereumCallCache + 'static>,
         _: Arc<ChainIdentifier>,
+        _: Arc<dyn graph::components::store::ChainStore>,
+        _: BlockNumber,
     ) -> Arc<dyn graph::blockchain::RuntimeAdapter<graph_chain_ethereum::Chain> + 'static> {
         Arc::new(NoopRuntimeAdapter { x: PhantomData })
     }
@@ -563,6 +563,37 @@ async fn end_block() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[graph::test]
+async fn data_source_limit() {
+    let RunnerTestRecipe { stores, test_info } =
+        RunnerTestRecipe::new("data_source_limit", "file-data-sources").await;
+
+    let blocks = {
+        let block_0 = genesis();
+        let mut block_1 = empty_block(block_0.ptr(), test_ptr(1));
+        push_test_command(
+            &mut block_1,
+            "CREATE_FILE",
+            "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn",
+        );
+        vec![block_0, block_1]
+    };
+    let chain = chain(&test_info.test_name, blocks, &stores, None).await;
+
+    // The two static data sources already use up the limit
+    let mut env_vars = EnvVars::default();
+    env_vars.subgraph_max_data_sources = 2;
+
+    let ctx = fixture::setup(&test_info, &stores, &chain, None, Some(env_vars)).await;
+    let err = ctx.start_and_sync_to_error(test_ptr(1)).await;
+
+    assert!(err.deterministic);
+    assert!(
+        err.message
+            .contains("Limit of 2 data sources per subgraph exceeded")
+    );
+}
+
 #[graph::test]
 async fn file_data_sources() {
     let RunnerTestRecipe { stores, test_info } =